@@ -7,6 +7,7 @@ export interface Configuration {
   showScores: boolean;
   showCloseButton: boolean;
   minimumQueryLength: number;
+  searchAllLanguages: boolean;
   forceOverwrite: boolean;
   resultNoun: { singular: string; plural: string };
   onQueryUpdate?: (query: string, results: unknown) => unknown;
@@ -21,6 +22,7 @@ export const defaultConfig: Readonly<Configuration> = {
   showScores: false,
   showCloseButton: true,
   minimumQueryLength: 3,
+  searchAllLanguages: false,
   forceOverwrite: false,
   resultNoun: { singular: "file", plural: "files" },
   onQueryUpdate: undefined,
@@ -175,7 +175,12 @@ export class Entity {
     }
 
     try {
-      const data = resolveSearch(this.name, query);
+      // Indexes built with language URL prefixes only show results in the
+      // page's language, unless they're told to search them all.
+      const language = this.config.searchAllLanguages
+        ? undefined
+        : document.documentElement.lang || undefined;
+      const data = resolveSearch(this.name, query, language);
       if (!data) return;
 
       this.injestSearchData(data);
@@ -34,3 +34,38 @@ test("resultToListItem happy path", () => {
     </a>`
   );
 });
+
+test("resultToListItem shows where excerpts outside the body matched", () => {
+  const result: Result = {
+    entry: {
+      fields: {},
+      title: "Deploying",
+      url: "https://jameslittle.me"
+    },
+    excerpts: [
+      {
+        fields: {},
+        score: 12,
+        text: "Matched keyword: kubernetes",
+        highlight_ranges: [{ beginning: 17, end: 27 }],
+        field: "keywords"
+      }
+    ],
+    score: 12,
+    title_highlight_ranges: []
+  };
+
+  const node = resultToListItem(result, { selected: false, showScores: false });
+  // eslint-disable-next-line @typescript-eslint/ban-ts-comment
+  // @ts-ignore
+  expect((node as Element).innerHTML).toEqualDisregardingWhitespace(
+    `<a href="https://jameslittle.me">
+      <div class="stork-title"><p>Deploying</p></div>
+      <div class="stork-excerpt stork-excerpt-keywords">
+        <p>
+          Matched keyword: <mark class="stork-highlight">kubernetes</mark>
+        </p>
+      </div>
+    </a>`
+  );
+});
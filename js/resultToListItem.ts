@@ -23,8 +23,14 @@ export function resultToListItem(
     </div>
       ${result.excerpts
         .map(
-          e => `<div class="stork-excerpt"><p>
-        ...${highlight(e.text, e.highlight_ranges || [])}...
+          e => `<div class="stork-excerpt${
+            e.field ? ` stork-excerpt-${e.field}` : ""
+          }"><p>
+        ${
+          e.field
+            ? highlight(e.text, e.highlight_ranges || [])
+            : `...${highlight(e.text, e.highlight_ranges || [])}...`
+        }
         </p>
         ${options.showScores ? `<code>${e.score}</code>` : ""}
         </div>`
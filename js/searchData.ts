@@ -10,6 +10,10 @@ export interface Entry {
   fields: Record<string, unknown>;
   title: string;
   url: string;
+  thumbnail_url?: string;
+  word_count?: number;
+  reading_time_minutes?: number;
+  text?: string;
 }
 
 export interface Excerpt {
@@ -18,6 +22,12 @@ export interface Excerpt {
   highlight_ranges?: Array<HighlightRange>;
   score: number;
   text: string;
+  trimmed_start?: boolean;
+  trimmed_end?: boolean;
+  sentence_start?: boolean;
+  sentence_end?: boolean;
+  url?: string;
+  field?: "title" | "url" | "keywords" | "summary";
 }
 
 export interface Result {
@@ -25,20 +35,37 @@ export interface Result {
   excerpts: Array<Excerpt>;
   score: number;
   title_highlight_ranges?: Array<HighlightRange>;
+  merged_entry_count?: number;
+  url?: string;
 }
 
 export interface SearchData {
   results: Array<Result>;
   total_hit_count: number;
   url_prefix: string;
+  facet_counts?: Record<string, number>;
+  fallback?: {
+    stage: "prefix_relaxation";
+    query: string;
+  };
+  surface_forms?: Record<string, Array<string>>;
 }
 
-export function resolveSearch(name: string, query: string): SearchData {
+/**
+ * Searches a registered index. When `language` is one the index was built
+ * with `language_url_prefixes` for, like the page's `<html lang>`, results
+ * are limited to documents in that language.
+ */
+export function resolveSearch(
+  name: string,
+  query: string,
+  language?: string
+): SearchData {
   let searchOutput = null;
   let data = null;
 
   try {
-    searchOutput = wasm_search(name, query);
+    searchOutput = wasm_search(name, query, language);
     // If wasm_search returns an error, it will return a JSON blob. Look for
     // data.error to see if this is the case.
     data = JSON.parse(searchOutput);
@@ -1,4 +1,4 @@
-use clap::{crate_version, App, AppSettings, Arg, SubCommand};
+use clap::{crate_version, App, AppSettings, Arg, ArgGroup, SubCommand};
 
 pub fn app() -> App<'static, 'static> {
     App::new("Stork")
@@ -32,6 +32,28 @@ pub fn app() -> App<'static, 'static> {
                 .min_values(2)
                 .hidden(true),
         )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .conflicts_with("verbose")
+                .help("Only prints errors"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .multiple(true)
+                .help("Prints more detailed logs; pass twice for debug output"),
+        )
+        .arg(
+            Arg::with_name("log_format")
+                .long("log-format")
+                .takes_value(true)
+                .value_name("LOG_FORMAT")
+                .possible_values(&["text", "json"])
+                .help("The format of the logs written to stderr"),
+        )
         .subcommand(
             SubCommand::with_name("build")
                 .about("Builds an index from a configuration and writes it to a file")
@@ -48,7 +70,7 @@ pub fn app() -> App<'static, 'static> {
                         .long("output")
                         .takes_value(true)
                         .value_name("OUTPUT_PATH")
-                        .required(true)
+                        .required_unless("estimate")
                         .help("The path of the index file that will be written, or - for stdout"),
                 )
                 .arg(
@@ -57,6 +79,74 @@ pub fn app() -> App<'static, 'static> {
                         .long("timing")
                         .help("Displays the duration of the build operation"),
                 )
+                .arg(
+                    Arg::with_name("delta_against")
+                        .long("delta-against")
+                        .takes_value(true)
+                        .value_name("PREVIOUS_INDEX_PATH")
+                        .requires("output")
+                        .help("Writes only what changed since this previously built index, as a delta file that can be applied to it"),
+                )
+                .arg(
+                    Arg::with_name("errors_json")
+                        .long("errors-json")
+                        .takes_value(true)
+                        .value_name("ERRORS_PATH")
+                        .help("Writes any document errors as a JSON array to this path, or - for stdout"),
+                )
+                .arg(
+                    Arg::with_name("show_all_errors")
+                        .long("show-all-errors")
+                        .help("Lists every document that failed to index, instead of the first few of each kind of error"),
+                )
+                .arg(
+                    Arg::with_name("nudges_json")
+                        .long("nudges-json")
+                        .takes_value(true)
+                        .value_name("NUDGES_PATH")
+                        .help("Writes any configuration suggestions as a JSON array to this path, or - for stdout"),
+                )
+                .arg(
+                    Arg::with_name("deny_nudges")
+                        .long("deny-nudges")
+                        .help("Fails the build if Stork has any suggestions for your configuration"),
+                )
+                .arg(
+                    Arg::with_name("check_urls")
+                        .long("check-urls")
+                        .help("Requests every document's URL after building, and reports the ones that don't respond successfully"),
+                )
+                .arg(
+                    Arg::with_name("deny_broken_urls")
+                        .long("deny-broken-urls")
+                        .requires("check_urls")
+                        .help("Fails the build if `--check-urls` finds any broken URLs"),
+                )
+                .arg(
+                    Arg::with_name("self_test")
+                        .long("self-test")
+                        .conflicts_with("skip_self_test")
+                        .help("Reads the built index back and searches it, failing the build if a document can't be found by its title"),
+                )
+                .arg(
+                    Arg::with_name("skip_self_test")
+                        .long("skip-self-test")
+                        .help("Skips the self-test, even if `output.self_test` is set in the configuration file"),
+                )
+                .arg(
+                    Arg::with_name("estimate")
+                        .long("estimate")
+                        .conflicts_with("output")
+                        .help("Estimates the size of the index from a sample of its documents, instead of building it"),
+                )
+                .arg(
+                    Arg::with_name("sample_size")
+                        .long("sample-size")
+                        .takes_value(true)
+                        .value_name("DOCUMENTS")
+                        .default_value("200")
+                        .help("The most documents `--estimate` reads"),
+                )
         )
         .subcommand(
             SubCommand::with_name("search")
@@ -95,10 +185,20 @@ pub fn app() -> App<'static, 'static> {
                         .display_order(100)
                         .takes_value(true)
                         .value_name("FORMAT")
-                        .possible_values(&["json", "pretty"])
+                        .possible_values(&["json", "pretty", "plain"])
                         .default_value("json")
                         .help("The output format for the returned search results"),
                 )
+                .arg(
+                    Arg::with_name("highlight")
+                        .long("highlight")
+                        .display_order(101)
+                        .takes_value(true)
+                        .value_name("STYLE")
+                        .possible_values(&["ansi", "markdown", "none"])
+                        .default_value("ansi")
+                        .help("How highlighted words are marked in `--format plain` results"),
+                )
         )
         .subcommand(
             SubCommand::with_name("test")
@@ -133,6 +233,50 @@ pub fn app() -> App<'static, 'static> {
                         .required(true)
                         .conflicts_with("config"),
                 )
+                .arg(
+                    Arg::with_name("query_log")
+                        .long("query-log")
+                        .help("Appends each query searched for on the test page to this file, as a line of JSON with its timestamp, result count, and top result's URL. The most recent queries are also served at /queries/recent.")
+                        .takes_value(true)
+                        .value_name("QUERY_LOG_PATH"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Work with configuration files.")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("migrate")
+                        .about("Updates a TOML configuration file to follow Stork's suggestions, keeping its comments and formatting.")
+                        .long_about("Updates a TOML configuration file to follow Stork's suggestions, keeping its comments and formatting. Only changes that can't affect the built index are made; any other suggestions are listed for you to handle.")
+                        .arg(
+                            Arg::with_name("config")
+                                .long("input")
+                                .short("i")
+                                .help("The path to your configuration file, or - for stdin")
+                                .takes_value(true)
+                                .value_name("CONFIG_PATH")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("output")
+                                .long("output")
+                                .short("o")
+                                .help("The path the migrated configuration file will be written to, or - for stdout")
+                                .takes_value(true)
+                                .value_name("OUTPUT_PATH"),
+                        )
+                        .arg(
+                            Arg::with_name("in_place")
+                                .long("in-place")
+                                .help("Overwrites the input configuration file with the migrated one"),
+                        )
+                        .group(
+                            ArgGroup::with_name("destination")
+                                .args(&["output", "in_place"])
+                                .required(true),
+                        ),
+                ),
         )
 }
 
@@ -152,6 +296,8 @@ mod tests {
             "stork search --index something.st --query my-query --json",
             "stork search --index something.st --query my-query --format json",
             "stork search --index something.st --query my-query --format pretty",
+            "stork search --index something.st --query my-query --format plain",
+            "stork search --index something.st --query my-query --format plain --highlight markdown",
             "stork search -i something.st -q my-query",
             "stork search -t --index something.st --query my-query --json",
             "stork search --timing -i something.st -q my-query",
@@ -164,9 +310,21 @@ mod tests {
             "stork test --config something.toml",
             "stork test -x something.st",
             "stork test --index something.st",
+            "stork test -x something.st --query-log queries.jsonl",
             "stork --build something.toml",
             "stork --search something.toml my-query",
             "stork --test something.st",
+            "stork -v build -i something.toml -o something.st",
+            "stork -vv build -i something.toml -o something.st",
+            "stork -q search -i something.st -q my-query",
+            "stork --quiet search -i something.st -q my-query",
+            "stork --log-format json -v build -i something.toml -o something.st",
+            "stork build -i something.toml -o something.st --errors-json errors.json",
+            "stork build -i something.toml -o something.st --nudges-json nudges.json",
+            "stork build -i something.toml -o something.st --deny-nudges",
+            "stork build -i something.toml -o something.st --show-all-errors",
+            "stork config migrate -i old.toml -o new.toml",
+            "stork config migrate --input old.toml --in-place",
         ];
 
         for input in valid_inputs {
@@ -187,10 +345,20 @@ mod tests {
             "stork --timing search --index - --query -",
             "stork -t search --index - --query -",
             "stork search --index something.st --query my-query --format bleh",
+            "stork search --index something.st --query my-query --format plain --highlight bold",
             "stork search --index something.st",
             "stork search --query my-query",
             "stork test --index something.st --input something.toml",
             "stork test -x something.st -i something.toml",
+            "stork -q -v build -i something.toml -o something.st",
+            "stork --log-format xml build -i something.toml -o something.st",
+            "stork build -i something.toml -o something.st --errors-json",
+            "stork build -i something.toml -o something.st --nudges-json",
+            "stork search -i something.st -q my-query --deny-nudges",
+            "stork search -i something.st -q my-query --show-all-errors",
+            "stork config migrate -i old.toml",
+            "stork config migrate -i old.toml -o new.toml --in-place",
+            "stork config -i old.toml -o new.toml",
         ];
 
         for input in invalid_inputs {
@@ -0,0 +1,84 @@
+use std::fmt::Display;
+
+use colored::Colorize;
+
+#[derive(Debug, PartialEq)]
+pub enum LineChange<'a> {
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+impl Display for LineChange<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineChange::Removed(line) => write!(f, "{}", format!("- {}", line).red()),
+            LineChange::Added(line) => write!(f, "{}", format!("+ {}", line).green()),
+        }
+    }
+}
+
+/**
+ * Lists the lines that were removed from `before` and added in `after`, in
+ * the order they appear. Unchanged lines are left out.
+ */
+pub fn changed_lines<'a>(before: &'a str, after: &'a str) -> Vec<LineChange<'a>> {
+    let before: Vec<&str> = before.lines().collect();
+    let after: Vec<&str> = after.lines().collect();
+
+    // lengths[i][j] is the length of the longest common subsequence of
+    // before[i..] and after[j..].
+    let mut lengths = vec![vec![0; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            lengths[i][j] = if before[i] == after[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut changes = vec![];
+    while i < before.len() && j < after.len() {
+        if before[i] == after[j] {
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            changes.push(LineChange::Removed(before[i]));
+            i += 1;
+        } else {
+            changes.push(LineChange::Added(after[j]));
+            j += 1;
+        }
+    }
+
+    changes.extend(before[i..].iter().copied().map(LineChange::Removed));
+    changes.extend(after[j..].iter().copied().map(LineChange::Added));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn only_changed_lines_are_listed() {
+        let before = "[output]\nfilename = \"index.st\"\nexcerpt_buffer = 4\n";
+        let after = "[output]\nexcerpt_buffer = 4\ndebug = true\n";
+
+        assert_eq!(
+            changed_lines(before, after),
+            vec![
+                LineChange::Removed("filename = \"index.st\""),
+                LineChange::Added("debug = true"),
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_input_has_no_changes() {
+        assert_eq!(changed_lines("a\nb\n", "a\nb\n"), vec![]);
+    }
+}
@@ -1,5 +1,8 @@
 use std::{io, num::ParseIntError};
-use stork_lib::{BuildError, ConfigReadError, IndexParseError, SearchError};
+use stork_lib::{
+    BuildError, ConfigMigrationError, ConfigReadError, IndexDeltaError, IndexParseError,
+    SearchError,
+};
 use thiserror::Error;
 
 #[allow(dead_code)]
@@ -23,7 +26,7 @@ pub enum StorkCommandLineError {
     #[error("{0}")]
     IndexParseError(#[from] IndexParseError),
 
-    #[error("{0}")]
+    #[error(transparent)]
     BuildError(#[from] BuildError),
 
     #[error("Invalid port `{0}`. Got error `{1}`")]
@@ -38,6 +41,24 @@ pub enum StorkCommandLineError {
     #[error("Couldn't display search results as JSON. Got error `{0}`")]
     SearchResultJsonSerializationError(#[from] serde_json::Error),
 
+    #[error("Couldn't write document errors as JSON. Got error `{0}`")]
+    DocumentErrorJsonSerializationError(serde_json::Error),
+
+    #[error("Couldn't write nudges as JSON. Got error `{0}`")]
+    NudgeJsonSerializationError(serde_json::Error),
+
+    #[error("The build produced {0} nudge(s), and `--deny-nudges` was set. Update your configuration and try again.")]
+    NudgesDenied(usize),
+
+    #[error("The build found {0} document(s) with broken URLs, and `--deny-broken-urls` was set. Fix or remove them and try again.")]
+    BrokenUrlsDenied(usize),
+
+    #[error("{0}")]
+    ConfigMigrationError(#[from] ConfigMigrationError),
+
+    #[error("Couldn't write the index delta: {0}")]
+    IndexDeltaError(#[from] IndexDeltaError),
+
     #[error("{0}")]
     InvalidCommandLineArguments(&'static str),
 
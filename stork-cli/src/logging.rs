@@ -0,0 +1,52 @@
+use clap::ArgMatches;
+use tracing_subscriber::filter::LevelFilter;
+
+/**
+ * Installs a subscriber that writes stork-lib's diagnostics to stderr.
+ *
+ * Warnings are shown by default; `-q` hides everything but errors, and each
+ * `-v` makes the output one level more detailed.
+ */
+pub fn install_subscriber(app_matches: &ArgMatches) {
+    let level = level_filter(
+        app_matches.is_present("quiet"),
+        app_matches.occurrences_of("verbose"),
+    );
+
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_target(false);
+
+    match app_matches.value_of("log_format") {
+        Some("json") => builder.json().init(),
+        _ => builder.without_time().init(),
+    }
+}
+
+fn level_filter(quiet: bool, verbosity: u64) -> LevelFilter {
+    if quiet {
+        return LevelFilter::ERROR;
+    }
+
+    match verbosity {
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::INFO,
+        2 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbosity_flags_map_to_levels() {
+        assert_eq!(level_filter(false, 0), LevelFilter::WARN);
+        assert_eq!(level_filter(false, 1), LevelFilter::INFO);
+        assert_eq!(level_filter(false, 2), LevelFilter::DEBUG);
+        assert_eq!(level_filter(false, 5), LevelFilter::TRACE);
+        assert_eq!(level_filter(true, 2), LevelFilter::ERROR);
+    }
+}
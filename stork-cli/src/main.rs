@@ -3,9 +3,11 @@ use std::{process::exit, time::Instant};
 use colored::Colorize;
 
 mod clap;
+mod diff;
 mod display_timings;
 mod errors;
 mod io;
+mod logging;
 mod pretty_print_search_results;
 
 #[cfg(feature = "test-server")]
@@ -15,10 +17,13 @@ use crate::clap::app;
 use io::{read_bytes_from_path, read_from_path, write_bytes};
 
 use ::clap::ArgMatches;
+use bytes::Bytes;
 use errors::StorkCommandLineError;
 use num_format::{Locale, ToFormattedString};
-use pretty_print_search_results::pretty_print_search_results;
-use stork_lib::{build_index, search, Config};
+use pretty_print_search_results::{plain_print_search_results, pretty_print_search_results};
+use stork_lib::{
+    build_index, estimate_index_size, index_delta, migrate_config, search, Config, HighlightStyle,
+};
 
 pub type ExitCode = i32;
 pub const EXIT_SUCCESS: ExitCode = 0;
@@ -35,11 +40,14 @@ fn main() {
 #[cfg(feature = "build-v3")]
 fn main() {
     let app_matches = app().get_matches();
+    logging::install_subscriber(&app_matches);
+    let quiet = app_matches.is_present("quiet");
 
     let result = match app_matches.subcommand() {
-        ("build", Some(submatches)) => build_handler(submatches),
+        ("build", Some(submatches)) => build_handler(submatches, quiet),
         ("search", Some(submatches)) => search_handler(submatches),
         ("test", Some(submatches)) => test_handler(submatches),
+        ("config", Some(submatches)) => config_handler(submatches),
 
         // Delete when releasing 2.0.0
         (_, _) => {
@@ -63,7 +71,7 @@ fn main() {
                     let itr = vec!["stork", "build", "--input", config_path, "--output", &output_path];
                     let global_matches = app().get_matches_from(itr);
                     let submatches = global_matches.subcommand_matches("build").unwrap();
-                    build_handler(submatches)
+                    build_handler(submatches, quiet)
                 };
                 wrapper()
             } else if let Some(values_iter) = app_matches.values_of("search") {
@@ -88,33 +96,137 @@ fn main() {
     };
 
     if let Err(error) = result {
-        eprintln!("{} {}", "Error:".red(), error);
+        let show_all_errors = app_matches
+            .subcommand_matches("build")
+            .map_or(false, |submatches| submatches.is_present("show_all_errors"));
+
+        if show_all_errors {
+            eprintln!("{} {:#}", "Error:".red(), error);
+        } else {
+            eprintln!("{} {}", "Error:".red(), error);
+        }
         exit(EXIT_FAILURE);
     }
 }
 
-fn build_handler(submatches: &ArgMatches) -> CmdResult {
+fn build_handler(submatches: &ArgMatches, quiet: bool) -> CmdResult {
     let start_time = Instant::now();
 
     let config_path = submatches.value_of("config").unwrap();
-    let output_path = submatches.value_of("output").unwrap();
 
     let config_string = read_from_path(config_path)?;
-    let config = Config::try_from(config_string.as_str())?;
-    let build_output = build_index(&config)?;
+    let mut config = Config::try_from(config_string.as_str())?;
+
+    if submatches.is_present("estimate") {
+        let sample_size = submatches
+            .value_of("sample_size")
+            .unwrap()
+            .parse::<usize>()
+            .map_err(|_| {
+                StorkCommandLineError::InvalidCommandLineArguments(
+                    "`--sample-size` must be a whole number of documents.",
+                )
+            })?;
+        println!("{}", estimate_index_size(&config, sample_size)?);
+        return Ok(());
+    }
+
+    let output_path = submatches.value_of("output").unwrap();
+    if submatches.is_present("self_test") {
+        config.output.self_test = true;
+    } else if submatches.is_present("skip_self_test") {
+        config.output.self_test = false;
+    }
+
+    if submatches.is_present("check_urls") {
+        config.input.verify_urls = true;
+    }
+
+    let build_result = build_index(&config);
+
+    if let Some(errors_path) = submatches.value_of("errors_json") {
+        let document_errors = match &build_result {
+            Ok(build_output) => build_output.description.warnings.as_slice(),
+            Err(error) => error.document_errors(),
+        };
+        let errors_json = serde_json::to_vec_pretty(document_errors)
+            .map_err(StorkCommandLineError::DocumentErrorJsonSerializationError)?;
+        write_bytes(errors_path, &Bytes::from(errors_json))?;
+    }
+
+    let build_output = build_result?;
+    let nudges = &build_output.description.nudges;
+
+    if !quiet {
+        for nudge in nudges {
+            eprintln!("{} {}", "Warning:".yellow(), nudge.message);
+        }
+    }
+
+    if let Some(nudges_path) = submatches.value_of("nudges_json") {
+        let nudges_json = serde_json::to_vec_pretty(nudges)
+            .map_err(StorkCommandLineError::NudgeJsonSerializationError)?;
+        write_bytes(nudges_path, &Bytes::from(nudges_json))?;
+    }
+
+    if submatches.is_present("deny_nudges") && !nudges.is_empty() {
+        return Err(StorkCommandLineError::NudgesDenied(nudges.len()));
+    }
+
+    let url_checks = &build_output.description.url_checks;
+    if submatches.is_present("deny_broken_urls") && !url_checks.broken.is_empty() {
+        if !quiet {
+            eprintln!("{}", url_checks);
+        }
+        return Err(StorkCommandLineError::BrokenUrlsDenied(
+            url_checks.broken.len(),
+        ));
+    }
 
     let build_time = Instant::now();
 
-    let bytes_written = write_bytes(output_path, &build_output.bytes)?;
+    if !build_output.shards.is_empty() && output_path == "-" {
+        return Err(StorkCommandLineError::InvalidCommandLineArguments(
+            "A sharded index can't be written to stdout. Pass a file path to `--output`; the shards will be written next to it.",
+        ));
+    }
+
+    let delta = match submatches.value_of("delta_against") {
+        Some(_) if !build_output.shards.is_empty() => {
+            return Err(StorkCommandLineError::InvalidCommandLineArguments(
+                "Deltas can only be written for single-file indexes. Remove `--delta-against`, or set `output.format` to `single`.",
+            ));
+        }
+        Some(previous_path) => {
+            let previous = read_bytes_from_path(previous_path)?;
+            Some(index_delta(&previous, &build_output.bytes)?)
+        }
+        None => None,
+    };
+
+    let mut bytes_written =
+        write_bytes(output_path, delta.as_ref().unwrap_or(&build_output.bytes))?;
+    for (shard_number, shard) in build_output.shards.iter().enumerate() {
+        bytes_written += write_bytes(&format!("{}.{}", output_path, shard_number), shard)?;
+    }
 
     let end_time = Instant::now();
 
     eprintln!(
-        "{} Index built successfully, wrote {} bytes.",
+        "{} {} built successfully, wrote {} bytes.",
         "Success:".green().to_string(),
+        if delta.is_some() {
+            "Index delta"
+        } else {
+            "Index"
+        },
         bytes_written.to_formatted_string(&Locale::en)
     );
-    eprintln!("{}", build_output.description);
+    if submatches.is_present("show_all_errors") {
+        eprintln!("{:#}", build_output.description);
+    } else {
+        eprintln!("{}", build_output.description);
+    }
 
     if submatches.is_present("timing") {
         eprintln!(
@@ -130,6 +242,45 @@ fn build_handler(submatches: &ArgMatches) -> CmdResult {
     Ok(())
 }
 
+fn config_handler(submatches: &ArgMatches) -> CmdResult {
+    match submatches.subcommand() {
+        ("migrate", Some(submatches)) => migrate_handler(submatches),
+        _ => unreachable!(),
+    }
+}
+
+fn migrate_handler(submatches: &ArgMatches) -> CmdResult {
+    let config_path = submatches.value_of("config").unwrap();
+    let output_path = if submatches.is_present("in_place") {
+        if config_path == "-" {
+            return Err(StorkCommandLineError::InvalidCommandLineArguments(
+                "`--in-place` can't be used when reading the configuration from stdin.",
+            ));
+        }
+        config_path
+    } else {
+        submatches.value_of("output").unwrap()
+    };
+
+    let config_string = read_from_path(config_path)?;
+    let migration = migrate_config(&config_string)?;
+
+    for change in diff::changed_lines(&config_string, &migration.config) {
+        eprintln!("{}", change);
+    }
+
+    for nudge in &migration.applied {
+        eprintln!("{} {}", "Migrated:".green(), nudge.message);
+    }
+
+    for nudge in &migration.remaining {
+        eprintln!("{} {}", "Needs review:".yellow(), nudge.message);
+    }
+
+    write_bytes(output_path, &Bytes::from(migration.config))?;
+    Ok(())
+}
+
 fn search_handler(submatches: &ArgMatches) -> CmdResult {
     let start_time = Instant::now();
 
@@ -156,6 +307,14 @@ fn search_handler(submatches: &ArgMatches) -> CmdResult {
         Some("pretty") => {
             println!("{}", pretty_print_search_results(&results));
         }
+        Some("plain") => {
+            let style = match submatches.value_of("highlight") {
+                Some("markdown") => Some(HighlightStyle::Markdown),
+                Some("none") => None,
+                _ => Some(HighlightStyle::Ansi),
+            };
+            println!("{}", plain_print_search_results(&results, style.as_ref()));
+        }
         _ => {
             let _ = app().print_help();
             return Ok(());
@@ -187,14 +346,20 @@ fn test_handler(submatches: &ArgMatches) -> CmdResult {
         .parse()
         .map_err(|e| StorkCommandLineError::InvalidPort(port_string.to_string(), e))?;
 
+    let query_log_path = submatches.value_of("query_log");
+    let query_log = test_server::QueryLog::new(query_log_path).map_err(|e| {
+        StorkCommandLineError::FileCreateError(query_log_path.unwrap_or_default().to_string(), e)
+    })?;
+
     if let Some(config_path) = submatches.value_of("config") {
         let config_string = read_from_path(config_path)?;
         let config = Config::try_from(config_string.as_str())?;
         let output = build_index(&config)?;
-        test_server::serve(&output.bytes, port).map_err(|_| StorkCommandLineError::ServerError)
+        test_server::serve(&output.bytes, port, query_log)
+            .map_err(|_| StorkCommandLineError::ServerError)
     } else if let Some(index_path) = submatches.value_of("index_path") {
         let index = read_bytes_from_path(index_path)?;
-        test_server::serve(&index, port).map_err(|_| StorkCommandLineError::ServerError)
+        test_server::serve(&index, port, query_log).map_err(|_| StorkCommandLineError::ServerError)
     } else {
         unreachable!()
     }
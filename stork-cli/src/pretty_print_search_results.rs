@@ -1,7 +1,7 @@
 use std::cmp::min;
 
 use colored::Colorize;
-use stork_lib::{HighlightRange, Output};
+use stork_lib::{render_excerpt_plain, HighlightRange, HighlightStyle, Output};
 use textwrap::termwidth;
 
 fn highlight_string(string: &str, ranges: &Vec<HighlightRange>) -> String {
@@ -51,6 +51,38 @@ pub fn pretty_print_search_results(results: &Output) -> String {
     output
 }
 
+/**
+ * The search results as plain text, without colors or wrapping, for screen
+ * readers and for pasting elsewhere. Highlights are marked in `style`, or
+ * not at all when it's `None`.
+ */
+pub fn plain_print_search_results(results: &Output, style: Option<&HighlightStyle>) -> String {
+    let unmarked = HighlightStyle::Custom {
+        before: String::new(),
+        after: String::new(),
+    };
+    let style = style.unwrap_or(&unmarked);
+
+    let mut output = String::new();
+    for result in results.results.iter() {
+        output.push_str(&format!(
+            "{}\n{}{}",
+            result.entry.title, results.url_prefix, result.entry.url
+        ));
+        for excerpt in result.excerpts.iter() {
+            output.push_str(&format!("\n- {}", render_excerpt_plain(excerpt, style)));
+        }
+        output.push_str("\n\n");
+    }
+
+    output.push_str(&format!(
+        "{} total results available",
+        results.total_hit_count
+    ));
+
+    output
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -58,14 +90,17 @@ mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
 
-    #[test]
-    fn display_pretty_search_results_given_output() {
-        let results = Output {
+    fn results() -> Output {
+        Output {
             results: vec![stork_lib::Result {
                 entry: stork_lib::Entry {
                     title: "Some Document Title".to_string(),
                     url: "https://example.com".to_string(),
                     fields: HashMap::new(),
+                    thumbnail_url: None,
+                    word_count: None,
+                    reading_time_minutes: None,
+                    text: None,
                 },
                 score: 25,
                 excerpts: vec![stork_lib::Excerpt {
@@ -79,18 +114,32 @@ mod tests {
                     )],
                     fields: HashMap::new(),
                     score: 12,
+                    trimmed_start: false,
+                    trimmed_end: false,
+                    sentence_start: false,
+                    sentence_end: false,
+                    url: None,
+                    field: stork_lib::ExcerptField::Body,
                 }],
                 title_highlight_ranges: vec![stork_lib::HighlightRange {
                     beginning: 0,
                     end: 5,
                 }],
+                merged_entry_count: None,
+                url: None,
             }],
             total_hit_count: 21,
             url_prefix: "".to_string(),
-        };
+            facet_counts: Default::default(),
+            fallback: None,
+            surface_forms: Default::default(),
+        }
+    }
 
+    #[test]
+    fn display_pretty_search_results_given_output() {
         assert_eq!(
-            pretty_print_search_results(&results),
+            pretty_print_search_results(&results()),
             format!(
                 "{}{}{}{}",
                 "Some Document Title".bold().green(),
@@ -100,4 +149,16 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn display_plain_search_results_given_output() {
+        assert_eq!(
+            plain_print_search_results(&results(), Some(&HighlightStyle::Markdown)),
+            "Some Document Title\nhttps://example.com\n- **T**his is the excerpt of the text\n\n21 total results available"
+        );
+        assert_eq!(
+            plain_print_search_results(&results(), None),
+            "Some Document Title\nhttps://example.com\n- This is the excerpt of the text\n\n21 total results available"
+        );
+    }
 }
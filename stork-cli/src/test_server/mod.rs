@@ -1,14 +1,46 @@
 use bytes::Bytes;
 use hyper::server::Server;
 use hyper::service::{make_service_fn, service_fn};
-use hyper::{Body, Request, Response, StatusCode};
+use hyper::{Body, Method, Request, Response, StatusCode};
 use std::convert::Infallible;
+use std::sync::Arc;
 use tokio::runtime::Runtime;
 
-pub fn serve(index: &Bytes, port: u16) -> Result<(), Box<dyn std::error::Error>> {
+mod query_log;
+pub use query_log::QueryLog;
+use query_log::RECENT_QUERY_CAPACITY;
+
+/// The name the test page's index is registered under, on the page and with
+/// the server's own copy of it.
+const TEST_INDEX_NAME: &str = "test";
+
+/// How many entries `/queries/recent` returns if no `limit` is given.
+const DEFAULT_RECENT_QUERY_LIMIT: usize = 20;
+
+/**
+ * Serves the test page and its index. The page reports each query typed into
+ * it to `POST /queries`, and the server searches its own copy of the index
+ * for it, so the query reaches Stork's query listener the same way a search
+ * in any other program that embeds Stork does. The listener records it in
+ * `query_log`, which `GET /queries/recent` reads from.
+ */
+pub fn serve(
+    index: &Bytes,
+    port: u16,
+    query_log: QueryLog,
+) -> Result<(), Box<dyn std::error::Error>> {
     let rt = Runtime::new()?;
     let index_bytes = index.clone();
 
+    stork_lib::register_index(TEST_INDEX_NAME, index.clone())?;
+    let query_log = Arc::new(query_log);
+    let listener_log = Arc::clone(&query_log);
+    stork_lib::set_query_listener(move |event| {
+        if event.index_name.as_deref() == Some(TEST_INDEX_NAME) {
+            listener_log.record(event);
+        }
+    });
+
     rt.block_on(async {
         // For every connection, we must make a `Service` to handle all
         // incoming HTTP requests on said connection.
@@ -17,17 +49,43 @@ pub fn serve(index: &Bytes, port: u16) -> Result<(), Box<dyn std::error::Error>>
             // `service_fn` is a helper to convert a function that
             // returns a Response into a `Service`.
             let bytes = index_bytes.clone();
+            let query_log = Arc::clone(&query_log);
             async move {
                 Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
                     let bytes_2 = bytes.clone();
+                    let query_log = Arc::clone(&query_log);
+                    let method = request.method().clone();
+                    let path = request.uri().path().to_string();
                     async move {
-                        Ok::<_, Infallible>(match request.uri().to_string().as_str() {
-                            "/" => {
+                        Ok::<_, Infallible>(match (&method, path.as_str()) {
+                            (&Method::GET, "/") => {
                                 let index_html = format!(include_str!("index.html"), env!("CARGO_PKG_VERSION"));
                                 Response::new(Body::from(index_html))
                             }
 
-                            "/test.st" => Response::new(Body::from(bytes_2)),
+                            (&Method::GET, "/test.st") => Response::new(Body::from(bytes_2)),
+
+                            (&Method::POST, "/queries") => {
+                                if let Ok(body) = hyper::body::to_bytes(request.into_body()).await {
+                                    let query = String::from_utf8_lossy(&body);
+                                    // Only run for the query listener, which records it
+                                    let _ = stork_lib::search_from_cache(TEST_INDEX_NAME, query.trim());
+                                }
+
+                                Response::builder()
+                                    .status(StatusCode::NO_CONTENT)
+                                    .body(Body::empty())
+                                    .unwrap()
+                            }
+
+                            (&Method::GET, "/queries/recent") => {
+                                let limit = recent_query_limit(request.uri().query());
+                                let recent = serde_json::Value::from(query_log.recent(limit));
+                                Response::builder()
+                                    .header("Content-Type", "application/json")
+                                    .body(Body::from(recent.to_string()))
+                                    .unwrap()
+                            }
 
                             _ => Response::builder()
                                 .status(StatusCode::NOT_FOUND)
@@ -52,6 +110,17 @@ pub fn serve(index: &Bytes, port: u16) -> Result<(), Box<dyn std::error::Error>>
     })
 }
 
+/// The `limit` parameter of a `/queries/recent` request's query string.
+fn recent_query_limit(query_string: Option<&str>) -> usize {
+    query_string
+        .unwrap_or_default()
+        .split('&')
+        .find_map(|parameter| parameter.strip_prefix("limit="))
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(DEFAULT_RECENT_QUERY_LIMIT)
+        .min(RECENT_QUERY_CAPACITY)
+}
+
 #[cfg(feature = "test-server")]
 async fn shutdown_signal() {
     // Wait for the CTRL+C signal
@@ -59,3 +128,23 @@ async fn shutdown_signal() {
         .await
         .expect("failed to install CTRL+C signal handler");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_query_limit_is_read_from_the_query_string() {
+        assert_eq!(recent_query_limit(None), DEFAULT_RECENT_QUERY_LIMIT);
+        assert_eq!(recent_query_limit(Some("limit=5")), 5);
+        assert_eq!(recent_query_limit(Some("format=json&limit=7")), 7);
+        assert_eq!(
+            recent_query_limit(Some("limit=lots")),
+            DEFAULT_RECENT_QUERY_LIMIT
+        );
+        assert_eq!(
+            recent_query_limit(Some("limit=5000")),
+            RECENT_QUERY_CAPACITY
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use stork_lib::QueryEvent;
+
+/// The most entries `/queries/recent` can return.
+pub const RECENT_QUERY_CAPACITY: usize = 100;
+
+/**
+ * The queries searched for on the test page, as reported by Stork's query
+ * listener. The most recent ones are kept for `/queries/recent`, and every
+ * one is appended to the `--query-log` file as a line of JSON, if there is
+ * one.
+ */
+pub struct QueryLog {
+    file: Option<Mutex<File>>,
+    recent: Mutex<VecDeque<Value>>,
+}
+
+impl QueryLog {
+    pub fn new(path: Option<&str>) -> io::Result<Self> {
+        let file = match path {
+            Some(path) => Some(Mutex::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            None => None,
+        };
+
+        Ok(QueryLog {
+            file,
+            recent: Mutex::new(VecDeque::with_capacity(RECENT_QUERY_CAPACITY)),
+        })
+    }
+
+    /// Failing to write to the log file is reported, but doesn't stop the
+    /// server.
+    pub fn record(&self, event: &QueryEvent) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis() as u64);
+        let entry = json!({
+            "timestamp": timestamp,
+            "query": event.query,
+            "result_count": event.result_count,
+            "top_result_url": event.top_result_url,
+        });
+
+        if let Some(file) = &self.file {
+            if let Err(e) = writeln!(file.lock().unwrap(), "{}", entry) {
+                eprintln!("Couldn't write to the query log: {}", e);
+            }
+        }
+
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() == RECENT_QUERY_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(entry);
+    }
+
+    /// The last `limit` entries, oldest first.
+    pub fn recent(&self, limit: usize) -> Vec<Value> {
+        let recent = self.recent.lock().unwrap();
+        recent
+            .iter()
+            .skip(recent.len().saturating_sub(limit))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn event(query: &str) -> QueryEvent {
+        QueryEvent {
+            index_name: Some("test".to_string()),
+            query: query.to_string(),
+            result_count: 2,
+            top_result_url: Some("https://example.com/otters".to_string()),
+            elapsed: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn queries_are_written_as_json_lines_and_kept_for_recent() {
+        let path =
+            std::env::temp_dir().join(format!("stork-query-log-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let log = QueryLog::new(path.to_str()).unwrap();
+        for query in ["otters", "beavers", "seals"] {
+            log.record(&event(query));
+        }
+
+        let lines: Vec<Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["query"], "otters");
+        assert_eq!(lines[0]["result_count"], 2);
+        assert_eq!(lines[0]["top_result_url"], "https://example.com/otters");
+        assert!(lines[0]["timestamp"].as_u64().unwrap() > 0);
+
+        let queries = |entries: Vec<Value>| -> Vec<String> {
+            entries
+                .iter()
+                .map(|entry| entry["query"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(queries(log.recent(2)), vec!["beavers", "seals"]);
+        assert_eq!(queries(log.recent(10)).len(), 3);
+    }
+
+    #[test]
+    fn only_the_most_recent_queries_are_kept() {
+        let log = QueryLog::new(None).unwrap();
+        for count in 0..RECENT_QUERY_CAPACITY + 5 {
+            log.record(&event(&count.to_string()));
+        }
+
+        let recent = log.recent(RECENT_QUERY_CAPACITY + 5);
+        assert_eq!(recent.len(), RECENT_QUERY_CAPACITY);
+        assert_eq!(recent[0]["query"], "5");
+    }
+}
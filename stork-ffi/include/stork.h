@@ -0,0 +1,69 @@
+#ifndef STORK_H
+#define STORK_H
+
+/* This file is generated by cbindgen. Run `just build-ffi-header` after changing stork-ffi/src/lib.rs. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum StorkStatus {
+  STORK_STATUS_OK = 0,
+  STORK_STATUS_NULL_POINTER = 1,
+  STORK_STATUS_INVALID_UTF8 = 2,
+  STORK_STATUS_INDEX_PARSE_ERROR = 3,
+  STORK_STATUS_SEARCH_ERROR = 4,
+  STORK_STATUS_UNSUPPORTED = 5,
+  STORK_STATUS_PANIC = 6,
+} StorkStatus;
+
+// An opaque handle to a parsed index.
+typedef struct StorkIndex StorkIndex;
+
+// Parses `len` bytes of a serialized index and writes a new handle to
+// `out_handle`.
+//
+// # Safety
+//
+// `bytes` must point to at least `len` readable bytes, and `out_handle`
+// must be a valid pointer to write to.
+StorkStatus stork_index_load(const uint8_t *bytes, uintptr_t len, StorkIndex **out_handle);
+
+// Searches the index for a UTF-8 query and writes the results, as the same
+// JSON the Javascript library receives, to `out_json`.
+//
+// # Safety
+//
+// `handle` must come from `stork_index_load` and not yet have been freed,
+// `query` must be a NUL-terminated string, and `out_json` must be a valid
+// pointer to write to.
+StorkStatus stork_search(const StorkIndex *handle, const char *query, char **out_json);
+
+// Writes a JSON array of up to `max_suggestions` indexed words that begin
+// with `prefix` to `out_json`.
+//
+// # Safety
+//
+// The same requirements as `stork_search` apply.
+StorkStatus stork_suggest(const StorkIndex *handle,
+                          const char *prefix,
+                          uintptr_t max_suggestions,
+                          char **out_json);
+
+// Releases an index handle. Passing a null pointer does nothing.
+//
+// # Safety
+//
+// `handle` must come from `stork_index_load` and must not be used again.
+StorkStatus stork_index_free(StorkIndex *handle);
+
+// Releases a string returned by `stork_search` or `stork_suggest`. Passing
+// a null pointer does nothing.
+//
+// # Safety
+//
+// `string` must come from this library and must not be used again.
+StorkStatus stork_string_free(char *string);
+
+#endif /* STORK_H */
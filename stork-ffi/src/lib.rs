@@ -0,0 +1,291 @@
+//! C bindings for searching Stork indexes from non-Rust applications.
+//!
+//! Every function returns a `StorkStatus`. Strings handed back to the caller
+//! are allocated by Rust and must be released with `stork_string_free`;
+//! index handles must be released with `stork_index_free`. A single index
+//! handle may be searched from several threads at once.
+
+use bytes::Bytes;
+use std::{
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    panic::{catch_unwind, UnwindSafe},
+    slice,
+};
+use stork_lib::{index_from_bytes, search_parsed_index, ParsedIndex};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorkStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidUtf8 = 2,
+    IndexParseError = 3,
+    SearchError = 4,
+    Unsupported = 5,
+    Panic = 6,
+}
+
+/// An opaque handle to a parsed index.
+pub struct StorkIndex(ParsedIndex);
+
+fn guard<F: FnOnce() -> StorkStatus + UnwindSafe>(f: F) -> StorkStatus {
+    catch_unwind(f).unwrap_or(StorkStatus::Panic)
+}
+
+unsafe fn str_from_ptr<'a>(string: *const c_char) -> Result<&'a str, StorkStatus> {
+    if string.is_null() {
+        return Err(StorkStatus::NullPointer);
+    }
+
+    CStr::from_ptr(string)
+        .to_str()
+        .map_err(|_| StorkStatus::InvalidUtf8)
+}
+
+unsafe fn write_json_string(json: String, out_json: *mut *mut c_char) -> StorkStatus {
+    match CString::new(json) {
+        Ok(c_string) => {
+            *out_json = c_string.into_raw();
+            StorkStatus::Ok
+        }
+        Err(_) => StorkStatus::InvalidUtf8,
+    }
+}
+
+/// Parses `len` bytes of a serialized index and writes a new handle to
+/// `out_handle`.
+///
+/// # Safety
+///
+/// `bytes` must point to at least `len` readable bytes, and `out_handle`
+/// must be a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn stork_index_load(
+    bytes: *const u8,
+    len: usize,
+    out_handle: *mut *mut StorkIndex,
+) -> StorkStatus {
+    guard(|| {
+        if bytes.is_null() || out_handle.is_null() {
+            return StorkStatus::NullPointer;
+        }
+
+        let data = Bytes::copy_from_slice(slice::from_raw_parts(bytes, len));
+
+        match index_from_bytes(data) {
+            Ok(parsed) => {
+                *out_handle = Box::into_raw(Box::new(StorkIndex(parsed)));
+                StorkStatus::Ok
+            }
+            Err(_) => StorkStatus::IndexParseError,
+        }
+    })
+}
+
+/// Searches the index for a UTF-8 query and writes the results, as the same
+/// JSON the Javascript library receives, to `out_json`.
+///
+/// # Safety
+///
+/// `handle` must come from `stork_index_load` and not yet have been freed,
+/// `query` must be a NUL-terminated string, and `out_json` must be a valid
+/// pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn stork_search(
+    handle: *const StorkIndex,
+    query: *const c_char,
+    out_json: *mut *mut c_char,
+) -> StorkStatus {
+    guard(|| {
+        if handle.is_null() || out_json.is_null() {
+            return StorkStatus::NullPointer;
+        }
+
+        let query = match str_from_ptr(query) {
+            Ok(query) => query,
+            Err(status) => return status,
+        };
+
+        let output = match search_parsed_index(&(*handle).0, query) {
+            Ok(output) => output,
+            Err(_) => return StorkStatus::SearchError,
+        };
+
+        match serde_json::to_string(&output) {
+            Ok(json) => write_json_string(json, out_json),
+            Err(_) => StorkStatus::SearchError,
+        }
+    })
+}
+
+/// Writes a JSON array of up to `max_suggestions` indexed words that begin
+/// with `prefix` to `out_json`.
+///
+/// # Safety
+///
+/// The same requirements as `stork_search` apply.
+#[no_mangle]
+pub unsafe extern "C" fn stork_suggest(
+    handle: *const StorkIndex,
+    prefix: *const c_char,
+    max_suggestions: usize,
+    out_json: *mut *mut c_char,
+) -> StorkStatus {
+    guard(|| {
+        if handle.is_null() || out_json.is_null() {
+            return StorkStatus::NullPointer;
+        }
+
+        let prefix = match str_from_ptr(prefix) {
+            Ok(prefix) => prefix.to_lowercase(),
+            Err(status) => return status,
+        };
+
+        #[allow(unreachable_patterns)]
+        let suggestions: Vec<&str> = match &(*handle).0 {
+            #[cfg(feature = "v3")]
+            ParsedIndex::V3(index) => index
+                .terms_with_prefix(&prefix)
+                .filter(|(_, stats)| stats.entry_count > 0)
+                .map(|(term, _)| term)
+                .take(max_suggestions)
+                .collect(),
+
+            _ => return StorkStatus::Unsupported,
+        };
+
+        match serde_json::to_string(&suggestions) {
+            Ok(json) => write_json_string(json, out_json),
+            Err(_) => StorkStatus::SearchError,
+        }
+    })
+}
+
+/// Releases an index handle. Passing a null pointer does nothing.
+///
+/// # Safety
+///
+/// `handle` must come from `stork_index_load` and must not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn stork_index_free(handle: *mut StorkIndex) -> StorkStatus {
+    guard(|| {
+        if !handle.is_null() {
+            drop(Box::from_raw(handle));
+        }
+        StorkStatus::Ok
+    })
+}
+
+/// Releases a string returned by `stork_search` or `stork_suggest`. Passing
+/// a null pointer does nothing.
+///
+/// # Safety
+///
+/// `string` must come from this library and must not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn stork_string_free(string: *mut c_char) -> StorkStatus {
+    guard(|| {
+        if !string.is_null() {
+            drop(CString::from_raw(string));
+        }
+        StorkStatus::Ok
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{ptr, sync::Arc, thread};
+
+    struct SendHandle(*mut StorkIndex);
+    unsafe impl Send for SendHandle {}
+    unsafe impl Sync for SendHandle {}
+
+    fn load_federalist() -> *mut StorkIndex {
+        let bytes = std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap();
+        let mut handle: *mut StorkIndex = ptr::null_mut();
+        let status = unsafe { stork_index_load(bytes.as_ptr(), bytes.len(), &mut handle) };
+        assert_eq!(status, StorkStatus::Ok);
+        handle
+    }
+
+    unsafe fn take_string(string: *mut c_char) -> String {
+        let owned = CStr::from_ptr(string).to_str().unwrap().to_string();
+        assert_eq!(stork_string_free(string), StorkStatus::Ok);
+        owned
+    }
+
+    #[test]
+    fn search_returns_json() {
+        let handle = load_federalist();
+        let query = CString::new("liberty").unwrap();
+        let mut json: *mut c_char = ptr::null_mut();
+
+        unsafe {
+            let status = stork_search(handle, query.as_ptr(), &mut json);
+            assert_eq!(status, StorkStatus::Ok);
+            assert!(take_string(json).contains("principles of liberty"));
+            stork_index_free(handle);
+        }
+    }
+
+    #[test]
+    fn suggest_returns_words_with_prefix() {
+        let handle = load_federalist();
+        let prefix = CString::new("libert").unwrap();
+        let mut json: *mut c_char = ptr::null_mut();
+
+        unsafe {
+            let status = stork_suggest(handle, prefix.as_ptr(), 5, &mut json);
+            assert_eq!(status, StorkStatus::Ok);
+            let suggestions: Vec<String> = serde_json::from_str(&take_string(json)).unwrap();
+            assert!(suggestions.iter().all(|s| s.starts_with("libert")));
+            assert!(suggestions.contains(&"liberty".to_string()));
+            stork_index_free(handle);
+        }
+    }
+
+    #[test]
+    fn invalid_index_bytes_return_error_code() {
+        let bytes = b"this is not an index";
+        let mut handle: *mut StorkIndex = ptr::null_mut();
+        let status = unsafe { stork_index_load(bytes.as_ptr(), bytes.len(), &mut handle) };
+        assert_eq!(status, StorkStatus::IndexParseError);
+        assert!(handle.is_null());
+    }
+
+    #[test]
+    fn null_pointers_return_error_code() {
+        let mut json: *mut c_char = ptr::null_mut();
+        let status = unsafe { stork_search(ptr::null(), ptr::null(), &mut json) };
+        assert_eq!(status, StorkStatus::NullPointer);
+    }
+
+    #[test]
+    fn one_handle_can_be_searched_concurrently() {
+        let handle = Arc::new(SendHandle(load_federalist()));
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let handle = Arc::clone(&handle);
+                thread::spawn(move || {
+                    let query = CString::new("liberty").unwrap();
+                    let mut json: *mut c_char = ptr::null_mut();
+                    unsafe {
+                        assert_eq!(
+                            stork_search(handle.0, query.as_ptr(), &mut json),
+                            StorkStatus::Ok
+                        );
+                        take_string(json)
+                    }
+                })
+            })
+            .collect();
+
+        let outputs: Vec<String> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        assert!(outputs.windows(2).all(|pair| pair[0] == pair[1]));
+
+        unsafe { stork_index_free(handle.0) };
+    }
+}
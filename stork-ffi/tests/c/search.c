@@ -0,0 +1,62 @@
+#include <stdio.h>
+#include <string.h>
+
+#include "stork.h"
+
+#define CHECK(expr)                                                  \
+  do {                                                               \
+    StorkStatus status = (expr);                                     \
+    if (status != STORK_STATUS_OK) {                                 \
+      fprintf(stderr, "%s failed with status %d\n", #expr, status);  \
+      return 1;                                                      \
+    }                                                                \
+  } while (0)
+
+int main(int argc, char **argv) {
+  if (argc != 2) {
+    fprintf(stderr, "usage: %s <index.st>\n", argv[0]);
+    return 1;
+  }
+
+  FILE *file = fopen(argv[1], "rb");
+  if (!file) {
+    perror("fopen");
+    return 1;
+  }
+
+  fseek(file, 0, SEEK_END);
+  long len = ftell(file);
+  fseek(file, 0, SEEK_SET);
+
+  uint8_t *bytes = malloc(len);
+  if (fread(bytes, 1, len, file) != (size_t)len) {
+    perror("fread");
+    return 1;
+  }
+  fclose(file);
+
+  StorkIndex *index = NULL;
+  CHECK(stork_index_load(bytes, (uintptr_t)len, &index));
+  free(bytes);
+
+  char *results = NULL;
+  CHECK(stork_search(index, "liberty", &results));
+  if (!strstr(results, "principles of liberty")) {
+    fprintf(stderr, "unexpected search results: %s\n", results);
+    return 1;
+  }
+  CHECK(stork_string_free(results));
+
+  char *suggestions = NULL;
+  CHECK(stork_suggest(index, "libert", 5, &suggestions));
+  if (!strstr(suggestions, "\"liberty\"")) {
+    fprintf(stderr, "unexpected suggestions: %s\n", suggestions);
+    return 1;
+  }
+  CHECK(stork_string_free(suggestions));
+
+  CHECK(stork_index_free(index));
+
+  printf("ok\n");
+  return 0;
+}
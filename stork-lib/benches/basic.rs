@@ -1,4 +1,4 @@
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
 use std::{convert::TryFrom, path::PathBuf, process::exit, time::Duration};
 use stork_lib::Config;
 
@@ -24,6 +24,108 @@ fn build_federalist(c: &mut Criterion) {
     });
 }
 
+/**
+ * A corpus of generated documents, so that index-building costs that grow
+ * with corpus size show up without any test corpora on disk.
+ * The words come from a fixed-seed generator, so every run builds the same
+ * index.
+ */
+fn synthetic_corpus_config(document_count: usize) -> Config {
+    const VOCABULARY: &[&str] = &[
+        "liberty",
+        "government",
+        "union",
+        "state",
+        "federal",
+        "power",
+        "people",
+        "constitution",
+        "running",
+        "runs",
+        "runner",
+        "walked",
+        "walking",
+        "search",
+        "index",
+        "document",
+        "excerpt",
+        "container",
+        "stem",
+        "prefix",
+        "alias",
+        "result",
+        "query",
+        "title",
+        "Harbor",
+        "harbors",
+        "harboring",
+        "mountain",
+        "river",
+        "valley",
+        "forest",
+        "meadow",
+    ];
+
+    // A simple LCG, so the corpus is the same on every run
+    let mut state: u32 = 12345;
+    let mut next_word = || {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        VOCABULARY[(state >> 16) as usize % VOCABULARY.len()]
+    };
+
+    let files: Vec<String> = (0..document_count)
+        .map(|index| {
+            let contents: Vec<&str> = (0..200).map(|_| next_word()).collect();
+            format!(
+                r#"{{ title = "Document {} {}", url = "/{}", contents = "{}." }}"#,
+                index,
+                next_word(),
+                index,
+                contents.join(" ")
+            )
+        })
+        .collect();
+
+    let toml = format!("[input]\nfiles = [\n{}\n]\n", files.join(",\n"));
+    Config::try_from(toml.as_str()).unwrap()
+}
+
+fn build_synthetic_corpus(c: &mut Criterion) {
+    let config = synthetic_corpus_config(10_000);
+
+    let mut group = c.benchmark_group("build");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(30));
+
+    group.bench_function("synthetic-10k", |b| {
+        b.iter(|| stork_lib::build_index(&config).unwrap())
+    });
+}
+
+/**
+ * The synthetic corpus uses a few dozen words over and over, so sharing
+ * stems between the documents being indexed at once should pay off.
+ */
+fn build_synthetic_corpus_stem_cache(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build/synthetic-10k/stem-cache");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(30));
+
+    for shared_stem_cache in [false, true] {
+        let mut config = synthetic_corpus_config(10_000);
+        config.input.shared_stem_cache = shared_stem_cache;
+
+        let name = if shared_stem_cache {
+            "shared"
+        } else {
+            "per-document"
+        };
+        group.bench_function(name, |b| {
+            b.iter(|| stork_lib::build_index(&config).unwrap())
+        });
+    }
+}
+
 fn search_federalist_for_liberty(c: &mut Criterion) {
     let config = config_from_path("./stork-lib/benches/federalist.toml");
     let bytes = stork_lib::build_index(&config).unwrap().bytes;
@@ -46,5 +148,144 @@ fn search_federalist_for_liberty(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, build_federalist, search_federalist_for_liberty);
+/**
+ * Full search and titles-only search for the same queries, so the cost of
+ * building excerpts shows up as the difference between the two.
+ */
+fn search_synthetic_corpus_titles(c: &mut Criterion) {
+    let config = synthetic_corpus_config(1_000);
+    let bytes = stork_lib::build_index(&config).unwrap().bytes;
+    let _ = stork_lib::register_index("synthetic", bytes);
+
+    let mut group = c.benchmark_group("search/synthetic-1k");
+    group.measurement_time(Duration::from_secs(10));
+
+    for query in ["harbor", "riv"] {
+        group.bench_function(format!("full/{}", query), |b| {
+            b.iter(|| stork_lib::search_from_cache("synthetic", query))
+        });
+
+        group.bench_function(format!("titles/{}", query), |b| {
+            b.iter(|| stork_lib::search_titles_from_cache("synthetic", query))
+        });
+    }
+}
+
+/**
+ * Typing one more character of a three-word query, searched from scratch and
+ * through a query session that has already searched the query before it.
+ */
+fn search_synthetic_corpus_as_you_type(c: &mut Criterion) {
+    let config = synthetic_corpus_config(1_000);
+    let bytes = stork_lib::build_index(&config).unwrap().bytes;
+    let _ = stork_lib::register_index("as-you-type", bytes);
+
+    let mut group = c.benchmark_group("search/synthetic-1k/as-you-type");
+    group.measurement_time(Duration::from_secs(10));
+
+    let (typed, next) = ("liberty harbor riv", "liberty harbor rive");
+
+    group.bench_function("cold", |b| {
+        b.iter(|| stork_lib::search_from_cache("as-you-type", next))
+    });
+
+    group.bench_function("session", |b| {
+        b.iter_batched(
+            || {
+                let handle = stork_lib::begin_query_session("as-you-type").unwrap();
+                stork_lib::update_query(handle, typed).unwrap();
+                handle
+            },
+            |handle| {
+                let output = stork_lib::update_query(handle, next);
+                stork_lib::end_query_session(handle);
+                output
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+/**
+ * An index with `vocabulary_size` generated words, plus a few that start
+ * with `ze`, which the generated words never do. The words that start with
+ * `ze` are the same at every size.
+ */
+fn generated_vocabulary_index(vocabulary_size: usize) -> stork_lib::Index {
+    // A simple LCG, so the vocabulary is the same on every run
+    let mut state: u32 = 54321;
+    let mut next_word = || -> String {
+        (0..8)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (b'a' + (state >> 16) as u8 % 25) as char
+            })
+            .collect()
+    };
+
+    let words: Vec<String> = (0..vocabulary_size).map(|_| next_word()).collect();
+    let files: Vec<String> = words
+        .chunks(1_000)
+        .enumerate()
+        .map(|(index, chunk)| {
+            format!(
+                r#"{{ title = "Document {}", url = "/{}", contents = "zebra zenith zephyr {}" }}"#,
+                index,
+                index,
+                chunk.join(" ")
+            )
+        })
+        .collect();
+
+    let toml = format!("[input]\nfiles = [\n{}\n]\n", files.join(",\n"));
+    let bytes = stork_lib::build_index(&Config::try_from(toml.as_str()).unwrap())
+        .unwrap()
+        .bytes;
+    stork_lib::Index::try_from(bytes.as_ref()).unwrap()
+}
+
+/**
+ * Listing the terms that start with a prefix, through the index's term
+ * transducer and by scanning every term, as the vocabulary grows. The
+ * transducer only visits the matching terms, so it should take about as long
+ * at every size.
+ */
+fn terms_with_prefix_by_vocabulary_size(c: &mut Criterion) {
+    let mut group = c.benchmark_group("terms-with-prefix");
+    group.measurement_time(Duration::from_secs(10));
+
+    for vocabulary_size in [1_000, 10_000, 100_000] {
+        let index = generated_vocabulary_index(vocabulary_size);
+
+        group.bench_with_input(
+            BenchmarkId::new("fst", vocabulary_size),
+            &index,
+            |b, index| b.iter(|| index.terms_with_prefix("ze").count()),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("scan", vocabulary_size),
+            &index,
+            |b, index| {
+                b.iter(|| {
+                    index
+                        .terms()
+                        .filter(|(term, _)| term.starts_with("ze"))
+                        .count()
+                })
+            },
+        );
+    }
+}
+
+criterion_group!(
+    benches,
+    build_federalist,
+    build_synthetic_corpus,
+    build_synthetic_corpus_stem_cache,
+    search_federalist_for_liberty,
+    search_synthetic_corpus_titles,
+    search_synthetic_corpus_as_you_type,
+    terms_with_prefix_by_vocabulary_size
+);
 criterion_main!(benches);
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+/**
+ * A cookie sent along with requests for `src_url` documents, such as a
+ * session cookie for a site that sits behind a login.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CookieConfig {
+    pub name: String,
+    pub value: String,
+
+    /// The host the cookie is sent to. Prefix it with a `.` to also send the
+    /// cookie to that host's subdomains.
+    pub domain: String,
+}
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/**
+ * Rules that leave documents out of the index once they've been read, like
+ * `{ url_patterns = ["/drafts"], minimum_word_count = 30 }`. The documents
+ * they exclude are listed in the build report.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct ExcludeConfig {
+    /// Documents read from a `path` that matches any of these globs are
+    /// excluded. Paths are relative to `input.base_directory`. `*` matches
+    /// any characters but `/`, `**` matches any characters, and `?` matches
+    /// any one character but `/`.
+    pub path_globs: Vec<String>,
+
+    /// Documents whose URL matches any of these patterns are excluded. They
+    /// use the same wildcards as `path_globs`, and are compared with the
+    /// document's URL before `input.url_prefix` is added.
+    pub url_patterns: Vec<String>,
+
+    /// Documents with fewer words than this are excluded.
+    pub minimum_word_count: Option<usize>,
+}
@@ -6,7 +6,7 @@ use std::collections::HashMap;
 
 type Fields = HashMap<String, String>;
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, SmartDefault, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct File {
     pub title: String,
@@ -33,10 +33,48 @@ pub struct File {
     #[serde(default)]
     pub filetype: Option<Filetype>,
 
+    /// An image shown alongside the document in search results. A relative
+    /// path is joined to `url_prefix` when the document is returned.
+    #[serde(default)]
+    pub thumbnail_url: Option<String>,
+
+    /// A short description of the document, indexed apart from its text.
+    /// Summary matches are worth less than title matches but more than
+    /// keyword matches, and searches can be limited to summaries alone.
+    #[serde(default)]
+    pub summary: Option<String>,
+
+    /// The date the document was published, like `2023-01-31`, which
+    /// searches can be limited by.
+    #[serde(default)]
+    pub date: Option<String>,
+
+    /// The absolute URL the document is published at, which relative
+    /// thumbnail URLs read from the document are resolved against, the way
+    /// a browser would resolve them. Useful for `contents` sources, which
+    /// have no URL of their own. Thumbnails read from documents are left as
+    /// they're written when this isn't set.
+    #[serde(default)]
+    pub base_url: Option<String>,
+
+    /// When false, the document's text isn't stored and its matches are
+    /// stored without their positions, so it's returned in search results
+    /// without excerpts. This keeps large documents, like a glossary, whose
+    /// title and URL are enough, from bloating the index. The document is
+    /// scored by its best match, and `title:` queries and highlights work as
+    /// usual.
+    #[serde(default = "default_generate_excerpts")]
+    #[default = true]
+    pub generate_excerpts: bool,
+
     #[serde(flatten, default)]
     pub fields: Fields,
 }
 
+fn default_generate_excerpts() -> bool {
+    true
+}
+
 impl File {
     pub fn source(&self) -> DataSource {
         match &self.explicit_source {
@@ -63,6 +101,20 @@ impl fmt::Display for File {
     }
 }
 
+/**
+ * Settings for every file of one filetype, from `input.filetype_defaults`.
+ * Each takes the place of the `input` option of the same name for those
+ * files, unless a file sets its own `_override`.
+ */
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct FiletypeDefaults {
+    pub stemming: Option<StemmingConfig>,
+    pub html_selector: Option<String>,
+    pub exclude_html_selector: Option<String>,
+    pub frontmatter_handling: Option<FrontmatterConfig>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, SmartDefault)]
 pub enum DataSource {
     #[serde(rename = "contents")]
@@ -76,12 +128,17 @@ pub enum DataSource {
     FilePath(String),
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Filetype {
     PlainText,
     SRTSubtitle,
     HTML,
     Markdown,
+
+    /// Plain text whose words are indexed exactly as written, without
+    /// stemming, regardless of the stemming config. Useful for logs and
+    /// identifiers.
+    PlainTextNoStem,
 }
 
 #[cfg(test)]
@@ -153,6 +210,25 @@ mod tests {
         assert_eq!(file.url, "blorp");
     }
 
+    #[test]
+    fn file_generates_excerpts_unless_disabled() {
+        let file: File = toml::from_str(
+            r#"title = "Derp"
+        url = "blorp""#,
+        )
+        .unwrap();
+        assert!(file.generate_excerpts);
+        assert!(File::default().generate_excerpts);
+
+        let file: File = toml::from_str(
+            r#"title = "Derp"
+        url = "blorp"
+        generate_excerpts = false"#,
+        )
+        .unwrap();
+        assert!(!file.generate_excerpts);
+    }
+
     #[test]
     fn file_with_only_src_url_fails() {
         let toml = r#"title = "Derp"
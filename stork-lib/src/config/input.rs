@@ -1,7 +1,16 @@
 use serde::{Deserialize, Serialize};
 use smart_default::SmartDefault;
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::RangeInclusive,
+};
 
-use super::{File, FrontmatterConfig, SRTConfig, StemmingConfig};
+use crate::CustomTokenizer;
+
+use super::{
+    CookieConfig, ExcludeConfig, File, Filetype, FiletypeDefaults, FrontmatterConfig,
+    JsonLdProperty, SRTConfig, StemmingConfig, UrlCanonicalizationConfig,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, SmartDefault, PartialEq)]
 #[serde(deny_unknown_fields)]
@@ -13,6 +22,179 @@ pub enum TitleBoost {
     Ridiculous,
 }
 
+/**
+ * How quickly title words lose weight the further they are from the start
+ * of the title, for `title_position_decay`.
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, SmartDefault, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TitlePositionDecayCurve {
+    /// Each word is worth `title_position_decay` times the word before it.
+    #[default]
+    Exponential,
+
+    /// Each word is worth `1 - title_position_decay` less than the word
+    /// before it, down to nothing.
+    Linear,
+}
+
+/**
+ * What to do when a document is indexed but looks like it has a problem,
+ * like having very few words.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, SmartDefault, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WarningHandling {
+    /// Index the document without mentioning the problem.
+    Allow,
+
+    /// Index the document and list the problem in the build report.
+    #[default]
+    Print,
+
+    /// Treat the problem as an error in the document.
+    Deny,
+}
+
+/**
+ * What to do with the email addresses or URLs found in a document's text.
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, SmartDefault, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkHandling {
+    /// Index them like any other text.
+    #[default]
+    Keep,
+
+    /// Leave them out of the index.
+    Drop,
+
+    /// Index only their domain, e.g. `example.com`.
+    Domain,
+}
+
+/**
+ * What to do with runs of single-letter words in a document's text, like the
+ * spaced-out acronym `A W S`.
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, SmartDefault, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpacedLetterHandling {
+    /// Index each letter on its own.
+    #[default]
+    Separate,
+
+    /// Index the run as one word, e.g. `aws`, instead of its letters.
+    Merge,
+
+    /// Index the run as one word, and each of its letters on its own too.
+    MergeAndKeep,
+}
+
+/**
+ * What to do with acronyms written with a period after each letter, like
+ * `U.S.A.` or `e.g.`.
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, SmartDefault, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AcronymPeriodHandling {
+    /// Index the acronym with the periods between its letters, e.g. `u.s.a`.
+    #[default]
+    Keep,
+
+    /// Index the acronym without its periods, e.g. `usa`, so it's found by
+    /// either spelling.
+    Collapse,
+}
+
+/**
+ * What to do with a word that's split across two lines with a hyphen, like
+ * `inter-` at the end of one line and `national` at the start of the next, as
+ * in text taken from PDFs or OCR.
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, SmartDefault, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HyphenatedLineBreakHandling {
+    /// Index each part on its own, like the parts of any hyphenated word.
+    #[default]
+    Split,
+
+    /// Rejoin the parts into one word, e.g. `international`, before the
+    /// text is split into words. Only a hyphen that ends a line, after a
+    /// letter and before a lowercase letter, is removed, so compounds like
+    /// `well-known` are left alone.
+    Join,
+}
+
+/**
+ * How a document's text is split into words, besides at whitespace and
+ * hyphens.
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, SmartDefault, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WordSegmentation {
+    /// Only split at whitespace and hyphens.
+    #[default]
+    Whitespace,
+
+    /// Also split runs of Thai and Lao text, which don't have spaces between
+    /// words, into the words of Stork's built-in dictionary. Text in other
+    /// scripts is split the same as with `whitespace`. Requires Stork to be
+    /// built with the `build-v3-thai-lao-segmentation` feature.
+    Dictionary,
+}
+
+/**
+ * What to do when `stemming` names a language that Stork's stemmer doesn't
+ * support.
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, SmartDefault, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UnsupportedStemmingLanguageHandling {
+    /// Fail the build before any document is read.
+    #[default]
+    Error,
+
+    /// Index the documents without stemming them, and report a nudge.
+    Fallback,
+}
+
+/**
+ * The lengths, in characters, of the n-grams indexed for substring search.
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, SmartDefault, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct NgramConfig {
+    #[default = 3]
+    pub min: u8,
+
+    #[default = 5]
+    pub max: u8,
+}
+
+impl NgramConfig {
+    /// The n-gram lengths to index. A `min` of 0 counts as 1, and a `max`
+    /// below `min` counts as `min`.
+    pub fn lengths(&self) -> RangeInclusive<usize> {
+        let min = self.min.max(1) as usize;
+        min..=(self.max as usize).max(min)
+    }
+}
+
+/**
+ * Scales the search scores of every document whose URL path starts with
+ * `prefix`, like `{ prefix = "/changelog/2018", multiplier = 0.2 }`.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PathScoreMultiplier {
+    pub prefix: String,
+
+    /// What the document's scores are multiplied by. Below 1 ranks the
+    /// documents lower, and above 1 ranks them higher.
+    pub multiplier: f64,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, SmartDefault, PartialEq)]
 #[serde(deny_unknown_fields, default)]
 #[allow(non_snake_case)]
@@ -22,14 +204,58 @@ pub struct InputConfig {
     pub base_directory: String,
     pub url_prefix: String,
     pub title_boost: TitleBoost,
+
+    /// How much less each word of a title adds to the title boost than the
+    /// word before it, so queries matching the start of a title rank it
+    /// higher than queries matching its end. Between 0 and 1; 1, the
+    /// default, weighs every title word the same.
+    #[default = 1.0]
+    pub title_position_decay: f64,
+
+    /// Whether `title_position_decay` is applied exponentially or linearly.
+    pub title_position_decay_curve: TitlePositionDecayCurve,
     pub stemming: StemmingConfig,
+
+    /// What to do when `stemming`, or a filetype's or file's stemming
+    /// override, names a language that can't be stemmed.
+    pub unsupported_stemming_language: UnsupportedStemmingLanguageHandling,
+
+    /// Each distinct word is stemmed once per document it's in. When true,
+    /// documents being indexed in parallel also share the stems they've
+    /// found, so each distinct word is stemmed about once per build. This
+    /// only changes how fast the index is built, not the index; it helps
+    /// most with large corpora that use the same words over and over.
+    #[default = false]
+    pub shared_stem_cache: bool,
     pub html_selector: Option<String>,
 
     #[default(None)]
     pub exclude_html_selector: Option<String>,
     pub frontmatter_handling: FrontmatterConfig,
+
+    /// Documents whose frontmatter contains any of these key/value pairs
+    /// are skipped instead of being indexed.
+    #[default(default_exclude_frontmatter_fields())]
+    pub exclude_frontmatter_fields: HashMap<String, String>,
+
+    /// Rules that leave documents out of the index by their path, URL, or
+    /// word count.
+    pub exclude: ExcludeConfig,
+
+    /// How documents' URLs are rewritten before they're compared, so that
+    /// variants of the same URL, like `/docs/intro/` and
+    /// `/docs/intro/index.html`, are found to be duplicates.
+    pub url_canonicalization: UrlCanonicalizationConfig,
     pub files: Vec<File>,
 
+    /// The path to a file that lists more web pages to index, one URL per
+    /// line, optionally followed by a tab and the page's title. Blank lines
+    /// and lines starting with `#` are skipped. Each page is fetched like a
+    /// `src_url` document listed after `files`, and is titled with its URL
+    /// if the line doesn't give a title.
+    #[default(None)]
+    pub urls_file: Option<String>,
+
     #[default = false]
     pub break_on_file_error: bool,
     pub srt_config: SRTConfig,
@@ -38,5 +264,275 @@ pub struct InputConfig {
     pub minimum_indexed_substring_length: u8,
 
     #[default = 1]
-    pub minimum_index_ideographic_substring_length: u8,
+    pub minimum_indexed_ideographic_substring_length: u8,
+
+    /// The old name of `minimum_indexed_ideographic_substring_length`. If
+    /// it's set, it's used instead.
+    #[serde(rename = "minimum_index_ideographic_substring_length")]
+    #[default(None)]
+    pub DEPRECATED_minimum_index_ideographic_substring_length: Option<u8>,
+
+    /// Search terms found in fewer documents than this, like rare typos, are
+    /// removed from the index once it's built. The build report says how
+    /// many were. 1, the default, keeps every term.
+    #[default = 1]
+    pub min_document_frequency: usize,
+
+    /// Words longer than this many characters, like a minified script that
+    /// was indexed by mistake, are cut short, with a warning for the
+    /// document.
+    #[default = 1024]
+    pub maximum_token_length: usize,
+
+    /// The most words indexed from each document. Words after them aren't
+    /// indexed, with a warning for the document.
+    #[default = 1_000_000]
+    pub maximum_words_per_document: usize,
+
+    /// The most search terms the index can have. A build that would have
+    /// more fails, naming the documents with the most terms.
+    #[default = 10_000_000]
+    pub maximum_unique_terms: usize,
+
+    /// When set, every run of `min` to `max` characters within each title and
+    /// contents word is indexed too, so a query can match the middle of a
+    /// word, like `x200` in `ABX2004`. This makes the index larger: with the
+    /// default lengths, the index of a 1,600-word essay grew by about 40%,
+    /// and n-grams were 30% of it. The build report shows how much of the
+    /// index is n-grams.
+    #[default(None)]
+    pub index_ngrams: Option<NgramConfig>,
+
+    /// When true, the segments of each document's URL path are indexed
+    /// as low-weight search terms.
+    #[default = false]
+    pub index_url_segments: bool,
+
+    /// When true, HTML documents with serious markup errors (such as
+    /// unclosed or misnested elements) fail to index instead of being
+    /// indexed from the parser's best-effort recovery.
+    #[default = false]
+    pub strict_html: bool,
+
+    /// The number of threads used to read documents and build the index.
+    /// Defaults to the number of CPUs; set it to 1 for a fully serial build.
+    #[default(None)]
+    pub threads: Option<usize>,
+
+    /// When true, each batch of documents is merged into the index as soon
+    /// as it's read, instead of every document's word list being held until
+    /// all of them are read. This uses much less memory on large corpora,
+    /// and builds an identical index. Async builds ignore this option.
+    #[default = false]
+    pub stream_documents: bool,
+    pub warnings: WarningHandling,
+
+    /// How email addresses in documents' text are indexed.
+    pub email_handling: LinkHandling,
+
+    /// How URLs in documents' text are indexed.
+    pub url_handling: LinkHandling,
+
+    /// How runs of two or more single-letter words, like `A W S`, are
+    /// indexed. Excerpts show the text as written either way.
+    pub spaced_letters: SpacedLetterHandling,
+
+    /// How acronyms with a period after each letter, like `U.S.A.`, are
+    /// indexed. Words at the end of a sentence are never changed, and
+    /// excerpts show the text as written either way.
+    pub acronym_periods: AcronymPeriodHandling,
+
+    /// How words split across lines with a hyphen are indexed. Plain-text
+    /// files aren't split into words as they're read when these are joined.
+    pub hyphenated_line_breaks: HyphenatedLineBreakHandling,
+
+    /// How words in scripts written without spaces, like Thai and Lao, are
+    /// found. Queries aren't segmented, so the words of a query in those
+    /// scripts have to be separated by spaces.
+    pub word_segmentation: WordSegmentation,
+
+    /// When true, the full-width forms of ASCII letters, digits, and
+    /// punctuation, like `ＡＢＣ１２３`, are indexed as ASCII, and half-width
+    /// katakana, like `ｶﾀｶﾅ`, as full-width katakana, so either form of a
+    /// word finds the other. Documents' text and titles are shown in the
+    /// normalized form too.
+    #[default = false]
+    pub normalize_width: bool,
+
+    /// When true, each emoji, like `🚀` or `👍🏽`, is indexed as a word of its
+    /// own, even when it's written next to other text, so it can be searched
+    /// for. Queries are split the same way.
+    #[default = false]
+    pub index_emoji: bool,
+
+    /// When true, the text of an HTML document's `<code>` and `<pre>`
+    /// elements is indexed as code: each word is split only at whitespace,
+    /// and the identifiers in it, like `getUserName` or `user_name`, can be
+    /// searched for whole or by their parts, like `user`. Its matches can be
+    /// weighted with `output.code_match_weight`, and a query can be limited
+    /// to them with `code:`.
+    #[default = false]
+    pub index_code_identifiers: bool,
+
+    /// Phrases, like a cookie banner or a "Share this page" link, that are
+    /// removed from every document's text before it's indexed. A phrase is
+    /// removed wherever its words appear in order, written exactly as they
+    /// are here; whitespace between them doesn't matter.
+    pub boilerplate_phrases: Vec<String>,
+
+    /// Regular expressions, like `Copyright \d{4}`, whose matches are removed
+    /// from every document's text before it's indexed. They're matched
+    /// against the document's words joined by single spaces, and only the
+    /// words that lie entirely within a match are removed.
+    pub boilerplate_patterns: Vec<String>,
+
+    /// When set, every run of 10 or more words that appears word for word in
+    /// more than this percent of the documents is reported in the build's
+    /// nudges, so it can be added to `boilerplate_phrases`. Only documents
+    /// whose text is stored for excerpts are checked.
+    #[default(None)]
+    pub boilerplate_detection_percent: Option<u8>,
+
+    /// The `name` or `property` of each HTML `<meta>` tag, like `author` or
+    /// `og:description`, whose content is stored in the document's fields.
+    /// Fields set in the config take precedence.
+    pub html_meta_tags: Vec<String>,
+
+    /// When set, HTML documents' `<meta>` tags whose `name` or `property`
+    /// starts with this prefix, ignoring case, are read like frontmatter
+    /// fields, so `<meta name="stork:draft" content="true">` is treated like
+    /// `draft: true` in a Markdown document's frontmatter. Tags that share a
+    /// name are joined into one comma-separated value, and fields from the
+    /// document's actual frontmatter take precedence.
+    #[default(None)]
+    pub html_meta_prefix: Option<String>,
+
+    /// When true, the comma-separated terms in HTML documents'
+    /// `<meta name="keywords">` tag are indexed as search terms, worth a bit
+    /// less than a match in the title.
+    #[default = false]
+    pub index_meta_keywords: bool,
+
+    /// Properties read from HTML documents' JSON-LD blocks, to be indexed as
+    /// search terms or stored in the document's fields. A block that isn't
+    /// valid JSON is skipped with a warning.
+    pub json_ld_properties: Vec<JsonLdProperty>,
+
+    /// When true, each document without a `thumbnail_url` of its own takes
+    /// one from its `thumbnail_frontmatter_field` or, for HTML documents,
+    /// its `og:image` meta tag.
+    #[default = false]
+    pub extract_thumbnails: bool,
+
+    /// The frontmatter field `extract_thumbnails` reads thumbnails from.
+    #[default("thumbnail".to_string())]
+    pub thumbnail_frontmatter_field: String,
+
+    /// When true, each document without a `summary` of its own takes one
+    /// from its `summary_frontmatter_field` or, for HTML documents, its
+    /// `description` meta tag.
+    #[default = false]
+    pub extract_summaries: bool,
+
+    /// The frontmatter field `extract_summaries` reads summaries from.
+    #[default("summary".to_string())]
+    pub summary_frontmatter_field: String,
+
+    /// When true, each document without a `date` of its own takes one from
+    /// its `date_frontmatter_field`.
+    #[default = false]
+    pub extract_dates: bool,
+
+    /// The frontmatter field `extract_dates` reads dates from.
+    #[default("date".to_string())]
+    pub date_frontmatter_field: String,
+
+    /// When true, documents that share a title are displayed with a segment
+    /// of their URL path that tells them apart, like "Installation (cli)".
+    #[default = false]
+    pub disambiguate_duplicate_titles: bool,
+
+    /// Sections of the site, each a label and the URL path prefix its
+    /// documents share, like `{ docs = "/docs/", blog = "/blog/" }`. Each
+    /// document is put in the section with the longest prefix its URL
+    /// matches, or `other`. Search results count how many matches are in
+    /// each section, and a `section:docs` query word limits results to one.
+    pub facet_url_prefixes: BTreeMap<String, String>,
+
+    /// The languages of the site, each a language tag and the URL path
+    /// prefix its documents share, like `{ en = "/en/", de = "/de/" }`. Each
+    /// document is tagged with the language of the longest prefix its URL
+    /// matches, the same way as `facet_url_prefixes`, and stemmed in that
+    /// language unless it has a `stemming` override of its own. Searches
+    /// given a language only return documents in that language, and
+    /// documents that match no prefix.
+    pub language_url_prefixes: BTreeMap<String, String>,
+
+    /// Rules that rank some documents higher or lower than others, by the
+    /// prefix their URL path starts with. A document is scaled by the rule
+    /// with the longest matching prefix, and prefixes are matched the same
+    /// way as `facet_url_prefixes`.
+    pub path_score_multipliers: Vec<PathScoreMultiplier>,
+
+    /// When true, each document without a `stemming` override of its own is
+    /// stemmed in the language it declares, with a `lang` frontmatter field or
+    /// an `<html lang>` attribute, or else the language its text is detected
+    /// to be in. When detection isn't confident enough, the document is
+    /// stemmed according to `stemming` and a warning is reported. Requires
+    /// Stork to be built with the `build-v3-langdetect` feature.
+    #[default = false]
+    pub detect_language: bool,
+
+    /// How confident, from 0 to 1, language detection has to be before a
+    /// document's detected language is used.
+    #[default = 0.5]
+    pub language_detection_threshold: f64,
+
+    /// Cookies sent when fetching `src_url` documents.
+    pub cookies: Vec<CookieConfig>,
+
+    /// The path to a Netscape-format cookie file, like the ones written by
+    /// `curl -c` or browser export extensions. Its cookies are sent when
+    /// fetching `src_url` documents, alongside any in `cookies`. It's read
+    /// once, before any document is, and the build fails if it can't be.
+    #[default(None)]
+    pub cookie_file: Option<String>,
+
+    /// When true, every document's URL is requested once the index is built,
+    /// and the ones that don't answer with a successful status are reported.
+    /// Requires Stork to be built with the `build-v3-web-scraping` feature.
+    #[default = false]
+    pub verify_urls: bool,
+
+    /// The URL that relative document URLs are resolved against when
+    /// `verify_urls` is on. Without one, relative URLs aren't requested.
+    #[default(None)]
+    pub verify_url_base: Option<String>,
+
+    /// The filetype of files without a `filetype` of their own, when it
+    /// can't be inferred from their extension, MIME type, or contents.
+    /// Without one, those files are read as plain text.
+    #[default(None)]
+    pub default_filetype: Option<Filetype>,
+
+    /// The tokenizer that splits every document's text, title, and keywords
+    /// into words, in place of Stork's own. It can only be set by programs
+    /// that use Stork as a library.
+    #[serde(skip)]
+    #[default(None)]
+    pub tokenizer: Option<CustomTokenizer>,
+
+    /// Settings for every file of a filetype, like
+    /// `[input.filetype_defaults.Markdown]`, applied once each file's
+    /// filetype is known, whether it's configured or inferred. A file's own
+    /// `_override` fields take precedence over these, and these take
+    /// precedence over the options of the same name above.
+    pub filetype_defaults: HashMap<Filetype, FiletypeDefaults>,
+}
+
+fn default_exclude_frontmatter_fields() -> HashMap<String, String> {
+    HashMap::from([
+        ("draft".to_string(), "true".to_string()),
+        ("search".to_string(), "false".to_string()),
+    ])
 }
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+
+/**
+ * A property read from the JSON-LD blocks (`<script type="application/ld+json">`)
+ * of HTML documents, like the answers of an FAQ:
+ *
+ * ```toml
+ * [[input.json_ld_properties]]
+ * type = "FAQPage"
+ * property = "mainEntity.acceptedAnswer.text"
+ * ```
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, SmartDefault, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct JsonLdProperty {
+    /// The `@type` of the objects the property is read from, like `FAQPage`
+    /// or `Article`. When unset, the property is read from objects of any
+    /// type.
+    #[serde(rename = "type")]
+    pub schema_type: Option<String>,
+
+    /// The path to the property within each object, with a `.` between the
+    /// names of nested properties. Where the path reaches an array, each of
+    /// its items is followed.
+    pub property: String,
+
+    /// When true, the property's values are indexed as search terms, worth
+    /// as much as the terms of an HTML `keywords` meta tag.
+    #[default = true]
+    pub index: bool,
+
+    /// The name of the field the property's values are stored in, if any,
+    /// separated by spaces when there's more than one. Fields set in the
+    /// config take precedence.
+    pub field: Option<String>,
+}
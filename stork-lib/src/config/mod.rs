@@ -2,10 +2,17 @@ use serde::{Deserialize, Serialize};
 use smart_default::SmartDefault;
 
 mod input;
-pub use input::{InputConfig, TitleBoost};
+pub use input::{
+    AcronymPeriodHandling, HyphenatedLineBreakHandling, InputConfig, LinkHandling, NgramConfig,
+    PathScoreMultiplier, SpacedLetterHandling, TitleBoost, TitlePositionDecayCurve,
+    UnsupportedStemmingLanguageHandling, WarningHandling, WordSegmentation,
+};
 
 mod output;
-pub use output::OutputConfig;
+pub use output::{
+    ExcerptStrategy, HeadingSlugStyle, IndexFormat, MatchWeights, OutputConfig, QueryOperator,
+    TieBreaker, UnknownQueryFields, ZeroResultFallback,
+};
 
 mod stemming;
 pub use stemming::StemmingConfig;
@@ -14,7 +21,7 @@ mod frontmatter;
 pub use self::frontmatter::FrontmatterConfig;
 
 mod file;
-pub use file::{DataSource, File, Filetype};
+pub use file::{DataSource, File, Filetype, FiletypeDefaults};
 
 mod srt;
 pub use srt::{SRTConfig, SRTTimestampFormat};
@@ -22,7 +29,19 @@ pub use srt::{SRTConfig, SRTTimestampFormat};
 mod errors;
 pub use errors::ConfigReadError;
 
-#[derive(Serialize, Deserialize, Debug, SmartDefault, PartialEq)]
+mod cookie;
+pub use cookie::CookieConfig;
+
+mod json_ld;
+pub use json_ld::JsonLdProperty;
+
+mod exclude;
+pub use exclude::ExcludeConfig;
+
+mod url_canonicalization;
+pub use url_canonicalization::UrlCanonicalizationConfig;
+
+#[derive(Serialize, Deserialize, Clone, Debug, SmartDefault, PartialEq)]
 #[serde(deny_unknown_fields, default)]
 pub struct Config {
     pub input: InputConfig,
@@ -66,7 +85,7 @@ impl TryFrom<&str> for Config {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap};
 
     use super::*;
     use pretty_assertions::assert_eq;
@@ -85,12 +104,23 @@ mod tests {
                 base_directory: "test/federalist".into(),
                 url_prefix: "".into(),
                 title_boost: TitleBoost::Moderate,
+                title_position_decay: 1.0,
+                title_position_decay_curve: TitlePositionDecayCurve::Exponential,
                 stemming: StemmingConfig::Language(
                     rust_stemmers::Algorithm::English,
                 ),
+                unsupported_stemming_language: UnsupportedStemmingLanguageHandling::Error,
+                shared_stem_cache: false,
                 html_selector: None,
                 exclude_html_selector: None,
                 frontmatter_handling: FrontmatterConfig::Omit,
+                exclude_frontmatter_fields: HashMap::from([
+                    ("draft".to_string(), "true".to_string()),
+                    ("search".to_string(), "false".to_string()),
+                ]),
+                exclude: ExcludeConfig::default(),
+                url_canonicalization: UrlCanonicalizationConfig::default(),
+                urls_file: None,
                 files: vec![
                     File {
                         title: "Introduction".into(),
@@ -106,6 +136,11 @@ mod tests {
                         exclude_html_selector_override: None,
                         frontmatter_handling_override: None,
                         filetype: None,
+                        thumbnail_url: None,
+                        summary: None,
+                        date: None,
+                        base_url: None,
+                        generate_excerpts: true,
                         fields: HashMap::new(),
                     },
                     File {
@@ -122,6 +157,11 @@ mod tests {
                         exclude_html_selector_override: None,
                         frontmatter_handling_override: None,
                         filetype: None,
+                        thumbnail_url: None,
+                        summary: None,
+                        date: None,
+                        base_url: None,
+                        generate_excerpts: true,
                         fields: HashMap::new(),
                     },
                     File {
@@ -138,6 +178,11 @@ mod tests {
                         exclude_html_selector_override: None,
                         frontmatter_handling_override: None,
                         filetype: None,
+                        thumbnail_url: None,
+                        summary: None,
+                        date: None,
+                        base_url: None,
+                        generate_excerpts: true,
                         fields: HashMap::new(),
                     },
                 ],
@@ -148,15 +193,92 @@ mod tests {
                     timestamp_format: SRTTimestampFormat::NumberOfSeconds,
                 },
                 minimum_indexed_substring_length: 3,
-                minimum_index_ideographic_substring_length: 1,
+                minimum_indexed_ideographic_substring_length: 1,
+                DEPRECATED_minimum_index_ideographic_substring_length: None,
+                min_document_frequency: 1,
+                maximum_token_length: 1024,
+                maximum_words_per_document: 1_000_000,
+                maximum_unique_terms: 10_000_000,
+                index_ngrams: None,
+                index_url_segments: false,
+                strict_html: false,
+                threads: None,
+                stream_documents: false,
+                warnings: WarningHandling::Print,
+                email_handling: LinkHandling::Keep,
+                url_handling: LinkHandling::Keep,
+                spaced_letters: SpacedLetterHandling::Separate,
+                acronym_periods: AcronymPeriodHandling::Keep,
+                hyphenated_line_breaks: HyphenatedLineBreakHandling::Split,
+                word_segmentation: WordSegmentation::Whitespace,
+                normalize_width: false,
+                index_emoji: false,
+                index_code_identifiers: false,
+                boilerplate_phrases: vec![],
+                boilerplate_patterns: vec![],
+                boilerplate_detection_percent: None,
+                html_meta_tags: vec![],
+                html_meta_prefix: None,
+                index_meta_keywords: false,
+                json_ld_properties: vec![],
+                extract_thumbnails: false,
+                thumbnail_frontmatter_field: "thumbnail".to_string(),
+                extract_summaries: false,
+                summary_frontmatter_field: "summary".to_string(),
+                extract_dates: false,
+                date_frontmatter_field: "date".to_string(),
+                disambiguate_duplicate_titles: false,
+                facet_url_prefixes: BTreeMap::new(),
+                language_url_prefixes: BTreeMap::new(),
+                path_score_multipliers: vec![],
+                detect_language: false,
+                language_detection_threshold: 0.5,
+                cookies: vec![],
+                cookie_file: None,
+                verify_urls: false,
+                verify_url_base: None,
+                default_filetype: None,
+                tokenizer: None,
+                filetype_defaults: HashMap::new(),
             },
             output: OutputConfig {
                 UNUSED_filename: None,
                 debug: true,
                 save_nearest_html_id: false,
+                markdown_heading_slugs: None,
+                save_stem_surface_forms: false,
+                store_document_text: false,
+                stored_text_max_chars: None,
                 excerpt_buffer: 8,
                 excerpts_per_result: 5,
+                max_stored_excerpts_per_result: None,
+                excerpt_strategy: ExcerptStrategy::Best,
+                excerpt_max_chars: None,
+                excerpt_sentence_tolerance: None,
+                excerpt_item_separator: "· ".to_string(),
+                unknown_query_fields: UnknownQueryFields::Literal,
+                query_operator: QueryOperator::Or,
+                length_normalization: 0.0,
+                proximity_boost: 0.0,
+                proximity_window: 8,
+                code_match_weight: 1.0,
+                match_weights: MatchWeights {
+                    exact: 1.0,
+                    prefix: 0.99,
+                    stem: 0.5,
+                    substring: 0.375,
+                },
+                zero_result_fallback: None,
+                strip_errors: false,
+                group_by_url_without_fragment: false,
+                reading_time_wpm: 0,
                 displayed_results_count: 10,
+                tie_breaker: TieBreaker::Title,
+                min_query_length: 1,
+                format: IndexFormat::Single,
+                shard_count: 8,
+                max_index_bytes: None,
+                self_test: false,
             },
         }
     }
@@ -184,6 +306,12 @@ debug = true
         assert_eq!(computed, expected)
     }
 
+    #[test]
+    fn warning_handling_is_parseable() {
+        let config = Config::try_from("[input]\nwarnings = \"deny\"").unwrap();
+        assert_eq!(config.input.warnings, WarningHandling::Deny);
+    }
+
     #[test]
     fn simple_json_config_is_parseable() {
         let contents = r#"
@@ -1,6 +1,162 @@
 use serde::{Deserialize, Serialize};
 use smart_default::SmartDefault;
 
+/**
+ * How the built index is laid out on disk.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, SmartDefault)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexFormat {
+    /// A single file containing the whole index.
+    #[default]
+    Single,
+
+    /// A small manifest holding the documents and a map of search terms to
+    /// shard numbers, plus `shard_count` shard files holding the search
+    /// terms themselves, so a frontend only fetches the shards it needs.
+    Sharded,
+}
+
+/**
+ * How results with the same score are ordered, so the same search always
+ * returns them in the same order.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, SmartDefault)]
+#[serde(rename_all = "lowercase")]
+pub enum TieBreaker {
+    /// By title, then by URL, then in the order the documents were
+    /// indexed.
+    #[default]
+    Title,
+
+    /// By URL, then by title, then in the order the documents were
+    /// indexed.
+    Url,
+
+    /// In the order the documents were indexed.
+    Index,
+}
+
+/**
+ * How the excerpts shown for each result are chosen from the places the
+ * query matched.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, SmartDefault)]
+#[serde(rename_all = "lowercase")]
+pub enum ExcerptStrategy {
+    /// The excerpts that appear first in the document, in document order.
+    First,
+
+    /// The highest-scoring excerpts, i.e. those where the query's words
+    /// match most densely, best first.
+    #[default]
+    Best,
+
+    /// The best excerpt from each of `excerpts_per_result` equal parts of
+    /// the document, in document order, topped up with the next best
+    /// excerpts if some parts have no matches.
+    Spread,
+}
+
+/**
+ * What a search does with a `field:word` query whose field isn't one Stork
+ * knows about.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, SmartDefault)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownQueryFields {
+    /// Search for the whole `field:word` text, as if it weren't scoped.
+    #[default]
+    Literal,
+
+    /// Fail the search with an error.
+    Error,
+}
+
+/**
+ * Whether a search's results have to match every word of the query, or any
+ * of them.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, SmartDefault)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryOperator {
+    /// Return documents that match any of the query's words.
+    #[default]
+    Or,
+
+    /// Only return documents that match all of the query's words, or a
+    /// stemmed, prefixed, or substring form of each. Stopwords don't have to
+    /// match.
+    And,
+}
+
+/**
+ * How the ids of Markdown documents' headings are made, so that excerpts
+ * link to the same anchors as the pages a site generator builds from them.
+ * Repeated ids within a document get `-1`, `-2`, ... suffixes.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HeadingSlugStyle {
+    /// GitHub's: lowercased, with punctuation removed and spaces replaced
+    /// by hyphens. `Configuring Output!` becomes `configuring-output`.
+    Github,
+
+    /// Jekyll's default: lowercased, with each run of characters that
+    /// aren't letters or numbers replaced by one hyphen.
+    Jekyll,
+
+    /// A template, where `{slug}` is replaced by the GitHub-style slug and
+    /// `{level}` by the heading's level, e.g. `section-{slug}`.
+    Template(String),
+}
+
+/**
+ * How much each kind of match between a query word and a document's word is
+ * worth, as a fraction of an exact match of a single word. The defaults are
+ * the weights Stork has always used.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, SmartDefault)]
+#[serde(deny_unknown_fields, default)]
+pub struct MatchWeights {
+    /// The query word is the document's word.
+    #[default = 1.0]
+    pub exact: f64,
+
+    /// The query word is the start of the document's word. Each character
+    /// the query word is missing costs a little more.
+    #[default = 0.99]
+    pub prefix: f64,
+
+    /// The query word and the document's word have the same stem.
+    #[default = 0.5]
+    pub stem: f64,
+
+    /// The query word is in the middle of the document's word. Only used
+    /// by indexes built with `input.index_ngrams`.
+    #[default = 0.375]
+    pub substring: f64,
+}
+
+/**
+ * What a search that finds nothing tries before giving up. The stages are
+ * tried in order, and the first one that finds something is reported in the
+ * search output's `fallback`.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, SmartDefault)]
+#[serde(deny_unknown_fields, default)]
+pub struct ZeroResultFallback {
+    /// Retry with each word of the query that isn't in the index shortened
+    /// to the longest start of it that is, so `installs` can find `install`.
+    #[default = true]
+    pub prefix_relaxation: bool,
+
+    /// The most index lookups the fallback stages may make between them,
+    /// which bounds how much longer a search that finds nothing can take.
+    #[default = 64]
+    pub max_lookups: u16,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, SmartDefault)]
 #[serde(deny_unknown_fields, default)]
 #[allow(non_snake_case)]
@@ -12,15 +168,160 @@ pub struct OutputConfig {
     #[default = false]
     pub debug: bool,
 
+    /// Whether to remember, for each word, the `id` of the element it's in
+    /// or the closest element with one before it, so results and excerpts
+    /// can link to that section with a `page#id` URL. Off by default, so
+    /// result URLs don't change.
     #[default = false]
     pub save_nearest_html_id: bool,
 
+    /// When `save_nearest_html_id` is set, how the ids of Markdown
+    /// documents' headings are made. By default, the Markdown renderer's own
+    /// ids are used, which join the heading's words with underscores.
+    #[default(None)]
+    pub markdown_heading_slugs: Option<HeadingSlugStyle>,
+
+    /// Stores, alongside each stem, the words that were reduced to it, so
+    /// frontends can highlight every surface form of a stemmed match. Search
+    /// output then lists them for each query word, as `surface_forms`.
+    #[default = false]
+    pub save_stem_surface_forms: bool,
+
+    /// Stores each document's text, as it was extracted and without markup,
+    /// with its entry, so consumers of the index can cut their own excerpts
+    /// from it. This makes the index larger.
+    #[default = false]
+    pub store_document_text: bool,
+
+    /// When `store_document_text` is set, the most characters of each
+    /// document's text that are stored, cut at the end of a whole word.
+    /// Unlimited by default.
+    #[default(None)]
+    pub stored_text_max_chars: Option<usize>,
+
     #[default = 8]
     pub excerpt_buffer: u8,
 
     #[default = 5]
     pub excerpts_per_result: u8,
 
+    /// The most excerpts stored in the index for each word in each document.
+    /// Storing fewer keeps the index smaller, but leaves fewer places for
+    /// `excerpt_strategy` to choose from. Defaults to `excerpts_per_result`,
+    /// and is never less than 1.
+    #[default(None)]
+    pub max_stored_excerpts_per_result: Option<u8>,
+
+    #[default(ExcerptStrategy::Best)]
+    pub excerpt_strategy: ExcerptStrategy,
+
+    /// The most characters shown in each excerpt. Excerpts are trimmed to
+    /// fit, a whole word at a time, keeping the words the query matched.
+    /// Unlimited by default.
+    #[default(None)]
+    pub excerpt_max_chars: Option<u16>,
+
+    /// When set, each end of an excerpt may move by up to this many words,
+    /// widening or narrowing it, to land on a sentence boundary. An end with
+    /// no boundary that near is left where `excerpt_buffer` puts it.
+    #[default(None)]
+    pub excerpt_sentence_tolerance: Option<u8>,
+
+    /// Shown between the list items, definition terms and descriptions, and
+    /// table rows of an HTML document when one excerpt takes words from more
+    /// than one of them. It's only shown in excerpts, and isn't searchable.
+    #[default("· ".to_string())]
+    pub excerpt_item_separator: String,
+
+    /// What searches do with a `field:word` query whose field isn't
+    /// `title`, `body`, `url`, `keywords`, `summary`, or `code`.
+    #[default(UnknownQueryFields::Literal)]
+    pub unknown_query_fields: UnknownQueryFields,
+
+    /// Whether results have to match all of a query's words, or any of them.
+    pub query_operator: QueryOperator,
+
+    /// How much each document's matches are scaled by its length compared
+    /// to the average document's, from 0 to 1, like BM25's `b`. At 0, the
+    /// default, length doesn't matter. At 1, a document twice the average
+    /// length scores half as much for the same matches, and one half the
+    /// average length scores twice as much.
+    #[default = 0.0]
+    pub length_normalization: f64,
+
+    /// How much an excerpt's score grows for each query word beyond the
+    /// first that it matches within `proximity_window` words of the others,
+    /// as a fraction of an exact match's score. Excerpts, and so results,
+    /// where the query's words are close together rank higher. At 0, the
+    /// default, proximity doesn't matter.
+    #[default = 0.0]
+    pub proximity_boost: f64,
+
+    /// How many consecutive words the query's words have to fall within for
+    /// `proximity_boost` to count them. Words are only counted together when
+    /// they're also in the same excerpt.
+    #[default = 8]
+    pub proximity_window: u8,
+
+    /// How much a match in code indexed with `input.index_code_identifiers`
+    /// is worth, compared to the same match in the rest of the text. At 1,
+    /// the default, they're worth the same.
+    #[default = 1.0]
+    pub code_match_weight: f64,
+
+    /// How much each kind of match adds to a result's score, so ranking can
+    /// favor, say, stemmed matches over prefix matches.
+    pub match_weights: MatchWeights,
+
+    /// When set, a search that finds nothing is retried with a looser
+    /// query. Off by default.
+    #[default(None)]
+    pub zero_result_fallback: Option<ZeroResultFallback>,
+
+    /// When true, the index only records how many documents couldn't be
+    /// indexed, not which ones or why, so those details aren't published
+    /// along with it.
+    #[default = false]
+    pub strip_errors: bool,
+
+    /// When true, documents whose URLs only differ by their fragment or
+    /// query string, like the sections of one page, are merged into a single
+    /// search result. The result is the best-scoring document's, with the
+    /// best excerpts of all of them.
+    #[default = false]
+    pub group_by_url_without_fragment: bool,
+
+    /// The words per minute each result's reading time is estimated with,
+    /// from the number of words in its document. 0 doesn't estimate it.
+    #[default = 0]
+    pub reading_time_wpm: u16,
+
     #[default = 10]
     pub displayed_results_count: u8,
+
+    /// How results with the same score are ordered.
+    pub tie_breaker: TieBreaker,
+
+    /// Queries with fewer characters than this return no results.
+    #[default = 1]
+    pub min_query_length: u8,
+
+    #[default(IndexFormat::Single)]
+    pub format: IndexFormat,
+
+    /// The number of shard files written when `format` is `sharded`.
+    #[default = 8]
+    pub shard_count: usize,
+
+    /// The largest the index file may be, in bytes. Larger indexes are
+    /// pruned, dropping their least useful search terms and excerpts, until
+    /// they fit.
+    #[default(None)]
+    pub max_index_bytes: Option<usize>,
+
+    /// When true, the built index is read back and searched for its most
+    /// frequent terms and its documents' titles, and the build fails if the
+    /// index can't be read or a title search doesn't find its document.
+    #[default = false]
+    pub self_test: bool,
 }
@@ -10,6 +10,11 @@ use toml::Value;
 pub enum StemmingConfig {
     None,
     Language(Algorithm),
+
+    /// A language that Stork's stemmer doesn't support, as it was written in
+    /// the config. What the build does with it depends on
+    /// `input.unsupported_stemming_language`.
+    Unsupported(String),
 }
 
 impl Default for StemmingConfig {
@@ -43,9 +48,7 @@ impl<'de> serde::Deserialize<'de> for StemmingConfig {
         use serde::de::Error;
 
         if let Ok(Value::String(string)) = Deserialize::deserialize(deserializer) {
-            StemmingConfig::try_from(&string).map_err(|_e| {
-                serde::de::Error::custom(format!("Unexpected value `{}`, expected `none` or a language supported by https://snowballstem.org/, e.g. `Dutch`", string.clone()))
-            })
+            Ok(StemmingConfig::try_from(&string).unwrap_or(StemmingConfig::Unsupported(string)))
         } else {
             Err(Error::custom(
             "Unexpected stemming config value; could not parse as string. (Maybe you need quotes?)",
@@ -60,6 +63,7 @@ impl From<StemmingConfig> for String {
         let _result = match stemming_config {
             StemmingConfig::Language(l) => write!(&mut output, "{:?}", l),
             StemmingConfig::None => write!(&mut output, "none"),
+            StemmingConfig::Unsupported(language) => write!(&mut output, "{}", language),
         };
         output
     }
@@ -98,6 +102,21 @@ mod tests {
         assert!(StemmingConfig::try_from(&"Blorp".to_string()).is_err())
     }
 
+    #[test]
+    fn unsupported_language_is_kept_for_the_build_to_handle() {
+        #[derive(Deserialize)]
+        struct Input {
+            stemming: StemmingConfig,
+        }
+
+        let input: Input = toml::from_str(r#"stemming = "Klingon""#).unwrap();
+        assert_eq!(
+            input.stemming,
+            StemmingConfig::Unsupported("Klingon".to_string())
+        );
+        assert_eq!(String::from(input.stemming), "Klingon".to_string());
+    }
+
     #[test]
     fn test_dutch_tostring() {
         assert_eq!(
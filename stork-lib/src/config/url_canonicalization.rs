@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+
+/**
+ * How documents' URLs are rewritten before they're compared to find
+ * documents with the same URL, like `{ strip_query_parameters = ["utm_*",
+ * "ref"] }`. The rewritten URLs are only compared; documents keep the URLs
+ * they were given. Each rule can be turned off on its own.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, SmartDefault, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct UrlCanonicalizationConfig {
+    /// `HTTPS://Example.COM/Docs` and `https://example.com/Docs` are the
+    /// same URL. Paths are compared as they're written.
+    #[default = true]
+    pub lowercase_host: bool,
+
+    /// `https://example.com:443/` and `https://example.com/` are the same
+    /// URL, as are `http://` URLs with and without port 80.
+    #[default = true]
+    pub strip_default_port: bool,
+
+    /// `/docs/intro/index.html` and `/docs/intro/` are the same URL.
+    #[default = true]
+    pub collapse_index_html: bool,
+
+    /// `/docs/intro/` and `/docs/intro` are the same URL.
+    #[default = true]
+    pub strip_trailing_slash: bool,
+
+    /// Query parameters that are left out, like tracking parameters. A name
+    /// ending in `*` leaves out every parameter that starts with the rest of
+    /// it.
+    #[default(vec!["utm_*".to_string()])]
+    pub strip_query_parameters: Vec<String>,
+}
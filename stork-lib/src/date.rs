@@ -0,0 +1,138 @@
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/**
+ * A calendar date, like a document's publication date. Dates are written in
+ * ISO 8601 form, `YYYY-MM-DD`; a time after the date, like
+ * `2023-01-01T12:00:00Z`, is accepted and ignored.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("`{0}` isn't a date of the form YYYY-MM-DD, like `2023-01-31`.")]
+pub struct DateParseError(pub String);
+
+fn is_leap_year(year: u16) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl FromStr for Date {
+    type Err = DateParseError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let error = || DateParseError(value.to_string());
+        let trimmed = value.trim();
+
+        // Anything after the date has to be a time.
+        let date = match (trimmed.get(..10), trimmed.get(10..)) {
+            (Some(date), Some(time))
+                if time.is_empty() || time.starts_with(|c| c == 'T' || c == 't' || c == ' ') =>
+            {
+                date
+            }
+            _ => return Err(error()),
+        };
+
+        let parts: Vec<&str> = date.split('-').collect();
+        let (year, month, day) = match parts.as_slice() {
+            [year, month, day]
+                if year.len() == 4
+                    && month.len() == 2
+                    && day.len() == 2
+                    && parts
+                        .iter()
+                        .all(|part| part.bytes().all(|b| b.is_ascii_digit())) =>
+            {
+                (year, month, day)
+            }
+            _ => return Err(error()),
+        };
+
+        let year: u16 = year.parse().map_err(|_| error())?;
+        let month: u8 = month.parse().map_err(|_| error())?;
+        let day: u8 = day.parse().map_err(|_| error())?;
+
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return Err(error());
+        }
+
+        Ok(Date { year, month, day })
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn iso_dates_parse_and_display() {
+        let date: Date = "2023-01-31".parse().unwrap();
+        assert_eq!(
+            date,
+            Date {
+                year: 2023,
+                month: 1,
+                day: 31
+            }
+        );
+        assert_eq!(date.to_string(), "2023-01-31");
+        assert_eq!("2023-01-31T12:00:00Z".parse::<Date>(), Ok(date));
+        assert_eq!(" 2023-01-31 08:15 ".parse::<Date>(), Ok(date));
+        assert_eq!("2024-02-29".parse::<Date>().unwrap().day, 29);
+    }
+
+    #[test]
+    fn invalid_dates_are_rejected() {
+        for value in [
+            "",
+            "2023",
+            "2023-1-31",
+            "2023-01-31x",
+            "2023-13-01",
+            "2023-02-29",
+            "1900-02-29",
+            "2023-04-31",
+            "2023-00-10",
+            "+023-01-01",
+            "yesterday",
+        ] {
+            assert_eq!(
+                value.parse::<Date>(),
+                Err(DateParseError(value.to_string())),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn dates_are_ordered_chronologically() {
+        let date = |value: &str| value.parse::<Date>().unwrap();
+        assert!(date("2022-12-31") < date("2023-01-01"));
+        assert!(date("2023-01-09") < date("2023-01-10"));
+        assert!(date("2023-02-01") > date("2023-01-31"));
+    }
+}
@@ -1,7 +1,7 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
 
-use crate::{stopwords, Excerpt, HighlightRange, Output, Result};
+use crate::{stopwords, Excerpt, ExcerptField, HighlightRange, Output, Result};
 
 use super::{
     scores::STOPWORD_SCORE, AliasTarget, Container, Entry, EntryIndex, Index, Score, SearchResult,
@@ -92,6 +92,10 @@ impl From<crate::index_v2::Entry> for crate::Entry {
             url: entry.url.clone(),
             title: entry.title.clone(),
             fields: entry.fields.unwrap_or_default(),
+            thumbnail_url: None,
+            word_count: None,
+            reading_time_minutes: None,
+            text: None,
         }
     }
 }
@@ -187,6 +191,12 @@ impl From<EntryAndIntermediateExcerpts> for Result {
                     score,
                     internal_annotations: Vec::default(),
                     fields: HashMap::default(),
+                    trimmed_start: false,
+                    trimmed_end: false,
+                    sentence_start: false,
+                    sentence_end: false,
+                    url: None,
+                    field: ExcerptField::Body,
                 }
             })
             .collect();
@@ -200,6 +210,8 @@ impl From<EntryAndIntermediateExcerpts> for Result {
             entry: crate::Entry::from(entry),
             excerpts,
             title_highlight_ranges: vec![],
+            merged_entry_count: None,
+            url: None,
             score,
         }
     }
@@ -254,6 +266,9 @@ pub fn search(index: &Index, query: &str) -> Output {
         results: output_results,
         total_hit_count: *total_len,
         url_prefix: String::default(),
+        facet_counts: Default::default(),
+        fallback: None,
+        surface_forms: Default::default(),
     }
 }
 
@@ -0,0 +1,397 @@
+use std::collections::BTreeMap;
+
+use fst::Streamer;
+
+use crate::{Fields, IndexMetadata, StoredDocumentError};
+
+use super::{Container, Entry, EntryIndex, Index, Score};
+
+/**
+ * A read-only view of a single document stored in an [`Index`].
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct EntryRef<'a> {
+    entry: &'a Entry,
+}
+
+impl<'a> EntryRef<'a> {
+    pub fn title(&self) -> &'a str {
+        &self.entry.title
+    }
+
+    pub fn url(&self) -> &'a str {
+        &self.entry.url
+    }
+
+    pub fn fields(&self) -> &'a Fields {
+        &self.entry.fields
+    }
+
+    /// The number of words the document had when it was indexed, whether or
+    /// not its text was stored. `None` for indexes built before it was
+    /// recorded.
+    pub fn word_count(&self) -> Option<usize> {
+        self.entry.word_count
+    }
+
+    /// The document's text, when the index was built with
+    /// `output.store_document_text`.
+    pub fn text(&self) -> Option<&'a str> {
+        self.entry.text.as_deref()
+    }
+}
+
+/**
+ * Summary information about a single search term in an [`Index`].
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TermStats {
+    /// The number of documents that contain this exact term.
+    pub entry_count: usize,
+
+    /// The number of times this exact term appears across all documents.
+    pub occurrence_count: usize,
+
+    /// The number of other terms (prefixes, stems) this term resolves to.
+    pub alias_count: usize,
+}
+
+impl From<&Container> for TermStats {
+    fn from(container: &Container) -> Self {
+        TermStats {
+            entry_count: container.results.len(),
+            occurrence_count: container
+                .results
+                .values()
+                .map(|result| result.excerpts.len())
+                .sum(),
+            alias_count: container.aliases.len(),
+        }
+    }
+}
+
+impl Index {
+    /**
+     * Iterates over the documents in the index, in the order they were
+     * listed in the configuration.
+     *
+     * ```
+     * # use std::convert::TryFrom;
+     * # let bytes = std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap();
+     * let index = stork_lib::Index::try_from(bytes.as_slice()).unwrap();
+     * let entry = index.entries().next().unwrap();
+     * assert_eq!(entry.title(), "Introduction");
+     * // This index was built before word counts were stored.
+     * assert_eq!(entry.word_count(), None);
+     * ```
+     */
+    pub fn entries(&self) -> impl Iterator<Item = EntryRef<'_>> {
+        self.entries.iter().map(|entry| EntryRef { entry })
+    }
+
+    /**
+     * The number of distinct search terms in the index.
+     *
+     * ```
+     * # use std::convert::TryFrom;
+     * # let bytes = std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap();
+     * let index = stork_lib::Index::try_from(bytes.as_slice()).unwrap();
+     * assert_eq!(index.term_count(), index.terms().count());
+     * ```
+     */
+    pub fn term_count(&self) -> usize {
+        self.containers.len()
+    }
+
+    /**
+     * The number of bytes that the n-grams indexed for substring search,
+     * with `input.index_ngrams`, add to the serialized index.
+     *
+     * ```
+     * # use std::convert::TryFrom;
+     * # let bytes = std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap();
+     * let index = stork_lib::Index::try_from(bytes.as_slice()).unwrap();
+     * assert_eq!(index.ngram_size_bytes(), 0);
+     * ```
+     */
+    pub fn ngram_size_bytes(&self) -> usize {
+        if self.ngrams.is_empty() {
+            0
+        } else {
+            rmp_serde::to_vec(&self.ngrams).unwrap().len()
+        }
+    }
+
+    /**
+     * Iterates over every search term in the index, in lexicographic order,
+     * alongside summary statistics for that term.
+     *
+     * ```
+     * # use std::convert::TryFrom;
+     * # let bytes = std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap();
+     * let index = stork_lib::Index::try_from(bytes.as_slice()).unwrap();
+     * let (_, stats) = index.terms().find(|(term, _)| *term == "liberty").unwrap();
+     * assert_eq!(stats.entry_count, 1);
+     * ```
+     */
+    pub fn terms(&self) -> impl Iterator<Item = (&str, TermStats)> {
+        self.containers
+            .iter()
+            .map(|(term, container)| (term.as_str(), TermStats::from(container)))
+    }
+
+    /**
+     * Iterates over every key the index can be searched by, in
+     * lexicographic order: each word as it was indexed (lowercased, with
+     * surrounding punctuation removed), each word's stem, and the starts of
+     * words that prefix searches match. These are the same keys as
+     * [`Index::terms`], without their statistics.
+     *
+     * ```
+     * # use std::convert::TryFrom;
+     * # let bytes = std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap();
+     * let index = stork_lib::Index::try_from(bytes.as_slice()).unwrap();
+     * assert!(index.vocabulary().any(|word| word == "liberty"));
+     * assert!(index.vocabulary().any(|word| word == "libert"));
+     * ```
+     */
+    pub fn vocabulary(&self) -> impl Iterator<Item = &str> {
+        self.containers.iter().map(|(word, _)| word.as_str())
+    }
+
+    /**
+     * Iterates over the search terms that start with `prefix`, in
+     * lexicographic order. The terms are also stored as a finite state
+     * transducer, which finds the matching ones without visiting the rest,
+     * so this takes about as long however many terms the index has.
+     *
+     * ```
+     * # use std::convert::TryFrom;
+     * # let bytes = std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap();
+     * let index = stork_lib::Index::try_from(bytes.as_slice()).unwrap();
+     * assert!(index.terms_with_prefix("libert").any(|(term, _)| term == "liberty"));
+     * ```
+     */
+    pub fn terms_with_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a str, TermStats)> + 'a {
+        let mut terms = vec![];
+        let mut stream = self.terms.with_prefix(prefix);
+        while let Some(term) = stream.next() {
+            let container = std::str::from_utf8(term)
+                .ok()
+                .and_then(|term| self.containers.get_key_value(term));
+            if let Some((term, container)) = container {
+                terms.push((term.as_str(), TermStats::from(container)));
+            }
+        }
+        terms.into_iter()
+    }
+
+    /**
+     * The documents that a search for `word` matches, with the score each
+     * one is matched with, in the order they were listed in the
+     * configuration. This includes documents reached through the word's
+     * prefixes and stems, like a search does, but skips excerpt building
+     * and ranking. A document matched more than one way keeps its highest
+     * score. A word that isn't indexed matches no documents.
+     *
+     * ```
+     * # use std::convert::TryFrom;
+     * # let bytes = std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap();
+     * let index = stork_lib::Index::try_from(bytes.as_slice()).unwrap();
+     * let entries = index.entries_for_word("Liberty");
+     * assert_eq!(entries.len(), 1);
+     * assert!(index.entries_for_word("xylophone").is_empty());
+     * ```
+     */
+    pub fn entries_for_word(&self, word: &str) -> Vec<(EntryRef<'_>, u8)> {
+        let container = match self.containers.get(&word.to_lowercase()) {
+            Some(container) => container,
+            None => return vec![],
+        };
+
+        let mut scores: BTreeMap<EntryIndex, Score> = container
+            .results
+            .iter()
+            .map(|(&entry_index, result)| (entry_index, result.score))
+            .collect();
+
+        for (alias_target, &alias_score) in &container.aliases {
+            if let Some(target_container) = self.containers.get(alias_target) {
+                for &entry_index in target_container.results.keys() {
+                    let score = scores.entry(entry_index).or_insert(alias_score);
+                    *score = (*score).max(alias_score);
+                }
+            }
+        }
+
+        scores
+            .into_iter()
+            .filter_map(|(entry_index, score)| {
+                let entry = self.entries.get(entry_index)?;
+                Some((EntryRef { entry }, score))
+            })
+            .collect()
+    }
+
+    /**
+     * Metadata describing the index format.
+     *
+     * ```
+     * # use std::convert::TryFrom;
+     * # let bytes = std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap();
+     * let index = stork_lib::Index::try_from(bytes.as_slice()).unwrap();
+     * assert_eq!(index.metadata().index_version, "stork-3");
+     * ```
+     */
+    pub fn metadata(&self) -> IndexMetadata {
+        IndexMetadata {
+            index_version: "stork-3".to_string(),
+            document_error_count: self.document_error_count,
+            stemming: self.config.stemming.clone().map(String::from),
+            stemming_varies: self.config.stemming_varies,
+        }
+    }
+
+    /**
+     * The documents that couldn't be indexed when the index was built. This
+     * is empty if the index was built with `output.strip_errors`, though
+     * [`Index::metadata`] still counts them.
+     */
+    pub fn document_errors(&self) -> &[StoredDocumentError] {
+        &self.document_errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "build-v3")]
+    use crate::{config::*, index_v3::build::build};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn term_stats_never_exceed_entry_count() {
+        let bytes = std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap();
+        let index = Index::try_from(bytes.as_slice()).unwrap();
+
+        assert_eq!(index.term_count(), 2477);
+        assert!(index
+            .terms()
+            .all(|(_, stats)| stats.entry_count <= index.entries().count()));
+    }
+
+    #[test]
+    fn entries_for_indexed_word_have_scores() {
+        let bytes = std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap();
+        let index = Index::try_from(bytes.as_slice()).unwrap();
+
+        let entries = index.entries_for_word("liberty");
+        let (_, stats) = index.terms().find(|(term, _)| *term == "liberty").unwrap();
+        assert_eq!(entries.len(), stats.entry_count);
+        assert!(entries.iter().all(|(_, score)| *score > 0));
+
+        // Prefixes reach the same documents through their aliases
+        let prefix_entries = index.entries_for_word("libert");
+        assert!(entries.iter().all(|(entry, _)| prefix_entries
+            .iter()
+            .any(|(prefix_entry, _)| prefix_entry.title() == entry.title())));
+    }
+
+    #[test]
+    fn entries_for_absent_word_are_empty() {
+        let bytes = std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap();
+        let index = Index::try_from(bytes.as_slice()).unwrap();
+
+        assert!(index.entries_for_word("xylophone").is_empty());
+    }
+
+    #[test]
+    fn terms_with_prefix_match_a_full_scan() {
+        let bytes = std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap();
+        let index = Index::try_from(bytes.as_slice()).unwrap();
+
+        for prefix in ["", "lib", "government", "zzz"] {
+            let scanned: Vec<&str> = index
+                .terms()
+                .map(|(term, _)| term)
+                .filter(|term| term.starts_with(prefix))
+                .collect();
+            let ranged: Vec<&str> = index
+                .terms_with_prefix(prefix)
+                .map(|(term, _)| term)
+                .collect();
+
+            assert_eq!(ranged, scanned, "prefix {:?}", prefix);
+        }
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn vocabulary_has_each_indexed_key_once() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        "Running dogs, running cats.".to_string(),
+                    )),
+                    title: "Guide".to_string(),
+                    filetype: Some(Filetype::PlainText),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let index = build(&config).unwrap().index;
+
+        assert_eq!(
+            index.vocabulary().collect::<Vec<&str>>(),
+            vec![
+                "cat", "cats", "dog", "dogs", "gui", "guid", "guide", "run", "runn", "runni",
+                "runnin", "running"
+            ]
+        );
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn terms_with_prefix_follow_pruning_and_serialization() {
+        let file = |title: &str, contents: &str| File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        };
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    file("One", "Running dogs, running cats."),
+                    file("Two", "Running dogs."),
+                ],
+                min_document_frequency: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let built = build(&config).unwrap().index;
+        let read = Index::try_from(bytes::Bytes::from(&built).as_ref()).unwrap();
+
+        for index in [&built, &read] {
+            let with_prefix: Vec<&str> = index
+                .terms_with_prefix("ca")
+                .map(|(term, _)| term)
+                .collect();
+            let scanned: Vec<&str> = index
+                .vocabulary()
+                .filter(|term| term.starts_with("ca"))
+                .collect();
+            assert_eq!(with_prefix, scanned);
+        }
+        assert!(!built.containers.contains_key("cats"));
+        assert_eq!(built.terms.len(), built.containers.len());
+        assert_eq!(read.terms.len(), built.containers.len());
+    }
+}
@@ -0,0 +1,107 @@
+/**
+ * The word without its periods, if it's an acronym written with one after
+ * each letter, like `U.S.A.` or `e.g.`, which becomes `USA` or `eg`. The
+ * word has to have at least two letters, each on its own between periods,
+ * so words that end a sentence, numbers like `3.5`, and abbreviations like
+ * `etc.` are left alone. Other punctuation around the word is ignored.
+ */
+pub(super) fn collapse_acronym_periods(word: &str) -> Option<String> {
+    let word = word.trim_matches(|c: char| c.is_ascii_punctuation() && c != '.');
+    let letters = word.strip_suffix('.').unwrap_or(word);
+
+    let mut collapsed = String::with_capacity(letters.len());
+    for part in letters.split('.') {
+        let mut chars = part.chars();
+        match (chars.next(), chars.next()) {
+            (Some(letter), None) if letter.is_alphabetic() => collapsed.push(letter),
+            _ => return None,
+        }
+    }
+
+    if collapsed.chars().count() < 2 {
+        return None;
+    }
+    Some(collapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "build-v3")]
+    use crate::{config::*, index_v3::build::build, V3Index as Index};
+
+    #[test]
+    fn acronyms_lose_their_periods() {
+        assert_eq!(collapse_acronym_periods("U.S.A."), Some("USA".to_string()));
+        assert_eq!(collapse_acronym_periods("u.s.a"), Some("usa".to_string()));
+        assert_eq!(collapse_acronym_periods("(e.g.,"), Some("eg".to_string()));
+        assert_eq!(collapse_acronym_periods("i.e."), Some("ie".to_string()));
+    }
+
+    #[test]
+    fn other_words_are_left_alone() {
+        for word in [
+            "end.", "A.", "etc.", "3.5", "a.b.c..", "U..S", ".net", "v1.2",
+        ] {
+            assert_eq!(collapse_acronym_periods(word), None, "{}", word);
+        }
+    }
+
+    #[cfg(feature = "build-v3")]
+    fn round_trip(index: &Index) -> Index {
+        let bytes = bytes::Bytes::from(index);
+        Index::try_from(bytes.as_ref()).unwrap()
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn acronym_periods_can_be_collapsed() {
+        let config = |acronym_periods| Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        "We toured the U.S.A. by train. Next we flew home, e.g. from Boston."
+                            .to_string(),
+                    )),
+                    title: "Travel".to_string(),
+                    filetype: Some(Filetype::PlainText),
+                    ..Default::default()
+                }],
+                acronym_periods,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let kept = build(&config(AcronymPeriodHandling::Keep)).unwrap().index;
+        assert!(kept.containers.contains_key("u.s.a"));
+        assert!(!kept.containers.contains_key("usa"));
+
+        let index = round_trip(
+            &build(&config(AcronymPeriodHandling::Collapse))
+                .unwrap()
+                .index,
+        );
+        assert!(index.containers.contains_key("usa"));
+        assert!(index.containers.contains_key("eg"));
+        assert!(!index.containers.contains_key("u.s.a"));
+
+        // Sentences end as they always have.
+        assert!(index.containers.contains_key("train"));
+        assert!(index.containers.contains_key("next"));
+
+        for query in ["usa", "U.S.A.", "u.s.a", "e.g."] {
+            let output = crate::index_v3::search(&index, query).unwrap();
+            assert_eq!(output.results.len(), 1, "{}", query);
+            assert!(
+                !output.results[0].excerpts[0].highlight_ranges.is_empty(),
+                "{}",
+                query
+            );
+        }
+        assert_eq!(
+            index.entries[0].contents,
+            "We toured the U.S.A. by train. Next we flew home, e.g. from Boston."
+        );
+    }
+}
@@ -1,10 +1,286 @@
-use crate::{index_v3::AnnotatedWord, InternalWordAnnotation};
+use std::{borrow::Cow, collections::HashSet, str::Utf8Error, sync::Arc};
+
+use crate::{
+    config::{InputConfig, LinkHandling, SpacedLetterHandling, WordSegmentation},
+    index_v3::AnnotatedWord,
+    InternalWordAnnotation, Tokenizer,
+};
+
+use super::segmentation::{segment_words, split_emoji_words};
+
+/**
+ * How the email addresses and URLs in a document's text are indexed.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct LinkHandlers {
+    pub(super) emails: LinkHandling,
+    pub(super) urls: LinkHandling,
+}
+
+impl From<&InputConfig> for LinkHandlers {
+    fn from(config: &InputConfig) -> Self {
+        LinkHandlers {
+            emails: config.email_handling,
+            urls: config.url_handling,
+        }
+    }
+}
+
+impl LinkHandlers {
+    /**
+     * Applies the configured handling to a whitespace-separated token.
+     * Returns `None` if the token is dropped, or the text to index in its
+     * place: the token itself, or its domain.
+     */
+    pub(super) fn apply<'a>(&self, token: &'a str) -> Option<&'a str> {
+        let link = token.trim_matches(|c: char| c.is_ascii_punctuation());
+
+        let (handling, domain) = if let Some(domain) = url_domain(link) {
+            (self.urls, domain)
+        } else if let Some(domain) = email_domain(link) {
+            (self.emails, domain)
+        } else {
+            return Some(token);
+        };
+
+        match handling {
+            LinkHandling::Keep => Some(token),
+            LinkHandling::Drop => None,
+            LinkHandling::Domain => Some(domain),
+        }
+    }
+
+    fn keeps_everything(&self) -> bool {
+        self.emails == LinkHandling::Keep && self.urls == LinkHandling::Keep
+    }
+}
+
+/// The host of a token like `https://www.example.com/path`, without `www.`
+fn url_domain(token: &str) -> Option<&str> {
+    let rest = ["http://", "https://", "www."]
+        .iter()
+        .find(|prefix| starts_with_ignore_ascii_case(token, prefix))
+        .map(|prefix| match *prefix {
+            "www." => token,
+            scheme => &token[scheme.len()..],
+        })?;
+
+    let host = rest
+        .split(|c| matches!(c, '/' | '?' | '#' | ':'))
+        .next()
+        .unwrap_or_default();
+    let host = host.rsplit('@').next().unwrap_or_default();
+    let host = if starts_with_ignore_ascii_case(host, "www.") {
+        &host[4..]
+    } else {
+        host
+    };
+
+    if host.contains('.') {
+        Some(host)
+    } else {
+        None
+    }
+}
+
+/// The domain of a token like `someone@example.com` or `mailto:someone@example.com`
+fn email_domain(token: &str) -> Option<&str> {
+    let (local, domain) = token.split_once('@')?;
+    let local = if starts_with_ignore_ascii_case(local, "mailto:") {
+        &local[7..]
+    } else {
+        local
+    };
+
+    let is_domain = domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && domain
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '.' || c == '-');
+
+    if !local.is_empty() && is_domain {
+        Some(domain)
+    } else {
+        None
+    }
+}
+
+fn starts_with_ignore_ascii_case(string: &str, prefix: &str) -> bool {
+    string
+        .get(..prefix.len())
+        .map_or(false, |start| start.eq_ignore_ascii_case(prefix))
+}
+
+/**
+ * Merges each run of two or more consecutive single-letter words, like the
+ * spaced-out acronym `a w s`, into one word (`aws`), keeping the letters
+ * themselves too if `handling` says to. Each word is paired with its index in
+ * the document's word list, and a merged word takes its first letter's
+ * index, so excerpts still point at the text as it was written.
+ */
+pub(super) fn merge_spaced_letters(
+    words: Vec<(usize, String)>,
+    handling: SpacedLetterHandling,
+) -> Vec<(usize, String)> {
+    if handling == SpacedLetterHandling::Separate {
+        return words;
+    }
+
+    let mut merged = Vec::with_capacity(words.len());
+    let mut run: Vec<(usize, String)> = vec![];
+
+    for (word_index, word) in words {
+        if is_single_letter(&word) {
+            run.push((word_index, word));
+            continue;
+        }
+
+        push_spaced_letter_run(&mut run, handling, &mut merged);
+        merged.push((word_index, word));
+    }
+
+    push_spaced_letter_run(&mut run, handling, &mut merged);
+    merged
+}
+
+fn push_spaced_letter_run(
+    run: &mut Vec<(usize, String)>,
+    handling: SpacedLetterHandling,
+    merged: &mut Vec<(usize, String)>,
+) {
+    if run.len() > 1 {
+        let word: String = run.iter().map(|(_, letter)| letter.as_str()).collect();
+        merged.push((run[0].0, word));
+
+        if handling == SpacedLetterHandling::Merge {
+            run.clear();
+        }
+    }
+
+    merged.append(run);
+}
+
+fn is_single_letter(word: &str) -> bool {
+    let mut chars = word.chars();
+    matches!((chars.next(), chars.next()), (Some(c), None) if c.is_alphabetic())
+}
+
+/**
+ * Cuts each word longer than `maximum_length` characters down to that many,
+ * so that one enormous token, like a minified script, can't make filling
+ * its containers blow up. Returns how many words were cut.
+ */
+pub(super) fn truncate_long_words(word_list: &mut [AnnotatedWord], maximum_length: usize) -> usize {
+    let mut truncated_count = 0;
+    for annotated_word in word_list {
+        if let Some((end, _)) = annotated_word.word.char_indices().nth(maximum_length) {
+            annotated_word.word = Arc::from(&annotated_word.word[..end]);
+            truncated_count += 1;
+        }
+    }
+    truncated_count
+}
+
+/**
+ * Rejoins each word that's split across two lines with a hyphen, like
+ * `inter-` and `national`, by removing the hyphen, the line break, and the
+ * spaces around it. The hyphen has to end its line, follow a letter, and be
+ * followed by a lowercase letter on the next line, so hyphens inside a line,
+ * dashes, and lists aren't touched.
+ */
+pub(super) fn join_hyphenated_line_breaks(text: &str) -> Cow<'_, str> {
+    let is_horizontal_space = |c: char| c == ' ' || c == '\t';
+
+    let mut joined = String::new();
+    let mut rest = text;
+    while let Some(hyphen) = rest.find('-') {
+        let (before, after) = (&rest[..hyphen], &rest[hyphen + 1..]);
+
+        let line_end = after.trim_start_matches(is_horizontal_space);
+        let after_line_break = line_end
+            .strip_prefix("\r\n")
+            .or_else(|| line_end.strip_prefix('\n'))
+            .map(|next_line| next_line.trim_start_matches(is_horizontal_space));
+
+        let follows_letter = before
+            .chars()
+            .next_back()
+            .map_or(false, char::is_alphabetic);
+        match after_line_break {
+            Some(next_line)
+                if follows_letter && next_line.chars().next().map_or(false, char::is_lowercase) =>
+            {
+                joined.push_str(before);
+                rest = next_line;
+            }
+            _ => {
+                joined.push_str(&rest[..=hyphen]);
+                rest = after;
+            }
+        }
+    }
+
+    if joined.len() + rest.len() == text.len() {
+        return Cow::Borrowed(text);
+    }
+
+    joined.push_str(rest);
+    Cow::Owned(joined)
+}
+
+/**
+ * Stork's own tokenizer, used unless `input.tokenizer` is set. It splits text
+ * at ASCII whitespace and hyphens, handles email addresses and URLs as
+ * `input.email_handling` and `input.url_handling` say to, splits Thai and
+ * Lao text into words if `input.word_segmentation` says to, and splits emoji
+ * from the words they're in if `input.index_emoji` does.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultTokenizer {
+    links: LinkHandlers,
+    segmentation: WordSegmentation,
+    index_emoji: bool,
+}
+
+impl From<&InputConfig> for DefaultTokenizer {
+    fn from(config: &InputConfig) -> Self {
+        DefaultTokenizer {
+            links: LinkHandlers::from(config),
+            segmentation: config.word_segmentation,
+            index_emoji: config.index_emoji,
+        }
+    }
+}
+
+impl Tokenizer for DefaultTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<AnnotatedWord> {
+        let words = text.make_annotated_words_with_links(self.links, |_, _| {});
+        split_emoji_words(segment_words(words, self.segmentation), self.index_emoji)
+    }
+}
+
+/// The tokenizer for documents built with `config`: its `tokenizer`, or else
+/// the default one.
+pub(super) fn configured_tokenizer(config: &InputConfig) -> Box<dyn Tokenizer> {
+    match &config.tokenizer {
+        Some(tokenizer) => Box::new(tokenizer.clone()),
+        None => Box::new(DefaultTokenizer::from(config)),
+    }
+}
 
 pub(super) trait AnnotatedWordable {
     fn make_annotated_words(&self) -> Vec<AnnotatedWord>;
     fn make_annotated_words_with_annotations<F>(&self, closure: F) -> Vec<AnnotatedWord>
     where
         F: Fn(&str, &mut Vec<InternalWordAnnotation>);
+    fn make_annotated_words_with_links<F>(
+        &self,
+        links: LinkHandlers,
+        closure: F,
+    ) -> Vec<AnnotatedWord>
+    where
+        F: Fn(&str, &mut Vec<InternalWordAnnotation>);
 }
 
 impl AnnotatedWordable for str {
@@ -16,14 +292,28 @@ impl AnnotatedWordable for str {
         &self,
         closure: F,
     ) -> Vec<AnnotatedWord> {
-        self.split(|c: char| c.is_ascii_whitespace() || c == '-')
-            .map(str::trim)
-            .filter(|s| !s.is_empty())
+        self.make_annotated_words_with_links(LinkHandlers::default(), closure)
+    }
+
+    /**
+     * Splits the string into words, handling email addresses and URLs as
+     * configured. A link that's dropped or reduced to its domain is handled
+     * whole, before the string is split on hyphens, so it can't leave any
+     * fragments behind.
+     */
+    fn make_annotated_words_with_links<F: Fn(&str, &mut Vec<InternalWordAnnotation>)>(
+        &self,
+        links: LinkHandlers,
+        closure: F,
+    ) -> Vec<AnnotatedWord> {
+        let mut words = WordTable::for_text(self);
+        self.split(|c: char| c.is_ascii_whitespace())
+            .flat_map(|token| split_token(links, token))
             .map(|w| {
                 let mut internal_annotations: Vec<InternalWordAnnotation> = Vec::new();
                 closure(w, &mut internal_annotations);
                 AnnotatedWord {
-                    word: w.to_string(),
+                    word: words.intern(w),
                     internal_annotations,
                     ..AnnotatedWord::default()
                 }
@@ -32,12 +322,134 @@ impl AnnotatedWordable for str {
     }
 }
 
+/// The words in a whitespace-separated token: its domain, if it's a link
+/// that's reduced to one, or else its hyphen-separated parts.
+fn split_token(links: LinkHandlers, token: &str) -> impl Iterator<Item = &str> {
+    let kept = if links.keeps_everything() {
+        Some(token)
+    } else {
+        links.apply(token)
+    };
+
+    let (domain, parts) = match kept {
+        None => (None, None),
+        Some(domain) if domain != token => (Some(domain), None),
+        Some(token) => (None, Some(token.split('-').map(str::trim))),
+    };
+    domain
+        .into_iter()
+        .chain(parts.into_iter().flatten())
+        .filter(|s| !s.is_empty())
+}
+
+/**
+ * The words seen while tokenizing a piece of text. Words repeat constantly,
+ * so each distinct word is allocated once, and every later occurrence
+ * shares that allocation.
+ */
+#[derive(Default)]
+pub(super) struct WordTable(HashSet<Arc<str>>);
+
+impl WordTable {
+    /// A table with room for the distinct words of `text`, which run to
+    /// about one per 16 bytes of prose, so it rarely has to grow.
+    fn for_text(text: &str) -> Self {
+        WordTable(HashSet::with_capacity(text.len() / 16))
+    }
+
+    pub(super) fn intern(&mut self, word: &str) -> Arc<str> {
+        if let Some(interned) = self.0.get(word) {
+            return Arc::clone(interned);
+        }
+
+        let interned: Arc<str> = Arc::from(word);
+        self.0.insert(Arc::clone(&interned));
+        interned
+    }
+}
+
+/**
+ * Splits text into words as it arrives in chunks, so a document never has to
+ * be held in memory all at once. Produces the same words as its tokenizer
+ * would for the whole text, as long as the tokenizer never joins words
+ * across whitespace.
+ *
+ * Words are split at ASCII whitespace, so everything up to a chunk's last
+ * whitespace byte can be split right away. Only the rest, which might be the
+ * start of a word that continues in the next chunk, is kept back. UTF-8
+ * sequences never contain ASCII bytes, so a multi-byte character split
+ * across chunks is kept back along with its word.
+ */
+pub(super) struct StreamingTokenizer {
+    tokenizer: Box<dyn Tokenizer>,
+    pending: Vec<u8>,
+    words: Vec<AnnotatedWord>,
+}
+
+impl StreamingTokenizer {
+    pub(super) fn new(tokenizer: Box<dyn Tokenizer>) -> Self {
+        StreamingTokenizer {
+            tokenizer,
+            pending: vec![],
+            words: vec![],
+        }
+    }
+
+    pub(super) fn feed(&mut self, chunk: &[u8]) -> Result<(), Utf8Error> {
+        // What's pending has no whitespace in it, so only the new chunk has
+        // to be searched, even if one enormous word spans many chunks.
+        let searched_from = self.pending.len();
+        self.pending.extend_from_slice(chunk);
+
+        let boundary = match chunk.iter().rposition(u8::is_ascii_whitespace) {
+            Some(position) => searched_from + position + 1,
+            None => return Ok(()),
+        };
+
+        let complete = std::str::from_utf8(&self.pending[..boundary])?;
+        self.words.extend(self.tokenizer.tokenize(complete));
+        self.pending.drain(..boundary);
+        Ok(())
+    }
+
+    /// The number of bytes fed in that haven't been split into words yet.
+    #[cfg(test)]
+    fn pending_byte_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub(super) fn finish(mut self) -> Result<Vec<AnnotatedWord>, Utf8Error> {
+        let rest = std::str::from_utf8(&self.pending)?;
+        self.words.extend(self.tokenizer.tokenize(rest));
+        Ok(self.words)
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
-    use crate::InternalWordAnnotation;
+    use crate::{
+        config::{
+            Config, DataSource, File, Filetype, HyphenatedLineBreakHandling, InputConfig,
+            LinkHandling, SpacedLetterHandling,
+        },
+        index_v3::build::build,
+        InternalWordAnnotation, V3Index as Index,
+    };
+
+    use super::{
+        join_hyphenated_line_breaks, merge_spaced_letters, truncate_long_words, AnnotatedWordable,
+        Arc, Cow, DefaultTokenizer, LinkHandlers, StreamingTokenizer,
+    };
 
-    use super::AnnotatedWordable;
+    const TRACKED_BODY: &str = "Read the release notes (https://www.example-news.com/2022/04/stork-1-5-released-with-new-features?utm_source=newsletter&utm_medium=email&utm_campaign=spring-launch-2022#section-3) or email press-team@example.org for details.";
+
+    fn words_with_links(text: &str, emails: LinkHandling, urls: LinkHandling) -> Vec<String> {
+        text.make_annotated_words_with_links(LinkHandlers { emails, urls }, |_, _| {})
+            .into_iter()
+            .map(|annotated_word| annotated_word.word.to_string())
+            .collect()
+    }
 
     #[test]
     fn annotated_words_split_on_hyphens() {
@@ -62,6 +474,15 @@ mod tests {
         assert_eq!(expected, computed);
     }
 
+    #[test]
+    fn repeated_words_share_one_allocation() {
+        let words = "the cat and the hat-the end".make_annotated_words();
+        assert_eq!(words.len(), 7);
+        assert!(Arc::ptr_eq(&words[0].word, &words[3].word));
+        assert!(Arc::ptr_eq(&words[0].word, &words[5].word));
+        assert!(!Arc::ptr_eq(&words[0].word, &words[1].word));
+    }
+
     #[test]
     fn annotated_words_can_correctly_annotate() {
         let computed = "Hastings         on  \n \t hudson".make_annotated_words_with_annotations(
@@ -79,4 +500,342 @@ mod tests {
             computed[2].internal_annotations[0]
         );
     }
+
+    #[test]
+    fn kept_links_are_split_like_other_text() {
+        let words = words_with_links(TRACKED_BODY, LinkHandling::Keep, LinkHandling::Keep);
+        assert_eq!(words.len(), TRACKED_BODY.make_annotated_words().len());
+        assert!(words.len() > 20);
+    }
+
+    #[test]
+    fn dropped_links_leave_no_fragments() {
+        let words = words_with_links(TRACKED_BODY, LinkHandling::Drop, LinkHandling::Drop);
+        assert_eq!(
+            words,
+            vec!["Read", "the", "release", "notes", "or", "email", "for", "details."]
+        );
+    }
+
+    #[test]
+    fn links_can_be_reduced_to_their_domains() {
+        let words = words_with_links(TRACKED_BODY, LinkHandling::Domain, LinkHandling::Domain);
+        assert_eq!(
+            words,
+            vec![
+                "Read",
+                "the",
+                "release",
+                "notes",
+                "example-news.com",
+                "or",
+                "email",
+                "example.org",
+                "for",
+                "details."
+            ]
+        );
+    }
+
+    #[test]
+    fn email_and_url_handling_are_independent() {
+        let words = words_with_links(
+            "Write to mailto:someone@example.com or visit www.example.com/contact",
+            LinkHandling::Domain,
+            LinkHandling::Drop,
+        );
+        assert_eq!(words, vec!["Write", "to", "example.com", "or", "visit"]);
+    }
+
+    #[test]
+    fn words_with_at_signs_or_dots_are_not_links() {
+        let words = words_with_links(
+            "Meet @stork at 5p.m. in file.txt",
+            LinkHandling::Drop,
+            LinkHandling::Drop,
+        );
+        assert_eq!(
+            words,
+            vec!["Meet", "@stork", "at", "5p.m.", "in", "file.txt"]
+        );
+    }
+
+    fn merged_words(words: &[&str], handling: SpacedLetterHandling) -> Vec<(usize, String)> {
+        let words = words.iter().map(ToString::to_string).enumerate().collect();
+        merge_spaced_letters(words, handling)
+    }
+
+    #[test]
+    fn spaced_letters_are_merged_at_their_first_letter() {
+        let words = ["the", "a", "w", "s", "console", "and", "a", "guide"];
+
+        assert_eq!(
+            merged_words(&words, SpacedLetterHandling::Merge),
+            vec![
+                (0, "the".to_string()),
+                (1, "aws".to_string()),
+                (4, "console".to_string()),
+                (5, "and".to_string()),
+                (6, "a".to_string()),
+                (7, "guide".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn words_hyphenated_at_line_breaks_are_joined() {
+        assert_eq!(
+            join_hyphenated_line_breaks(
+                "inter-\nnational trade, end- \r\n  ings, well-known re-\nEnter 1990-\n2000 a -\nb"
+            ),
+            "international trade, endings, well-known re-\nEnter 1990-\n2000 a -\nb"
+        );
+
+        let untouched = "well-known\nword-\n";
+        assert!(matches!(
+            join_hyphenated_line_breaks(untouched),
+            Cow::Borrowed(text) if text == untouched
+        ));
+    }
+
+    #[test]
+    fn merged_letters_can_be_kept() {
+        assert_eq!(
+            merged_words(&["c", "i", "a"], SpacedLetterHandling::MergeAndKeep),
+            vec![
+                (0, "cia".to_string()),
+                (0, "c".to_string()),
+                (1, "i".to_string()),
+                (2, "a".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            merged_words(&["c", "i", "a"], SpacedLetterHandling::Separate),
+            vec![
+                (0, "c".to_string()),
+                (1, "i".to_string()),
+                (2, "a".to_string()),
+            ]
+        );
+    }
+
+    fn streamed_words(text: &str, chunk_size: usize, links: LinkHandlers) -> Vec<String> {
+        let mut tokenizer = StreamingTokenizer::new(Box::new(DefaultTokenizer {
+            links,
+            ..Default::default()
+        }));
+        for chunk in text.as_bytes().chunks(chunk_size) {
+            tokenizer.feed(chunk).unwrap();
+        }
+
+        tokenizer
+            .finish()
+            .unwrap()
+            .into_iter()
+            .map(|annotated_word| annotated_word.word.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn streamed_words_match_whole_text_at_any_chunk_size() {
+        let text = format!(
+            "  Ünïcödé wörds — split\tacross\r\nchunks: naïve-café {}  ",
+            TRACKED_BODY
+        );
+        let links = LinkHandlers {
+            emails: LinkHandling::Drop,
+            urls: LinkHandling::Domain,
+        };
+        let expected = words_with_links(&text, links.emails, links.urls);
+
+        for chunk_size in 1..=text.len() {
+            assert_eq!(
+                streamed_words(&text, chunk_size, links),
+                expected,
+                "chunk size {}",
+                chunk_size
+            );
+        }
+    }
+
+    #[test]
+    fn streaming_only_keeps_back_an_unfinished_word() {
+        const CHUNK_SIZE: usize = 64 * 1024;
+        const LONGEST_WORD: usize = "documents,".len();
+
+        let sentence = "Stork indexes very large documents, one chunk at a time. ";
+        let mut tokenizer = StreamingTokenizer::new(Box::new(DefaultTokenizer::default()));
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        let mut max_pending_byte_count = 0;
+
+        for _ in 0..(4 * 1024 * 1024 / sentence.len()) {
+            chunk.extend_from_slice(sentence.as_bytes());
+            if chunk.len() >= CHUNK_SIZE {
+                tokenizer.feed(&chunk[..CHUNK_SIZE]).unwrap();
+                chunk.drain(..CHUNK_SIZE);
+                max_pending_byte_count = max_pending_byte_count.max(tokenizer.pending_byte_count());
+            }
+        }
+        tokenizer.feed(&chunk).unwrap();
+
+        assert!(max_pending_byte_count <= LONGEST_WORD);
+        assert_eq!(
+            tokenizer.finish().unwrap().len(),
+            4 * 1024 * 1024 / sentence.len() * 10
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_is_an_error_when_streamed() {
+        let mut tokenizer = StreamingTokenizer::new(Box::new(DefaultTokenizer::default()));
+        assert!(tokenizer.feed(b"valid \xff\xfe invalid ").is_err());
+    }
+
+    #[test]
+    fn long_words_are_cut_at_a_character_boundary() {
+        let mut words = "tiny ééééé abcdefgh".make_annotated_words();
+
+        assert_eq!(truncate_long_words(&mut words, 4), 2);
+        assert_eq!(
+            words.iter().map(|word| &*word.word).collect::<Vec<&str>>(),
+            vec!["tiny", "éééé", "abcd"]
+        );
+    }
+
+    #[test]
+    fn spaced_letters_are_searchable_when_merged() {
+        let config = |spaced_letters| Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        "Deploy the site to A W S with one command".to_string(),
+                    )),
+                    title: "Deploying".to_string(),
+                    filetype: Some(Filetype::PlainText),
+                    ..Default::default()
+                }],
+                spaced_letters,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let index = build(&config(SpacedLetterHandling::Separate))
+            .unwrap()
+            .index;
+        assert!(crate::index_v3::search(&index, "aws")
+            .unwrap()
+            .results
+            .is_empty());
+
+        let index = build(&config(SpacedLetterHandling::Merge)).unwrap().index;
+        let output = crate::index_v3::search(&index, "aws").unwrap();
+        assert_eq!(output.results.len(), 1);
+
+        let excerpt = &output.results[0].excerpts[0];
+        let highlight = &excerpt.highlight_ranges[0];
+        let highlighted: String = excerpt
+            .text
+            .chars()
+            .skip(highlight.beginning)
+            .take(highlight.end - highlight.beginning)
+            .collect();
+        assert_eq!(highlighted, "A W S");
+    }
+
+    #[test]
+    fn words_hyphenated_across_lines_can_be_joined() {
+        let config = |hyphenated_line_breaks| Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        "Rules for inter-\nnational and cross-border trade".to_string(),
+                    )),
+                    title: "Trade".to_string(),
+                    ..Default::default()
+                }],
+                hyphenated_line_breaks,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let split = build(&config(HyphenatedLineBreakHandling::Split)).unwrap();
+        assert!(!split.index.containers.contains_key("international"));
+        assert!(split.index.containers.contains_key("national"));
+
+        let joined = build(&config(HyphenatedLineBreakHandling::Join)).unwrap();
+        assert!(!joined.index.containers["international"].results.is_empty());
+        assert!(!joined.index.containers.contains_key("national"));
+        assert!(joined.index.containers.contains_key("border"));
+        assert_eq!(
+            joined.index.entries[0].contents,
+            "Rules for international and cross border trade"
+        );
+    }
+
+    #[test]
+    fn custom_tokenizer_splits_every_document() {
+        /// Keeps hyphenated gene names like `BRCA-1` whole, and splits
+        /// reactions like `NaCl+H2O` into their compounds.
+        struct ChemistryTokenizer;
+
+        impl crate::Tokenizer for ChemistryTokenizer {
+            fn tokenize(&self, text: &str) -> Vec<crate::AnnotatedWord> {
+                text.split(|c: char| c.is_whitespace() || c == '+')
+                    .filter(|word| !word.is_empty())
+                    .map(crate::AnnotatedWord::new)
+                    .collect()
+            }
+        }
+
+        let config = |tokenizer| Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        "Mix NaCl+H2O before sequencing BRCA-1.".to_string(),
+                    )),
+                    title: "Lab notes".to_string(),
+                    filetype: Some(Filetype::PlainText),
+                    ..Default::default()
+                }],
+                tokenizer,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let default_index = build(&config(None)).unwrap().index;
+        assert_eq!(
+            default_index.entries[0].contents,
+            "Mix NaCl+H2O before sequencing BRCA 1."
+        );
+        assert!(!default_index.containers["brca"].results.is_empty());
+
+        let index = build(&config(Some(crate::CustomTokenizer::new(
+            ChemistryTokenizer,
+        ))))
+        .unwrap()
+        .index;
+        assert_eq!(
+            index.entries[0].contents,
+            "Mix NaCl H2O before sequencing BRCA-1."
+        );
+        assert!(index.containers.contains_key("h2o"));
+        assert!(index.containers.contains_key("brca-1"));
+        // `brca` is only a prefix of `brca-1` now, not a word of its own.
+        assert!(index.containers["brca"].results.is_empty());
+
+        let titles = |index: &Index, query: &str| {
+            crate::index_v3::search(index, query)
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| result.entry.title)
+                .collect::<Vec<String>>()
+        };
+        assert!(titles(&default_index, "h2o").is_empty());
+        assert_eq!(titles(&index, "h2o"), vec!["Lab notes"]);
+    }
 }
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+
+use crate::index_v3::{Entry, FACET_FIELD};
+
+/// The facet of documents whose URL matches none of the configured prefixes.
+pub(super) const OTHER_FACET: &str = "other";
+
+/**
+ * Stores a facet in each document's fields: the label of the longest of
+ * `prefixes` that the document's URL path starts with, or `other`. A facet
+ * already set in the document's fields, by the config or its frontmatter,
+ * is kept.
+ *
+ * Prefixes are compared with the path of absolute URLs, so `/docs/` matches
+ * both `/docs/install` and `https://example.com/docs/install`.
+ */
+pub(super) fn assign_facets(entries: &mut [Entry], prefixes: &BTreeMap<String, String>) {
+    for entry in entries {
+        let facet = longest_matching_prefix(&entry.url, prefixes).unwrap_or(OTHER_FACET);
+
+        entry
+            .fields
+            .entry(FACET_FIELD.to_string())
+            .or_insert_with(|| facet.to_string());
+    }
+}
+
+/**
+ * The label of the longest of `prefixes` that the path of `url` starts with.
+ */
+pub(super) fn longest_matching_prefix<'a>(
+    url: &str,
+    prefixes: &'a BTreeMap<String, String>,
+) -> Option<&'a str> {
+    let path = url_path(url);
+    prefixes
+        .iter()
+        .filter(|(_, prefix)| path.starts_with(prefix.as_str()))
+        .max_by_key(|(_, prefix)| prefix.len())
+        .map(|(label, _)| label.as_str())
+}
+
+pub(super) fn url_path(url: &str) -> &str {
+    match url.split_once("://") {
+        Some((_scheme, rest)) => rest.find('/').map_or("/", |slash| &rest[slash..]),
+        None => url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::config::*;
+    use crate::index_v3::build::build;
+    use pretty_assertions::assert_eq;
+
+    fn entry(url: &str) -> Entry {
+        Entry {
+            contents: String::new(),
+            title: String::new(),
+            url: url.to_string(),
+            fields: HashMap::new(),
+            score_multiplier: None,
+            word_count: None,
+            text: None,
+            item_starts: vec![],
+        }
+    }
+
+    #[test]
+    fn longest_matching_prefix_wins() {
+        let prefixes = BTreeMap::from([
+            ("docs".to_string(), "/docs/".to_string()),
+            ("api".to_string(), "/docs/api/".to_string()),
+            ("blog".to_string(), "/blog/".to_string()),
+        ]);
+        let mut entries = vec![
+            entry("/docs/install"),
+            entry("/docs/api/search"),
+            entry("https://example.com/blog/release"),
+            entry("/about"),
+        ];
+        entries[3]
+            .fields
+            .insert(FACET_FIELD.to_string(), "company".to_string());
+
+        assign_facets(&mut entries, &prefixes);
+
+        let facets: Vec<&str> = entries
+            .iter()
+            .map(|entry| entry.fields[FACET_FIELD].as_str())
+            .collect();
+        assert_eq!(facets, vec!["docs", "api", "blog", "company"]);
+    }
+
+    #[test]
+    fn unmatched_urls_are_other() {
+        let prefixes = BTreeMap::from([("docs".to_string(), "/docs/".to_string())]);
+        let mut entries = vec![entry("/documentation"), entry("https://example.com")];
+
+        assign_facets(&mut entries, &prefixes);
+
+        assert_eq!(entries[0].fields[FACET_FIELD], OTHER_FACET);
+        assert_eq!(entries[1].fields[FACET_FIELD], OTHER_FACET);
+    }
+
+    #[test]
+    fn facets_are_counted_and_filtered_by_section() {
+        let file = |title: &str, url: &str| File {
+            explicit_source: Some(DataSource::Contents(
+                "Install the command line tool.".to_string(),
+            )),
+            title: title.to_string(),
+            url: url.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        };
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    file("Installing", "/docs/install"),
+                    file("Upgrading", "/docs/upgrade"),
+                    file("Release notes", "/blog/release"),
+                    file("About", "/about"),
+                ],
+                facet_url_prefixes: BTreeMap::from([
+                    ("docs".to_string(), "/docs/".to_string()),
+                    ("blog".to_string(), "/blog/".to_string()),
+                ]),
+                ..Default::default()
+            },
+            output: OutputConfig {
+                displayed_results_count: 1,
+                ..Default::default()
+            },
+        };
+        let index = build(&config).unwrap().index;
+        let expected_counts = BTreeMap::from([
+            ("blog".to_string(), 1),
+            ("docs".to_string(), 2),
+            ("other".to_string(), 1),
+        ]);
+
+        let unfiltered = crate::index_v3::search(&index, "install").unwrap();
+        assert_eq!(unfiltered.total_hit_count, 4);
+        assert_eq!(unfiltered.facet_counts, expected_counts);
+
+        let docs = crate::index_v3::search(&index, "install section:docs").unwrap();
+        assert_eq!(docs.total_hit_count, 2);
+        assert_eq!(docs.facet_counts, expected_counts);
+        assert_eq!(docs.results.len(), 1);
+        assert!(docs.results[0].entry.url.starts_with("/docs/"));
+    }
+}
@@ -0,0 +1,138 @@
+use std::cmp::Reverse;
+
+use super::assign_facets::url_path;
+use crate::{config::PathScoreMultiplier, index_v3::Entry};
+
+/**
+ * The rules of `input.path_score_multipliers`, with how many documents each
+ * one was applied to.
+ */
+pub(super) struct PathScoreMultiplierMatches<'a> {
+    pub(super) rules: &'a [PathScoreMultiplier],
+    pub(super) match_counts: Vec<usize>,
+}
+
+/**
+ * Stores the multiplier of the rule with the longest prefix each document's
+ * URL path starts with, or of the first such rule if several have the same
+ * prefix. Prefixes are compared like those of `facet_url_prefixes`.
+ */
+pub(super) fn assign_score_multipliers<'a>(
+    entries: &mut [Entry],
+    rules: &'a [PathScoreMultiplier],
+) -> PathScoreMultiplierMatches<'a> {
+    let mut match_counts = vec![0; rules.len()];
+
+    for entry in entries {
+        let path = url_path(&entry.url);
+        let matching_rule = rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| path.starts_with(rule.prefix.as_str()))
+            .min_by_key(|(_, rule)| Reverse(rule.prefix.len()));
+
+        if let Some((position, rule)) = matching_rule {
+            entry.score_multiplier = Some(rule.multiplier);
+            match_counts[position] += 1;
+        }
+    }
+
+    PathScoreMultiplierMatches {
+        rules,
+        match_counts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::config::*;
+    use crate::index_v3::build::build;
+    use pretty_assertions::assert_eq;
+
+    fn entry(url: &str) -> Entry {
+        Entry {
+            contents: String::new(),
+            title: String::new(),
+            url: url.to_string(),
+            fields: HashMap::new(),
+            score_multiplier: None,
+            word_count: None,
+            text: None,
+            item_starts: vec![],
+        }
+    }
+
+    fn rule(prefix: &str, multiplier: f64) -> PathScoreMultiplier {
+        PathScoreMultiplier {
+            prefix: prefix.to_string(),
+            multiplier,
+        }
+    }
+
+    #[test]
+    fn longest_matching_prefix_wins() {
+        let rules = [
+            rule("/changelog/", 0.5),
+            rule("/changelog/2018/", 0.2),
+            rule("/changelog/2018/", 0.1),
+            rule("/archive/", 0.3),
+        ];
+        let mut entries = vec![
+            entry("/changelog/2018/march"),
+            entry("https://example.com/changelog/2022/may"),
+            entry("/docs/install"),
+        ];
+
+        let matches = assign_score_multipliers(&mut entries, &rules);
+
+        let multipliers: Vec<Option<f64>> =
+            entries.iter().map(|entry| entry.score_multiplier).collect();
+        assert_eq!(multipliers, vec![Some(0.2), Some(0.5), None]);
+        assert_eq!(matches.match_counts, vec![1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn path_score_multipliers_scale_matching_documents() {
+        let file = |title: &str, url: &str| File {
+            explicit_source: Some(DataSource::Contents(
+                "Notes on the new search feature".to_string(),
+            )),
+            title: title.to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        };
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    file("Old release", "/changelog/2018/release"),
+                    file("New release", "/docs/release"),
+                ],
+                path_score_multipliers: vec![PathScoreMultiplier {
+                    prefix: "/changelog/".to_string(),
+                    multiplier: 0.2,
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let build_result = build(&config).unwrap();
+        assert!(!build_result
+            .nudges
+            .iter()
+            .any(|nudge| nudge.config_path.as_deref() == Some("input.path_score_multipliers")));
+
+        let output = crate::index_v3::search(&build_result.index, "search").unwrap();
+        let results: Vec<(&str, usize)> = output
+            .results
+            .iter()
+            .map(|result| (result.entry.title.as_str(), result.score))
+            .collect();
+        assert_eq!(results[0].0, "New release");
+        assert_eq!(results[1].0, "Old release");
+        assert_eq!(results[1].1, (results[0].1 as f64 * 0.2).round() as usize);
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::{hash_map::DefaultHasher, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use regex::Regex;
+
+use super::IndexGenerationError;
+use crate::{
+    config::{Config, InputConfig},
+    index_v3::{AnnotatedWord, Entry},
+    InternalWordAnnotation,
+};
+
+/**
+ * Removes the text of `input.boilerplate_phrases` and
+ * `input.boilerplate_patterns` from documents' word lists. Whole words are
+ * removed, so the contents that excerpts are made from are still the words
+ * of the list joined by spaces.
+ */
+pub(super) struct Boilerplate {
+    phrases: Vec<Vec<String>>,
+    patterns: Vec<Regex>,
+}
+
+impl Boilerplate {
+    /// Patterns that don't compile are left out, since
+    /// `check_boilerplate_patterns` fails the build before any are used.
+    pub(super) fn new(config: &InputConfig) -> Self {
+        let mut phrases: Vec<Vec<String>> = config
+            .boilerplate_phrases
+            .iter()
+            .map(|phrase| phrase.split_whitespace().map(ToString::to_string).collect())
+            .filter(|words: &Vec<String>| !words.is_empty())
+            .collect();
+
+        // Longer phrases are tried first, so a phrase that starts another
+        // one doesn't leave the rest of the other behind.
+        phrases.sort_by_key(|words| std::cmp::Reverse(words.len()));
+
+        let patterns = config
+            .boilerplate_patterns
+            .iter()
+            .filter_map(|pattern| Regex::new(pattern).ok())
+            .collect();
+
+        Boilerplate { phrases, patterns }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.phrases.is_empty() && self.patterns.is_empty()
+    }
+
+    /**
+     * Removes the words of each phrase where they appear in order, and the
+     * words that lie entirely within a match of a pattern, which is matched
+     * against the words joined by spaces. An `ItemStart` annotation on a
+     * removed word moves to the next word that's kept.
+     */
+    pub(super) fn remove_from(&self, word_list: &mut Vec<AnnotatedWord>) {
+        let mut removed = vec![false; word_list.len()];
+
+        let mut index = 0;
+        while index < word_list.len() {
+            let phrase_length = self
+                .phrases
+                .iter()
+                .find(|phrase| {
+                    word_list[index..].len() >= phrase.len()
+                        && phrase
+                            .iter()
+                            .zip(&word_list[index..])
+                            .all(|(phrase_word, aw)| **phrase_word == *aw.word)
+                })
+                .map(Vec::len);
+
+            match phrase_length {
+                Some(length) => {
+                    removed[index..index + length].fill(true);
+                    index += length;
+                }
+                None => index += 1,
+            }
+        }
+
+        if !self.patterns.is_empty() {
+            let mut text = String::new();
+            let mut word_ranges = Vec::with_capacity(word_list.len());
+            for aw in word_list.iter() {
+                if !text.is_empty() {
+                    text.push(' ');
+                }
+                word_ranges.push((text.len(), text.len() + aw.word.len()));
+                text.push_str(&aw.word);
+            }
+
+            for pattern in &self.patterns {
+                for found in pattern.find_iter(&text) {
+                    let first_word =
+                        word_ranges.partition_point(|(start, _)| *start < found.start());
+                    for (word_index, (start, end)) in
+                        word_ranges.iter().enumerate().skip(first_word)
+                    {
+                        if *end > found.end() {
+                            break;
+                        }
+                        if start < end {
+                            removed[word_index] = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        if !removed.contains(&true) {
+            return;
+        }
+
+        let mut carried_item_start = false;
+        let mut kept = Vec::with_capacity(word_list.len());
+        for (mut aw, removed) in word_list.drain(..).zip(removed) {
+            let item_start = aw
+                .internal_annotations
+                .contains(&InternalWordAnnotation::ItemStart);
+
+            if removed {
+                carried_item_start |= item_start;
+                continue;
+            }
+
+            if carried_item_start && !item_start {
+                aw.internal_annotations
+                    .push(InternalWordAnnotation::ItemStart);
+            }
+            carried_item_start = false;
+            kept.push(aw);
+        }
+
+        *word_list = kept;
+    }
+}
+
+/**
+ * Fails the build if one of `input.boilerplate_patterns` isn't a valid
+ * regular expression.
+ */
+pub(super) fn check_boilerplate_patterns(config: &Config) -> Result<(), IndexGenerationError> {
+    for pattern in &config.input.boilerplate_patterns {
+        if let Err(error) = Regex::new(pattern) {
+            return Err(IndexGenerationError::InvalidBoilerplatePattern {
+                pattern: pattern.clone(),
+                message: error.to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// The fewest words a phrase found by `find_repeated_phrases` can have.
+pub(super) const REPEATED_PHRASE_MINIMUM_WORDS: usize = 10;
+
+/**
+ * A run of words that appears, word for word, in many of the documents, like
+ * a cookie banner or a footer that the HTML selectors didn't leave out.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct RepeatedPhrase {
+    pub(super) phrase: String,
+    pub(super) document_count: usize,
+    pub(super) document_total: usize,
+}
+
+/**
+ * Finds each run of `REPEATED_PHRASE_MINIMUM_WORDS` or more words that
+ * appears in more than `percent` percent of the documents whose contents
+ * are stored, and in at least two of them. Overlapping runs are joined into
+ * the longest phrase they make up in the first document they're found in,
+ * so each one can be added to `input.boilerplate_phrases` as it is.
+ * Phrases are listed in the order they're first found.
+ */
+pub(super) fn find_repeated_phrases(entries: &[Entry], percent: u8) -> Vec<RepeatedPhrase> {
+    let documents: Vec<Vec<&str>> = entries
+        .iter()
+        .map(|entry| entry.contents.split(' ').collect::<Vec<&str>>())
+        .filter(|words| words.len() >= REPEATED_PHRASE_MINIMUM_WORDS)
+        .collect();
+    let document_total = entries
+        .iter()
+        .filter(|entry| !entry.contents.is_empty())
+        .count();
+
+    let window_hashes: Vec<Vec<u64>> = documents
+        .iter()
+        .map(|words| {
+            words
+                .windows(REPEATED_PHRASE_MINIMUM_WORDS)
+                .map(|window| {
+                    let mut hasher = DefaultHasher::new();
+                    window.hash(&mut hasher);
+                    hasher.finish()
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut document_counts: HashMap<u64, usize> = HashMap::new();
+    for hashes in &window_hashes {
+        let unique: HashSet<&u64> = hashes.iter().collect();
+        for hash in unique {
+            *document_counts.entry(*hash).or_default() += 1;
+        }
+    }
+
+    let is_repeated =
+        |count: usize| count >= 2 && count * 100 > usize::from(percent) * document_total;
+
+    let mut reported: HashSet<u64> = HashSet::new();
+    let mut phrases = vec![];
+    for (words, hashes) in documents.iter().zip(&window_hashes) {
+        let mut window = 0;
+        while window < hashes.len() {
+            let count = document_counts[&hashes[window]];
+            if !is_repeated(count) || reported.contains(&hashes[window]) {
+                window += 1;
+                continue;
+            }
+
+            // Each window that follows on and is repeated too extends the
+            // phrase by one word.
+            let first_window = window;
+            let mut document_count = count;
+            while let Some(next) = hashes.get(window + 1) {
+                let next_count = document_counts[next];
+                if !is_repeated(next_count) || reported.contains(next) {
+                    break;
+                }
+                document_count = document_count.min(next_count);
+                window += 1;
+            }
+
+            reported.extend(&hashes[first_window..=window]);
+            phrases.push(RepeatedPhrase {
+                phrase: words[first_window..window + REPEATED_PHRASE_MINIMUM_WORDS].join(" "),
+                document_count,
+                document_total,
+            });
+            window += 1;
+        }
+    }
+
+    phrases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::*;
+    use crate::index_v3::build::build;
+    use crate::V3Index as Index;
+
+    fn word_list(text: &str) -> Vec<AnnotatedWord> {
+        text.split(' ')
+            .map(|word| AnnotatedWord {
+                word: word.into(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn removed(config: InputConfig, text: &str) -> String {
+        let mut words = word_list(text);
+        Boilerplate::new(&config).remove_from(&mut words);
+        words
+            .iter()
+            .map(|aw| &*aw.word)
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn phrases_are_removed_wherever_they_appear() {
+        let config = InputConfig {
+            boilerplate_phrases: vec![
+                "We use cookies.".to_string(),
+                "  Share  this ".to_string(),
+                "".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            removed(
+                config,
+                "We use cookies. Otters hold hands. Share this Share this page We use cookies"
+            ),
+            "Otters hold hands. page We use cookies"
+        );
+    }
+
+    #[test]
+    fn patterns_remove_the_words_they_cover() {
+        let config = InputConfig {
+            boilerplate_patterns: vec![r"Copyright \d{4}".to_string(), "ipsum do".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            removed(config, "Lorem ipsum dolor Copyright 2021 Otters"),
+            "Lorem dolor Otters"
+        );
+    }
+
+    #[test]
+    fn item_starts_move_to_the_next_word_kept() {
+        let config = InputConfig {
+            boilerplate_phrases: vec!["Skip to".to_string()],
+            ..Default::default()
+        };
+        let mut words = word_list("Skip to content");
+        words[0]
+            .internal_annotations
+            .push(InternalWordAnnotation::ItemStart);
+
+        Boilerplate::new(&config).remove_from(&mut words);
+        assert_eq!(words.len(), 1);
+        assert_eq!(
+            words[0].internal_annotations,
+            vec![InternalWordAnnotation::ItemStart]
+        );
+    }
+
+    #[test]
+    fn invalid_patterns_fail_the_build() {
+        let mut config = Config::default();
+        config.input.boilerplate_patterns = vec!["(unclosed".to_string()];
+
+        assert!(matches!(
+            check_boilerplate_patterns(&config),
+            Err(IndexGenerationError::InvalidBoilerplatePattern { pattern, .. }) if pattern == "(unclosed"
+        ));
+    }
+
+    fn entry(contents: &str) -> Entry {
+        Entry {
+            contents: contents.to_string(),
+            title: String::new(),
+            url: String::new(),
+            fields: HashMap::new(),
+            score_multiplier: None,
+            word_count: None,
+            text: None,
+            item_starts: vec![],
+        }
+    }
+
+    #[test]
+    fn phrases_in_most_documents_are_found() {
+        let footer = "Copyright the otter society all rights reserved and then some more words";
+        let entries = vec![
+            entry(&format!("Otters hold hands. {}", footer)),
+            entry(&format!("{} Beavers build dams.", footer)),
+            entry(&format!("Seals are {} loud", footer)),
+            entry("Nothing to see here at all, just a few words of text that differ."),
+        ];
+
+        assert_eq!(
+            find_repeated_phrases(&entries, 50),
+            vec![RepeatedPhrase {
+                phrase: footer.to_string(),
+                document_count: 3,
+                document_total: 4,
+            }]
+        );
+        assert!(find_repeated_phrases(&entries, 75).is_empty());
+    }
+
+    #[test]
+    fn short_repeats_are_not_phrases() {
+        let entries = vec![
+            entry("one two three four five six seven eight nine"),
+            entry("one two three four five six seven eight nine"),
+        ];
+
+        assert!(find_repeated_phrases(&entries, 0).is_empty());
+    }
+
+    fn plaintext_file(title: &str, contents: &str) -> File {
+        File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn boilerplate_is_removed_and_repeated_phrases_are_reported() {
+        let footer =
+            "Thanks for reading! Subscribe to the newsletter for more otter facts every week.";
+        let config = |boilerplate_phrases: Vec<&str>| Config {
+            input: InputConfig {
+                files: vec![
+                    plaintext_file("Hands", &format!("Otters hold hands. {}", footer)),
+                    plaintext_file("Dams", &format!("Beavers build dams. {}", footer)),
+                    plaintext_file(
+                        "Seals",
+                        &format!("Seals are loud. Copyright 2021 {}", footer),
+                    ),
+                ],
+                boilerplate_phrases: boilerplate_phrases.into_iter().map(String::from).collect(),
+                boilerplate_patterns: vec![r"Copyright \d{4}".to_string()],
+                boilerplate_detection_percent: Some(50),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let result = build(&config(vec![])).unwrap();
+        assert_eq!(result.nudges.len(), 1);
+        assert_eq!(result.nudges[0].code, "N014_REPEATED_PHRASE");
+        assert_eq!(
+            result.nudges[0].config_path,
+            Some("input.boilerplate_phrases".to_string())
+        );
+        assert!(result.nudges[0]
+            .message
+            .contains(&format!("\"{}\" appears in 3 of 3 documents", footer)));
+        assert_eq!(
+            result.index.entries[2].contents,
+            format!("Seals are loud. {}", footer)
+        );
+
+        let bytes = bytes::Bytes::from(&build(&config(vec![footer])).unwrap().index);
+        let index = Index::try_from(bytes.as_ref()).unwrap();
+        assert_eq!(index.entries[0].contents, "Otters hold hands.");
+        assert_eq!(index.entries[2].contents, "Seals are loud.");
+        assert!(!index.containers.contains_key("newsletter"));
+        assert!(!index.containers.contains_key("copyright"));
+
+        let output = crate::index_v3::search(&index, "seals").unwrap();
+        let excerpt = &output.results[0].excerpts[0];
+        let highlight = &excerpt.highlight_ranges[0];
+        let highlighted: String = excerpt
+            .text
+            .chars()
+            .skip(highlight.beginning)
+            .take(highlight.end - highlight.beginning)
+            .collect();
+        assert_eq!(highlighted, "Seals");
+
+        let error = build(&Config {
+            input: InputConfig {
+                files: vec![plaintext_file(
+                    "Twelve",
+                    "one two three four five six seven eight nine ten eleven twelve",
+                )],
+                boilerplate_patterns: vec!["(".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            IndexGenerationError::InvalidBoilerplatePattern { .. }
+        ));
+    }
+}
@@ -0,0 +1,232 @@
+use crate::config::UrlCanonicalizationConfig;
+
+/**
+ * The form of `url` that's compared to find documents with the same URL, as
+ * rewritten by the rules of `input.url_canonicalization`. URLs without a
+ * scheme and host, like `/docs/intro`, only have their paths and query
+ * strings rewritten. The fragment is kept, since it can lead to a different
+ * part of the page.
+ */
+pub(super) fn canonical_url(url: &str, config: &UrlCanonicalizationConfig) -> String {
+    let (url, fragment) = match url.find('#') {
+        Some(index) => url.split_at(index),
+        None => (url, ""),
+    };
+    let (url, query) = match url.find('?') {
+        Some(index) => (&url[..index], Some(&url[index + 1..])),
+        None => (url, None),
+    };
+
+    let (origin, path) = match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let host_end = rest.find('/').unwrap_or(rest.len());
+            let (host, path) = rest.split_at(host_end);
+            (Some(canonical_origin(scheme, host, config)), path)
+        }
+        None => (None, url),
+    };
+
+    let mut canonical = origin.unwrap_or_default();
+    canonical.push_str(canonical_path(path, config));
+
+    if let Some(query) = query {
+        let parameters: Vec<&str> = query
+            .split('&')
+            .filter(|parameter| !parameter.is_empty())
+            .filter(|parameter| {
+                let name = parameter.split('=').next().unwrap_or_default();
+                !is_stripped_parameter(name, &config.strip_query_parameters)
+            })
+            .collect();
+
+        if !parameters.is_empty() {
+            canonical.push('?');
+            canonical.push_str(&parameters.join("&"));
+        }
+    }
+
+    canonical.push_str(fragment);
+    canonical
+}
+
+fn canonical_origin(scheme: &str, host: &str, config: &UrlCanonicalizationConfig) -> String {
+    let scheme = scheme.to_ascii_lowercase();
+    let host = if config.lowercase_host {
+        host.to_ascii_lowercase()
+    } else {
+        host.to_string()
+    };
+
+    let default_port = match scheme.as_str() {
+        "http" => Some(":80"),
+        "https" => Some(":443"),
+        _ => None,
+    };
+    let host = match default_port {
+        Some(port) if config.strip_default_port => host.strip_suffix(port).unwrap_or(&host),
+        _ => &host,
+    };
+
+    format!("{}://{}", scheme, host)
+}
+
+fn canonical_path<'a>(path: &'a str, config: &UrlCanonicalizationConfig) -> &'a str {
+    let mut path = path;
+
+    if config.collapse_index_html {
+        if let Some(directory) = path.strip_suffix("index.html") {
+            if directory.is_empty() || directory.ends_with('/') {
+                path = directory;
+            }
+        }
+    }
+
+    if config.strip_trailing_slash {
+        path = path.trim_end_matches('/');
+    }
+
+    path
+}
+
+fn is_stripped_parameter(name: &str, stripped: &[String]) -> bool {
+    stripped
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == pattern,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::*;
+    use crate::index_v3::build::{build, errors::DocumentWarningKind};
+
+    fn canonical(url: &str) -> String {
+        canonical_url(url, &UrlCanonicalizationConfig::default())
+    }
+
+    #[test]
+    fn variants_of_a_url_are_canonicalized_together() {
+        for url in [
+            "https://example.com/docs/intro",
+            "https://example.com/docs/intro/",
+            "https://example.com/docs/intro/index.html",
+            "HTTPS://Example.COM:443/docs/intro",
+            "https://example.com/docs/intro?utm_source=feed&utm_medium=rss",
+        ] {
+            assert_eq!(canonical(url), "https://example.com/docs/intro", "{}", url);
+        }
+
+        assert_eq!(canonical("https://example.com/"), "https://example.com");
+        assert_eq!(canonical("/index.html"), "");
+        assert_eq!(canonical("/docs/"), "/docs");
+    }
+
+    #[test]
+    fn differences_that_matter_are_kept() {
+        assert_eq!(
+            canonical("https://example.com:8443/Docs/intro?page=2&utm_id=7#setup"),
+            "https://example.com:8443/Docs/intro?page=2#setup"
+        );
+        assert_eq!(
+            canonical("http://example.com:443/docs/myindex.html"),
+            "http://example.com:443/docs/myindex.html"
+        );
+    }
+
+    #[test]
+    fn each_rule_can_be_turned_off() {
+        let url = "https://Example.com:443/docs/index.html?utm_source=feed&ref=home";
+
+        let none = UrlCanonicalizationConfig {
+            lowercase_host: false,
+            strip_default_port: false,
+            collapse_index_html: false,
+            strip_trailing_slash: false,
+            strip_query_parameters: vec![],
+        };
+        assert_eq!(canonical_url(url, &none), url);
+
+        let custom = UrlCanonicalizationConfig {
+            collapse_index_html: false,
+            strip_query_parameters: vec!["ref".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            canonical_url(url, &custom),
+            "https://example.com/docs/index.html?utm_source=feed"
+        );
+    }
+
+    fn plaintext_file(title: &str, contents: &str) -> File {
+        File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        }
+    }
+
+    const TWELVE_WORDS: &str = "one two three four five six seven eight nine ten eleven twelve";
+
+    #[test]
+    fn variants_of_a_url_are_skipped_as_duplicates() {
+        let config = |url_canonicalization| Config {
+            input: InputConfig {
+                files: vec![
+                    File {
+                        url: "https://example.com/docs/intro".to_string(),
+                        ..plaintext_file("Intro", TWELVE_WORDS)
+                    },
+                    File {
+                        url: "https://Example.com/docs/intro/index.html".to_string(),
+                        ..plaintext_file("Intro index", TWELVE_WORDS)
+                    },
+                    File {
+                        url: "https://example.com/docs/intro/?utm_source=feed".to_string(),
+                        ..plaintext_file("Intro from feed", TWELVE_WORDS)
+                    },
+                ],
+                url_canonicalization,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let build_result = build(&config(UrlCanonicalizationConfig::default())).unwrap();
+        assert_eq!(build_result.index.entries.len(), 1);
+        assert_eq!(
+            build_result
+                .warnings
+                .iter()
+                .map(|warning| &warning.kind)
+                .collect::<Vec<_>>(),
+            vec![
+                &DocumentWarningKind::DuplicateCanonicalUrl {
+                    url: "https://Example.com/docs/intro/index.html".to_string(),
+                    other_url: "https://example.com/docs/intro".to_string(),
+                    other_source: "Intro".to_string()
+                },
+                &DocumentWarningKind::DuplicateCanonicalUrl {
+                    url: "https://example.com/docs/intro/?utm_source=feed".to_string(),
+                    other_url: "https://example.com/docs/intro".to_string(),
+                    other_source: "Intro".to_string()
+                }
+            ]
+        );
+        assert!(build_result.warnings[0]
+            .kind
+            .to_string()
+            .contains("`https://example.com/docs/intro`, which is the same as `https://Example.com/docs/intro/index.html`"));
+
+        let build_result = build(&config(UrlCanonicalizationConfig {
+            lowercase_host: false,
+            strip_query_parameters: vec![],
+            ..Default::default()
+        }))
+        .unwrap();
+        assert_eq!(build_result.index.entries.len(), 3);
+    }
+}
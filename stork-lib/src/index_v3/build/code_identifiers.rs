@@ -0,0 +1,146 @@
+/**
+ * The words a word of code is indexed under besides itself, lowercased: each
+ * identifier in it, like `user` and `getUserName` in `user.getUserName()`,
+ * and each part of an identifier written in camelCase or snake_case, like
+ * `get`, `user`, and `name`. Each word is listed once.
+ */
+pub(super) fn identifier_words(word: &str) -> Vec<String> {
+    let mut words: Vec<String> = vec![];
+
+    let identifiers = word
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|identifier| !identifier.is_empty());
+
+    for identifier in identifiers {
+        let parts = identifier_parts(identifier);
+        let parts = if parts.len() > 1 { parts } else { vec![] };
+
+        for word in std::iter::once(identifier).chain(parts) {
+            let word = word.to_lowercase();
+            if !words.contains(&word) {
+                words.push(word);
+            }
+        }
+    }
+
+    words
+}
+
+/**
+ * Splits an identifier at its underscores and where its case changes, so
+ * `getHTTPResponse_code` becomes `get`, `HTTP`, `Response`, and `code`.
+ */
+fn identifier_parts(identifier: &str) -> Vec<&str> {
+    let mut parts = vec![];
+
+    for snake_part in identifier.split('_').filter(|part| !part.is_empty()) {
+        let chars: Vec<(usize, char)> = snake_part.char_indices().collect();
+        let mut start = 0;
+
+        for index in 1..chars.len() {
+            let (offset, c) = chars[index];
+            let previous = chars[index - 1].1;
+            let next_is_lowercase = chars
+                .get(index + 1)
+                .map_or(false, |(_, next)| next.is_lowercase());
+
+            // A capital starts a part after a lowercase letter or a digit,
+            // or, when it's followed by a lowercase letter, after a run of
+            // capitals, like the `R` of `HTTPResponse`.
+            if c.is_uppercase()
+                && (previous.is_lowercase()
+                    || previous.is_numeric()
+                    || (previous.is_uppercase() && next_is_lowercase))
+            {
+                parts.push(&snake_part[start..offset]);
+                start = offset;
+            }
+        }
+
+        parts.push(&snake_part[start..]);
+    }
+
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::*;
+    use crate::index_v3::build::build;
+    use crate::V3Index as Index;
+
+    #[test]
+    fn identifiers_are_split_into_their_parts() {
+        assert_eq!(
+            identifier_words("user.getUserName();"),
+            vec!["user", "getusername", "get", "name"]
+        );
+        assert_eq!(
+            identifier_words("MAX_RETRY_COUNT"),
+            vec!["max_retry_count", "max", "retry", "count"]
+        );
+        assert_eq!(
+            identifier_parts("getHTTPResponse_code"),
+            vec!["get", "HTTP", "Response", "code"]
+        );
+        assert_eq!(identifier_parts("utf8Decode"), vec!["utf8", "Decode"]);
+    }
+
+    #[test]
+    fn plain_words_are_only_themselves() {
+        assert_eq!(identifier_words("otter"), vec!["otter"]);
+        assert!(identifier_words("=>").is_empty());
+    }
+
+    #[test]
+    fn code_identifiers_are_searchable_whole_and_by_their_parts() {
+        let config = |index_code_identifiers| Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        "<html><body><main><p>Look up the person who is signed in.</p>\
+                         <pre><code>let name = user.getUserName();</code></pre>\
+                         </main></body></html>"
+                            .to_string(),
+                    )),
+                    title: "Accounts".to_string(),
+                    filetype: Some(Filetype::HTML),
+                    ..Default::default()
+                }],
+                index_code_identifiers,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let highlighted = |index: &Index, query: &str| -> Vec<String> {
+            crate::index_v3::search(index, query)
+                .unwrap()
+                .results
+                .iter()
+                .flat_map(|result| &result.excerpts)
+                .flat_map(|excerpt| {
+                    excerpt.highlight_ranges.iter().map(move |range| {
+                        excerpt
+                            .text
+                            .chars()
+                            .skip(range.beginning)
+                            .take(range.end - range.beginning)
+                            .collect()
+                    })
+                })
+                .collect()
+        };
+
+        let index = build(&config(false)).unwrap().index;
+        assert!(highlighted(&index, "getUserName").is_empty());
+
+        let bytes = bytes::Bytes::from(&build(&config(true)).unwrap().index);
+        let index = Index::try_from(bytes.as_ref()).unwrap();
+        assert_eq!(highlighted(&index, "getUserName"), vec!["getUserName"]);
+        assert_eq!(highlighted(&index, "user"), vec!["user"]);
+        assert_eq!(highlighted(&index, "code:name"), vec!["name", "Name"]);
+        assert_eq!(highlighted(&index, "code:signed"), Vec::<String>::new());
+        assert_eq!(highlighted(&index, "signed"), vec!["signed"]);
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::index_v3::Entry;
+
+/**
+ * Appends a segment of each document's URL path to any title shared by more
+ * than one document, so that `/cli/installation` and `/library/installation`
+ * are displayed as "Installation (cli)" and "Installation (library)".
+ *
+ * The segment is the one closest to the end of the path that's different for
+ * every document sharing the title. If there's no such segment, those titles
+ * are left as they are.
+ */
+pub(super) fn disambiguate_titles(entries: &mut [Entry]) {
+    let mut entries_by_title: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        entries_by_title
+            .entry(entry.title.as_str())
+            .or_default()
+            .push(index);
+    }
+
+    let suffixes: Vec<(usize, String)> = entries_by_title
+        .into_values()
+        .filter(|indexes| indexes.len() > 1)
+        .filter_map(|indexes| {
+            let paths: Vec<Vec<&str>> = indexes
+                .iter()
+                .map(|&index| url_path_segments(&entries[index].url))
+                .collect();
+
+            let segments = distinguishing_segments(&paths)?;
+            Some(indexes.into_iter().zip(segments).collect::<Vec<_>>())
+        })
+        .flatten()
+        .collect();
+
+    for (index, segment) in suffixes {
+        let entry = &mut entries[index];
+        entry.title = format!("{} ({})", entry.title, segment);
+    }
+}
+
+/**
+ * Finds the depth, counting back from the end of each path, at which every
+ * path has a different segment, and returns those segments in order.
+ */
+fn distinguishing_segments(paths: &[Vec<&str>]) -> Option<Vec<String>> {
+    let shortest_path = paths.iter().map(Vec::len).min()?;
+
+    (1..=shortest_path).find_map(|depth| {
+        let segments: Vec<&str> = paths.iter().map(|path| path[path.len() - depth]).collect();
+        let unique_segments: HashSet<&str> = segments.iter().copied().collect();
+
+        if unique_segments.len() == segments.len() {
+            Some(segments.into_iter().map(str::to_string).collect())
+        } else {
+            None
+        }
+    })
+}
+
+fn url_path_segments(url: &str) -> Vec<&str> {
+    let path = match url.split_once("://") {
+        Some((_scheme, rest)) => rest.split_once('/').map_or("", |(_host, path)| path),
+        None => url,
+    };
+
+    let path = path.split(|c| c == '?' || c == '#').next().unwrap_or("");
+
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, DataSource, File, Filetype, InputConfig};
+    use crate::index_v3::build::build;
+
+    fn entry(title: &str, url: &str) -> Entry {
+        Entry {
+            contents: String::new(),
+            title: title.to_string(),
+            url: url.to_string(),
+            fields: HashMap::default(),
+            score_multiplier: None,
+            word_count: None,
+            text: None,
+            item_starts: vec![],
+        }
+    }
+
+    fn titles(entries: &[Entry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.title.as_str()).collect()
+    }
+
+    #[test]
+    fn duplicate_titles_get_the_segment_that_tells_them_apart() {
+        let mut entries = vec![
+            entry("Installation", "/docs/cli/installation/"),
+            entry(
+                "Installation",
+                "https://example.com/docs/library/installation",
+            ),
+            entry("Overview", "/docs/cli/"),
+        ];
+
+        disambiguate_titles(&mut entries);
+
+        assert_eq!(
+            titles(&entries),
+            vec!["Installation (cli)", "Installation (library)", "Overview"]
+        );
+    }
+
+    #[test]
+    fn titles_are_kept_when_no_segment_tells_them_apart() {
+        let mut entries = vec![
+            entry("Installation", "/docs/installation"),
+            entry("Installation", "/docs/installation?version=2"),
+            entry("Setup", ""),
+            entry("Setup", "/setup"),
+        ];
+
+        disambiguate_titles(&mut entries);
+
+        assert_eq!(
+            titles(&entries),
+            vec!["Installation", "Installation", "Setup", "Setup"]
+        );
+    }
+
+    fn plaintext_file(title: &str, contents: &str) -> File {
+        File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        }
+    }
+
+    const TWELVE_WORDS: &str = "one two three four five six seven eight nine ten eleven twelve";
+
+    #[test]
+    fn duplicate_titles_are_disambiguated_when_configured() {
+        let files = vec![
+            File {
+                url: "/cli/installation".to_string(),
+                ..plaintext_file("Installation", TWELVE_WORDS)
+            },
+            File {
+                url: "/library/installation".to_string(),
+                ..plaintext_file("Installation", TWELVE_WORDS)
+            },
+        ];
+
+        let titles = |disambiguate_duplicate_titles| {
+            let config = Config {
+                input: InputConfig {
+                    files: files.clone(),
+                    disambiguate_duplicate_titles,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            build(&config)
+                .unwrap()
+                .index
+                .entries
+                .into_iter()
+                .map(|entry| entry.title)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(titles(false), vec!["Installation", "Installation"]);
+        assert_eq!(
+            titles(true),
+            vec!["Installation (cli)", "Installation (library)"]
+        );
+    }
+}
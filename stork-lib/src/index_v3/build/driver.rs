@@ -0,0 +1,160 @@
+use std::{
+    future::Future,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+use rayon::ThreadPool;
+
+use super::{
+    errors::{IndexGenerationError, WordListGenerationError},
+    fill_intermediate_entries::{ReadResult, ReaderConfig, WebClient},
+    BuildProgress,
+};
+
+/**
+ * What differs between building an index synchronously and asynchronously:
+ * how progress is reported, whether web pages are fetched before their
+ * documents are read, and how the build waits for work on its thread pool.
+ * The build itself is a single future that either kind of driver drives.
+ */
+pub(super) trait BuildDriver {
+    /// Reports a progress event to whoever started the build.
+    async fn report(&mut self, progress: BuildProgress);
+
+    /// Fetches the document `reader_config` describes without blocking, or
+    /// returns `None` to have it read on the pool like any other document.
+    async fn prefetch(
+        &self,
+        reader_config: &ReaderConfig,
+        web_client: &WebClient,
+    ) -> Option<Result<ReadResult, WordListGenerationError>>;
+
+    /// Runs `work` on `pool`, inside the current span, and waits for it to
+    /// finish.
+    async fn run_on_pool<T, W>(
+        &self,
+        pool: &Arc<ThreadPool>,
+        work: W,
+    ) -> Result<T, IndexGenerationError>
+    where
+        T: Send + 'static,
+        W: FnOnce() -> T + Send + 'static;
+}
+
+/**
+ * Drives a build on the calling thread, blocking it until each step is
+ * done, and reports progress to a callback.
+ */
+pub(super) struct SyncDriver<F: FnMut(BuildProgress)> {
+    on_progress: F,
+}
+
+impl<F: FnMut(BuildProgress)> SyncDriver<F> {
+    pub(super) fn new(on_progress: F) -> Self {
+        SyncDriver { on_progress }
+    }
+}
+
+impl<F: FnMut(BuildProgress)> BuildDriver for SyncDriver<F> {
+    async fn report(&mut self, progress: BuildProgress) {
+        (self.on_progress)(progress);
+    }
+
+    async fn prefetch(
+        &self,
+        _reader_config: &ReaderConfig,
+        _web_client: &WebClient,
+    ) -> Option<Result<ReadResult, WordListGenerationError>> {
+        None
+    }
+
+    async fn run_on_pool<T, W>(
+        &self,
+        pool: &Arc<ThreadPool>,
+        work: W,
+    ) -> Result<T, IndexGenerationError>
+    where
+        T: Send + 'static,
+        W: FnOnce() -> T + Send + 'static,
+    {
+        let span = tracing::Span::current();
+        Ok(pool.install(move || span.in_scope(work)))
+    }
+}
+
+/**
+ * Drives a build from an async runtime. Web pages are fetched without
+ * blocking, work on the pool runs on the runtime's blocking threads, and
+ * progress is sent to a channel, if there is one.
+ */
+#[cfg(feature = "async")]
+pub(super) struct AsyncDriver {
+    progress: Option<tokio::sync::mpsc::Sender<BuildProgress>>,
+}
+
+#[cfg(feature = "async")]
+impl AsyncDriver {
+    pub(super) fn new(progress: Option<tokio::sync::mpsc::Sender<BuildProgress>>) -> Self {
+        AsyncDriver { progress }
+    }
+}
+
+#[cfg(feature = "async")]
+impl BuildDriver for AsyncDriver {
+    async fn report(&mut self, progress: BuildProgress) {
+        if let Some(sender) = &self.progress {
+            let _ = sender.send(progress).await;
+        }
+    }
+
+    async fn prefetch(
+        &self,
+        reader_config: &ReaderConfig,
+        web_client: &WebClient,
+    ) -> Option<Result<ReadResult, WordListGenerationError>> {
+        super::fill_intermediate_entries::prefetch(reader_config, web_client).await
+    }
+
+    async fn run_on_pool<T, W>(
+        &self,
+        pool: &Arc<ThreadPool>,
+        work: W,
+    ) -> Result<T, IndexGenerationError>
+    where
+        T: Send + 'static,
+        W: FnOnce() -> T + Send + 'static,
+    {
+        let pool = Arc::clone(pool);
+        let span = tracing::Span::current();
+        tokio::task::spawn_blocking(move || pool.install(move || span.in_scope(work)))
+            .await
+            .map_err(|error| IndexGenerationError::BuildTaskFailed(error.to_string()))
+    }
+}
+
+/**
+ * Runs a future to completion on the current thread. A build driven by
+ * `SyncDriver` never waits on anything that another thread finishes, so it's
+ * polled once; the thread is only parked if something does.
+ */
+pub(super) fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
@@ -1,16 +1,35 @@
+use serde::{Serialize, Serializer};
 use std::{fmt, path::PathBuf};
 use thiserror::Error;
 
-use crate::config::File;
+use crate::{config::File, StoredDocumentError};
+
+/**
+ * A position within a document's contents. Lines and columns count from 1.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SourceLocation {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
 
 #[derive(Debug, Error, Clone, PartialEq)]
 pub enum WordListGenerationError {
     #[error("SRT file could not be parsed.")]
-    InvalidSRT,
+    InvalidSRT(Option<SourceLocation>),
 
     #[error("The file `{0}` could not be found.")]
     FileNotFound(PathBuf),
 
+    #[error("The file `{0}` doesn't look like text, so it was skipped.")]
+    BinaryFileSkipped(PathBuf),
+
     #[error("Could not determine the file's filetype. Please give this file a file extension Stork knows about, or disambiguate the file's filetype within your config.")]
     CannotDetermineFiletype,
 
@@ -31,27 +50,295 @@ pub enum WordListGenerationError {
 
     #[error("Stork was not built with the `web-scraping` feature enabled.")]
     FeatureNotAvailable,
+
+    #[error("The HTML document is malformed: {0}. Fix the markup, or remove `strict_html` from your config.")]
+    MalformedHtml(String),
+
+    #[error("The document's frontmatter could not be parsed: {0}")]
+    InvalidFrontmatter(String, SourceLocation),
+
+    #[error("{0} This is an error because `input.warnings` is set to `deny`.")]
+    DeniedWarning(DocumentWarningKind),
 }
 
-fn pluralize_with_count(count: usize, singular: &str, plural: &str) -> String {
+impl WordListGenerationError {
+    /**
+     * A stable identifier for this kind of error, suitable for grouping
+     * failures in CI. Codes are never reused or renumbered.
+     */
+    pub fn code(&self) -> &'static str {
+        match self {
+            WordListGenerationError::InvalidSRT(_) => "E001_INVALID_SRT",
+            WordListGenerationError::FileNotFound(_) => "E002_FILE_NOT_FOUND",
+            WordListGenerationError::BinaryFileSkipped(_) => "E003_BINARY_FILE_SKIPPED",
+            WordListGenerationError::CannotDetermineFiletype => "E004_CANNOT_DETERMINE_FILETYPE",
+            WordListGenerationError::SelectorNotPresent(_) => "E005_SELECTOR_NOT_PRESENT",
+            WordListGenerationError::WebPageNotFetched => "E006_WEB_PAGE_NOT_FETCHED",
+            WordListGenerationError::WebPageErrorfulStatusCode(_) => "E007_WEB_PAGE_ERROR_STATUS",
+            WordListGenerationError::UnknownContentType => "E008_UNKNOWN_CONTENT_TYPE",
+            WordListGenerationError::EmptyWordList => "E009_EMPTY_WORD_LIST",
+            WordListGenerationError::FeatureNotAvailable => "E010_FEATURE_NOT_AVAILABLE",
+            WordListGenerationError::MalformedHtml(_) => "E011_MALFORMED_HTML",
+            WordListGenerationError::InvalidFrontmatter(..) => "E012_INVALID_FRONTMATTER",
+            WordListGenerationError::DeniedWarning(_) => "E013_DENIED_WARNING",
+        }
+    }
+
+    /// Where in the document the error was found, if Stork can tell.
+    pub fn location(&self) -> Option<SourceLocation> {
+        match self {
+            WordListGenerationError::InvalidSRT(location) => *location,
+            WordListGenerationError::InvalidFrontmatter(_, location) => Some(*location),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Something about a document that doesn't stop it from being indexed, but
+ * probably isn't what the author intended.
+ */
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum DocumentWarningKind {
+    #[error("The document only contains {0} words.")]
+    FewWords(usize),
+
+    #[error("Another document, `{other_source}`, is also titled `{title}`.")]
+    DuplicateTitle { title: String, other_source: String },
+
+    #[error(
+        "The document has {word_count} words, fewer than the excerpt buffer of {excerpt_buffer}."
+    )]
+    ExcerptBufferExceedsDocument {
+        word_count: usize,
+        excerpt_buffer: u8,
+    },
+
+    /// The document wasn't indexed, so that its URL only leads to one result.
+    #[error(
+        "Another document, `{other_source}`, has the same URL `{url}`, so this one wasn't indexed."
+    )]
+    DuplicateUrl { url: String, other_source: String },
+
+    /// Like `DuplicateUrl`, for a URL that's only the same as the other
+    /// document's once both are rewritten by `input.url_canonicalization`.
+    #[error(
+        "Another document, `{other_source}`, has the URL `{other_url}`, which is the same as `{url}` once both are canonicalized as `input.url_canonicalization` says to, so this one wasn't indexed."
+    )]
+    DuplicateCanonicalUrl {
+        url: String,
+        other_url: String,
+        other_source: String,
+    },
+
+    /// Only reported when `input.detect_language` is on.
+    #[error(
+        "Stork couldn't confidently detect a language it can stem, so the document was stemmed according to `input.stemming`."
+    )]
+    LanguageNotDetected,
+
+    /// Only reported when `input.json_ld_properties` is set. The blocks are
+    /// skipped, and the rest of the document is indexed.
+    #[error("The document has {0} JSON-LD blocks that aren't valid JSON, so they were skipped.")]
+    MalformedJsonLd(usize),
+
+    /// The document is indexed without a thumbnail.
+    #[error("The thumbnail `{0}` isn't a path or an http(s) URL of at most 2048 characters, so it wasn't stored.")]
+    InvalidThumbnail(String),
+
+    /// The document is indexed without a date, so searches limited to a
+    /// range of dates treat it as undated.
+    #[error("The date `{0}` isn't of the form YYYY-MM-DD, so it wasn't stored.")]
+    InvalidDate(String),
+
+    /// The words are indexed as their first `maximum_token_length`
+    /// characters.
+    #[error("The document has {count} words longer than the `maximum_token_length` of {maximum_token_length} characters, so they were cut short.")]
+    LongWordsTruncated {
+        count: usize,
+        maximum_token_length: usize,
+    },
+
+    /// The document is indexed, but searches won't find the words after
+    /// the limit.
+    #[error("The document has {word_count} words, more than the `maximum_words_per_document` of {maximum_words_per_document}, so the rest weren't indexed.")]
+    TooManyWords {
+        word_count: usize,
+        maximum_words_per_document: usize,
+    },
+}
+
+impl DocumentWarningKind {
+    /// A stable identifier for this kind of warning, like `WordListGenerationError::code`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            DocumentWarningKind::FewWords(_) => "W001_FEW_WORDS",
+            DocumentWarningKind::DuplicateTitle { .. } => "W002_DUPLICATE_TITLE",
+            DocumentWarningKind::ExcerptBufferExceedsDocument { .. } => {
+                "W003_EXCERPT_BUFFER_EXCEEDS_DOCUMENT"
+            }
+            DocumentWarningKind::DuplicateUrl { .. } => "W004_DUPLICATE_URL",
+            DocumentWarningKind::LanguageNotDetected => "W005_LANGUAGE_NOT_DETECTED",
+            DocumentWarningKind::MalformedJsonLd(_) => "W006_MALFORMED_JSON_LD",
+            DocumentWarningKind::InvalidThumbnail(_) => "W007_INVALID_THUMBNAIL",
+            DocumentWarningKind::InvalidDate(_) => "W008_INVALID_DATE",
+            DocumentWarningKind::LongWordsTruncated { .. } => "W009_LONG_WORDS_TRUNCATED",
+            DocumentWarningKind::TooManyWords { .. } => "W010_TOO_MANY_WORDS",
+            DocumentWarningKind::DuplicateCanonicalUrl { .. } => "W011_DUPLICATE_CANONICAL_URL",
+        }
+    }
+}
+
+pub(super) fn pluralize_with_count(count: usize, singular: &str, plural: &str) -> String {
     format!("{} {}", count, if count == 1 { singular } else { plural })
 }
 
+/**
+ * An error that stops an index from being built.
+ *
+ * Large groups of document errors are summarized when displayed; use the
+ * alternate form (`{:#}`) to list every document.
+ */
 #[derive(Debug, Error)]
 pub enum IndexGenerationError {
-    #[error("No files specified in config file")]
     NoFilesSpecified,
-
-    #[error("All files failed to be indexed.\n{}", DocumentError::display_list(.0))]
     AllDocumentErrors(Vec<DocumentError>),
-
-    #[allow(clippy::all)]
-    #[error(
-        "{} found while indexing files. If you want to fail silently and still build an index, remove `break_on_file_error` from your config.\n{}", 
-        pluralize_with_count(.0.len(), "error", "errors"),
-        DocumentError::display_list(.0)
-    )]
     PartialDocumentErrors(Vec<DocumentError>),
+    ThreadPoolNotCreated(String),
+    IndexTooLarge {
+        max_bytes: usize,
+        pruned_bytes: usize,
+    },
+
+    /// The config names a stemming language that Stork can't stem, and
+    /// `input.unsupported_stemming_language` is `error`.
+    UnsupportedStemmingLanguage(String),
+
+    /// The index would have more than `input.maximum_unique_terms` search
+    /// terms. The documents with the most terms of their own are listed,
+    /// most first, with how many each has.
+    TooManyTerms {
+        term_count: usize,
+        maximum_unique_terms: usize,
+        worst_documents: Vec<(String, usize)>,
+    },
+
+    /// One of `input.boilerplate_patterns` isn't a valid regular expression.
+    InvalidBoilerplatePattern {
+        pattern: String,
+        message: String,
+    },
+
+    /// The file named by `input.urls_file` couldn't be read.
+    UnreadableUrlsFile {
+        path: String,
+        message: String,
+    },
+
+    /// One of `input.cookies`, or the file named by `input.cookie_file`,
+    /// couldn't be loaded, so no web pages were fetched.
+    InvalidCookies(String),
+
+    /// Work the build handed to its thread pool didn't finish, because it
+    /// panicked or because the async runtime running it shut down.
+    BuildTaskFailed(String),
+}
+
+impl fmt::Display for IndexGenerationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let display_list = if f.alternate() {
+            DocumentError::display_full_list
+        } else {
+            DocumentError::display_list
+        };
+
+        match self {
+            IndexGenerationError::NoFilesSpecified => write!(f, "No files specified in config file"),
+            IndexGenerationError::AllDocumentErrors(errors) => {
+                write!(f, "All files failed to be indexed.\n{}", display_list(errors))
+            }
+            IndexGenerationError::PartialDocumentErrors(errors) => write!(
+                f,
+                "{} found while indexing files. If you want to fail silently and still build an index, remove `break_on_file_error` from your config.\n{}",
+                pluralize_with_count(errors.len(), "error", "errors"),
+                display_list(errors)
+            ),
+            IndexGenerationError::ThreadPoolNotCreated(message) => write!(
+                f,
+                "Couldn't start the threads used to build the index: {}",
+                message
+            ),
+            IndexGenerationError::IndexTooLarge {
+                max_bytes,
+                pruned_bytes,
+            } => write!(
+                f,
+                "The index is still {} bytes after removing every search term, which is more than the `max_index_bytes` limit of {} bytes. Raise the limit, or index fewer documents.",
+                pruned_bytes, max_bytes
+            ),
+            IndexGenerationError::UnsupportedStemmingLanguage(language) => write!(
+                f,
+                "Stork can't stem `{}`. Use `none` or a language supported by https://snowballstem.org/, e.g. `Dutch`, or set `input.unsupported_stemming_language` to `fallback` to index it without stemming.",
+                language
+            ),
+            IndexGenerationError::TooManyTerms {
+                term_count,
+                maximum_unique_terms,
+                worst_documents,
+            } => write!(
+                f,
+                "The index would have {} search terms, more than the `maximum_unique_terms` limit of {}. The documents with the most terms are {}. Check that they're meant to be indexed, or raise the limit.",
+                term_count,
+                maximum_unique_terms,
+                worst_documents
+                    .iter()
+                    .map(|(document, count)| format!(
+                        "`{}` ({})",
+                        document,
+                        pluralize_with_count(*count, "term", "terms")
+                    ))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            IndexGenerationError::InvalidBoilerplatePattern { pattern, message } => write!(
+                f,
+                "`{}` in `input.boilerplate_patterns` isn't a valid regular expression: {}",
+                pattern, message
+            ),
+            IndexGenerationError::UnreadableUrlsFile { path, message } => write!(
+                f,
+                "The URLs file `{}` named by `input.urls_file` couldn't be read: {}",
+                path, message
+            ),
+            IndexGenerationError::InvalidCookies(message) => write!(
+                f,
+                "The cookies for fetching web pages couldn't be loaded: {}",
+                message
+            ),
+            IndexGenerationError::BuildTaskFailed(message) => {
+                write!(f, "Stork stopped building the index: {}", message)
+            }
+        }
+    }
+}
+
+impl IndexGenerationError {
+    /// The per-document errors that caused the build to fail, if any.
+    pub fn document_errors(&self) -> &[DocumentError] {
+        match self {
+            IndexGenerationError::AllDocumentErrors(errors)
+            | IndexGenerationError::PartialDocumentErrors(errors) => errors,
+            IndexGenerationError::NoFilesSpecified
+            | IndexGenerationError::ThreadPoolNotCreated(_)
+            | IndexGenerationError::IndexTooLarge { .. }
+            | IndexGenerationError::UnsupportedStemmingLanguage(_)
+            | IndexGenerationError::TooManyTerms { .. }
+            | IndexGenerationError::InvalidBoilerplatePattern { .. }
+            | IndexGenerationError::UnreadableUrlsFile { .. }
+            | IndexGenerationError::InvalidCookies(_)
+            | IndexGenerationError::BuildTaskFailed(_) => &[],
+        }
+    }
 }
 
 impl PartialEq for IndexGenerationError {
@@ -73,30 +360,224 @@ pub struct DocumentError {
     pub word_list_generation_error: WordListGenerationError,
 }
 
+impl From<&DocumentError> for StoredDocumentError {
+    fn from(document_error: &DocumentError) -> Self {
+        StoredDocumentError {
+            file: document_error.file.to_string(),
+            code: document_error.word_list_generation_error.code().to_string(),
+            message: document_error.word_list_generation_error.to_string(),
+        }
+    }
+}
+
 impl std::fmt::Display for DocumentError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "In file `{}`: {}",
+            "In file `{}`: {} ({}",
             self.file,
             self.word_list_generation_error.to_string(),
+            self.code(),
+        )?;
+
+        match self.location() {
+            Some(location) => write!(f, " at {})", location),
+            None => write!(f, ")"),
+        }
+    }
+}
+
+/**
+ * Associates a `DocumentWarningKind` with a `File`.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentWarning {
+    pub file: File,
+    pub kind: DocumentWarningKind,
+}
+
+impl std::fmt::Display for DocumentWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "In file `{}`: {} ({})",
+            self.file,
+            self.kind,
+            self.kind.code()
         )
     }
 }
 
+impl Serialize for DocumentWarning {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedDocumentError {
+            code: self.kind.code(),
+            title: &self.file.title,
+            source: self.file.to_string(),
+            message: self.kind.to_string(),
+            location: None,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl DocumentWarning {
+    pub fn display_list(vec: &[DocumentWarning]) -> String {
+        format!(
+            "Warning: Stork found {} while indexing:\n",
+            pluralize_with_count(vec.len(), "possible problem", "possible problems"),
+        ) + &vec
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/**
+ * The shape a `DocumentError` or `DocumentWarning` takes when it's
+ * serialized, e.g. for `stork build --errors-json`.
+ */
+#[derive(Serialize)]
+struct SerializedDocumentError<'a> {
+    code: &'static str,
+    title: &'a str,
+    source: String,
+    message: String,
+    location: Option<SourceLocation>,
+}
+
+impl Serialize for DocumentError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedDocumentError {
+            code: self.code(),
+            title: &self.file.title,
+            source: self.file.to_string(),
+            message: self.word_list_generation_error.to_string(),
+            location: self.location(),
+        }
+        .serialize(serializer)
+    }
+}
+
 impl DocumentError {
+    pub fn code(&self) -> &'static str {
+        self.word_list_generation_error.code()
+    }
+
+    pub fn location(&self) -> Option<SourceLocation> {
+        self.word_list_generation_error.location()
+    }
+
+    /**
+     * Lists the errors grouped by kind. Only the first few documents of each
+     * group are named; see `display_full_list` to name all of them.
+     */
     pub fn display_list(vec: &[DocumentError]) -> String {
+        Self::display_grouped_list(vec, Some(DOCUMENTS_LISTED_PER_GROUP))
+    }
+
+    /// Lists the errors grouped by kind, naming every document.
+    pub fn display_full_list(vec: &[DocumentError]) -> String {
+        Self::display_grouped_list(vec, None)
+    }
+
+    fn display_grouped_list(vec: &[DocumentError], limit: Option<usize>) -> String {
         format!(
             "Warning: Stork couldn't include {} in the index because of the following errors:\n",
             pluralize_with_count(vec.len(), "file", "files"),
-        ) + &vec
+        ) + &group_document_errors(vec)
             .iter()
-            .map(ToString::to_string)
+            .map(|group| group.display(limit))
             .collect::<Vec<String>>()
             .join("\n")
     }
 }
 
+/// How many documents are named for each kind of error before the rest are
+/// summarized.
+const DOCUMENTS_LISTED_PER_GROUP: usize = 5;
+
+/**
+ * Document errors that share an error code.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentErrorGroup {
+    pub code: &'static str,
+    pub errors: Vec<DocumentError>,
+}
+
+impl DocumentErrorGroup {
+    /// The message of every error in the group, if they're all the same.
+    pub fn shared_message(&self) -> Option<String> {
+        let mut messages = self
+            .errors
+            .iter()
+            .map(|error| error.word_list_generation_error.to_string());
+        let first = messages.next()?;
+        if messages.all(|message| message == first) {
+            Some(first)
+        } else {
+            None
+        }
+    }
+
+    fn display(&self, limit: Option<usize>) -> String {
+        if let [error] = self.errors.as_slice() {
+            return error.to_string();
+        }
+
+        let shared_message = self.shared_message();
+        let mut lines = vec![format!(
+            "{} in {}:{}",
+            self.code,
+            pluralize_with_count(self.errors.len(), "file", "files"),
+            shared_message
+                .as_ref()
+                .map(|message| format!(" {}", message))
+                .unwrap_or_default()
+        )];
+
+        let listed_count = limit.unwrap_or(self.errors.len()).min(self.errors.len());
+        for error in &self.errors[..listed_count] {
+            let mut line = format!("  - In file `{}`", error.file);
+            if shared_message.is_none() {
+                line.push_str(&format!(": {}", error.word_list_generation_error));
+            }
+            if let Some(location) = error.location() {
+                line.push_str(&format!(" (at {})", location));
+            }
+            lines.push(line);
+        }
+
+        if listed_count < self.errors.len() {
+            lines.push(format!("  - and {} more", self.errors.len() - listed_count));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/**
+ * Groups document errors by their code. Groups are ordered by where their
+ * first error appears, and errors keep their order within each group.
+ */
+pub fn group_document_errors(errors: &[DocumentError]) -> Vec<DocumentErrorGroup> {
+    let mut groups: Vec<DocumentErrorGroup> = vec![];
+
+    for error in errors {
+        match groups.iter_mut().find(|group| group.code == error.code()) {
+            Some(group) => group.errors.push(error.clone()),
+            None => groups.push(DocumentErrorGroup {
+                code: error.code(),
+                errors: vec![error.clone()],
+            }),
+        }
+    }
+
+    groups
+}
+
 #[cfg(test)]
 mod tests {
     use crate::config::DataSource;
@@ -118,10 +599,74 @@ mod tests {
         }
         .to_string();
 
-        let expected = "In file `My Test File`: The file `/test` could not be found.";
+        let expected =
+            "In file `My Test File`: The file `/test` could not be found. (E002_FILE_NOT_FOUND)";
+        assert_eq!(computed, expected);
+    }
+
+    #[test]
+    fn test_documenterror_display_with_location() {
+        let computed = DocumentError {
+            file: File {
+                title: "Subtitles".to_string(),
+                explicit_source: Some(DataSource::FilePath("subtitles.srt".to_string())),
+                ..Default::default()
+            },
+            word_list_generation_error: WordListGenerationError::InvalidSRT(Some(SourceLocation {
+                line: 6,
+                column: 1,
+            })),
+        }
+        .to_string();
+
+        let expected = "In file `subtitles.srt`: SRT file could not be parsed. (E001_INVALID_SRT at line 6, column 1)";
         assert_eq!(computed, expected);
     }
 
+    #[test]
+    fn test_documentwarning_display() {
+        let computed = DocumentWarning {
+            file: File {
+                title: "Stub".to_string(),
+                explicit_source: Some(DataSource::FilePath("stub.md".to_string())),
+                ..Default::default()
+            },
+            kind: DocumentWarningKind::FewWords(3),
+        }
+        .to_string();
+
+        assert_eq!(
+            computed,
+            "In file `stub.md`: The document only contains 3 words. (W001_FEW_WORDS)"
+        );
+    }
+
+    #[test]
+    fn test_documenterror_serializes_structurally() {
+        let error = DocumentError {
+            file: File {
+                title: "Subtitles".to_string(),
+                explicit_source: Some(DataSource::FilePath("subtitles.srt".to_string())),
+                ..Default::default()
+            },
+            word_list_generation_error: WordListGenerationError::InvalidSRT(Some(SourceLocation {
+                line: 6,
+                column: 1,
+            })),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "E001_INVALID_SRT",
+                "title": "Subtitles",
+                "source": "subtitles.srt",
+                "message": "SRT file could not be parsed.",
+                "location": { "line": 6, "column": 1 }
+            })
+        );
+    }
+
     #[test]
     fn test_documenterror_list_display() {
         let computed = DocumentError::display_list(&[
@@ -147,7 +692,71 @@ mod tests {
             },
         ]);
 
-        let expected = "Warning: Stork couldn't include 2 files in the index because of the following errors:\nIn file `My Test File`: The file `/test` could not be found.\nIn file `My Test File 2`: The file `/test2` could not be found.";
+        let expected = "Warning: Stork couldn't include 2 files in the index because of the following errors:\nE002_FILE_NOT_FOUND in 2 files:\n  - In file `My Test File`: The file `/test` could not be found.\n  - In file `My Test File 2`: The file `/test2` could not be found.";
         assert_eq!(computed, expected);
     }
+
+    fn document_error(title: &str, error: WordListGenerationError) -> DocumentError {
+        DocumentError {
+            file: File {
+                title: title.to_string(),
+                explicit_source: Some(DataSource::Contents("ignored".to_string())),
+                ..Default::default()
+            },
+            word_list_generation_error: error,
+        }
+    }
+
+    #[test]
+    fn mixed_errors_are_grouped_by_code() {
+        let errors = vec![
+            document_error("One", WordListGenerationError::EmptyWordList),
+            document_error("Two", WordListGenerationError::WebPageNotFetched),
+            document_error("Three", WordListGenerationError::EmptyWordList),
+        ];
+
+        let groups = group_document_errors(&errors);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].code, "E009_EMPTY_WORD_LIST");
+        assert_eq!(groups[0].errors, vec![errors[0].clone(), errors[2].clone()]);
+        assert_eq!(groups[1].code, "E006_WEB_PAGE_NOT_FETCHED");
+        assert_eq!(groups[1].errors, vec![errors[1].clone()]);
+    }
+
+    #[test]
+    fn singleton_groups_display_like_a_single_error() {
+        let errors = vec![document_error(
+            "One",
+            WordListGenerationError::WebPageNotFetched,
+        )];
+
+        let expected = "Warning: Stork couldn't include 1 file in the index because of the following errors:\nIn file `One`: The web page could not be fetched (E006_WEB_PAGE_NOT_FETCHED)";
+        assert_eq!(DocumentError::display_list(&errors), expected);
+    }
+
+    #[test]
+    fn large_groups_are_summarized() {
+        let errors: Vec<DocumentError> = (1..=7)
+            .map(|n| {
+                document_error(
+                    &format!("Doc {}", n),
+                    WordListGenerationError::EmptyWordList,
+                )
+            })
+            .collect();
+
+        let expected = "Warning: Stork couldn't include 7 files in the index because of the following errors:
+E009_EMPTY_WORD_LIST in 7 files: After parsing the document, there were no words found in the word list.
+  - In file `Doc 1`
+  - In file `Doc 2`
+  - In file `Doc 3`
+  - In file `Doc 4`
+  - In file `Doc 5`
+  - and 2 more";
+        assert_eq!(DocumentError::display_list(&errors), expected);
+
+        let full_list = DocumentError::display_full_list(&errors);
+        assert!(full_list.contains("  - In file `Doc 7`"));
+        assert!(!full_list.contains("more"));
+    }
 }
@@ -0,0 +1,382 @@
+use std::fmt::Display;
+
+use bytes::Bytes;
+use num_format::{Locale, ToFormattedString};
+
+use crate::config::{Config, File, Filetype};
+
+use super::{
+    build, fill_intermediate_entries::expected_filetype, urls_file::with_urls_file, BuildResult,
+};
+use super::{errors::IndexGenerationError, Entry};
+
+/**
+ * The parts of an index that a `SizeEstimate` is broken down into.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeContributor {
+    /// The stored text of each document, which excerpts are cut from.
+    ExcerptText,
+
+    /// The search terms and where in each document they're found, including
+    /// any n-grams for substring search.
+    Postings,
+
+    /// Each document's title and URL.
+    Titles,
+
+    /// Everything else: fields, stored errors, settings, and the overhead of
+    /// serializing it all.
+    Other,
+}
+
+const CONTRIBUTORS: [SizeContributor; 4] = [
+    SizeContributor::ExcerptText,
+    SizeContributor::Postings,
+    SizeContributor::Titles,
+    SizeContributor::Other,
+];
+
+impl Display for SizeContributor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SizeContributor::ExcerptText => "excerpt text",
+            SizeContributor::Postings => "postings",
+            SizeContributor::Titles => "titles and URLs",
+            SizeContributor::Other => "other",
+        })
+    }
+}
+
+/**
+ * How large an index is likely to be, estimated by building an index from a
+ * sample of its documents.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeEstimate {
+    pub document_count: usize,
+    pub sampled_document_count: usize,
+
+    /// The estimated size of the index file, in bytes.
+    pub estimated_bytes: usize,
+
+    /// Two standard errors either side of `estimated_bytes`, from how much
+    /// the sizes of the sampled documents vary. When every document was
+    /// sampled, both are `estimated_bytes`.
+    pub low_bytes: usize,
+    pub high_bytes: usize,
+
+    /// The estimated bytes of each part of the index, largest first.
+    pub contributors: Vec<(SizeContributor, usize)>,
+}
+
+impl Display for SizeEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Estimated index size: {} bytes (likely between {} and {}), from {} of {} documents",
+            self.estimated_bytes.to_formatted_string(&Locale::en),
+            self.low_bytes.to_formatted_string(&Locale::en),
+            self.high_bytes.to_formatted_string(&Locale::en),
+            self.sampled_document_count.to_formatted_string(&Locale::en),
+            self.document_count.to_formatted_string(&Locale::en),
+        )?;
+
+        for (contributor, bytes) in &self.contributors {
+            write!(
+                f,
+                "\n  - {} bytes of {} ({}%)",
+                bytes.to_formatted_string(&Locale::en),
+                contributor,
+                bytes * 100 / self.estimated_bytes.max(1)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The bytes each document adds to the index, split by `CONTRIBUTORS`.
+type DocumentSize = [f64; 4];
+
+/**
+ * Estimates the size of the index `config` would build, by building an index
+ * from up to `max_sample_documents` of its documents, spread evenly through
+ * the config, with the usual readers and tokenizers.
+ *
+ * Each document that wasn't sampled is assumed to add as many bytes as the
+ * sampled documents of the same filetype did, on average. Sampled documents
+ * that failed to index count as adding nothing. Postings are extrapolated
+ * linearly, though a corpus's vocabulary grows more slowly than its length,
+ * so the estimate leans high for corpora much larger than the sample.
+ */
+pub fn estimate_index_size(
+    config: &Config,
+    max_sample_documents: usize,
+) -> Result<SizeEstimate, IndexGenerationError> {
+    let config = &*with_urls_file(config)?;
+    let files = &config.input.files;
+    if files.is_empty() {
+        return Err(IndexGenerationError::NoFilesSpecified);
+    }
+
+    let sampled = sample_indices(files.len(), max_sample_documents.max(1));
+
+    let mut sample_config = config.clone();
+    sample_config.input.files = sampled.iter().map(|&i| files[i].clone()).collect();
+    sample_config.output.max_index_bytes = None;
+    let sample = build(&sample_config)?;
+
+    let sample_sizes = document_sizes(&sample_config.input.files, &sample);
+
+    // The sampled documents' sizes, grouped by the filetype they'd be read as.
+    let mut groups: Vec<(Option<Filetype>, Vec<DocumentSize>)> = vec![];
+    for (file, size) in sample_config.input.files.iter().zip(&sample_sizes) {
+        let filetype = expected_filetype(file, config.input.default_filetype.as_ref());
+        match groups.iter_mut().find(|(group, _)| *group == filetype) {
+            Some((_, sizes)) => sizes.push(*size),
+            None => groups.push((filetype, vec![*size])),
+        }
+    }
+
+    let mut totals: DocumentSize = [0.0; 4];
+    for size in &sample_sizes {
+        add(&mut totals, size);
+    }
+
+    // How many unsampled documents are extrapolated from each group, with
+    // documents of a filetype that wasn't sampled extrapolated from all of
+    // them.
+    let mut unsampled_counts = vec![0usize; groups.len()];
+    let mut unsampled_without_group = 0;
+    for (_, file) in files
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| sampled.binary_search(i).is_err())
+    {
+        let filetype = expected_filetype(file, config.input.default_filetype.as_ref());
+        match groups.iter().position(|(group, _)| *group == filetype) {
+            Some(position) => unsampled_counts[position] += 1,
+            None => unsampled_without_group += 1,
+        }
+    }
+
+    let mut variance = 0.0;
+    for ((_, sizes), &count) in groups.iter().zip(&unsampled_counts) {
+        extrapolate(sizes, &sample_sizes, count, &mut totals, &mut variance);
+    }
+    extrapolate(
+        &sample_sizes,
+        &sample_sizes,
+        unsampled_without_group,
+        &mut totals,
+        &mut variance,
+    );
+
+    let estimated_bytes = totals.iter().sum::<f64>();
+    let margin = 2.0 * variance.sqrt();
+
+    let mut contributors: Vec<(SizeContributor, usize)> = CONTRIBUTORS
+        .iter()
+        .copied()
+        .zip(totals.iter().map(|&bytes| bytes.round() as usize))
+        .collect();
+    contributors.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    Ok(SizeEstimate {
+        document_count: files.len(),
+        sampled_document_count: sampled.len(),
+        estimated_bytes: estimated_bytes.round() as usize,
+        low_bytes: (estimated_bytes - margin).max(0.0).round() as usize,
+        high_bytes: (estimated_bytes + margin).round() as usize,
+        contributors,
+    })
+}
+
+/// The indexes of `sample_size` documents spread evenly through `count`, in
+/// order.
+fn sample_indices(count: usize, sample_size: usize) -> Vec<usize> {
+    if count <= sample_size {
+        return (0..count).collect();
+    }
+
+    (0..sample_size).map(|i| i * count / sample_size).collect()
+}
+
+/**
+ * Splits the sample index's size between its documents. Postings are shared
+ * out by how many words each document has, and anything that isn't text or
+ * postings is shared out equally.
+ */
+fn document_sizes(files: &[File], sample: &BuildResult) -> Vec<DocumentSize> {
+    let index = &sample.index;
+    let total_bytes = Bytes::from(index).len();
+    let postings_bytes = rmp_serde::to_vec(&index.containers).unwrap().len()
+        + rmp_serde::to_vec(&index.terms).unwrap().len()
+        + index.ngram_size_bytes();
+    let text_bytes: usize = index.entries.iter().map(|entry| entry.contents.len()).sum();
+    let title_bytes: usize = index.entries.iter().map(title_and_url_bytes).sum();
+    let other_bytes = total_bytes.saturating_sub(postings_bytes + text_bytes + title_bytes);
+
+    let word_counts: Vec<usize> = index
+        .entries
+        .iter()
+        .map(|entry| entry.contents.split_whitespace().count())
+        .collect();
+    let total_words: usize = word_counts.iter().sum();
+    let entry_count = index.entries.len() as f64;
+
+    // Entries are in the same order as the files that indexed successfully.
+    let mut entries = index.entries.iter().zip(word_counts);
+    files
+        .iter()
+        .map(|file| {
+            if sample.errors.iter().any(|error| &error.file == file) {
+                return [0.0; 4];
+            }
+
+            let (entry, word_count) = match entries.next() {
+                Some(entry) => entry,
+                None => return [0.0; 4],
+            };
+
+            let postings_share = if total_words == 0 {
+                1.0 / entry_count
+            } else {
+                word_count as f64 / total_words as f64
+            };
+
+            [
+                entry.contents.len() as f64,
+                postings_bytes as f64 * postings_share,
+                title_and_url_bytes(entry) as f64,
+                other_bytes as f64 / entry_count,
+            ]
+        })
+        .collect()
+}
+
+fn title_and_url_bytes(entry: &Entry) -> usize {
+    entry.title.len() + entry.url.len()
+}
+
+/**
+ * Adds `count` documents the size of the average of `sizes` to `totals`, and
+ * the variance that adds to the estimate to `variance`. A group of fewer than
+ * two documents uses the spread of the whole sample.
+ */
+fn extrapolate(
+    sizes: &[DocumentSize],
+    sample_sizes: &[DocumentSize],
+    count: usize,
+    totals: &mut DocumentSize,
+    variance: &mut f64,
+) {
+    if count == 0 || sizes.is_empty() {
+        return;
+    }
+
+    let mut mean: DocumentSize = [0.0; 4];
+    for size in sizes {
+        add(&mut mean, size);
+    }
+    for part in &mut mean {
+        *part /= sizes.len() as f64;
+    }
+
+    for (total, part) in totals.iter_mut().zip(&mean) {
+        *total += part * count as f64;
+    }
+
+    let spread = if sizes.len() >= 2 {
+        sizes
+    } else {
+        sample_sizes
+    };
+    let count = count as f64;
+    *variance += count * count * sample_variance(spread) / sizes.len() as f64;
+}
+
+fn add(totals: &mut DocumentSize, size: &DocumentSize) {
+    for (total, part) in totals.iter_mut().zip(size) {
+        *total += part;
+    }
+}
+
+/// The sample variance of the documents' total sizes.
+fn sample_variance(sizes: &[DocumentSize]) -> f64 {
+    if sizes.len() < 2 {
+        return 0.0;
+    }
+
+    let totals: Vec<f64> = sizes.iter().map(|size| size.iter().sum()).collect();
+    let mean = totals.iter().sum::<f64>() / totals.len() as f64;
+    totals
+        .iter()
+        .map(|total| (total - mean).powi(2))
+        .sum::<f64>()
+        / (totals.len() - 1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{DataSource, InputConfig};
+    use pretty_assertions::assert_eq;
+
+    fn file(i: usize) -> File {
+        File {
+            explicit_source: Some(DataSource::Contents(format!(
+                "Document number {} talks about {} things in some detail.",
+                i,
+                i * 7
+            ))),
+            title: format!("Document {}", i),
+            url: format!("/docs/{}", i),
+            ..Default::default()
+        }
+    }
+
+    fn config(count: usize) -> Config {
+        Config {
+            input: InputConfig {
+                files: (0..count).map(file).collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn samples_are_spread_through_the_documents() {
+        assert_eq!(sample_indices(3, 5), vec![0, 1, 2]);
+        assert_eq!(sample_indices(10, 4), vec![0, 2, 5, 7]);
+    }
+
+    #[test]
+    fn estimate_of_a_fully_sampled_corpus_is_exact() {
+        let config = config(6);
+        let actual = Bytes::from(&build(&config).unwrap().index).len();
+
+        let estimate = estimate_index_size(&config, 10).unwrap();
+        assert_eq!(estimate.sampled_document_count, 6);
+        assert_eq!(estimate.estimated_bytes, actual);
+        assert_eq!((estimate.low_bytes, estimate.high_bytes), (actual, actual));
+    }
+
+    #[test]
+    fn estimate_from_a_sample_is_near_the_real_size() {
+        let config = config(60);
+        let actual = Bytes::from(&build(&config).unwrap().index).len();
+
+        let estimate = estimate_index_size(&config, 20).unwrap();
+        assert_eq!(estimate.sampled_document_count, 20);
+        assert!(estimate.low_bytes <= estimate.estimated_bytes);
+        assert!(estimate.estimated_bytes <= estimate.high_bytes);
+
+        // Shared prefixes and stems make the sample's postings per document
+        // a little larger than the full index's, but not by much.
+        let error = (estimate.estimated_bytes as f64 - actual as f64).abs() / actual as f64;
+        assert!(error < 0.25, "estimated {:?}, actual {}", estimate, actual);
+    }
+}
@@ -0,0 +1,286 @@
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+
+use crate::config::{DataSource, ExcludeConfig, File};
+
+use super::intermediate_entry::NormalizedEntry;
+
+/**
+ * The rule of `input.exclude` that left a document out of the index.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExclusionRule {
+    PathGlob(String),
+    UrlPattern(String),
+    MinimumWordCount(usize),
+}
+
+/**
+ * A document that was read, but left out of the index by `input.exclude`.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExcludedDocument {
+    pub file: File,
+    pub rule: ExclusionRule,
+}
+
+impl ExclusionRule {
+    /// Whether the rule names the document itself, rather than judging its
+    /// contents, so the document could have been left out of `input.files`.
+    pub(super) fn matches_listing(&self) -> bool {
+        !matches!(self, ExclusionRule::MinimumWordCount(_))
+    }
+}
+
+impl fmt::Display for ExclusionRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExclusionRule::PathGlob(glob) => write!(f, "its path matches `{}`", glob),
+            ExclusionRule::UrlPattern(pattern) => write!(f, "its URL matches `{}`", pattern),
+            ExclusionRule::MinimumWordCount(count) => {
+                write!(f, "it has fewer than {} words", count)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ExcludedDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.file, self.rule)
+    }
+}
+
+#[derive(Serialize)]
+struct SerializedExcludedDocument<'a> {
+    title: &'a str,
+    source: String,
+    rule: String,
+}
+
+impl Serialize for ExcludedDocument {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedExcludedDocument {
+            title: &self.file.title,
+            source: self.file.to_string(),
+            rule: self.rule.to_string(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/**
+ * The first rule of `exclude` that applies to a document, checking its path,
+ * then its URL, then its word count.
+ */
+pub(super) fn exclusion_rule(
+    exclude: &ExcludeConfig,
+    file: &File,
+    entry: &NormalizedEntry,
+) -> Option<ExclusionRule> {
+    if let DataSource::FilePath(path) = file.source() {
+        let path = path.trim_start_matches("./");
+        if let Some(glob) = exclude
+            .path_globs
+            .iter()
+            .find(|glob| glob_matches(glob, path))
+        {
+            return Some(ExclusionRule::PathGlob(glob.clone()));
+        }
+    }
+
+    if let Some(pattern) = exclude
+        .url_patterns
+        .iter()
+        .find(|pattern| glob_matches(pattern, &entry.url))
+    {
+        return Some(ExclusionRule::UrlPattern(pattern.clone()));
+    }
+
+    match exclude.minimum_word_count {
+        Some(minimum) if entry.annotated_word_list.word_list.len() < minimum => {
+            Some(ExclusionRule::MinimumWordCount(minimum))
+        }
+        _ => None,
+    }
+}
+
+/// Whether `text` matches all of `glob`, where `**` matches any characters,
+/// `*` matches any characters but `/`, and `?` matches any one character but
+/// `/`. A `**/` also matches nothing, so `**/README.md` matches `README.md`.
+fn glob_matches(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches_from(&glob, &text)
+}
+
+fn matches_from(glob: &[char], text: &[char]) -> bool {
+    match glob {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            matches_from(rest, text)
+                || (0..text.len())
+                    .filter(|&position| text[position] == '/')
+                    .any(|position| matches_from(rest, &text[position + 1..]))
+        }
+        ['*', '*', rest @ ..] => {
+            (0..=text.len()).any(|position| matches_from(rest, &text[position..]))
+        }
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&position| position == 0 || text[position - 1] != '/')
+            .any(|position| matches_from(rest, &text[position..])),
+        ['?', rest @ ..] => {
+            matches!(text.first(), Some(&c) if c != '/') && matches_from(rest, &text[1..])
+        }
+        [c, rest @ ..] => text.first() == Some(c) && matches_from(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::*;
+    use crate::index_v3::build::build;
+    use crate::index_v3::AnnotatedWordList;
+    use crate::AnnotatedWord;
+    use std::collections::HashMap;
+
+    #[test]
+    fn globs_match_within_and_across_directories() {
+        assert!(glob_matches("drafts/*.md", "drafts/post.md"));
+        assert!(!glob_matches("drafts/*.md", "drafts/2022/post.md"));
+        assert!(glob_matches("drafts/**", "drafts/2022/post.md"));
+        assert!(glob_matches("**/README.md", "README.md"));
+        assert!(glob_matches("**/README.md", "docs/api/README.md"));
+        assert!(!glob_matches("**/README.md", "docs/NOT_README.md"));
+        assert!(glob_matches("post-?.html", "post-1.html"));
+        assert!(!glob_matches("post-?.html", "post-10.html"));
+        assert!(glob_matches("/blog/*", "/blog/"));
+        assert!(!glob_matches("/blog/*", "/blog/2022/post"));
+    }
+
+    #[test]
+    fn the_first_applicable_rule_is_reported() {
+        let exclude = ExcludeConfig {
+            path_globs: vec!["drafts/**".to_string()],
+            url_patterns: vec!["/private/**".to_string()],
+            minimum_word_count: Some(3),
+        };
+        let file = |path: &str| File {
+            explicit_source: Some(DataSource::FilePath(path.to_string())),
+            ..Default::default()
+        };
+        let entry = |url: &str, word_count: usize| NormalizedEntry {
+            annotated_word_list: AnnotatedWordList {
+                word_list: vec![AnnotatedWord::new("word"); word_count],
+            },
+            stem_algorithm: None,
+            title: "Stub".to_string(),
+            url: url.to_string(),
+            fields: HashMap::new(),
+            keywords: vec![],
+            summary: None,
+            language_undetected: false,
+            malformed_json_ld_blocks: 0,
+            invalid_thumbnail: None,
+            invalid_date: None,
+            truncated_word_count: 0,
+            unlimited_word_count: None,
+            stored_text: None,
+            generate_excerpts: true,
+        };
+
+        assert_eq!(
+            exclusion_rule(&exclude, &file("./drafts/a.md"), &entry("/private/a", 1)),
+            Some(ExclusionRule::PathGlob("drafts/**".to_string()))
+        );
+        assert_eq!(
+            exclusion_rule(&exclude, &file("posts/a.md"), &entry("/private/a", 1)),
+            Some(ExclusionRule::UrlPattern("/private/**".to_string()))
+        );
+        assert_eq!(
+            exclusion_rule(&exclude, &file("posts/a.md"), &entry("/posts/a", 2)),
+            Some(ExclusionRule::MinimumWordCount(3))
+        );
+        assert_eq!(
+            exclusion_rule(&exclude, &file("posts/a.md"), &entry("/posts/a", 3)),
+            None
+        );
+    }
+
+    #[test]
+    fn exclusion_rules_leave_documents_out_and_report_them() {
+        use crate::config::ExcludeConfig;
+
+        let directory = std::env::temp_dir().join("stork-exclude-test");
+        std::fs::create_dir_all(directory.join("drafts")).unwrap();
+        let words = "Mount the telescope on the tripod and level it before use";
+        std::fs::write(directory.join("drafts/mount.txt"), words).unwrap();
+        std::fs::write(directory.join("guide.txt"), words).unwrap();
+
+        let path_file = |title: &str, path: &str| File {
+            explicit_source: Some(DataSource::FilePath(path.to_string())),
+            title: title.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        };
+
+        let result = build(&Config {
+            input: InputConfig {
+                base_directory: directory.to_string_lossy().to_string(),
+                files: vec![
+                    path_file("Draft", "drafts/mount.txt"),
+                    path_file("Guide", "./guide.txt"),
+                    File {
+                        explicit_source: Some(DataSource::Contents(words.to_string())),
+                        title: "Notes".to_string(),
+                        url: "/private/notes".to_string(),
+                        filetype: Some(Filetype::PlainText),
+                        ..Default::default()
+                    },
+                    File {
+                        explicit_source: Some(DataSource::Contents("Telescope".to_string())),
+                        title: "Stub".to_string(),
+                        filetype: Some(Filetype::PlainText),
+                        ..Default::default()
+                    },
+                ],
+                exclude: ExcludeConfig {
+                    path_globs: vec!["drafts/**".to_string()],
+                    url_patterns: vec!["/private/*".to_string()],
+                    minimum_word_count: Some(5),
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+
+        let titles: Vec<&str> = result
+            .index
+            .entries
+            .iter()
+            .map(|entry| entry.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["Guide"]);
+
+        assert_eq!(
+            result
+                .excluded
+                .iter()
+                .map(|excluded| (excluded.file.title.as_str(), excluded.rule.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Draft", ExclusionRule::PathGlob("drafts/**".to_string())),
+                ("Notes", ExclusionRule::UrlPattern("/private/*".to_string())),
+                ("Stub", ExclusionRule::MinimumWordCount(5)),
+            ]
+        );
+        assert!(result.errors.is_empty());
+        assert!(result.warnings.is_empty());
+
+        let nudge_codes: Vec<&str> = result.nudges.iter().map(|nudge| nudge.code).collect();
+        assert_eq!(nudge_codes, vec!["N009_LISTED_FILES_EXCLUDED"]);
+        assert!(result.nudges[0].message.starts_with("2 documents are"));
+    }
+}
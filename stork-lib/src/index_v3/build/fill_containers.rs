@@ -1,157 +1,479 @@
-use rust_stemmers::Stemmer;
-use std::{collections::BTreeMap, convert::TryInto, ops::Range};
+use rayon::prelude::*;
+use rust_stemmers::{Algorithm, Stemmer};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::TryInto,
+    ops::Range,
+};
 
 use crate::{
-    config::Config,
+    config::{AcronymPeriodHandling, Config},
     index_v3::{
-        scores::{PREFIX_SCORE, STEM_SCORE},
-        AnnotatedWord, Container, Excerpt, SearchResult, WordListSource,
+        acronyms::collapse_acronym_periods, scores::weighted_score, AnnotatedWord, Container,
+        EntryIndex, Excerpt, Score, SearchResult, WordListSource,
     },
+    InternalWordAnnotation, Tokenizer,
 };
 
 use super::{
-    annotated_words_from_string::AnnotatedWordable, intermediate_entry::NormalizedEntry,
-    remove_surrounding_punctuation,
+    annotated_words_from_string::{merge_spaced_letters, AnnotatedWordable},
+    code_identifiers::identifier_words,
+    intermediate_entry::NormalizedEntry,
+    interner::{Interner, Stems, Word},
+    normalize_word,
+    segmentation::{segment_words, split_emoji_words},
 };
 
+/**
+ * A `Container` whose aliases are keyed by interned words, used until every
+ * entry has been processed.
+ */
+#[derive(Default)]
+struct InternedContainer {
+    results: BTreeMap<EntryIndex, SearchResult>,
+    aliases: HashMap<Word, Score>,
+}
+
+/**
+ * Containers keyed by interned words, filled one batch of entries at a
+ * time.
+ *
+ * A word's stem aliases can only be added once every entry's stems are
+ * known, so they're added by `finish`. That doesn't change any alias
+ * scores: a word that's both a prefix alias and a stem alias in the same
+ * container keeps whichever score is higher, and every stem alias has the
+ * same score.
+ */
+#[derive(Default)]
+pub(super) struct InternedContainers {
+    containers: HashMap<Word, InternedContainer>,
+
+    /// The words that appeared in entries stemmed with each algorithm.
+    stemmed_words: Vec<(Algorithm, HashSet<Word>)>,
+}
+
+impl InternedContainers {
+    /**
+     * Fills the containers for each entry in parallel on the current rayon
+     * pool, then merges them in entry order. `first_entry_index` is the
+     * index of the first of `intermediate_entries` in the whole index.
+     */
+    pub(super) fn add_entries(
+        &mut self,
+        config: &Config,
+        intermediate_entries: &[NormalizedEntry],
+        first_entry_index: EntryIndex,
+        words: &Interner,
+    ) {
+        let containers_per_entry: Vec<(HashMap<Word, InternedContainer>, HashSet<Word>)> =
+            intermediate_entries
+                .par_iter()
+                .enumerate()
+                .map(|(offset, entry)| {
+                    let mut entry_containers = HashMap::new();
+                    let mut entry_words = HashSet::new();
+                    fill_containers_for_entry(
+                        config,
+                        first_entry_index + offset,
+                        entry,
+                        words,
+                        &mut entry_containers,
+                        &mut entry_words,
+                    );
+                    (entry_containers, entry_words)
+                })
+                .collect();
+
+        for (entry, (entry_containers, entry_words)) in
+            intermediate_entries.iter().zip(containers_per_entry)
+        {
+            for (word, entry_container) in entry_containers {
+                let container = self.containers.entry(word).or_default();
+
+                // Each entry only writes results under its own index.
+                container.results.extend(entry_container.results);
+
+                for (alias, score) in entry_container.aliases {
+                    container.aliases.entry(alias).or_insert(score);
+                }
+            }
+
+            if let Some(stem_algorithm) = entry.stem_algorithm {
+                match self
+                    .stemmed_words
+                    .iter_mut()
+                    .find(|(algorithm, _)| *algorithm == stem_algorithm)
+                {
+                    Some((_, stemmed_words)) => stemmed_words.extend(entry_words),
+                    None => self.stemmed_words.push((stem_algorithm, entry_words)),
+                }
+            }
+        }
+    }
+
+    /**
+     * Adds the stem aliases, then resolves every word back into a string,
+     * adding the result to `containers`.
+     */
+    pub(super) fn finish(
+        mut self,
+        config: &Config,
+        words: &Interner,
+        stems: &Stems,
+        containers: &mut BTreeMap<String, Container>,
+    ) {
+        let stem_score = weighted_score(config.output.match_weights.stem);
+        for (stem_algorithm, stemmed_words) in &self.stemmed_words {
+            let stemmer = Stemmer::create(*stem_algorithm);
+            for &word in stemmed_words {
+                fill_other_containers_alias_maps_with_reverse_stems(
+                    stem_score,
+                    &stemmer,
+                    words,
+                    stems,
+                    &mut self.containers,
+                    words.resolve(&word),
+                    word,
+                );
+            }
+        }
+
+        for (word, interned_container) in self.containers {
+            let container = containers
+                .entry(words.resolve(&word).to_string())
+                .or_insert_with(Container::new);
+
+            container.results.extend(interned_container.results);
+            container.aliases.extend(
+                interned_container
+                    .aliases
+                    .into_iter()
+                    .map(|(alias, score)| (words.resolve(&alias).to_string(), score)),
+            );
+        }
+    }
+}
+
+/**
+ * Fills the containers for every entry at once. The streaming build fills
+ * the same containers one batch of entries at a time.
+ */
 pub fn fill_containers(
     config: &Config,
     intermediate_entries: &[NormalizedEntry],
-    stems: &BTreeMap<String, Vec<String>>,
+    words: &Interner,
+    stems: &Stems,
     containers: &mut BTreeMap<String, Container>,
 ) {
-    for (entry_index, entry) in intermediate_entries.iter().enumerate() {
-        let words_in_title: Vec<AnnotatedWord> = entry.title.make_annotated_words();
-        let words_in_contents: Vec<AnnotatedWord> = entry.annotated_word_list.word_list.clone();
-
-        let word_lists = vec![
-            (WordListSource::Title, words_in_title),
-            (WordListSource::Contents, words_in_contents),
-        ];
-
-        for (source, word_list) in word_lists {
-            for (word_index, annotated_word) in word_list.iter().enumerate() {
-                let normalized_word =
-                    remove_surrounding_punctuation(&annotated_word.word.to_lowercase());
-
-                if normalized_word.is_empty() {
-                    continue;
+    let mut interned_containers = InternedContainers::default();
+    interned_containers.add_entries(config, intermediate_entries, 0, words);
+    interned_containers.finish(config, words, stems, containers);
+}
+
+/**
+ * The containers one entry fills on its own: its results, and the prefix
+ * aliases of its words. Stem aliases depend on the rest of the corpus, so
+ * they aren't included.
+ */
+pub(super) fn containers_for_entry(
+    config: &Config,
+    entry_index: EntryIndex,
+    entry: &NormalizedEntry,
+) -> BTreeMap<String, Container> {
+    let words = Interner::default();
+    let mut entry_containers = HashMap::new();
+    fill_containers_for_entry(
+        config,
+        entry_index,
+        entry,
+        &words,
+        &mut entry_containers,
+        &mut HashSet::new(),
+    );
+
+    entry_containers
+        .into_iter()
+        .map(|(word, interned_container)| {
+            let mut container = Container::new();
+            container.results = interned_container.results;
+            container.aliases = interned_container
+                .aliases
+                .into_iter()
+                .map(|(alias, score)| (words.resolve(&alias).to_string(), score))
+                .collect();
+            (words.resolve(&word).to_string(), container)
+        })
+        .collect()
+}
+
+pub(super) fn max_stored_excerpts(config: &Config) -> usize {
+    config
+        .output
+        .max_stored_excerpts_per_result
+        .unwrap_or(config.output.excerpts_per_result)
+        .max(1) as usize
+}
+
+fn fill_containers_for_entry(
+    config: &Config,
+    entry_index: usize,
+    entry: &NormalizedEntry,
+    words: &Interner,
+    containers: &mut HashMap<Word, InternedContainer>,
+    entry_words: &mut HashSet<Word>,
+) {
+    let tokenize = |text: &str| match &config.input.tokenizer {
+        Some(tokenizer) => tokenizer.tokenize(text),
+        None => split_emoji_words(
+            segment_words(text.make_annotated_words(), config.input.word_segmentation),
+            config.input.index_emoji,
+        ),
+    };
+    let words_in_title: Vec<AnnotatedWord> = tokenize(&entry.title);
+    let max_stored_excerpts = max_stored_excerpts(config);
+    let exact_score = weighted_score(config.output.match_weights.exact);
+    let prefix_score = weighted_score(config.output.match_weights.prefix);
+
+    let mut word_lists: Vec<(WordListSource, Cow<[AnnotatedWord]>)> = vec![
+        (WordListSource::Title, Cow::Owned(words_in_title)),
+        (
+            WordListSource::Contents,
+            Cow::Borrowed(&entry.annotated_word_list.word_list),
+        ),
+    ];
+
+    if config.input.index_url_segments {
+        word_lists.push((
+            WordListSource::Url,
+            Cow::Owned(words_in_url_path(&entry.url)),
+        ));
+    }
+
+    if !entry.keywords.is_empty() {
+        word_lists.push((
+            WordListSource::Keywords,
+            Cow::Owned(tokenize(&entry.keywords.join(" "))),
+        ));
+    }
+
+    if let Some(summary) = &entry.summary {
+        word_lists.push((WordListSource::Summary, Cow::Owned(tokenize(summary))));
+    }
+
+    for (source, word_list) in word_lists {
+        let normalized_words = word_list
+            .iter()
+            .map(|annotated_word| {
+                let normalized_word = normalize_word(&annotated_word.word);
+                match config.input.acronym_periods {
+                    AcronymPeriodHandling::Keep => normalized_word,
+                    AcronymPeriodHandling::Collapse => {
+                        collapse_acronym_periods(&normalized_word).unwrap_or(normalized_word)
+                    }
                 }
+            })
+            .enumerate()
+            .collect();
 
-                fill_container_results_map(
-                    containers,
-                    &normalized_word,
+        for (word_index, normalized_word) in
+            merge_spaced_letters(normalized_words, config.input.spaced_letters)
+        {
+            if normalized_word.is_empty() {
+                continue;
+            }
+
+            let annotated_word = &word_list[word_index];
+
+            // Entries without excerpts have no text for a contents
+            // excerpt to point into, so only the match is stored.
+            let max_stored_excerpts =
+                if entry.generate_excerpts || source != WordListSource::Contents {
+                    max_stored_excerpts
+                } else {
+                    0
+                };
+
+            // Code is also indexed under the identifiers in it, and their
+            // parts, at the same place in the contents.
+            let (word_source, identifiers) = if source == WordListSource::Contents
+                && annotated_word
+                    .internal_annotations
+                    .contains(&InternalWordAnnotation::Code)
+            {
+                (WordListSource::Code, identifier_words(&annotated_word.word))
+            } else {
+                (source, vec![])
+            };
+
+            let extra_words = identifiers
+                .into_iter()
+                .filter(|identifier_word| *identifier_word != normalized_word);
+
+            for normalized_word in std::iter::once(normalized_word.clone()).chain(extra_words) {
+                let word = words.get_or_intern(&normalized_word);
+                if entry.stem_algorithm.is_some() {
+                    entry_words.insert(word);
+                }
+
+                let entry_result = containers
+                    .entry(word)
+                    .or_default()
+                    .results
+                    .entry(entry_index)
+                    .or_insert_with(|| SearchResult::new(exact_score));
+                push_excerpt(
+                    entry_result,
                     word_index,
-                    entry_index,
                     annotated_word,
-                    source,
+                    word_source,
+                    max_stored_excerpts,
                 );
 
                 // Step 2B: Fill _other containers'_ aliases maps with the
                 // prefixes of this word
                 fill_other_containers_alias_maps_with_prefixes(
+                    prefix_score,
                     config.input.minimum_indexed_substring_length,
-                    config.input.minimum_index_ideographic_substring_length,
-                    containers,
-                    &normalized_word,
-                );
-
-                // Step 2C: Fill _other containers'_ alias maps with the
-                // reverse-stems of this word
-                fill_other_containers_alias_maps_with_reverse_stems(
-                    entry,
-                    stems,
+                    config
+                        .input
+                        .DEPRECATED_minimum_index_ideographic_substring_length
+                        .unwrap_or(config.input.minimum_indexed_ideographic_substring_length),
+                    words,
                     containers,
                     &normalized_word,
+                    word,
                 );
             }
+
+            // Step 2C, filling _other containers'_ alias maps with the
+            // reverse-stems of this word, happens in `finish`.
         }
     }
 }
 
-fn fill_container_results_map(
-    containers: &mut BTreeMap<String, Container>,
-    normalized_word: &str,
+/**
+ * Splits the path of a URL into words, e.g. `/docs/networking/dns-records/`
+ * becomes `docs`, `networking`, `dns`, and `records`. The scheme, host, query
+ * string, and fragment are ignored.
+ */
+fn words_in_url_path(url: &str) -> Vec<AnnotatedWord> {
+    let path = match url.split_once("://") {
+        Some((_scheme, rest)) => rest.split_once('/').map_or("", |(_host, path)| path),
+        None => url,
+    };
+
+    let path = path.split(|c| c == '?' || c == '#').next().unwrap_or("");
+
+    path.split(|c| c == '/' || c == '-' || c == '_' || c == '.')
+        .filter(|segment| !segment.is_empty())
+        .map(AnnotatedWord::new)
+        .collect()
+}
+
+fn push_excerpt(
+    entry_result: &mut SearchResult,
     word_index: usize,
-    entry_index: usize,
     annotated_word: &AnnotatedWord,
     source: WordListSource,
+    max_stored_excerpts: usize,
 ) {
-    let results_map = &mut containers
-        .entry(normalized_word.to_string())
-        .or_insert_with(Container::new)
-        .results;
+    // Title words come first, so a title match is always kept.
+    if entry_result.excerpts.len() >= max_stored_excerpts {
+        return;
+    }
 
-    let entry_result: &mut SearchResult = results_map
-        .entry(entry_index)
-        .or_insert_with(SearchResult::new);
+    // Item starts are stored with the entry, not with each excerpt, and
+    // code is told apart by the excerpt's source.
+    let internal_annotations = annotated_word
+        .internal_annotations
+        .iter()
+        .filter(|annotation| {
+            !matches!(
+                annotation,
+                InternalWordAnnotation::ItemStart | InternalWordAnnotation::Code
+            )
+        })
+        .cloned()
+        .collect();
 
     entry_result.excerpts.push(Excerpt {
         word_index,
         source,
-        internal_annotations: annotated_word.internal_annotations.clone(),
+        internal_annotations,
         fields: annotated_word.fields.clone(),
     });
 }
 
 fn fill_other_containers_alias_maps_with_prefixes(
+    prefix_score: Score,
     prefix_length: u8,
     ideograph_prefix_length: u8,
-    containers: &mut BTreeMap<String, Container>,
+    words: &Interner,
+    containers: &mut HashMap<Word, InternedContainer>,
     normalized_word: &str,
+    word: Word,
 ) {
-    let chars: Vec<char> = normalized_word.chars().collect();
+    let char_offsets: Vec<usize> = normalized_word
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .collect();
+    let char_count = char_offsets.len();
 
-    let substring_max_length_range: Range<usize> = if string_is_cjk_ideographic(&chars) {
-        (ideograph_prefix_length as usize)..chars.len()
+    let substring_max_length_range: Range<usize> = if string_is_cjk_ideographic(normalized_word) {
+        (ideograph_prefix_length as usize)..char_count
     } else {
-        (prefix_length as usize)..chars.len()
+        (prefix_length as usize)..char_count
     };
 
     for n in substring_max_length_range {
-        let substring: String = chars[0..n].iter().collect();
+        let prefix = words.get_or_intern(&normalized_word[..char_offsets[n]]);
 
-        let alises_map = &mut containers
-            .entry(substring.clone())
-            .or_insert_with(Container::new)
-            .aliases;
-
-        let _alias_score = alises_map.entry(normalized_word.to_string()).or_insert(
-            PREFIX_SCORE
-                .saturating_sub(chars.len().saturating_sub(n).try_into().unwrap_or(u8::MAX)),
-        );
+        let _alias_score = containers
+            .entry(prefix)
+            .or_default()
+            .aliases
+            .entry(word)
+            .or_insert(
+                prefix_score
+                    .saturating_sub(char_count.saturating_sub(n).try_into().unwrap_or(u8::MAX)),
+            );
     }
 }
 
 fn fill_other_containers_alias_maps_with_reverse_stems(
-    entry: &NormalizedEntry,
-    stems: &BTreeMap<String, Vec<String>>,
-    containers: &mut BTreeMap<String, Container>,
+    stem_score: Score,
+    stemmer: &Stemmer,
+    words: &Interner,
+    stems: &Stems,
+    containers: &mut HashMap<Word, InternedContainer>,
     normalized_word: &str,
+    word: Word,
 ) {
-    if let Some(stem_algorithm) = entry.stem_algorithm {
-        let stem = Stemmer::create(stem_algorithm)
-            .stem(normalized_word)
-            .to_string();
-        if let Some(reverse_stems_vector) = stems.get(&stem) {
-            for reverse_stem in reverse_stems_vector {
-                if reverse_stem != normalized_word {
-                    let _alias_score = containers
-                        .entry(reverse_stem.clone())
-                        .or_insert_with(Container::new)
-                        .aliases
-                        .entry(normalized_word.to_string())
-                        .or_insert(STEM_SCORE as u8);
-                }
+    // Stems are interned while filling `stems`, so a stem that was never
+    // interned has no words to alias.
+    let stem = match words.get(stemmer.stem(normalized_word)) {
+        Some(stem) => stem,
+        None => return,
+    };
+
+    if let Some(reverse_stems_vector) = stems.get(&stem) {
+        for &reverse_stem in reverse_stems_vector {
+            if reverse_stem != word {
+                containers
+                    .entry(reverse_stem)
+                    .or_default()
+                    .aliases
+                    .entry(word)
+                    .and_modify(|score| *score = (*score).max(stem_score))
+                    .or_insert(stem_score);
             }
         }
     }
 }
 
-fn string_is_cjk_ideographic(s: &[char]) -> bool {
-    s.iter()
-        .map(char_is_cjk_ideograph)
-        .fold(true, |acc, x| acc & x)
+fn string_is_cjk_ideographic(s: &str) -> bool {
+    s.chars().all(|c| char_is_cjk_ideograph(&c))
 }
 
 #[allow(clippy::trivially_copy_pass_by_ref)]
@@ -191,12 +513,15 @@ fn char_is_cjk_ideograph(c: &char) -> bool {
 #[cfg(test)]
 mod tests {
     use crate::{
-        config::Config,
-        index_v3::{build::intermediate_entry::NormalizedEntry, AnnotatedWordList},
+        config::{Config, DataSource, File, Filetype, InputConfig, MatchWeights, OutputConfig},
+        index_v3::{
+            build::{build, intermediate_entry::NormalizedEntry},
+            AnnotatedWord, AnnotatedWordList, WordListSource,
+        },
     };
     use std::collections::{BTreeMap, HashMap};
 
-    use super::fill_containers;
+    use super::{fill_containers, words_in_url_path, Interner, Stems};
 
     #[test]
     fn container_filling_continues_after_encountering_unnormalizable_word() {
@@ -206,6 +531,16 @@ mod tests {
             url: "".to_string(),
             fields: HashMap::default(),
             stem_algorithm: None,
+            keywords: vec![],
+            summary: None,
+            language_undetected: false,
+            malformed_json_ld_blocks: 0,
+            invalid_thumbnail: None,
+            invalid_date: None,
+            truncated_word_count: 0,
+            unlimited_word_count: None,
+            stored_text: None,
+            generate_excerpts: true,
         };
 
         let mut containers = BTreeMap::default();
@@ -213,7 +548,8 @@ mod tests {
         fill_containers(
             &Config::default(),
             &[intermediate_entry],
-            &BTreeMap::default(),
+            &Interner::default(),
+            &Stems::default(),
             &mut containers,
         );
 
@@ -223,4 +559,248 @@ mod tests {
             containers.keys()
         );
     }
+
+    #[test]
+    fn url_path_is_split_into_words() {
+        let computed: Vec<String> =
+            words_in_url_path("https://example.com/docs/networking/dns_records.html?q=1#top")
+                .into_iter()
+                .map(|aw| aw.word.to_string())
+                .collect();
+
+        assert_eq!(
+            computed,
+            vec!["docs", "networking", "dns", "records", "html"]
+        );
+    }
+
+    #[test]
+    fn relative_url_path_is_split_into_words() {
+        let computed: Vec<String> = words_in_url_path("/docs/networking/dns-lookup/")
+            .into_iter()
+            .map(|aw| aw.word.to_string())
+            .collect();
+
+        assert_eq!(computed, vec!["docs", "networking", "dns", "lookup"]);
+    }
+
+    fn stored_excerpt_count(output: OutputConfig) -> usize {
+        let intermediate_entry = NormalizedEntry {
+            annotated_word_list: AnnotatedWordList {
+                word_list: ["fox"; 8]
+                    .iter()
+                    .map(|word| AnnotatedWord {
+                        word: (*word).into(),
+                        ..AnnotatedWord::default()
+                    })
+                    .collect(),
+            },
+            title: "The fox".to_string(),
+            url: "".to_string(),
+            fields: HashMap::default(),
+            stem_algorithm: None,
+            keywords: vec![],
+            summary: None,
+            language_undetected: false,
+            malformed_json_ld_blocks: 0,
+            invalid_thumbnail: None,
+            invalid_date: None,
+            truncated_word_count: 0,
+            unlimited_word_count: None,
+            stored_text: None,
+            generate_excerpts: true,
+        };
+
+        let config = Config {
+            output,
+            ..Config::default()
+        };
+        let mut containers = BTreeMap::default();
+
+        fill_containers(
+            &config,
+            &[intermediate_entry],
+            &Interner::default(),
+            &Stems::default(),
+            &mut containers,
+        );
+
+        containers["fox"].results[&0].excerpts.len()
+    }
+
+    #[test]
+    fn entries_without_excerpts_store_only_their_matches() {
+        let intermediate_entry = NormalizedEntry {
+            annotated_word_list: AnnotatedWordList {
+                word_list: ["aardvark", "an", "animal", "aardvark"]
+                    .iter()
+                    .map(|word| AnnotatedWord {
+                        word: (*word).into(),
+                        ..AnnotatedWord::default()
+                    })
+                    .collect(),
+            },
+            title: "Glossary animal".to_string(),
+            url: "".to_string(),
+            fields: HashMap::default(),
+            stem_algorithm: None,
+            keywords: vec![],
+            summary: None,
+            language_undetected: false,
+            malformed_json_ld_blocks: 0,
+            invalid_thumbnail: None,
+            invalid_date: None,
+            truncated_word_count: 0,
+            unlimited_word_count: None,
+            stored_text: None,
+            generate_excerpts: false,
+        };
+
+        let mut containers = BTreeMap::default();
+        fill_containers(
+            &Config::default(),
+            &[intermediate_entry],
+            &Interner::default(),
+            &Stems::default(),
+            &mut containers,
+        );
+
+        assert!(containers["aardvark"].results[&0].excerpts.is_empty());
+
+        // Title matches keep their excerpts, for highlighting and `title:`
+        // queries.
+        let animal_sources: Vec<WordListSource> = containers["animal"].results[&0]
+            .excerpts
+            .iter()
+            .map(|excerpt| excerpt.source)
+            .collect();
+        assert_eq!(animal_sources, vec![WordListSource::Title]);
+    }
+
+    #[test]
+    fn stored_excerpts_are_capped_at_excerpts_per_result_by_default() {
+        assert_eq!(
+            stored_excerpt_count(OutputConfig {
+                excerpts_per_result: 2,
+                ..OutputConfig::default()
+            }),
+            2
+        );
+    }
+
+    #[test]
+    fn stored_excerpts_honor_the_build_cap() {
+        assert_eq!(
+            stored_excerpt_count(OutputConfig {
+                excerpts_per_result: 2,
+                max_stored_excerpts_per_result: Some(3),
+                ..OutputConfig::default()
+            }),
+            3
+        );
+
+        assert_eq!(
+            stored_excerpt_count(OutputConfig {
+                max_stored_excerpts_per_result: Some(0),
+                ..OutputConfig::default()
+            }),
+            1
+        );
+    }
+
+    #[test]
+    fn url_segment_keyword_retrieves_entry() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        "How to configure name resolution".to_string(),
+                    )),
+                    title: "Resolution".to_string(),
+                    url: "/docs/networking/dns/".to_string(),
+                    filetype: Some(Filetype::PlainText),
+                    ..Default::default()
+                }],
+                index_url_segments: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let index = build(&config).unwrap().index;
+        let output = crate::index_v3::search(&index, "dns").unwrap();
+
+        assert_eq!(output.results.len(), 1);
+        assert_eq!(output.results[0].entry.url, "/docs/networking/dns/");
+    }
+
+    #[test]
+    fn url_segments_are_not_indexed_by_default() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        "How to configure name resolution".to_string(),
+                    )),
+                    title: "Resolution".to_string(),
+                    url: "/docs/networking/dns/".to_string(),
+                    filetype: Some(Filetype::PlainText),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let index = build(&config).unwrap().index;
+        assert!(crate::index_v3::search(&index, "dns")
+            .unwrap()
+            .results
+            .is_empty());
+    }
+
+    fn plaintext_file(title: &str, contents: &str) -> File {
+        File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn match_weights_reorder_stem_and_prefix_matches() {
+        // "flies" has the same stem as "fly", and "fly" starts "flyer": the
+        // two documents only differ in how they match.
+        let config = |stem| Config {
+            input: InputConfig {
+                files: vec![
+                    plaintext_file("Alpha", "Watch the flies."),
+                    plaintext_file("Beta", "Watch the flyer."),
+                    plaintext_file("Gamma", "Watch it fly."),
+                ],
+                ..Default::default()
+            },
+            output: OutputConfig {
+                match_weights: MatchWeights {
+                    stem,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        };
+
+        let titles = |stem| {
+            let index = build(&config(stem)).unwrap().index;
+            crate::index_v3::search(&index, "fly")
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| result.entry.title)
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(titles(0.5), vec!["Gamma", "Beta", "Alpha"]);
+        assert_eq!(titles(0.99), vec!["Gamma", "Alpha", "Beta"]);
+    }
 }
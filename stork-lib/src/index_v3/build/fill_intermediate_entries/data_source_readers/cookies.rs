@@ -0,0 +1,194 @@
+use std::sync::Arc;
+
+use reqwest::{cookie::Jar, Url};
+
+use crate::{config::InputConfig, IndexGenerationError};
+
+struct SeededCookie<'a> {
+    domain: &'a str,
+    include_subdomains: bool,
+    path: &'a str,
+    secure: bool,
+    name: &'a str,
+    value: &'a str,
+}
+
+impl SeededCookie<'_> {
+    /// Adds the cookie to the jar. A cookie without a `Domain` attribute is
+    /// only sent to the exact host it was set for.
+    fn add_to(&self, jar: &Jar) -> Result<(), IndexGenerationError> {
+        let host = self.domain.trim_start_matches('.');
+        let scheme = if self.secure { "https" } else { "http" };
+        let url = Url::parse(&format!("{}://{}{}", scheme, host, self.path)).map_err(|_| {
+            IndexGenerationError::InvalidCookies(format!(
+                "the cookie `{}` has an invalid domain `{}`",
+                self.name, self.domain
+            ))
+        })?;
+
+        let mut cookie = format!("{}={}; Path={}", self.name, self.value, self.path);
+        if self.include_subdomains {
+            cookie.push_str(&format!("; Domain={}", host));
+        }
+        if self.secure {
+            cookie.push_str("; Secure");
+        }
+
+        jar.add_cookie_str(&cookie, &url);
+        Ok(())
+    }
+}
+
+/**
+ * Collects the cookies from the config and from the configured cookie file
+ * into a jar that can be handed to a `reqwest` client.
+ */
+pub(crate) fn cookie_jar(config: &InputConfig) -> Result<Arc<Jar>, IndexGenerationError> {
+    let jar = Jar::default();
+
+    for cookie in &config.cookies {
+        SeededCookie {
+            domain: &cookie.domain,
+            include_subdomains: cookie.domain.starts_with('.'),
+            path: "/",
+            secure: false,
+            name: &cookie.name,
+            value: &cookie.value,
+        }
+        .add_to(&jar)?;
+    }
+
+    if let Some(path) = &config.cookie_file {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            IndexGenerationError::InvalidCookies(format!("`{}` could not be read: {}", path, e))
+        })?;
+
+        for cookie in parse_netscape_cookie_file(&contents)? {
+            cookie.add_to(&jar)?;
+        }
+    }
+
+    Ok(Arc::new(jar))
+}
+
+/// Each line of a Netscape cookie file holds seven tab-separated fields:
+/// domain, subdomain flag, path, secure flag, expiry, name and value.
+fn parse_netscape_cookie_file(
+    contents: &str,
+) -> Result<Vec<SeededCookie<'_>>, IndexGenerationError> {
+    let mut cookies = vec![];
+
+    for (line_index, line) in contents.lines().enumerate() {
+        // curl marks HttpOnly cookies by prefixing an otherwise-normal line
+        let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.trim_end_matches('\r').split('\t').collect();
+        match fields[..] {
+            [domain, include_subdomains, path, secure, _expires, name, value] => {
+                cookies.push(SeededCookie {
+                    domain,
+                    include_subdomains: include_subdomains.eq_ignore_ascii_case("TRUE"),
+                    path,
+                    secure: secure.eq_ignore_ascii_case("TRUE"),
+                    name,
+                    value,
+                })
+            }
+            _ => {
+                return Err(IndexGenerationError::InvalidCookies(format!(
+                    "line {} of the cookie file doesn't have seven tab-separated fields",
+                    line_index + 1
+                )))
+            }
+        }
+    }
+
+    Ok(cookies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CookieConfig;
+    use reqwest::cookie::CookieStore;
+
+    fn cookies_sent_to(jar: &Jar, url: &str) -> Option<String> {
+        jar.cookies(&Url::parse(url).unwrap())
+            .map(|header| header.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn config_cookies_are_scoped_to_their_domain() {
+        let jar = cookie_jar(&InputConfig {
+            cookies: vec![
+                CookieConfig {
+                    name: "session".to_string(),
+                    value: "abc".to_string(),
+                    domain: "intranet.example.com".to_string(),
+                },
+                CookieConfig {
+                    name: "sso".to_string(),
+                    value: "xyz".to_string(),
+                    domain: ".corp.example.com".to_string(),
+                },
+            ],
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert_eq!(
+            cookies_sent_to(&jar, "http://intranet.example.com/docs"),
+            Some("session=abc".to_string())
+        );
+        assert_eq!(
+            cookies_sent_to(&jar, "https://wiki.corp.example.com/"),
+            Some("sso=xyz".to_string())
+        );
+        assert_eq!(cookies_sent_to(&jar, "http://example.com/"), None);
+    }
+
+    #[test]
+    fn netscape_cookie_file_is_parsed() {
+        let contents = "# Netscape HTTP Cookie File\n\n\
+            intranet.example.com\tFALSE\t/\tFALSE\t0\tsession\tabc\n\
+            #HttpOnly_.example.org\tTRUE\t/private\tTRUE\t2147483647\ttoken\t123\n";
+
+        let cookies = parse_netscape_cookie_file(contents).unwrap();
+        assert_eq!(cookies.len(), 2);
+
+        let jar = Jar::default();
+        for cookie in cookies {
+            cookie.add_to(&jar).unwrap();
+        }
+
+        assert_eq!(
+            cookies_sent_to(&jar, "http://intranet.example.com/"),
+            Some("session=abc".to_string())
+        );
+        assert_eq!(
+            cookies_sent_to(&jar, "https://docs.example.org/private/page"),
+            Some("token=123".to_string())
+        );
+        assert_eq!(
+            cookies_sent_to(&jar, "http://docs.example.org/private/page"),
+            None
+        );
+        assert_eq!(cookies_sent_to(&jar, "https://docs.example.org/"), None);
+    }
+
+    #[test]
+    fn malformed_cookie_file_lines_are_an_error() {
+        assert_eq!(
+            parse_netscape_cookie_file("example.com\tFALSE\t/\n")
+                .err()
+                .map(|error| error.to_string()),
+            Some(
+                "The cookies for fetching web pages couldn't be loaded: line 1 of the cookie file doesn't have seven tab-separated fields"
+                    .to_string()
+            )
+        );
+    }
+}
@@ -1,12 +1,23 @@
-use crate::config::Filetype;
+use crate::config::{Filetype, FrontmatterConfig, HyphenatedLineBreakHandling};
+use crate::index_v3::{
+    build::annotated_words_from_string::{configured_tokenizer, StreamingTokenizer},
+    AnnotatedWordList,
+};
 
 use super::{ReadResult, ReaderConfig, WordListGenerationError};
 use std::{
     fs::File,
-    io::{BufReader, Read},
+    io::{BufRead, BufReader, Read},
     path::Path,
 };
 
+/// Plain-text files at least this large are split into words as they're
+/// read, rather than being read into memory whole first.
+const STREAMING_THRESHOLD_BYTES: u64 = 8 * 1024 * 1024;
+
+/// How much of a streamed file is read at a time.
+const STREAMING_CHUNK_BYTES: usize = 64 * 1024;
+
 pub(crate) fn read(
     path: &str,
     config: &ReaderConfig,
@@ -16,20 +27,148 @@ pub(crate) fn read(
 
     let file = File::open(&full_pathname)
         .map_err(|_| WordListGenerationError::FileNotFound(full_pathname.clone()))?;
-    let mut buf_reader = BufReader::new(file);
-    let mut buffer = String::new();
-    let _bytes_read = buf_reader.read_to_string(&mut buffer);
+    let file_size = file.metadata().map_or(0, |metadata| metadata.len());
+    let mut buf_reader = BufReader::with_capacity(STREAMING_CHUNK_BYTES, file);
+
+    let known_filetype = config
+        .file
+        .filetype
+        .clone()
+        .or_else(|| get_filetype_from_path(&full_pathname));
+
+    if let Some(filetype @ (Filetype::PlainText | Filetype::PlainTextNoStem)) = &known_filetype {
+        // Words are only rejoined across line breaks, and normalized for
+        // width, in a whole buffer.
+        if file_size >= STREAMING_THRESHOLD_BYTES
+            && config.global.hyphenated_line_breaks == HyphenatedLineBreakHandling::Split
+            && !config.global.normalize_width
+            && !starts_with_frontmatter(&mut buf_reader, config, filetype)
+        {
+            let word_list = stream_words(buf_reader, config, &full_pathname)?;
+            return Ok(ReadResult {
+                buffer: String::new(),
+                filetype: Some(filetype.clone()),
+                frontmatter_fields: None,
+                streamed_word_list: Some(word_list),
+            });
+        }
+    }
+
+    let mut bytes = Vec::new();
+    let _bytes_read = buf_reader.read_to_end(&mut bytes);
+
+    if looks_binary(&bytes) {
+        return Err(WordListGenerationError::BinaryFileSkipped(full_pathname));
+    }
+
+    let buffer = String::from_utf8(bytes)
+        .map_err(|_| WordListGenerationError::BinaryFileSkipped(full_pathname.clone()))?;
 
-    let filetype_from_extension = get_filetype_from_path(&full_pathname);
+    // Sniffing falls back to plain text, which only means it didn't find
+    // anything, so a configured default filetype comes first.
+    let filetype = known_filetype.unwrap_or_else(|| {
+        match (sniff_filetype(&buffer), &config.global.default_filetype) {
+            (Filetype::PlainText, Some(default_filetype)) => default_filetype.clone(),
+            (sniffed, _) => sniffed,
+        }
+    });
 
     Ok(ReadResult {
         buffer,
-        filetype: config.file.filetype.clone().or(filetype_from_extension),
+        filetype: Some(filetype),
         frontmatter_fields: None,
+        streamed_word_list: None,
+    })
+}
+
+/**
+ * Whether the file opens with a frontmatter block that the config asks to be
+ * handled. Frontmatter has to be parsed as a whole, so those files aren't
+ * streamed. Only looks at the reader's buffer, without consuming it.
+ */
+fn starts_with_frontmatter(
+    buf_reader: &mut BufReader<File>,
+    config: &ReaderConfig,
+    filetype: &Filetype,
+) -> bool {
+    *config.frontmatter_handling(Some(filetype)) != FrontmatterConfig::Ignore
+        && buf_reader.fill_buf().map_or(false, |start| {
+            String::from_utf8_lossy(start)
+                .trim_start_matches('\u{feff}')
+                .trim_start()
+                .starts_with("---")
+        })
+}
+
+/**
+ * Splits a plain-text file into words a chunk at a time, so that only the
+ * words, and never the file's whole text, are held in memory. The file is
+ * skipped as binary by the same rules as `looks_binary`, which are checked
+ * as the chunks are read.
+ */
+fn stream_words(
+    mut buf_reader: BufReader<File>,
+    config: &ReaderConfig,
+    full_pathname: &Path,
+) -> Result<AnnotatedWordList, WordListGenerationError> {
+    let binary_file_skipped =
+        || WordListGenerationError::BinaryFileSkipped(full_pathname.to_path_buf());
+
+    let mut tokenizer = StreamingTokenizer::new(configured_tokenizer(&config.global));
+    let mut byte_count = 0;
+    let mut control_byte_count = 0;
+
+    loop {
+        let chunk = match buf_reader.fill_buf() {
+            Ok([]) => break,
+            Ok(chunk) => chunk,
+            Err(_) => return Err(binary_file_skipped()),
+        };
+
+        if chunk.contains(&0) {
+            return Err(binary_file_skipped());
+        }
+
+        byte_count += chunk.len();
+        control_byte_count += chunk
+            .iter()
+            .filter(|b| b.is_ascii_control() && !b.is_ascii_whitespace())
+            .count();
+
+        tokenizer.feed(chunk).map_err(|_| binary_file_skipped())?;
+
+        let chunk_length = chunk.len();
+        buf_reader.consume(chunk_length);
+    }
+
+    if control_byte_count * 10 > byte_count {
+        return Err(binary_file_skipped());
+    }
+
+    Ok(AnnotatedWordList {
+        word_list: tokenizer.finish().map_err(|_| binary_file_skipped())?,
     })
 }
 
-fn get_filetype_from_path(path: &Path) -> Option<Filetype> {
+/**
+ * Guesses whether a file's contents are binary data rather than text: any
+ * NUL byte, or more than 10% of bytes being non-whitespace control
+ * characters, is treated as a sign of a binary file.
+ */
+fn looks_binary(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return true;
+    }
+
+    let control_byte_count = bytes
+        .iter()
+        .filter(|b| b.is_ascii_control() && !b.is_ascii_whitespace())
+        .count();
+
+    control_byte_count * 10 > bytes.len()
+}
+
+pub(super) fn get_filetype_from_path(path: &Path) -> Option<Filetype> {
     let ext_str = path.extension()?.to_str()?;
     match String::from(ext_str).to_ascii_lowercase().as_ref() {
         "html" | "htm" => Some(Filetype::HTML),
@@ -39,3 +178,238 @@ fn get_filetype_from_path(path: &Path) -> Option<Filetype> {
         _ => None,
     }
 }
+
+/**
+ * Guesses a filetype from the start of a file's contents, for files whose
+ * extension doesn't say what they are. HTML is recognized by its doctype or
+ * root element (XHTML by its XML prolog), and Markdown by a heading on its
+ * first line. Anything else, including scripts with a shebang line, is
+ * plain text.
+ */
+fn sniff_filetype(buffer: &str) -> Filetype {
+    let start = buffer.trim_start_matches('\u{feff}').trim_start();
+    let start_lowercase = start
+        .chars()
+        .take(512)
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    if start_lowercase.starts_with("<!doctype html") || start_lowercase.starts_with("<html") {
+        return Filetype::HTML;
+    }
+
+    if start_lowercase.starts_with("<?xml") {
+        return if start_lowercase.contains("<html") {
+            Filetype::HTML
+        } else {
+            Filetype::PlainText
+        };
+    }
+
+    let mut lines = start.lines();
+    let first_line = lines.next().unwrap_or_default();
+    let second_line = lines.next().unwrap_or_default().trim_end();
+
+    let heading_text = first_line.trim_start_matches('#');
+    let heading_level = first_line.len() - heading_text.len();
+    let is_atx_heading = (1..=6).contains(&heading_level) && heading_text.starts_with(' ');
+    let is_setext_heading = !first_line.trim().is_empty()
+        && !second_line.is_empty()
+        && (second_line.chars().all(|c| c == '=') || second_line.chars().all(|c| c == '-'));
+
+    if is_atx_heading || is_setext_heading {
+        Filetype::Markdown
+    } else {
+        Filetype::PlainText
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{File as StorkFile, InputConfig, OutputConfig};
+    use pretty_assertions::assert_eq;
+    use std::io::Write;
+
+    fn reader_config_in(directory: &Path) -> ReaderConfig {
+        ReaderConfig {
+            global: InputConfig {
+                base_directory: directory.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            file: StorkFile::default(),
+            output: OutputConfig::default(),
+        }
+    }
+
+    #[test]
+    fn random_bytes_are_skipped_as_binary() {
+        // A simple LCG, so the "random" bytes are the same on every run
+        let mut state: u32 = 12345;
+        let bytes: Vec<u8> = (0..4096)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+
+        let directory = std::env::temp_dir();
+        let filename = "stork-binary-file-test.txt";
+        std::fs::File::create(directory.join(filename))
+            .unwrap()
+            .write_all(&bytes)
+            .unwrap();
+
+        let error = read(filename, &reader_config_in(&directory)).err().unwrap();
+        assert_eq!(
+            error,
+            WordListGenerationError::BinaryFileSkipped(directory.join(filename))
+        );
+    }
+
+    #[test]
+    fn extensionless_html_file_is_sniffed_as_html() {
+        let directory = std::env::temp_dir();
+        let filename = "stork-extensionless-html-test";
+        std::fs::File::create(directory.join(filename))
+            .unwrap()
+            .write_all(b"<!doctype html>\n<html><body><p>Hello</p></body></html>\n")
+            .unwrap();
+
+        let read_result = read(filename, &reader_config_in(&directory)).unwrap();
+        assert_eq!(read_result.filetype, Some(Filetype::HTML));
+    }
+
+    #[test]
+    fn configured_filetype_wins_over_sniffing() {
+        let directory = std::env::temp_dir();
+        let filename = "stork-extensionless-configured-test";
+        std::fs::File::create(directory.join(filename))
+            .unwrap()
+            .write_all(b"<!DOCTYPE html>\n<p>Shown verbatim</p>\n")
+            .unwrap();
+
+        let mut reader_config = reader_config_in(&directory);
+        reader_config.file.filetype = Some(Filetype::PlainText);
+
+        let read_result = read(filename, &reader_config).unwrap();
+        assert_eq!(read_result.filetype, Some(Filetype::PlainText));
+    }
+
+    #[test]
+    fn default_filetype_is_used_when_nothing_else_says_what_a_file_is() {
+        let directory = std::env::temp_dir();
+        let unknown = "stork-extensionless-default-test";
+        std::fs::File::create(directory.join(unknown))
+            .unwrap()
+            .write_all(b"Some *emphasized* words\n")
+            .unwrap();
+        let sniffed = "stork-extensionless-sniffed-default-test";
+        std::fs::File::create(directory.join(sniffed))
+            .unwrap()
+            .write_all(b"<!doctype html>\n<html><body><p>Hello</p></body></html>\n")
+            .unwrap();
+        let with_extension = "stork-default-filetype-test.txt";
+        std::fs::File::create(directory.join(with_extension))
+            .unwrap()
+            .write_all(b"Some *emphasized* words\n")
+            .unwrap();
+
+        let mut reader_config = reader_config_in(&directory);
+        let filetype = |filename, reader_config: &ReaderConfig| {
+            read(filename, reader_config).unwrap().filetype
+        };
+        assert_eq!(filetype(unknown, &reader_config), Some(Filetype::PlainText));
+
+        reader_config.global.default_filetype = Some(Filetype::Markdown);
+        assert_eq!(filetype(unknown, &reader_config), Some(Filetype::Markdown));
+        assert_eq!(filetype(sniffed, &reader_config), Some(Filetype::HTML));
+        assert_eq!(
+            filetype(with_extension, &reader_config),
+            Some(Filetype::PlainText)
+        );
+
+        reader_config.file.filetype = Some(Filetype::SRTSubtitle);
+        assert_eq!(
+            filetype(unknown, &reader_config),
+            Some(Filetype::SRTSubtitle)
+        );
+    }
+
+    #[test]
+    fn sniffed_filetypes() {
+        let cases = [
+            (
+                "<?xml version=\"1.0\"?>\n<html xmlns=\"http://www.w3.org/1999/xhtml\">",
+                Filetype::HTML,
+            ),
+            (
+                "<?xml version=\"1.0\"?>\n<feed></feed>",
+                Filetype::PlainText,
+            ),
+            ("# Project\n\nSome words.", Filetype::Markdown),
+            ("Project\n=======\n\nSome words.", Filetype::Markdown),
+            (
+                "#!/bin/sh\n# Installs the project\necho hi",
+                Filetype::PlainText,
+            ),
+            ("#hashtag without a space", Filetype::PlainText),
+            ("MIT License\n\nCopyright (c) 2022", Filetype::PlainText),
+            ("", Filetype::PlainText),
+        ];
+
+        for (contents, expected) in cases {
+            assert_eq!(sniff_filetype(contents), expected, "{:?}", contents);
+        }
+    }
+
+    #[test]
+    fn large_plain_text_file_is_streamed() {
+        use crate::index_v3::build::annotated_words_from_string::{
+            AnnotatedWordable, LinkHandlers,
+        };
+
+        let paragraph = "Transcript of the annual meeting — naïve questions, résumé-worthy answers, and www.example.com links.\n";
+        let repetitions = STREAMING_THRESHOLD_BYTES as usize / paragraph.len() + 1;
+        let contents = paragraph.repeat(repetitions);
+
+        let directory = std::env::temp_dir();
+        let filename = "stork-large-transcript-test.txt";
+        std::fs::File::create(directory.join(filename))
+            .unwrap()
+            .write_all(contents.as_bytes())
+            .unwrap();
+
+        let reader_config = reader_config_in(&directory);
+        let read_result = read(filename, &reader_config).unwrap();
+        assert!(read_result.buffer.is_empty());
+
+        let streamed: Vec<String> = read_result
+            .streamed_word_list
+            .unwrap()
+            .word_list
+            .into_iter()
+            .map(|annotated_word| annotated_word.word.to_string())
+            .collect();
+        let whole: Vec<String> = contents
+            .make_annotated_words_with_links(LinkHandlers::from(&reader_config.global), |_, _| {})
+            .into_iter()
+            .map(|annotated_word| annotated_word.word.to_string())
+            .collect();
+
+        assert_eq!(streamed.len(), repetitions * 14);
+        assert!(streamed == whole);
+    }
+
+    #[test]
+    fn text_is_not_binary() {
+        assert!(!looks_binary(
+            "Some text,\n\twith whitespace — and unicode.".as_bytes()
+        ));
+    }
+
+    #[test]
+    fn nul_bytes_are_binary() {
+        assert!(looks_binary(b"PK\x03\x04\x00\x00"));
+    }
+}
@@ -1,12 +1,38 @@
-use crate::config::{DataSource, Filetype};
+use std::path::Path;
+
+use crate::config::{DataSource, File, Filetype};
 
 use super::{ReadResult, ReaderConfig, WordListGenerationError};
 
+#[cfg(feature = "build-v3-web-scraping")]
+mod cookies;
 pub mod filepath_data_source_reader;
 pub mod url_data_source_reader;
+mod web_client;
+pub use web_client::WebClient;
+
+/**
+ * The filetype a file will probably be read as, found without reading it:
+ * its configured filetype, or else the one its path or URL's extension
+ * implies. Inline contents are `default_filetype`, or plain text, unless
+ * configured otherwise.
+ */
+pub fn expected_filetype(file: &File, default_filetype: Option<&Filetype>) -> Option<Filetype> {
+    file.filetype.clone().or_else(|| match file.source() {
+        DataSource::Contents(_) => default_filetype.cloned().or(Some(Filetype::PlainText)),
+        DataSource::URL(url) => {
+            let path = url.split(|c| c == '?' || c == '#').next().unwrap_or("");
+            filepath_data_source_reader::get_filetype_from_path(Path::new(path))
+        }
+        DataSource::FilePath(path) => {
+            filepath_data_source_reader::get_filetype_from_path(Path::new(&path))
+        }
+    })
+}
 
 pub fn read_from_data_source(
     reader_config: &ReaderConfig,
+    web_client: &WebClient,
 ) -> Result<ReadResult, WordListGenerationError> {
     match &reader_config.file.source() {
         DataSource::Contents(contents) => Ok(ReadResult {
@@ -15,12 +41,18 @@ pub fn read_from_data_source(
                 .file
                 .filetype
                 .clone()
+                .or_else(|| reader_config.global.default_filetype.clone())
                 .or(Some(Filetype::PlainText)),
             frontmatter_fields: None,
+            streamed_word_list: None,
         }),
 
-        DataSource::URL(url) => return url_data_source_reader::read(url, reader_config),
+        DataSource::URL(url) => {
+            return url_data_source_reader::read(url, reader_config, web_client)
+        }
         DataSource::FilePath(path) => filepath_data_source_reader::read(path, reader_config),
     }
-    .map(|read_result| read_result.extract_frontmatter(reader_config))
+    .map(|read_result| read_result.normalize_width(reader_config))
+    .and_then(|read_result| read_result.extract_frontmatter(reader_config))
+    .map(|read_result| read_result.repair_hyphenated_line_breaks(reader_config))
 }
@@ -1,9 +1,13 @@
-use super::{ReadResult, ReaderConfig, WordListGenerationError};
+use super::{ReadResult, ReaderConfig, WebClient, WordListGenerationError};
+
+#[cfg(feature = "build-v3-web-scraping")]
+use {crate::config::Filetype, mime::Mime};
 
 #[cfg(not(feature = "build-v3-web-scraping"))]
 pub(crate) fn read(
     _url: &str,
     _config: &ReaderConfig,
+    _web_client: &WebClient,
 ) -> Result<ReadResult, WordListGenerationError> {
     Err(WordListGenerationError::FeatureNotAvailable)
 }
@@ -12,49 +16,165 @@ pub(crate) fn read(
 pub(crate) fn read(
     url: &str,
     config: &ReaderConfig,
+    web_client: &WebClient,
 ) -> Result<ReadResult, WordListGenerationError> {
-    use crate::config::Filetype;
-    use mime::Mime;
     use std::io::Read;
 
-    fn filetype_from_mime(mime: &Mime) -> Option<Filetype> {
-        match (mime.type_(), mime.subtype()) {
-            (mime::TEXT, mime::PLAIN) => Some(Filetype::PlainText),
-            (mime::TEXT, mime::HTML) => Some(Filetype::HTML),
-            _ => None,
-        }
-    }
+    let mut resp = web_client
+        .blocking()
+        .ok_or(WordListGenerationError::WebPageNotFetched)?
+        .get(url)
+        .send()
+        .map_err(|_| WordListGenerationError::WebPageNotFetched)?;
 
-    let mut resp =
-        reqwest::blocking::get(url).map_err(|_| WordListGenerationError::WebPageNotFetched)?;
+    let _status = resp.error_for_status_ref().map_err(status_error)?;
 
-    let _status = resp.error_for_status_ref().map_err(|error| {
-        if let Some(status_code) = error.status().map(|s| s.as_u16()) {
-            return WordListGenerationError::WebPageErrorfulStatusCode(status_code);
-        } else {
-            return WordListGenerationError::WebPageNotFetched;
-        }
-    })?;
+    let mime_type = mime_type(resp.headers())?;
+
+    let mut buffer = String::new();
+    let _bytes_read = resp.read_to_string(&mut buffer);
+
+    Ok(read_result(buffer, &mime_type, config))
+}
 
-    let mime_type: Mime = resp
-        .headers()
+#[cfg(feature = "async")]
+pub(crate) async fn read_async(
+    url: &str,
+    config: &ReaderConfig,
+    web_client: &WebClient,
+) -> Result<ReadResult, WordListGenerationError> {
+    let resp = web_client
+        .non_blocking()
+        .ok_or(WordListGenerationError::WebPageNotFetched)?
+        .get(url)
+        .send()
+        .await
+        .map_err(|_| WordListGenerationError::WebPageNotFetched)?;
+
+    let _status = resp.error_for_status_ref().map_err(status_error)?;
+
+    let mime_type = mime_type(resp.headers())?;
+
+    let buffer = resp.text().await.unwrap_or_default();
+
+    Ok(read_result(buffer, &mime_type, config))
+}
+
+#[cfg(feature = "build-v3-web-scraping")]
+fn status_error(error: reqwest::Error) -> WordListGenerationError {
+    if let Some(status_code) = error.status().map(|s| s.as_u16()) {
+        WordListGenerationError::WebPageErrorfulStatusCode(status_code)
+    } else {
+        WordListGenerationError::WebPageNotFetched
+    }
+}
+
+#[cfg(feature = "build-v3-web-scraping")]
+fn mime_type(headers: &reqwest::header::HeaderMap) -> Result<Mime, WordListGenerationError> {
+    headers
         .get(reqwest::header::CONTENT_TYPE)
         .ok_or(WordListGenerationError::UnknownContentType)?
         .to_str()
         .map_err(|_| WordListGenerationError::UnknownContentType)?
         .parse()
-        .map_err(|_| WordListGenerationError::UnknownContentType)?;
+        .map_err(|_| WordListGenerationError::UnknownContentType)
+}
 
-    let mut buffer = String::new();
-    let _bytes_read = resp.read_to_string(&mut buffer);
+#[cfg(feature = "build-v3-web-scraping")]
+fn read_result(buffer: String, mime_type: &Mime, config: &ReaderConfig) -> ReadResult {
+    fn filetype_from_mime(mime: &Mime) -> Option<Filetype> {
+        match (mime.type_(), mime.subtype()) {
+            (mime::TEXT, mime::PLAIN) => Some(Filetype::PlainText),
+            (mime::TEXT, mime::HTML) => Some(Filetype::HTML),
+            _ => None,
+        }
+    }
 
-    Ok(ReadResult {
+    ReadResult {
         buffer,
         filetype: config
             .file
             .filetype
             .clone()
-            .or(filetype_from_mime(&mime_type)),
+            .or_else(|| filetype_from_mime(mime_type))
+            .or_else(|| config.global.default_filetype.clone()),
         frontmatter_fields: None,
-    })
+        streamed_word_list: None,
+    }
+}
+
+#[cfg(all(test, feature = "build-v3-web-scraping"))]
+mod tests {
+    use super::*;
+    use crate::config::{CookieConfig, File, InputConfig, OutputConfig};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serves a single request, answering with a page only if the request
+    /// carries the `session=abc` cookie.
+    fn serve_one_request_requiring_cookie() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 4096];
+            let length = stream.read(&mut request).unwrap();
+            let request = String::from_utf8_lossy(&request[..length]).to_lowercase();
+
+            let response = if request.contains("cookie: session=abc") {
+                let body = "Welcome to the intranet";
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+            } else {
+                "HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string()
+            };
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+
+        format!("http://{}/page", address)
+    }
+
+    fn reader_config(cookies: Vec<CookieConfig>) -> ReaderConfig {
+        ReaderConfig {
+            global: InputConfig {
+                cookies,
+                ..Default::default()
+            },
+            file: File::default(),
+            output: OutputConfig::default(),
+        }
+    }
+
+    #[test]
+    fn gated_page_is_fetched_with_cookie() {
+        let url = serve_one_request_requiring_cookie();
+        let config = reader_config(vec![CookieConfig {
+            name: "session".to_string(),
+            value: "abc".to_string(),
+            domain: "127.0.0.1".to_string(),
+        }]);
+
+        let web_client = WebClient::new(&config.global).unwrap();
+        let result = read(&url, &config, &web_client).unwrap();
+        assert_eq!(result.buffer, "Welcome to the intranet");
+        assert_eq!(result.filetype, Some(Filetype::PlainText));
+    }
+
+    #[test]
+    fn gated_page_is_not_fetched_without_cookie() {
+        let url = serve_one_request_requiring_cookie();
+        let config = reader_config(vec![]);
+
+        let web_client = WebClient::new(&config.global).unwrap();
+        assert_eq!(
+            read(&url, &config, &web_client).err(),
+            Some(WordListGenerationError::WebPageErrorfulStatusCode(401))
+        );
+    }
 }
@@ -0,0 +1,72 @@
+#[cfg(feature = "build-v3-web-scraping")]
+use std::sync::{Arc, OnceLock};
+
+#[cfg(feature = "build-v3-web-scraping")]
+use reqwest::cookie::Jar;
+
+#[cfg(feature = "build-v3-web-scraping")]
+use super::cookies::cookie_jar;
+use crate::{config::InputConfig, IndexGenerationError};
+
+/**
+ * What every web page in a build is fetched with. It's made once per build,
+ * before any document is read, so the configured cookies are only loaded
+ * once and a broken cookie file fails the build up front. The HTTP clients
+ * are made the first time they're needed and then shared, so connections
+ * to the same host are reused.
+ */
+#[derive(Debug, Default)]
+pub struct WebClient {
+    #[cfg(feature = "build-v3-web-scraping")]
+    cookies: Arc<Jar>,
+
+    #[cfg(feature = "build-v3-web-scraping")]
+    blocking: OnceLock<Option<reqwest::blocking::Client>>,
+
+    #[cfg(feature = "async")]
+    non_blocking: OnceLock<Option<reqwest::Client>>,
+}
+
+impl WebClient {
+    #[cfg(feature = "build-v3-web-scraping")]
+    pub fn new(config: &InputConfig) -> Result<Self, IndexGenerationError> {
+        Ok(WebClient {
+            cookies: cookie_jar(config)?,
+            ..Default::default()
+        })
+    }
+
+    /// Without the `build-v3-web-scraping` feature nothing is fetched, so
+    /// there are no cookies to load.
+    #[cfg(not(feature = "build-v3-web-scraping"))]
+    pub fn new(_config: &InputConfig) -> Result<Self, IndexGenerationError> {
+        Ok(WebClient::default())
+    }
+
+    /// The client for fetching pages from a thread that can block. `None`
+    /// if it couldn't be made, which is reported for each page instead.
+    #[cfg(feature = "build-v3-web-scraping")]
+    pub(crate) fn blocking(&self) -> Option<&reqwest::blocking::Client> {
+        self.blocking
+            .get_or_init(|| {
+                reqwest::blocking::Client::builder()
+                    .cookie_provider(Arc::clone(&self.cookies))
+                    .build()
+                    .ok()
+            })
+            .as_ref()
+    }
+
+    /// The client for fetching pages from async code, like `blocking`.
+    #[cfg(feature = "async")]
+    pub(crate) fn non_blocking(&self) -> Option<&reqwest::Client> {
+        self.non_blocking
+            .get_or_init(|| {
+                reqwest::Client::builder()
+                    .cookie_provider(Arc::clone(&self.cookies))
+                    .build()
+                    .ok()
+            })
+            .as_ref()
+    }
+}
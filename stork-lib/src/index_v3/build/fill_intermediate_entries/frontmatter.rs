@@ -1,7 +1,10 @@
 use frontmatter::{parse_and_find_content, Yaml};
 use std::collections::HashMap;
 
-use crate::{config::FrontmatterConfig, Fields};
+use crate::{config::FrontmatterConfig, index_v3::build::errors::SourceLocation, Fields};
+
+/// The length of the `---\n` line that opens a frontmatter block.
+const FRONTMATTER_OPENING_LINES: usize = 1;
 
 pub fn parse_frontmatter(handling: &FrontmatterConfig, buffer: &str) -> (Fields, Box<String>) {
     let default_output = (HashMap::new(), Box::new(buffer.to_string()));
@@ -21,9 +24,12 @@ pub fn parse_frontmatter(handling: &FrontmatterConfig, buffer: &str) -> (Fields,
                     .map(|(k, v)| {
                         (
                             k.into_string().unwrap_or_else(|| "".to_string()),
-                            v.clone().into_string().unwrap_or_else(|| {
-                                v.into_i64().map_or("error".to_string(), |i| i.to_string())
-                            }),
+                            match v {
+                                Yaml::Boolean(b) => b.to_string(),
+                                v => v.clone().into_string().unwrap_or_else(|| {
+                                    v.into_i64().map_or("error".to_string(), |i| i.to_string())
+                                }),
+                            },
                         )
                     })
                     .collect();
@@ -35,12 +41,32 @@ pub fn parse_frontmatter(handling: &FrontmatterConfig, buffer: &str) -> (Fields,
     }
 }
 
+/**
+ * If the buffer starts with a frontmatter block that isn't valid YAML,
+ * returns the parser's message and where in the buffer the error is.
+ */
+pub fn find_frontmatter_error(buffer: &str) -> Option<(String, SourceLocation)> {
+    parse_and_find_content(buffer).err().map(|error| {
+        let marker = error.marker();
+        (
+            error.to_string(),
+            SourceLocation {
+                line: marker.line() + FRONTMATTER_OPENING_LINES,
+                column: marker.col() + 1,
+            },
+        )
+    })
+}
+
 #[cfg(test)]
 #[allow(clippy::unnecessary_mut_passed)]
 mod tests {
     use crate::Fields;
 
     use super::*;
+    use crate::config::*;
+    use crate::index_v3::build::build;
+
     #[test]
     fn omit_option() {
         let expected: (Fields, String) = (HashMap::new(), "this is not".to_string());
@@ -93,4 +119,64 @@ this is not
         let computed = (output.0, output.1.to_string());
         assert_eq!(expected, computed)
     }
+
+    #[test]
+    fn parse_option_stringifies_booleans() {
+        let (fields, _) = parse_frontmatter(
+            &FrontmatterConfig::Parse,
+            "---\ndraft: true\nsearch: false\n---\n\nthis is not",
+        );
+
+        assert_eq!(fields.get("draft"), Some(&"true".to_string()));
+        assert_eq!(fields.get("search"), Some(&"false".to_string()));
+    }
+
+    #[test]
+    fn frontmatter_error_is_located() {
+        let (_, location) =
+            find_frontmatter_error("---\ntitle: fine\nbad: \"unterminated\n---\n\ncontent")
+                .unwrap();
+
+        assert_eq!(location, SourceLocation { line: 3, column: 6 });
+    }
+
+    #[test]
+    fn valid_frontmatter_has_no_error() {
+        assert_eq!(
+            find_frontmatter_error("---\ntitle: fine\n---\n\ncontent"),
+            None
+        );
+    }
+
+    #[test]
+    fn draft_frontmatter_excludes_file_from_index() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    File {
+                        explicit_source: Some(DataSource::Contents(
+                            "---\ndraft: true\n---\n\nThis is a draft".to_string(),
+                        )),
+                        title: "Draft File".to_string(),
+                        filetype: Some(Filetype::Markdown),
+                        ..Default::default()
+                    },
+                    File {
+                        explicit_source: Some(DataSource::Contents("This is contents".to_string())),
+                        title: "Successful File".to_string(),
+                        filetype: Some(Filetype::PlainText),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let build_results = build(&config).unwrap();
+
+        assert!(build_results.errors.is_empty());
+        assert_eq!(build_results.index.entries.len(), 1);
+        assert_eq!(build_results.index.entries[0].title, "Successful File");
+    }
 }
@@ -0,0 +1,283 @@
+use serde_json::Value;
+
+use crate::{config::JsonLdProperty, Fields};
+
+/**
+ * What was read from a document's JSON-LD blocks.
+ */
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct JsonLdData {
+    /// The values of properties to be indexed as search terms.
+    pub(super) keywords: Vec<String>,
+
+    /// The values of properties to be stored, keyed by field name.
+    pub(super) fields: Fields,
+
+    /// How many blocks weren't valid JSON, and were skipped.
+    pub(super) malformed_block_count: usize,
+}
+
+/**
+ * Reads the configured properties from each JSON-LD block. A property is
+ * read from every object of its type, wherever it's nested in the block,
+ * such as each `Question` in an `FAQPage` or each item of an `@graph`.
+ */
+pub(super) fn read_json_ld(blocks: &[String], properties: &[JsonLdProperty]) -> JsonLdData {
+    let mut data = JsonLdData::default();
+
+    for block in blocks {
+        let value: Value = match serde_json::from_str(block) {
+            Ok(value) => value,
+            Err(_) => {
+                data.malformed_block_count += 1;
+                continue;
+            }
+        };
+
+        let mut objects = vec![];
+        collect_objects(&value, &mut objects);
+
+        for property in properties {
+            let mut values = vec![];
+            for object in objects
+                .iter()
+                .filter(|object| has_type(object, property.schema_type.as_deref()))
+            {
+                let path: Vec<&str> = property.property.split('.').collect();
+                collect_values(object, &path, &mut values);
+            }
+
+            if values.is_empty() {
+                continue;
+            }
+
+            if let Some(field) = &property.field {
+                let stored = data.fields.entry(field.clone()).or_default();
+                for value in &values {
+                    if !stored.is_empty() {
+                        stored.push(' ');
+                    }
+                    stored.push_str(value);
+                }
+            }
+
+            if property.index {
+                data.keywords.extend(values);
+            }
+        }
+    }
+
+    data
+}
+
+fn collect_objects<'a>(value: &'a Value, objects: &mut Vec<&'a Value>) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                collect_objects(item, objects);
+            }
+        }
+        Value::Object(map) => {
+            objects.push(value);
+            for child in map.values() {
+                collect_objects(child, objects);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn has_type(object: &Value, schema_type: Option<&str>) -> bool {
+    let schema_type = match schema_type {
+        Some(schema_type) => schema_type,
+        None => return true,
+    };
+
+    match object.get("@type") {
+        Some(Value::String(object_type)) => object_type == schema_type,
+        Some(Value::Array(object_types)) => object_types
+            .iter()
+            .any(|object_type| object_type.as_str() == Some(schema_type)),
+        _ => false,
+    }
+}
+
+fn collect_values(value: &Value, path: &[&str], values: &mut Vec<String>) {
+    match (value, path.split_first()) {
+        (Value::Array(items), _) => {
+            for item in items {
+                collect_values(item, path, values);
+            }
+        }
+        (Value::Object(map), Some((name, rest))) => {
+            if let Some(child) = map.get(*name) {
+                collect_values(child, rest, values);
+            }
+        }
+        (Value::String(text), None) if !text.trim().is_empty() => {
+            values.push(text.trim().to_string());
+        }
+        (Value::Number(number), None) => values.push(number.to_string()),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::*;
+    use crate::index_v3::build::{build, errors::DocumentWarningKind};
+    use pretty_assertions::assert_eq;
+
+    const FAQ: &str = r#"{
+        "@context": "https://schema.org",
+        "@type": "FAQPage",
+        "name": "Stork FAQ",
+        "mainEntity": [
+            {
+                "@type": "Question",
+                "name": "Does Stork need a server?",
+                "acceptedAnswer": { "@type": "Answer", "text": "No, it runs in the browser." }
+            },
+            {
+                "@type": "Question",
+                "name": "How big is an index?",
+                "acceptedAnswer": { "@type": "Answer", "text": "Roughly the size of its documents." }
+            }
+        ]
+    }"#;
+
+    fn property(schema_type: Option<&str>, path: &str, field: Option<&str>) -> JsonLdProperty {
+        JsonLdProperty {
+            schema_type: schema_type.map(ToString::to_string),
+            property: path.to_string(),
+            field: field.map(ToString::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn properties_are_read_through_nested_arrays() {
+        let data = read_json_ld(
+            &[FAQ.to_string()],
+            &[
+                property(Some("FAQPage"), "mainEntity.acceptedAnswer.text", None),
+                property(None, "name", Some("name")),
+            ],
+        );
+
+        assert_eq!(
+            data.keywords,
+            vec![
+                "No, it runs in the browser.",
+                "Roughly the size of its documents.",
+                "Stork FAQ",
+                "Does Stork need a server?",
+                "How big is an index?",
+            ]
+        );
+        assert_eq!(
+            data.fields.get("name").map(String::as_str),
+            Some("Stork FAQ Does Stork need a server? How big is an index?")
+        );
+        assert_eq!(data.malformed_block_count, 0);
+    }
+
+    #[test]
+    fn types_must_match_and_graphs_are_searched() {
+        let graph = r#"{ "@graph": [
+            { "@type": ["Article", "NewsArticle"], "headline": "Stork 2.0" },
+            { "@type": "Person", "headline": "Not an article" }
+        ] }"#;
+
+        let data = read_json_ld(
+            &[graph.to_string()],
+            &[property(Some("Article"), "headline", None)],
+        );
+        assert_eq!(data.keywords, vec!["Stork 2.0"]);
+    }
+
+    #[test]
+    fn malformed_blocks_are_counted_and_skipped() {
+        let data = read_json_ld(
+            &["{ \"@type\": ".to_string(), FAQ.to_string()],
+            &[property(Some("FAQPage"), "name", None)],
+        );
+
+        assert_eq!(data.keywords, vec!["Stork FAQ"]);
+        assert_eq!(data.malformed_block_count, 1);
+    }
+
+    #[test]
+    fn json_ld_faq_answers_are_searchable() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        r#"<html>
+                            <head>
+                                <script type="application/ld+json">
+                                    {
+                                        "@context": "https://schema.org",
+                                        "@type": "FAQPage",
+                                        "mainEntity": [{
+                                            "@type": "Question",
+                                            "name": "Does Stork need a server?",
+                                            "acceptedAnswer": {
+                                                "@type": "Answer",
+                                                "text": "No, the index is searched by WebAssembly in the browser."
+                                            }
+                                        }]
+                                    }
+                                </script>
+                                <script type="application/ld+json">{ "@type": </script>
+                            </head>
+                            <body><main><p>Answers to common questions</p></main></body>
+                        </html>"#
+                            .to_string(),
+                    )),
+                    title: "FAQ".to_string(),
+                    url: "/faq/".to_string(),
+                    filetype: Some(Filetype::HTML),
+                    ..Default::default()
+                }],
+                json_ld_properties: vec![
+                    JsonLdProperty {
+                        schema_type: Some("FAQPage".to_string()),
+                        property: "mainEntity.acceptedAnswer.text".to_string(),
+                        ..Default::default()
+                    },
+                    JsonLdProperty {
+                        schema_type: Some("Question".to_string()),
+                        property: "name".to_string(),
+                        index: false,
+                        field: Some("question".to_string()),
+                    },
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let build_results = build(&config).unwrap();
+        assert!(build_results
+            .warnings
+            .iter()
+            .any(|warning| warning.kind == DocumentWarningKind::MalformedJsonLd(1)));
+
+        let index = build_results.index;
+        assert_eq!(
+            index.entries[0].fields.get("question"),
+            Some(&"Does Stork need a server?".to_string())
+        );
+
+        let output = crate::index_v3::search(&index, "webassembly").unwrap();
+        assert_eq!(output.results.len(), 1);
+        assert_eq!(output.results[0].entry.url, "/faq/");
+
+        assert!(crate::index_v3::search(&index, "server")
+            .unwrap()
+            .results
+            .is_empty());
+    }
+}
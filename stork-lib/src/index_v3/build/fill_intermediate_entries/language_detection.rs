@@ -0,0 +1,107 @@
+use rust_stemmers::Algorithm;
+
+use crate::index_v3::AnnotatedWordList;
+
+/// A document's language is clear well before its end, so detection only
+/// looks at this many of its words.
+#[cfg(feature = "build-v3-langdetect")]
+const SAMPLE_WORD_COUNT: usize = 500;
+
+/**
+ * The language a document was detected to be written in, and the stemmer
+ * for that language.
+ */
+#[derive(Debug, PartialEq)]
+pub(super) struct DetectedLanguage {
+    /// The language's ISO 639-3 code, like `deu`.
+    pub(super) code: &'static str,
+    pub(super) algorithm: Algorithm,
+}
+
+/**
+ * Detects the language of a document's words. Returns `None` if detection
+ * is less confident than `threshold`, or if Stork has no stemmer for the
+ * language it found.
+ */
+#[cfg(feature = "build-v3-langdetect")]
+pub(super) fn detect_language(
+    word_list: &AnnotatedWordList,
+    threshold: f64,
+) -> Option<DetectedLanguage> {
+    let sample = word_list
+        .word_list
+        .iter()
+        .take(SAMPLE_WORD_COUNT)
+        .map(|annotated_word| &*annotated_word.word)
+        .collect::<Vec<&str>>()
+        .join(" ");
+
+    let info = whatlang::detect(&sample)?;
+    if info.confidence() < threshold {
+        return None;
+    }
+
+    Some(DetectedLanguage {
+        code: info.lang().code(),
+        algorithm: stemmer_for_language_tag(info.lang().code())?,
+    })
+}
+
+#[cfg(not(feature = "build-v3-langdetect"))]
+pub(super) fn detect_language(
+    _word_list: &AnnotatedWordList,
+    _threshold: f64,
+) -> Option<DetectedLanguage> {
+    None
+}
+
+/**
+ * The stemmer for a language given as a BCP 47 tag, like `de` or `pt-BR`, or
+ * as an ISO 639-3 code, like `deu`.
+ */
+pub(super) fn stemmer_for_language_tag(tag: &str) -> Option<Algorithm> {
+    let language = tag.trim().split(|c| c == '-' || c == '_').next()?;
+
+    match language.to_ascii_lowercase().as_str() {
+        "ar" | "ara" => Some(Algorithm::Arabic),
+        "da" | "dan" => Some(Algorithm::Danish),
+        "nl" | "nld" => Some(Algorithm::Dutch),
+        "en" | "eng" => Some(Algorithm::English),
+        "fi" | "fin" => Some(Algorithm::Finnish),
+        "fr" | "fra" => Some(Algorithm::French),
+        "de" | "deu" => Some(Algorithm::German),
+        "el" | "ell" => Some(Algorithm::Greek),
+        "hu" | "hun" => Some(Algorithm::Hungarian),
+        "it" | "ita" => Some(Algorithm::Italian),
+        "no" | "nb" | "nn" | "nob" | "nno" => Some(Algorithm::Norwegian),
+        "pt" | "por" => Some(Algorithm::Portuguese),
+        "ro" | "ron" => Some(Algorithm::Romanian),
+        "ru" | "rus" => Some(Algorithm::Russian),
+        "es" | "spa" => Some(Algorithm::Spanish),
+        "sv" | "swe" => Some(Algorithm::Swedish),
+        "ta" | "tam" => Some(Algorithm::Tamil),
+        "tr" | "tur" => Some(Algorithm::Turkish),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn language_tags_pick_stemmers() {
+        assert_eq!(stemmer_for_language_tag("de"), Some(Algorithm::German));
+        assert_eq!(
+            stemmer_for_language_tag("pt-BR"),
+            Some(Algorithm::Portuguese)
+        );
+        assert_eq!(
+            stemmer_for_language_tag("NB_no"),
+            Some(Algorithm::Norwegian)
+        );
+        assert_eq!(stemmer_for_language_tag("eng"), Some(Algorithm::English));
+        assert_eq!(stemmer_for_language_tag("ja"), None);
+        assert_eq!(stemmer_for_language_tag(""), None);
+    }
+}
@@ -1,20 +1,61 @@
-use super::{DocumentError, WordListGenerationError};
+use super::{
+    annotated_words_from_string::{join_hyphenated_line_breaks, truncate_long_words},
+    assign_facets::longest_matching_prefix,
+    errors::{DocumentWarning, DocumentWarningKind},
+    exclude::{exclusion_rule, ExcludedDocument},
+    DocumentError, WordListGenerationError,
+};
 
 mod data_source_readers;
+pub(super) use data_source_readers::expected_filetype;
 use data_source_readers::read_from_data_source;
+pub(super) use data_source_readers::WebClient;
 
 mod word_list_generators;
-use word_list_generators::create_word_list;
+use word_list_generators::{
+    create_word_list,
+    html_word_list_generator::{
+        read_html_lang, read_json_ld_blocks, read_meta_tags, read_prefixed_meta_tags,
+    },
+};
 
 mod frontmatter;
-use self::frontmatter::parse_frontmatter;
+use self::frontmatter::{find_frontmatter_error, parse_frontmatter};
+
+mod json_ld;
+use json_ld::read_json_ld;
 
-use super::{IndexGenerationError, NormalizedEntry};
+mod thumbnails;
+use thumbnails::{is_valid_thumbnail_url, resolve_thumbnail_url};
+
+mod language_detection;
+use language_detection::{detect_language, stemmer_for_language_tag};
+
+use super::{
+    boilerplate::Boilerplate,
+    canonical_url::canonical_url,
+    driver::BuildDriver,
+    intermediate_entry::{stored_text, EntrySink},
+    BuildProgress, IndexGenerationError, NormalizedEntry,
+};
 use crate::config::{
-    Config, DataSource, File, Filetype, InputConfig, OutputConfig, StemmingConfig,
+    Config, DataSource, File, Filetype, FiletypeDefaults, FrontmatterConfig,
+    HyphenatedLineBreakHandling, InputConfig, OutputConfig, StemmingConfig,
+    UrlCanonicalizationConfig, WarningHandling,
 };
-use indicatif::{ProgressBar, ProgressDrawTarget, ProgressIterator, ProgressStyle};
-use std::{collections::HashMap, convert::TryInto};
+use crate::index_v3::{
+    width::normalize_width, AnnotatedWordList, DATE_FIELD, LANGUAGE_FIELD, THUMBNAIL_FIELD,
+};
+use crate::Date;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use rayon::{prelude::*, ThreadPool};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    convert::TryInto,
+    sync::{Arc, Mutex},
+};
+use tracing::Instrument;
 
 use unicode_segmentation::UnicodeSegmentation;
 
@@ -29,28 +70,94 @@ pub struct ReadResult {
     /// use the filetype here if it's available.
     pub(super) filetype: Option<Filetype>,
 
-    #[allow(dead_code)]
+    /// The fields found in the document's frontmatter, whether or not the
+    /// configured frontmatter handling keeps them in the index.
     pub(super) frontmatter_fields: Option<HashMap<String, String>>,
+
+    /// A large plain-text file's words, read straight from the file in
+    /// chunks instead of into `buffer`, which is left empty.
+    pub(super) streamed_word_list: Option<AnnotatedWordList>,
 }
 
 impl ReadResult {
-    fn extract_frontmatter(&self, config: &ReaderConfig) -> Self {
-        let handling = config
-            .file
-            .frontmatter_handling_override
-            .as_ref()
-            .unwrap_or(&config.global.frontmatter_handling);
+    fn extract_frontmatter(self, config: &ReaderConfig) -> Result<Self, WordListGenerationError> {
+        let handling = config.frontmatter_handling(self.filetype.as_ref());
 
-        let (frontmatter_fields, buffer) = parse_frontmatter(handling, &self.buffer);
+        if *handling == FrontmatterConfig::Parse {
+            if let Some((message, location)) = find_frontmatter_error(&self.buffer) {
+                return Err(WordListGenerationError::InvalidFrontmatter(
+                    message, location,
+                ));
+            }
+        }
+
+        let (_, buffer) = parse_frontmatter(handling, &self.buffer);
+
+        let mut frontmatter_fields = match handling {
+            FrontmatterConfig::Ignore => HashMap::new(),
+            _ => parse_frontmatter(&FrontmatterConfig::Parse, &self.buffer).0,
+        };
 
-        ReadResult {
+        if let (Some(Filetype::HTML), Some(prefix)) =
+            (&self.filetype, &config.global.html_meta_prefix)
+        {
+            for (name, content) in read_prefixed_meta_tags(&buffer, prefix) {
+                frontmatter_fields.entry(name).or_insert(content);
+            }
+        }
+
+        Ok(ReadResult {
             buffer: buffer.to_string(),
-            filetype: self.filetype.clone(),
+            filetype: self.filetype,
             frontmatter_fields: Some(frontmatter_fields),
+            streamed_word_list: self.streamed_word_list,
+        })
+    }
+
+    fn normalize_width(mut self, config: &ReaderConfig) -> Self {
+        if config.global.normalize_width {
+            if let Cow::Owned(buffer) = normalize_width(&self.buffer) {
+                self.buffer = buffer;
+            }
         }
+
+        self
+    }
+
+    fn repair_hyphenated_line_breaks(mut self, config: &ReaderConfig) -> Self {
+        if config.global.hyphenated_line_breaks == HyphenatedLineBreakHandling::Join {
+            if let Cow::Owned(buffer) = join_hyphenated_line_breaks(&self.buffer) {
+                self.buffer = buffer;
+            }
+        }
+
+        self
+    }
+
+    fn is_excluded_by_frontmatter(&self, exclusions: &HashMap<String, String>) -> bool {
+        self.frontmatter_fields.as_ref().map_or(false, |fields| {
+            exclusions.iter().any(|(key, excluded_value)| {
+                fields
+                    .get(key)
+                    .map_or(false, |value| value.eq_ignore_ascii_case(excluded_value))
+            })
+        })
     }
 }
 
+/**
+ * What's found out about the documents while they're read, besides their
+ * entries: the errors that kept documents out of the index, the warnings
+ * about documents that were indexed, and the documents `input.exclude` left
+ * out, each in the order the documents were read.
+ */
+#[derive(Debug, Default)]
+pub(super) struct DocumentReports {
+    pub(super) errors: Vec<DocumentError>,
+    pub(super) warnings: Vec<DocumentWarning>,
+    pub(super) excluded: Vec<ExcludedDocument>,
+}
+
 pub struct ReaderConfig {
     pub global: InputConfig,
     pub file: File,
@@ -58,88 +165,742 @@ pub struct ReaderConfig {
 }
 
 impl ReaderConfig {
-    fn get_stem_algorithm(&self) -> Option<rust_stemmers::Algorithm> {
-        let current_stem_config = self
-            .file
-            .stemming_override
+    fn new(config: &Config, file: &File) -> Self {
+        ReaderConfig {
+            global: config.input.clone(),
+            file: file.clone(),
+            output: config.output.clone(),
+        }
+    }
+
+    /// The `input.filetype_defaults` for a document read as `filetype`.
+    fn filetype_defaults(&self, filetype: Option<&Filetype>) -> Option<&FiletypeDefaults> {
+        self.global.filetype_defaults.get(filetype?)
+    }
+
+    /// The document's own stemming override, or else its filetype's default.
+    fn stemming_override(&self, filetype: Option<&Filetype>) -> Option<&StemmingConfig> {
+        self.file.stemming_override.as_ref().or_else(|| {
+            self.filetype_defaults(filetype)
+                .and_then(|defaults| defaults.stemming.as_ref())
+        })
+    }
+
+    /// The tag of the `input.language_url_prefixes` prefix that the
+    /// document's URL matches, if any.
+    fn url_language(&self) -> Option<&str> {
+        longest_matching_prefix(&self.file.url, &self.global.language_url_prefixes)
+    }
+
+    fn frontmatter_handling(&self, filetype: Option<&Filetype>) -> &FrontmatterConfig {
+        self.file
+            .frontmatter_handling_override
+            .as_ref()
+            .or_else(|| {
+                self.filetype_defaults(filetype)
+                    .and_then(|defaults| defaults.frontmatter_handling.as_ref())
+            })
+            .unwrap_or(&self.global.frontmatter_handling)
+    }
+
+    fn html_selector(&self, filetype: Option<&Filetype>) -> Option<&str> {
+        self.file
+            .html_selector_override
+            .as_ref()
+            .or_else(|| {
+                self.filetype_defaults(filetype)
+                    .and_then(|defaults| defaults.html_selector.as_ref())
+            })
+            .or(self.global.html_selector.as_ref())
+            .map(String::as_str)
+    }
+
+    fn exclude_html_selector(&self, filetype: Option<&Filetype>) -> Option<&str> {
+        self.file
+            .exclude_html_selector_override
             .as_ref()
+            .or_else(|| {
+                self.filetype_defaults(filetype)
+                    .and_then(|defaults| defaults.exclude_html_selector.as_ref())
+            })
+            .or(self.global.exclude_html_selector.as_ref())
+            .map(String::as_str)
+    }
+
+    fn get_stem_algorithm(&self, filetype: Option<&Filetype>) -> Option<rust_stemmers::Algorithm> {
+        if self.file.filetype == Some(Filetype::PlainTextNoStem) {
+            return None;
+        }
+
+        let current_stem_config = self
+            .stemming_override(filetype)
             .unwrap_or(&self.global.stemming);
 
         match current_stem_config {
             StemmingConfig::Language(alg) => Some(*alg),
-            StemmingConfig::None => None,
+            StemmingConfig::None | StemmingConfig::Unsupported(_) => None,
         }
     }
+
+    /// Documents with their own stemming override, or whose filetype has a
+    /// default one, keep it, and plain text that shouldn't be stemmed isn't.
+    /// Without the `build-v3-langdetect` feature, the config option only
+    /// produces a nudge.
+    fn should_detect_language(&self, filetype: Option<&Filetype>) -> bool {
+        cfg!(feature = "build-v3-langdetect")
+            && self.global.detect_language
+            && self.stemming_override(filetype).is_none()
+            && self.file.filetype != Some(Filetype::PlainTextNoStem)
+    }
 }
 
-pub(super) fn fill_intermediate_entries(
+/**
+ * Reads every document in the config into `intermediate_entries`, recording
+ * what's found out about them in `reports`. URLs are fetched ahead of time
+ * if `driver` can do so without blocking, and the rest of the work for each
+ * document runs on `pool`.
+ *
+ * Documents are read one pool-sized chunk at a time, so progress events
+ * still arrive shortly before each document is read, a single-thread pool
+ * reads documents strictly in order, and dropping an async build's future
+ * between chunks cancels it.
+ */
+pub(super) async fn fill_intermediate_entries<D, S>(
     config: &Config,
-    intermediate_entries: &mut Vec<NormalizedEntry>,
-    document_errors: &mut Vec<DocumentError>,
-) -> Result<(), IndexGenerationError> {
+    pool: &Arc<ThreadPool>,
+    web_client: &Arc<WebClient>,
+    intermediate_entries: &Arc<Mutex<S>>,
+    reports: &mut DocumentReports,
+    driver: &mut D,
+) -> Result<(), IndexGenerationError>
+where
+    D: BuildDriver,
+    S: EntrySink + Send + 'static,
+{
     if config.input.files.is_empty() {
         return Err(IndexGenerationError::NoFilesSpecified);
     }
 
     let progress_bar = build_progress_bar(config);
+    let total = config.input.files.len();
+    let files: Vec<(usize, &File)> = config.input.files.iter().enumerate().collect();
+    let mut seen_documents = SeenDocuments::new(&config.input.url_canonicalization);
 
-    for stork_file in config
-        .input
-        .files
-        .iter()
-        .progress_with(progress_bar.clone())
-    {
-        let reader_config = ReaderConfig {
-            global: config.input.clone(),
-            file: stork_file.clone(),
-            output: config.output.clone(),
+    for chunk in files.chunks(pool.current_num_threads()) {
+        let mut documents = Vec::with_capacity(chunk.len());
+
+        for (index, stork_file) in chunk {
+            driver
+                .report(BuildProgress::StartedDocument {
+                    index: *index,
+                    total,
+                    title: stork_file.title.clone(),
+                })
+                .await;
+
+            tick_progress_bar_with_filename(&progress_bar, &stork_file.title);
+
+            let reader_config = ReaderConfig::new(config, stork_file);
+            let document_span =
+                tracing::debug_span!("document", index = *index, title = %stork_file.title);
+            let prefetched = driver
+                .prefetch(&reader_config, web_client)
+                .instrument(document_span.clone())
+                .await;
+            documents.push((reader_config, prefetched, document_span));
+        }
+
+        let chunk_web_client = Arc::clone(web_client);
+        let intermediate_entry_results: Vec<_> = driver
+            .run_on_pool(pool, move || {
+                documents
+                    .into_par_iter()
+                    .map(|(reader_config, prefetched, document_span)| {
+                        let _document_span = document_span.entered();
+                        normalize_prefetched(&reader_config, prefetched, &chunk_web_client)
+                    })
+                    .collect()
+            })
+            .await?;
+
+        {
+            let mut intermediate_entries = intermediate_entries.lock().unwrap();
+            for ((_, stork_file), intermediate_entry_result) in
+                chunk.iter().zip(intermediate_entry_results)
+            {
+                record_intermediate_entry_result(
+                    config,
+                    stork_file,
+                    intermediate_entry_result,
+                    &mut *intermediate_entries,
+                    &mut seen_documents,
+                    reports,
+                )?;
+            }
+        }
+
+        let flushed_entries = Arc::clone(intermediate_entries);
+        driver
+            .run_on_pool(pool, move || flushed_entries.lock().unwrap().flush())
+            .await?;
+        progress_bar.inc(chunk.len() as u64);
+    }
+
+    progress_bar.finish_using_style();
+
+    finish_filling_intermediate_entries(config, &reports.errors)
+}
+
+/**
+ * Fetches a document from the web without blocking, for an async build.
+ * Documents from anywhere else are read on the build's pool, so there's
+ * nothing to fetch for them.
+ */
+#[cfg(feature = "async")]
+pub(super) async fn prefetch(
+    reader_config: &ReaderConfig,
+    web_client: &WebClient,
+) -> Option<Result<ReadResult, WordListGenerationError>> {
+    match reader_config.file.source() {
+        DataSource::URL(url) => Some(
+            data_source_readers::url_data_source_reader::read_async(
+                &url,
+                reader_config,
+                web_client,
+            )
+            .await
+            .and_then(|read_result| read_result.extract_frontmatter(reader_config)),
+        ),
+        _ => None,
+    }
+}
+
+fn normalize_prefetched(
+    reader_config: &ReaderConfig,
+    prefetched: Option<Result<ReadResult, WordListGenerationError>>,
+    web_client: &WebClient,
+) -> Result<Option<NormalizedEntry>, WordListGenerationError> {
+    match prefetched {
+        Some(read_result) => normalize(reader_config, read_result?),
+        None => read_and_normalize(reader_config, web_client),
+    }
+}
+
+fn read_and_normalize(
+    reader_config: &ReaderConfig,
+    web_client: &WebClient,
+) -> Result<Option<NormalizedEntry>, WordListGenerationError> {
+    let read_result = read_from_data_source(reader_config, web_client)?;
+    normalize(reader_config, read_result)
+}
+
+/**
+ * Turns the contents of a document into a `NormalizedEntry`, or returns
+ * `None` if the document's frontmatter excludes it from the index.
+ */
+fn normalize(
+    reader_config: &ReaderConfig,
+    mut read_result: ReadResult,
+) -> Result<Option<NormalizedEntry>, WordListGenerationError> {
+    if read_result.is_excluded_by_frontmatter(&reader_config.global.exclude_frontmatter_fields) {
+        return Ok(None);
+    }
+
+    let mut annotated_word_list = match read_result.streamed_word_list.take() {
+        Some(word_list) => word_list,
+        None => create_word_list(reader_config, &read_result)?,
+    };
+
+    let boilerplate = Boilerplate::new(&reader_config.global);
+    if !boilerplate.is_empty() {
+        boilerplate.remove_from(&mut annotated_word_list.word_list);
+    }
+
+    let maximum_words = reader_config.global.maximum_words_per_document;
+    let word_count = annotated_word_list.word_list.len();
+    let unlimited_word_count = if word_count > maximum_words {
+        annotated_word_list.word_list.truncate(maximum_words);
+        Some(word_count)
+    } else {
+        None
+    };
+    let truncated_word_count = truncate_long_words(
+        &mut annotated_word_list.word_list,
+        reader_config.global.maximum_token_length,
+    );
+
+    if annotated_word_list.word_list.is_empty() {
+        return Err(WordListGenerationError::EmptyWordList);
+    }
+
+    let mut fields = reader_config.file.fields.clone();
+    let mut keywords = vec![];
+    let mut malformed_json_ld_blocks = 0;
+    let mut og_image = None;
+    let mut meta_description = None;
+    let extract_thumbnail =
+        reader_config.global.extract_thumbnails && reader_config.file.thumbnail_url.is_none();
+    let extract_summary =
+        reader_config.global.extract_summaries && reader_config.file.summary.is_none();
+
+    if read_result.filetype == Some(Filetype::HTML) {
+        let global = &reader_config.global;
+        let mut meta_tags = if global.html_meta_tags.is_empty()
+            && !global.index_meta_keywords
+            && !extract_thumbnail
+            && !extract_summary
+        {
+            HashMap::new()
+        } else {
+            let mut names = global.html_meta_tags.clone();
+            names.push(KEYWORDS_META_TAG.to_string());
+            names.push(THUMBNAIL_META_TAG.to_string());
+            names.push(SUMMARY_META_TAG.to_string());
+            read_meta_tags(&read_result.buffer, &names)
         };
 
-        tick_progress_bar_with_filename(&progress_bar, &stork_file.title);
+        if extract_thumbnail {
+            og_image = meta_tags.get(THUMBNAIL_META_TAG).cloned();
+        }
 
-        let intermediate_entry_result: Result<NormalizedEntry, WordListGenerationError> =
-            || -> Result<NormalizedEntry, WordListGenerationError> {
-                let read_result = read_from_data_source(&reader_config)?;
-                let annotated_word_list = create_word_list(&reader_config, &read_result)?;
+        if extract_summary {
+            meta_description = meta_tags.get(SUMMARY_META_TAG).cloned();
+        }
 
-                if annotated_word_list.word_list.is_empty() {
-                    return Err(WordListGenerationError::EmptyWordList);
-                }
+        if global.index_meta_keywords {
+            if let Some(content) = meta_tags.get(KEYWORDS_META_TAG) {
+                keywords = content
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|keyword| !keyword.is_empty())
+                    .map(ToString::to_string)
+                    .collect();
+            }
+        }
 
-                Ok(NormalizedEntry {
-                    annotated_word_list,
-                    stem_algorithm: reader_config.get_stem_algorithm(),
-                    title: stork_file.title.clone(),
-                    url: stork_file.url.clone(),
-                    fields: reader_config.file.fields,
-                })
-            }();
+        meta_tags.retain(|name, _| global.html_meta_tags.contains(name));
+
+        // Fields set in the config take precedence over the document's own.
+        for (name, content) in meta_tags {
+            fields.entry(name).or_insert(content);
+        }
+
+        if !global.json_ld_properties.is_empty() {
+            let json_ld = read_json_ld(
+                &read_json_ld_blocks(&read_result.buffer),
+                &global.json_ld_properties,
+            );
 
-        match intermediate_entry_result {
-            Ok(ie) => {
-                intermediate_entries.push(ie);
+            keywords.extend(json_ld.keywords);
+            malformed_json_ld_blocks = json_ld.malformed_block_count;
+            for (name, content) in json_ld.fields {
+                fields.entry(name).or_insert(content);
             }
+        }
+    }
 
-            Err(e) => {
-                let document_error = DocumentError {
-                    file: stork_file.clone(),
-                    word_list_generation_error: e,
-                };
+    let thumbnail = if extract_thumbnail {
+        read_result
+            .frontmatter_fields
+            .as_ref()
+            .and_then(|frontmatter_fields| {
+                frontmatter_fields.get(&reader_config.global.thumbnail_frontmatter_field)
+            })
+            .cloned()
+            .or(og_image)
+    } else {
+        reader_config.file.thumbnail_url.clone()
+    };
 
-                if config.input.break_on_file_error {
-                    return Err(IndexGenerationError::PartialDocumentErrors(vec![
-                        document_error,
-                    ]));
-                }
+    let summary = if extract_summary {
+        read_result
+            .frontmatter_fields
+            .as_ref()
+            .and_then(|frontmatter_fields| {
+                frontmatter_fields.get(&reader_config.global.summary_frontmatter_field)
+            })
+            .cloned()
+            .or(meta_description)
+    } else {
+        reader_config.file.summary.clone()
+    }
+    .filter(|summary| !summary.trim().is_empty());
 
-                document_errors.push(document_error)
+    let mut invalid_thumbnail = None;
+    if let Some(thumbnail) = thumbnail.map(|thumbnail| thumbnail.trim().to_string()) {
+        if is_valid_thumbnail_url(&thumbnail) {
+            // Configured thumbnails are joined to `url_prefix` at search time
+            // instead, so only the ones read from the document are resolved.
+            let thumbnail = if extract_thumbnail {
+                resolve_thumbnail_url(thumbnail, reader_config.file.base_url.as_deref())
+            } else {
+                thumbnail
+            };
+            fields
+                .entry(THUMBNAIL_FIELD.to_string())
+                .or_insert(thumbnail);
+        } else {
+            invalid_thumbnail = Some(thumbnail);
+        }
+    }
+
+    let date = if reader_config.global.extract_dates && reader_config.file.date.is_none() {
+        read_result
+            .frontmatter_fields
+            .as_ref()
+            .and_then(|frontmatter_fields| {
+                frontmatter_fields.get(&reader_config.global.date_frontmatter_field)
+            })
+            .cloned()
+    } else {
+        reader_config.file.date.clone()
+    };
+
+    let mut invalid_date = None;
+    if let Some(date) = date.filter(|date| !date.trim().is_empty()) {
+        match date.parse::<Date>() {
+            // Searches read dates back from this field, so it always holds
+            // the normalized form, even if the fields already have a date.
+            Ok(parsed) => {
+                fields.insert(DATE_FIELD.to_string(), parsed.to_string());
+            }
+            Err(_) => invalid_date = Some(date),
+        }
+    }
+
+    let mut stem_algorithm = reader_config.get_stem_algorithm(read_result.filetype.as_ref());
+    let mut language_undetected = false;
+
+    // A document under one of the languages' URL prefixes is stemmed in that
+    // language, unless it's stemmed some other way of its own.
+    let url_language = reader_config.url_language();
+    if let Some(language) = url_language {
+        fields
+            .entry(LANGUAGE_FIELD.to_string())
+            .or_insert_with(|| language.to_string());
+    }
+    let url_language_algorithm = url_language.and_then(stemmer_for_language_tag).filter(|_| {
+        reader_config
+            .stemming_override(read_result.filetype.as_ref())
+            .is_none()
+            && reader_config.file.filetype != Some(Filetype::PlainTextNoStem)
+    });
+
+    if let Some(algorithm) = url_language_algorithm {
+        stem_algorithm = Some(algorithm);
+    } else if reader_config.should_detect_language(read_result.filetype.as_ref()) {
+        let declared_algorithm = declared_language(&read_result)
+            .as_deref()
+            .and_then(stemmer_for_language_tag);
+        let threshold = reader_config.global.language_detection_threshold;
+
+        if let Some(algorithm) = declared_algorithm {
+            stem_algorithm = Some(algorithm);
+        } else if let Some(language) = detect_language(&annotated_word_list, threshold) {
+            stem_algorithm = Some(language.algorithm);
+            fields
+                .entry(DETECTED_LANGUAGE_FIELD.to_string())
+                .or_insert_with(|| language.code.to_string());
+        } else {
+            language_undetected = true;
+        }
+    }
+
+    let stored_text = stored_text(&reader_config.output, &annotated_word_list);
+
+    Ok(Some(NormalizedEntry {
+        annotated_word_list,
+        stem_algorithm,
+        title: if reader_config.global.normalize_width {
+            normalize_width(&reader_config.file.title).into_owned()
+        } else {
+            reader_config.file.title.clone()
+        },
+        url: reader_config.file.url.clone(),
+        fields,
+        keywords,
+        summary,
+        language_undetected,
+        malformed_json_ld_blocks,
+        invalid_thumbnail,
+        invalid_date,
+        truncated_word_count,
+        unlimited_word_count,
+        stored_text,
+        generate_excerpts: reader_config.file.generate_excerpts,
+    }))
+}
+
+/**
+ * The language a document says it's written in, with a `lang` or `language`
+ * frontmatter field or, for HTML, the `<html lang>` attribute.
+ */
+fn declared_language(read_result: &ReadResult) -> Option<String> {
+    let frontmatter_language = read_result.frontmatter_fields.as_ref().and_then(|fields| {
+        fields
+            .get("lang")
+            .or_else(|| fields.get("language"))
+            .cloned()
+    });
+
+    frontmatter_language.or_else(|| match read_result.filetype {
+        Some(Filetype::HTML) => read_html_lang(&read_result.buffer),
+        _ => None,
+    })
+}
+
+/// The `<meta>` tag whose comma-separated terms `index_meta_keywords` indexes.
+const KEYWORDS_META_TAG: &str = "keywords";
+
+/// The `<meta>` tag `extract_thumbnails` reads HTML documents' thumbnails
+/// from.
+const THUMBNAIL_META_TAG: &str = "og:image";
+
+/// The `<meta>` tag `extract_summaries` reads HTML documents' summaries from.
+const SUMMARY_META_TAG: &str = "description";
+
+/// The field a document's detected language is stored in, as an ISO 639-3
+/// code like `deu`.
+pub(super) const DETECTED_LANGUAGE_FIELD: &str = "detected_language";
+
+fn record_intermediate_entry_result(
+    config: &Config,
+    stork_file: &File,
+    intermediate_entry_result: Result<Option<NormalizedEntry>, WordListGenerationError>,
+    intermediate_entries: &mut dyn EntrySink,
+    seen_documents: &mut SeenDocuments,
+    reports: &mut DocumentReports,
+) -> Result<(), IndexGenerationError> {
+    if let Ok(Some(entry)) = &intermediate_entry_result {
+        if let Some(rule) = exclusion_rule(&config.input.exclude, stork_file, entry) {
+            tracing::debug!(file = %stork_file, %rule, "Skipping document: excluded by `input.exclude`");
+            reports.excluded.push(ExcludedDocument {
+                file: stork_file.clone(),
+                rule,
+            });
+            return Ok(());
+        }
+    }
+
+    let intermediate_entry_result = intermediate_entry_result.and_then(|entry| match entry {
+        Some(entry) => {
+            let warnings = find_document_warnings(config, &entry, seen_documents);
+            handle_document_warnings(config, stork_file, warnings, &mut reports.warnings)?;
+            Ok(Some(entry))
+        }
+        None => Ok(None),
+    });
+
+    match intermediate_entry_result {
+        Ok(Some(ie)) if seen_documents.has_url(&ie.url) => {
+            tracing::warn!(file = %stork_file, url = %ie.url, "Skipping document: another document has the same URL");
+        }
+
+        Ok(Some(ie)) => {
+            seen_documents.record(&ie, stork_file);
+            intermediate_entries.push(ie);
+        }
+
+        Ok(None) => {
+            tracing::warn!(file = %stork_file, "Skipping document: excluded by its frontmatter");
+        }
+
+        Err(e) => {
+            tracing::debug!(file = %stork_file, error = %e, "Document could not be indexed");
+
+            let document_error = DocumentError {
+                file: stork_file.clone(),
+                word_list_generation_error: e,
+            };
+
+            if config.input.break_on_file_error {
+                return Err(IndexGenerationError::PartialDocumentErrors(vec![
+                    document_error,
+                ]));
+            }
+
+            reports.errors.push(document_error)
+        }
+    };
+
+    Ok(())
+}
+
+/// Documents with fewer words than this are reported with a warning.
+const FEW_WORDS_THRESHOLD: usize = 10;
+
+/**
+ * The title and URL of each document that has been indexed so far, along
+ * with the source of the first document to use each one. URLs are keyed by
+ * their canonical form, and keep the URL the document was given.
+ */
+struct SeenDocuments {
+    titles: HashMap<String, String>,
+    urls: HashMap<String, SeenUrl>,
+    canonicalization: UrlCanonicalizationConfig,
+}
+
+struct SeenUrl {
+    url: String,
+    source: String,
+}
+
+impl SeenDocuments {
+    fn new(canonicalization: &UrlCanonicalizationConfig) -> Self {
+        SeenDocuments {
+            titles: HashMap::new(),
+            urls: HashMap::new(),
+            canonicalization: canonicalization.clone(),
+        }
+    }
+
+    /// The document that already has `url`, or a URL with the same
+    /// canonical form.
+    fn with_url(&self, url: &str) -> Option<&SeenUrl> {
+        if url.is_empty() {
+            return None;
+        }
+
+        self.urls.get(&canonical_url(url, &self.canonicalization))
+    }
+
+    fn has_url(&self, url: &str) -> bool {
+        self.with_url(url).is_some()
+    }
+
+    fn record(&mut self, entry: &NormalizedEntry, stork_file: &File) {
+        let source = stork_file.to_string();
+
+        if !entry.url.is_empty() {
+            self.urls
+                .entry(canonical_url(&entry.url, &self.canonicalization))
+                .or_insert_with(|| SeenUrl {
+                    url: entry.url.clone(),
+                    source: source.clone(),
+                });
+        }
+
+        self.titles.entry(entry.title.clone()).or_insert(source);
+    }
+}
+
+fn find_document_warnings(
+    config: &Config,
+    entry: &NormalizedEntry,
+    seen_documents: &SeenDocuments,
+) -> Vec<DocumentWarningKind> {
+    // A document whose URL is already taken won't be indexed, so its other
+    // warnings aren't worth reporting.
+    if let Some(seen_url) = seen_documents.with_url(&entry.url) {
+        let warning = if seen_url.url == entry.url {
+            DocumentWarningKind::DuplicateUrl {
+                url: entry.url.clone(),
+                other_source: seen_url.source.clone(),
+            }
+        } else {
+            DocumentWarningKind::DuplicateCanonicalUrl {
+                url: entry.url.clone(),
+                other_url: seen_url.url.clone(),
+                other_source: seen_url.source.clone(),
             }
         };
+        return vec![warning];
+    }
+
+    let mut warnings = vec![];
+    let word_count = entry.annotated_word_list.word_list.len();
+
+    if word_count < FEW_WORDS_THRESHOLD {
+        warnings.push(DocumentWarningKind::FewWords(word_count));
     }
 
+    if let Some(other_source) = seen_documents.titles.get(&entry.title) {
+        warnings.push(DocumentWarningKind::DuplicateTitle {
+            title: entry.title.clone(),
+            other_source: other_source.clone(),
+        });
+    }
+
+    if word_count < config.output.excerpt_buffer as usize {
+        warnings.push(DocumentWarningKind::ExcerptBufferExceedsDocument {
+            word_count,
+            excerpt_buffer: config.output.excerpt_buffer,
+        });
+    }
+
+    if entry.language_undetected {
+        warnings.push(DocumentWarningKind::LanguageNotDetected);
+    }
+
+    if let Some(thumbnail) = &entry.invalid_thumbnail {
+        warnings.push(DocumentWarningKind::InvalidThumbnail(thumbnail.clone()));
+    }
+
+    if let Some(date) = &entry.invalid_date {
+        warnings.push(DocumentWarningKind::InvalidDate(date.clone()));
+    }
+
+    if entry.truncated_word_count > 0 {
+        warnings.push(DocumentWarningKind::LongWordsTruncated {
+            count: entry.truncated_word_count,
+            maximum_token_length: config.input.maximum_token_length,
+        });
+    }
+
+    if let Some(word_count) = entry.unlimited_word_count {
+        warnings.push(DocumentWarningKind::TooManyWords {
+            word_count,
+            maximum_words_per_document: config.input.maximum_words_per_document,
+        });
+    }
+
+    if entry.malformed_json_ld_blocks > 0 {
+        warnings.push(DocumentWarningKind::MalformedJsonLd(
+            entry.malformed_json_ld_blocks,
+        ));
+    }
+
+    warnings
+}
+
+/**
+ * Records a document's warnings according to `input.warnings`, or, when
+ * warnings are denied, turns the first one into an error.
+ */
+fn handle_document_warnings(
+    config: &Config,
+    stork_file: &File,
+    warnings: Vec<DocumentWarningKind>,
+    document_warnings: &mut Vec<DocumentWarning>,
+) -> Result<(), WordListGenerationError> {
+    match config.input.warnings {
+        WarningHandling::Allow => Ok(()),
+
+        WarningHandling::Print => {
+            for kind in warnings {
+                tracing::debug!(file = %stork_file, code = kind.code(), "{}", kind);
+                document_warnings.push(DocumentWarning {
+                    file: stork_file.clone(),
+                    kind,
+                });
+            }
+            Ok(())
+        }
+
+        WarningHandling::Deny => match warnings.into_iter().next() {
+            Some(kind) => Err(WordListGenerationError::DeniedWarning(kind)),
+            None => Ok(()),
+        },
+    }
+}
+
+fn finish_filling_intermediate_entries(
+    config: &Config,
+    document_errors: &[DocumentError],
+) -> Result<(), IndexGenerationError> {
     if config.input.break_on_file_error && !document_errors.is_empty() {
         return Err(IndexGenerationError::PartialDocumentErrors(
-            document_errors.clone(),
+            document_errors.to_vec(),
         ));
     }
 
@@ -205,14 +966,91 @@ fn truncate_with_ellipsis_to_length(
 
 #[cfg(test)]
 mod tests {
-    use super::{fill_intermediate_entries, truncate_with_ellipsis_to_length};
+    use super::{
+        fill_intermediate_entries, truncate_with_ellipsis_to_length, DocumentReports, ReaderConfig,
+        WebClient,
+    };
     use crate::{
-        config::{Config, DataSource, File, InputConfig, OutputConfig},
-        index_v3::build::{errors::WordListGenerationError, intermediate_entry::NormalizedEntry},
-        DocumentError, IndexGenerationError,
+        config::{
+            Config, DataSource, File, Filetype, FiletypeDefaults, InputConfig, OutputConfig,
+            StemmingConfig,
+        },
+        index_v3::build::{
+            build,
+            driver::{block_on, SyncDriver},
+            errors::WordListGenerationError,
+            intermediate_entry::NormalizedEntry,
+        },
+        DocumentWarningKind, IndexGenerationError,
     };
+    #[cfg(feature = "build-v3-langdetect")]
+    use rust_stemmers::Algorithm;
+    use std::sync::{Arc, Mutex};
     use unicode_segmentation::UnicodeSegmentation;
 
+    fn fill(
+        config: &Config,
+        reports: &mut DocumentReports,
+    ) -> Result<Vec<NormalizedEntry>, IndexGenerationError> {
+        let intermediate_entries: Arc<Mutex<Vec<NormalizedEntry>>> = Arc::default();
+        block_on(fill_intermediate_entries(
+            config,
+            &Arc::new(rayon::ThreadPoolBuilder::new().build().unwrap()),
+            &Arc::new(WebClient::default()),
+            &intermediate_entries,
+            reports,
+            &mut SyncDriver::new(|_| {}),
+        ))?;
+        let intermediate_entries = std::mem::take(&mut *intermediate_entries.lock().unwrap());
+        Ok(intermediate_entries)
+    }
+
+    #[test]
+    fn filetype_defaults_take_precedence_over_input_options_but_not_file_overrides() {
+        use crate::config::Filetype;
+        use rust_stemmers::Algorithm;
+        use std::collections::HashMap;
+
+        let reader_config = |file: File| ReaderConfig {
+            global: InputConfig {
+                stemming: StemmingConfig::Language(Algorithm::English),
+                html_selector: Some("main".to_string()),
+                exclude_html_selector: Some(".global".to_string()),
+                filetype_defaults: HashMap::from([(
+                    Filetype::Markdown,
+                    FiletypeDefaults {
+                        stemming: Some(StemmingConfig::None),
+                        html_selector: Some(".markdown".to_string()),
+                        ..Default::default()
+                    },
+                )]),
+                ..Default::default()
+            },
+            file,
+            output: OutputConfig::default(),
+        };
+        let markdown = Some(&Filetype::Markdown);
+        let html = Some(&Filetype::HTML);
+
+        let plain = reader_config(File::default());
+        assert_eq!(plain.html_selector(markdown), Some(".markdown"));
+        assert_eq!(plain.html_selector(html), Some("main"));
+        assert_eq!(plain.exclude_html_selector(markdown), Some(".global"));
+        assert_eq!(plain.get_stem_algorithm(markdown), None);
+        assert_eq!(plain.get_stem_algorithm(html), Some(Algorithm::English));
+
+        let overriding = reader_config(File {
+            html_selector_override: Some(".file".to_string()),
+            stemming_override: Some(StemmingConfig::Language(Algorithm::French)),
+            ..Default::default()
+        });
+        assert_eq!(overriding.html_selector(markdown), Some(".file"));
+        assert_eq!(
+            overriding.get_stem_algorithm(markdown),
+            Some(Algorithm::French)
+        );
+    }
+
     #[test]
     fn break_on_file_error_breaks() {
         let mut invalid_file = File::default();
@@ -223,12 +1061,9 @@ mod tests {
         let output = OutputConfig::default();
         let config = Config { input, output };
 
-        let mut intermediate_entries: Vec<NormalizedEntry> = vec![];
-        let mut document_errors: Vec<DocumentError> = vec![];
+        let mut reports = DocumentReports::default();
 
-        let r = fill_intermediate_entries(&config, &mut intermediate_entries, &mut document_errors)
-            .err()
-            .unwrap();
+        let r = fill(&config, &mut reports).err().unwrap();
         if let IndexGenerationError::PartialDocumentErrors(vec) = r {
             let word_list_generation_error = &vec[0].word_list_generation_error;
             assert_eq!(
@@ -250,19 +1085,165 @@ mod tests {
         let output = OutputConfig::default();
         let config = Config { input, output };
 
-        let mut intermediate_entries: Vec<NormalizedEntry> = vec![];
-        let mut document_errors: Vec<DocumentError> = vec![];
+        let mut reports = DocumentReports::default();
 
-        let result =
-            fill_intermediate_entries(&config, &mut intermediate_entries, &mut document_errors);
+        let result = fill(&config, &mut reports);
         assert!(result.is_ok());
-        assert_eq!(document_errors.len(), 1);
+        assert_eq!(reports.errors.len(), 1);
         assert_eq!(
-            document_errors[0].word_list_generation_error,
+            reports.errors[0].word_list_generation_error,
             WordListGenerationError::EmptyWordList
         );
     }
 
+    #[test]
+    fn url_language_prefixes_tag_and_stem_documents() {
+        use rust_stemmers::Algorithm;
+        use std::collections::BTreeMap;
+
+        let file = |url: &str, stemming_override| File {
+            title: url.to_string(),
+            url: url.to_string(),
+            explicit_source: Some(DataSource::Contents("Words to index".to_string())),
+            stemming_override,
+            ..Default::default()
+        };
+
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    file("/de/haus", None),
+                    file("/en/house", None),
+                    file("/de/unstemmed", Some(StemmingConfig::None)),
+                    file("/about", None),
+                ],
+                stemming: StemmingConfig::Language(Algorithm::English),
+                language_url_prefixes: BTreeMap::from([
+                    ("de".to_string(), "/de/".to_string()),
+                    ("en".to_string(), "/en/".to_string()),
+                ]),
+                ..Default::default()
+            },
+            output: OutputConfig::default(),
+        };
+
+        let intermediate_entries = fill(&config, &mut DocumentReports::default()).unwrap();
+
+        let languages: Vec<(Option<&str>, Option<Algorithm>)> = intermediate_entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.fields.get("language").map(String::as_str),
+                    entry.stem_algorithm,
+                )
+            })
+            .collect();
+        assert_eq!(
+            languages,
+            vec![
+                (Some("de"), Some(Algorithm::German)),
+                (Some("en"), Some(Algorithm::English)),
+                (Some("de"), None),
+                (None, Some(Algorithm::English)),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "build-v3-langdetect")]
+    fn detected_language_picks_the_stemmer() {
+        let file = |title: &str, contents: &str| File {
+            title: title.to_string(),
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            ..Default::default()
+        };
+
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    file(
+                        "Häuser",
+                        "Die Kinder spielten den ganzen Nachmittag in den Gärten hinter \
+                         den alten Häusern, während ihre Eltern sich über die Zukunft \
+                         des Dorfes unterhielten und gemeinsam Kuchen aßen.",
+                    ),
+                    file(
+                        "Houses",
+                        "The children played all afternoon in the gardens behind the old \
+                         houses, while their parents talked about the future of the \
+                         village and ate cake together.",
+                    ),
+                ],
+                detect_language: true,
+                ..Default::default()
+            },
+            output: OutputConfig::default(),
+        };
+
+        let intermediate_entries = fill(&config, &mut DocumentReports::default()).unwrap();
+
+        let german = &intermediate_entries[0];
+        assert_eq!(german.stem_algorithm, Some(Algorithm::German));
+        assert_eq!(german.fields["detected_language"], "deu");
+
+        let english = &intermediate_entries[1];
+        assert_eq!(english.stem_algorithm, Some(Algorithm::English));
+        assert_eq!(english.fields["detected_language"], "eng");
+    }
+
+    #[test]
+    #[cfg(feature = "build-v3-langdetect")]
+    fn declared_language_wins_and_uncertain_detection_warns() {
+        let contents = "The children played all afternoon in the gardens behind the old houses.";
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    File {
+                        title: "Declared".to_string(),
+                        explicit_source: Some(DataSource::Contents(format!(
+                            "<html lang=\"fr-CA\"><body><main>{}</main></body></html>",
+                            contents
+                        ))),
+                        filetype: Some(Filetype::HTML),
+                        ..Default::default()
+                    },
+                    File {
+                        title: "Undeclared".to_string(),
+                        explicit_source: Some(DataSource::Contents(contents.to_string())),
+                        ..Default::default()
+                    },
+                ],
+                detect_language: true,
+                language_detection_threshold: 1.5,
+                ..Default::default()
+            },
+            output: OutputConfig::default(),
+        };
+
+        let mut reports = DocumentReports::default();
+        let intermediate_entries = fill(&config, &mut reports).unwrap();
+
+        assert_eq!(
+            intermediate_entries[0].stem_algorithm,
+            Some(Algorithm::French)
+        );
+        assert_eq!(
+            intermediate_entries[1].stem_algorithm,
+            Some(Algorithm::English)
+        );
+        assert!(!intermediate_entries[1]
+            .fields
+            .contains_key("detected_language"));
+
+        let warned_titles: Vec<&str> = reports
+            .warnings
+            .iter()
+            .filter(|warning| warning.kind == DocumentWarningKind::LanguageNotDetected)
+            .map(|warning| warning.file.title.as_str())
+            .collect();
+        assert_eq!(warned_titles, vec!["Undeclared"]);
+    }
+
     #[test]
     fn test_truncate_with_ellipsis_on_naughty_strings() {
         // https://github.com/minimaxir/big-list-of-naughty-strings/blob/master/blns.txt#L152
@@ -283,4 +1264,255 @@ mod tests {
             }
         }
     }
+
+    fn plaintext_file(title: &str, contents: &str) -> File {
+        File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        }
+    }
+
+    const TWELVE_WORDS: &str = "one two three four five six seven eight nine ten eleven twelve";
+
+    #[test]
+    fn duplicate_url_is_skipped_with_warning() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    File {
+                        url: "/same".to_string(),
+                        ..plaintext_file("First", TWELVE_WORDS)
+                    },
+                    File {
+                        url: "/same".to_string(),
+                        ..plaintext_file("Second", TWELVE_WORDS)
+                    },
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let build_result = build(&config).unwrap();
+
+        assert_eq!(build_result.index.entries.len(), 1);
+        assert_eq!(build_result.index.entries[0].title, "First");
+        assert_eq!(
+            build_result
+                .warnings
+                .into_iter()
+                .map(|warning| warning.kind)
+                .collect::<Vec<_>>(),
+            vec![DocumentWarningKind::DuplicateUrl {
+                url: "/same".to_string(),
+                other_source: "First".to_string()
+            }]
+        );
+    }
+
+    fn html_meta_config(html_meta_tags: Vec<String>, index_meta_keywords: bool) -> Config {
+        Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        r#"<html>
+                            <head>
+                                <meta name="author" content="Ada Lovelace">
+                                <meta name="keywords" content="wasm, webassembly">
+                                <meta property="og:title" content="Stork">
+                            </head>
+                            <body><main><p>How to configure the index</p></main></body>
+                        </html>"#
+                            .to_string(),
+                    )),
+                    title: "Configuration".to_string(),
+                    url: "/docs/config/".to_string(),
+                    filetype: Some(Filetype::HTML),
+                    ..Default::default()
+                }],
+                html_meta_tags,
+                index_meta_keywords,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn html_meta_tags_are_stored_and_keywords_are_searchable() {
+        let index = build(&html_meta_config(
+            vec!["author".to_string(), "og:title".to_string()],
+            true,
+        ))
+        .unwrap()
+        .index;
+
+        let fields = &index.entries[0].fields;
+        assert_eq!(fields.get("author"), Some(&"Ada Lovelace".to_string()));
+        assert_eq!(fields.get("og:title"), Some(&"Stork".to_string()));
+        assert_eq!(fields.get("keywords"), None);
+
+        for query in ["wasm", "webassembly"] {
+            let output = crate::index_v3::search(&index, query).unwrap();
+            assert_eq!(output.results.len(), 1, "{}", query);
+            assert_eq!(output.results[0].entry.url, "/docs/config/");
+        }
+    }
+
+    #[test]
+    fn html_meta_tags_are_ignored_by_default() {
+        let index = build(&html_meta_config(vec![], false)).unwrap().index;
+
+        assert!(index.entries[0].fields.is_empty());
+        assert!(crate::index_v3::search(&index, "wasm")
+            .unwrap()
+            .results
+            .is_empty());
+    }
+
+    #[test]
+    fn prefixed_html_meta_tags_work_like_frontmatter() {
+        let html_file = |title: &str, head: &str| {
+            File {
+            explicit_source: Some(DataSource::Contents(format!(
+                "<html><head>{}</head><body><main><p>Notes on the lighthouse keeper's routine.</p></main></body></html>",
+                head
+            ))),
+            title: title.to_string(),
+            url: title.to_lowercase(),
+            filetype: Some(Filetype::HTML),
+            ..Default::default()
+        }
+        };
+
+        let config = Config {
+            input: InputConfig {
+                html_meta_prefix: Some("stork:".to_string()),
+                extract_thumbnails: true,
+                files: vec![
+                    html_file("Draft", r#"<meta name="Stork:draft" content="true">"#),
+                    html_file(
+                        "Published",
+                        r#"<meta name="STORK:thumbnail" content="/img/lighthouse.png">"#,
+                    ),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let build_results = build(&config).unwrap();
+        assert!(build_results.errors.is_empty());
+        assert_eq!(build_results.index.entries.len(), 1);
+        assert_eq!(build_results.index.entries[0].title, "Published");
+        assert_eq!(
+            build_results.index.entries[0]
+                .fields
+                .get(crate::index_v3::THUMBNAIL_FIELD)
+                .map(String::as_str),
+            Some("/img/lighthouse.png")
+        );
+    }
+
+    #[test]
+    fn filetype_defaults_apply_to_files_with_inferred_filetypes() {
+        let directory = std::env::temp_dir();
+        let filename = "stork-filetype-defaults-test.md";
+        std::fs::write(
+            directory.join(filename),
+            "Build the project with `cargo` before running the tests.",
+        )
+        .unwrap();
+
+        let config = Config {
+            input: InputConfig {
+                base_directory: directory.to_string_lossy().to_string(),
+                filetype_defaults: std::collections::HashMap::from([(
+                    Filetype::Markdown,
+                    FiletypeDefaults {
+                        exclude_html_selector: Some("code".to_string()),
+                        ..Default::default()
+                    },
+                )]),
+                files: vec![
+                    File {
+                        explicit_source: Some(DataSource::FilePath(filename.to_string())),
+                        title: "Markdown".to_string(),
+                        ..Default::default()
+                    },
+                    plaintext_file(
+                        "Plain text",
+                        "Build the project with cargo before running the tests.",
+                    ),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let index = build(&config).unwrap().index;
+        let titles: Vec<String> = crate::index_v3::search(&index, "cargo")
+            .unwrap()
+            .results
+            .into_iter()
+            .map(|result| result.entry.title)
+            .collect();
+
+        assert_eq!(titles, vec!["Plain text".to_string()]);
+    }
+
+    #[test]
+    fn summaries_are_searchable_on_their_own() {
+        let index = build(&Config {
+            input: InputConfig {
+                files: vec![
+                    File {
+                        summary: Some("How to configure the telescope mount".to_string()),
+                        ..plaintext_file("Setup", "Run the installer and wait for it to finish")
+                    },
+                    plaintext_file("Mounts", "Every telescope mount is different"),
+                    File {
+                        explicit_source: Some(DataSource::Contents(
+                            r#"<html><head><meta name="description" content="Calibrating the mount"></head><body><main>Point it at a bright star</main></body></html>"#
+                                .to_string(),
+                        )),
+                        title: "Calibration".to_string(),
+                        filetype: Some(Filetype::HTML),
+                        ..Default::default()
+                    },
+                ],
+                extract_summaries: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap()
+        .index;
+        let titles = |query: &str, summaries_only: bool| -> Vec<String> {
+            let options = crate::SearchOptions {
+                summaries_only,
+                ..Default::default()
+            };
+            let mut titles: Vec<String> =
+                crate::index_v3::search_with_options(&index, query, &options)
+                    .unwrap()
+                    .results
+                    .into_iter()
+                    .map(|result| result.entry.title)
+                    .collect();
+            titles.sort();
+            titles
+        };
+
+        assert_eq!(titles("configure", false), vec!["Setup"]);
+        assert_eq!(
+            titles("mount", false),
+            vec!["Calibration", "Mounts", "Setup"]
+        );
+        assert_eq!(titles("mount", true), vec!["Calibration", "Setup"]);
+        assert!(titles("installer", true).is_empty());
+        assert_eq!(titles("summary:telescope", false), vec!["Setup"]);
+    }
 }
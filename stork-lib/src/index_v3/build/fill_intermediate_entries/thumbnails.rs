@@ -0,0 +1,272 @@
+use url::Url;
+
+/// The longest thumbnail URL stored, in characters. Longer ones are likely
+/// to be inline `data:` images, which would bloat the index.
+pub(super) const MAX_THUMBNAIL_URL_LENGTH: usize = 2048;
+
+/**
+ * Whether a thumbnail looks like something a browser can load: a path, or
+ * an `http` or `https` URL, no longer than `MAX_THUMBNAIL_URL_LENGTH`
+ * characters and without whitespace, control characters, or characters that
+ * would break out of an HTML attribute.
+ */
+pub(super) fn is_valid_thumbnail_url(thumbnail: &str) -> bool {
+    if thumbnail.is_empty() || thumbnail.chars().count() > MAX_THUMBNAIL_URL_LENGTH {
+        return false;
+    }
+
+    if thumbnail
+        .chars()
+        .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | '"' | '\'' | '`'))
+    {
+        return false;
+    }
+
+    match scheme(thumbnail) {
+        Some(scheme) => scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https"),
+        None => true,
+    }
+}
+
+/**
+ * Resolves a thumbnail read from a document against the document's
+ * `base_url`, the way a browser would resolve it on the page. The thumbnail
+ * is returned as it was if it's already absolute, if the base URL can't be
+ * parsed, or if the resolved URL wouldn't be a valid thumbnail.
+ */
+pub(super) fn resolve_thumbnail_url(thumbnail: String, base_url: Option<&str>) -> String {
+    let base_url = match base_url.and_then(|base_url| Url::parse(base_url.trim()).ok()) {
+        Some(base_url) => base_url,
+        None => return thumbnail,
+    };
+
+    if scheme(&thumbnail).is_some() {
+        return thumbnail;
+    }
+
+    match base_url.join(&thumbnail) {
+        Ok(resolved) if is_valid_thumbnail_url(resolved.as_str()) => resolved.into(),
+        _ => thumbnail,
+    }
+}
+
+/// The URL's scheme, like `https`, if it has one.
+fn scheme(url: &str) -> Option<&str> {
+    let (scheme, _) = url.split_once(':')?;
+    let mut chars = scheme.chars();
+
+    let is_scheme = chars.next().map_or(false, |c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+
+    is_scheme.then(|| scheme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::*;
+    use crate::index_v3::build::{build, errors::DocumentWarningKind};
+
+    #[test]
+    fn paths_and_web_urls_are_valid_thumbnails() {
+        for thumbnail in [
+            "img/cover.png",
+            "/img/cover.png",
+            "../cover.png?v=2",
+            "//cdn.example.com/cover.png",
+            "https://cdn.example.com/cover.png",
+            "HTTP://example.com/a:b.png",
+        ] {
+            assert!(is_valid_thumbnail_url(thumbnail), "{}", thumbnail);
+        }
+    }
+
+    #[test]
+    fn other_values_are_invalid_thumbnails() {
+        let too_long = format!("/{}.png", "a".repeat(MAX_THUMBNAIL_URL_LENGTH));
+
+        for thumbnail in [
+            "",
+            "javascript:alert(1)",
+            "data:image/png;base64,iVBORw0KGgo=",
+            "my cover.png",
+            "cover.png\"><script>",
+            too_long.as_str(),
+        ] {
+            assert!(!is_valid_thumbnail_url(thumbnail), "{}", thumbnail);
+        }
+    }
+
+    #[test]
+    fn relative_thumbnails_are_resolved_against_the_base_url() {
+        let base_url = Some("https://example.com/blog/post/");
+
+        for (thumbnail, expected) in [
+            ("cover.png", "https://example.com/blog/post/cover.png"),
+            ("../cover.png?v=2", "https://example.com/blog/cover.png?v=2"),
+            ("/img/cover.png", "https://example.com/img/cover.png"),
+            (
+                "//cdn.example.com/cover.png",
+                "https://cdn.example.com/cover.png",
+            ),
+            (
+                "http://other.example.com/a.png",
+                "http://other.example.com/a.png",
+            ),
+        ] {
+            assert_eq!(
+                resolve_thumbnail_url(thumbnail.to_string(), base_url),
+                expected
+            );
+        }
+
+        for base_url in [None, Some("not a url"), Some("/blog/post/")] {
+            assert_eq!(
+                resolve_thumbnail_url("cover.png".to_string(), base_url),
+                "cover.png"
+            );
+        }
+    }
+
+    #[test]
+    fn thumbnails_come_from_config_frontmatter_or_og_image() {
+        let contents = "A guide to the lighthouse keeper's daily routine.";
+        let config = Config {
+            input: InputConfig {
+                url_prefix: "https://example.com/docs/".to_string(),
+                extract_thumbnails: true,
+                files: vec![
+                    File {
+                        explicit_source: Some(DataSource::Contents(contents.to_string())),
+                        title: "Config".to_string(),
+                        thumbnail_url: Some("img/config.png".to_string()),
+                        url: "config".to_string(),
+                        filetype: Some(Filetype::PlainText),
+                        ..Default::default()
+                    },
+                    File {
+                        explicit_source: Some(DataSource::Contents(format!(
+                            "---\nthumbnail: /img/frontmatter.png\n---\n\n{}",
+                            contents
+                        ))),
+                        title: "Frontmatter".to_string(),
+                        url: "frontmatter".to_string(),
+                        filetype: Some(Filetype::Markdown),
+                        ..Default::default()
+                    },
+                    File {
+                        explicit_source: Some(DataSource::Contents(format!(
+                            r#"<html>
+                                <head><meta property="og:image" content="https://cdn.example.com/og.png"></head>
+                                <body><main><p>{}</p></main></body>
+                            </html>"#,
+                            contents
+                        ))),
+                        title: "Open Graph".to_string(),
+                        url: "open-graph".to_string(),
+                        filetype: Some(Filetype::HTML),
+                        ..Default::default()
+                    },
+                    File {
+                        explicit_source: Some(DataSource::Contents(contents.to_string())),
+                        title: "Invalid".to_string(),
+                        thumbnail_url: Some("javascript:alert(1)".to_string()),
+                        url: "invalid".to_string(),
+                        filetype: Some(Filetype::PlainText),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let build_results = build(&config).unwrap();
+        assert!(build_results.warnings.iter().any(|warning| warning.kind
+            == DocumentWarningKind::InvalidThumbnail("javascript:alert(1)".to_string())));
+
+        let mut thumbnails: Vec<(String, Option<String>)> =
+            crate::index_v3::search(&build_results.index, "lighthouse")
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| (result.entry.title, result.entry.thumbnail_url))
+                .collect();
+        thumbnails.sort();
+
+        assert_eq!(
+            thumbnails,
+            vec![
+                (
+                    "Config".to_string(),
+                    Some("https://example.com/docs/img/config.png".to_string())
+                ),
+                (
+                    "Frontmatter".to_string(),
+                    Some("https://example.com/docs/img/frontmatter.png".to_string())
+                ),
+                ("Invalid".to_string(), None),
+                (
+                    "Open Graph".to_string(),
+                    Some("https://cdn.example.com/og.png".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn relative_thumbnails_in_contents_resolve_against_base_url() {
+        let html = r#"<html>
+            <head><meta property="og:image" content="../img/lighthouse.png"></head>
+            <body><main><p>A guide to the lighthouse keeper's daily routine.</p></main></body>
+        </html>"#;
+        let html_file = |title: &str, url: &str, base_url: Option<&str>| File {
+            explicit_source: Some(DataSource::Contents(html.to_string())),
+            title: title.to_string(),
+            url: url.to_string(),
+            filetype: Some(Filetype::HTML),
+            base_url: base_url.map(str::to_string),
+            ..Default::default()
+        };
+
+        let config = Config {
+            input: InputConfig {
+                url_prefix: "https://example.com/docs/".to_string(),
+                extract_thumbnails: true,
+                files: vec![
+                    html_file(
+                        "With base",
+                        "keepers/routine",
+                        Some("https://lighthouses.example.org/keepers/"),
+                    ),
+                    html_file("Without base", "keepers/routine-without-base", None),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut thumbnails: Vec<(String, Option<String>)> =
+            crate::index_v3::search(&build(&config).unwrap().index, "lighthouse")
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| (result.entry.title, result.entry.thumbnail_url))
+                .collect();
+        thumbnails.sort();
+
+        assert_eq!(
+            thumbnails,
+            vec![
+                (
+                    "With base".to_string(),
+                    Some("https://lighthouses.example.org/img/lighthouse.png".to_string())
+                ),
+                (
+                    "Without base".to_string(),
+                    Some("https://example.com/docs/../img/lighthouse.png".to_string())
+                ),
+            ]
+        );
+    }
+}
@@ -1,40 +1,85 @@
 use crate::{
-    index_v3::{AnnotatedWord, AnnotatedWordList},
-    InternalWordAnnotation,
+    index_v3::{
+        build::{
+            annotated_words_from_string::{LinkHandlers, WordTable},
+            segmentation::{segment_words, split_emoji_words},
+        },
+        AnnotatedWord, AnnotatedWordList,
+    },
+    InternalWordAnnotation, Tokenizer,
 };
 
 use super::{ReadResult, ReaderConfig, WordListGenerationError};
-use kuchiki::{traits::TendrilSink, ElementData, NodeDataRef};
-use std::collections::HashMap;
+use kuchiki::{traits::TendrilSink, ElementData, NodeDataRef, NodeRef, ParseOpts};
+use std::{borrow::Cow, cell::RefCell, collections::HashMap, rc::Rc};
+
+/**
+ * The html5ever parse errors that mean the document's structure was
+ * guessed at, rather than recovered from a harmless omission like a missing
+ * doctype.
+ */
+const SERIOUS_PARSE_ERRORS: &[&str] = &[
+    "Unexpected open tag at end of body",
+    "Unexpected open element",
+    "Found special tag while closing generic tag",
+    "No matching tag to close",
+    "Closing wrong heading tag",
+    "No heading tag to close",
+    "Formatting element not",
+    "Bad open element on",
+];
+
+fn parse_document(buffer: &str, strict: bool) -> Result<NodeRef, WordListGenerationError> {
+    if !strict {
+        return Ok(kuchiki::parse_html().one(buffer));
+    }
+
+    let parse_errors: Rc<RefCell<Vec<Cow<str>>>> = Rc::new(RefCell::new(Vec::new()));
+    let sink_errors = Rc::clone(&parse_errors);
+    let document = kuchiki::parse_html_with_options(ParseOpts {
+        on_parse_error: Some(Box::new(move |error: Cow<'static, str>| {
+            sink_errors.borrow_mut().push(error)
+        })),
+        ..ParseOpts::default()
+    })
+    .one(buffer);
+
+    let serious_error = parse_errors
+        .borrow()
+        .iter()
+        .find(|error| {
+            SERIOUS_PARSE_ERRORS
+                .iter()
+                .any(|serious| error.starts_with(serious))
+        })
+        .cloned();
+
+    match serious_error {
+        Some(error) => Err(WordListGenerationError::MalformedHtml(error.to_string())),
+        None => Ok(document),
+    }
+}
 
 pub fn generate(
     config: &ReaderConfig,
     read_result: &ReadResult,
 ) -> Result<AnnotatedWordList, WordListGenerationError> {
-    let document = kuchiki::parse_html().one(read_result.buffer.clone());
-
-    let selector: &str = {
-        config
-            .file
-            .html_selector_override
-            .as_ref()
-            .or_else(|| config.global.html_selector.as_ref())
-            .map_or("main", std::string::String::as_str)
-    };
-
-    let exclude_selector: Option<&str> = {
-        config
-            .file
-            .exclude_html_selector_override
-            .as_ref()
-            .or_else(|| config.global.exclude_html_selector.as_ref())
-            .map(std::string::String::as_str)
-    };
+    let document = parse_document(&read_result.buffer, config.global.strict_html)?;
+
+    let filetype = read_result.filetype.as_ref();
+    let selector: &str = config.html_selector(filetype).unwrap_or("main");
+    let exclude_selector: Option<&str> = config.exclude_html_selector(filetype);
 
     if let Ok(css_matches) = document.select(selector) {
+        let links = LinkHandlers::from(&config.global);
         let mut word_list: Vec<AnnotatedWord> = vec![];
+        let mut words = WordTable::default();
         let mut latest_id: Option<String> = None;
 
+        // Whether an item has started or ended since the last words, so the
+        // next words start a new one.
+        let mut item_boundary = false;
+
         let matches_vec: Vec<NodeDataRef<ElementData>> = css_matches.into_iter().collect();
 
         if matches_vec.is_empty() {
@@ -55,6 +100,12 @@ pub fn generate(
             }
 
             for elem in as_node.traverse_inclusive() {
+                if let kuchiki::iter::NodeEdge::Start(node_ref)
+                | kuchiki::iter::NodeEdge::End(node_ref) = &elem
+                {
+                    item_boundary |= is_item(node_ref);
+                }
+
                 if let kuchiki::iter::NodeEdge::Start(node_ref) = elem {
                     let contents: String = (|| {
                         let mut output = "".to_string();
@@ -81,24 +132,54 @@ pub fn generate(
                     })();
 
                     if !contents.is_empty() {
-                        let mut annotated_words: Vec<AnnotatedWord> = contents
-                            .split_whitespace()
-                            .map(ToString::to_string)
-                            .map(|word| AnnotatedWord {
-                                word,
-                                internal_annotations: {
-                                    if let Some(latest_id) = latest_id.clone() {
-                                        vec![InternalWordAnnotation::UrlSuffix(format!(
-                                            "#{}",
-                                            latest_id
-                                        ))]
-                                    } else {
-                                        vec![]
-                                    }
-                                },
-                                fields: HashMap::default(),
-                            })
-                            .collect();
+                        // Code is only split at whitespace, so its
+                        // identifiers are indexed whole.
+                        let in_code = config.global.index_code_identifiers
+                            && node_ref.as_text().is_some()
+                            && node_ref.ancestors().any(|ancestor| is_code(&ancestor));
+
+                        let mut annotated_words = if in_code {
+                            contents
+                                .split_whitespace()
+                                .map(|word| AnnotatedWord {
+                                    word: words.intern(word),
+                                    internal_annotations: vec![InternalWordAnnotation::Code],
+                                    ..AnnotatedWord::default()
+                                })
+                                .collect()
+                        } else {
+                            match &config.global.tokenizer {
+                                Some(tokenizer) => tokenizer.tokenize(&contents),
+                                None => split_emoji_words(
+                                    segment_words(
+                                        contents
+                                            .split_whitespace()
+                                            .filter_map(|word| links.apply(word))
+                                            .map(|word| AnnotatedWord::new(words.intern(word)))
+                                            .collect(),
+                                        config.global.word_segmentation,
+                                    ),
+                                    config.global.index_emoji,
+                                ),
+                            }
+                        };
+
+                        if let Some(latest_id) = &latest_id {
+                            for annotated_word in &mut annotated_words {
+                                annotated_word.internal_annotations.push(
+                                    InternalWordAnnotation::UrlSuffix(format!("#{}", latest_id)),
+                                );
+                            }
+                        }
+
+                        if let Some(first_word) = annotated_words.first_mut() {
+                            if item_boundary && !word_list.is_empty() {
+                                first_word
+                                    .internal_annotations
+                                    .push(InternalWordAnnotation::ItemStart);
+                            }
+                            item_boundary = false;
+                        }
 
                         word_list.append(&mut annotated_words);
                     }
@@ -118,9 +199,155 @@ pub fn generate(
     ))
 }
 
+/**
+ * Whether the node is an element whose text is one of a list of separate
+ * items: a list item, a definition list's term or description, or a table
+ * row. Its words don't form phrases with the words around it.
+ */
+fn is_item(node_ref: &NodeRef) -> bool {
+    node_ref.as_element().map_or(false, |element| {
+        matches!(&*element.name.local, "li" | "dt" | "dd" | "tr")
+    })
+}
+
+/**
+ * Whether the node is a `<code>` or `<pre>` element, whose text is indexed
+ * as code with `input.index_code_identifiers`.
+ */
+fn is_code(node_ref: &NodeRef) -> bool {
+    node_ref.as_element().map_or(false, |element| {
+        matches!(&*element.name.local, "code" | "pre")
+    })
+}
+
+/**
+ * Reads the content of each `<meta>` tag whose `name` or `property` is one
+ * of `names`, ignoring case, keyed by the name as it's given in `names`.
+ * The first tag with a given name wins.
+ */
+pub fn read_meta_tags(buffer: &str, names: &[String]) -> HashMap<String, String> {
+    let document = kuchiki::parse_html().one(buffer);
+    let mut meta_tags = HashMap::new();
+
+    if let Ok(meta_elements) = document.select("meta[content]") {
+        for meta_element in meta_elements {
+            let attributes = meta_element.attributes.borrow();
+            let name = attributes
+                .get("name")
+                .or_else(|| attributes.get("property"));
+
+            if let (Some(name), Some(content)) = (name, attributes.get("content")) {
+                if let Some(wanted) = names
+                    .iter()
+                    .find(|wanted| wanted.eq_ignore_ascii_case(name))
+                {
+                    meta_tags
+                        .entry(wanted.clone())
+                        .or_insert_with(|| content.trim().to_string());
+                }
+            }
+        }
+    }
+
+    meta_tags
+}
+
+/**
+ * Reads the content of each `<meta>` tag whose `name` or `property` starts
+ * with `prefix`, ignoring case, keyed by the rest of the name. Tags that
+ * share a name are joined, in document order, with `, `.
+ */
+pub fn read_prefixed_meta_tags(buffer: &str, prefix: &str) -> HashMap<String, String> {
+    let document = kuchiki::parse_html().one(buffer);
+    let mut meta_tags: HashMap<String, String> = HashMap::new();
+
+    if let Ok(meta_elements) = document.select("meta[content]") {
+        for meta_element in meta_elements {
+            let attributes = meta_element.attributes.borrow();
+            let name = attributes
+                .get("name")
+                .or_else(|| attributes.get("property"))
+                .map(str::trim);
+
+            let field = name.and_then(|name| {
+                let has_prefix = name
+                    .get(..prefix.len())
+                    .map_or(false, |start| start.eq_ignore_ascii_case(prefix));
+                has_prefix.then(|| &name[prefix.len()..])
+            });
+
+            if let (Some(field), Some(content)) = (field, attributes.get("content")) {
+                if field.is_empty() {
+                    continue;
+                }
+
+                let content = content.trim();
+                meta_tags
+                    .entry(field.to_string())
+                    .and_modify(|existing| {
+                        existing.push_str(", ");
+                        existing.push_str(content);
+                    })
+                    .or_insert_with(|| content.to_string());
+            }
+        }
+    }
+
+    meta_tags
+}
+
+/**
+ * Reads the text of each of the document's JSON-LD blocks, the
+ * `<script type="application/ld+json">` elements that describe it with
+ * structured data.
+ */
+pub fn read_json_ld_blocks(buffer: &str) -> Vec<String> {
+    let document = kuchiki::parse_html().one(buffer);
+    let mut blocks = vec![];
+
+    if let Ok(script_elements) = document.select("script[type]") {
+        for script_element in script_elements {
+            let is_json_ld =
+                script_element
+                    .attributes
+                    .borrow()
+                    .get("type")
+                    .map_or(false, |script_type| {
+                        script_type
+                            .trim()
+                            .eq_ignore_ascii_case("application/ld+json")
+                    });
+
+            if is_json_ld {
+                blocks.push(script_element.text_contents());
+            }
+        }
+    }
+
+    blocks
+}
+
+/**
+ * Reads the language the document declares in its `<html lang>` attribute,
+ * like `de` or `pt-BR`.
+ */
+pub fn read_html_lang(buffer: &str) -> Option<String> {
+    let document = kuchiki::parse_html().one(buffer);
+    let html_element = document.select_first("html").ok()?;
+    let attributes = html_element.attributes.borrow();
+
+    attributes
+        .get("lang")
+        .map(str::trim)
+        .filter(|lang| !lang.is_empty())
+        .map(ToString::to_string)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::config::{File, Filetype, InputConfig, OutputConfig};
+    use crate::config::{Config, DataSource, File, Filetype, InputConfig, OutputConfig};
+    use crate::index_v3::build::build;
+    use crate::V3Index as Index;
 
     use super::*;
 
@@ -144,6 +371,7 @@ mod tests {
             buffer: str.to_string(),
             filetype: Some(Filetype::HTML),
             frontmatter_fields: None,
+            streamed_word_list: None,
         }
     }
 
@@ -153,8 +381,8 @@ mod tests {
             .unwrap()
             .word_list
             .iter()
-            .map(|aw| aw.word.clone())
-            .collect::<Vec<String>>()
+            .map(|aw| &*aw.word)
+            .collect::<Vec<&str>>()
             .join(" ");
     }
 
@@ -365,6 +593,62 @@ mod tests {
         )
     }
 
+    #[test]
+    fn prefixed_meta_tags_are_read_and_joined() {
+        let html = r#"<html><head>
+            <meta name="stork:tags" content="docs">
+            <meta name="STORK:tags" content=" cli ">
+            <meta property="Stork:draft" content="true">
+            <meta name="stork:" content="nameless">
+            <meta name="author" content="Someone">
+        </head><body><main><p>Text</p></main></body></html>"#;
+
+        let mut computed: Vec<(String, String)> = read_prefixed_meta_tags(html, "stork:")
+            .into_iter()
+            .collect();
+        computed.sort();
+
+        assert_eq!(
+            computed,
+            vec![
+                ("draft".to_string(), "true".to_string()),
+                ("tags".to_string(), "docs, cli".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn strict_html_rejects_misnested_markup() {
+        let html = "<main><div><p>Unclosed content</main>";
+
+        let mut config = reader_config_from_html_selectors(None, None);
+        assert_eq!(
+            extract_text(generate(&config, &read_result_from_string(html))),
+            "Unclosed content"
+        );
+
+        config.global.strict_html = true;
+        assert!(matches!(
+            generate(&config, &read_result_from_string(html)),
+            Err(WordListGenerationError::MalformedHtml(_))
+        ));
+    }
+
+    #[test]
+    fn strict_html_accepts_well_formed_markup() {
+        let mut config = reader_config_from_html_selectors(None, None);
+        config.global.strict_html = true;
+
+        let computed = extract_text(generate(
+            &config,
+            &read_result_from_string(
+                "<html><head></head><body><main><p>This is some text</p></main></body></html>",
+            ),
+        ));
+
+        assert_eq!(computed, "This is some text");
+    }
+
     #[test]
     fn test_self_closing_tag_behavior() {
         /*
@@ -456,10 +740,10 @@ mod tests {
                 annotated_word
                     .internal_annotations
                     .into_iter()
-                    .map(|word_annotation| match word_annotation {
-                        InternalWordAnnotation::UrlSuffix(suffix) => suffix,
+                    .find_map(|word_annotation| match word_annotation {
+                        InternalWordAnnotation::UrlSuffix(suffix) => Some(suffix),
+                        InternalWordAnnotation::ItemStart | InternalWordAnnotation::Code => None,
                     })
-                    .next()
                     .unwrap()
             })
             .collect();
@@ -519,4 +803,101 @@ mod tests {
 
         assert_eq!(computed, 0)
     }
+
+    #[test]
+    fn results_link_to_the_section_with_the_nearest_html_id() {
+        let build_with = |save_nearest_html_id: bool| {
+            build(&Config {
+                input: InputConfig {
+                    files: vec![File {
+                        explicit_source: Some(DataSource::Contents(
+                            r#"<html><body><main><p>Welcome to the guide</p><div id="faq"><p>Refunds take a week</p></div></main></body></html>"#
+                                .to_string(),
+                        )),
+                        title: "Guide".to_string(),
+                        url: "guide".to_string(),
+                        filetype: Some(Filetype::HTML),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                output: OutputConfig {
+                    save_nearest_html_id,
+                    ..Default::default()
+                },
+            })
+            .unwrap()
+            .index
+        };
+
+        let index = build_with(true);
+        let output =
+            crate::index_v3::search_with_options(&index, "refunds", &Default::default()).unwrap();
+        assert_eq!(output.results[0].url.as_deref(), Some("guide#faq"));
+        assert_eq!(
+            output.results[0].excerpts[0].url.as_deref(),
+            Some("guide#faq")
+        );
+
+        let index = build_with(false);
+        let output =
+            crate::index_v3::search_with_options(&index, "refunds", &Default::default()).unwrap();
+        assert_eq!(output.results[0].url, None);
+        assert_eq!(output.results[0].entry.url, "guide");
+    }
+
+    #[test]
+    fn list_items_are_kept_apart_in_excerpts() {
+        let html = "<html><body><main>\
+            <ul><li>First item</li><li>Second item<ul><li>Nested</li></ul></li></ul>\
+            <dl><dt>Term</dt><dd>Description</dd></dl>\
+            </main></body></html>";
+        let config = Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(html.to_string())),
+                    title: "Lists".to_string(),
+                    filetype: Some(Filetype::HTML),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let bytes = bytes::Bytes::from(&build(&config).unwrap().index);
+        let index = Index::try_from(bytes.as_ref()).unwrap();
+
+        // The separator is only added to excerpts, so it's never searchable.
+        let entry = &index.entries[0];
+        assert_eq!(
+            entry.contents,
+            "First item Second item Nested Term Description"
+        );
+        assert_eq!(entry.item_starts, vec![2, 4, 5, 6]);
+        assert!(!index.containers.contains_key("·"));
+
+        let output = crate::index_v3::search(&index, "second").unwrap();
+        let excerpt = &output.results[0].excerpts[0];
+        assert_eq!(
+            excerpt.text,
+            "First item · Second item · Nested · Term · Description"
+        );
+        let range = &excerpt.highlight_ranges[0];
+        let highlighted: String = excerpt
+            .text
+            .chars()
+            .skip(range.beginning)
+            .take(range.end - range.beginning)
+            .collect();
+        assert_eq!(highlighted, "Second");
+
+        // Words at the end of one item and the start of the next aren't
+        // matched together.
+        let output = crate::index_v3::search(&index, "item second").unwrap();
+        let excerpts = &output.results[0].excerpts;
+        assert_eq!(excerpts.len(), 2);
+        assert!(excerpts
+            .iter()
+            .any(|excerpt| excerpt.highlight_ranges.len() == 1));
+    }
 }
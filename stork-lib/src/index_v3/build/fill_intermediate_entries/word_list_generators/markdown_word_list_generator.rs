@@ -1,4 +1,8 @@
-use crate::index_v3::AnnotatedWordList;
+use std::collections::HashSet;
+
+use kuchiki::traits::TendrilSink;
+
+use crate::{config::HeadingSlugStyle, index_v3::AnnotatedWordList};
 
 use super::{html_word_list_generator, ReadResult, ReaderConfig, WordListGenerationError};
 
@@ -6,28 +10,95 @@ pub fn generate(
     config: &ReaderConfig,
     read_result: &ReadResult,
 ) -> Result<AnnotatedWordList, WordListGenerationError> {
-    let html_string = format!(
+    let mut html_string = format!(
         "<html><body><main>{}</main></body></html>",
         markdown::to_html(&read_result.buffer)
     );
 
+    if config.output.save_nearest_html_id {
+        if let Some(slug_style) = &config.output.markdown_heading_slugs {
+            html_string = replace_heading_ids(&html_string, slug_style);
+        }
+    }
+
     let html_read_result = ReadResult {
         buffer: html_string,
         filetype: read_result.filetype.clone(),
         frontmatter_fields: None,
+        streamed_word_list: None,
     };
     html_word_list_generator::generate(config, &html_read_result)
 }
 
+/**
+ * Gives each heading in the rendered document an id made with `slug_style`,
+ * suffixing repeated ids with `-1`, `-2`, and so on, like GitHub does.
+ */
+fn replace_heading_ids(html: &str, slug_style: &HeadingSlugStyle) -> String {
+    let document = kuchiki::parse_html().one(html);
+    let mut used_ids: HashSet<String> = HashSet::new();
+
+    if let Ok(headings) = document.select("h1, h2, h3, h4, h5, h6") {
+        for heading in headings {
+            let level = heading.name.local.trim_start_matches('h');
+            let slug = heading_slug(&heading.text_contents(), level, slug_style);
+
+            let mut id = slug.clone();
+            let mut suffix = 0;
+            while used_ids.contains(&id) {
+                suffix += 1;
+                id = format!("{}-{}", slug, suffix);
+            }
+
+            used_ids.insert(id.clone());
+            heading.attributes.borrow_mut().insert("id", id);
+        }
+    }
+
+    document.to_string()
+}
+
+fn heading_slug(text: &str, level: &str, slug_style: &HeadingSlugStyle) -> String {
+    match slug_style {
+        HeadingSlugStyle::Github => github_slug(text),
+        HeadingSlugStyle::Jekyll => jekyll_slug(text),
+        HeadingSlugStyle::Template(template) => template
+            .replace("{slug}", &github_slug(text))
+            .replace("{level}", level),
+    }
+}
+
+fn github_slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+fn jekyll_slug(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|run| !run.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
 #[cfg(test)]
 mod tests {
 
     use crate::{
-        config::{File, Filetype, InputConfig, OutputConfig},
+        config::{File, Filetype, HeadingSlugStyle, InputConfig, OutputConfig},
         index_v3::build::fill_intermediate_entries::{ReadResult, ReaderConfig},
+        InternalWordAnnotation,
     };
 
-    use super::generate;
+    use super::{generate, heading_slug};
 
     #[test]
     fn test_markdown() {
@@ -52,6 +123,7 @@ Goodbye!"#
                     .to_string(),
                 filetype: Some(Filetype::Markdown),
                 frontmatter_fields: None,
+                streamed_word_list: None,
             },
         )
         .unwrap()
@@ -63,4 +135,85 @@ Goodbye!"#
 
         assert_eq!(expected, computed);
     }
+
+    fn url_suffixes(slug_style: HeadingSlugStyle) -> Vec<(String, String)> {
+        generate(
+            &ReaderConfig {
+                global: InputConfig::default(),
+                file: File::default(),
+                output: OutputConfig {
+                    save_nearest_html_id: true,
+                    markdown_heading_slugs: Some(slug_style),
+                    ..OutputConfig::default()
+                },
+            },
+            &ReadResult {
+                buffer: r#"
+# Configuring Output!
+
+Intro
+
+## Examples
+
+First
+
+## Examples
+
+Second"#
+                    .to_string(),
+                filetype: Some(Filetype::Markdown),
+                frontmatter_fields: None,
+                streamed_word_list: None,
+            },
+        )
+        .unwrap()
+        .word_list
+        .into_iter()
+        .filter_map(|aw| match aw.internal_annotations.first() {
+            Some(InternalWordAnnotation::UrlSuffix(suffix)) => {
+                Some((aw.word.to_string(), suffix.clone()))
+            }
+            _ => None,
+        })
+        .filter(|(word, _)| word == "Intro" || word == "First" || word == "Second")
+        .collect()
+    }
+
+    #[test]
+    fn markdown_headings_get_github_slugs() {
+        assert_eq!(
+            url_suffixes(HeadingSlugStyle::Github),
+            vec![
+                ("Intro".to_string(), "#configuring-output".to_string()),
+                ("First".to_string(), "#examples".to_string()),
+                ("Second".to_string(), "#examples-1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_headings_get_templated_slugs() {
+        assert_eq!(
+            url_suffixes(HeadingSlugStyle::Template("h{level}-{slug}".to_string())),
+            vec![
+                ("Intro".to_string(), "#h1-configuring-output".to_string()),
+                ("First".to_string(), "#h2-examples".to_string()),
+                ("Second".to_string(), "#h2-examples-1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn slug_styles_differ_on_punctuation() {
+        let text = "  What's new in v1.4?  ";
+
+        assert_eq!(
+            heading_slug(text, "2", &HeadingSlugStyle::Github),
+            "whats-new-in-v14"
+        );
+        assert_eq!(
+            heading_slug(text, "2", &HeadingSlugStyle::Jekyll),
+            "what-s-new-in-v1-4"
+        );
+    }
 }
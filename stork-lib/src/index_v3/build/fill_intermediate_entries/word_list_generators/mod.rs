@@ -12,7 +12,9 @@ pub(super) fn create_word_list(
     read_result: &ReadResult,
 ) -> Result<AnnotatedWordList, WordListGenerationError> {
     match read_result.filetype {
-        Some(Filetype::PlainText) => plaintext_word_list_generator::generate(config, read_result),
+        Some(Filetype::PlainText | Filetype::PlainTextNoStem) => {
+            plaintext_word_list_generator::generate(config, read_result)
+        }
         Some(Filetype::SRTSubtitle) => srt_word_list_generator::generate(config, read_result),
         Some(Filetype::HTML) => html_word_list_generator::generate(config, read_result),
         Some(Filetype::Markdown) => markdown_word_list_generator::generate(config, read_result),
@@ -1,11 +1,13 @@
 use crate::config::SRTConfig;
 use crate::config::SRTTimestampFormat;
-use crate::index_v3::build::annotated_words_from_string::AnnotatedWordable;
+use crate::index_v3::build::annotated_words_from_string::configured_tokenizer;
 use crate::index_v3::build::fill_intermediate_entries::ReaderConfig;
 use crate::index_v3::AnnotatedWord;
 use crate::index_v3::AnnotatedWordList;
 use crate::InternalWordAnnotation;
 
+use crate::index_v3::build::errors::SourceLocation;
+
 use super::ReadResult;
 use super::WordListGenerationError;
 
@@ -13,25 +15,56 @@ pub fn generate(
     config: &ReaderConfig,
     read_result: &ReadResult,
 ) -> Result<AnnotatedWordList, WordListGenerationError> {
-    let subs = srtparse::from_str(&read_result.buffer)
-        .map_err(|_e| WordListGenerationError::InvalidSRT)?;
+    let subs = srtparse::from_str(&read_result.buffer).map_err(|_e| {
+        WordListGenerationError::InvalidSRT(find_invalid_timestamp(&read_result.buffer))
+    })?;
+    let tokenizer = configured_tokenizer(&config.global);
     let mut word_list: Vec<AnnotatedWord> = Vec::new();
 
     for sub in subs {
-        let mut annotated_words_for_this_sub =
-            sub.text
-                .make_annotated_words_with_annotations(|_word, internal_annotations| {
-                    internal_annotations.push(InternalWordAnnotation::UrlSuffix(
-                        build_srt_url_time_suffix(&sub.start_time, &config.global.srt_config),
-                    ));
-                });
-
-        word_list.append(&mut annotated_words_for_this_sub);
+        let url_suffix = build_srt_url_time_suffix(&sub.start_time, &config.global.srt_config);
+        word_list.extend(
+            tokenizer
+                .tokenize(&sub.text)
+                .into_iter()
+                .map(|mut annotated_word| {
+                    annotated_word
+                        .internal_annotations
+                        .push(InternalWordAnnotation::UrlSuffix(url_suffix.clone()));
+                    annotated_word
+                }),
+        );
     }
 
     Ok(AnnotatedWordList { word_list })
 }
 
+/**
+ * srtparse doesn't report where it failed, so look for the first timestamp
+ * line that doesn't parse.
+ */
+fn find_invalid_timestamp(buffer: &str) -> Option<SourceLocation> {
+    buffer.lines().enumerate().find_map(|(line_index, line)| {
+        if !line.contains("-->") {
+            return None;
+        }
+
+        let mut column = 1;
+        for part in line.split("-->") {
+            let leading_whitespace = part.len() - part.trim_start().len();
+            if part.trim().parse::<srtparse::Time>().is_err() {
+                return Some(SourceLocation {
+                    line: line_index + 1,
+                    column: column + leading_whitespace,
+                });
+            }
+            column += part.chars().count() + "-->".len();
+        }
+
+        None
+    })
+}
+
 fn build_srt_url_time_suffix(time: &srtparse::Time, srt_config: &SRTConfig) -> String {
     let time_string = match srt_config.timestamp_format {
         SRTTimestampFormat::NumberOfSeconds => {
@@ -43,3 +76,28 @@ fn build_srt_url_time_suffix(time: &srtparse::Time, srt_config: &SRTConfig) -> S
         .timestamp_template_string
         .replace("{ts}", &time_string)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_timestamp_is_located() {
+        let buffer =
+            "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\n00:00:03,000 --> 00:0x:04,000\nWorld\n";
+
+        assert_eq!(
+            find_invalid_timestamp(buffer),
+            Some(SourceLocation {
+                line: 6,
+                column: 18
+            })
+        );
+    }
+
+    #[test]
+    fn valid_timestamps_have_no_location() {
+        let buffer = "1\n00:00:01,000 --> 00:00:02,000\nHello\n";
+        assert_eq!(find_invalid_timestamp(buffer), None);
+    }
+}
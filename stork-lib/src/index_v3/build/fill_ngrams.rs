@@ -0,0 +1,152 @@
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::normalize_word;
+use crate::{
+    config::NgramConfig,
+    index_v3::{ngrams::word_ngrams, Entry, EntryIndex},
+};
+
+/**
+ * Maps every n-gram of the entries' title and contents words to the entries
+ * it appears in. Entries are split into n-grams in parallel on the current
+ * rayon pool, then merged in entry order, so each list of entries is sorted.
+ *
+ * The words are read back from each entry's stored title and contents, which
+ * are what a search checks its candidates against.
+ */
+pub(super) fn fill_ngrams(
+    entries: &[Entry],
+    config: &NgramConfig,
+) -> BTreeMap<String, Vec<EntryIndex>> {
+    let ngrams_per_entry: Vec<BTreeSet<String>> = entries
+        .par_iter()
+        .map(|entry| entry_ngrams(entry, config))
+        .collect();
+
+    let mut ngrams: BTreeMap<String, Vec<EntryIndex>> = BTreeMap::new();
+    for (entry_index, entry_ngrams) in ngrams_per_entry.into_iter().enumerate() {
+        for ngram in entry_ngrams {
+            ngrams.entry(ngram).or_default().push(entry_index);
+        }
+    }
+
+    ngrams
+}
+
+/**
+ * Every n-gram of one entry's title and contents words.
+ */
+pub(super) fn entry_ngrams(entry: &Entry, config: &NgramConfig) -> BTreeSet<String> {
+    let title_words = entry
+        .title
+        .split(|c: char| c.is_ascii_whitespace() || c == '-');
+    let contents_words = entry.contents.split_whitespace();
+
+    let mut entry_ngrams = BTreeSet::new();
+    for word in title_words.chain(contents_words) {
+        let normalized_word = normalize_word(word);
+        for n in config.lengths() {
+            entry_ngrams.extend(word_ngrams(&normalized_word, n));
+        }
+    }
+    entry_ngrams
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::*;
+    use crate::index_v3::build::build;
+
+    fn plaintext_file(title: &str, contents: &str) -> File {
+        File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ngrams_match_the_middle_of_words() {
+        let config = |index_ngrams| Config {
+            input: InputConfig {
+                files: vec![
+                    plaintext_file("Brackets", "Part ABX2004 fits every frame. Order it today."),
+                    plaintext_file("Hinges", "Parts abx20q and zbx200 are sold separately."),
+                ],
+                index_ngrams,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let without_ngrams = build(&config(None)).unwrap();
+        assert!(crate::index_v3::search(&without_ngrams.index, "x200")
+            .unwrap()
+            .results
+            .is_empty());
+
+        let with_ngrams = build(&config(Some(NgramConfig { min: 3, max: 5 }))).unwrap();
+        let output = crate::index_v3::search(&with_ngrams.index, "x200").unwrap();
+        let mut titles: Vec<&str> = output
+            .results
+            .iter()
+            .map(|result| result.entry.title.as_str())
+            .collect();
+        titles.sort_unstable();
+        assert_eq!(titles, vec!["Brackets", "Hinges"]);
+
+        let brackets = output
+            .results
+            .iter()
+            .find(|result| result.entry.title == "Brackets")
+            .unwrap();
+        let excerpt = &brackets.excerpts[0];
+        let highlight = &excerpt.highlight_ranges[0];
+        let highlighted: String = excerpt
+            .text
+            .chars()
+            .skip(highlight.beginning)
+            .take(highlight.end - highlight.beginning)
+            .collect();
+        assert_eq!(highlighted, "X200");
+
+        // The second document has both of the query's 5-grams, `abx20` and
+        // `bx200`, but in different words, so it isn't a match.
+        let output = crate::index_v3::search(&with_ngrams.index, "abx200").unwrap();
+        assert_eq!(output.results.len(), 1);
+        assert_eq!(output.results[0].entry.title, "Brackets");
+    }
+
+    #[test]
+    fn ngrams_make_the_index_larger() {
+        let config = |index_ngrams| {
+            Config {
+            input: InputConfig {
+                files: vec![plaintext_file(
+                    "Catalog",
+                    "Brackets ABX2004 ABX2005 CDY3100, hinges HNG-0042 HNG-0043, and fasteners FST77812 FST77813",
+                )],
+                index_ngrams,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        };
+
+        let without_ngrams = crate::IndexDescription::from(&build(&config(None)).unwrap());
+        let with_ngrams =
+            crate::IndexDescription::from(&build(&config(Some(NgramConfig::default()))).unwrap());
+
+        assert_eq!(without_ngrams.ngram_bytes, 0);
+        assert!(with_ngrams.ngram_bytes > 0);
+        assert!(
+            with_ngrams.index_size_bytes - without_ngrams.index_size_bytes
+                >= with_ngrams.ngram_bytes
+        );
+        assert!(with_ngrams
+            .to_string()
+            .contains("bytes of n-grams for substring search"));
+    }
+}
@@ -1,26 +1,472 @@
-use super::{remove_surrounding_punctuation, NormalizedEntry};
-use rust_stemmers::Stemmer;
-use std::collections::BTreeMap;
+use super::{
+    interner::{Interner, Stems, Word},
+    normalize_word, IndexGenerationError, NormalizedEntry,
+};
+use crate::config::{Config, StemmingConfig, UnsupportedStemmingLanguageHandling};
+use crate::index_v3::Container;
+use rayon::prelude::*;
+use rust_stemmers::{Algorithm, Stemmer};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::RwLock;
 
+/// The stem of each normalized word that's been stemmed with one algorithm.
+type StemCache = HashMap<Word, Word>;
+
+/**
+ * The languages in `input.stemming`, `input.filetype_defaults`, and each
+ * file's `stemming_override` that Stork's stemmer doesn't support, in the
+ * order they're configured, without repeats.
+ */
+pub(super) fn unsupported_stemming_languages(config: &Config) -> Vec<&str> {
+    let mut filetype_defaults: Vec<_> = config.input.filetype_defaults.iter().collect();
+    filetype_defaults.sort_by_key(|(filetype, _)| format!("{:?}", filetype));
+
+    let configured = std::iter::once(&config.input.stemming)
+        .chain(
+            filetype_defaults
+                .into_iter()
+                .filter_map(|(_, defaults)| defaults.stemming.as_ref()),
+        )
+        .chain(
+            config
+                .input
+                .files
+                .iter()
+                .filter_map(|file| file.stemming_override.as_ref()),
+        );
+
+    let mut languages: Vec<&str> = vec![];
+    for stemming in configured {
+        if let StemmingConfig::Unsupported(language) = stemming {
+            if !languages.contains(&language.as_str()) {
+                languages.push(language);
+            }
+        }
+    }
+    languages
+}
+
+/**
+ * Fails the build if the config names a language that can't be stemmed and
+ * `input.unsupported_stemming_language` is `error`. Otherwise, documents in
+ * those languages aren't stemmed, and the `Nudger` reports each language.
+ */
+pub(super) fn check_stemming_languages(config: &Config) -> Result<(), IndexGenerationError> {
+    if config.input.unsupported_stemming_language == UnsupportedStemmingLanguageHandling::Fallback {
+        return Ok(());
+    }
+
+    match unsupported_stemming_languages(config).first() {
+        Some(language) => Err(IndexGenerationError::UnsupportedStemmingLanguage(
+            language.to_string(),
+        )),
+        None => Ok(()),
+    }
+}
+
+/**
+ * Maps each stem in the corpus to the words that reduce to it, in the order
+ * those words first appear. Entries are stemmed in parallel on the current
+ * rayon pool, then merged in entry order so the result doesn't depend on
+ * the number of threads. With `share_cache`, the entries being stemmed at
+ * the same time share the stems they've found, for `input.shared_stem_cache`.
+ */
 pub fn fill_stems(
     intermediate_entries: &[NormalizedEntry],
-    stems: &mut BTreeMap<String, Vec<String>>,
+    words: &Interner,
+    stems: &mut Stems,
+    share_cache: bool,
 ) {
-    for entry in intermediate_entries {
-        let contents = &entry.annotated_word_list;
-
-        if let Some(stem_algorithm) = entry.stem_algorithm {
-            for annotated_word in &contents.word_list {
-                let normalized_word =
-                    remove_surrounding_punctuation(&annotated_word.word.to_lowercase());
-                let stem = Stemmer::create(stem_algorithm)
-                    .stem(&normalized_word)
-                    .to_string();
-                let stem_vector = stems.entry(stem).or_insert_with(Vec::default);
-                if !stem_vector.contains(&normalized_word) {
-                    stem_vector.push(normalized_word);
+    // `Algorithm` can't be hashed, but there are only a few of them.
+    let mut shared_caches: Vec<(Algorithm, RwLock<StemCache>)> = vec![];
+    if share_cache {
+        for algorithm in intermediate_entries
+            .iter()
+            .filter_map(|entry| entry.stem_algorithm)
+        {
+            if !shared_caches.iter().any(|(cached, _)| *cached == algorithm) {
+                shared_caches.push((algorithm, RwLock::default()));
+            }
+        }
+    }
+
+    let stems_per_entry: Vec<Stems> = intermediate_entries
+        .par_iter()
+        .map(|entry| {
+            let mut entry_stems = Stems::new();
+            if let Some(stem_algorithm) = entry.stem_algorithm {
+                let entry_words = entry
+                    .annotated_word_list
+                    .word_list
+                    .iter()
+                    .map(|annotated_word| &*annotated_word.word);
+                let shared_cache = shared_caches
+                    .iter()
+                    .find(|(cached, _)| *cached == stem_algorithm)
+                    .map(|(_, cache)| cache);
+                fill_stems_for_words(
+                    entry_words,
+                    &Stemmer::create(stem_algorithm),
+                    words,
+                    shared_cache,
+                    &mut entry_stems,
+                );
+            }
+            entry_stems
+        })
+        .collect();
+
+    for entry_stems in stems_per_entry {
+        for (stem, entry_words) in entry_stems {
+            let stem_vector = stems.entry(stem).or_insert_with(Vec::default);
+            for word in entry_words {
+                if !stem_vector.contains(&word) {
+                    stem_vector.push(word);
+                }
+            }
+        }
+    }
+}
+
+/**
+ * Stems each word of an entry. A word that's already been seen in the entry
+ * is skipped, since its stem is already recorded, and a word's stem is
+ * looked up in `shared_cache` before it's stemmed.
+ */
+fn fill_stems_for_words<'a, I: Iterator<Item = &'a str>>(
+    entry_words: I,
+    stemmer: &Stemmer,
+    words: &Interner,
+    shared_cache: Option<&RwLock<StemCache>>,
+    stems: &mut Stems,
+) {
+    let mut seen_words: HashSet<&str> = HashSet::new();
+    for entry_word in entry_words {
+        if !seen_words.insert(entry_word) {
+            continue;
+        }
+
+        let word = words.get_or_intern(normalize_word(entry_word));
+        let cached_stem = shared_cache.and_then(|cache| cache.read().unwrap().get(&word).copied());
+        let stem = match cached_stem {
+            Some(stem) => stem,
+            None => {
+                let stem = words.get_or_intern(stemmer.stem(words.resolve(&word)));
+                if let Some(cache) = shared_cache {
+                    cache.write().unwrap().insert(word, stem);
+                }
+                stem
+            }
+        };
+
+        let stem_vector = stems.entry(stem).or_insert_with(Vec::default);
+        if !stem_vector.contains(&word) {
+            stem_vector.push(word);
+        }
+    }
+}
+
+/**
+ * Records, in each stem's container and in the container of each word that
+ * was reduced to it, the words that were reduced to that stem, so a search
+ * for any of them can report the others.
+ */
+pub fn fill_surface_forms(
+    stems: &Stems,
+    words: &Interner,
+    containers: &mut BTreeMap<String, Container>,
+) {
+    for (stem, surface_forms) in stems {
+        let mut sorted_forms: Vec<String> = surface_forms
+            .iter()
+            .map(|word| words.resolve(word).to_string())
+            .collect();
+        sorted_forms.sort();
+
+        for key in std::iter::once(stem).chain(surface_forms) {
+            let container = containers
+                .entry(words.resolve(key).to_string())
+                .or_insert_with(Container::new);
+            merge_surface_forms(&mut container.surface_forms, &sorted_forms);
+        }
+    }
+}
+
+/// Adds `new_forms` to a container's sorted surface forms, since a word can
+/// be a surface form of more than one stem when entries are stemmed in
+/// different languages.
+fn merge_surface_forms(surface_forms: &mut Vec<String>, new_forms: &[String]) {
+    for form in new_forms {
+        if let Err(position) = surface_forms.binary_search(form) {
+            surface_forms.insert(position, form.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::*;
+    use crate::index_v3::{build::build, AnnotatedWord, AnnotatedWordList};
+    use rust_stemmers::Algorithm;
+    use std::collections::HashMap;
+
+    #[test]
+    fn stem_and_surface_form_containers_list_surface_forms() {
+        let entry = NormalizedEntry {
+            annotated_word_list: AnnotatedWordList {
+                word_list: ["Running", "runs", "run.", "walked"]
+                    .iter()
+                    .map(|word| AnnotatedWord {
+                        word: (*word).into(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            },
+            stem_algorithm: Some(Algorithm::English),
+            keywords: vec![],
+            summary: None,
+            language_undetected: false,
+            malformed_json_ld_blocks: 0,
+            invalid_thumbnail: None,
+            invalid_date: None,
+            truncated_word_count: 0,
+            unlimited_word_count: None,
+            stored_text: None,
+            generate_excerpts: true,
+            title: "".to_string(),
+            url: "".to_string(),
+            fields: HashMap::default(),
+        };
+
+        let words = Interner::default();
+        let mut stems = Stems::new();
+        fill_stems(&[entry], &words, &mut stems, false);
+
+        let mut containers = BTreeMap::new();
+        fill_surface_forms(&stems, &words, &mut containers);
+
+        assert_eq!(
+            containers.get("run").unwrap().surface_forms,
+            vec!["run", "running", "runs"]
+        );
+        assert_eq!(
+            containers.get("walk").unwrap().surface_forms,
+            vec!["walked"]
+        );
+
+        assert_eq!(
+            containers.get("running").unwrap().surface_forms,
+            vec!["run", "running", "runs"]
+        );
+        assert_eq!(
+            containers.get("walked").unwrap().surface_forms,
+            vec!["walked"]
+        );
+    }
+
+    #[test]
+    fn surface_forms_keep_first_appearance_order_before_sorting() {
+        let entries: Vec<NormalizedEntry> = ["runs", "running runs"]
+            .iter()
+            .map(|contents| NormalizedEntry {
+                annotated_word_list: AnnotatedWordList {
+                    word_list: contents
+                        .split(' ')
+                        .map(|word| AnnotatedWord {
+                            word: (*word).into(),
+                            ..Default::default()
+                        })
+                        .collect(),
+                },
+                stem_algorithm: Some(Algorithm::English),
+                keywords: vec![],
+                summary: None,
+                language_undetected: false,
+                malformed_json_ld_blocks: 0,
+                invalid_thumbnail: None,
+                invalid_date: None,
+                truncated_word_count: 0,
+                unlimited_word_count: None,
+                stored_text: None,
+                generate_excerpts: true,
+                title: "".to_string(),
+                url: "".to_string(),
+                fields: HashMap::default(),
+            })
+            .collect();
+
+        let words = Interner::default();
+        let mut stems = Stems::new();
+        fill_stems(&entries, &words, &mut stems, false);
+
+        let run = words.get("run").unwrap();
+        let surface_forms: Vec<&str> = stems[&run].iter().map(|word| words.resolve(word)).collect();
+        assert_eq!(surface_forms, vec!["runs", "running"]);
+    }
+
+    fn entry(contents: &str, stem_algorithm: Option<Algorithm>) -> NormalizedEntry {
+        NormalizedEntry {
+            annotated_word_list: AnnotatedWordList {
+                word_list: contents
+                    .split(' ')
+                    .map(|word| AnnotatedWord {
+                        word: (*word).into(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            },
+            stem_algorithm,
+            keywords: vec![],
+            summary: None,
+            language_undetected: false,
+            malformed_json_ld_blocks: 0,
+            invalid_thumbnail: None,
+            invalid_date: None,
+            truncated_word_count: 0,
+            unlimited_word_count: None,
+            stored_text: None,
+            generate_excerpts: true,
+            title: "".to_string(),
+            url: "".to_string(),
+            fields: HashMap::default(),
+        }
+    }
+
+    /// Every word stemmed as it comes, without any caching.
+    fn uncached_stems(entries: &[NormalizedEntry]) -> BTreeMap<String, Vec<String>> {
+        let mut stems: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for entry in entries {
+            if let Some(stem_algorithm) = entry.stem_algorithm {
+                let stemmer = Stemmer::create(stem_algorithm);
+                for annotated_word in &entry.annotated_word_list.word_list {
+                    let word = normalize_word(&annotated_word.word);
+                    let surface_forms = stems.entry(stemmer.stem(&word).to_string()).or_default();
+                    if !surface_forms.contains(&word) {
+                        surface_forms.push(word);
+                    }
                 }
             }
         }
+        stems
+    }
+
+    #[test]
+    fn cached_stems_match_uncached_stems() {
+        let entries: Vec<NormalizedEntry> = (0..200)
+            .map(|index| match index % 3 {
+                0 => entry(
+                    "Running runs run. runner Running walked",
+                    Some(Algorithm::English),
+                ),
+                1 => entry("laufen läuft Laufen gelaufen runs", Some(Algorithm::German)),
+                _ => entry("running unstemmed words", None),
+            })
+            .collect();
+        let expected = uncached_stems(&entries);
+
+        for share_cache in [false, true] {
+            let words = Interner::default();
+            let mut stems = Stems::new();
+            fill_stems(&entries, &words, &mut stems, share_cache);
+
+            let resolved: BTreeMap<String, Vec<String>> = stems
+                .iter()
+                .map(|(stem, surface_forms)| {
+                    (
+                        words.resolve(stem).to_string(),
+                        surface_forms
+                            .iter()
+                            .map(|word| words.resolve(word).to_string())
+                            .collect(),
+                    )
+                })
+                .collect();
+            assert_eq!(resolved, expected);
+        }
+    }
+
+    #[test]
+    fn plaintext_no_stem_files_store_words_verbatim() {
+        let build_with_filetype = |filetype| {
+            let config = Config {
+                input: InputConfig {
+                    files: vec![File {
+                        explicit_source: Some(DataSource::Contents("running runs".to_string())),
+                        title: "Log".to_string(),
+                        filetype: Some(filetype),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            build(&config).unwrap().index
+        };
+
+        let stemmed = build_with_filetype(Filetype::PlainText);
+        assert!(stemmed.containers["runs"].aliases.contains_key("running"));
+
+        let verbatim = build_with_filetype(Filetype::PlainTextNoStem);
+        assert!(verbatim.containers.contains_key("running"));
+        assert!(verbatim.containers.contains_key("runs"));
+        assert!(!verbatim.containers["runs"].aliases.contains_key("running"));
+    }
+
+    #[test]
+    fn unsupported_stemming_languages_fail_or_fall_back() {
+        use crate::config::UnsupportedStemmingLanguageHandling;
+
+        let config = |handling| -> Config {
+            let mut config = Config::try_from(
+                r#"
+                [input]
+                stemming = "Klingon"
+                "#,
+            )
+            .unwrap();
+            config.input.unsupported_stemming_language = handling;
+            config.input.files = vec![
+                File {
+                    explicit_source: Some(DataSource::Contents(
+                        "She was walking to the station".to_string(),
+                    )),
+                    title: "Walking".to_string(),
+                    filetype: Some(Filetype::PlainText),
+                    ..Default::default()
+                },
+                File {
+                    explicit_source: Some(DataSource::Contents(
+                        "They kept running all morning".to_string(),
+                    )),
+                    title: "Running".to_string(),
+                    stemming_override: Some(StemmingConfig::Unsupported("Elvish".to_string())),
+                    filetype: Some(Filetype::PlainText),
+                    ..Default::default()
+                },
+            ];
+            config
+        };
+
+        let error = build(&config(UnsupportedStemmingLanguageHandling::Error)).unwrap_err();
+        assert_eq!(
+            error,
+            IndexGenerationError::UnsupportedStemmingLanguage("Klingon".to_string())
+        );
+        assert!(error.to_string().contains("`Klingon`"));
+
+        let result = build(&config(UnsupportedStemmingLanguageHandling::Fallback)).unwrap();
+        assert_eq!(result.index.entries.len(), 2);
+        assert_eq!(result.index.config.stemming, Some(StemmingConfig::None));
+
+        let nudges: Vec<(&str, &str)> = result
+            .nudges
+            .iter()
+            .map(|nudge| (nudge.code, nudge.message.as_str()))
+            .filter(|(code, _)| *code == "N010_UNSUPPORTED_STEMMING_LANGUAGE")
+            .collect();
+        assert_eq!(nudges.len(), 2);
+        assert!(nudges[0].1.contains("`Klingon`"));
+        assert!(nudges[1].1.contains("`Elvish`"));
     }
 }
@@ -1,4 +1,5 @@
 use crate::{
+    config::OutputConfig,
     index_v3::{AnnotatedWordList, Entry},
     Fields,
 };
@@ -10,16 +11,115 @@ pub struct NormalizedEntry {
     pub(super) title: String,
     pub(super) url: String,
     pub(super) fields: Fields,
+
+    /// Terms the document lists about itself, like those in an HTML
+    /// document's `keywords` meta tag.
+    pub(super) keywords: Vec<String>,
+
+    /// A short description of the document, from the config, its
+    /// frontmatter, or its `description` meta tag.
+    pub(super) summary: Option<String>,
+
+    /// Language detection was on, but couldn't tell which language the
+    /// document is in, so it's stemmed according to the config.
+    pub(super) language_undetected: bool,
+
+    /// How many of an HTML document's JSON-LD blocks weren't valid JSON, when
+    /// `input.json_ld_properties` is set.
+    pub(super) malformed_json_ld_blocks: usize,
+
+    /// A thumbnail that was given or extracted for the document, but wasn't
+    /// stored because it doesn't look like a path or URL.
+    pub(super) invalid_thumbnail: Option<String>,
+
+    /// A date that was given or extracted for the document, but wasn't
+    /// stored because it isn't of the form `YYYY-MM-DD`.
+    pub(super) invalid_date: Option<String>,
+
+    /// How many words were cut short to `input.maximum_token_length`.
+    pub(super) truncated_word_count: usize,
+
+    /// How many words the document had, when more than
+    /// `input.maximum_words_per_document` of them were left out.
+    pub(super) unlimited_word_count: Option<usize>,
+
+    /// The text stored with the entry, when `output.store_document_text` is
+    /// set.
+    pub(super) stored_text: Option<String>,
+
+    /// The file's `generate_excerpts`. When false, the entry's text isn't
+    /// stored and its contents matches are stored without excerpts.
+    pub(super) generate_excerpts: bool,
 }
 
-impl From<&NormalizedEntry> for Entry {
-    fn from(ie: &NormalizedEntry) -> Self {
+impl From<NormalizedEntry> for Entry {
+    fn from(ie: NormalizedEntry) -> Self {
         Entry {
-            contents: ie.annotated_word_list.get_full_text(),
-            title: ie.title.clone(),
-            url: ie.url.clone(),
-            fields: ie.fields.clone(),
+            contents: if ie.generate_excerpts {
+                ie.annotated_word_list.get_full_text()
+            } else {
+                String::new()
+            },
+            title: ie.title,
+            url: ie.url,
+            fields: ie.fields,
+            score_multiplier: None,
+            word_count: Some(ie.annotated_word_list.word_list.len()),
+            text: ie.stored_text,
+            item_starts: if ie.generate_excerpts {
+                ie.annotated_word_list.item_starts()
+            } else {
+                vec![]
+            },
+        }
+    }
+}
+
+/**
+ * The text to store with a document's entry, according to
+ * `output.store_document_text` and `output.stored_text_max_chars`: its words
+ * joined with spaces, as many whole words as fit.
+ */
+pub(super) fn stored_text(config: &OutputConfig, word_list: &AnnotatedWordList) -> Option<String> {
+    if !config.store_document_text {
+        return None;
+    }
+
+    let max_chars = match config.stored_text_max_chars {
+        Some(max_chars) => max_chars,
+        None => return Some(word_list.get_full_text()),
+    };
+
+    let mut text = String::new();
+    let mut char_count = 0;
+    for annotated_word in &word_list.word_list {
+        let separator_count = if text.is_empty() { 0 } else { 1 };
+        let word_char_count = annotated_word.word.chars().count();
+        if char_count + separator_count + word_char_count > max_chars {
+            break;
+        }
+        if separator_count > 0 {
+            text.push(' ');
         }
+        text.push_str(&annotated_word.word);
+        char_count += separator_count + word_char_count;
+    }
+    Some(text)
+}
+
+/**
+ * Where documents go once they've been read and normalized.
+ */
+pub(super) trait EntrySink {
+    fn push(&mut self, entry: NormalizedEntry);
+
+    /// Called after each batch of documents is read, on the build's pool.
+    fn flush(&mut self) {}
+}
+
+impl EntrySink for Vec<NormalizedEntry> {
+    fn push(&mut self, entry: NormalizedEntry) {
+        Vec::push(self, entry);
     }
 }
 
@@ -43,11 +143,25 @@ mod tests {
             title: "My Title".to_string(),
             url: "https://example.com".to_string(),
             fields: fields.clone(),
+            score_multiplier: None,
+            word_count: None,
+            text: None,
+            item_starts: vec![],
         };
 
-        let generated = Entry::from(&NormalizedEntry {
+        let generated = Entry::from(NormalizedEntry {
             annotated_word_list: AnnotatedWordList { word_list: vec![] },
             stem_algorithm: None,
+            keywords: vec![],
+            summary: None,
+            language_undetected: false,
+            malformed_json_ld_blocks: 0,
+            invalid_thumbnail: None,
+            invalid_date: None,
+            truncated_word_count: 0,
+            unlimited_word_count: None,
+            stored_text: None,
+            generate_excerpts: true,
             title: "My Title".to_string(),
             url: "https://example.com".to_string(),
             fields: fields.clone(),
@@ -0,0 +1,249 @@
+use std::convert::TryFrom;
+
+use thiserror::Error;
+use toml_edit::{Document, Item, Table, TableLike};
+
+use crate::config::{Config, ConfigReadError};
+
+use super::nudger::{Migration, Nudge, Nudger};
+
+/**
+ * The result of applying every mechanical nudge to a config file.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigMigration {
+    /// The migrated config file, with its comments and formatting intact.
+    pub config: String,
+
+    /// The nudges that the migration resolved.
+    pub applied: Vec<Nudge>,
+
+    /// The nudges that need a person to decide what to do. The options they
+    /// refer to are left untouched.
+    pub remaining: Vec<Nudge>,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigMigrationError {
+    #[error("{0}")]
+    ConfigReadError(#[from] ConfigReadError),
+
+    #[error("Only TOML configs can be migrated. Stork recieved error: `{0}`")]
+    UnparseableToml(#[from] toml_edit::TomlError),
+}
+
+/**
+ * Rewrites a TOML config so that every nudge that can be resolved without
+ * changing the built index is resolved.
+ */
+pub fn migrate_config(config_string: &str) -> Result<ConfigMigration, ConfigMigrationError> {
+    let config = Config::try_from(config_string)?;
+    let mut document: Document = config_string.parse()?;
+
+    let mut applied = vec![];
+    let mut remaining = vec![];
+
+    for (nudge, migration) in Nudger::from(&config).into_nudges_with_migrations() {
+        let migrated = match (migration, &nudge.config_path) {
+            (Some(Migration::RemoveKey), Some(path)) => take_key(&mut document, path).is_some(),
+            (Some(Migration::MoveKey { to }), Some(path)) => move_key(&mut document, path, to),
+            _ => false,
+        };
+
+        if migrated {
+            applied.push(nudge);
+        } else {
+            remaining.push(nudge);
+        }
+    }
+
+    Ok(ConfigMigration {
+        config: document.to_string(),
+        applied,
+        remaining,
+    })
+}
+
+/// Removes the option at `path`, returning it if it was set.
+fn take_key(document: &mut Document, path: &str) -> Option<Item> {
+    let (table_path, key) = split_path(path);
+    table_at(document, &table_path, false)?.remove(key)
+}
+
+/**
+ * Moves the option at `from` to `to`, with its value and comments, adding
+ * the tables on the way to `to` if there aren't any. If `to` is already set,
+ * both are left alone, since only a person can tell which one is meant.
+ */
+fn move_key(document: &mut Document, from: &str, to: &str) -> bool {
+    let (to_table_path, to_key) = split_path(to);
+    let is_taken =
+        table_at(document, &to_table_path, false).map_or(false, |table| table.contains_key(to_key));
+    if is_taken {
+        return false;
+    }
+
+    let item = match take_key(document, from) {
+        Some(item) => item,
+        None => return false,
+    };
+
+    match table_at(document, &to_table_path, true) {
+        Some(table) => table.insert(to_key, item).is_none(),
+        None => false,
+    }
+}
+
+/// The dotted path to an option's table, and the option's key.
+fn split_path(path: &str) -> (Vec<&str>, &str) {
+    let mut segments: Vec<&str> = path.split('.').collect();
+    let key = segments.pop().unwrap_or_default();
+    (segments, key)
+}
+
+/// The table at `path`. With `create`, missing tables are added as implicit
+/// tables, so they don't get a header of their own unless they need one.
+fn table_at<'a>(
+    document: &'a mut Document,
+    path: &[&str],
+    create: bool,
+) -> Option<&'a mut dyn TableLike> {
+    let mut table: &mut dyn TableLike = document.as_table_mut();
+    for segment in path {
+        if create && !table.contains_key(segment) {
+            let mut implicit = Table::new();
+            implicit.set_implicit(true);
+            table.insert(segment, Item::Table(implicit));
+        }
+        table = table.get_mut(segment).and_then(Item::as_table_like_mut)?;
+    }
+    Some(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index_v3::build::build;
+    use bytes::Bytes;
+
+    const CONFIG: &str = r#"# Search for the docs site
+[input]
+files = [
+    { title = "First", url = "/first", contents = "One two three four five six seven eight nine ten eleven twelve." },
+    { title = "Second", url = "/second", contents = "Twelve eleven ten nine eight seven six five four three two one." },
+]
+
+[output]
+filename = "index.st"
+excerpt_buffer = 4 # keep excerpts short
+"#;
+
+    #[test]
+    fn unused_output_filename_is_removed() {
+        let migration = migrate_config(CONFIG).unwrap();
+
+        assert!(!migration.config.contains("filename"));
+        assert!(migration.config.starts_with("# Search for the docs site\n"));
+        assert!(migration
+            .config
+            .contains("excerpt_buffer = 4 # keep excerpts short"));
+        assert_eq!(migration.applied.len(), 1);
+        assert_eq!(migration.applied[0].code, "N002_OUTPUT_FILENAME");
+        assert!(migration.remaining.is_empty());
+    }
+
+    #[test]
+    fn migrated_config_builds_an_identical_index() {
+        let migration = migrate_config(CONFIG).unwrap();
+
+        let original = build(&Config::try_from(CONFIG).unwrap()).unwrap();
+        let migrated = build(&Config::try_from(migration.config.as_str()).unwrap()).unwrap();
+
+        assert_eq!(Bytes::from(&original.index), Bytes::from(&migrated.index));
+        assert!(migrated.nudges.is_empty());
+    }
+
+    #[test]
+    fn nudges_needing_judgment_are_left_alone() {
+        let config = CONFIG.replace("[input]\n", "[input]\nsurrounding_word_count = 4\n");
+        let migration = migrate_config(&config).unwrap();
+
+        assert!(migration.config.contains("surrounding_word_count = 4"));
+        assert_eq!(migration.remaining.len(), 1);
+        assert_eq!(
+            migration.remaining[0].code,
+            "N001_INPUT_SURROUNDING_WORD_COUNT"
+        );
+    }
+
+    #[test]
+    fn renamed_options_are_renamed() {
+        let config = CONFIG.replace(
+            "[input]\n",
+            "[input]\nminimum_index_ideographic_substring_length = 2 # for the Japanese pages\n",
+        );
+        let migration = migrate_config(&config).unwrap();
+
+        assert!(!migration
+            .config
+            .contains("minimum_index_ideographic_substring_length"));
+        assert!(migration
+            .config
+            .contains("minimum_indexed_ideographic_substring_length = 2 # for the Japanese pages"));
+        assert!(migration
+            .applied
+            .iter()
+            .any(|nudge| nudge.code == "N015_RENAMED_OPTION"));
+
+        let original = build(&Config::try_from(config.as_str()).unwrap()).unwrap();
+        let migrated = build(&Config::try_from(migration.config.as_str()).unwrap()).unwrap();
+        assert_eq!(Bytes::from(&original.index), Bytes::from(&migrated.index));
+        assert!(migrated.nudges.is_empty());
+    }
+
+    #[test]
+    fn renamed_options_set_under_both_names_are_left_alone() {
+        let config = CONFIG.replace(
+            "[input]\n",
+            "[input]\nminimum_index_ideographic_substring_length = 2\nminimum_indexed_ideographic_substring_length = 3\n",
+        );
+        let migration = migrate_config(&config).unwrap();
+
+        assert!(migration
+            .config
+            .contains("minimum_index_ideographic_substring_length = 2"));
+        assert!(migration
+            .remaining
+            .iter()
+            .any(|nudge| nudge.code == "N015_RENAMED_OPTION"));
+    }
+
+    #[test]
+    fn moved_sections_keep_their_options_and_comments() {
+        let mut document: Document = "[input.srt_config]\n# Link to the player\ntimestamp_linking = true\n\n[output]\nexcerpt_buffer = 4\n"
+            .parse()
+            .unwrap();
+
+        assert!(move_key(
+            &mut document,
+            "input.srt_config",
+            "output.srt_config"
+        ));
+
+        let moved = document.to_string();
+        assert!(
+            moved.contains("[output.srt_config]\n# Link to the player\ntimestamp_linking = true")
+        );
+        assert!(!moved.contains("[input.srt_config]"));
+        assert!(moved.contains("excerpt_buffer = 4"));
+    }
+
+    #[test]
+    fn json_configs_cannot_be_migrated() {
+        let config = r#"{"input": {"files": []}, "output": {"filename": "index.st"}}"#;
+        assert!(matches!(
+            migrate_config(config),
+            Err(ConfigMigrationError::UnparseableToml(_))
+        ));
+    }
+}
@@ -1,94 +1,617 @@
-use std::collections::BTreeMap;
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
 
+mod assign_facets;
+mod assign_score_multipliers;
+mod boilerplate;
+mod canonical_url;
+mod code_identifiers;
+mod disambiguate_titles;
+mod driver;
 mod fill_containers;
 mod fill_intermediate_entries;
+mod fill_ngrams;
 mod fill_stems;
+mod interner;
+mod segmentation;
+mod streaming;
+mod urls_file;
 
 mod annotated_words_from_string;
+pub use annotated_words_from_string::DefaultTokenizer;
 pub mod errors;
+pub mod exclude;
 pub mod intermediate_entry;
 
+use assign_facets::assign_facets;
+use assign_score_multipliers::assign_score_multipliers;
+use boilerplate::{check_boilerplate_patterns, find_repeated_phrases};
+use disambiguate_titles::disambiguate_titles;
 use fill_containers::fill_containers;
-use fill_intermediate_entries::fill_intermediate_entries;
-use fill_stems::fill_stems;
+use fill_intermediate_entries::{
+    fill_intermediate_entries, DocumentReports, WebClient, DETECTED_LANGUAGE_FIELD,
+};
 
-use errors::{DocumentError, IndexGenerationError, WordListGenerationError};
+#[cfg(feature = "async")]
+use driver::AsyncDriver;
+use driver::{block_on, BuildDriver, SyncDriver};
+use fill_ngrams::fill_ngrams;
+use fill_stems::{check_stemming_languages, fill_stems, fill_surface_forms};
+use interner::{Interner, Stems};
+use streaming::StreamingIndex;
+use urls_file::with_urls_file;
 
-use intermediate_entry::NormalizedEntry;
+use errors::{DocumentError, DocumentWarning, IndexGenerationError, WordListGenerationError};
 
+use exclude::ExcludedDocument;
+use intermediate_entry::{EntrySink, NormalizedEntry};
+
+pub mod estimate;
+pub mod migrate;
 pub mod nudger;
-use nudger::Nudger;
+use nudger::{Nudge, Nudger};
+
+pub mod prune;
+pub mod update;
+use prune::{prune_rare_terms, prune_to_size, PruningAction};
+
+pub mod verify_urls;
+use verify_urls::{verify_urls, UrlCheckReport};
 
-use crate::{config::Config, V3Index as Index};
+use crate::{
+    config::{Config, StemmingConfig},
+    StoredDocumentError, V3Index as Index,
+};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use tracing::Instrument;
 
-use super::{Container, Entry, PassthroughConfig};
+use super::{Container, Entry, PassthroughConfig, TermSet};
 
 #[derive(Debug)]
 pub struct BuildResult {
     pub index: Index,
     pub errors: Vec<DocumentError>,
+    pub warnings: Vec<DocumentWarning>,
+    pub nudges: Vec<Nudge>,
+
+    /// What was removed because of `input.min_document_frequency` or to fit
+    /// `output.max_index_bytes`, in order.
+    pub pruning: Vec<PruningAction>,
+
+    /// How many documents were detected to be in each language, keyed by
+    /// ISO 639-3 code. Empty unless `input.detect_language` is on.
+    pub detected_languages: BTreeMap<String, usize>,
+
+    /// The URLs that were requested, and which of them are broken. Empty
+    /// unless `input.verify_urls` is on.
+    pub url_checks: UrlCheckReport,
+
+    /// The documents that `input.exclude` left out of the index, with the
+    /// rule that excluded each one, in the order they were read.
+    pub excluded: Vec<ExcludedDocument>,
+}
+
+/**
+ * Events reported while an index is being built, so callers can display
+ * their own progress indicators.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildProgress {
+    /// The builder has started reading the document at `index` (out of
+    /// `total` documents in the config).
+    StartedDocument {
+        index: usize,
+        total: usize,
+        title: String,
+    },
+
+    /// Every document has been read, and the builder is assembling the index.
+    FillingContainers,
 }
 
 pub fn build(config: &Config) -> Result<BuildResult, IndexGenerationError> {
-    Nudger::from(config).print();
+    build_with_progress(config, |_| {})
+}
 
-    let mut intermediate_entries: Vec<NormalizedEntry> = Vec::new();
-    let mut document_errors: Vec<DocumentError> = Vec::new();
-    fill_intermediate_entries(config, &mut intermediate_entries, &mut document_errors)?;
+pub fn build_with_progress<F: FnMut(BuildProgress)>(
+    config: &Config,
+    on_progress: F,
+) -> Result<BuildResult, IndexGenerationError> {
+    build_keeping_partial_results(config, on_progress, false).map_err(|failure| failure.error)
+}
 
-    if intermediate_entries.is_empty() {
-        if !document_errors.is_empty() {
-            return Err(IndexGenerationError::AllDocumentErrors(dbg!(
-                document_errors
-            )));
-        } else {
-            return Err(IndexGenerationError::NoFilesSpecified);
+/**
+ * A build that failed, along with an index of the documents that were read
+ * before it did.
+ */
+#[derive(Debug)]
+pub struct PartialBuildError {
+    pub error: IndexGenerationError,
+
+    /// The index built from every document read before the build failed,
+    /// with the document that failed it among its errors. `None` if no
+    /// document was read successfully, or if the build failed before or
+    /// after reading documents, like when the index is too large.
+    pub partial: Option<Box<BuildResult>>,
+}
+
+impl From<IndexGenerationError> for PartialBuildError {
+    fn from(error: IndexGenerationError) -> Self {
+        PartialBuildError {
+            error,
+            partial: None,
+        }
+    }
+}
+
+/**
+ * Builds an index like `build` does, but if a document error stops the
+ * build, as it does with `input.break_on_file_error`, the documents read
+ * until then are still built into an index and returned with the error.
+ */
+pub fn build_with_partial_results(config: &Config) -> Result<BuildResult, PartialBuildError> {
+    build_keeping_partial_results(config, |_| {}, true)
+}
+
+fn build_keeping_partial_results<F: FnMut(BuildProgress)>(
+    config: &Config,
+    on_progress: F,
+    keep_partial_results: bool,
+) -> Result<BuildResult, PartialBuildError> {
+    block_on(drive_build(
+        config,
+        &mut SyncDriver::new(on_progress),
+        keep_partial_results,
+    ))
+}
+
+/**
+ * The build that every entry point runs, whether it blocks or not; `driver`
+ * decides how it waits for each step.
+ */
+async fn drive_build<D: BuildDriver>(
+    config: &Config,
+    driver: &mut D,
+    keep_partial_results: bool,
+) -> Result<BuildResult, PartialBuildError> {
+    let config = Arc::new(with_urls_file(config)?.into_owned());
+    let build_span = tracing::info_span!("build", documents = config.input.files.len());
+    drive_build_in_span(config, driver, keep_partial_results)
+        .instrument(build_span)
+        .await
+}
+
+async fn drive_build_in_span<D: BuildDriver>(
+    config: Arc<Config>,
+    driver: &mut D,
+    keep_partial_results: bool,
+) -> Result<BuildResult, PartialBuildError> {
+    check_stemming_languages(&config)?;
+    check_boilerplate_patterns(&config)?;
+    let web_client = Arc::new(WebClient::new(&config.input)?);
+    let pool = Arc::new(thread_pool(&config)?);
+
+    if config.input.stream_documents {
+        // Merges each batch of documents into the index as soon as it's
+        // read, so only one batch's word lists are held in memory at a time.
+        let streaming_index = StreamingIndex::new(Arc::clone(&config));
+        return fill_and_finish(
+            config,
+            &pool,
+            &web_client,
+            streaming_index,
+            driver,
+            keep_partial_results,
+            |config, streaming_index: StreamingIndex, reports, web_client| {
+                let (entries, containers) = streaming_index.finish();
+                assemble_index(config, entries, containers, reports, web_client)
+            },
+        )
+        .await;
+    }
+
+    fill_and_finish(
+        config,
+        &pool,
+        &web_client,
+        Vec::new(),
+        driver,
+        keep_partial_results,
+        build_from_intermediate_entries,
+    )
+    .await
+}
+
+/**
+ * Reads every document into `entries`, then builds the index from them with
+ * `finish` on the pool. If reading fails and `keep_partial_results` is set,
+ * the documents read until then are still built into a partial index.
+ */
+async fn fill_and_finish<D, S, F>(
+    config: Arc<Config>,
+    pool: &Arc<ThreadPool>,
+    web_client: &Arc<WebClient>,
+    entries: S,
+    driver: &mut D,
+    keep_partial_results: bool,
+    finish: F,
+) -> Result<BuildResult, PartialBuildError>
+where
+    D: BuildDriver,
+    S: EntrySink + Send + 'static,
+    F: FnOnce(&Config, S, DocumentReports, &WebClient) -> Result<BuildResult, IndexGenerationError>
+        + Send
+        + 'static,
+{
+    let entries = Arc::new(Mutex::new(entries));
+    let mut reports = DocumentReports::default();
+    let filled =
+        fill_intermediate_entries(&config, pool, web_client, &entries, &mut reports, driver)
+            .instrument(tracing::info_span!("fill_intermediate_entries"))
+            .await;
+
+    if let Err(error) = &filled {
+        add_fatal_document_errors(error, &mut reports.errors);
+    }
+
+    // Each step of filling has finished with the entries by now, unless the
+    // runtime gave up on one of them while it was still running.
+    let entries = Arc::try_unwrap(entries)
+        .ok()
+        .and_then(|entries| entries.into_inner().ok());
+    let web_client = Arc::clone(web_client);
+    let finish_on_pool =
+        entries.map(|entries| move || finish(&config, entries, reports, &web_client));
+
+    match (filled, finish_on_pool) {
+        (Err(error), finish_on_pool) => {
+            let partial = match finish_on_pool {
+                Some(finish_on_pool) if keep_partial_results => driver
+                    .run_on_pool(pool, finish_on_pool)
+                    .await
+                    .ok()
+                    .and_then(Result::ok),
+                _ => None,
+            };
+
+            Err(PartialBuildError {
+                error,
+                partial: partial.map(Box::new),
+            })
+        }
+        (Ok(()), None) => Err(IndexGenerationError::BuildTaskFailed(
+            "the documents that were read are still in use".to_string(),
+        )
+        .into()),
+        (Ok(()), Some(finish_on_pool)) => {
+            driver.report(BuildProgress::FillingContainers).await;
+            driver
+                .run_on_pool(pool, finish_on_pool)
+                .await?
+                .map_err(PartialBuildError::from)
         }
     }
+}
+
+/**
+ * Records the document errors that stopped a build among the errors of its
+ * partial index, unless they were already recorded.
+ */
+fn add_fatal_document_errors(
+    error: &IndexGenerationError,
+    document_errors: &mut Vec<DocumentError>,
+) {
+    for document_error in error.document_errors() {
+        if !document_errors.contains(document_error) {
+            document_errors.push(document_error.clone());
+        }
+    }
+}
 
-    let mut stems: BTreeMap<String, Vec<String>> = BTreeMap::new();
-    fill_stems(&intermediate_entries, &mut stems);
+/**
+ * Builds an index without blocking the async runtime it's called from.
+ * Progress events are sent to `progress`, if it's present; they're the same
+ * events that `build_with_progress` reports.
+ */
+#[cfg(feature = "async")]
+pub async fn build_async(
+    config: &Config,
+    progress: Option<tokio::sync::mpsc::Sender<BuildProgress>>,
+) -> Result<BuildResult, IndexGenerationError> {
+    drive_build(config, &mut AsyncDriver::new(progress), false)
+        .await
+        .map_err(|failure| failure.error)
+}
+
+/**
+ * The pool that reading, stemming, and container building share. A pool
+ * with one thread builds the index entirely serially.
+ */
+fn thread_pool(config: &Config) -> Result<ThreadPool, IndexGenerationError> {
+    ThreadPoolBuilder::new()
+        .num_threads(config.input.threads.unwrap_or(0))
+        .build()
+        .map_err(|error| IndexGenerationError::ThreadPoolNotCreated(error.to_string()))
+}
+
+fn build_from_intermediate_entries(
+    config: &Config,
+    intermediate_entries: Vec<NormalizedEntry>,
+    reports: DocumentReports,
+    web_client: &WebClient,
+) -> Result<BuildResult, IndexGenerationError> {
+    let words = Interner::default();
+    let mut stems = Stems::new();
+    tracing::info_span!("fill_stems").in_scope(|| {
+        fill_stems(
+            &intermediate_entries,
+            &words,
+            &mut stems,
+            config.input.shared_stem_cache,
+        )
+    });
 
     let mut containers: BTreeMap<String, Container> = BTreeMap::new();
-    fill_containers(config, &intermediate_entries, &stems, &mut containers);
+    tracing::info_span!("fill_containers").in_scope(|| {
+        fill_containers(
+            config,
+            &intermediate_entries,
+            &words,
+            &stems,
+            &mut containers,
+        );
+
+        if config.output.save_stem_surface_forms {
+            fill_surface_forms(&stems, &words, &mut containers);
+        }
+    });
 
     let entries: Vec<Entry> = intermediate_entries
-        .iter()
+        .into_iter()
         .map(Entry::from)
         .collect::<Vec<Entry>>();
 
+    assemble_index(config, entries, containers, reports, web_client)
+}
+
+fn assemble_index(
+    config: &Config,
+    mut entries: Vec<Entry>,
+    containers: BTreeMap<String, Container>,
+    reports: DocumentReports,
+    web_client: &WebClient,
+) -> Result<BuildResult, IndexGenerationError> {
+    let DocumentReports {
+        errors: document_errors,
+        warnings: document_warnings,
+        excluded: excluded_documents,
+    } = reports;
+
+    if entries.is_empty() {
+        if !document_errors.is_empty() {
+            return Err(IndexGenerationError::AllDocumentErrors(document_errors));
+        } else {
+            return Err(IndexGenerationError::NoFilesSpecified);
+        }
+    }
+
+    if containers.len() > config.input.maximum_unique_terms {
+        return Err(IndexGenerationError::TooManyTerms {
+            term_count: containers.len(),
+            maximum_unique_terms: config.input.maximum_unique_terms,
+            worst_documents: documents_with_most_terms(&entries, &containers),
+        });
+    }
+
+    if config.input.disambiguate_duplicate_titles {
+        disambiguate_titles(&mut entries);
+    }
+
+    if !config.input.facet_url_prefixes.is_empty() {
+        assign_facets(&mut entries, &config.input.facet_url_prefixes);
+    }
+
+    let path_score_multipliers =
+        assign_score_multipliers(&mut entries, &config.input.path_score_multipliers);
+
+    let mut detected_languages: BTreeMap<String, usize> = BTreeMap::new();
+    if config.input.detect_language {
+        for language in entries
+            .iter()
+            .filter_map(|entry| entry.fields.get(DETECTED_LANGUAGE_FIELD))
+        {
+            *detected_languages.entry(language.clone()).or_default() += 1;
+        }
+    }
+
+    tracing::info!(
+        entries = entries.len(),
+        containers = containers.len(),
+        errors = document_errors.len(),
+        warnings = document_warnings.len(),
+        "Built index"
+    );
+
+    let url_checks = if config.input.verify_urls {
+        verify_urls(&entries, &config.input, web_client)
+    } else {
+        UrlCheckReport::default()
+    };
+
     let passthrough_config = PassthroughConfig {
         url_prefix: config.input.url_prefix.clone(),
         title_boost: config.input.title_boost.clone(),
+        title_position_decay: config.input.title_position_decay.clamp(0.0, 1.0),
+        title_position_decay_curve: config.input.title_position_decay_curve,
         excerpt_buffer: config.output.excerpt_buffer,
         excerpts_per_result: config.output.excerpts_per_result,
         displayed_results_count: config.output.displayed_results_count,
+        min_query_length: config.output.min_query_length,
+        excerpt_strategy: config.output.excerpt_strategy,
+        excerpt_max_chars: config.output.excerpt_max_chars,
+        excerpt_sentence_tolerance: config.output.excerpt_sentence_tolerance,
+        excerpt_item_separator: config.output.excerpt_item_separator.clone(),
+        unknown_query_fields: config.output.unknown_query_fields,
+        query_operator: config.output.query_operator,
+        normalize_width: config.input.normalize_width,
+        acronym_periods: config.input.acronym_periods,
+        index_emoji: config.input.index_emoji,
+        index_ngrams: config.input.index_ngrams,
+        languages: config.input.language_url_prefixes.keys().cloned().collect(),
+        length_normalization: config.output.length_normalization.clamp(0.0, 1.0),
+        proximity_boost: config.output.proximity_boost.max(0.0),
+        proximity_window: config.output.proximity_window,
+        code_match_weight: config.output.code_match_weight.max(0.0),
+        match_weights: config.output.match_weights,
+        zero_result_fallback: config.output.zero_result_fallback,
+        group_by_url_without_fragment: config.output.group_by_url_without_fragment,
+        tie_breaker: config.output.tie_breaker,
+        reading_time_wpm: config.output.reading_time_wpm,
+        average_word_count: average_word_count(&entries),
+        stemming: Some(match &config.input.stemming {
+            StemmingConfig::Unsupported(_) => StemmingConfig::None,
+            stemming => stemming.clone(),
+        }),
+        stemming_varies: (cfg!(feature = "build-v3-langdetect") && config.input.detect_language)
+            || !config.input.language_url_prefixes.is_empty()
+            || config
+                .input
+                .filetype_defaults
+                .values()
+                .any(|defaults| defaults.stemming.is_some())
+            || config.input.files.iter().any(|file| {
+                file.stemming_override.is_some()
+                    || file.filetype == Some(crate::config::Filetype::PlainTextNoStem)
+            }),
+    };
+
+    let stored_document_errors = if config.output.strip_errors {
+        vec![]
+    } else {
+        document_errors
+            .iter()
+            .map(StoredDocumentError::from)
+            .collect()
     };
 
-    let index = Index {
+    let repeated_phrases = match config.input.boilerplate_detection_percent {
+        Some(percent) => tracing::info_span!("find_repeated_phrases")
+            .in_scope(|| find_repeated_phrases(&entries, percent)),
+        None => vec![],
+    };
+
+    let ngrams = match &config.input.index_ngrams {
+        Some(ngram_config) => {
+            tracing::info_span!("fill_ngrams").in_scope(|| fill_ngrams(&entries, ngram_config))
+        }
+        None => BTreeMap::new(),
+    };
+
+    let mut index = Index {
         entries,
+        terms: TermSet::from_sorted(containers.keys()),
         containers,
         config: passthrough_config,
+        document_error_count: document_errors.len(),
+        document_errors: stored_document_errors,
+        ngrams,
     };
 
+    let mut pruning: Vec<PruningAction> =
+        prune_rare_terms(&mut index, config.input.min_document_frequency)
+            .into_iter()
+            .collect();
+
+    if let Some(max_bytes) = config.output.max_index_bytes {
+        pruning.extend(
+            tracing::info_span!("prune").in_scope(|| prune_to_size(&mut index, max_bytes))?,
+        );
+    }
+
+    let mut nudges = Nudger::from(config).into_nudges();
+    nudges.extend(Nudger::from(document_warnings.as_slice()).into_nudges());
+    nudges.extend(Nudger::from(&path_score_multipliers).into_nudges());
+    nudges.extend(Nudger::from(excluded_documents.as_slice()).into_nudges());
+    nudges.extend(Nudger::from(&index).into_nudges());
+    nudges.extend(Nudger::from(repeated_phrases.as_slice()).into_nudges());
+
     Ok(BuildResult {
         index,
         errors: document_errors,
+        nudges,
+        warnings: document_warnings,
+        pruning,
+        detected_languages,
+        url_checks,
+        excluded: excluded_documents,
     })
 }
 
-fn remove_surrounding_punctuation(input: &str) -> String {
-    let mut chars: Vec<char> = input.chars().collect();
+/// How many documents `IndexGenerationError::TooManyTerms` names.
+const WORST_DOCUMENTS_COUNT: usize = 5;
 
-    while chars.first().unwrap_or(&'a').is_ascii_punctuation() {
-        chars.remove(0);
+/**
+ * The documents with results in the most containers, most first, named by
+ * title, or by URL if they have no title. Ties are broken by the order the
+ * documents were listed in.
+ */
+fn documents_with_most_terms(
+    entries: &[Entry],
+    containers: &BTreeMap<String, Container>,
+) -> Vec<(String, usize)> {
+    let mut term_counts = vec![0; entries.len()];
+    for container in containers.values() {
+        for entry_index in container.results.keys() {
+            if let Some(count) = term_counts.get_mut(*entry_index) {
+                *count += 1;
+            }
+        }
     }
 
-    while chars.last().unwrap_or(&'a').is_ascii_punctuation() {
-        chars.pop();
-    }
+    let mut documents: Vec<(usize, usize)> = term_counts.into_iter().enumerate().collect();
+    documents.sort_by_key(|&(entry_index, count)| (Reverse(count), entry_index));
+    documents
+        .into_iter()
+        .take(WORST_DOCUMENTS_COUNT)
+        .map(|(entry_index, count)| {
+            let entry = &entries[entry_index];
+            let name = if entry.title.is_empty() {
+                entry.url.clone()
+            } else {
+                entry.title.clone()
+            };
+            (name, count)
+        })
+        .collect()
+}
+
+/**
+ * The mean of the entries' stored word counts, for `length_normalization`.
+ * Entries from indexes built before word counts were stored are left out.
+ */
+fn average_word_count(entries: &[Entry]) -> f64 {
+    let word_counts: Vec<usize> = entries
+        .iter()
+        .filter_map(|entry| entry.word_count)
+        .collect();
 
-    chars.into_iter().collect()
+    word_counts.iter().sum::<usize>() as f64 / word_counts.len().max(1) as f64
+}
+
+fn remove_surrounding_punctuation(input: &str) -> &str {
+    input.trim_matches(|c: char| c.is_ascii_punctuation())
+}
+
+/**
+ * The form a word is indexed under: lowercased, without surrounding
+ * punctuation. Lowercasing never produces ASCII punctuation, so trimming
+ * first gives the same result with a single allocation.
+ */
+fn normalize_word(word: &str) -> String {
+    remove_surrounding_punctuation(word).to_lowercase()
 }
 
 #[cfg(test)]
@@ -96,7 +619,9 @@ mod tests {
 
     use crate::config::*;
 
+    use super::errors::DocumentWarningKind;
     use super::*;
+    use std::convert::TryFrom;
 
     use pretty_assertions::assert_eq;
 
@@ -180,6 +705,14 @@ mod tests {
         assert_eq!(expected, computed);
     }
 
+    #[test]
+    fn words_are_normalized_without_surrounding_punctuation() {
+        assert_eq!(normalize_word("\"Liberty,\""), "liberty");
+        assert_eq!(normalize_word("(Don't)"), "don't");
+        assert_eq!(normalize_word("ÉTÉ."), "été");
+        assert_eq!(normalize_word("..."), "");
+    }
+
     #[test]
     fn test_all_invalid_files_return_error() {
         let config = Config {
@@ -246,6 +779,708 @@ mod tests {
         assert_eq!(build(&config).unwrap().index.entries.len(), 1);
     }
 
+    #[test]
+    fn fatal_document_error_returns_partial_index() {
+        for stream_documents in [false, true] {
+            let config = Config {
+                input: InputConfig {
+                    files: vec![
+                        plaintext_file("First", TWELVE_WORDS),
+                        plaintext_file("Second", TWELVE_WORDS),
+                        generate_invalid_file_missing_selector(),
+                        plaintext_file("Fourth", TWELVE_WORDS),
+                    ],
+                    break_on_file_error: true,
+                    stream_documents,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            assert_eq!(
+                build(&config).unwrap_err(),
+                IndexGenerationError::PartialDocumentErrors(vec![])
+            );
+
+            let failure = build_with_partial_results(&config).unwrap_err();
+            assert_eq!(
+                failure.error,
+                IndexGenerationError::PartialDocumentErrors(vec![])
+            );
+
+            let partial = failure.partial.unwrap();
+            let titles: Vec<&str> = partial
+                .index
+                .entries
+                .iter()
+                .map(|entry| entry.title.as_str())
+                .collect();
+            assert_eq!(titles, vec!["First", "Second"], "{}", stream_documents);
+            assert_eq!(partial.errors.len(), 1);
+            assert_eq!(partial.errors[0].file.title, "Missing Selector");
+            assert_eq!(partial.index.metadata().document_error_count, 1);
+        }
+    }
+
+    #[test]
+    fn partial_index_needs_a_document_read_before_the_error() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    generate_invalid_file_missing_selector(),
+                    generate_valid_file(),
+                ],
+                break_on_file_error: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let failure = build_with_partial_results(&config).unwrap_err();
+        assert!(failure.partial.is_none());
+    }
+
+    fn round_trip(index: &Index) -> Index {
+        let bytes = bytes::Bytes::from(index);
+        Index::try_from(bytes.as_ref()).unwrap()
+    }
+
+    #[test]
+    fn document_errors_survive_serialization() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    generate_invalid_file_missing_selector(),
+                    generate_valid_file(),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let index = round_trip(&build(&config).unwrap().index);
+
+        assert_eq!(index.metadata().document_error_count, 1);
+        assert_eq!(
+            index.document_errors(),
+            &[StoredDocumentError {
+                file: "Missing Selector".to_string(),
+                code: "E005_SELECTOR_NOT_PRESENT".to_string(),
+                message: "The selector `.article` is not present in the HTML document.".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn item_starts_survive_serialization_without_stored_text() {
+        let html = "<html><body><main>\
+            <ul><li>First item</li><li>Second item</li></ul>\
+            </main></body></html>";
+        let config = Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(html.to_string())),
+                    title: "Lists".to_string(),
+                    filetype: Some(Filetype::HTML),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            output: OutputConfig {
+                store_document_text: false,
+                ..Default::default()
+            },
+        };
+
+        let index = round_trip(&build(&config).unwrap().index);
+
+        let entry = &index.entries[0];
+        assert_eq!(entry.text, None);
+        assert_eq!(entry.item_starts, vec![2]);
+    }
+
+    #[test]
+    fn stripped_document_errors_are_only_counted() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    generate_invalid_file_missing_selector(),
+                    generate_valid_file(),
+                ],
+                ..Default::default()
+            },
+            output: OutputConfig {
+                strip_errors: true,
+                ..Default::default()
+            },
+        };
+
+        let index = round_trip(&build(&config).unwrap().index);
+
+        assert_eq!(index.metadata().document_error_count, 1);
+        assert!(index.document_errors().is_empty());
+    }
+
+    #[test]
+    fn stemming_settings_survive_serialization() {
+        let config = |stemming_override| Config {
+            input: InputConfig {
+                stemming: StemmingConfig::Language(rust_stemmers::Algorithm::Dutch),
+                files: vec![
+                    generate_valid_file(),
+                    File {
+                        stemming_override,
+                        ..plaintext_file("Second", TWELVE_WORDS)
+                    },
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let index = round_trip(&build(&config(None)).unwrap().index);
+        assert_eq!(
+            index.config.stemming,
+            Some(StemmingConfig::Language(rust_stemmers::Algorithm::Dutch))
+        );
+        assert_eq!(index.metadata().stemming, Some("Dutch".to_string()));
+        assert!(!index.metadata().stemming_varies);
+
+        let index = round_trip(&build(&config(Some(StemmingConfig::None))).unwrap().index);
+        assert_eq!(index.metadata().stemming, Some("Dutch".to_string()));
+        assert!(index.metadata().stemming_varies);
+    }
+
+    fn plaintext_file(title: &str, contents: &str) -> File {
+        File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        }
+    }
+
+    const TWELVE_WORDS: &str = "one two three four five six seven eight nine ten eleven twelve";
+
+    fn warning_kinds(config: &Config) -> Vec<DocumentWarningKind> {
+        build(config)
+            .unwrap()
+            .warnings
+            .into_iter()
+            .map(|warning| warning.kind)
+            .collect()
+    }
+
+    #[test]
+    fn short_document_produces_warnings() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![plaintext_file("Short", "only four words here")],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            warning_kinds(&config),
+            vec![
+                DocumentWarningKind::FewWords(4),
+                DocumentWarningKind::ExcerptBufferExceedsDocument {
+                    word_count: 4,
+                    excerpt_buffer: 8
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_title_produces_warning() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    plaintext_file("Same", TWELVE_WORDS),
+                    plaintext_file("Same", TWELVE_WORDS),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            warning_kinds(&config),
+            vec![DocumentWarningKind::DuplicateTitle {
+                title: "Same".to_string(),
+                other_source: "Same".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn large_excerpt_buffer_produces_warning() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![plaintext_file("Twelve", TWELVE_WORDS)],
+                ..Default::default()
+            },
+            output: OutputConfig {
+                excerpt_buffer: 20,
+                ..Default::default()
+            },
+        };
+
+        assert_eq!(
+            warning_kinds(&config),
+            vec![DocumentWarningKind::ExcerptBufferExceedsDocument {
+                word_count: 12,
+                excerpt_buffer: 20
+            }]
+        );
+    }
+
+    #[test]
+    fn allowed_warnings_are_not_reported() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![plaintext_file("Short", "only four words here")],
+                warnings: WarningHandling::Allow,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert!(warning_kinds(&config).is_empty());
+    }
+
+    #[test]
+    fn denied_warnings_become_document_errors() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    plaintext_file("Short", "only four words here"),
+                    plaintext_file("Twelve", TWELVE_WORDS),
+                ],
+                warnings: WarningHandling::Deny,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let build_result = build(&config).unwrap();
+
+        assert!(build_result.warnings.is_empty());
+        assert_eq!(build_result.index.entries.len(), 1);
+        assert_eq!(
+            build_result.errors[0].word_list_generation_error,
+            WordListGenerationError::DeniedWarning(DocumentWarningKind::FewWords(4))
+        );
+    }
+
+    #[test]
+    fn single_threaded_build_matches_parallel_build() {
+        let sentences = [
+            "Running runners run quickly through the running track",
+            "The runner walked, then ran, then walked again",
+            "Quick quizzes quietly question quotient queries",
+            "Tracks and trackers tracked the racing runners",
+        ];
+
+        let files: Vec<File> = (0..24)
+            .map(|i| File {
+                explicit_source: Some(DataSource::Contents(format!(
+                    "{} {}",
+                    sentences[i % sentences.len()],
+                    sentences[(i * 7 + 1) % sentences.len()]
+                ))),
+                title: format!("Document {}", i),
+                filetype: Some(Filetype::PlainText),
+                ..Default::default()
+            })
+            .collect();
+
+        let build_with_threads = |threads| {
+            let config = Config {
+                input: InputConfig {
+                    files: files.clone(),
+                    threads: Some(threads),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            bytes::Bytes::from(&build(&config).unwrap().index)
+        };
+
+        let serial = build_with_threads(1);
+        assert_eq!(serial, build_with_threads(1));
+        assert_eq!(serial, build_with_threads(8));
+    }
+
+    #[test]
+    fn word_counts_and_reading_times_are_stored_for_each_entry() {
+        let index = build(&Config {
+            input: InputConfig {
+                files: vec![
+                    plaintext_file("Short", "A brief note about gardens"),
+                    File {
+                        generate_excerpts: false,
+                        ..plaintext_file(
+                            "Long",
+                            "Gardens need water and light and patience and a little luck",
+                        )
+                    },
+                ],
+                ..Default::default()
+            },
+            output: OutputConfig {
+                reading_time_wpm: 4,
+                ..Default::default()
+            },
+        })
+        .unwrap()
+        .index;
+
+        let word_counts: Vec<Option<usize>> =
+            index.entries().map(|entry| entry.word_count()).collect();
+        assert_eq!(word_counts, vec![Some(5), Some(11)]);
+
+        let mut results: Vec<(String, Option<usize>, Option<usize>)> =
+            crate::index_v3::search_with_options(&index, "gardens", &Default::default())
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| {
+                    (
+                        result.entry.title,
+                        result.entry.word_count,
+                        result.entry.reading_time_minutes,
+                    )
+                })
+                .collect();
+        results.sort();
+        assert_eq!(
+            results,
+            vec![
+                ("Long".to_string(), Some(11), Some(3)),
+                ("Short".to_string(), Some(5), Some(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn document_text_can_be_stored_and_survives_serialization() {
+        let build_with = |store_document_text: bool, stored_text_max_chars: Option<usize>| {
+            build(&Config {
+                input: InputConfig {
+                    files: vec![File {
+                        explicit_source: Some(DataSource::Contents(
+                            r#"<html><body><main><h1>Pruning roses</h1><p>Cut <em>above</em> an outward bud.</p></main></body></html>"#
+                                .to_string(),
+                        )),
+                        title: "Roses".to_string(),
+                        filetype: Some(Filetype::HTML),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                output: OutputConfig {
+                    store_document_text,
+                    stored_text_max_chars,
+                    ..Default::default()
+                },
+            })
+            .unwrap()
+            .index
+        };
+
+        let index = round_trip(&build_with(true, None));
+        assert_eq!(
+            index.entries().next().unwrap().text(),
+            Some("Pruning roses Cut above an outward bud.")
+        );
+        assert_eq!(
+            crate::index_v3::search(&index, "roses").unwrap().results[0]
+                .entry
+                .text
+                .as_deref(),
+            Some("Pruning roses Cut above an outward bud.")
+        );
+
+        let index = round_trip(&build_with(true, Some(20)));
+        assert_eq!(
+            index.entries().next().unwrap().text(),
+            Some("Pruning roses Cut")
+        );
+
+        let index = round_trip(&build_with(false, None));
+        assert_eq!(index.entries().next().unwrap().text(), None);
+    }
+
+    #[test]
+    fn enormous_words_are_cut_short() {
+        let minified = "x".repeat(2_000_000);
+        let config = Config {
+            input: InputConfig {
+                files: vec![plaintext_file(
+                    "Bundle",
+                    &format!("var bundle {} end", minified),
+                )],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let start = std::time::Instant::now();
+        let build_result = build(&config).unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_secs(30));
+
+        assert_eq!(
+            build_result
+                .warnings
+                .iter()
+                .map(|warning| warning.kind.clone())
+                .collect::<Vec<DocumentWarningKind>>(),
+            vec![
+                DocumentWarningKind::FewWords(4),
+                DocumentWarningKind::ExcerptBufferExceedsDocument {
+                    word_count: 4,
+                    excerpt_buffer: 8
+                },
+                DocumentWarningKind::LongWordsTruncated {
+                    count: 1,
+                    maximum_token_length: 1024
+                },
+            ]
+        );
+        assert!(build_result
+            .index
+            .containers
+            .keys()
+            .all(|term| term.chars().count() <= 1024));
+        assert_eq!(
+            crate::index_v3::search(&build_result.index, "bundle")
+                .unwrap()
+                .results
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn words_beyond_the_per_document_limit_are_not_indexed() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![plaintext_file(
+                    "Log",
+                    &format!("{} needle", vec![TWELVE_WORDS; 50_000].join(" ")),
+                )],
+                maximum_words_per_document: 100,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let start = std::time::Instant::now();
+        let build_result = build(&config).unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_secs(30));
+
+        assert_eq!(
+            build_result
+                .warnings
+                .iter()
+                .map(|warning| warning.kind.clone())
+                .collect::<Vec<DocumentWarningKind>>(),
+            vec![DocumentWarningKind::TooManyWords {
+                word_count: 600_001,
+                maximum_words_per_document: 100,
+            }]
+        );
+        assert_eq!(
+            build_result.index.entries().next().unwrap().word_count(),
+            Some(100)
+        );
+        assert!(crate::index_v3::search(&build_result.index, "needle")
+            .unwrap()
+            .results
+            .is_empty());
+    }
+
+    #[test]
+    fn too_many_terms_fail_the_build_and_name_the_worst_documents() {
+        let garbage: Vec<String> = (0..5_000).map(|n| format!("ocr{}garbage", n)).collect();
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    plaintext_file("Article", TWELVE_WORDS),
+                    plaintext_file("Scan", &garbage.join(" ")),
+                ],
+                maximum_unique_terms: 1_000,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let start = std::time::Instant::now();
+        let error = build(&config).unwrap_err();
+        assert!(start.elapsed() < std::time::Duration::from_secs(30));
+
+        match &error {
+            IndexGenerationError::TooManyTerms {
+                maximum_unique_terms,
+                worst_documents,
+                ..
+            } => {
+                assert_eq!(*maximum_unique_terms, 1_000);
+                assert_eq!(worst_documents[0].0, "Scan");
+                assert!(worst_documents[0].1 >= 5_000);
+                assert_eq!(worst_documents[1].0, "Article");
+            }
+            _ => panic!("expected TooManyTerms, got {:?}", error),
+        }
+        assert!(error.to_string().contains("`Scan` ("));
+    }
+
+    #[test]
+    fn builds_of_every_filetype_are_reproducible() {
+        let sources = [
+            (Filetype::PlainText, "Running runners run along the track"),
+            (
+                Filetype::Markdown,
+                "# Trackers\n\nThe trackers *tracked* the `runners` quietly.",
+            ),
+            (
+                Filetype::HTML,
+                "<html><body><main><h1 id=\"quiz\">Quizzes</h1><p>Quick quizzes question the runners.</p></main></body></html>",
+            ),
+            (
+                Filetype::SRTSubtitle,
+                "1\n00:00:01,000 --> 00:00:04,000\nThe runner walked, then ran.\n\n2\n00:00:05,000 --> 00:00:08,000\nThen walked again.\n",
+            ),
+        ];
+
+        let files: Vec<File> = (0..16)
+            .map(|i| {
+                let (filetype, contents) = &sources[i % sources.len()];
+                File {
+                    explicit_source: Some(DataSource::Contents(contents.to_string())),
+                    title: format!("Document {}", i),
+                    url: format!("/docs/{}", i),
+                    filetype: Some(filetype.clone()),
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        // The index has no timestamps, and MessagePack stores the config's
+        // floats as fixed-width binary, so the thread count and time of the
+        // build don't change its bytes.
+        let build_with_threads = |threads| {
+            let config = Config {
+                input: InputConfig {
+                    files: files.clone(),
+                    threads: Some(threads),
+                    index_ngrams: Some(NgramConfig { min: 3, max: 4 }),
+                    ..Default::default()
+                },
+                output: OutputConfig {
+                    save_stem_surface_forms: true,
+                    length_normalization: 0.35,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            bytes::Bytes::from(&build(&config).unwrap().index)
+        };
+
+        let serial = build_with_threads(1);
+        assert_eq!(serial, build_with_threads(3));
+        assert_eq!(serial, build_with_threads(8));
+    }
+
+    #[test]
+    fn build_reports_progress_for_each_document() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    generate_invalid_file_empty_contents(),
+                    generate_valid_file(),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut events = vec![];
+        build_with_progress(&config, |event| events.push(event)).unwrap();
+
+        assert_eq!(
+            events,
+            vec![
+                BuildProgress::StartedDocument {
+                    index: 0,
+                    total: 2,
+                    title: "Empty Contents".to_string()
+                },
+                BuildProgress::StartedDocument {
+                    index: 1,
+                    total: 2,
+                    title: "Successful File".to_string()
+                },
+                BuildProgress::FillingContainers,
+            ]
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_build_matches_sync_build() {
+        for stream_documents in [false, true] {
+            let config = Config {
+                input: InputConfig {
+                    files: vec![
+                        generate_invalid_file_empty_contents(),
+                        generate_valid_file(),
+                    ],
+                    stream_documents,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+
+            // Spawning the build checks that it can move between threads.
+            let (sender, mut receiver) = tokio::sync::mpsc::channel(16);
+            let async_config = config.clone();
+            let async_results = runtime
+                .block_on(
+                    runtime.spawn(async move { build_async(&async_config, Some(sender)).await }),
+                )
+                .unwrap()
+                .unwrap();
+
+            let mut async_events = vec![];
+            while let Ok(event) = receiver.try_recv() {
+                async_events.push(event);
+            }
+
+            let mut sync_events = vec![];
+            let sync_results =
+                build_with_progress(&config, |event| sync_events.push(event)).unwrap();
+
+            assert_eq!(async_events, sync_events);
+            assert_eq!(async_results.errors, sync_results.errors);
+            assert_eq!(
+                async_results.index.entries.len(),
+                sync_results.index.entries.len()
+            );
+            assert_eq!(
+                async_results.index.term_count(),
+                sync_results.index.term_count()
+            );
+        }
+    }
+
     #[test]
     fn long_normalized_word_can_be_indexed() {
         // Bug reported in issue 227.
@@ -293,4 +1528,24 @@ mod tests {
         let build_results = build(&config).unwrap();
         assert!(build_results.errors.is_empty());
     }
+
+    #[test]
+    #[cfg(feature = "build-v3-web-scraping")]
+    fn unreadable_cookie_file_fails_the_build_before_documents_are_read() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![plaintext_file("Twelve", TWELVE_WORDS)],
+                cookie_file: Some("no/such/cookies.txt".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let failure = build_with_partial_results(&config).unwrap_err();
+        assert!(matches!(
+            failure.error,
+            IndexGenerationError::InvalidCookies(_)
+        ));
+        assert!(failure.partial.is_none());
+    }
 }
@@ -1,4 +1,16 @@
-use crate::config::Config;
+use serde::Serialize;
+
+use crate::config::{
+    Config, File, Filetype, OutputConfig, UnsupportedStemmingLanguageHandling, WordSegmentation,
+};
+use crate::V3Index as Index;
+
+use super::assign_score_multipliers::PathScoreMultiplierMatches;
+use super::boilerplate::RepeatedPhrase;
+use super::errors::{pluralize_with_count, DocumentWarning};
+use super::exclude::ExcludedDocument;
+use super::fill_intermediate_entries::expected_filetype;
+use super::fill_stems::unsupported_stemming_languages;
 
 /**
  * Nudge users to build better config files.
@@ -11,61 +23,518 @@ use crate::config::Config;
 
 #[derive(Debug, PartialEq)]
 pub(super) struct Nudger {
-    nudges: Vec<Nudge>,
+    nudges: Vec<NudgeKind>,
+}
+
+/**
+ * A suggestion for improving a config file, reported alongside a successful
+ * build.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Nudge {
+    /// A stable identifier for this kind of nudge.
+    pub code: &'static str,
+    pub message: String,
+
+    /// The config option the nudge is about, if there is one.
+    pub config_path: Option<String>,
+
+    /// What to use instead of `config_path`, if there's a replacement.
+    pub suggested_replacement: Option<String>,
+}
+
+/**
+ * A change to the config file that resolves a nudge without changing the
+ * index that gets built, so `stork config migrate` can apply it unattended.
+ *
+ * No option value has been replaced by another yet, so there's no migration
+ * for rewriting values; one belongs here when a value is.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Migration {
+    /// The option at the nudge's `config_path` has no effect and can be
+    /// deleted.
+    RemoveKey,
+
+    /// The option at the nudge's `config_path` has a new name, or has moved
+    /// to another section, and works the same there.
+    MoveKey { to: &'static str },
 }
 
 #[derive(Debug, PartialEq)]
-enum Nudge {
+enum NudgeKind {
     InputSurroundingWordCount,
     OutputFile,
+    DocumentWarnings(usize),
+    LanguageDetectionUnavailable,
+    UrlVerificationUnavailable,
+    WordSegmentationUnavailable,
+
+    /// Documents listed in `input.files` that `input.exclude` left out by
+    /// their path or URL.
+    ListedFilesExcluded(usize),
+
+    /// A stemming language that can't be stemmed, whose documents were
+    /// indexed without stemming.
+    UnsupportedStemmingLanguage {
+        language: String,
+    },
+
+    /// An option in `input.filetype_defaults` that every file of its
+    /// filetype overrides.
+    ShadowedFiletypeDefault {
+        filetype: Filetype,
+        option: &'static str,
+    },
+
+    /// A rule in `input.path_score_multipliers` that no document used.
+    UnusedPathScoreMultiplier {
+        prefix: String,
+    },
+
+    /// Two rules in `input.path_score_multipliers` where one's prefix starts
+    /// with the other's, with how many documents used each.
+    OverlappingPathScoreMultipliers {
+        prefixes: (String, String),
+        match_counts: (usize, usize),
+    },
+
+    /// `output.excerpts_per_result` is more than the most excerpts the
+    /// built index stores for any word in any document.
+    ExcerptsPerResultExceedsStored {
+        excerpts_per_result: u8,
+        most_stored: usize,
+    },
+
+    /// `output.displayed_results_count` is more than the number of
+    /// documents in the built index.
+    DisplayedResultsCountExceedsEntries {
+        displayed_results_count: u8,
+        entry_count: usize,
+    },
+
+    /// A run of words found in enough documents to reach
+    /// `input.boilerplate_detection_percent`.
+    RepeatedPhrase {
+        phrase: String,
+        document_count: usize,
+        document_total: usize,
+    },
+
+    /// An option set under the name it had before it was renamed or moved.
+    RenamedOption {
+        from: &'static str,
+        to: &'static str,
+    },
 }
 
-impl Nudge {
-    fn description(&self) -> &str {
+impl NudgeKind {
+    fn code(&self) -> &'static str {
+        match self {
+            NudgeKind::InputSurroundingWordCount => "N001_INPUT_SURROUNDING_WORD_COUNT",
+            NudgeKind::OutputFile => "N002_OUTPUT_FILENAME",
+            NudgeKind::DocumentWarnings(_) => "N003_DOCUMENT_WARNINGS",
+            NudgeKind::LanguageDetectionUnavailable => "N004_LANGUAGE_DETECTION_UNAVAILABLE",
+            NudgeKind::UrlVerificationUnavailable => "N008_URL_VERIFICATION_UNAVAILABLE",
+            NudgeKind::WordSegmentationUnavailable => "N011_WORD_SEGMENTATION_UNAVAILABLE",
+            NudgeKind::ListedFilesExcluded(_) => "N009_LISTED_FILES_EXCLUDED",
+            NudgeKind::UnsupportedStemmingLanguage { .. } => "N010_UNSUPPORTED_STEMMING_LANGUAGE",
+            NudgeKind::ShadowedFiletypeDefault { .. } => "N005_SHADOWED_FILETYPE_DEFAULT",
+            NudgeKind::UnusedPathScoreMultiplier { .. } => "N006_UNUSED_PATH_SCORE_MULTIPLIER",
+            NudgeKind::OverlappingPathScoreMultipliers { .. } => {
+                "N007_OVERLAPPING_PATH_SCORE_MULTIPLIERS"
+            }
+            NudgeKind::ExcerptsPerResultExceedsStored { .. } => {
+                "N012_EXCERPTS_PER_RESULT_EXCEEDS_STORED"
+            }
+            NudgeKind::DisplayedResultsCountExceedsEntries { .. } => {
+                "N013_DISPLAYED_RESULTS_COUNT_EXCEEDS_DOCUMENTS"
+            }
+            NudgeKind::RepeatedPhrase { .. } => "N014_REPEATED_PHRASE",
+            NudgeKind::RenamedOption { .. } => "N015_RENAMED_OPTION",
+        }
+    }
+
+    fn config_path(&self) -> Option<String> {
+        match self {
+            NudgeKind::InputSurroundingWordCount => {
+                Some("input.surrounding_word_count".to_string())
+            }
+            NudgeKind::OutputFile => Some("output.filename".to_string()),
+            NudgeKind::DocumentWarnings(_) => Some("input.warnings".to_string()),
+            NudgeKind::LanguageDetectionUnavailable => Some("input.detect_language".to_string()),
+            NudgeKind::UrlVerificationUnavailable => Some("input.verify_urls".to_string()),
+            NudgeKind::WordSegmentationUnavailable => Some("input.word_segmentation".to_string()),
+            NudgeKind::ListedFilesExcluded(_) => Some("input.exclude".to_string()),
+            NudgeKind::UnsupportedStemmingLanguage { .. } => Some("input.stemming".to_string()),
+            NudgeKind::ShadowedFiletypeDefault { filetype, option } => {
+                Some(format!("input.filetype_defaults.{:?}.{}", filetype, option))
+            }
+            NudgeKind::UnusedPathScoreMultiplier { .. }
+            | NudgeKind::OverlappingPathScoreMultipliers { .. } => {
+                Some("input.path_score_multipliers".to_string())
+            }
+            NudgeKind::ExcerptsPerResultExceedsStored { .. } => {
+                Some("output.excerpts_per_result".to_string())
+            }
+            NudgeKind::DisplayedResultsCountExceedsEntries { .. } => {
+                Some("output.displayed_results_count".to_string())
+            }
+            NudgeKind::RepeatedPhrase { .. } => Some("input.boilerplate_phrases".to_string()),
+            NudgeKind::RenamedOption { from, .. } => Some(from.to_string()),
+        }
+    }
+
+    fn suggested_replacement(&self) -> Option<&'static str> {
         match self {
-            Nudge::InputSurroundingWordCount => "The config option `input.surrounding_word_count` is deprecated and has no effect. Please use output.excerpt_buffer instead.",
-            Nudge::OutputFile => "The config option `output.filename` is deprecated and has no effect. Please use the --output command line option instead."
+            NudgeKind::InputSurroundingWordCount => Some("output.excerpt_buffer"),
+            NudgeKind::OutputFile => Some("--output"),
+            NudgeKind::DocumentWarnings(_) => None,
+            NudgeKind::LanguageDetectionUnavailable => None,
+            NudgeKind::UrlVerificationUnavailable => None,
+            NudgeKind::WordSegmentationUnavailable => None,
+            NudgeKind::ListedFilesExcluded(_) => None,
+            NudgeKind::UnsupportedStemmingLanguage { .. } => None,
+            NudgeKind::ShadowedFiletypeDefault { .. } => None,
+            NudgeKind::UnusedPathScoreMultiplier { .. } => None,
+            NudgeKind::OverlappingPathScoreMultipliers { .. } => None,
+            NudgeKind::ExcerptsPerResultExceedsStored { .. } => None,
+            NudgeKind::DisplayedResultsCountExceedsEntries { .. } => None,
+            NudgeKind::RepeatedPhrase { .. } => None,
+            NudgeKind::RenamedOption { to, .. } => Some(to),
+        }
+    }
+
+    /// Options that still affect the index, or whose replacement would,
+    /// need a person to decide what to do and have no migration.
+    fn migration(&self) -> Option<Migration> {
+        match self {
+            NudgeKind::InputSurroundingWordCount => None,
+            NudgeKind::OutputFile => Some(Migration::RemoveKey),
+            NudgeKind::DocumentWarnings(_) => None,
+            NudgeKind::LanguageDetectionUnavailable => None,
+            NudgeKind::UrlVerificationUnavailable => None,
+            NudgeKind::WordSegmentationUnavailable => None,
+            NudgeKind::ListedFilesExcluded(_) => None,
+            NudgeKind::UnsupportedStemmingLanguage { .. } => None,
+            NudgeKind::ShadowedFiletypeDefault { .. } => Some(Migration::RemoveKey),
+            NudgeKind::UnusedPathScoreMultiplier { .. } => None,
+            NudgeKind::OverlappingPathScoreMultipliers { .. } => None,
+            NudgeKind::ExcerptsPerResultExceedsStored { .. } => None,
+            NudgeKind::DisplayedResultsCountExceedsEntries { .. } => None,
+            NudgeKind::RepeatedPhrase { .. } => None,
+            NudgeKind::RenamedOption { to, .. } => Some(Migration::MoveKey { to }),
+        }
+    }
+
+    fn description(&self) -> String {
+        match self {
+            NudgeKind::InputSurroundingWordCount => "The config option `input.surrounding_word_count` is deprecated and has no effect. Please use output.excerpt_buffer instead.".to_string(),
+            NudgeKind::OutputFile => "The config option `output.filename` is deprecated and has no effect. Please use the --output command line option instead.".to_string(),
+            NudgeKind::DocumentWarnings(count) => format!("Stork found possible problems in {}; they're listed in the build report. Set `input.warnings` to `allow` to hide them, or to `deny` to treat them as errors.", pluralize_with_count(*count, "document", "documents")),
+            NudgeKind::LanguageDetectionUnavailable => "The config option `input.detect_language` has no effect because this build of Stork doesn't include the `build-v3-langdetect` feature. Every document is stemmed according to `input.stemming`.".to_string(),
+            NudgeKind::UrlVerificationUnavailable => "The config option `input.verify_urls` has no effect because this build of Stork doesn't include the `build-v3-web-scraping` feature. No URLs were requested.".to_string(),
+            NudgeKind::WordSegmentationUnavailable => "The config option `input.word_segmentation = \"dictionary\"` has no effect because this build of Stork doesn't include the `build-v3-thai-lao-segmentation` feature. Text is only split into words at whitespace and hyphens.".to_string(),
+            NudgeKind::ListedFilesExcluded(count) => format!("{} listed in `input.files` but excluded by a path or URL rule in `input.exclude`; they're listed in the build report. If that's intended, remove them from `input.files` instead.", pluralize_with_count(*count, "document is", "documents are")),
+            NudgeKind::UnsupportedStemmingLanguage { language } => format!("Stork can't stem `{}`, so the documents configured to be stemmed in it weren't stemmed. Use `none` or a language supported by https://snowballstem.org/, e.g. `Dutch`.", language),
+            NudgeKind::ShadowedFiletypeDefault { filetype, option } => format!("The config option `input.filetype_defaults.{:?}.{}` has no effect because every {:?} file sets its own `{}_override`.", filetype, option, filetype, option),
+            NudgeKind::UnusedPathScoreMultiplier { prefix } => format!("The rule for `{}` in `input.path_score_multipliers` applies to no documents. Prefixes are compared with the path of each document's URL, like `/docs/`.", prefix),
+            NudgeKind::OverlappingPathScoreMultipliers { prefixes: (first, second), match_counts: (first_count, second_count) } => format!("The rules for `{}` and `{}` in `input.path_score_multipliers` overlap. Each document uses the one with the longer prefix, or the first if they're the same: `{}` applies to {}, and `{}` to {}.", first, second, first, pluralize_with_count(*first_count, "document", "documents"), second, pluralize_with_count(*second_count, "document", "documents")),
+            NudgeKind::ExcerptsPerResultExceedsStored { excerpts_per_result, most_stored } => format!("The config option `output.excerpts_per_result` is {}, but no document has more than {} stored for any one word, so a search for one word never shows more than {} per result. Lower `output.excerpts_per_result`, or raise `output.max_stored_excerpts_per_result` if it's what limits them.", excerpts_per_result, pluralize_with_count(*most_stored, "excerpt", "excerpts"), most_stored),
+            NudgeKind::DisplayedResultsCountExceedsEntries { displayed_results_count, entry_count } => format!("The config option `output.displayed_results_count` is {}, but the index has {}, so no search shows more than {} results.", displayed_results_count, pluralize_with_count(*entry_count, "document", "documents"), entry_count),
+            NudgeKind::RepeatedPhrase { phrase, document_count, document_total } => format!("The phrase \"{}\" appears in {} of {} documents. If it's boilerplate, like a footer or a cookie banner, add it to `input.boilerplate_phrases` to keep it out of the index.", phrase, document_count, document_total),
+            NudgeKind::RenamedOption { from, to } => format!("The config option `{}` has been renamed to `{}`, which works the same. The old name still works, but `stork config migrate` can rename it for you.", from, to),
         }
     }
 }
 
 impl From<&Config> for Nudger {
     fn from(config: &Config) -> Self {
-        let mut nudges: Vec<Nudge> = vec![];
+        let mut nudges: Vec<NudgeKind> = vec![];
 
         if config.input.UNUSED_surrounding_word_count.is_some() {
-            nudges.push(Nudge::InputSurroundingWordCount)
+            nudges.push(NudgeKind::InputSurroundingWordCount)
         }
 
         if config.output.UNUSED_filename.is_some() {
-            nudges.push(Nudge::OutputFile)
+            nudges.push(NudgeKind::OutputFile)
+        }
+
+        if config
+            .input
+            .DEPRECATED_minimum_index_ideographic_substring_length
+            .is_some()
+        {
+            nudges.push(NudgeKind::RenamedOption {
+                from: "input.minimum_index_ideographic_substring_length",
+                to: "input.minimum_indexed_ideographic_substring_length",
+            })
         }
 
+        if config.input.detect_language && cfg!(not(feature = "build-v3-langdetect")) {
+            nudges.push(NudgeKind::LanguageDetectionUnavailable)
+        }
+
+        if config.input.verify_urls && cfg!(not(feature = "build-v3-web-scraping")) {
+            nudges.push(NudgeKind::UrlVerificationUnavailable)
+        }
+
+        if config.input.word_segmentation == WordSegmentation::Dictionary
+            && cfg!(not(feature = "build-v3-thai-lao-segmentation"))
+        {
+            nudges.push(NudgeKind::WordSegmentationUnavailable)
+        }
+
+        if config.input.unsupported_stemming_language
+            == UnsupportedStemmingLanguageHandling::Fallback
+        {
+            nudges.extend(
+                unsupported_stemming_languages(config)
+                    .into_iter()
+                    .map(|language| NudgeKind::UnsupportedStemmingLanguage {
+                        language: language.to_string(),
+                    }),
+            );
+        }
+
+        nudges.extend(shadowed_filetype_defaults(config));
+
         Nudger { nudges }
     }
 }
 
-impl Nudger {
-    pub(super) fn print(&self) {
-        if !self.nudges.is_empty() {
-            eprintln!("Config Warnings:");
+/// An option in `input.filetype_defaults`, whether it's set for a filetype,
+/// and whether a file overrides it.
+type FiletypeDefaultOption = (&'static str, bool, fn(&File) -> bool);
+
+/**
+ * The options in `input.filetype_defaults` that no file uses, because every
+ * file of that filetype sets its own override. Files whose filetype isn't
+ * known until they're read might be of any filetype, so they count as
+ * files of each one. Filetypes without any files aren't reported.
+ */
+fn shadowed_filetype_defaults(config: &Config) -> Vec<NudgeKind> {
+    let mut filetype_defaults: Vec<_> = config.input.filetype_defaults.iter().collect();
+    filetype_defaults.sort_by_key(|(filetype, _)| format!("{:?}", filetype));
+
+    let mut shadowed = vec![];
+    for (filetype, defaults) in filetype_defaults {
+        let files: Vec<&File> = config
+            .input
+            .files
+            .iter()
+            .filter(|file| {
+                expected_filetype(file, config.input.default_filetype.as_ref())
+                    .map_or(true, |expected| expected == *filetype)
+            })
+            .collect();
+
+        if files.is_empty() {
+            continue;
         }
 
-        for nudge in &self.nudges {
-            eprintln!("{}", nudge.description());
+        let options: [FiletypeDefaultOption; 4] = [
+            ("stemming", defaults.stemming.is_some(), |file| {
+                file.stemming_override.is_some()
+            }),
+            ("html_selector", defaults.html_selector.is_some(), |file| {
+                file.html_selector_override.is_some()
+            }),
+            (
+                "exclude_html_selector",
+                defaults.exclude_html_selector.is_some(),
+                |file| file.exclude_html_selector_override.is_some(),
+            ),
+            (
+                "frontmatter_handling",
+                defaults.frontmatter_handling.is_some(),
+                |file| file.frontmatter_handling_override.is_some(),
+            ),
+        ];
+
+        for (option, is_set, is_overridden) in options {
+            if is_set && files.iter().all(|file| is_overridden(file)) {
+                shadowed.push(NudgeKind::ShadowedFiletypeDefault {
+                    filetype: filetype.clone(),
+                    option,
+                });
+            }
         }
     }
+
+    shadowed
+}
+
+/**
+ * Reports the rules of `input.path_score_multipliers` that weren't used, and
+ * each pair of rules that overlap, so their prefixes can be checked.
+ */
+impl From<&PathScoreMultiplierMatches<'_>> for Nudger {
+    fn from(matches: &PathScoreMultiplierMatches) -> Self {
+        let rules: Vec<(&str, usize)> = matches
+            .rules
+            .iter()
+            .map(|rule| rule.prefix.as_str())
+            .zip(matches.match_counts.iter().copied())
+            .collect();
+
+        let mut nudges: Vec<NudgeKind> = rules
+            .iter()
+            .filter(|(_, match_count)| *match_count == 0)
+            .map(|(prefix, _)| NudgeKind::UnusedPathScoreMultiplier {
+                prefix: prefix.to_string(),
+            })
+            .collect();
+
+        for (position, (first, first_count)) in rules.iter().enumerate() {
+            for (second, second_count) in &rules[position + 1..] {
+                if first.starts_with(second) || second.starts_with(first) {
+                    nudges.push(NudgeKind::OverlappingPathScoreMultipliers {
+                        prefixes: (first.to_string(), second.to_string()),
+                        match_counts: (*first_count, *second_count),
+                    });
+                }
+            }
+        }
+
+        Nudger { nudges }
+    }
+}
+
+/**
+ * Checks the display counts that were changed from their defaults against
+ * what the built index can give them, since otherwise the frontend quietly
+ * shows fewer excerpts or results than asked for.
+ */
+impl From<&Index> for Nudger {
+    fn from(index: &Index) -> Self {
+        let defaults = OutputConfig::default();
+        let mut nudges = vec![];
+
+        let excerpts_per_result = index.config.excerpts_per_result;
+        if excerpts_per_result != defaults.excerpts_per_result {
+            let most_stored = index
+                .containers
+                .values()
+                .flat_map(|container| container.results.values())
+                .map(|result| result.excerpts.len())
+                .max()
+                .unwrap_or_default();
+
+            if usize::from(excerpts_per_result) > most_stored {
+                nudges.push(NudgeKind::ExcerptsPerResultExceedsStored {
+                    excerpts_per_result,
+                    most_stored,
+                });
+            }
+        }
+
+        let displayed_results_count = index.config.displayed_results_count;
+        if displayed_results_count != defaults.displayed_results_count
+            && usize::from(displayed_results_count) > index.entries.len()
+        {
+            nudges.push(NudgeKind::DisplayedResultsCountExceedsEntries {
+                displayed_results_count,
+                entry_count: index.entries.len(),
+            });
+        }
+
+        Nudger { nudges }
+    }
+}
+
+impl From<&[RepeatedPhrase]> for Nudger {
+    fn from(repeated_phrases: &[RepeatedPhrase]) -> Self {
+        let nudges = repeated_phrases
+            .iter()
+            .map(|repeated| NudgeKind::RepeatedPhrase {
+                phrase: repeated.phrase.clone(),
+                document_count: repeated.document_count,
+                document_total: repeated.document_total,
+            })
+            .collect();
+
+        Nudger { nudges }
+    }
+}
+
+impl From<&[DocumentWarning]> for Nudger {
+    fn from(warnings: &[DocumentWarning]) -> Self {
+        let mut documents: Vec<&crate::config::File> =
+            warnings.iter().map(|warning| &warning.file).collect();
+        documents.dedup();
+
+        let nudges = if documents.is_empty() {
+            vec![]
+        } else {
+            vec![NudgeKind::DocumentWarnings(documents.len())]
+        };
+
+        Nudger { nudges }
+    }
+}
+
+impl From<&[ExcludedDocument]> for Nudger {
+    fn from(excluded_documents: &[ExcludedDocument]) -> Self {
+        let count = excluded_documents
+            .iter()
+            .filter(|excluded| excluded.rule.matches_listing())
+            .count();
+
+        let nudges = if count == 0 {
+            vec![]
+        } else {
+            vec![NudgeKind::ListedFilesExcluded(count)]
+        };
+
+        Nudger { nudges }
+    }
+}
+
+impl Nudger {
+    /**
+     * Converts the nudges into values the caller can display however it
+     * likes. Each one is also logged as a `tracing` debug event.
+     */
+    pub(super) fn into_nudges(self) -> Vec<Nudge> {
+        self.into_nudges_with_migrations()
+            .into_iter()
+            .map(|(nudge, _)| nudge)
+            .collect()
+    }
+
+    /**
+     * Like `into_nudges`, but pairs each nudge with the change that would
+     * resolve it automatically, if there is one.
+     */
+    pub(super) fn into_nudges_with_migrations(self) -> Vec<(Nudge, Option<Migration>)> {
+        self.nudges
+            .into_iter()
+            .map(|kind| {
+                let nudge = Nudge {
+                    code: kind.code(),
+                    message: kind.description(),
+                    config_path: kind.config_path(),
+                    suggested_replacement: kind.suggested_replacement().map(ToString::to_string),
+                };
+                tracing::debug!(code = nudge.code, "{}", nudge.message);
+                (nudge, kind.migration())
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::*;
+    use crate::index_v3::build::{build, errors::DocumentWarningKind};
+    use std::collections::HashMap;
 
     #[test]
     fn create_nudge() {
         let intended = Nudger {
-            nudges: vec![Nudge::InputSurroundingWordCount],
+            nudges: vec![NudgeKind::InputSurroundingWordCount],
         };
 
         let generated = Nudger::from(&Config {
@@ -79,10 +548,261 @@ mod tests {
         assert_eq!(intended, generated)
     }
 
+    #[test]
+    fn document_warnings_create_one_nudge_per_build() {
+        let file = File {
+            title: "Stub".to_string(),
+            ..Default::default()
+        };
+
+        let generated = Nudger::from(
+            [
+                DocumentWarning {
+                    file: file.clone(),
+                    kind: DocumentWarningKind::FewWords(2),
+                },
+                DocumentWarning {
+                    file,
+                    kind: DocumentWarningKind::ExcerptBufferExceedsDocument {
+                        word_count: 2,
+                        excerpt_buffer: 8,
+                    },
+                },
+            ]
+            .as_slice(),
+        );
+
+        assert_eq!(
+            generated,
+            Nudger {
+                nudges: vec![NudgeKind::DocumentWarnings(1)]
+            }
+        );
+    }
+
+    #[test]
+    fn only_files_excluded_by_path_or_url_create_a_nudge() {
+        use crate::index_v3::build::exclude::ExclusionRule;
+
+        let excluded = |rule| ExcludedDocument {
+            file: File::default(),
+            rule,
+        };
+
+        assert_eq!(
+            Nudger::from([excluded(ExclusionRule::MinimumWordCount(30))].as_slice()),
+            Nudger { nudges: vec![] }
+        );
+        assert_eq!(
+            Nudger::from(
+                [
+                    excluded(ExclusionRule::PathGlob("drafts/**".to_string())),
+                    excluded(ExclusionRule::MinimumWordCount(30)),
+                    excluded(ExclusionRule::UrlPattern("/private/*".to_string())),
+                ]
+                .as_slice()
+            ),
+            Nudger {
+                nudges: vec![NudgeKind::ListedFilesExcluded(2)]
+            }
+        );
+    }
+
+    #[test]
+    fn nudges_describe_the_config_option() {
+        let nudges = Nudger::from(&Config {
+            output: OutputConfig {
+                UNUSED_filename: Some("index.st".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .into_nudges();
+
+        assert_eq!(nudges.len(), 1);
+        assert_eq!(nudges[0].code, "N002_OUTPUT_FILENAME");
+        assert_eq!(nudges[0].config_path.as_deref(), Some("output.filename"));
+        assert_eq!(nudges[0].suggested_replacement.as_deref(), Some("--output"));
+    }
+
+    #[test]
+    fn filetype_default_overridden_by_every_file_creates_nudge() {
+        let html_file = |path: &str| File {
+            explicit_source: Some(DataSource::FilePath(path.to_string())),
+            html_selector_override: Some("article".to_string()),
+            ..Default::default()
+        };
+        let config = |files: Vec<File>| Config {
+            input: InputConfig {
+                filetype_defaults: HashMap::from([(
+                    Filetype::HTML,
+                    FiletypeDefaults {
+                        html_selector: Some(".content".to_string()),
+                        exclude_html_selector: Some("nav".to_string()),
+                        ..Default::default()
+                    },
+                )]),
+                files,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let nudges =
+            Nudger::from(&config(vec![html_file("a.html"), html_file("b.htm")])).into_nudges();
+        assert_eq!(nudges.len(), 1);
+        assert_eq!(nudges[0].code, "N005_SHADOWED_FILETYPE_DEFAULT");
+        assert_eq!(
+            nudges[0].config_path.as_deref(),
+            Some("input.filetype_defaults.HTML.html_selector")
+        );
+
+        // A file whose filetype is only known once it's read might be HTML.
+        let unknown = File {
+            explicit_source: Some(DataSource::FilePath("notes".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(
+            Nudger::from(&config(vec![html_file("a.html"), unknown])),
+            Nudger { nudges: vec![] }
+        );
+    }
+
+    #[test]
+    fn unused_and_overlapping_path_score_multipliers_create_nudges() {
+        let rules = [
+            PathScoreMultiplier {
+                prefix: "/changelog/".to_string(),
+                multiplier: 0.5,
+            },
+            PathScoreMultiplier {
+                prefix: "/blog/".to_string(),
+                multiplier: 0.8,
+            },
+            PathScoreMultiplier {
+                prefix: "/changelog/2018/".to_string(),
+                multiplier: 0.2,
+            },
+        ];
+
+        let nudges = Nudger::from(&PathScoreMultiplierMatches {
+            rules: &rules,
+            match_counts: vec![12, 0, 1],
+        })
+        .into_nudges();
+
+        let codes: Vec<&str> = nudges.iter().map(|nudge| nudge.code).collect();
+        assert_eq!(
+            codes,
+            vec![
+                "N006_UNUSED_PATH_SCORE_MULTIPLIER",
+                "N007_OVERLAPPING_PATH_SCORE_MULTIPLIERS"
+            ]
+        );
+        assert!(nudges[0].message.contains("`/blog/`"));
+        assert!(nudges[1].message.ends_with(
+            "`/changelog/` applies to 12 documents, and `/changelog/2018/` to 1 document."
+        ));
+    }
+
     #[test]
     fn default_config_creates_empty_nudge() {
         let intended = Nudger { nudges: vec![] };
         let generated = Nudger::from(&Config::default());
         assert_eq!(intended, generated)
     }
+
+    #[test]
+    fn deprecated_config_options_are_reported_as_nudges() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        "one two three four five six seven eight nine ten eleven twelve"
+                            .to_string(),
+                    )),
+                    title: "Twelve".to_string(),
+                    filetype: Some(Filetype::PlainText),
+                    ..Default::default()
+                }],
+                UNUSED_surrounding_word_count: Some(12),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let nudges = build(&config).unwrap().nudges;
+        assert_eq!(nudges.len(), 1);
+        assert_eq!(nudges[0].code, "N001_INPUT_SURROUNDING_WORD_COUNT");
+    }
+
+    #[test]
+    fn display_counts_the_index_cannot_satisfy_are_reported_as_nudges() {
+        let config = |excerpts_per_result, displayed_results_count| Config {
+            input: InputConfig {
+                files: vec![
+                    File {
+                        explicit_source: Some(DataSource::Contents(
+                            "one two three four five six seven eight nine ten eleven twelve"
+                                .to_string(),
+                        )),
+                        title: "Twelve".to_string(),
+                        filetype: Some(Filetype::PlainText),
+                        ..Default::default()
+                    },
+                    File {
+                        explicit_source: Some(DataSource::Contents(
+                            "one two one two one two".to_string(),
+                        )),
+                        title: "Twice".to_string(),
+                        filetype: Some(Filetype::PlainText),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+            output: OutputConfig {
+                excerpts_per_result,
+                displayed_results_count,
+                ..Default::default()
+            },
+        };
+        // The short documents get other nudges too, which aren't tested here.
+        let display_count_nudges = |config: &Config| -> Vec<Nudge> {
+            build(config)
+                .unwrap()
+                .nudges
+                .into_iter()
+                .filter(|nudge| {
+                    nudge.code == "N012_EXCERPTS_PER_RESULT_EXCEEDS_STORED"
+                        || nudge.code == "N013_DISPLAYED_RESULTS_COUNT_EXCEEDS_DOCUMENTS"
+                })
+                .collect()
+        };
+
+        let nudges = display_count_nudges(&config(8, 3));
+        assert_eq!(
+            nudges.iter().map(|nudge| nudge.code).collect::<Vec<_>>(),
+            vec![
+                "N012_EXCERPTS_PER_RESULT_EXCEEDS_STORED",
+                "N013_DISPLAYED_RESULTS_COUNT_EXCEEDS_DOCUMENTS"
+            ]
+        );
+        assert!(nudges[0]
+            .message
+            .contains(" is 8, but no document has more than 3 excerpts "));
+        assert!(nudges[1]
+            .message
+            .contains(" is 3, but the index has 2 documents"));
+
+        assert!(display_count_nudges(&config(3, 2)).is_empty());
+
+        // Defaults aren't tuned for the documents, so they're left alone.
+        let defaults = OutputConfig::default();
+        assert!(display_count_nudges(&config(
+            defaults.excerpts_per_result,
+            defaults.displayed_results_count
+        ))
+        .is_empty());
+    }
 }
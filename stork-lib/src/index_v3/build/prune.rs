@@ -0,0 +1,365 @@
+use std::{cmp::Reverse, collections::BTreeSet, fmt};
+
+use bytes::Bytes;
+use serde::Serialize;
+
+use super::{errors::pluralize_with_count, IndexGenerationError};
+use crate::index_v3::{Container, Index};
+
+/**
+ * Something removed from an index because of `input.min_document_frequency`,
+ * or so that it fits within `output.max_index_bytes`.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum PruningAction {
+    /// Search terms that appear in fewer documents than
+    /// `input.min_document_frequency` were removed, in alphabetical order.
+    RemovedRareTerms(Vec<String>),
+
+    /// The n-grams indexed for substring search were removed, saving this
+    /// many bytes.
+    RemovedNgrams(usize),
+
+    /// Search terms that appear in more than half of the documents were
+    /// removed, in the order listed.
+    RemovedCommonTerms(Vec<String>),
+
+    /// Every result was trimmed to its first excerpt, removing this many
+    /// excerpts.
+    TrimmedExcerpts(usize),
+
+    /// Further search terms were removed, in the order listed.
+    RemovedTerms(Vec<String>),
+}
+
+impl fmt::Display for PruningAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PruningAction::RemovedRareTerms(terms) => write!(
+                f,
+                "Removed {} that appear in fewer documents than `min_document_frequency`",
+                pluralize_with_count(terms.len(), "search term", "search terms")
+            ),
+            PruningAction::RemovedNgrams(bytes) => write!(
+                f,
+                "Removed the n-grams indexed for substring search, saving {}",
+                pluralize_with_count(*bytes, "byte", "bytes")
+            ),
+            PruningAction::RemovedCommonTerms(terms) => write!(
+                f,
+                "Removed {} that appear in more than half of the documents",
+                pluralize_with_count(terms.len(), "search term", "search terms")
+            ),
+            PruningAction::TrimmedExcerpts(count) => write!(
+                f,
+                "Removed {}, keeping one per result",
+                pluralize_with_count(*count, "excerpt", "excerpts")
+            ),
+            PruningAction::RemovedTerms(terms) => write!(
+                f,
+                "Removed {}, starting with those that appear in the most documents",
+                pluralize_with_count(terms.len(), "search term", "search terms")
+            ),
+        }
+    }
+}
+
+/**
+ * Removes content from the index, least valuable first, until its
+ * serialized size is at most `max_bytes`.
+ *
+ * The n-grams indexed for substring search go first, since searches still
+ * work without them. Common search terms are next, since they do little to
+ * narrow down results. If that isn't enough, results are trimmed to a single excerpt,
+ * and finally the remaining search terms are removed. Ties are broken by
+ * size and then alphabetically, so the same index is always pruned the
+ * same way.
+ */
+pub(super) fn prune_to_size(
+    index: &mut Index,
+    max_bytes: usize,
+) -> Result<Vec<PruningAction>, IndexGenerationError> {
+    let mut actions = vec![];
+    let mut size = serialized_size(index);
+
+    if size > max_bytes && !index.ngrams.is_empty() {
+        let ngram_bytes = index.ngram_size_bytes();
+        index.ngrams.clear();
+        index.config.index_ngrams = None;
+        size = serialized_size(index);
+        actions.push(PruningAction::RemovedNgrams(ngram_bytes));
+    }
+
+    if size > max_bytes {
+        let entry_count = index.entries.len();
+        let common_terms = remove_terms(index, &mut size, max_bytes, |container| {
+            container.results.len() * 2 > entry_count
+        });
+        if !common_terms.is_empty() {
+            actions.push(PruningAction::RemovedCommonTerms(common_terms));
+        }
+    }
+
+    if size > max_bytes {
+        let trimmed_count = trim_excerpts(index);
+        size = serialized_size(index);
+        if trimmed_count > 0 {
+            actions.push(PruningAction::TrimmedExcerpts(trimmed_count));
+        }
+    }
+
+    if size > max_bytes {
+        let terms = remove_terms(index, &mut size, max_bytes, |_| true);
+        if !terms.is_empty() {
+            actions.push(PruningAction::RemovedTerms(terms));
+        }
+    }
+
+    if size > max_bytes {
+        return Err(IndexGenerationError::IndexTooLarge {
+            max_bytes,
+            pruned_bytes: size,
+        });
+    }
+
+    for action in &actions {
+        tracing::info!("{}", action);
+    }
+
+    Ok(actions)
+}
+
+/**
+ * Removes the search terms whose own results are in fewer than
+ * `min_document_frequency` documents. A removed term that other terms
+ * resolve through, like a prefix, keeps its container for them, but nothing
+ * resolves to it any more.
+ */
+pub(super) fn prune_rare_terms(
+    index: &mut Index,
+    min_document_frequency: usize,
+) -> Option<PruningAction> {
+    let mut removed = vec![];
+    for (term, container) in index.containers.iter_mut() {
+        if !container.results.is_empty() && container.results.len() < min_document_frequency {
+            container.results.clear();
+            removed.push(term.clone());
+        }
+    }
+
+    if removed.is_empty() {
+        return None;
+    }
+
+    remove_dangling_aliases(index, &removed);
+    let action = PruningAction::RemovedRareTerms(removed);
+    tracing::info!("{}", action);
+    Some(action)
+}
+
+fn serialized_size(index: &Index) -> usize {
+    Bytes::from(index).len()
+}
+
+/// The number of bytes a term and its container add to the serialized index.
+fn container_size(term: &str, container: &Container) -> usize {
+    rmp_serde::to_vec(term).unwrap().len() + rmp_serde::to_vec(container).unwrap().len()
+}
+
+/**
+ * Removes the candidate terms that appear in the most documents until the
+ * index fits, and returns the removed terms. Removing a term never shrinks
+ * the index by less than its container's size, so subtracting that size
+ * tells us when to stop without reserializing the index after every term.
+ */
+fn remove_terms<F: Fn(&Container) -> bool>(
+    index: &mut Index,
+    size: &mut usize,
+    max_bytes: usize,
+    is_candidate: F,
+) -> Vec<String> {
+    let mut candidates: Vec<(usize, usize, &String)> = index
+        .containers
+        .iter()
+        .filter(|(_, container)| is_candidate(container))
+        .map(|(term, container)| {
+            (
+                container.results.len(),
+                container_size(term, container),
+                term,
+            )
+        })
+        .collect();
+    candidates
+        .sort_by_key(|&(entry_count, bytes, term)| (Reverse(entry_count), Reverse(bytes), term));
+
+    let mut estimated_size = *size;
+    let mut removed = vec![];
+    for (_, bytes, term) in candidates {
+        if estimated_size <= max_bytes {
+            break;
+        }
+        estimated_size = estimated_size.saturating_sub(bytes);
+        removed.push(term.clone());
+    }
+
+    for term in &removed {
+        index.containers.remove(term);
+    }
+    remove_dangling_aliases(index, &removed);
+
+    *size = serialized_size(index);
+    removed
+}
+
+/// Drops aliases that point at removed terms, then any containers left with
+/// nothing in them.
+fn remove_dangling_aliases(index: &mut Index, removed: &[String]) {
+    let removed: BTreeSet<&String> = removed.iter().collect();
+
+    for container in index.containers.values_mut() {
+        container
+            .aliases
+            .retain(|target, _| !removed.contains(target));
+    }
+
+    index.containers.retain(|_, container| {
+        !(container.results.is_empty()
+            && container.aliases.is_empty()
+            && container.surface_forms.is_empty())
+    });
+    index.index_terms();
+}
+
+/// Trims every result to its first excerpt, returning how many excerpts
+/// were removed.
+fn trim_excerpts(index: &mut Index) -> usize {
+    let mut removed_count = 0;
+
+    for container in index.containers.values_mut() {
+        for result in container.results.values_mut() {
+            if result.excerpts.len() > 1 {
+                removed_count += result.excerpts.len() - 1;
+                result.excerpts.truncate(1);
+            }
+        }
+    }
+
+    removed_count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, DataSource, File, InputConfig, NgramConfig, OutputConfig};
+    use crate::index_v3::build::build;
+
+    fn config(max_index_bytes: Option<usize>) -> Config {
+        let contents = [
+            "The quick brown fox jumps over the lazy dog, and the dog sleeps on.",
+            "The lazy cat watches the quick brown fox leap over the sleeping dog.",
+            "A small bird sings while the fox and the dog chase each other around.",
+        ];
+
+        Config {
+            input: InputConfig {
+                files: contents
+                    .iter()
+                    .enumerate()
+                    .map(|(index, contents)| File {
+                        title: format!("Document {}", index),
+                        explicit_source: Some(DataSource::Contents(contents.to_string())),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+            output: OutputConfig {
+                max_index_bytes,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn unpruned_size() -> usize {
+        serialized_size(&build(&config(None)).unwrap().index)
+    }
+
+    #[test]
+    fn index_within_budget_is_untouched() {
+        let result = build(&config(Some(unpruned_size()))).unwrap();
+        assert!(result.pruning.is_empty());
+        assert_eq!(serialized_size(&result.index), unpruned_size());
+    }
+
+    #[test]
+    fn tight_budget_prunes_index_under_cap() {
+        let max_bytes = unpruned_size() / 2;
+        let result = build(&config(Some(max_bytes))).unwrap();
+
+        assert!(serialized_size(&result.index) <= max_bytes);
+        assert!(matches!(
+            result.pruning.first(),
+            Some(PruningAction::RemovedCommonTerms(terms)) if terms.contains(&"the".to_string())
+        ));
+    }
+
+    #[test]
+    fn ngrams_are_pruned_first() {
+        let mut config = config(Some(unpruned_size()));
+        config.input.index_ngrams = Some(NgramConfig::default());
+
+        let result = build(&config).unwrap();
+        assert!(matches!(
+            result.pruning.as_slice(),
+            [PruningAction::RemovedNgrams(bytes)] if *bytes > 0
+        ));
+        assert!(result.index.ngrams.is_empty());
+        assert!(result.index.config.index_ngrams.is_none());
+    }
+
+    #[test]
+    fn pruning_is_deterministic() {
+        let max_bytes = unpruned_size() / 3;
+        let first = build(&config(Some(max_bytes))).unwrap();
+        let second = build(&config(Some(max_bytes))).unwrap();
+
+        assert_eq!(first.pruning, second.pruning);
+        assert_eq!(Bytes::from(&first.index), Bytes::from(&second.index));
+    }
+
+    #[test]
+    fn terms_in_too_few_documents_are_pruned() {
+        let mut rare_config = config(None);
+        rare_config.input.min_document_frequency = 2;
+
+        let result = build(&rare_config).unwrap();
+        assert!(matches!(
+            result.pruning.as_slice(),
+            [PruningAction::RemovedRareTerms(terms)] if terms.contains(&"sings".to_string())
+        ));
+        assert!(!result.index.containers.contains_key("sings"));
+        assert!(result.index.containers.contains_key("lazy"));
+        assert!(crate::index_v3::search(&result.index, "sings")
+            .unwrap()
+            .results
+            .is_empty());
+        assert_eq!(
+            crate::index_v3::search(&result.index, "lazy")
+                .unwrap()
+                .results
+                .len(),
+            2
+        );
+
+        assert!(build(&config(None)).unwrap().pruning.is_empty());
+    }
+
+    #[test]
+    fn impossible_budget_is_an_error() {
+        assert!(matches!(
+            build(&config(Some(10))),
+            Err(IndexGenerationError::IndexTooLarge { max_bytes: 10, .. })
+        ));
+    }
+}
@@ -0,0 +1,402 @@
+use crate::{
+    config::WordSegmentation,
+    index_v3::{emoji::split_emoji, AnnotatedWord},
+    InternalWordAnnotation,
+};
+
+#[cfg(feature = "build-v3-thai-lao-segmentation")]
+use {lazy_static::lazy_static, std::collections::HashSet};
+
+#[cfg(feature = "build-v3-thai-lao-segmentation")]
+lazy_static! {
+    static ref DICTIONARY: Dictionary = Dictionary::new(include_str!("thai_lao_words.txt"));
+}
+
+/**
+ * The words that runs of Thai and Lao text are split into, read from a list
+ * with one word per line, where blank lines and lines starting with `#` are
+ * skipped.
+ */
+#[cfg(feature = "build-v3-thai-lao-segmentation")]
+struct Dictionary {
+    words: HashSet<&'static str>,
+    longest_word_length: usize,
+}
+
+#[cfg(feature = "build-v3-thai-lao-segmentation")]
+impl Dictionary {
+    fn new(list: &'static str) -> Self {
+        let words: HashSet<&str> = list
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        let longest_word_length = words.iter().map(|word| word.chars().count()).max();
+
+        Dictionary {
+            words,
+            longest_word_length: longest_word_length.unwrap_or_default(),
+        }
+    }
+
+    /// The length, in bytes, of the longest word in the dictionary that
+    /// `text` starts with, if it doesn't end in the middle of a character
+    /// cluster.
+    fn longest_prefix(&self, text: &str) -> Option<usize> {
+        char_ends(text)
+            .take(self.longest_word_length)
+            .filter(|&end| is_cluster_boundary(text, end))
+            .filter(|&end| self.words.contains(&text[..end]))
+            .last()
+    }
+}
+
+/**
+ * Splits each word with Thai or Lao text in it into the dictionary's words,
+ * when `segmentation` says to. Text in other scripts stays in one piece, and
+ * punctuation stays with the text it's next to. Each piece keeps the
+ * original word's annotations and fields, except that only the first starts
+ * an item, and becomes a word of its own in the document's word list, so
+ * excerpts and highlights point at it alone.
+ */
+#[cfg(feature = "build-v3-thai-lao-segmentation")]
+pub(super) fn segment_words(
+    words: Vec<AnnotatedWord>,
+    segmentation: WordSegmentation,
+) -> Vec<AnnotatedWord> {
+    if segmentation == WordSegmentation::Whitespace {
+        return words;
+    }
+
+    words
+        .into_iter()
+        .flat_map(|annotated_word| {
+            if !annotated_word.word.chars().any(is_thai_or_lao) {
+                return vec![annotated_word];
+            }
+
+            into_pieces(&annotated_word, segment_word(&annotated_word.word))
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "build-v3-thai-lao-segmentation"))]
+pub(super) fn segment_words(
+    words: Vec<AnnotatedWord>,
+    _segmentation: WordSegmentation,
+) -> Vec<AnnotatedWord> {
+    words
+}
+
+/**
+ * Splits each emoji out of the words it's written in, when `index_emoji` says
+ * to, so it can be searched for on its own. The pieces are annotated like the
+ * pieces of segmented words.
+ */
+pub(super) fn split_emoji_words(
+    words: Vec<AnnotatedWord>,
+    index_emoji: bool,
+) -> Vec<AnnotatedWord> {
+    if !index_emoji {
+        return words;
+    }
+
+    words
+        .into_iter()
+        .flat_map(|annotated_word| {
+            let pieces = split_emoji(&annotated_word.word);
+            if pieces.len() < 2 {
+                return vec![annotated_word];
+            }
+
+            let pieces = pieces.into_iter().map(ToString::to_string).collect();
+            into_pieces(&annotated_word, pieces)
+        })
+        .collect()
+}
+
+/// The pieces of a word, each with the word's annotations and fields, except
+/// that only the first starts an item.
+fn into_pieces(annotated_word: &AnnotatedWord, pieces: Vec<String>) -> Vec<AnnotatedWord> {
+    pieces
+        .into_iter()
+        .enumerate()
+        .map(|(index, word)| {
+            let mut piece = AnnotatedWord {
+                word: word.into(),
+                ..annotated_word.clone()
+            };
+            if index > 0 {
+                piece
+                    .internal_annotations
+                    .retain(|annotation| *annotation != InternalWordAnnotation::ItemStart);
+            }
+            piece
+        })
+        .collect()
+}
+
+#[cfg(feature = "build-v3-thai-lao-segmentation")]
+fn segment_word(word: &str) -> Vec<String> {
+    let mut pieces: Vec<String> = vec![];
+
+    // Punctuation that starts the word, which is joined to its first piece
+    let mut leading_punctuation = String::new();
+
+    let mut rest = word;
+    while let Some(first) = rest.chars().next() {
+        let thai_or_lao = is_thai_or_lao(first);
+        let run_end = rest
+            .char_indices()
+            .find(|(_, c)| is_thai_or_lao(*c) != thai_or_lao)
+            .map_or(rest.len(), |(index, _)| index);
+        let (run, after) = rest.split_at(run_end);
+
+        let run_pieces = if thai_or_lao {
+            segment_run(run)
+        } else if run.chars().any(char::is_alphanumeric) {
+            vec![run]
+        } else {
+            vec![]
+        };
+
+        if run_pieces.is_empty() {
+            match pieces.last_mut() {
+                Some(last) => last.push_str(run),
+                None => leading_punctuation.push_str(run),
+            }
+        }
+
+        for piece in run_pieces {
+            pieces.push(std::mem::take(&mut leading_punctuation) + piece);
+        }
+
+        rest = after;
+    }
+
+    pieces
+}
+
+/**
+ * Splits a run of Thai or Lao characters at the longest dictionary word that
+ * starts at each point. Text that no word starts in is kept together, up to
+ * where the next word starts.
+ */
+#[cfg(feature = "build-v3-thai-lao-segmentation")]
+fn segment_run(run: &str) -> Vec<&str> {
+    let mut pieces = vec![];
+    let mut unknown_start = None;
+    let mut position = 0;
+
+    while position < run.len() {
+        let rest = &run[position..];
+        match DICTIONARY.longest_prefix(rest) {
+            Some(length) => {
+                if let Some(start) = unknown_start.take() {
+                    pieces.push(&run[start..position]);
+                }
+                pieces.push(&rest[..length]);
+                position += length;
+            }
+            None => {
+                unknown_start.get_or_insert(position);
+                position += char_ends(rest)
+                    .find(|&end| is_cluster_boundary(rest, end))
+                    .unwrap_or(rest.len());
+            }
+        }
+    }
+
+    if let Some(start) = unknown_start {
+        pieces.push(&run[start..]);
+    }
+    pieces
+}
+
+/// The byte offset after each character of `text`.
+#[cfg(feature = "build-v3-thai-lao-segmentation")]
+fn char_ends(text: &str) -> impl Iterator<Item = usize> + '_ {
+    text.char_indices().map(|(index, c)| index + c.len_utf8())
+}
+
+/// Whether `text` can be split at byte offset `end` without separating a
+/// character from the vowels and tone marks written with it.
+#[cfg(feature = "build-v3-thai-lao-segmentation")]
+fn is_cluster_boundary(text: &str, end: usize) -> bool {
+    let before = text[..end].chars().next_back();
+    let after = text[end..].chars().next();
+    !before.map_or(false, is_leading_vowel) && !after.map_or(false, attaches_to_previous)
+}
+
+#[cfg(feature = "build-v3-thai-lao-segmentation")]
+fn is_thai_or_lao(c: char) -> bool {
+    ('\u{0E00}'..='\u{0EFF}').contains(&c)
+}
+
+/// Vowels written before the consonant they're pronounced after.
+#[cfg(feature = "build-v3-thai-lao-segmentation")]
+fn is_leading_vowel(c: char) -> bool {
+    matches!(c, '\u{0E40}'..='\u{0E44}' | '\u{0EC0}'..='\u{0EC4}')
+}
+
+/// Combining vowels and tone marks, and the marks that repeat or abbreviate
+/// the word before them.
+#[cfg(feature = "build-v3-thai-lao-segmentation")]
+fn attaches_to_previous(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0E2F}'
+            | '\u{0E31}'
+            | '\u{0E33}'..='\u{0E3A}'
+            | '\u{0E46}'..='\u{0E4E}'
+            | '\u{0EAF}'
+            | '\u{0EB1}'
+            | '\u{0EB3}'..='\u{0EBC}'
+            | '\u{0EC6}'
+            | '\u{0EC8}'..='\u{0ECD}'
+    )
+}
+
+#[cfg(all(test, feature = "build-v3-thai-lao-segmentation"))]
+mod tests {
+    use super::*;
+    use crate::config::*;
+    use crate::index_v3::{build::build, WordListSource};
+
+    fn segmented(text: &str) -> Vec<String> {
+        let words = text.split_whitespace().map(AnnotatedWord::new).collect();
+        segment_words(words, WordSegmentation::Dictionary)
+            .into_iter()
+            .map(|annotated_word| annotated_word.word.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn thai_sentences_are_split_into_words() {
+        assert_eq!(
+            segmented("ฉันชอบกินอาหารไทย"),
+            vec!["ฉัน", "ชอบ", "กิน", "อาหาร", "ไทย"]
+        );
+    }
+
+    #[test]
+    fn lao_sentences_are_split_into_words() {
+        assert_eq!(
+            segmented("ຂ້ອຍມັກອາຫານລາວ"),
+            vec!["ຂ້ອຍ", "ມັກ", "ອາຫານ", "ລາວ"]
+        );
+    }
+
+    #[test]
+    fn the_longest_word_is_preferred() {
+        assert_eq!(segmented("ค้นหาเอกสาร"), vec!["ค้นหา", "เอกสาร"]);
+    }
+
+    #[test]
+    fn unknown_text_is_kept_together_until_the_next_word() {
+        assert_eq!(segmented("สวัสดีชาวโลก"), vec!["สวัสดี", "ชาว", "โลก"]);
+    }
+
+    #[test]
+    fn other_scripts_and_punctuation_are_left_alone() {
+        assert_eq!(
+            segmented("Stork (ภาษาไทย), search-as-you-type"),
+            vec!["Stork", "(ภาษา", "ไทย),", "search-as-you-type"]
+        );
+        assert_eq!(segmented("iPhoneรุ่นใหม่"), vec!["iPhone", "รุ่น", "ใหม่"]);
+    }
+
+    #[test]
+    fn words_are_not_split_inside_a_character_cluster() {
+        // `ได้` is a word, but `ไ` can't be split from the `ด` it's written
+        // before, and the tone mark on `ด` can't be split from it either.
+        for piece in segmented("ได้ไม่ได้") {
+            let first = piece.chars().next().unwrap();
+            let last = piece.chars().next_back().unwrap();
+            assert!(!attaches_to_previous(first), "{}", piece);
+            assert!(!is_leading_vowel(last), "{}", piece);
+        }
+    }
+
+    #[test]
+    fn whitespace_segmentation_changes_nothing() {
+        let words = vec![AnnotatedWord::new("ฉันชอบกินอาหารไทย")];
+        assert_eq!(
+            segment_words(words.clone(), WordSegmentation::Whitespace),
+            words
+        );
+    }
+
+    #[test]
+    fn thai_text_can_be_segmented_into_words() {
+        let config = |word_segmentation| Config {
+            input: InputConfig {
+                files: vec![
+                    File {
+                        explicit_source: Some(DataSource::Contents(
+                            "Stork ฉันชอบกินอาหารไทย every day".to_string(),
+                        )),
+                        title: "อาหาร".to_string(),
+                        filetype: Some(Filetype::PlainText),
+                        ..Default::default()
+                    },
+                    File {
+                        explicit_source: Some(DataSource::Contents(
+                            "<html><body><main>ภาษาไทย and English</main></body></html>"
+                                .to_string(),
+                        )),
+                        title: "HTML".to_string(),
+                        filetype: Some(Filetype::HTML),
+                        ..Default::default()
+                    },
+                ],
+                word_segmentation,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let whitespace = build(&config(WordSegmentation::Whitespace)).unwrap().index;
+        let contents_excerpts = whitespace.containers["อาหาร"]
+            .results
+            .values()
+            .flat_map(|result| &result.excerpts)
+            .filter(|excerpt| excerpt.source == WordListSource::Contents)
+            .count();
+        assert_eq!(contents_excerpts, 0);
+
+        let index = build(&config(WordSegmentation::Dictionary)).unwrap().index;
+        assert_eq!(
+            index.entries[0].contents,
+            "Stork ฉัน ชอบ กิน อาหาร ไทย every day"
+        );
+        assert_eq!(index.entries[1].contents, "ภาษา ไทย and English");
+
+        let output = crate::index_v3::search(&index, "อาหาร").unwrap();
+        let result = &output.results[0];
+        assert_eq!(result.title_highlight_ranges.len(), 1);
+        let excerpt = &result.excerpts[0];
+        let range = &excerpt.highlight_ranges[0];
+        let highlighted: String = excerpt
+            .text
+            .chars()
+            .skip(range.beginning)
+            .take(range.end - range.beginning)
+            .collect();
+        assert_eq!(highlighted, "อาหาร");
+
+        let titles = |query: &str| -> Vec<String> {
+            let mut titles: Vec<String> = crate::index_v3::search(&index, query)
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| result.entry.title)
+                .collect();
+            titles.sort();
+            titles
+        };
+        assert_eq!(titles("ไทย"), vec!["HTML", "อาหาร"]);
+        assert_eq!(titles("stork"), vec!["อาหาร"]);
+        assert_eq!(titles("english"), vec!["HTML"]);
+    }
+}
@@ -0,0 +1,153 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{
+    config::Config,
+    index_v3::{Container, Entry},
+};
+
+use super::{
+    fill_containers::InternedContainers,
+    fill_stems::{fill_stems, fill_surface_forms},
+    intermediate_entry::{EntrySink, NormalizedEntry},
+    interner::{Interner, Stems},
+};
+
+/**
+ * Builds an index's containers as documents are read. Each batch of
+ * documents is merged into the containers when it's flushed, after which
+ * only the text stored in each document's `Entry` is kept.
+ *
+ * Batches are merged in document order, so the finished index is the same
+ * as one built from every document at once.
+ */
+pub(super) struct StreamingIndex {
+    config: Arc<Config>,
+    words: Interner,
+    stems: Stems,
+    containers: InternedContainers,
+    entries: Vec<Entry>,
+    pending: Vec<NormalizedEntry>,
+}
+
+impl StreamingIndex {
+    pub(super) fn new(config: Arc<Config>) -> Self {
+        StreamingIndex {
+            config,
+            words: Interner::default(),
+            stems: Stems::new(),
+            containers: InternedContainers::default(),
+            entries: vec![],
+            pending: vec![],
+        }
+    }
+
+    /**
+     * Merges any documents that haven't been flushed, then resolves the
+     * containers. Should be called on the build's pool.
+     */
+    pub(super) fn finish(mut self) -> (Vec<Entry>, BTreeMap<String, Container>) {
+        self.flush();
+
+        let mut containers = BTreeMap::new();
+        self.containers
+            .finish(&self.config, &self.words, &self.stems, &mut containers);
+
+        if self.config.output.save_stem_surface_forms {
+            fill_surface_forms(&self.stems, &self.words, &mut containers);
+        }
+
+        (self.entries, containers)
+    }
+}
+
+impl EntrySink for StreamingIndex {
+    fn push(&mut self, entry: NormalizedEntry) {
+        self.pending.push(entry);
+    }
+
+    fn flush(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+
+        fill_stems(
+            &pending,
+            &self.words,
+            &mut self.stems,
+            self.config.input.shared_stem_cache,
+        );
+        self.containers
+            .add_entries(&self.config, &pending, self.entries.len(), &self.words);
+
+        self.entries.extend(pending.into_iter().map(Entry::from));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{Config, DataSource, File, InputConfig, OutputConfig, StemmingConfig};
+    use crate::index_v3::build::build;
+    use bytes::Bytes;
+    use rust_stemmers::Algorithm;
+
+    fn config(stream_documents: bool, threads: usize) -> Config {
+        let contents = [
+            "Running runners run quickly through the running water.",
+            "The runner ran; runs were counted by the quick counters.",
+            "Counting counts: the counter counted every quickly-run lap.",
+            "Water runs downhill, and the quickest runner wins the race.",
+            "Races are won by runners who keep running when others stop.",
+        ];
+
+        Config {
+            input: InputConfig {
+                files: contents
+                    .iter()
+                    .enumerate()
+                    .map(|(index, contents)| File {
+                        title: format!("Document {}", index % 3),
+                        url: format!("/docs/running-{}", index),
+                        explicit_source: Some(DataSource::Contents(contents.to_string())),
+                        stemming_override: if index == 2 {
+                            Some(StemmingConfig::None)
+                        } else {
+                            None
+                        },
+                        ..Default::default()
+                    })
+                    .chain(std::iter::once(File {
+                        title: "Coureurs".to_string(),
+                        explicit_source: Some(DataSource::Contents(
+                            "Les coureurs courent et le coureur court".to_string(),
+                        )),
+                        stemming_override: Some(StemmingConfig::Language(Algorithm::French)),
+                        ..Default::default()
+                    }))
+                    .collect(),
+                index_url_segments: true,
+                stream_documents,
+                threads: Some(threads),
+                ..Default::default()
+            },
+            output: OutputConfig {
+                save_stem_surface_forms: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn streaming_build_matches_in_memory_build() {
+        let in_memory = build(&config(false, 1)).unwrap();
+
+        for threads in [1, 2, 4] {
+            let streamed = build(&config(true, threads)).unwrap();
+
+            assert_eq!(
+                Bytes::from(&streamed.index),
+                Bytes::from(&in_memory.index),
+                "{} threads",
+                threads
+            );
+            assert_eq!(streamed.warnings, in_memory.warnings);
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use rust_stemmers::Stemmer;
+use thiserror::Error;
+
+use crate::{
+    config::{Config, StemmingConfig},
+    index_v3::{scores::weighted_score, AnnotatedWordList, WordListSource},
+    V3Index as Index,
+};
+
+use super::{
+    annotated_words_from_string::configured_tokenizer,
+    average_word_count,
+    fill_containers::{containers_for_entry, max_stored_excerpts},
+    fill_ngrams::entry_ngrams,
+    intermediate_entry::{stored_text, NormalizedEntry},
+    normalize_word,
+};
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum EntryUpdateError {
+    #[error("The index has {entry_count} entries, so there's no entry {entry_index} to update.")]
+    EntryOutOfRange {
+        entry_index: usize,
+        entry_count: usize,
+    },
+
+    #[error("The entry's new text has no words to index.")]
+    EmptyText,
+}
+
+/**
+ * Replaces the text of the entry at `entry_index` in a built index, updating
+ * only the containers its old and new words are in, without reading any
+ * documents again. `config` should be the config the index was built with.
+ *
+ * The new text is indexed as plain text and stemmed according to
+ * `input.stemming`. The entry's title, URL, fields, and keyword matches stay
+ * as they are, and every other entry keeps its index and its results. A word
+ * that's no longer in any entry keeps an empty container, since other
+ * containers may still alias it, until the index is rebuilt.
+ */
+pub fn update_entry_text(
+    index: &mut Index,
+    config: &Config,
+    entry_index: usize,
+    text: &str,
+) -> Result<(), EntryUpdateError> {
+    let entry = index
+        .entries
+        .get(entry_index)
+        .ok_or(EntryUpdateError::EntryOutOfRange {
+            entry_index,
+            entry_count: index.entries.len(),
+        })?;
+
+    let word_list = configured_tokenizer(&config.input).tokenize(text);
+    if word_list.is_empty() {
+        return Err(EntryUpdateError::EmptyText);
+    }
+
+    let stem_algorithm = match &config.input.stemming {
+        StemmingConfig::Language(algorithm) => Some(*algorithm),
+        StemmingConfig::None | StemmingConfig::Unsupported(_) => None,
+    };
+
+    let normalized_entry = NormalizedEntry {
+        annotated_word_list: AnnotatedWordList { word_list },
+        stem_algorithm,
+        title: entry.title.clone(),
+        url: entry.url.clone(),
+        fields: HashMap::new(),
+        keywords: vec![],
+        summary: None,
+        language_undetected: false,
+        malformed_json_ld_blocks: 0,
+        invalid_thumbnail: None,
+        invalid_date: None,
+        truncated_word_count: 0,
+        unlimited_word_count: None,
+        stored_text: None,
+        generate_excerpts: true,
+    };
+
+    let new_containers = containers_for_entry(config, entry_index, &normalized_entry);
+
+    // Keywords and summaries aren't stored with the entry, so their matches
+    // can't be rebuilt and are kept. Every other match is rebuilt below.
+    let mut old_words = vec![];
+    for (word, container) in index.containers.iter_mut() {
+        if let Some(result) = container.results.get_mut(&entry_index) {
+            result.excerpts.retain(|excerpt| {
+                matches!(
+                    excerpt.source,
+                    WordListSource::Keywords | WordListSource::Summary
+                )
+            });
+            if result.excerpts.is_empty() {
+                container.results.remove(&entry_index);
+            }
+            old_words.push(word.clone());
+        }
+    }
+
+    let max_stored_excerpts = max_stored_excerpts(config);
+    let mut new_words = vec![];
+    for (word, new_container) in new_containers {
+        let container = index.containers.entry(word.clone()).or_default();
+        for (entry_index, mut result) in new_container.results {
+            if let Some(keyword_result) = container.results.remove(&entry_index) {
+                result.excerpts.extend(keyword_result.excerpts);
+                result.excerpts.truncate(max_stored_excerpts);
+            }
+            container.results.insert(entry_index, result);
+            new_words.push(word.clone());
+        }
+
+        for (alias, score) in new_container.aliases {
+            container.aliases.entry(alias).or_insert(score);
+        }
+    }
+
+    if let Some(stem_algorithm) = stem_algorithm {
+        let stemmer = Stemmer::create(stem_algorithm);
+        fill_stem_aliases(index, config, &stemmer, &new_words);
+
+        if config.output.save_stem_surface_forms {
+            fill_surface_forms(index, &stemmer, &old_words, &normalized_entry);
+        }
+    }
+    index.index_terms();
+
+    index.entries[entry_index].contents = normalized_entry.annotated_word_list.get_full_text();
+    index.entries[entry_index].word_count =
+        Some(normalized_entry.annotated_word_list.word_list.len());
+    index.entries[entry_index].text =
+        stored_text(&config.output, &normalized_entry.annotated_word_list);
+    index.entries[entry_index].item_starts = normalized_entry.annotated_word_list.item_starts();
+    index.config.average_word_count = average_word_count(&index.entries);
+
+    if let Some(ngram_config) = &index.config.index_ngrams {
+        let entry_ngrams = entry_ngrams(&index.entries[entry_index], ngram_config);
+
+        for entries in index.ngrams.values_mut() {
+            entries.retain(|&ngram_entry| ngram_entry != entry_index);
+        }
+        index.ngrams.retain(|_, entries| !entries.is_empty());
+
+        for ngram in entry_ngrams {
+            let entries = index.ngrams.entry(ngram).or_default();
+            if let Err(position) = entries.binary_search(&entry_index) {
+                entries.insert(position, entry_index);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * Aliases each of the entry's words to every other indexed word with the same
+ * stem, and those words to it.
+ */
+fn fill_stem_aliases(index: &mut Index, config: &Config, stemmer: &Stemmer, new_words: &[String]) {
+    let stem_score = weighted_score(config.output.match_weights.stem);
+
+    let new_stems: BTreeSet<String> = new_words
+        .iter()
+        .map(|word| stemmer.stem(word).to_string())
+        .collect();
+
+    let mut words_by_stem: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (word, container) in &index.containers {
+        if container.results.is_empty() {
+            continue;
+        }
+
+        let stem = stemmer.stem(word);
+        if new_stems.contains(stem.as_ref()) {
+            words_by_stem
+                .entry(stem.to_string())
+                .or_default()
+                .push(word.clone());
+        }
+    }
+
+    for word in new_words {
+        let stem = stemmer.stem(word);
+        for other_word in words_by_stem.get(stem.as_ref()).into_iter().flatten() {
+            if other_word == word {
+                continue;
+            }
+
+            for (container, alias) in [(other_word, word), (word, other_word)] {
+                index
+                    .containers
+                    .entry(container.clone())
+                    .or_default()
+                    .aliases
+                    .entry(alias.clone())
+                    .and_modify(|score| *score = (*score).max(stem_score))
+                    .or_insert(stem_score);
+            }
+        }
+    }
+}
+
+/**
+ * Adds the entry's new words to their stems' surface forms, and removes the
+ * words it no longer has that no other entry has either. Each changed stem's
+ * surface forms are then copied to the containers of its words, as a full
+ * build does.
+ */
+fn fill_surface_forms(
+    index: &mut Index,
+    stemmer: &Stemmer,
+    old_words: &[String],
+    entry: &NormalizedEntry,
+) {
+    let mut changed_stems = BTreeSet::new();
+
+    for word in old_words {
+        let is_indexed = index
+            .containers
+            .get(word)
+            .map_or(false, |container| !container.results.is_empty());
+        if is_indexed {
+            continue;
+        }
+
+        let stem = stemmer.stem(word).to_string();
+        if let Some(container) = index.containers.get_mut(&stem) {
+            container
+                .surface_forms
+                .retain(|surface_form| surface_form != word);
+        }
+        if let Some(container) = index.containers.get_mut(word) {
+            container.surface_forms.clear();
+        }
+        changed_stems.insert(stem);
+    }
+
+    for annotated_word in &entry.annotated_word_list.word_list {
+        let word = normalize_word(&annotated_word.word);
+        let stem = stemmer.stem(&word).to_string();
+        let surface_forms = &mut index
+            .containers
+            .entry(stem.clone())
+            .or_default()
+            .surface_forms;
+
+        if let Err(position) = surface_forms.binary_search(&word) {
+            surface_forms.insert(position, word);
+        }
+        changed_stems.insert(stem);
+    }
+
+    for stem in changed_stems {
+        let surface_forms = match index.containers.get(&stem) {
+            Some(container) => container.surface_forms.clone(),
+            None => continue,
+        };
+        for word in &surface_forms {
+            index
+                .containers
+                .entry(word.clone())
+                .or_default()
+                .surface_forms = surface_forms.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{DataSource, File, InputConfig},
+        index_v3::{build::build, search, verify_index},
+    };
+    use pretty_assertions::assert_eq;
+
+    fn file(title: &str, contents: &str) -> File {
+        File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn results_by_container(index: &Index) -> BTreeMap<String, String> {
+        index
+            .containers
+            .iter()
+            .map(|(word, container)| (word.clone(), format!("{:?}", container)))
+            .collect()
+    }
+
+    #[test]
+    fn editing_an_entry_updates_only_the_containers_it_affects() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    file("Cats", "Cats sleep most of the day"),
+                    file("Pets", "Cats and dogs play together"),
+                    file("Dogs", "Dogs bark at the mail carrier"),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut index = build(&config).unwrap().index;
+        let before = results_by_container(&index);
+
+        update_entry_text(&mut index, &config, 1, "Cats and birds play together").unwrap();
+        let after = results_by_container(&index);
+
+        let changed: Vec<&String> = after
+            .keys()
+            .filter(|word| before.get(*word) != after.get(*word))
+            .collect();
+        assert!(!changed.is_empty());
+        for word in changed {
+            assert!(
+                word == "dogs" || "birds".starts_with(word.as_str()),
+                "{} changed",
+                word
+            );
+        }
+
+        assert!(!index.containers["dogs"].results.contains_key(&1));
+        assert!(index.containers["dogs"].results.contains_key(&2));
+        assert!(index.containers["birds"].results.contains_key(&1));
+        assert_eq!(index.entries[1].contents, "Cats and birds play together");
+
+        let output = search(&index, "birds").unwrap();
+        assert_eq!(
+            output
+                .results
+                .iter()
+                .map(|result| result.entry.title.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["Pets"]
+        );
+        assert_eq!(verify_index(&index), Ok(()));
+    }
+
+    #[test]
+    fn entries_out_of_range_are_not_updated() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![file("Cats", "Cats sleep most of the day")],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut index = build(&config).unwrap().index;
+
+        assert_eq!(
+            update_entry_text(&mut index, &config, 1, "Dogs"),
+            Err(EntryUpdateError::EntryOutOfRange {
+                entry_index: 1,
+                entry_count: 1
+            })
+        );
+        assert_eq!(
+            update_entry_text(&mut index, &config, 0, "  "),
+            Err(EntryUpdateError::EmptyText)
+        );
+    }
+}
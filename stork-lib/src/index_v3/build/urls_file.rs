@@ -0,0 +1,162 @@
+use std::borrow::Cow;
+
+use super::IndexGenerationError;
+use crate::config::{Config, File};
+
+/**
+ * The config with a `File` for each page listed in `input.urls_file` added
+ * after `input.files`, or the config as it is if it doesn't list a URLs
+ * file. The returned config doesn't list the URLs file, so it can be built
+ * without its pages being added again.
+ */
+pub(super) fn with_urls_file(config: &Config) -> Result<Cow<'_, Config>, IndexGenerationError> {
+    let path = match &config.input.urls_file {
+        Some(path) => path,
+        None => return Ok(Cow::Borrowed(config)),
+    };
+
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| IndexGenerationError::UnreadableUrlsFile {
+            path: path.clone(),
+            message: e.to_string(),
+        })?;
+
+    let mut config = config.clone();
+    config.input.urls_file = None;
+    config.input.files.extend(parse_urls_file(&contents));
+    Ok(Cow::Owned(config))
+}
+
+/// Each line is a URL, or a URL and a title separated by a tab.
+fn parse_urls_file(contents: &str) -> Vec<File> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (url, title) = match line.split_once('\t') {
+                Some((url, title)) if !title.trim().is_empty() => (url.trim(), title.trim()),
+                Some((url, _)) => (url.trim(), url.trim()),
+                None => (line, line),
+            };
+
+            File {
+                title: title.to_string(),
+                url: url.to_string(),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DataSource;
+    #[cfg(feature = "build-v3-web-scraping")]
+    use crate::{config::InputConfig, index_v3::build::build};
+
+    #[test]
+    fn urls_and_titles_are_read_from_each_line() {
+        let files = parse_urls_file(
+            "# Docs\nhttps://example.com/intro\tIntroduction\n\n  https://example.com/faq  \n#https://example.com/old\nhttps://example.com/blank-title\t\n",
+        );
+
+        let listed: Vec<(&str, &str)> = files
+            .iter()
+            .map(|file| (file.url.as_str(), file.title.as_str()))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("https://example.com/intro", "Introduction"),
+                ("https://example.com/faq", "https://example.com/faq"),
+                (
+                    "https://example.com/blank-title",
+                    "https://example.com/blank-title"
+                ),
+            ]
+        );
+        assert_eq!(
+            files[0].source(),
+            DataSource::URL("https://example.com/intro".to_string())
+        );
+    }
+
+    #[test]
+    fn configs_without_a_urls_file_are_left_alone() {
+        let config = Config::default();
+        assert!(matches!(with_urls_file(&config), Ok(Cow::Borrowed(_))));
+
+        let mut config = Config::default();
+        config.input.urls_file = Some("/nonexistent/stork-urls.txt".to_string());
+        assert!(matches!(
+            with_urls_file(&config),
+            Err(IndexGenerationError::UnreadableUrlsFile { .. })
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "build-v3-web-scraping")]
+    fn pages_listed_in_a_urls_file_are_indexed() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        use std::thread;
+
+        // Answers every request with a page that names the path it was for.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 4096];
+                let length = stream.read(&mut request).unwrap();
+                let request = String::from_utf8_lossy(&request[..length]).to_string();
+                let path = request.split(' ').nth(1).unwrap_or_default();
+
+                let body = format!("Otters live at {} on this server.", path);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let urls_file = std::env::temp_dir().join("stork-urls-file-test.txt");
+        std::fs::write(
+            &urls_file,
+            format!(
+                "# Pages to index\nhttp://{0}/rivers\tRivers\n#http://{0}/drafts\n\nhttp://{0}/lakes\n",
+                address
+            ),
+        )
+        .unwrap();
+
+        let config = Config {
+            input: InputConfig {
+                urls_file: Some(urls_file.to_string_lossy().to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let index = build(&config).unwrap().index;
+        std::fs::remove_file(&urls_file).unwrap();
+
+        let entries: Vec<(&str, &str)> = index
+            .entries
+            .iter()
+            .map(|entry| (entry.title.as_str(), entry.contents.as_str()))
+            .collect();
+        let lakes_url = format!("http://{}/lakes", address);
+        assert_eq!(
+            entries,
+            vec![
+                ("Rivers", "Otters live at /rivers on this server."),
+                (lakes_url.as_str(), "Otters live at /lakes on this server."),
+            ]
+        );
+    }
+}
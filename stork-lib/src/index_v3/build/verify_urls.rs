@@ -0,0 +1,306 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+use url::Url;
+
+use crate::config::InputConfig;
+use crate::index_v3::Entry;
+
+use super::errors::pluralize_with_count;
+use super::fill_intermediate_entries::WebClient;
+
+/// The most URLs that are requested at the same time.
+#[cfg(feature = "build-v3-web-scraping")]
+const CONCURRENT_URL_CHECKS: usize = 8;
+
+/**
+ * A document whose URL didn't answer with a successful status when
+ * `input.verify_urls` requested it.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BrokenUrl {
+    pub title: String,
+
+    /// The URL that was requested: the document's URL after `url_prefix`,
+    /// resolved against `input.verify_url_base` if it was relative.
+    pub url: String,
+
+    /// The status of the response, or `None` if there was no response.
+    pub status: Option<u16>,
+}
+
+/**
+ * What `input.verify_urls` found when it requested every document's URL.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UrlCheckReport {
+    /// How many distinct URLs were requested.
+    pub checked_count: usize,
+
+    /// The documents whose URLs are broken, in the order they were indexed.
+    pub broken: Vec<BrokenUrl>,
+
+    /// How many documents have relative URLs that weren't requested,
+    /// because `input.verify_url_base` isn't set.
+    pub skipped_relative_count: usize,
+}
+
+impl fmt::Display for BrokenUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            Some(status) => write!(f, "{} ({}): status {}", self.url, self.title, status),
+            None => write!(f, "{} ({}): no response", self.url, self.title),
+        }
+    }
+}
+
+impl fmt::Display for UrlCheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Checked {}; {} broken.",
+            pluralize_with_count(self.checked_count, "URL", "URLs"),
+            pluralize_with_count(
+                self.broken.len(),
+                "document's URL is",
+                "documents' URLs are"
+            )
+        )?;
+        for broken_url in &self.broken {
+            write!(f, "\n  - {}", broken_url)?;
+        }
+        if self.skipped_relative_count > 0 {
+            write!(
+                f,
+                "\nSkipped {} with relative URLs; set `input.verify_url_base` to check them.",
+                pluralize_with_count(self.skipped_relative_count, "document", "documents")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * The URL a document's results link to, made absolute so it can be
+ * requested. Relative URLs are resolved against `input.verify_url_base`, or
+ * are `None` without one.
+ */
+fn requestable_url(entry: &Entry, input: &InputConfig) -> Option<String> {
+    let url = format!("{}{}", input.url_prefix, entry.url);
+    match Url::parse(&url) {
+        Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => Some(url),
+        Ok(_) => None,
+        Err(_) => input
+            .verify_url_base
+            .as_ref()
+            .and_then(|base| Url::parse(base).ok())
+            .and_then(|base| base.join(&url).ok())
+            .map(String::from),
+    }
+}
+
+/**
+ * Requests each document's URL, a few at a time, and reports the ones that
+ * don't answer with a 2xx status once redirects are followed. Each URL is
+ * requested with `HEAD` first, and with `GET` if that fails, since some
+ * servers don't answer `HEAD` requests. Requests carry `input.cookies`, like
+ * the ones that fetch `src_url` documents.
+ */
+pub(super) fn verify_urls(
+    entries: &[Entry],
+    input: &InputConfig,
+    web_client: &WebClient,
+) -> UrlCheckReport {
+    let _span = tracing::info_span!("verify_urls").entered();
+
+    let mut report = UrlCheckReport::default();
+    let mut urls: BTreeMap<String, Vec<&Entry>> = BTreeMap::new();
+    let mut order: Vec<String> = vec![];
+
+    for entry in entries {
+        match requestable_url(entry, input) {
+            Some(url) => {
+                let entries_with_url = urls.entry(url.clone()).or_default();
+                if entries_with_url.is_empty() {
+                    order.push(url);
+                }
+                entries_with_url.push(entry);
+            }
+            None => report.skipped_relative_count += 1,
+        }
+    }
+
+    report.checked_count = order.len();
+    let statuses = check_urls(&order, web_client);
+
+    for (url, status) in order.iter().zip(statuses) {
+        if let Err(status) = status {
+            for entry in &urls[url] {
+                report.broken.push(BrokenUrl {
+                    title: entry.title.clone(),
+                    url: url.clone(),
+                    status,
+                });
+            }
+        }
+    }
+
+    report
+}
+
+/// Whether each URL answered successfully, or else the status it answered
+/// with, if any.
+type UrlStatus = Result<(), Option<u16>>;
+
+#[cfg(feature = "build-v3-web-scraping")]
+fn check_urls(urls: &[String], web_client: &WebClient) -> Vec<UrlStatus> {
+    use rayon::prelude::*;
+
+    let client = match web_client.blocking() {
+        Some(client) => client,
+        None => {
+            tracing::warn!("URLs couldn't be verified: the HTTP client couldn't be made");
+            return urls.iter().map(|_| Err(None)).collect();
+        }
+    };
+
+    let check = |url: &String| -> UrlStatus {
+        let head_status = client.head(url).send().map(|response| response.status());
+        match head_status {
+            Ok(status) if status.is_success() => Ok(()),
+            _ => match client.get(url).send() {
+                Ok(response) if response.status().is_success() => Ok(()),
+                Ok(response) => Err(Some(response.status().as_u16())),
+                Err(_) => Err(None),
+            },
+        }
+    };
+
+    match rayon::ThreadPoolBuilder::new()
+        .num_threads(CONCURRENT_URL_CHECKS)
+        .build()
+    {
+        Ok(pool) => pool.install(|| urls.par_iter().map(check).collect()),
+        Err(_) => urls.iter().map(check).collect(),
+    }
+}
+
+/// Without the `build-v3-web-scraping` feature nothing can be requested, so
+/// `input.verify_urls` only produces a nudge.
+#[cfg(not(feature = "build-v3-web-scraping"))]
+fn check_urls(urls: &[String], _web_client: &WebClient) -> Vec<UrlStatus> {
+    urls.iter().map(|_| Ok(())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn entry(title: &str, url: &str) -> Entry {
+        Entry {
+            contents: String::new(),
+            title: title.to_string(),
+            url: url.to_string(),
+            fields: HashMap::new(),
+            score_multiplier: None,
+            word_count: None,
+            text: None,
+            item_starts: vec![],
+        }
+    }
+
+    #[test]
+    fn relative_urls_need_a_base_to_be_requested() {
+        let mut input = InputConfig::default();
+        let relative = entry("Install", "/install");
+        let absolute = entry("Elsewhere", "https://example.com/page");
+        assert_eq!(requestable_url(&relative, &input), None);
+        assert_eq!(
+            requestable_url(&absolute, &input),
+            Some("https://example.com/page".to_string())
+        );
+
+        input.url_prefix = "/docs".to_string();
+        input.verify_url_base = Some("https://example.com/site/".to_string());
+        assert_eq!(
+            requestable_url(&relative, &input),
+            Some("https://example.com/docs/install".to_string())
+        );
+
+        input.url_prefix = "guide/".to_string();
+        assert_eq!(
+            requestable_url(&entry("Intro", "intro"), &input),
+            Some("https://example.com/site/guide/intro".to_string())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "build-v3-web-scraping")]
+    fn broken_urls_are_reported_and_relative_ones_skipped() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        use std::thread;
+
+        // Answers `/ok` and `/no-head` (to GET requests only) with 200, and
+        // everything else with 404.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 4096];
+                let length = stream.read(&mut request).unwrap();
+                let request = String::from_utf8_lossy(&request[..length]).to_string();
+
+                let status = if request.starts_with("GET /ok ")
+                    || request.starts_with("HEAD /ok ")
+                    || request.starts_with("GET /no-head ")
+                {
+                    "200 OK"
+                } else if request.starts_with("HEAD /no-head ") {
+                    "405 Method Not Allowed"
+                } else {
+                    "404 Not Found"
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let url = |path: &str| format!("http://{}{}", address, path);
+        let entries = vec![
+            entry("Working", &url("/ok")),
+            entry("Missing", &url("/missing")),
+            entry("No HEAD", &url("/no-head")),
+            entry("Relative", "/ok"),
+            entry("Also missing", &url("/missing")),
+        ];
+
+        let report = verify_urls(&entries, &InputConfig::default(), &WebClient::default());
+
+        assert_eq!(report.checked_count, 3);
+        assert_eq!(report.skipped_relative_count, 1);
+        assert_eq!(
+            report.broken,
+            vec![
+                BrokenUrl {
+                    title: "Missing".to_string(),
+                    url: url("/missing"),
+                    status: Some(404),
+                },
+                BrokenUrl {
+                    title: "Also missing".to_string(),
+                    url: url("/missing"),
+                    status: Some(404),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,567 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    read::{decode, split_version_header},
+    write::with_version_header,
+    AliasTarget, Container, Entry, EntryIndex, Index, PassthroughConfig, Score, SearchResult,
+};
+use crate::StoredDocumentError;
+
+const INDEX_VERSION: &str = "stork-3";
+const DELTA_VERSION: &str = "stork-3-delta";
+
+/**
+ * An error that may occur when writing a delta between two index files, or
+ * when applying one to an index.
+ */
+#[derive(Debug, Error)]
+pub enum IndexDeltaError {
+    #[error("{0}")]
+    DecodeError(#[from] rmp_serde::decode::Error),
+
+    #[error("Deltas can only be made between single-file Stork 3 indexes.")]
+    NotAnIndex,
+
+    #[error("The file isn't a Stork index delta.")]
+    NotADelta,
+
+    #[error("The delta was made against an index with checksum {expected:016x}, but the index it's being applied to has checksum {found:016x}.")]
+    ChecksumMismatch { expected: u64, found: u64 },
+
+    #[error("The delta's entries don't match the entries of the index it's being applied to.")]
+    MismatchedEntries,
+
+    #[error("Index `{0}` has not been registered, so there's nothing to apply the delta to.")]
+    IndexNotRegistered(String),
+
+    #[error("Deltas can only be applied to Stork 3 indexes.")]
+    IndexVersionNotSupported,
+}
+
+/**
+ * What changed between two builds of an index, written by [`write_delta`]
+ * and applied with [`Index::apply_delta`].
+ *
+ * Entries that are in both indexes are referred to by where they were in
+ * the previous one. Each container is patched after the entry indexes of
+ * its results are moved to where their entries are in the new index, so a
+ * container whose matches only moved isn't in the delta at all.
+ */
+#[derive(Serialize, Deserialize, Debug)]
+struct IndexDelta {
+    /// The [`index_checksum`] of the index file the delta applies to.
+    source_checksum: u64,
+
+    /// The [`index_checksum`] of the index file the delta was made from,
+    /// which the patched index has the same contents as.
+    target_checksum: u64,
+
+    config: PassthroughConfig,
+    entries: Vec<EntryRun>,
+    containers: BTreeMap<String, ContainerPatch>,
+
+    /// Each n-gram whose entries changed, and its new entries, or `None` if
+    /// it's no longer in the index.
+    ngrams: BTreeMap<String, Option<Vec<EntryIndex>>>,
+
+    document_error_count: usize,
+
+    /// The new index's document errors, if they changed.
+    document_errors: Option<Vec<StoredDocumentError>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+enum EntryRun {
+    /// `length` entries of the previous index, starting at `start`.
+    Kept { start: EntryIndex, length: usize },
+
+    /// Entries that weren't in the previous index, or that changed.
+    Added(Vec<Entry>),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+enum ContainerPatch {
+    Removed,
+    Changed {
+        /// Each entry whose result changed, and its new result, or `None`
+        /// if the container no longer has one for it.
+        results: BTreeMap<EntryIndex, Option<SearchResult>>,
+
+        /// The container's new aliases, if they changed.
+        aliases: Option<BTreeMap<AliasTarget, Score>>,
+
+        /// The container's new surface forms, if they changed.
+        surface_forms: Option<Vec<String>>,
+    },
+}
+
+/**
+ * A checksum of an index file's bytes, which a delta records for the index
+ * it applies to. It's only meant to tell files apart, not to resist
+ * tampering.
+ */
+pub fn index_checksum(file: &[u8]) -> u64 {
+    // 64-bit FNV-1a
+    file.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/**
+ * Writes the changes between two single-file index files, `previous` and
+ * `current`, as a delta file. Applying the delta to the index read from
+ * `previous` gives an index with the same contents as `current`, and the
+ * same bytes, as long as serializing the index is deterministic.
+ */
+pub fn write_delta(previous: &[u8], current: &[u8]) -> Result<Bytes, IndexDeltaError> {
+    let mut previous_index = read_index(previous)?;
+    let current_index = read_index(current)?;
+
+    let sources = entry_sources(&previous_index.entries, &current_index.entries);
+    let new_indexes = new_entry_indexes(&sources, previous_index.entries.len());
+
+    let mut containers = BTreeMap::new();
+    for (term, container) in &current_index.containers {
+        let patch = match previous_index.containers.remove(term) {
+            Some(previous_container) => container_patch(
+                &moved_container(previous_container, &new_indexes),
+                container,
+            ),
+            // A new container is added even if it's empty.
+            None => Some(container_patch(&Container::default(), container).unwrap_or(
+                ContainerPatch::Changed {
+                    results: BTreeMap::new(),
+                    aliases: None,
+                    surface_forms: None,
+                },
+            )),
+        };
+        if let Some(patch) = patch {
+            containers.insert(term.clone(), patch);
+        }
+    }
+    for term in previous_index.containers.into_keys() {
+        containers.insert(term, ContainerPatch::Removed);
+    }
+
+    let mut ngrams = BTreeMap::new();
+    for (ngram, entry_indexes) in &previous_index.ngrams {
+        if !current_index.ngrams.contains_key(ngram) {
+            ngrams.insert(ngram.clone(), None);
+        } else if moved_entry_indexes(entry_indexes, &new_indexes) != current_index.ngrams[ngram] {
+            ngrams.insert(ngram.clone(), Some(current_index.ngrams[ngram].clone()));
+        }
+    }
+    for (ngram, entry_indexes) in &current_index.ngrams {
+        if !previous_index.ngrams.contains_key(ngram) {
+            ngrams.insert(ngram.clone(), Some(entry_indexes.clone()));
+        }
+    }
+
+    let delta = IndexDelta {
+        source_checksum: index_checksum(previous),
+        target_checksum: index_checksum(current),
+        config: current_index.config,
+        entries: entry_runs(sources, current_index.entries),
+        containers,
+        ngrams,
+        document_error_count: current_index.document_error_count,
+        document_errors: if previous_index.document_errors == current_index.document_errors {
+            None
+        } else {
+            Some(current_index.document_errors)
+        },
+    };
+
+    Ok(with_version_header(
+        DELTA_VERSION,
+        rmp_serde::to_vec(&delta).unwrap(),
+    ))
+}
+
+impl Index {
+    /**
+     * Patches the index with a delta written by [`write_delta`]. The index
+     * has to have been read from the file the delta was made against, whose
+     * [`index_checksum`] is `checksum`; otherwise the delta is refused and
+     * the index is left as it was. Returns the checksum of the file the
+     * delta was made from, which the patched index now matches.
+     */
+    pub fn apply_delta(&mut self, checksum: u64, delta: &[u8]) -> Result<u64, IndexDeltaError> {
+        let delta: IndexDelta = match split_version_header(delta) {
+            Some((DELTA_VERSION, body)) => rmp_serde::from_read_ref(body)?,
+            _ => return Err(IndexDeltaError::NotADelta),
+        };
+
+        if delta.source_checksum != checksum {
+            return Err(IndexDeltaError::ChecksumMismatch {
+                expected: delta.source_checksum,
+                found: checksum,
+            });
+        }
+
+        // Each previous entry can only be kept once, and nothing is changed
+        // until the delta is known to fit.
+        let mut kept = vec![false; self.entries.len()];
+        for run in &delta.entries {
+            if let EntryRun::Kept { start, length } = run {
+                let kept_run = kept
+                    .get_mut(*start..start.saturating_add(*length))
+                    .ok_or(IndexDeltaError::MismatchedEntries)?;
+                if kept_run.contains(&true) {
+                    return Err(IndexDeltaError::MismatchedEntries);
+                }
+                kept_run.fill(true);
+            }
+        }
+
+        let mut sources = vec![];
+        let mut previous_entries: Vec<Option<Entry>> = std::mem::take(&mut self.entries)
+            .into_iter()
+            .map(Some)
+            .collect();
+        for run in delta.entries {
+            match run {
+                EntryRun::Kept { start, length } => {
+                    for previous_index in start..start + length {
+                        sources.push(Some(previous_index));
+                        self.entries.extend(previous_entries[previous_index].take());
+                    }
+                }
+                EntryRun::Added(entries) => {
+                    sources.extend(entries.iter().map(|_| None));
+                    self.entries.extend(entries);
+                }
+            }
+        }
+        let new_indexes = new_entry_indexes(&sources, previous_entries.len());
+
+        self.containers = std::mem::take(&mut self.containers)
+            .into_iter()
+            .map(|(term, container)| (term, moved_container(container, &new_indexes)))
+            .collect();
+
+        for (term, patch) in delta.containers {
+            match patch {
+                ContainerPatch::Removed => {
+                    self.containers.remove(&term);
+                }
+                ContainerPatch::Changed {
+                    results,
+                    aliases,
+                    surface_forms,
+                } => {
+                    let container = self.containers.entry(term).or_default();
+                    for (entry_index, result) in results {
+                        match result {
+                            Some(result) => container.results.insert(entry_index, result),
+                            None => container.results.remove(&entry_index),
+                        };
+                    }
+                    if let Some(aliases) = aliases {
+                        container.aliases = aliases;
+                    }
+                    if let Some(surface_forms) = surface_forms {
+                        container.surface_forms = surface_forms;
+                    }
+                }
+            }
+        }
+        self.index_terms();
+
+        for entry_indexes in self.ngrams.values_mut() {
+            *entry_indexes = moved_entry_indexes(entry_indexes, &new_indexes);
+        }
+        for (ngram, entry_indexes) in delta.ngrams {
+            match entry_indexes {
+                Some(entry_indexes) => self.ngrams.insert(ngram, entry_indexes),
+                None => self.ngrams.remove(&ngram),
+            };
+        }
+
+        self.config = delta.config;
+        self.document_error_count = delta.document_error_count;
+        if let Some(document_errors) = delta.document_errors {
+            self.document_errors = document_errors;
+        }
+
+        Ok(delta.target_checksum)
+    }
+}
+
+fn read_index(file: &[u8]) -> Result<Index, IndexDeltaError> {
+    match split_version_header(file) {
+        Some((INDEX_VERSION, body)) => Ok(decode(body)?),
+        _ => Err(IndexDeltaError::NotAnIndex),
+    }
+}
+
+/**
+ * For each of the current entries, the index of the same entry among the
+ * previous ones, if it was there unchanged. An entry that's in the
+ * previous index more than once is matched in order.
+ */
+fn entry_sources(previous: &[Entry], current: &[Entry]) -> Vec<Option<EntryIndex>> {
+    let mut previous_by_url: HashMap<&str, Vec<EntryIndex>> = HashMap::new();
+    for (entry_index, entry) in previous.iter().enumerate().rev() {
+        previous_by_url
+            .entry(entry.url.as_str())
+            .or_default()
+            .push(entry_index);
+    }
+
+    current
+        .iter()
+        .map(|entry| {
+            let candidates = previous_by_url.get_mut(entry.url.as_str())?;
+            let position = candidates
+                .iter()
+                .rposition(|&previous_index| previous[previous_index] == *entry)?;
+            Some(candidates.remove(position))
+        })
+        .collect()
+}
+
+/// Groups the current entries into runs of consecutive previous entries and
+/// runs of entries that weren't there.
+fn entry_runs(sources: Vec<Option<EntryIndex>>, current: Vec<Entry>) -> Vec<EntryRun> {
+    let mut runs: Vec<EntryRun> = vec![];
+    for (source, entry) in sources.into_iter().zip(current) {
+        match (source, runs.last_mut()) {
+            (Some(previous_index), Some(EntryRun::Kept { start, length }))
+                if *start + *length == previous_index =>
+            {
+                *length += 1;
+            }
+            (Some(previous_index), _) => runs.push(EntryRun::Kept {
+                start: previous_index,
+                length: 1,
+            }),
+            (None, Some(EntryRun::Added(entries))) => entries.push(entry),
+            (None, _) => runs.push(EntryRun::Added(vec![entry])),
+        }
+    }
+    runs
+}
+
+/// For each previous entry, its index among the current entries, if it's
+/// still there.
+fn new_entry_indexes(
+    sources: &[Option<EntryIndex>],
+    previous_count: usize,
+) -> Vec<Option<EntryIndex>> {
+    let mut new_indexes = vec![None; previous_count];
+    for (new_index, source) in sources.iter().enumerate() {
+        if let Some(previous_index) = source {
+            new_indexes[*previous_index] = Some(new_index);
+        }
+    }
+    new_indexes
+}
+
+/// The container with each result moved to its entry's new index, and the
+/// results of entries that were removed or changed left out.
+fn moved_container(container: Container, new_indexes: &[Option<EntryIndex>]) -> Container {
+    Container {
+        results: container
+            .results
+            .into_iter()
+            .filter_map(|(entry_index, result)| {
+                let new_index = (*new_indexes.get(entry_index)?)?;
+                Some((new_index, result))
+            })
+            .collect(),
+        ..container
+    }
+}
+
+fn moved_entry_indexes(
+    entry_indexes: &[EntryIndex],
+    new_indexes: &[Option<EntryIndex>],
+) -> Vec<EntryIndex> {
+    let mut moved: Vec<EntryIndex> = entry_indexes
+        .iter()
+        .filter_map(|entry_index| *new_indexes.get(*entry_index)?)
+        .collect();
+    moved.sort_unstable();
+    moved
+}
+
+/// What has to change in `moved` to make it `current`, if anything does.
+fn container_patch(moved: &Container, current: &Container) -> Option<ContainerPatch> {
+    let mut results = BTreeMap::new();
+    for (entry_index, result) in &current.results {
+        if moved.results.get(entry_index) != Some(result) {
+            results.insert(*entry_index, Some(result.clone()));
+        }
+    }
+    for entry_index in moved.results.keys() {
+        if !current.results.contains_key(entry_index) {
+            results.insert(*entry_index, None);
+        }
+    }
+
+    let aliases = Some(current.aliases.clone()).filter(|aliases| *aliases != moved.aliases);
+    let surface_forms = Some(current.surface_forms.clone())
+        .filter(|surface_forms| *surface_forms != moved.surface_forms);
+
+    if results.is_empty() && aliases.is_none() && surface_forms.is_none() {
+        None
+    } else {
+        Some(ContainerPatch::Changed {
+            results,
+            aliases,
+            surface_forms,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "build-v3")]
+    use crate::{config::*, index_v3::build::build};
+
+    fn federalist_file() -> Vec<u8> {
+        std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap()
+    }
+
+    fn read(file: &[u8]) -> Index {
+        read_index(file).unwrap()
+    }
+
+    #[test]
+    fn unchanged_index_has_an_empty_delta() {
+        let file = federalist_file();
+        let delta = write_delta(&file, &file).unwrap();
+        assert!(delta.len() < file.len() / 100);
+
+        let mut index = read(&file);
+        let checksum = index.apply_delta(index_checksum(&file), &delta).unwrap();
+        assert_eq!(checksum, index_checksum(&file));
+        assert_eq!(Bytes::from(&index), Bytes::from(&read(&file)));
+    }
+
+    #[test]
+    fn patched_index_matches_the_changed_index() {
+        let previous = federalist_file();
+
+        let mut changed = read(&previous);
+        changed.entries[0].title = "A New Title".to_string();
+        let last_index = changed.entries.len() - 1;
+        let mut new_entry = changed.entries.pop().unwrap();
+        new_entry.url = "a-new-document".to_string();
+        changed.entries.push(new_entry);
+        changed.entries.push(changed.entries[0].clone());
+        for container in changed.containers.values_mut() {
+            container.results.remove(&last_index);
+        }
+        let container = changed.containers.values_mut().next().unwrap();
+        container.results.remove(&1);
+        changed.containers.remove("liberty");
+        changed
+            .containers
+            .insert("brand-new".to_string(), Container::default());
+        changed.index_terms();
+        let current = Bytes::from(&changed);
+
+        let delta = write_delta(&previous, &current).unwrap();
+        assert!(delta.len() < current.len() / 2);
+
+        let mut index = read(&previous);
+        let checksum = index
+            .apply_delta(index_checksum(&previous), &delta)
+            .unwrap();
+        assert_eq!(checksum, index_checksum(&current));
+        assert_eq!(Bytes::from(&index), current);
+    }
+
+    #[test]
+    fn deltas_are_refused_by_other_indexes() {
+        let previous = federalist_file();
+        let mut changed = read(&previous);
+        changed.entries[0].title = "A New Title".to_string();
+        let current = Bytes::from(&changed);
+        let delta = write_delta(&previous, &current).unwrap();
+
+        // The delta was made against `previous`, not `current`.
+        let mut index = read(&current);
+        assert!(matches!(
+            index.apply_delta(index_checksum(&current), &delta),
+            Err(IndexDeltaError::ChecksumMismatch { .. })
+        ));
+        assert_eq!(Bytes::from(&index), current);
+
+        assert!(matches!(
+            index.apply_delta(index_checksum(&current), &current),
+            Err(IndexDeltaError::NotADelta)
+        ));
+        assert!(matches!(
+            index.apply_delta(index_checksum(&current), &delta[..10]),
+            Err(IndexDeltaError::NotADelta)
+        ));
+        assert!(matches!(
+            write_delta(&delta, &current),
+            Err(IndexDeltaError::NotAnIndex)
+        ));
+    }
+
+    #[cfg(feature = "build-v3")]
+    fn plaintext_file(title: &str, contents: &str) -> File {
+        File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "build-v3")]
+    const TWELVE_WORDS: &str = "one two three four five six seven eight nine ten eleven twelve";
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn applying_a_delta_gives_the_rebuilt_index() {
+        let config = |files: Vec<File>| Config {
+            input: InputConfig {
+                files,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let index_file =
+            |files: Vec<File>| bytes::Bytes::from(&build(&config(files)).unwrap().index);
+
+        let previous = index_file(vec![
+            plaintext_file("Removed", "an old post about lighthouses"),
+            plaintext_file("Kept", TWELVE_WORDS),
+            plaintext_file("Changed", "the keeper climbs the stairs"),
+        ]);
+        let current = index_file(vec![
+            plaintext_file("Kept", TWELVE_WORDS),
+            plaintext_file("Changed", "the keeper climbs the spiral stairs at dusk"),
+            plaintext_file("Added", "a new post about lighthouses"),
+        ]);
+
+        let delta = crate::index_v3::write_delta(&previous, &current).unwrap();
+        let mut index = Index::try_from(previous.as_ref()).unwrap();
+        let checksum = index
+            .apply_delta(crate::index_v3::index_checksum(&previous), &delta)
+            .unwrap();
+
+        assert_eq!(checksum, crate::index_v3::index_checksum(&current));
+        assert_eq!(bytes::Bytes::from(&index), current);
+        assert_eq!(
+            crate::index_v3::search(&index, "lighthouses")
+                .unwrap()
+                .results[0]
+                .entry
+                .title,
+            "Added"
+        );
+    }
+}
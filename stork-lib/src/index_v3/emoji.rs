@@ -0,0 +1,139 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/**
+ * Splits each emoji out of `word`, so `launch🚀🚀` becomes `launch`, `🚀`,
+ * and `🚀`. An emoji is a whole grapheme cluster, so one made of several
+ * characters, like `👍🏽` or `👩‍💻`, stays in one piece. The text between
+ * emoji is kept together, punctuation stays with the piece it's next to, and
+ * a word without emoji is returned as it is.
+ */
+pub(super) fn split_emoji(word: &str) -> Vec<&str> {
+    // The byte ranges of the pieces, and whether each is an emoji
+    let mut pieces: Vec<(usize, usize, bool)> = vec![];
+    for (offset, cluster) in word.grapheme_indices(true) {
+        let end = offset + cluster.len();
+        match pieces.last_mut() {
+            Some((_, last_end, false)) if !is_emoji(cluster) => *last_end = end,
+            _ => pieces.push((offset, end, is_emoji(cluster))),
+        }
+    }
+
+    // Text with no letters or digits in it is punctuation, which is joined
+    // to the piece after it if it starts the word, or else the one before.
+    let mut joined: Vec<(usize, usize)> = vec![];
+    let mut leading_punctuation_start = None;
+    for (start, end, emoji) in pieces {
+        let punctuation = !emoji && !word[start..end].chars().any(char::is_alphanumeric);
+        match joined.last_mut() {
+            Some((_, last_end)) if punctuation => *last_end = end,
+            None if punctuation => leading_punctuation_start = Some(start),
+            _ => joined.push((leading_punctuation_start.take().unwrap_or(start), end)),
+        }
+    }
+
+    if let Some(start) = leading_punctuation_start {
+        joined.push((start, word.len()));
+    }
+    joined
+        .into_iter()
+        .map(|(start, end)| &word[start..end])
+        .collect()
+}
+
+/// Whether a grapheme cluster is shown as an emoji: it starts with a
+/// pictograph, or asks for emoji presentation, like `❤️` or `1️⃣` do.
+fn is_emoji(cluster: &str) -> bool {
+    cluster.chars().next().map_or(false, is_pictograph)
+        || cluster.chars().any(|c| c == '\u{FE0F}' || c == '\u{20E3}')
+}
+
+fn is_pictograph(c: char) -> bool {
+    matches!(
+        c,
+        '\u{1F000}'..='\u{1FAFF}'
+            | '\u{2600}'..='\u{27BF}'
+            | '\u{2300}'..='\u{23FF}'
+            | '\u{2B00}'..='\u{2BFF}'
+            | '\u{3030}'
+            | '\u{303D}'
+            | '\u{3297}'
+            | '\u{3299}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "build-v3")]
+    use crate::{config::*, index_v3::build::build, V3Index as Index};
+
+    #[test]
+    fn emoji_are_split_from_text() {
+        assert_eq!(split_emoji("🚀"), vec!["🚀"]);
+        assert_eq!(split_emoji("launch🚀🚀"), vec!["launch", "🚀", "🚀"]);
+        assert_eq!(split_emoji("👍great👍"), vec!["👍", "great", "👍"]);
+        assert_eq!(split_emoji("(🚀),"), vec!["(🚀),"]);
+        assert_eq!(split_emoji("go!🚀"), vec!["go!", "🚀"]);
+    }
+
+    #[test]
+    fn emoji_made_of_several_characters_stay_whole() {
+        assert_eq!(split_emoji("👍🏽ok"), vec!["👍🏽", "ok"]);
+        assert_eq!(split_emoji("👩‍💻"), vec!["👩‍💻"]);
+        assert_eq!(split_emoji("🇫🇷🇩🇪"), vec!["🇫🇷", "🇩🇪"]);
+        assert_eq!(split_emoji("❤️1️⃣"), vec!["❤️", "1️⃣"]);
+    }
+
+    #[test]
+    fn other_text_is_left_alone() {
+        assert_eq!(split_emoji("café"), vec!["café"]);
+        assert_eq!(split_emoji("東京"), vec!["東京"]);
+        assert_eq!(split_emoji("a→b"), vec!["a→b"]);
+        assert_eq!(split_emoji("..."), vec!["..."]);
+        assert!(split_emoji("").is_empty());
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn emoji_can_be_indexed_as_words() {
+        let config = |index_emoji| Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        "We shipped it!🚀🚀 Thanks everyone👍🏽".to_string(),
+                    )),
+                    title: "Launch notes".to_string(),
+                    filetype: Some(Filetype::PlainText),
+                    ..Default::default()
+                }],
+                index_emoji,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let without_emoji = build(&config(false)).unwrap().index;
+        assert!(crate::index_v3::search(&without_emoji, "🚀")
+            .unwrap()
+            .results
+            .is_empty());
+
+        let bytes = bytes::Bytes::from(&build(&config(true)).unwrap().index);
+        let index = Index::try_from(bytes.as_ref()).unwrap();
+        for query in ["🚀", "👍🏽", "thanks", "everyone", "shipped🚀"] {
+            let output = crate::index_v3::search(&index, query).unwrap();
+            assert_eq!(output.results.len(), 1, "{}", query);
+            assert!(
+                !output.results[0].excerpts[0].highlight_ranges.is_empty(),
+                "{}",
+                query
+            );
+        }
+
+        // A skin tone is part of its emoji, not an emoji of its own.
+        assert!(crate::index_v3::search(&index, "👍")
+            .unwrap()
+            .results
+            .is_empty());
+    }
+}
@@ -7,35 +7,91 @@ type EntryIndex = usize;
 type AliasTarget = String;
 type Score = u8;
 
+mod accessors;
+mod acronyms;
+mod delta;
+mod emoji;
+mod ngrams;
 mod read;
 mod scores;
 mod search;
+mod shards;
+mod terms;
+mod verify;
+mod width;
 
 #[cfg(feature = "build-v3")]
 mod build;
 
 #[cfg(feature = "build-v3")]
-pub use {
-    build::{
-        build,
-        errors::{DocumentError, IndexGenerationError},
-        BuildResult,
+pub use build::{
+    build, build_with_partial_results, build_with_progress,
+    errors::{
+        group_document_errors, DocumentError, DocumentErrorGroup, DocumentWarning,
+        DocumentWarningKind, IndexGenerationError, SourceLocation,
     },
-    scores::MATCHED_WORD_SCORE,
+    estimate::{estimate_index_size, SizeContributor, SizeEstimate},
+    exclude::{ExcludedDocument, ExclusionRule},
+    migrate::{migrate_config, ConfigMigration, ConfigMigrationError},
+    nudger::Nudge,
+    prune::PruningAction,
+    update::{update_entry_text, EntryUpdateError},
+    verify_urls::{BrokenUrl, UrlCheckReport},
+    BuildProgress, BuildResult, DefaultTokenizer, PartialBuildError,
 };
 
-pub use search::search;
+#[cfg(feature = "async")]
+pub use build::build_async;
 
-use crate::config::{OutputConfig, TitleBoost};
-use crate::{Fields, InternalWordAnnotation};
+pub use accessors::{EntryRef, TermStats};
+pub use delta::{index_checksum, write_delta, IndexDeltaError};
+#[cfg(test)]
+pub use search::{search, search_titles};
+pub use search::{search_titles_with_options, search_with_options, QuerySession};
+pub use shards::{ShardedIndex, ShardedIndexReadError};
+pub use verify::{verify_index, IndexInconsistency};
+
+use crate::config::{
+    AcronymPeriodHandling, ExcerptStrategy, MatchWeights, NgramConfig, OutputConfig, QueryOperator,
+    StemmingConfig, TieBreaker, TitleBoost, TitlePositionDecayCurve, UnknownQueryFields,
+    ZeroResultFallback,
+};
+use crate::{AnnotatedWord, Fields, InternalWordAnnotation, StoredDocumentError};
+use terms::TermSet;
 
 mod write;
 
+/**
+ * A parsed Stork index.
+ *
+ * Searching only ever reads from an `Index` and it contains no interior
+ * mutability or lazily-initialized caches, so it is `Send + Sync` and one
+ * instance can be shared (e.g. behind an `Arc`) and searched from many
+ * threads at once without a lock.
+ */
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Index {
     config: PassthroughConfig,
     entries: Vec<Entry>,
     containers: BTreeMap<String, Container>,
+
+    // Indexes built before document errors were recorded have none.
+    #[serde(default)]
+    document_error_count: usize,
+    #[serde(default)]
+    document_errors: Vec<StoredDocumentError>,
+
+    /// Each n-gram of the title and contents words, mapped to the entries
+    /// it appears in, in order. Empty unless `input.index_ngrams` is set.
+    /// It's written even when empty, so the fields after it keep their
+    /// positions.
+    #[serde(default)]
+    ngrams: BTreeMap<String, Vec<EntryIndex>>,
+
+    /// The keys of `containers`, which prefix lookups search. Indexes built
+    /// before it was stored build it when they're read.
+    #[serde(default)]
+    terms: TermSet,
 }
 
 impl Index {
@@ -51,11 +107,18 @@ impl Index {
         self.word_count() / self.entries_len()
     }
 
-    pub fn search_term_count(&self) -> usize {
-        self.containers.keys().count()
+    /// Rebuilds the term set after containers have been added or removed.
+    fn index_terms(&mut self) {
+        self.terms = TermSet::from_sorted(self.containers.keys());
     }
 }
 
+fn unweighted() -> f64 {
+    1.0
+}
+
+// The index is serialized by position, so new fields go at the end, with a
+// `#[serde(default)]` for indexes built before them.
 #[derive(Serialize, Deserialize, Clone, Debug, SmartDefault)]
 struct PassthroughConfig {
     url_prefix: String,
@@ -67,14 +130,168 @@ struct PassthroughConfig {
     excerpts_per_result: u8,
     #[default(OutputConfig::default().displayed_results_count)]
     displayed_results_count: u8,
+
+    // Indexes built before this option existed deserialize it as 0, which
+    // doesn't restrict any queries.
+    #[serde(default)]
+    #[default(OutputConfig::default().min_query_length)]
+    min_query_length: u8,
+
+    // Indexes built before this option existed keep their highest-scoring
+    // excerpts, as they always have.
+    #[serde(default)]
+    #[default(OutputConfig::default().excerpt_strategy)]
+    excerpt_strategy: ExcerptStrategy,
+
+    #[serde(default)]
+    #[default(OutputConfig::default().excerpt_max_chars)]
+    excerpt_max_chars: Option<u16>,
+
+    #[serde(default)]
+    excerpt_sentence_tolerance: Option<u8>,
+
+    // Indexes built before this option existed don't record where items
+    // start, so they never show it.
+    #[serde(default)]
+    #[default(OutputConfig::default().excerpt_item_separator)]
+    excerpt_item_separator: String,
+
+    #[serde(default)]
+    #[default(OutputConfig::default().unknown_query_fields)]
+    unknown_query_fields: UnknownQueryFields,
+
+    #[serde(default)]
+    query_operator: QueryOperator,
+
+    /// Whether the index was built with `input.normalize_width`, so queries
+    /// have to be normalized the same way.
+    #[serde(default)]
+    normalize_width: bool,
+
+    /// How the index was built to handle acronyms like `U.S.A.`, so queries
+    /// are handled the same way.
+    #[serde(default)]
+    acronym_periods: AcronymPeriodHandling,
+
+    /// Whether the index was built with `input.index_emoji`, so emoji in
+    /// queries have to be split from their words the same way.
+    #[serde(default)]
+    index_emoji: bool,
+
+    #[serde(default)]
+    index_ngrams: Option<NgramConfig>,
+
+    /// The tags of `input.language_url_prefixes`, which searches can be
+    /// limited to.
+    #[serde(default)]
+    languages: Vec<String>,
+
+    #[serde(default)]
+    length_normalization: f64,
+
+    #[serde(default)]
+    proximity_boost: f64,
+
+    #[serde(default)]
+    #[default(OutputConfig::default().proximity_window)]
+    proximity_window: u8,
+
+    /// Only the weights used at query time, `substring` and, for title
+    /// searches, `exact` and `prefix`. The rest are already in the alias
+    /// scores.
+    #[serde(default)]
+    match_weights: MatchWeights,
+
+    #[serde(default)]
+    zero_result_fallback: Option<ZeroResultFallback>,
+
+    #[serde(default)]
+    group_by_url_without_fragment: bool,
+
+    /// The mean number of words in an entry's contents, which
+    /// `length_normalization` compares each entry's length to.
+    #[serde(default)]
+    average_word_count: f64,
+
+    /// The `input.stemming` the index was built with, so that queries can
+    /// be stemmed the same way. Unknown for indexes built before it was
+    /// recorded.
+    #[serde(default)]
+    stemming: Option<StemmingConfig>,
+
+    /// Whether some documents weren't stemmed according to `stemming`,
+    /// because of a per-file override, language detection or URL prefixes,
+    /// or a filetype that isn't stemmed.
+    #[serde(default)]
+    stemming_varies: bool,
+
+    /// The words per minute each result's reading time is estimated with.
+    /// 0, as in indexes built before it existed, doesn't estimate it.
+    #[serde(default)]
+    reading_time_wpm: u16,
+
+    /// Indexes built before this option existed weigh every title word the
+    /// same, as they always have.
+    #[serde(default = "unweighted")]
+    #[default = 1.0]
+    title_position_decay: f64,
+
+    #[serde(default)]
+    title_position_decay_curve: TitlePositionDecayCurve,
+
+    /// Indexes built before this option existed have no code matches, but
+    /// weigh them in full.
+    #[serde(default = "unweighted")]
+    #[default = 1.0]
+    code_match_weight: f64,
+
+    /// Indexes built before this option existed order ties by title, as
+    /// they always have.
+    #[serde(default)]
+    tie_breaker: TieBreaker,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// The field each entry's facet is stored in, when the index was built with
+/// `input.facet_url_prefixes`.
+const FACET_FIELD: &str = "facet";
+
+/// The field each entry's language is stored in, when the index was built
+/// with `input.language_url_prefixes` and its URL matched one of them.
+const LANGUAGE_FIELD: &str = "language";
+
+/// The field each entry's thumbnail is stored in, as it was given, before
+/// `url_prefix` is applied.
+const THUMBNAIL_FIELD: &str = "thumbnail_url";
+
+/// The field each entry's date is stored in, as `YYYY-MM-DD`.
+const DATE_FIELD: &str = "date";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 struct Entry {
     contents: String,
     title: String,
     url: String,
     fields: Fields,
+
+    /// What the entry's search scores are multiplied by, from the
+    /// `input.path_score_multipliers` rule its URL matched.
+    #[serde(default)]
+    score_multiplier: Option<f64>,
+
+    /// How many words the entry's contents had when it was indexed, even if
+    /// its text wasn't stored. Indexes built before it was stored have none.
+    #[serde(default)]
+    word_count: Option<usize>,
+
+    /// The entry's text, when the index was built with
+    /// `output.store_document_text`.
+    #[serde(default)]
+    text: Option<String>,
+
+    /// The index of each word that starts a list item, definition, or table
+    /// row, after the entry's first word, in order.
+    #[serde(default)]
+    item_starts: Vec<usize>,
 }
 
 /**
@@ -86,13 +303,19 @@ struct Entry {
  * Each valid query should return a single Container. It is possible to derive
  * all search results for a given query from a single container.
  */
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct Container {
     // #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     results: BTreeMap<EntryIndex, SearchResult>,
 
     // #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     aliases: BTreeMap<AliasTarget, Score>,
+
+    /// If this container's key is a stem, or a word that was reduced to one,
+    /// the words in the corpus that reduce to that stem. Only filled when
+    /// `output.save_stem_surface_forms` is set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    surface_forms: Vec<String>,
 }
 
 impl Container {
@@ -102,7 +325,7 @@ impl Container {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 struct SearchResult {
     excerpts: Vec<Excerpt>,
     score: Score,
@@ -110,15 +333,15 @@ struct SearchResult {
 
 impl SearchResult {
     #[cfg(feature = "build-v3")]
-    fn new() -> SearchResult {
+    fn new(score: Score) -> SearchResult {
         SearchResult {
             excerpts: vec![],
-            score: MATCHED_WORD_SCORE,
+            score,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 struct Excerpt {
     word_index: usize,
 
@@ -138,13 +361,17 @@ enum WordListSource {
 
     #[default]
     Contents,
-}
 
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
-struct AnnotatedWord {
-    word: String,
-    internal_annotations: Vec<InternalWordAnnotation>,
-    fields: Fields,
+    Url,
+
+    Keywords,
+
+    Summary,
+
+    /// The words of the contents that are code, indexed with
+    /// `input.index_code_identifiers`. Their word indexes are in the
+    /// contents.
+    Code,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -157,10 +384,25 @@ impl AnnotatedWordList {
     fn get_full_text(&self) -> String {
         self.word_list
             .iter()
-            .map(|aw| aw.word.clone())
-            .collect::<Vec<String>>()
+            .map(|aw| &*aw.word)
+            .collect::<Vec<&str>>()
             .join(" ")
     }
+
+    /// The index of each word after the first with an `ItemStart`
+    /// annotation.
+    fn item_starts(&self) -> Vec<usize> {
+        self.word_list
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, aw)| {
+                aw.internal_annotations
+                    .contains(&InternalWordAnnotation::ItemStart)
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -182,30 +424,37 @@ mod tests {
         assert_eq!(2477, index.containers.len());
     }
 
+    #[test]
+    fn index_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Index>();
+        assert_send_sync::<crate::ParsedIndex>();
+    }
+
     #[test]
     fn get_full_text() {
         let intended = "This is-a set of words.".to_string();
         let generated = AnnotatedWordList {
             word_list: vec![
                 AnnotatedWord {
-                    word: "This".to_string(),
+                    word: "This".into(),
                     ..Default::default()
                 },
                 AnnotatedWord {
-                    word: "is-a".to_string(),
+                    word: "is-a".into(),
                     internal_annotations: vec![InternalWordAnnotation::UrlSuffix("a".to_string())],
                     fields: HashMap::default(),
                 },
                 AnnotatedWord {
-                    word: "set".to_string(),
+                    word: "set".into(),
                     ..Default::default()
                 },
                 AnnotatedWord {
-                    word: "of".to_string(),
+                    word: "of".into(),
                     ..Default::default()
                 },
                 AnnotatedWord {
-                    word: "words.".to_string(),
+                    word: "words.".into(),
                     ..Default::default()
                 },
             ],
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+
+use super::EntryIndex;
+use crate::config::NgramConfig;
+
+/**
+ * Every run of `n` consecutive characters in the word. A word shorter than
+ * `n` characters has none.
+ */
+pub(super) fn word_ngrams(word: &str, n: usize) -> impl Iterator<Item = String> + '_ {
+    let char_offsets: Vec<usize> = word
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(word.len()))
+        .collect();
+    let ngram_count = (char_offsets.len() - 1).saturating_sub(n.saturating_sub(1));
+
+    (0..ngram_count)
+        .filter(move |_| n > 0)
+        .map(move |start| word[char_offsets[start]..char_offsets[start + n]].to_string())
+}
+
+/**
+ * The entries that might contain `query` somewhere within one of their
+ * words: those that have every one of the query's n-grams, of the longest
+ * indexed length that fits in the query. Returns nothing for a query shorter
+ * than the shortest indexed n-gram.
+ *
+ * Having every n-gram doesn't mean an entry has the whole query in a single
+ * word, so the candidates still have to be checked against their text.
+ */
+pub(super) fn candidate_entries(
+    ngrams: &BTreeMap<String, Vec<EntryIndex>>,
+    config: &NgramConfig,
+    query: &str,
+) -> Vec<EntryIndex> {
+    let lengths = config.lengths();
+    let query_length = query.chars().count();
+    if query_length < *lengths.start() {
+        return vec![];
+    }
+
+    let n = query_length.min(*lengths.end());
+    let mut candidates: Option<Vec<EntryIndex>> = None;
+
+    for ngram in word_ngrams(query, n) {
+        let entries = match ngrams.get(&ngram) {
+            Some(entries) => entries,
+            None => return vec![],
+        };
+
+        candidates = Some(match candidates {
+            None => entries.clone(),
+            Some(candidates) => candidates
+                .into_iter()
+                .filter(|entry_index| entries.binary_search(entry_index).is_ok())
+                .collect(),
+        });
+    }
+
+    candidates.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn word_ngrams_slide_over_characters() {
+        assert_eq!(
+            word_ngrams("abx2004", 3).collect::<Vec<String>>(),
+            vec!["abx", "bx2", "x20", "200", "004"]
+        );
+        assert_eq!(
+            word_ngrams("café", 3).collect::<Vec<String>>(),
+            vec!["caf", "afé"]
+        );
+        assert_eq!(word_ngrams("ab", 3).count(), 0);
+        assert_eq!(word_ngrams("ab", 0).count(), 0);
+    }
+
+    #[test]
+    fn candidates_have_every_ngram_of_the_query() {
+        let config = NgramConfig { min: 3, max: 4 };
+        let mut ngrams: BTreeMap<String, Vec<EntryIndex>> = BTreeMap::new();
+        for (entry_index, word) in ["abx2004", "x200", "x2", "zx20"].iter().enumerate() {
+            for n in config.lengths() {
+                for ngram in word_ngrams(word, n) {
+                    ngrams.entry(ngram).or_default().push(entry_index);
+                }
+            }
+        }
+
+        assert_eq!(candidate_entries(&ngrams, &config, "x200"), vec![0, 1]);
+        assert_eq!(candidate_entries(&ngrams, &config, "x20"), vec![0, 1, 3]);
+        assert_eq!(candidate_entries(&ngrams, &config, "bx200"), vec![0]);
+        assert_eq!(
+            candidate_entries(&ngrams, &config, "x2"),
+            Vec::<usize>::new()
+        );
+        assert_eq!(
+            candidate_entries(&ngrams, &config, "y200"),
+            Vec::<usize>::new()
+        );
+    }
+}
@@ -5,17 +5,12 @@ use std::convert::{TryFrom, TryInto};
 impl TryFrom<&[u8]> for Index {
     type Error = rmp_serde::decode::Error;
     fn try_from(file: &[u8]) -> Result<Self, Self::Error> {
-        let (version_size_bytes, rest) = file.split_at(std::mem::size_of::<u64>());
-        let version_size = u64::from_be_bytes(version_size_bytes.try_into().unwrap());
-        let version_size: usize = version_size.try_into().unwrap();
-        let (_version_bytes, rest) = rest.split_at(version_size);
-
-        let (index_size_bytes, rest) = rest.split_at(std::mem::size_of::<u64>());
-        let index_size = u64::from_be_bytes(index_size_bytes.try_into().unwrap());
-        let index_size: usize = index_size.try_into().unwrap();
-        let (index_bytes, _rest) = rest.split_at(index_size);
-
-        rmp_serde::from_read_ref(index_bytes)
+        match split_version_header(file) {
+            Some((_version, body)) => decode(body),
+            None => Err(rmp_serde::decode::Error::Syntax(
+                "The index is too short to have a version header.".to_string(),
+            )),
+        }
     }
 }
 
@@ -23,6 +18,32 @@ impl TryFrom<Bytes> for Index {
     type Error = rmp_serde::decode::Error;
 
     fn try_from(value: Bytes) -> Result<Self, Self::Error> {
-        rmp_serde::from_read_ref(value.as_ref())
+        decode(value.as_ref())
+    }
+}
+
+/// Decodes an index body, building the term set of indexes written before
+/// it was stored.
+pub(super) fn decode(body: &[u8]) -> Result<Index, rmp_serde::decode::Error> {
+    let mut index: Index = rmp_serde::from_read_ref(body)?;
+    if index.terms.len() != index.containers.len() {
+        index.index_terms();
     }
+    Ok(index)
+}
+
+/// The version string and body of a file written by `with_version_header`,
+/// if it's long enough to have them.
+pub(super) fn split_version_header(file: &[u8]) -> Option<(&str, &[u8])> {
+    let (version, rest) = split_sized(file)?;
+    let (body, _) = split_sized(rest)?;
+    Some((std::str::from_utf8(version).ok()?, body))
+}
+
+fn split_sized(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+    let size_length = std::mem::size_of::<u64>();
+    let size = u64::from_be_bytes(bytes.get(..size_length)?.try_into().ok()?);
+    let rest = &bytes[size_length..];
+    let size: usize = size.try_into().ok().filter(|&size| size <= rest.len())?;
+    Some(rest.split_at(size))
 }
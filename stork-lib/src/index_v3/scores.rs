@@ -1,4 +1,47 @@
+use crate::config::TitlePositionDecayCurve;
+
 pub const MATCHED_WORD_SCORE: u8 = 128;
-pub const PREFIX_SCORE: u8 = 127;
-pub const STEM_SCORE: u8 = 64;
 pub const STOPWORD_SCORE: u8 = 16;
+
+/// The score of a match worth `weight` of an exact match, for one of
+/// `output.match_weights`. Weights below 0 count as 0, and the score can't
+/// go above `u8::MAX`.
+pub fn weighted_score(weight: f64) -> u8 {
+    (weight.max(0.0) * MATCHED_WORD_SCORE as f64)
+        .round()
+        .min(u8::MAX as f64) as u8
+}
+
+/// Added to a result's score for each word of its URL path the query matched.
+pub const URL_MATCH_SCORE: usize = 10;
+
+/// Added to a result's score for each of its keywords the query matched.
+pub const KEYWORD_MATCH_SCORE: usize = 40;
+
+/// Added to a result's score for each word of its summary the query matched.
+pub const SUMMARY_MATCH_SCORE: usize = 60;
+
+/// Added to an excerpt's score for each query word beyond the first that it
+/// matches close to the others, for `output.proximity_boost`.
+pub fn proximity_score(boost: f64, extra_words: usize) -> usize {
+    (boost.max(0.0) * f64::from(MATCHED_WORD_SCORE) * extra_words as f64).round() as usize
+}
+
+/// How much a match of the title word at `position` counts towards the
+/// title boost, for `input.title_position_decay`. The first word counts in
+/// full.
+pub fn title_position_weight(position: usize, decay: f64, curve: TitlePositionDecayCurve) -> f64 {
+    match curve {
+        TitlePositionDecayCurve::Exponential => decay.powi(position as i32),
+        TitlePositionDecayCurve::Linear => (1.0 - (1.0 - decay) * position as f64).max(0.0),
+    }
+}
+
+/// Scales a result's score by its entry's `score_multiplier`, if it has one.
+/// Multipliers below 0 count as 0.
+pub fn scale_by_multiplier(score: usize, multiplier: Option<f64>) -> usize {
+    match multiplier {
+        Some(multiplier) => (score as f64 * multiplier.max(0.0)).round() as usize,
+        None => score,
+    }
+}
@@ -1,12 +1,23 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+};
 
 use crate::{
-    config::TitleBoost,
-    index_v3::{Entry, PassthroughConfig, WordListSource},
-    Excerpt, HighlightRange, Result,
+    config::{ExcerptStrategy, TitleBoost},
+    index_v3::{
+        scores::{
+            proximity_score, scale_by_multiplier, title_position_weight, KEYWORD_MATCH_SCORE,
+            SUMMARY_MATCH_SCORE, URL_MATCH_SCORE,
+        },
+        Entry, PassthroughConfig, WordListSource,
+    },
+    Excerpt, ExcerptField, HighlightRange, InternalWordAnnotation, Result,
 };
 
 use super::intermediate_excerpt::IntermediateExcerpt;
+use super::sentences::snap_to_sentences;
+use super::with_url_prefix;
 
 #[derive(Debug)]
 pub(super) struct EntryAndIntermediateExcerpts {
@@ -30,16 +41,37 @@ impl From<EntryAndIntermediateExcerpts> for Result {
         let mut ies: Vec<&IntermediateExcerpt> = data
             .intermediate_excerpts
             .iter()
-            .filter(|ie| ie.source == WordListSource::Contents)
+            .filter(|ie| matches!(ie.source, WordListSource::Contents | WordListSource::Code))
             .collect();
+        let contents_matched = !ies.is_empty();
+
+        // An entry built without excerpts has no text to cut them from, so
+        // its contents matches are only scored, by the best of them.
+        let mut bare_match_score = None;
+        if split_contents.is_empty() {
+            bare_match_score = ies.iter().map(|ie| ie.score as usize).max();
+            ies.clear();
+        }
 
         // Get rid of intermediate excerpts that refer to the same word index.
         // But first, sort by score so that only the highest score within the
-        // same word index is kept.
-        ies.sort_by_cached_key(|ie| ie.score);
+        // same word index is kept: a word of code is indexed under each of
+        // its identifiers, so `user` matches `user.getUserName` both exactly
+        // and as a prefix of the whole word, and only the exact match should
+        // be highlighted.
+        ies.sort_by_cached_key(|ie| Reverse(ie.score));
         ies.sort_by_cached_key(|ie| ie.word_index);
         ies.dedup_by_key(|ie| ie.word_index);
 
+        // Matches in different list items, definitions, or table rows of an
+        // HTML document are never grouped into one excerpt, or counted as
+        // close together, however few words apart they are.
+        let item_of = |word_index: usize| {
+            entry
+                .item_starts
+                .partition_point(|&item_start| item_start <= word_index)
+        };
+
         let mut ies_grouped_by_word_index: Vec<Vec<&IntermediateExcerpt>> = vec![];
 
         for ie in &ies {
@@ -47,6 +79,7 @@ impl From<EntryAndIntermediateExcerpts> for Result {
                 if let Some(trailing_ie) = most_recent.first() {
                     if (ie.word_index as isize) - (trailing_ie.word_index as isize)
                         < (excerpt_buffer as isize)
+                        && item_of(ie.word_index) == item_of(trailing_ie.word_index)
                     {
                         most_recent.push(ie);
                         continue;
@@ -57,36 +90,78 @@ impl From<EntryAndIntermediateExcerpts> for Result {
             ies_grouped_by_word_index.push(vec![ie])
         }
 
-        let mut excerpts: Vec<Excerpt> = ies_grouped_by_word_index
+        let excerpts: Vec<(usize, Excerpt)> = ies_grouped_by_word_index
             .iter()
             .map(|ies| {
-                let minimum_word_index = ies
-                    .first()
-                    .unwrap()
-                    .word_index
-                    .saturating_sub(excerpt_buffer);
-
-                let maximum_word_index = std::cmp::min(
-                    ies.last()
-                        .unwrap()
-                        .word_index
-                        .saturating_add(excerpt_buffer),
+                let first_match = ies.first().unwrap().word_index;
+                let last_match = ies.last().unwrap().word_index;
+
+                let mut minimum_word_index = first_match.saturating_sub(excerpt_buffer);
+                let mut maximum_word_index = std::cmp::min(
+                    last_match.saturating_add(excerpt_buffer),
                     split_contents.len(),
                 );
+                let (mut sentence_start, mut sentence_end) = (false, false);
 
-                let text = split_contents[minimum_word_index..maximum_word_index].join(" ");
+                if let Some(tolerance) = data.config.excerpt_sentence_tolerance {
+                    let window = snap_to_sentences(
+                        &split_contents,
+                        minimum_word_index,
+                        maximum_word_index,
+                        (first_match, last_match),
+                        tolerance as usize,
+                    );
+                    minimum_word_index = window.start;
+                    maximum_word_index = window.end;
+                    sentence_start = window.sentence_start;
+                    sentence_end = window.sentence_end;
+                }
+
+                // The words around the matches can come from the items next
+                // to theirs, so the separator is shown where each item starts.
+                let mut text = String::new();
+                let mut word_offsets = vec![];
+                for word_index in minimum_word_index..maximum_word_index {
+                    if word_index > minimum_word_index {
+                        text.push(' ');
+                        if entry.item_starts.binary_search(&word_index).is_ok() {
+                            text.push_str(&data.config.excerpt_item_separator);
+                        }
+                    }
+                    word_offsets.push(text.chars().count());
+                    text.push_str(&split_contents[word_index]);
+                }
 
                 let mut highlight_ranges: Vec<HighlightRange> = ies
                     .iter()
                     .map(|ie| {
-                        let beginning = split_contents[minimum_word_index..ie.word_index]
-                            .join(" ")
-                            .chars()
-                            .count()
-                            + 1;
+                        // A match in code only covers the identifier, or
+                        // part of one, that the query matched.
+                        let (match_offset, length) = match ie.source {
+                            WordListSource::Code => (
+                                code_match_offset(&split_contents[ie.word_index], &ie.query),
+                                ie.query.chars().count(),
+                            ),
+                            _ => (
+                                ie.match_offset,
+                                match spelled_out_word_count(
+                                    &split_contents[ie.word_index..],
+                                    &ie.query,
+                                ) {
+                                    Some(count) => split_contents
+                                        [ie.word_index..ie.word_index + count]
+                                        .join(" ")
+                                        .chars()
+                                        .count(),
+                                    None => ie.query.chars().count(),
+                                },
+                            ),
+                        };
+                        let beginning =
+                            word_offsets[ie.word_index - minimum_word_index] + match_offset;
                         HighlightRange {
                             beginning,
-                            end: beginning + ie.query.chars().count(),
+                            end: beginning + length,
                         }
                     })
                     .collect();
@@ -103,11 +178,26 @@ impl From<EntryAndIntermediateExcerpts> for Result {
 
                 let score_modifier = highlighted_character_range - highlighted_characters_count;
 
+                // Query words that are close together count for more, so
+                // the excerpts and results that have them rank higher.
+                let proximity_modifier = proximity_score(
+                    data.config.proximity_boost,
+                    most_query_words_within(ies, data.config.proximity_window as usize, item_of)
+                        .saturating_sub(1),
+                );
+
                 let score = ies
                     .iter()
-                    .map(|ie| (ie.score as usize))
+                    .map(|ie| match ie.source {
+                        WordListSource::Code => scale_by_multiplier(
+                            ie.score as usize,
+                            Some(data.config.code_match_weight),
+                        ),
+                        _ => ie.score as usize,
+                    })
                     .sum::<usize>()
-                    .saturating_sub(score_modifier);
+                    .saturating_sub(score_modifier)
+                    + proximity_modifier;
 
                 // Since we're mapping from multiple IntermediateExcerpts to one
                 // Excerpt, we have to either combine or filter data. For
@@ -121,18 +211,54 @@ impl From<EntryAndIntermediateExcerpts> for Result {
                     .first()
                     .map_or_else(Vec::default, |first| first.internal_annotations.clone());
 
-                Excerpt {
-                    text,
-                    highlight_ranges,
-                    score,
-                    internal_annotations,
-                    fields,
-                }
+                let url = internal_annotations
+                    .iter()
+                    .find_map(|annotation| match annotation {
+                        InternalWordAnnotation::UrlSuffix(suffix) => {
+                            Some(format!("{}{}", entry.url, suffix))
+                        }
+                        InternalWordAnnotation::ItemStart | InternalWordAnnotation::Code => None,
+                    });
+
+                (
+                    minimum_word_index,
+                    Excerpt {
+                        text,
+                        highlight_ranges,
+                        score,
+                        internal_annotations,
+                        fields,
+                        trimmed_start: false,
+                        trimmed_end: false,
+                        sentence_start,
+                        sentence_end,
+                        url,
+                        field: ExcerptField::Body,
+                    },
+                )
             })
             .collect();
 
-        excerpts.sort_by_key(|e| -(e.score as i16));
-        excerpts.truncate(data.config.excerpts_per_result as usize);
+        // The result is scored by its best excerpts, whichever ones are
+        // shown, so the excerpt strategy doesn't change the result order.
+        let mut best_excerpt_scores: Vec<usize> =
+            excerpts.iter().map(|(_, excerpt)| excerpt.score).collect();
+        best_excerpt_scores.extend(bare_match_score);
+        best_excerpt_scores.sort_by_key(|&score| Reverse(score));
+        best_excerpt_scores.truncate(data.config.excerpts_per_result as usize);
+
+        let mut excerpts = select_excerpts(
+            excerpts,
+            data.config.excerpt_strategy,
+            data.config.excerpts_per_result as usize,
+            split_contents.len(),
+        );
+
+        if let Some(max_chars) = data.config.excerpt_max_chars {
+            for excerpt in &mut excerpts {
+                trim_excerpt(excerpt, max_chars as usize);
+            }
+        }
 
         let split_title: Vec<&str> = entry
             .title
@@ -144,28 +270,99 @@ impl From<EntryAndIntermediateExcerpts> for Result {
             .filter(|&ie| ie.source == WordListSource::Title)
             .map(|ie| {
                 let space_offset = if ie.word_index == 0 { 0 } else { 1 };
-                let beginning = split_title[0..ie.word_index].join(" ").len() + space_offset;
+                let match_offset = split_title[ie.word_index]
+                    .char_indices()
+                    .nth(ie.match_offset)
+                    .map_or(0, |(offset, _)| offset);
+                let beginning =
+                    split_title[0..ie.word_index].join(" ").len() + space_offset + match_offset;
+                let length = match spelled_out_word_count(&split_title[ie.word_index..], &ie.query)
+                {
+                    Some(count) => split_title[ie.word_index..ie.word_index + count]
+                        .join(" ")
+                        .len(),
+                    None => ie.query.len(),
+                };
                 HighlightRange {
                     beginning,
-                    end: beginning + ie.query.len(),
+                    end: beginning + length,
                 }
             })
             .collect();
 
         title_highlight_ranges.sort();
 
-        let title_boost_modifier = title_highlight_ranges.len()
+        // A result that only matched outside its contents shows where it
+        // matched, rather than contents with nothing highlighted.
+        if !contents_matched {
+            excerpts = field_excerpts(
+                &entry.title,
+                &data.intermediate_excerpts,
+                &title_highlight_ranges,
+                data.config.excerpts_per_result as usize,
+            );
+        }
+
+        // Matches near the start of the title count for more than those
+        // near its end, unless `title_position_decay` is 1.
+        let title_match_weight: f64 = data
+            .intermediate_excerpts
+            .iter()
+            .filter(|&ie| ie.source == WordListSource::Title)
+            .map(|ie| {
+                title_position_weight(
+                    ie.word_index,
+                    data.config.title_position_decay,
+                    data.config.title_position_decay_curve,
+                )
+            })
+            .sum();
+        let title_boost_modifier = (title_match_weight
             * match data.config.title_boost {
-                TitleBoost::Minimal => 25,
-                TitleBoost::Moderate => 75,
-                TitleBoost::Large => 150,
-                TitleBoost::Ridiculous => 5000,
-            };
+                TitleBoost::Minimal => 25.0,
+                TitleBoost::Moderate => 75.0,
+                TitleBoost::Large => 150.0,
+                TitleBoost::Ridiculous => 5000.0,
+            })
+        .round() as usize;
+
+        // Matches against the document's URL path are only worth a small,
+        // fixed amount, so they can't outweigh matches in the contents.
+        let url_match_modifier = data
+            .intermediate_excerpts
+            .iter()
+            .filter(|&ie| ie.source == WordListSource::Url)
+            .map(|ie| ie.word_index)
+            .collect::<HashSet<usize>>()
+            .len()
+            * URL_MATCH_SCORE;
+
+        // Keywords are chosen by the document's author, so they're worth
+        // more than URL matches, but still less than title matches.
+        let keyword_match_modifier = data
+            .intermediate_excerpts
+            .iter()
+            .filter(|&ie| ie.source == WordListSource::Keywords)
+            .map(|ie| ie.word_index)
+            .collect::<HashSet<usize>>()
+            .len()
+            * KEYWORD_MATCH_SCORE;
+
+        // A summary describes the whole document, so matching it is worth
+        // more than a keyword, but less than the title at its default boost.
+        let summary_match_modifier = data
+            .intermediate_excerpts
+            .iter()
+            .filter(|&ie| ie.source == WordListSource::Summary)
+            .map(|ie| ie.word_index)
+            .collect::<HashSet<usize>>()
+            .len()
+            * SUMMARY_MATCH_SCORE;
 
         // Sort each result by a sum of an exponental backoff of its excerpts' scores.
         // This more evenly weights a single high score excerpt vs multiple low score excerpts.
         let result_score = {
-            let mut sorted_excerpt_scores: Vec<usize> = excerpts.iter().map(|e| e.score).collect();
+            let mut sorted_excerpt_scores = best_excerpt_scores;
             sorted_excerpt_scores.sort();
 
             let sum: usize = sorted_excerpt_scores
@@ -179,22 +376,375 @@ impl From<EntryAndIntermediateExcerpts> for Result {
                 })
                 .sum();
 
-            sum + title_boost_modifier
+            let word_count = entry.word_count.unwrap_or(split_contents.len());
+            let sum = scale_for_length(sum, word_count, &data.config);
+
+            scale_by_multiplier(
+                sum + title_boost_modifier
+                    + url_match_modifier
+                    + keyword_match_modifier
+                    + summary_match_modifier,
+                entry.score_multiplier,
+            )
         };
 
+        let mut output_entry = crate::Entry::from(entry);
+        output_entry.thumbnail_url = output_entry
+            .thumbnail_url
+            .map(|thumbnail_url| with_url_prefix(&data.config.url_prefix, &thumbnail_url));
+        output_entry.reading_time_minutes =
+            reading_time_minutes(output_entry.word_count, data.config.reading_time_wpm);
+
+        let url = excerpts
+            .iter()
+            .min_by_key(|excerpt| Reverse(excerpt.score))
+            .and_then(|excerpt| excerpt.url.clone());
+
         Result {
-            entry: crate::Entry::from(entry),
+            entry: output_entry,
             excerpts,
             title_highlight_ranges,
             score: result_score,
+            merged_entry_count: None,
+            url,
+        }
+    }
+}
+
+/**
+ * Estimates how many minutes it takes to read `word_count` words at `wpm`
+ * words per minute, rounding up so that short documents take a minute
+ * rather than none.
+ */
+fn reading_time_minutes(word_count: Option<usize>, wpm: u16) -> Option<usize> {
+    match (word_count, wpm) {
+        (_, 0) | (None, _) | (Some(0), _) => None,
+        (Some(word_count), wpm) => {
+            let wpm = wpm as usize;
+            Some(word_count / wpm + usize::from(word_count % wpm != 0))
+        }
+    }
+}
+
+/**
+ * Scales the score of an entry's excerpts by how long the entry is compared
+ * to the average entry, as BM25 does: by `1 / (1 - b + b * length / average)`,
+ * where `b` is `length_normalization`. Indexes built before the average was
+ * stored, and entries with no words, aren't scaled.
+ */
+fn scale_for_length(score: usize, word_count: usize, config: &PassthroughConfig) -> usize {
+    let b = config.length_normalization;
+    if b <= 0.0 || config.average_word_count <= 0.0 || word_count == 0 {
+        return score;
+    }
+
+    let relative_length = word_count as f64 / config.average_word_count;
+    (score as f64 / (1.0 - b + b * relative_length)) as usize
+}
+
+/**
+ * If `words` start with the letters of `query` spelled out one per word, like
+ * `A W S` for `aws`, returns how many words that is. A query matches there
+ * when the index was built to merge spaced-out letters, and the highlight
+ * should cover every letter.
+ */
+fn spelled_out_word_count<S: AsRef<str>>(words: &[S], query: &str) -> Option<usize> {
+    let letter_count = query.chars().count();
+    if letter_count < 2 || words.len() < letter_count {
+        return None;
+    }
+
+    let spells_query = words.iter().zip(query.chars()).all(|(word, letter)| {
+        let mut chars = word
+            .as_ref()
+            .trim_matches(|c: char| !c.is_alphanumeric())
+            .chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => c.to_lowercase().eq(letter.to_lowercase()),
+            _ => false,
+        }
+    });
+
+    if spells_query {
+        Some(letter_count)
+    } else {
+        None
+    }
+}
+
+/**
+ * An excerpt for each part of the document besides its contents that the
+ * query matched, like `Matched keyword: kubernetes`, best matches first.
+ * The title is shown whole; for the other parts, which aren't stored with
+ * the entry, the words that matched are listed.
+ */
+fn field_excerpts(
+    title: &str,
+    intermediate_excerpts: &[IntermediateExcerpt],
+    title_highlight_ranges: &[HighlightRange],
+    max_excerpts: usize,
+) -> Vec<Excerpt> {
+    let fields = [
+        (WordListSource::Title, ExcerptField::Title, "Matched title"),
+        (
+            WordListSource::Summary,
+            ExcerptField::Summary,
+            "Matched summary",
+        ),
+        (
+            WordListSource::Keywords,
+            ExcerptField::Keywords,
+            "Matched keyword",
+        ),
+        (WordListSource::Url, ExcerptField::Url, "Matched URL"),
+    ];
+
+    let mut excerpts: Vec<Excerpt> = fields
+        .iter()
+        .filter_map(|&(source, field, label)| {
+            // The best match of each word, in the order the words appear.
+            let mut ies: Vec<&IntermediateExcerpt> = intermediate_excerpts
+                .iter()
+                .filter(|ie| ie.source == source)
+                .collect();
+            ies.sort_by_key(|ie| (ie.word_index, Reverse(ie.score)));
+            ies.dedup_by_key(|ie| ie.word_index);
+            if ies.is_empty() {
+                return None;
+            }
+
+            let mut text = format!("{}: ", label);
+            let mut highlight_ranges = vec![];
+            if source == WordListSource::Title {
+                let offset = text.chars().count();
+                text.push_str(title);
+                highlight_ranges.extend(title_highlight_ranges.iter().map(|range| {
+                    HighlightRange {
+                        beginning: range.beginning + offset,
+                        end: range.end + offset,
+                    }
+                }));
+            } else {
+                let mut words: Vec<&str> = vec![];
+                for ie in &ies {
+                    if !words.contains(&ie.query.as_str()) {
+                        words.push(&ie.query);
+                    }
+                }
+
+                for (index, word) in words.iter().enumerate() {
+                    if index > 0 {
+                        text.push_str(", ");
+                    }
+                    let beginning = text.chars().count();
+                    text.push_str(word);
+                    highlight_ranges.push(HighlightRange {
+                        beginning,
+                        end: beginning + word.chars().count(),
+                    });
+                }
+            }
+
+            Some(Excerpt {
+                text,
+                highlight_ranges,
+                score: ies.iter().map(|ie| ie.score as usize).sum(),
+                internal_annotations: vec![],
+                fields: HashMap::new(),
+                trimmed_start: false,
+                trimmed_end: false,
+                sentence_start: false,
+                sentence_end: false,
+                url: None,
+                field,
+            })
+        })
+        .collect();
+
+    excerpts.sort_by_key(|excerpt| Reverse(excerpt.score));
+    excerpts.truncate(max_excerpts);
+    excerpts
+}
+
+/**
+ * How many characters into a word of code the identifier, or part of one,
+ * that a query matched starts, like 3 for `user` in `getUserName`.
+ */
+fn code_match_offset(word: &str, query: &str) -> usize {
+    let lowercase_word = word.to_lowercase();
+    lowercase_word
+        .find(query)
+        .map_or(0, |offset| lowercase_word[..offset].chars().count())
+}
+
+/**
+ * The most distinct query words matched within any `window` consecutive
+ * words of the same item, given intermediate excerpts sorted by word index
+ * and the item each word index is in.
+ */
+fn most_query_words_within(
+    ies: &[&IntermediateExcerpt],
+    window: usize,
+    item_of: impl Fn(usize) -> usize,
+) -> usize {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut most = 0;
+    let mut start = 0;
+
+    for ie in ies {
+        *counts.entry(ie.query.as_str()).or_default() += 1;
+
+        while ie.word_index - ies[start].word_index >= window.max(1)
+            || item_of(ie.word_index) != item_of(ies[start].word_index)
+        {
+            let query = ies[start].query.as_str();
+            if let Some(count) = counts.get_mut(query) {
+                *count -= 1;
+                if *count == 0 {
+                    counts.remove(query);
+                }
+            }
+            start += 1;
+        }
+
+        most = most.max(counts.len());
+    }
+
+    most
+}
+
+/**
+ * Picks up to `count` excerpts, given alongside the index of the first word
+ * they show and in document order.
+ */
+#[allow(clippy::cast_possible_wrap, clippy::cast_possible_truncation)]
+fn select_excerpts(
+    mut excerpts: Vec<(usize, Excerpt)>,
+    strategy: ExcerptStrategy,
+    count: usize,
+    word_count: usize,
+) -> Vec<Excerpt> {
+    match strategy {
+        ExcerptStrategy::First => excerpts.truncate(count),
+
+        ExcerptStrategy::Best => {
+            excerpts.sort_by_key(|(_, excerpt)| -(excerpt.score as i16));
+            excerpts.truncate(count);
+        }
+
+        ExcerptStrategy::Spread => {
+            excerpts.sort_by_key(|(_, excerpt)| -(excerpt.score as i16));
+
+            let mut used_parts = HashSet::new();
+            let (mut spread, remaining): (Vec<_>, Vec<_>) =
+                excerpts.into_iter().partition(|(word_index, _)| {
+                    used_parts.insert(word_index * count / word_count.max(1))
+                });
+
+            spread.extend(remaining);
+            spread.truncate(count);
+            spread.sort_by_key(|(word_index, _)| *word_index);
+            excerpts = spread;
+        }
+    }
+
+    excerpts.into_iter().map(|(_, excerpt)| excerpt).collect()
+}
+
+/**
+ * Drops whole words from either end of the excerpt until it's at most
+ * `max_chars` characters long, widening outwards from the highlighted words
+ * so they're kept. Words are never split, so neither are grapheme clusters,
+ * and "start" and "end" are in reading order for right-to-left text too.
+ *
+ * If the highlighted words alone are too long, words are dropped from the
+ * end, but the first highlighted word is always kept.
+ */
+fn trim_excerpt(excerpt: &mut Excerpt, max_chars: usize) {
+    if excerpt.text.chars().count() <= max_chars {
+        return;
+    }
+
+    // The text is made of words joined with single spaces.
+    let mut word_offsets: Vec<(usize, usize)> = vec![];
+    let mut offset = 0;
+    for word in excerpt.text.split(' ') {
+        let length = word.chars().count();
+        word_offsets.push((offset, length));
+        offset += length + 1;
+    }
+
+    let word_containing = |char_offset: usize| {
+        word_offsets
+            .iter()
+            .rposition(|&(start, _)| start <= char_offset)
+            .unwrap_or(0)
+    };
+
+    let highlighted_words: Vec<usize> = excerpt
+        .highlight_ranges
+        .iter()
+        .map(|range| word_containing(range.beginning))
+        .collect();
+
+    let first = highlighted_words.iter().copied().min().unwrap_or(0);
+    let last = highlighted_words.iter().copied().max().unwrap_or(first);
+
+    let window_length = |first: usize, last: usize| {
+        let (start, _) = word_offsets[first];
+        let (end_start, end_length) = word_offsets[last];
+        end_start + end_length - start
+    };
+
+    let (mut first, mut last) = (first, last);
+    while last > first && window_length(first, last) > max_chars {
+        last -= 1;
+    }
+
+    // Grow the window a word at a time, alternating between its ends.
+    let mut grow_end = true;
+    loop {
+        let can_grow_end =
+            last + 1 < word_offsets.len() && window_length(first, last + 1) <= max_chars;
+        let can_grow_start = first > 0 && window_length(first - 1, last) <= max_chars;
+
+        match (can_grow_start, can_grow_end) {
+            (false, false) => break,
+            (true, true) if grow_end => last += 1,
+            (true, true) => first -= 1,
+            (false, true) => last += 1,
+            (true, false) => first -= 1,
         }
+
+        grow_end = !grow_end;
     }
+
+    let (start, _) = word_offsets[first];
+    let end = start + window_length(first, last);
+
+    excerpt.text = excerpt.text.chars().skip(start).take(end - start).collect();
+    excerpt.highlight_ranges = excerpt
+        .highlight_ranges
+        .iter()
+        .zip(highlighted_words)
+        .filter(|(_, word)| (first..=last).contains(word))
+        .map(|(range, _)| HighlightRange {
+            beginning: range.beginning - start,
+            end: (range.end - start).min(end - start),
+        })
+        .collect();
+    excerpt.trimmed_start = first > 0;
+    excerpt.trimmed_end = last + 1 < word_offsets.len();
+    excerpt.sentence_start &= !excerpt.trimmed_start;
+    excerpt.sentence_end &= !excerpt.trimmed_end;
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::index_v3::Entry;
+    #[cfg(feature = "build-v3")]
+    use crate::{config::*, index_v3::build::build};
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -205,11 +755,16 @@ mod tests {
                 title: "The quick brown fox jumps over the lazy dog".to_string(),
                 url: String::default(),
                 fields: HashMap::default(),
+                score_multiplier: None,
+                word_count: None,
+                text: None,
+                item_starts: vec![],
             },
             config: PassthroughConfig::default(),
             intermediate_excerpts: vec![
                 IntermediateExcerpt {
                     query: "over".to_string(),
+                    match_offset: 0,
                     entry_index: 0,
                     score: 128,
                     source: WordListSource::Title,
@@ -219,6 +774,7 @@ mod tests {
                 },
                 IntermediateExcerpt {
                     query: "brown".to_string(),
+                    match_offset: 0,
                     entry_index: 0,
                     score: 128,
                     source: WordListSource::Title,
@@ -235,10 +791,15 @@ mod tests {
                 title: "The quick brown fox jumps over the lazy dog".to_string(),
                 url: String::default(),
                 fields: HashMap::default(),
+                score_multiplier: None,
+                word_count: None,
+                text: None,
+                item_starts: vec![],
             },
             config: PassthroughConfig::default(),
             intermediate_excerpts: vec![IntermediateExcerpt {
                 query: "over".to_string(),
+                match_offset: 0,
                 entry_index: 0,
                 score: 128,
                 source: WordListSource::Title,
@@ -263,11 +824,16 @@ mod tests {
                 title: "The quick brown fox jumps over the lazy dog".to_string(),
                 url: String::default(),
                 fields: HashMap::default(),
+                score_multiplier: None,
+                word_count: None,
+                text: None,
+                item_starts: vec![],
             },
             config: PassthroughConfig::default(),
             intermediate_excerpts: vec![
                 IntermediateExcerpt {
                     query: "over".to_string(),
+                    match_offset: 0,
                     entry_index: 0,
                     score: 128,
                     source: WordListSource::Title,
@@ -277,6 +843,7 @@ mod tests {
                 },
                 IntermediateExcerpt {
                     query: "brown".to_string(),
+                    match_offset: 0,
                     entry_index: 0,
                     score: 128,
                     source: WordListSource::Title,
@@ -305,11 +872,16 @@ mod tests {
                 title: "Introduction".to_string(),
                 url: String::default(),
                 fields: HashMap::default(),
+                score_multiplier: None,
+                word_count: None,
+                text: None,
+                item_starts: vec![],
             },
             config: PassthroughConfig::default(),
             intermediate_excerpts: vec![
                 IntermediateExcerpt {
                     query: "unequivocal".to_string(),
+                    match_offset: 0,
                     entry_index: 0,
                     score: 128,
                     source: WordListSource::Contents,
@@ -319,6 +891,7 @@ mod tests {
                 },
                 IntermediateExcerpt {
                     query: "\u{2018}surprisingly\u{2019}".to_string(),
+                    match_offset: 0,
                     entry_index: 0,
                     score: 128,
                     source: WordListSource::Contents,
@@ -356,6 +929,318 @@ mod tests {
         );
     }
 
+    /// A 40-word document, `w0 w1 ... w39`, with the query matching the
+    /// given word indexes.
+    fn result_with_matches(
+        word_indexes: &[usize],
+        excerpt_strategy: ExcerptStrategy,
+        excerpts_per_result: u8,
+    ) -> Result {
+        let contents: Vec<String> = (0..40).map(|index| format!("w{}", index)).collect();
+
+        Result::from(EntryAndIntermediateExcerpts {
+            entry: Entry {
+                contents: contents.join(" "),
+                title: "Title".to_string(),
+                url: String::default(),
+                fields: HashMap::default(),
+                score_multiplier: None,
+                word_count: None,
+                text: None,
+                item_starts: vec![],
+            },
+            config: PassthroughConfig {
+                excerpt_strategy,
+                excerpts_per_result,
+                ..PassthroughConfig::default()
+            },
+            intermediate_excerpts: word_indexes
+                .iter()
+                .map(|&word_index| IntermediateExcerpt {
+                    query: format!("w{}", word_index),
+                    match_offset: 0,
+                    entry_index: 0,
+                    score: 128,
+                    source: WordListSource::Contents,
+                    word_index,
+                    internal_annotations: Vec::default(),
+                    fields: HashMap::default(),
+                })
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn best_strategy_prefers_dense_excerpt_over_earlier_sparse_one() {
+        let matches = [1, 30, 31, 32];
+
+        let first = result_with_matches(&matches, ExcerptStrategy::First, 1);
+        assert!(first.excerpts[0].text.contains("w1 "));
+        assert!(!first.excerpts[0].text.contains("w31"));
+
+        let best = result_with_matches(&matches, ExcerptStrategy::Best, 1);
+        assert!(best.excerpts[0].text.contains("w31"));
+        assert_eq!(best.excerpts[0].highlight_ranges.len(), 3);
+
+        assert_eq!(first.score, best.score);
+    }
+
+    #[test]
+    fn spread_strategy_picks_excerpts_from_different_parts() {
+        // Two dense clusters in the first half, one lone match in the second
+        let matches = [2, 3, 4, 10, 11, 12, 30];
+
+        let best = result_with_matches(&matches, ExcerptStrategy::Best, 2);
+        assert!(best.excerpts.iter().all(|e| !e.text.contains("w30")));
+
+        let spread = result_with_matches(&matches, ExcerptStrategy::Spread, 2);
+        assert_eq!(spread.excerpts.len(), 2);
+        assert!(spread.excerpts[0].text.contains("w3 "));
+        assert!(spread.excerpts[1].text.contains("w30"));
+    }
+
+    #[test]
+    fn proximity_boost_prefers_excerpts_with_several_query_words() {
+        let matches = [(2, "apples"), (4, "apples"), (30, "apples"), (33, "pears")];
+        let mut contents: Vec<String> = (0..40).map(|index| format!("w{}", index)).collect();
+        for &(word_index, query) in &matches {
+            contents[word_index] = query.to_string();
+        }
+
+        let result = |proximity_boost| {
+            Result::from(EntryAndIntermediateExcerpts {
+                entry: Entry {
+                    contents: contents.join(" "),
+                    title: "Title".to_string(),
+                    url: String::default(),
+                    fields: HashMap::default(),
+                    score_multiplier: None,
+                    word_count: None,
+                    text: None,
+                    item_starts: vec![],
+                },
+                config: PassthroughConfig {
+                    excerpts_per_result: 1,
+                    proximity_boost,
+                    ..PassthroughConfig::default()
+                },
+                intermediate_excerpts: matches
+                    .iter()
+                    .map(|&(word_index, query)| IntermediateExcerpt {
+                        query: query.to_string(),
+                        match_offset: 0,
+                        entry_index: 0,
+                        score: 128,
+                        source: WordListSource::Contents,
+                        word_index,
+                        internal_annotations: Vec::default(),
+                        fields: HashMap::default(),
+                    })
+                    .collect(),
+            })
+        };
+
+        let unboosted = result(0.0);
+        assert!(unboosted.excerpts[0].text.contains("apples w3 apples"));
+
+        // Both excerpts have two matches worth 128 each, less the characters
+        // between them, but only the second has two different query words.
+        let boosted = result(0.5);
+        assert!(boosted.excerpts[0].text.contains("apples w31 w32 pears"));
+        assert_eq!(unboosted.excerpts[0].score, 256 - 4);
+        assert_eq!(boosted.excerpts[0].score, 256 - 9 + 64);
+        assert!(boosted.score > unboosted.score);
+    }
+
+    #[test]
+    fn query_words_are_only_counted_together_within_the_window() {
+        let ie = |query: &str, word_index| IntermediateExcerpt {
+            query: query.to_string(),
+            match_offset: 0,
+            entry_index: 0,
+            score: 128,
+            source: WordListSource::Contents,
+            word_index,
+            internal_annotations: Vec::default(),
+            fields: HashMap::default(),
+        };
+        let ies = [ie("a", 0), ie("b", 3), ie("a", 5), ie("c", 9)];
+        let ies: Vec<&IntermediateExcerpt> = ies.iter().collect();
+
+        let one_item = |_| 0;
+        assert_eq!(most_query_words_within(&ies, 1, one_item), 1);
+        assert_eq!(most_query_words_within(&ies, 4, one_item), 2);
+        assert_eq!(most_query_words_within(&ies, 6, one_item), 2);
+        assert_eq!(most_query_words_within(&ies, 7, one_item), 3);
+        assert_eq!(most_query_words_within(&[], 5, one_item), 0);
+
+        // With an item starting at word 4, the `b` at 3 and the `a` at 5 are
+        // never together, but the `a` at 0 and the `b` at 3 still are.
+        let two_items = |word_index| usize::from(word_index >= 4);
+        assert_eq!(most_query_words_within(&ies, 3, one_item), 2);
+        assert_eq!(most_query_words_within(&ies, 3, two_items), 1);
+        assert_eq!(most_query_words_within(&ies, 4, two_items), 2);
+        assert_eq!(most_query_words_within(&ies, 7, two_items), 2);
+    }
+
+    #[test]
+    fn trimmed_excerpt_keeps_highlights_and_whole_words() {
+        let mut excerpt = result_with_matches(&[20], ExcerptStrategy::Best, 1).excerpts[0].clone();
+        assert_eq!(excerpt.text.chars().count(), 63);
+
+        trim_excerpt(&mut excerpt, 15);
+
+        assert_eq!(excerpt.text, "w19 w20 w21 w22");
+        assert_eq!(
+            excerpt.highlight_ranges,
+            vec![HighlightRange {
+                beginning: 4,
+                end: 7
+            }]
+        );
+        assert!(excerpt.trimmed_start);
+        assert!(excerpt.trimmed_end);
+    }
+
+    #[test]
+    fn excerpts_snap_to_sentences_but_not_abbreviations() {
+        let contents =
+            "Our clinic opened in May. Dr. Jones treats rare conditions, e.g. Fabry disease, \
+            and sees patients weekly. Appointments are booked online.";
+        let matched_word_index = contents
+            .split_whitespace()
+            .position(|word| word == "rare")
+            .unwrap();
+
+        let excerpt = |excerpt_sentence_tolerance| {
+            Result::from(EntryAndIntermediateExcerpts {
+                entry: Entry {
+                    contents: contents.to_string(),
+                    title: "Clinic".to_string(),
+                    url: String::default(),
+                    fields: HashMap::default(),
+                    score_multiplier: None,
+                    word_count: None,
+                    text: None,
+                    item_starts: vec![],
+                },
+                config: PassthroughConfig {
+                    excerpt_buffer: 4,
+                    excerpt_sentence_tolerance,
+                    ..PassthroughConfig::default()
+                },
+                intermediate_excerpts: vec![IntermediateExcerpt {
+                    query: "rare".to_string(),
+                    match_offset: 0,
+                    entry_index: 0,
+                    score: 128,
+                    source: WordListSource::Contents,
+                    word_index: matched_word_index,
+                    internal_annotations: Vec::default(),
+                    fields: HashMap::default(),
+                }],
+            })
+            .excerpts
+            .remove(0)
+        };
+
+        let raw = excerpt(None);
+        assert_eq!(
+            raw.text,
+            "May. Dr. Jones treats rare conditions, e.g. Fabry"
+        );
+        assert!(!raw.sentence_start);
+        assert!(!raw.sentence_end);
+
+        let snapped = excerpt(Some(5));
+        assert_eq!(
+            snapped.text,
+            "Dr. Jones treats rare conditions, e.g. Fabry disease, and sees patients weekly."
+        );
+        assert!(snapped.sentence_start);
+        assert!(snapped.sentence_end);
+        assert_eq!(
+            snapped.highlight_ranges,
+            vec![HighlightRange {
+                beginning: 17,
+                end: 21
+            }]
+        );
+    }
+
+    fn excerpt_with_highlight(text: &str, beginning: usize, end: usize) -> Excerpt {
+        Excerpt {
+            text: text.to_string(),
+            highlight_ranges: vec![HighlightRange { beginning, end }],
+            score: 128,
+            internal_annotations: vec![],
+            fields: HashMap::default(),
+            trimmed_start: false,
+            trimmed_end: false,
+            sentence_start: false,
+            sentence_end: false,
+            url: None,
+            field: ExcerptField::Body,
+        }
+    }
+
+    #[test]
+    fn trimming_never_splits_grapheme_clusters() {
+        // Each word ends with a combining accent.
+        let mut excerpt =
+            excerpt_with_highlight("e\u{301}te\u{301} cafe\u{301} nai\u{308}ve", 6, 11);
+
+        trim_excerpt(&mut excerpt, 6);
+
+        assert_eq!(excerpt.text, "cafe\u{301}");
+        assert_eq!(
+            excerpt.highlight_ranges,
+            vec![HighlightRange {
+                beginning: 0,
+                end: 5
+            }]
+        );
+        assert!(excerpt.trimmed_start);
+        assert!(excerpt.trimmed_end);
+    }
+
+    #[test]
+    fn right_to_left_excerpts_are_trimmed_in_reading_order() {
+        let mut excerpt = excerpt_with_highlight(
+            "\u{5e9}\u{5dc}\u{5d5}\u{5dd} \u{5e2}\u{5d5}\u{5dc}\u{5dd} \u{5d9}\u{5e4}\u{5d4}",
+            5,
+            9,
+        );
+
+        trim_excerpt(&mut excerpt, 9);
+
+        assert_eq!(
+            excerpt.text,
+            "\u{5e2}\u{5d5}\u{5dc}\u{5dd} \u{5d9}\u{5e4}\u{5d4}"
+        );
+        assert!(excerpt.trimmed_start);
+        assert!(!excerpt.trimmed_end);
+    }
+
+    #[test]
+    fn short_excerpts_are_not_trimmed() {
+        let mut excerpt = excerpt_with_highlight("a short excerpt", 2, 7);
+        let untrimmed = excerpt.clone();
+
+        trim_excerpt(&mut excerpt, 40);
+
+        assert_eq!(excerpt, untrimmed);
+    }
+
+    #[test]
+    fn reading_time_rounds_up_and_can_be_disabled() {
+        assert_eq!(reading_time_minutes(Some(450), 200), Some(3));
+        assert_eq!(reading_time_minutes(Some(400), 200), Some(2));
+        assert_eq!(reading_time_minutes(Some(12), 200), Some(1));
+        assert_eq!(reading_time_minutes(Some(450), 0), None);
+        assert_eq!(reading_time_minutes(None, 200), None);
+    }
+
     #[test]
     fn title_highlighting_works_when_title_has_no_spaces() {
         let entry_and_intermediate_excerpts = EntryAndIntermediateExcerpts {
@@ -364,10 +1249,15 @@ mod tests {
                 title: "api-methods-animate".to_string(),
                 url: String::default(),
                 fields: HashMap::default(),
+                score_multiplier: None,
+                word_count: None,
+                text: None,
+                item_starts: vec![],
             },
             config: PassthroughConfig::default(),
             intermediate_excerpts: vec![IntermediateExcerpt {
                 query: "anim".to_string(),
+                match_offset: 0,
                 entry_index: 0,
                 score: 128,
                 source: WordListSource::Title,
@@ -381,4 +1271,230 @@ mod tests {
 
         dbg!(output_result);
     }
+
+    #[cfg(feature = "build-v3")]
+    fn plaintext_file(title: &str, contents: &str) -> File {
+        File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn length_normalization_ranks_focused_pages_above_long_ones() {
+        let filler =
+            "Unrelated notes about the weather, the garden, and the neighbours. ".repeat(8);
+        let rambling = format!(
+            "{filler} Ownership comes up briefly. {filler} Ownership again. {filler} More ownership. {filler} Ownership. {filler} A final word on ownership. {filler}",
+            filler = filler
+        );
+        let config = |length_normalization| Config {
+            input: InputConfig {
+                files: vec![
+                    plaintext_file("Rambling", &rambling),
+                    plaintext_file("Focused", "Ownership explained: the rules of ownership."),
+                ],
+                ..Default::default()
+            },
+            output: OutputConfig {
+                length_normalization,
+                ..Default::default()
+            },
+        };
+
+        let titles = |length_normalization| {
+            let index = build(&config(length_normalization)).unwrap().index;
+            crate::index_v3::search(&index, "ownership")
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| result.entry.title)
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(titles(0.0), vec!["Rambling", "Focused"]);
+        assert_eq!(titles(0.75), vec!["Focused", "Rambling"]);
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn files_without_excerpts_are_matched_but_not_excerpted() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    File {
+                        generate_excerpts: false,
+                        ..plaintext_file(
+                            "Glossary",
+                            "Aardvark: a burrowing mammal. Abacus: a counting frame.",
+                        )
+                    },
+                    plaintext_file("Zoo", "The aardvark enclosure opens at noon."),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let index = build(&config).unwrap().index;
+        assert_eq!(index.entries[0].contents, "");
+        // The entry without stored text still counts towards the average.
+        assert_eq!(index.config.average_word_count, 7.0);
+
+        let aardvark = &index.containers["aardvark"];
+        assert!(aardvark.results[&0].excerpts.is_empty());
+        assert!(!aardvark.results[&1].excerpts.is_empty());
+
+        let output = crate::index_v3::search(&index, "aardvark").unwrap();
+        let mut excerpt_counts: Vec<(String, usize)> = output
+            .results
+            .into_iter()
+            .map(|result| (result.entry.title, result.excerpts.len()))
+            .collect();
+        excerpt_counts.sort();
+
+        assert_eq!(
+            excerpt_counts,
+            vec![("Glossary".to_string(), 0), ("Zoo".to_string(), 1)]
+        );
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn proximity_boost_ranks_nearby_query_words_higher() {
+        let filler =
+            "The weather turned cold and the leaves fell from every branch in the valley below. "
+                .repeat(2);
+        let config = |proximity_boost| {
+            Config {
+            input: InputConfig {
+                files: vec![
+                    plaintext_file(
+                        "Far",
+                        &format!(
+                            "Apples are picked in autumn. {} Pears come later. {} More apples. {} More pears.",
+                            filler, filler, filler
+                        ),
+                    ),
+                    plaintext_file(
+                        "Near",
+                        &format!(
+                            "Apples and pears are picked in autumn. {} Then winter.",
+                            filler
+                        ),
+                    ),
+                ],
+                ..Default::default()
+            },
+            output: OutputConfig {
+                proximity_boost,
+                ..Default::default()
+            },
+        }
+        };
+
+        let titles = |proximity_boost| {
+            let index = build(&config(proximity_boost)).unwrap().index;
+            crate::index_v3::search(&index, "apples pears")
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| result.entry.title)
+                .collect::<Vec<String>>()
+        };
+
+        // Mentioning each word twice, far apart, outscores mentioning both
+        // once, unless having both words close together counts for more.
+        assert_eq!(titles(0.0), vec!["Far", "Near"]);
+        assert_eq!(titles(2.0), vec!["Near", "Far"]);
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn results_that_only_match_outside_their_contents_show_where() {
+        let index = build(&Config {
+            input: InputConfig {
+                files: vec![
+                    File {
+                        summary: Some("Rolling out containers to a cluster".to_string()),
+                        ..plaintext_file(
+                            "Deploying to Kubernetes",
+                            "one two three four five six seven eight nine ten eleven twelve",
+                        )
+                    },
+                    plaintext_file(
+                        "Cluster notes",
+                        "Our cluster runs kubernetes on three nodes",
+                    ),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap()
+        .index;
+        let excerpts = |query: &str, title: &str| -> Vec<(ExcerptField, String, Vec<String>)> {
+            let output = crate::index_v3::search(&index, query).unwrap();
+            let result = output
+                .results
+                .iter()
+                .find(|result| result.entry.title == title)
+                .unwrap();
+            result
+                .excerpts
+                .iter()
+                .map(|excerpt| {
+                    let highlighted = excerpt
+                        .highlight_ranges
+                        .iter()
+                        .map(|range| {
+                            excerpt
+                                .text
+                                .chars()
+                                .skip(range.beginning)
+                                .take(range.end - range.beginning)
+                                .collect()
+                        })
+                        .collect();
+                    (excerpt.field, excerpt.text.clone(), highlighted)
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            excerpts("kubernetes", "Deploying to Kubernetes"),
+            vec![(
+                ExcerptField::Title,
+                "Matched title: Deploying to Kubernetes".to_string(),
+                vec!["Kubernetes".to_string()]
+            )]
+        );
+        assert_eq!(
+            excerpts("containers", "Deploying to Kubernetes"),
+            vec![(
+                ExcerptField::Summary,
+                "Matched summary: containers".to_string(),
+                vec!["containers".to_string()]
+            )]
+        );
+
+        let body_excerpts = excerpts("kubernetes", "Cluster notes");
+        assert_eq!(body_excerpts.len(), 1);
+        assert_eq!(body_excerpts[0].0, ExcerptField::Body);
+        assert_eq!(body_excerpts[0].2, vec!["kubernetes".to_string()]);
+
+        let output = crate::index_v3::search(&index, "kubernetes").unwrap();
+        let json = serde_json::to_value(&output).unwrap();
+        let fields: Vec<Option<&str>> = json["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| result["excerpts"][0]["field"].as_str())
+            .collect();
+        assert!(fields.contains(&Some("title")));
+        assert!(fields.contains(&None));
+    }
 }
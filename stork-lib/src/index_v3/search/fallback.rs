@@ -0,0 +1,177 @@
+use super::query::{field_name, QueryWord};
+
+/**
+ * Shortens each query word that isn't indexed to the longest start of it
+ * that is, one character at a time, for
+ * `ZeroResultFallback::prefix_relaxation`. Each shortened word checked costs
+ * one of `max_lookups`; words are left as they are once they run out.
+ * Negated words are never shortened, so they don't exclude more documents.
+ * Returns `None` if no word was shortened.
+ */
+pub(super) fn relax_prefixes(
+    words: &[QueryWord],
+    max_lookups: u16,
+    is_indexed: impl Fn(&str) -> bool,
+) -> Option<Vec<QueryWord>> {
+    let mut lookups_left = max_lookups;
+    let mut relaxed_any = false;
+
+    let relaxed = words
+        .iter()
+        .map(|query_word| {
+            let mut word = query_word.word.clone();
+            if !query_word.negated && !is_indexed(&word) {
+                let prefix_ends: Vec<usize> = word
+                    .char_indices()
+                    .skip(1)
+                    .map(|(offset, _)| offset)
+                    .collect();
+
+                for &end in prefix_ends.iter().rev() {
+                    if lookups_left == 0 {
+                        break;
+                    }
+                    lookups_left -= 1;
+
+                    if is_indexed(&word[..end]) {
+                        word.truncate(end);
+                        relaxed_any = true;
+                        break;
+                    }
+                }
+            }
+
+            QueryWord {
+                word,
+                source: query_word.source,
+                negated: query_word.negated,
+            }
+        })
+        .collect();
+
+    relaxed_any.then(|| relaxed)
+}
+
+/**
+ * Writes query words back out as a query, for the output's `fallback`. Any
+ * `section:` the query had isn't included.
+ */
+pub(super) fn describe_query(words: &[QueryWord]) -> String {
+    words
+        .iter()
+        .map(|query_word| {
+            let word = match query_word.source {
+                Some(source) => format!("{}:{}", field_name(source), query_word.word),
+                None => query_word.word.clone(),
+            };
+            if query_word.negated {
+                format!("-{}", word)
+            } else {
+                word
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index_v3::WordListSource;
+    #[cfg(feature = "build-v3")]
+    use crate::{config::*, index_v3::build::build};
+    use pretty_assertions::assert_eq;
+
+    fn word(word: &str, source: Option<WordListSource>) -> QueryWord {
+        QueryWord {
+            word: word.to_string(),
+            source,
+            negated: false,
+        }
+    }
+
+    #[test]
+    fn unindexed_words_are_shortened_to_their_longest_indexed_prefix() {
+        let indexed = ["ins", "inst", "install", "guide"];
+        let relaxed = relax_prefixes(
+            &[
+                word("installs", None),
+                word("guide", Some(WordListSource::Title)),
+            ],
+            64,
+            |word| indexed.contains(&word),
+        );
+
+        assert_eq!(
+            relaxed,
+            Some(vec![
+                word("install", None),
+                word("guide", Some(WordListSource::Title))
+            ])
+        );
+        assert_eq!(
+            describe_query(&relaxed.unwrap()),
+            "install title:guide".to_string()
+        );
+    }
+
+    #[test]
+    fn relaxation_stops_when_lookups_run_out() {
+        let indexed = ["in"];
+        let words = [word("installs", None)];
+
+        // Shortening `installs` to `in` takes six lookups.
+        assert_eq!(
+            relax_prefixes(&words, 5, |word| indexed.contains(&word)),
+            None
+        );
+        assert_eq!(
+            relax_prefixes(&words, 6, |word| indexed.contains(&word)),
+            Some(vec![word("in", None)])
+        );
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn zero_result_queries_fall_back_to_indexed_prefixes() {
+        let config = |zero_result_fallback| Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        "Install the command line tool.".to_string(),
+                    )),
+                    title: "Setup".to_string(),
+                    filetype: Some(Filetype::PlainText),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            output: OutputConfig {
+                zero_result_fallback,
+                ..Default::default()
+            },
+        };
+
+        let without_fallback = build(&config(None)).unwrap().index;
+        let output = crate::index_v3::search(&without_fallback, "installs").unwrap();
+        assert_eq!(output.total_hit_count, 0);
+        assert_eq!(output.fallback, None);
+
+        let with_fallback = build(&config(Some(ZeroResultFallback::default())))
+            .unwrap()
+            .index;
+        let output = crate::index_v3::search(&with_fallback, "installs").unwrap();
+        assert_eq!(output.total_hit_count, 1);
+        assert_eq!(
+            output.fallback,
+            Some(crate::Fallback {
+                stage: crate::FallbackStage::PrefixRelaxation,
+                query: "install".to_string(),
+            })
+        );
+
+        // A query that finds something as typed is left alone.
+        let output = crate::index_v3::search(&with_fallback, "install").unwrap();
+        assert_eq!(output.fallback, None);
+    }
+}
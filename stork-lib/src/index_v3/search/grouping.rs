@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use crate::Result;
+
+/// The URL without its fragment or query string.
+fn url_without_fragment(url: &str) -> &str {
+    url.split(|c| c == '#' || c == '?').next().unwrap_or(url)
+}
+
+/**
+ * The directory a URL is in: everything up to the last `/` of its path,
+ * ignoring a trailing one, so `/blog/page/2` and `/blog/page/3/` are both in
+ * `/blog/page/`.
+ */
+fn url_prefix(url: &str) -> &str {
+    let url = url_without_fragment(url);
+    let trimmed = url.strip_suffix('/').unwrap_or(url);
+    trimmed.rfind('/').map_or("", |slash| &url[..=slash])
+}
+
+/**
+ * Merges the results whose URLs only differ by fragment or query string into
+ * the best-scoring one of them, which keeps its place, title, and score. Its
+ * excerpts are the best `excerpts_per_result` of all the merged results'.
+ * `results` have to be sorted from best to worst.
+ */
+pub(super) fn group_by_url_without_fragment(
+    results: Vec<Result>,
+    excerpts_per_result: usize,
+) -> Vec<Result> {
+    let mut grouped: Vec<Result> = Vec::with_capacity(results.len());
+    let mut group_indexes: HashMap<String, usize> = HashMap::new();
+
+    for result in results {
+        let url = url_without_fragment(&result.entry.url).to_string();
+        match group_indexes.get(&url) {
+            Some(&group_index) => {
+                let group = &mut grouped[group_index];
+                group.excerpts.extend(result.excerpts);
+                group.merged_entry_count = Some(group.merged_entry_count.unwrap_or(1) + 1);
+            }
+            None => {
+                group_indexes.insert(url, grouped.len());
+                grouped.push(result);
+            }
+        }
+    }
+
+    for group in grouped
+        .iter_mut()
+        .filter(|group| group.merged_entry_count.is_some())
+    {
+        group
+            .excerpts
+            .sort_by_key(|excerpt| std::cmp::Reverse(excerpt.score));
+        group.excerpts.truncate(excerpts_per_result);
+    }
+
+    grouped
+}
+
+/**
+ * Keeps only the best `max` results from each directory of URLs, so that
+ * near-duplicates like the pages of a paginated list can't crowd out every
+ * other result. `results` have to be sorted from best to worst.
+ */
+pub(super) fn limit_results_per_url_prefix(results: Vec<Result>, max: usize) -> Vec<Result> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    results
+        .into_iter()
+        .filter(|result| {
+            let count = counts
+                .entry(url_prefix(&result.entry.url).to_string())
+                .or_default();
+            *count += 1;
+            *count <= max
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    #[cfg(feature = "build-v3")]
+    use crate::{config::*, index_v3::build::build, V3Index as Index};
+    use crate::{Entry, Excerpt, ExcerptField};
+    use pretty_assertions::assert_eq;
+
+    fn result(url: &str, excerpt_scores: &[usize]) -> Result {
+        Result {
+            entry: Entry {
+                url: url.to_string(),
+                title: url.to_string(),
+                fields: HashMap::new(),
+                thumbnail_url: None,
+                word_count: None,
+                reading_time_minutes: None,
+                text: None,
+            },
+            excerpts: excerpt_scores
+                .iter()
+                .map(|&score| Excerpt {
+                    text: format!("{} {}", url, score),
+                    highlight_ranges: vec![],
+                    score,
+                    internal_annotations: vec![],
+                    fields: HashMap::new(),
+                    trimmed_start: false,
+                    trimmed_end: false,
+                    sentence_start: false,
+                    sentence_end: false,
+                    url: None,
+                    field: ExcerptField::Body,
+                })
+                .collect(),
+            title_highlight_ranges: vec![],
+            score: excerpt_scores.iter().sum(),
+            merged_entry_count: None,
+            url: None,
+        }
+    }
+
+    #[test]
+    fn url_prefixes_ignore_fragments_and_trailing_slashes() {
+        assert_eq!(url_prefix("/blog/page/2"), "/blog/page/");
+        assert_eq!(url_prefix("/blog/page/3/"), "/blog/page/");
+        assert_eq!(url_prefix("/blog/page/4?sort=new#top"), "/blog/page/");
+        assert_eq!(
+            url_prefix("https://example.com/about"),
+            "https://example.com/"
+        );
+        assert_eq!(url_prefix("about.html"), "");
+    }
+
+    #[test]
+    fn results_differing_by_fragment_are_merged() {
+        let grouped = group_by_url_without_fragment(
+            vec![
+                result("/guide#install", &[30, 20]),
+                result("/faq", &[25]),
+                result("/guide?page=2", &[22, 5]),
+                result("/guide#usage", &[10]),
+            ],
+            3,
+        );
+
+        assert_eq!(
+            grouped
+                .iter()
+                .map(|result| (result.entry.url.as_str(), result.merged_entry_count))
+                .collect::<Vec<_>>(),
+            vec![("/guide#install", Some(3)), ("/faq", None)]
+        );
+        assert_eq!(
+            grouped[0]
+                .excerpts
+                .iter()
+                .map(|excerpt| excerpt.score)
+                .collect::<Vec<_>>(),
+            vec![30, 22, 20]
+        );
+        assert_eq!(grouped[0].score, 50);
+    }
+
+    #[test]
+    fn results_per_url_prefix_are_limited() {
+        let limited = limit_results_per_url_prefix(
+            vec![
+                result("/blog/page/1", &[40]),
+                result("/blog/page/2", &[35]),
+                result("/docs/setup", &[30]),
+                result("/blog/page/3", &[25]),
+            ],
+            2,
+        );
+
+        assert_eq!(
+            limited
+                .iter()
+                .map(|result| result.entry.url.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/blog/page/1", "/blog/page/2", "/docs/setup"]
+        );
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn results_can_be_grouped_by_url_and_limited_per_url_prefix() {
+        let file = |title: &str, url: &str, contents: &str| File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        };
+        let config = |group_by_url_without_fragment| Config {
+            input: InputConfig {
+                files: vec![
+                    file(
+                        "Installing",
+                        "/guide#install",
+                        "Install the plugin before you enable the plugin",
+                    ),
+                    file("Using", "/guide#usage", "Then enable the plugin"),
+                    file("Archive 1", "/news/page/1", "An older plugin release"),
+                    file("Archive 2", "/news/page/2", "The first plugin release"),
+                ],
+                ..Default::default()
+            },
+            output: OutputConfig {
+                group_by_url_without_fragment,
+                ..Default::default()
+            },
+        };
+        let search = |index: &Index, options: &crate::SearchOptions| {
+            let output = crate::index_v3::search_with_options(index, "plugin", options).unwrap();
+            let mut results: Vec<(String, Option<usize>, usize)> = output
+                .results
+                .into_iter()
+                .map(|result| {
+                    (
+                        result.entry.url,
+                        result.merged_entry_count,
+                        result.excerpts.len(),
+                    )
+                })
+                .collect();
+            results.sort();
+            (output.total_hit_count, results)
+        };
+
+        let ungrouped = build(&config(false)).unwrap().index;
+        assert_eq!(search(&ungrouped, &Default::default()).0, 4);
+
+        let grouped = build(&config(true)).unwrap().index;
+        assert_eq!(
+            search(&grouped, &Default::default()),
+            (
+                3,
+                vec![
+                    ("/guide#install".to_string(), Some(2), 2),
+                    ("/news/page/1".to_string(), None, 1),
+                    ("/news/page/2".to_string(), None, 1),
+                ]
+            )
+        );
+
+        let limited = search(
+            &grouped,
+            &crate::SearchOptions {
+                max_results_per_url_prefix: Some(1),
+                ..Default::default()
+            },
+        );
+        assert_eq!(limited.0, 3);
+        assert_eq!(limited.1.len(), 2);
+        assert_eq!(limited.1[0].0, "/guide#install");
+        assert!(limited.1[1].0.starts_with("/news/page/"));
+    }
+}
@@ -8,6 +8,10 @@ use crate::{
 #[derive(Clone, Debug)]
 pub(super) struct IntermediateExcerpt {
     pub(super) query: String,
+
+    /// How many characters into the word the query matched. Only a
+    /// substring match starts anywhere but the beginning.
+    pub(super) match_offset: usize,
     pub(super) entry_index: EntryIndex,
     pub(super) score: Score,
     pub(super) source: WordListSource,
@@ -1,39 +1,433 @@
 pub mod intermediate_excerpt;
-use std::collections::BTreeMap;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use intermediate_excerpt::IntermediateExcerpt;
 
 mod entry_and_intermediate_excerpts;
 use entry_and_intermediate_excerpts::EntryAndIntermediateExcerpts;
 
+mod fallback;
+use fallback::{describe_query, relax_prefixes};
+
+mod grouping;
+use grouping::{group_by_url_without_fragment, limit_results_per_url_prefix};
+
+mod query;
+use query::{
+    parse_query, split_emoji_query_words, take_dates, take_section, QueryDates, QueryWord,
+};
+
+mod sentences;
+
+mod session;
+pub use session::QuerySession;
+
+mod substring;
+use substring::substring_excerpts;
+
+mod titles;
+#[cfg(test)]
+pub use titles::search_titles;
+pub use titles::search_titles_with_options;
+
+use crate::config::{AcronymPeriodHandling, QueryOperator, TieBreaker};
 use crate::stopwords;
-use crate::Output;
+use crate::Date;
 use crate::Result;
+use crate::SearchError;
+use crate::SearchOptions;
 use crate::V3Index as Index;
+use crate::{Fallback, FallbackStage, Output};
 
+use super::acronyms::collapse_acronym_periods;
 use super::scores::STOPWORD_SCORE;
+use super::width::normalize_width;
 use super::AliasTarget;
 use super::Container;
 use super::Entry;
 use super::EntryIndex;
+use super::Excerpt as StoredExcerpt;
 use super::Score;
 use super::SearchResult;
+use super::WordListSource;
+use super::DATE_FIELD;
+use super::FACET_FIELD;
+use super::LANGUAGE_FIELD;
+use super::THUMBNAIL_FIELD;
 
-pub fn search(index: &Index, query: &str) -> Output {
-    let normalized_query = query.to_lowercase();
-    let words_in_query: Vec<String> = normalized_query
-        .split(|c| c == ' ' || c == '-')
-        .map(ToString::to_string)
-        .collect();
+#[cfg(test)]
+pub fn search(index: &Index, query: &str) -> core::result::Result<Output, SearchError> {
+    search_with_options(index, query, &SearchOptions::default())
+}
+
+/**
+ * Searches the index, limited to the documents `options` allow. Words in the
+ * query can be scoped to one part of each document with `title:`, `body:`,
+ * `url:`, `keywords:`, or `summary:`; an unknown field name is only an error
+ * if the index was built to reject them. The query can also limit the
+ * documents by date with `after:2023-01-01` and `before:2024-01-01`, like
+ * `SearchOptions::after` and `before`.
+ */
+pub fn search_with_options(
+    index: &Index,
+    query: &str,
+    options: &SearchOptions,
+) -> core::result::Result<Output, SearchError> {
+    search_reusing(index, query, options, None)
+}
+
+/**
+ * What a search found, kept by a `QuerySession` so that its next search can
+ * reuse whatever the new query doesn't change.
+ */
+#[derive(Debug, Default)]
+struct SearchCache {
+    /// Each of the query's words, other than negated ones, in order, with
+    /// the excerpts it matched.
+    words: Vec<(QueryWord, Vec<IntermediateExcerpt>)>,
+
+    /// The entries that the words changed since the previous search matched,
+    /// before or after the change, whose results have to be rebuilt.
+    changed_entries: BTreeSet<EntryIndex>,
+
+    /// The result built for each entry the search returned, before results
+    /// were grouped or truncated.
+    results: BTreeMap<EntryIndex, Result>,
+}
+
+/**
+ * Searches like `search_with_options`. Given a session's `cache`, reuses the
+ * excerpts it has for words the query still has in the same place, and the
+ * results it has for entries none of the other words match, then fills it
+ * from this search.
+ */
+fn search_reusing(
+    index: &Index,
+    query: &str,
+    options: &SearchOptions,
+    mut cache: Option<&mut SearchCache>,
+) -> core::result::Result<Output, SearchError> {
+    let _search_span = tracing::debug_span!("search", query).entered();
+
+    if query.trim().chars().count() < index.config.min_query_length as usize {
+        return Ok(Output {
+            results: vec![],
+            total_hit_count: 0,
+            url_prefix: index.config.url_prefix.clone(),
+            facet_counts: BTreeMap::new(),
+            fallback: None,
+            surface_forms: BTreeMap::new(),
+        });
+    }
+
+    let query = if index.config.normalize_width {
+        normalize_width(query)
+    } else {
+        Cow::Borrowed(query)
+    };
+    let (normalized_query, section) = take_section(&query.to_lowercase());
+    let (normalized_query, dates) = take_dates(&normalized_query)?;
+    let mut words_in_query = parse_query(&normalized_query, index.config.unknown_query_fields)?;
+    if index.config.index_emoji {
+        words_in_query = split_emoji_query_words(words_in_query);
+    }
+    if index.config.acronym_periods == AcronymPeriodHandling::Collapse {
+        for query_word in &mut words_in_query {
+            if let Some(collapsed) = collapse_acronym_periods(&query_word.word) {
+                query_word.word = collapsed;
+            }
+        }
+    }
+    if options.summaries_only {
+        for query_word in words_in_query.iter_mut().filter(|word| !word.negated) {
+            query_word.source = Some(WordListSource::Summary);
+        }
+    }
+
+    let scope = Scope {
+        section: section.as_deref(),
+        language: resolve_language(index, options.language.as_deref()),
+        dates: DateRange::new(dates, options),
+    };
 
-    // Get the containers for each word in the query, and separate them
-    // into intermediate excerpts
-    let mut intermediate_excerpts: Vec<IntermediateExcerpt> = words_in_query
+    let (mut excerpts_by_index, mut facet_counts) =
+        find_matches(index, &words_in_query, &scope, cache.as_deref_mut());
+
+    let mut fallback = None;
+    if excerpts_by_index.is_empty() {
+        if let Some(((fallback_excerpts, fallback_facet_counts), stage)) =
+            try_fallbacks(index, &words_in_query, &scope)
+        {
+            excerpts_by_index = fallback_excerpts;
+            facet_counts = fallback_facet_counts;
+            fallback = Some(stage);
+
+            // These results are for a different query, so none of them can
+            // be reused.
+            if let Some(cache) = cache.as_deref_mut() {
+                *cache = SearchCache::default();
+            }
+        }
+    }
+
+    let mut output_results: Vec<Result> = match cache {
+        Some(cache) if fallback.is_none() => cached_results(index, excerpts_by_index, cache),
+        _ => excerpts_by_index
+            .into_iter()
+            .map(|(entry_index, ies)| entry_result(index, entry_index, ies))
+            .collect(),
+    };
+    // The results are in entry order, and the sort is stable, so the
+    // entry order breaks any tie the tie breaker leaves.
+    output_results.sort_by(|a, b| {
+        b.score.cmp(&a.score).then_with(|| {
+            break_tie(
+                index.config.tie_breaker,
+                (&a.entry.title, &a.entry.url),
+                (&b.entry.title, &b.entry.url),
+            )
+        })
+    });
+
+    if index.config.group_by_url_without_fragment {
+        output_results = group_by_url_without_fragment(
+            output_results,
+            index.config.excerpts_per_result as usize,
+        );
+    }
+
+    let total_hit_count = output_results.len();
+
+    if let Some(max) = options.max_results_per_url_prefix {
+        output_results = limit_results_per_url_prefix(output_results, max);
+    }
+
+    output_results.truncate(index.config.displayed_results_count as usize);
+
+    Ok(Output {
+        results: output_results,
+        total_hit_count,
+        url_prefix: index.config.url_prefix.clone(),
+        facet_counts,
+        fallback,
+        surface_forms: stem_surface_forms(index, &words_in_query),
+    })
+}
+
+fn entry_result(index: &Index, entry_index: EntryIndex, ies: Vec<IntermediateExcerpt>) -> Result {
+    Result::from(EntryAndIntermediateExcerpts {
+        entry: index.entries[entry_index].clone(),
+        config: index.config.clone(),
+        intermediate_excerpts: ies,
+    })
+}
+
+/**
+ * Builds the result of each matched entry, reusing the one `cache` has for
+ * entries that none of the changed words match, and keeps them in `cache`
+ * for the session's next search.
+ */
+fn cached_results(
+    index: &Index,
+    excerpts_by_index: BTreeMap<EntryIndex, Vec<IntermediateExcerpt>>,
+    cache: &mut SearchCache,
+) -> Vec<Result> {
+    let mut previous_results = std::mem::take(&mut cache.results);
+    excerpts_by_index
+        .into_iter()
+        .map(|(entry_index, ies)| {
+            let result = match previous_results.remove(&entry_index) {
+                Some(result) if !cache.changed_entries.contains(&entry_index) => result,
+                _ => entry_result(index, entry_index, ies),
+            };
+            cache.results.insert(entry_index, result.clone());
+            result
+        })
+        .collect()
+}
+
+/**
+ * The corpus words that share a stem with each of the query's words that
+ * isn't negated, for indexes built with `output.save_stem_surface_forms`.
+ * Words that are their stem's only form are left out.
+ */
+fn stem_surface_forms(
+    index: &Index,
+    words_in_query: &[QueryWord],
+) -> BTreeMap<String, Vec<String>> {
+    words_in_query
         .iter()
-        .filter_map(|word| index.containers.get_key_value(word))
-        .map(|(word, ctr)| ContainerWithQuery::new(ctr.clone(), word))
-        .flat_map(|ctr_query| ctr_query.get_intermediate_excerpts(index))
-        .collect();
+        .filter(|query_word| !query_word.negated)
+        .filter_map(|query_word| {
+            let surface_forms = &index.containers.get(&query_word.word)?.surface_forms;
+            surface_forms
+                .iter()
+                .any(|form| *form != query_word.word)
+                .then(|| (query_word.word.clone(), surface_forms.clone()))
+        })
+        .collect()
+}
+
+/**
+ * Orders two results with the same score by their `(title, url)`, as
+ * `output.tie_breaker` says. `Equal` leaves them in entry order.
+ */
+fn break_tie(tie_breaker: TieBreaker, a: (&str, &str), b: (&str, &str)) -> Ordering {
+    let ((a_title, a_url), (b_title, b_url)) = (a, b);
+    match tie_breaker {
+        TieBreaker::Title => a_title.cmp(b_title).then_with(|| a_url.cmp(b_url)),
+        TieBreaker::Url => a_url.cmp(b_url).then_with(|| a_title.cmp(b_title)),
+        TieBreaker::Index => Ordering::Equal,
+    }
+}
+
+/// The excerpts that matched each entry, and how many entries matched in
+/// each section.
+type Matches = (
+    BTreeMap<EntryIndex, Vec<IntermediateExcerpt>>,
+    BTreeMap<String, usize>,
+);
+
+/**
+ * The documents a search is limited to: those in a `section:` named in the
+ * query, those in the language the search asked for or in none, and those
+ * in its range of dates.
+ */
+struct Scope<'a> {
+    section: Option<&'a str>,
+    language: Option<&'a str>,
+    dates: DateRange,
+}
+
+/**
+ * The dates a search is limited to, from its options and its query, keeping
+ * the narrower of the two at each end. `after` is inclusive and `before` is
+ * exclusive, so consecutive ranges don't overlap.
+ */
+struct DateRange {
+    after: Option<Date>,
+    before: Option<Date>,
+    require_dates: bool,
+}
+
+impl DateRange {
+    fn new(query_dates: QueryDates, options: &SearchOptions) -> Self {
+        DateRange {
+            after: query_dates.after.max(options.after),
+            before: match (query_dates.before, options.before) {
+                (Some(query_before), Some(options_before)) => {
+                    Some(query_before.min(options_before))
+                }
+                (query_before, options_before) => query_before.or(options_before),
+            },
+            require_dates: options.require_dates,
+        }
+    }
+
+    /// Entries without a date are only left out of a range when
+    /// `require_dates` is set; without a range, every entry is included.
+    fn includes(&self, entry: &Entry) -> bool {
+        if self.after.is_none() && self.before.is_none() {
+            return true;
+        }
+
+        let date = entry
+            .fields
+            .get(DATE_FIELD)
+            .and_then(|date| date.parse::<Date>().ok());
+        match date {
+            Some(date) => {
+                self.after.map_or(true, |after| date >= after)
+                    && self.before.map_or(true, |before| date < before)
+            }
+            None => !self.require_dates,
+        }
+    }
+}
+
+impl Scope<'_> {
+    fn includes_section(&self, entry: &Entry) -> bool {
+        self.section.map_or(true, |section| {
+            entry.fields.get(FACET_FIELD).map(String::as_str) == Some(section)
+        })
+    }
+
+    fn includes_language(&self, entry: &Entry) -> bool {
+        match (self.language, entry.fields.get(LANGUAGE_FIELD)) {
+            (Some(language), Some(entry_language)) => entry_language == language,
+            _ => true,
+        }
+    }
+
+    fn includes_date(&self, entry: &Entry) -> bool {
+        self.dates.includes(entry)
+    }
+}
+
+/**
+ * The one of the index's languages that `requested` names, either exactly
+ * or, for a region tag like `de-AT`, by its primary language `de`. Tags are
+ * compared without regard to case.
+ */
+fn resolve_language<'a>(index: &'a Index, requested: Option<&str>) -> Option<&'a str> {
+    let requested = requested?.trim();
+    let primary = requested
+        .split(|c| c == '-' || c == '_')
+        .next()
+        .unwrap_or(requested);
+
+    [requested, primary].into_iter().find_map(|tag| {
+        index
+            .config
+            .languages
+            .iter()
+            .find(|language| language.eq_ignore_ascii_case(tag))
+            .map(String::as_str)
+    })
+}
+
+/**
+ * Finds the excerpts that match the query's words, grouped by entry and
+ * limited to the search's `scope`. With the `and` query operator, only
+ * entries that every word but stopwords matched are kept.
+ *
+ * Entries that contain a negated word are left out. Negated words only
+ * exclude, so a query of nothing but negated words matches nothing.
+ */
+fn find_matches(
+    index: &Index,
+    words_in_query: &[QueryWord],
+    scope: &Scope,
+    cache: Option<&mut SearchCache>,
+) -> Matches {
+    let (negated_words, words_in_query): (Vec<&QueryWord>, Vec<&QueryWord>) = words_in_query
+        .iter()
+        .partition(|query_word| query_word.negated);
+
+    let excerpts_per_word: Vec<Vec<IntermediateExcerpt>> = match cache {
+        Some(cache) => cached_word_excerpts(index, &words_in_query, cache),
+        None => words_in_query
+            .iter()
+            .map(|query_word| word_excerpts(index, query_word))
+            .collect(),
+    };
+
+    let required_entries: Option<BTreeSet<EntryIndex>> = match index.config.query_operator {
+        QueryOperator::Or => None,
+        QueryOperator::And => words_in_query
+            .iter()
+            .zip(&excerpts_per_word)
+            .filter(|(query_word, _)| !stopwords.contains(&query_word.word.as_str()))
+            .map(|(_, excerpts)| excerpts.iter().map(|ie| ie.entry_index).collect())
+            .reduce(|matched: BTreeSet<EntryIndex>, word_matched| {
+                matched.intersection(&word_matched).copied().collect()
+            }),
+    };
+
+    let mut intermediate_excerpts: Vec<IntermediateExcerpt> =
+        excerpts_per_word.into_iter().flatten().collect();
 
     for mut ie in &mut intermediate_excerpts {
         if stopwords.contains(&ie.query.as_str()) {
@@ -49,28 +443,229 @@ pub fn search(index: &Index, query: &str) -> Output {
             .push(ie)
     }
 
-    let total_len = &excerpts_by_index.len();
+    if let Some(required_entries) = required_entries {
+        excerpts_by_index.retain(|entry_index, _| required_entries.contains(entry_index));
+    }
 
-    let mut output_results: Vec<Result> = excerpts_by_index
+    let excluded_entries = excluded_entries(index, &negated_words);
+    excerpts_by_index.retain(|entry_index, _| {
+        let entry = &index.entries[*entry_index];
+        !excluded_entries.contains(entry_index)
+            && scope.includes_language(entry)
+            && scope.includes_date(entry)
+    });
+
+    // Facets are counted across every matching document in the search's
+    // language and dates, before any section is filtered to and before the results are
+    // truncated, so that a UI can show how many results each section would
+    // have.
+    let mut facet_counts: BTreeMap<String, usize> = BTreeMap::new();
+    for entry_index in excerpts_by_index.keys() {
+        if let Some(facet) = index.entries[*entry_index].fields.get(FACET_FIELD) {
+            *facet_counts.entry(facet.clone()).or_default() += 1;
+        }
+    }
+
+    excerpts_by_index.retain(|entry_index, _| scope.includes_section(&index.entries[*entry_index]));
+
+    (excerpts_by_index, facet_counts)
+}
+
+/**
+ * The excerpts of each of the query's words, reusing the ones `cache` has
+ * for words in the same place as in its query. When the only change is
+ * characters added to the last word, that word's excerpts are narrowed from
+ * the ones it had before. Then fills `cache` with this query's words.
+ */
+fn cached_word_excerpts(
+    index: &Index,
+    words_in_query: &[&QueryWord],
+    cache: &mut SearchCache,
+) -> Vec<Vec<IntermediateExcerpt>> {
+    // A word in the same place as in the cached query matches the same
+    // excerpts. Any other word's matches, and the matches of the word it
+    // replaced, may change the results of the entries they're in.
+    let previous_words = std::mem::take(&mut cache.words);
+    cache.changed_entries.clear();
+    let last_word_extended = |position: usize| {
+        position + 1 == words_in_query.len() && previous_words.len() == words_in_query.len()
+    };
+    let excerpts_per_word: Vec<Vec<IntermediateExcerpt>> = words_in_query
         .iter()
-        .map(|(entry_index, ies)| {
-            let data = EntryAndIntermediateExcerpts {
-                entry: index.entries[*entry_index].clone(),
-                config: index.config.clone(),
-                intermediate_excerpts: ies.clone(),
-            };
-            Result::from(data)
-        })
+        .enumerate()
+        .map(
+            |(position, query_word)| match previous_words.get(position) {
+                Some((previous_word, excerpts)) if previous_word == *query_word => excerpts.clone(),
+                previous => {
+                    let excerpts = previous
+                        .filter(|_| last_word_extended(position))
+                        .and_then(|(previous_word, excerpts)| {
+                            narrowed_word_excerpts(index, query_word, previous_word, excerpts)
+                        })
+                        .unwrap_or_else(|| word_excerpts(index, query_word));
+                    cache
+                        .changed_entries
+                        .extend(excerpts.iter().map(|ie| ie.entry_index));
+                    excerpts
+                }
+            },
+        )
         .collect();
-    output_results.sort_by_key(|or| or.entry.title.clone());
-    output_results.sort_by_key(|or| -(or.score as i64));
-    output_results.truncate(index.config.displayed_results_count as usize);
 
-    Output {
-        results: output_results,
-        total_hit_count: *total_len,
-        url_prefix: index.config.url_prefix.clone(),
+    for (position, (previous_word, excerpts)) in previous_words.iter().enumerate() {
+        if words_in_query.get(position) != Some(&previous_word) {
+            cache
+                .changed_entries
+                .extend(excerpts.iter().map(|ie| ie.entry_index));
+        }
     }
+
+    cache.words = words_in_query
+        .iter()
+        .map(|query_word| (*query_word).clone())
+        .zip(excerpts_per_word.iter().cloned())
+        .collect();
+
+    excerpts_per_word
+}
+
+/**
+ * Gets the container for a word in the query, and separates it into
+ * intermediate excerpts, keeping only those from the part of the document a
+ * scoped word has to match. A word without a container of its own can still
+ * match in the middle of a word, if the index has n-grams.
+ */
+fn word_excerpts(index: &Index, query_word: &QueryWord) -> Vec<IntermediateExcerpt> {
+    let source = query_word.source;
+    let excerpts = match index.containers.get_key_value(&query_word.word) {
+        Some((word, ctr)) => {
+            ContainerWithQuery::new(ctr.clone(), word).get_intermediate_excerpts(index)
+        }
+        None => substring_excerpts(index, &query_word.word),
+    };
+
+    excerpts
+        .into_iter()
+        .filter(|ie| source.map_or(true, |source| ie.source == source))
+        .collect()
+}
+
+/**
+ * The excerpts of `query_word`, which is `previous_word` with characters
+ * added to its end, narrowed from `previous_excerpts`, the ones the previous
+ * word matched. The longer word's aliases are mostly words the shorter word
+ * already matched, so their excerpts are taken from the previous ones with
+ * the longer word's alias score, and only the other aliases are read from
+ * the index. The excerpts are the same, and in the same order, as
+ * `word_excerpts` would give. `None` if the word doesn't extend the previous
+ * one, or if either word has no container of its own.
+ */
+fn narrowed_word_excerpts(
+    index: &Index,
+    query_word: &QueryWord,
+    previous_word: &QueryWord,
+    previous_excerpts: &[IntermediateExcerpt],
+) -> Option<Vec<IntermediateExcerpt>> {
+    let extends_previous_word = query_word.source == previous_word.source
+        && query_word.word.len() > previous_word.word.len()
+        && query_word.word.starts_with(&previous_word.word);
+    if !extends_previous_word {
+        return None;
+    }
+    let previous_container = index.containers.get(&previous_word.word)?;
+    let (word, container) = index.containers.get_key_value(&query_word.word)?;
+
+    // Each word the previous word matched, whether it's the previous word
+    // itself or one of its aliases, with that word's excerpts in order.
+    let mut previous_matches: HashMap<&str, Vec<&IntermediateExcerpt>> = HashMap::new();
+    for ie in previous_excerpts {
+        previous_matches.entry(&ie.query).or_default().push(ie);
+    }
+    let previously_matched = |target: &str| {
+        target == previous_word.word || previous_container.aliases.contains_key(target)
+    };
+
+    let source = query_word.source;
+    let in_source = |ie: &IntermediateExcerpt| source.map_or(true, |source| ie.source == source);
+    let mut excerpts: Vec<IntermediateExcerpt> = results_excerpts(word, &container.results, None)
+        .filter(in_source)
+        .collect();
+    for (alias_target, alias_score) in &container.aliases {
+        if previously_matched(alias_target) {
+            let matched = previous_matches.get(alias_target.as_str());
+            excerpts.extend(matched.into_iter().flatten().map(|ie| IntermediateExcerpt {
+                score: *alias_score,
+                ..(*ie).clone()
+            }));
+        } else if let Some(target_container) = index.containers.get(alias_target) {
+            excerpts.extend(
+                results_excerpts(alias_target, &target_container.results, Some(*alias_score))
+                    .filter(in_source),
+            );
+        }
+    }
+
+    Some(excerpts)
+}
+
+/**
+ * The entries that contain any of the negated words, in the part of the
+ * document each word is scoped to. Only the word's own container is read, so
+ * `-window` doesn't exclude entries that only have `windows`.
+ */
+fn excluded_entries(index: &Index, negated_words: &[&QueryWord]) -> BTreeSet<EntryIndex> {
+    negated_words
+        .iter()
+        .filter_map(|query_word| {
+            index
+                .containers
+                .get(&query_word.word)
+                .map(|container| (query_word.source, container))
+        })
+        .flat_map(|(source, container)| {
+            container
+                .results
+                .iter()
+                .filter(move |(_, result)| {
+                    excerpts_or_bare_match(result)
+                        .iter()
+                        .any(|excerpt| source.map_or(true, |source| excerpt.source == source))
+                })
+                .map(|(entry_index, _)| *entry_index)
+        })
+        .collect()
+}
+
+/**
+ * Runs the enabled stages of `output.zero_result_fallback`, in order, for a
+ * query that found nothing, returning the matches from the first stage that
+ * finds any.
+ */
+fn try_fallbacks(
+    index: &Index,
+    words_in_query: &[QueryWord],
+    scope: &Scope,
+) -> Option<(Matches, Fallback)> {
+    let fallback_config = index.config.zero_result_fallback?;
+
+    if fallback_config.prefix_relaxation {
+        if let Some(relaxed_words) =
+            relax_prefixes(words_in_query, fallback_config.max_lookups, |word| {
+                index.containers.contains_key(word)
+            })
+        {
+            let matches = find_matches(index, &relaxed_words, scope, None);
+            if !matches.0.is_empty() {
+                let fallback = Fallback {
+                    stage: FallbackStage::PrefixRelaxation,
+                    query: describe_query(&relaxed_words),
+                };
+                return Some((matches, fallback));
+            }
+        }
+    }
+
+    None
 }
 
 struct ContainerWithQuery {
@@ -89,38 +684,18 @@ impl ContainerWithQuery {
     }
 
     fn get_intermediate_excerpts(&self, index: &Index) -> Vec<IntermediateExcerpt> {
-        let mut output = vec![];
         // Put container's results in output
-        for (entry_index, result) in &self.results {
-            for excerpt in result.excerpts.clone() {
-                output.push(IntermediateExcerpt {
-                    query: self.query.to_string(),
-                    entry_index: *entry_index,
-                    score: result.score,
-                    source: excerpt.source,
-                    word_index: excerpt.word_index,
-                    internal_annotations: excerpt.internal_annotations,
-                    fields: excerpt.fields,
-                })
-            }
-        }
+        let mut output: Vec<IntermediateExcerpt> =
+            results_excerpts(&self.query, &self.results, None).collect();
 
         // Put alias containers' results in output
         for (alias_target, alias_score) in &self.aliases {
             if let Some(target_container) = index.containers.get(alias_target) {
-                for (entry_index, result) in target_container.results.clone() {
-                    for excerpt in result.excerpts.clone() {
-                        output.push(IntermediateExcerpt {
-                            query: alias_target.to_string(),
-                            entry_index,
-                            score: *alias_score,
-                            internal_annotations: excerpt.internal_annotations,
-                            source: excerpt.source,
-                            word_index: excerpt.word_index,
-                            fields: excerpt.fields,
-                        })
-                    }
-                }
+                output.extend(results_excerpts(
+                    alias_target,
+                    &target_container.results,
+                    Some(*alias_score),
+                ));
             }
         }
 
@@ -128,22 +703,150 @@ impl ContainerWithQuery {
     }
 }
 
+/**
+ * The intermediate excerpts of the results in `query`'s container, scored
+ * `alias_score` when they're reached through an alias, and with each
+ * result's own score otherwise.
+ */
+fn results_excerpts<'a>(
+    query: &'a str,
+    results: &'a BTreeMap<EntryIndex, SearchResult>,
+    alias_score: Option<Score>,
+) -> impl Iterator<Item = IntermediateExcerpt> + 'a {
+    results.iter().flat_map(move |(entry_index, result)| {
+        excerpts_or_bare_match(result)
+            .into_iter()
+            .map(move |excerpt| IntermediateExcerpt {
+                query: query.to_string(),
+                match_offset: 0,
+                entry_index: *entry_index,
+                score: alias_score.unwrap_or(result.score),
+                source: excerpt.source,
+                word_index: excerpt.word_index,
+                internal_annotations: excerpt.internal_annotations,
+                fields: excerpt.fields,
+            })
+    })
+}
+
+/**
+ * A result's stored excerpts or, for an entry built without excerpts, a
+ * single contents excerpt standing in for the match. Its entry has no text,
+ * so it's scored but never shown.
+ */
+fn excerpts_or_bare_match(result: &SearchResult) -> Vec<StoredExcerpt> {
+    if result.excerpts.is_empty() {
+        vec![StoredExcerpt::default()]
+    } else {
+        result.excerpts.clone()
+    }
+}
+
+/**
+ * Converts an entry for output, moving its thumbnail out of its fields. The
+ * thumbnail is returned as it was stored; see `with_url_prefix`.
+ */
 impl From<Entry> for crate::Entry {
-    fn from(entry: Entry) -> Self {
+    fn from(mut entry: Entry) -> Self {
+        let thumbnail_url = entry.fields.remove(THUMBNAIL_FIELD);
         crate::Entry {
             url: entry.url.clone(),
             title: entry.title.clone(),
             fields: entry.fields,
+            thumbnail_url,
+            word_count: entry.word_count,
+            reading_time_minutes: None,
+            text: entry.text,
         }
     }
 }
 
+/**
+ * Joins a relative thumbnail path to the index's `url_prefix`, the same way
+ * the Javascript library joins it to each result's URL, but without doubling
+ * the slash between them. URLs with a scheme, like `https://`, and
+ * protocol-relative URLs, like `//cdn.example.com/a.png`, are left alone.
+ */
+fn with_url_prefix(url_prefix: &str, thumbnail_url: &str) -> String {
+    let has_scheme = thumbnail_url.split_once(':').map_or(false, |(scheme, _)| {
+        !scheme.is_empty() && !scheme.contains('/')
+    });
+
+    if has_scheme || thumbnail_url.starts_with("//") {
+        thumbnail_url.to_string()
+    } else if url_prefix.ends_with('/') && thumbnail_url.starts_with('/') {
+        format!("{}{}", url_prefix, &thumbnail_url[1..])
+    } else {
+        format!("{}{}", url_prefix, thumbnail_url)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "build-v3")]
+    use crate::{config::*, index_v3::build::build};
     use std::convert::TryFrom;
     use std::fs;
     use std::io::{BufReader, Read};
+
+    #[test]
+    fn ties_are_broken_by_the_configured_key() {
+        let alpha_b = ("Alpha", "/b");
+        let beta_a = ("Beta", "/a");
+
+        assert_eq!(
+            break_tie(TieBreaker::Title, alpha_b, beta_a),
+            Ordering::Less
+        );
+        assert_eq!(
+            break_tie(TieBreaker::Url, alpha_b, beta_a),
+            Ordering::Greater
+        );
+        assert_eq!(
+            break_tie(TieBreaker::Index, alpha_b, beta_a),
+            Ordering::Equal
+        );
+        assert_eq!(
+            break_tie(TieBreaker::Title, ("Alpha", "/a"), alpha_b),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "build-v3")]
+    fn query_words_report_the_surface_forms_of_their_stem() {
+        use crate::config::{Config, DataSource, File, Filetype, InputConfig, OutputConfig};
+
+        let config = Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        "She runs. He was running. They run. Walk home.".to_string(),
+                    )),
+                    title: "Running".to_string(),
+                    filetype: Some(Filetype::PlainText),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            output: OutputConfig {
+                save_stem_surface_forms: true,
+                ..Default::default()
+            },
+        };
+        let index = crate::index_v3::build(&config).unwrap().index;
+
+        let output = search(&index, "running walk -home").unwrap();
+        assert_eq!(
+            output.surface_forms,
+            BTreeMap::from([(
+                "running".to_string(),
+                vec!["run".to_string(), "running".to_string(), "runs".to_string()]
+            )])
+        );
+    }
+
     #[test]
     fn e2e_v3_search_works() {
         let file = fs::File::open("../test-assets/federalist-min-0.7.0.st").unwrap();
@@ -152,9 +855,342 @@ mod tests {
         let _bytes_read = buf_reader.read_to_end(&mut index_bytes);
 
         let index = Index::try_from(index_bytes.as_slice()).unwrap();
-        let generated = search(&index, "liber old world");
+        let generated = search(&index, "liber old world").unwrap();
         let expected = serde_json::from_str("{\"results\":[{\"entry\":{\"url\":\"https://www.congress.gov/resources/display/content/The+Federalist+Papers#TheFederalistPapers-1\",\"title\":\"Introduction\",\"fields\":{}},\"excerpts\":[{\"text\":\"in many respects the most interesting in the world. It has been frequently remarked that it\",\"highlight_ranges\":[{\"beginning\":45,\"end\":50}],\"score\":128,\"internal_annotations\":[],\"fields\":{}},{\"text\":\"despotic power and hostile to the principles of liberty. An over-scrupulous jealousy of danger to the\",\"highlight_ranges\":[{\"beginning\":48,\"end\":55}],\"score\":125,\"internal_annotations\":[],\"fields\":{}},{\"text\":\"of love, and that the noble enthusiasm of liberty is apt to be infected with a\",\"highlight_ranges\":[{\"beginning\":42,\"end\":49}],\"score\":125,\"internal_annotations\":[],\"fields\":{}},{\"text\":\"of government is essential to the security of liberty; that, in the contemplation of a sound\",\"highlight_ranges\":[{\"beginning\":46,\"end\":53}],\"score\":125,\"internal_annotations\":[],\"fields\":{}},{\"text\":\"that this is the safest course for your liberty, your dignity, and your happiness. I affect\",\"highlight_ranges\":[{\"beginning\":40,\"end\":47}],\"score\":125,\"internal_annotations\":[],\"fields\":{}}],\"title_highlight_ranges\":[],\"score\":878}],\"total_hit_count\":1,\"url_prefix\":\"\"}").unwrap();
 
         assert_eq!(generated, expected, "{:?}", generated);
     }
+
+    #[test]
+    fn concurrent_searches_match_serial_results() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let file = fs::File::open("../test-assets/federalist-min-0.7.0.st").unwrap();
+        let mut buf_reader = BufReader::new(file);
+        let mut index_bytes: Vec<u8> = Vec::new();
+        let _bytes_read = buf_reader.read_to_end(&mut index_bytes);
+        let index = Arc::new(Index::try_from(index_bytes.as_slice()).unwrap());
+
+        let queries = vec!["liberty", "lib", "old world", "government", "the", "zzz"];
+        let baseline: Arc<Vec<Output>> = Arc::new(
+            queries
+                .iter()
+                .map(|query| search(&index, query).unwrap())
+                .collect(),
+        );
+
+        let threads: Vec<_> = (0..8)
+            .map(|thread_number| {
+                let index = Arc::clone(&index);
+                let baseline = Arc::clone(&baseline);
+                let queries = queries.clone();
+                thread::spawn(move || {
+                    for i in 0..250 {
+                        let query_index = (i + thread_number) % queries.len();
+                        let computed = search(&index, queries[query_index]).unwrap();
+                        assert_eq!(computed, baseline[query_index]);
+                    }
+                })
+            })
+            .collect();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn relative_thumbnails_are_joined_to_the_url_prefix() {
+        let prefix = "https://example.com/docs/";
+
+        assert_eq!(
+            with_url_prefix(prefix, "img/a.png"),
+            "https://example.com/docs/img/a.png"
+        );
+        assert_eq!(
+            with_url_prefix(prefix, "/img/a.png"),
+            "https://example.com/docs/img/a.png"
+        );
+        assert_eq!(
+            with_url_prefix("https://example.com", "/img/a.png"),
+            "https://example.com/img/a.png"
+        );
+        assert_eq!(with_url_prefix("", "/img/a.png"), "/img/a.png");
+        assert_eq!(
+            with_url_prefix(prefix, "https://cdn.example.com/a.png"),
+            "https://cdn.example.com/a.png"
+        );
+        assert_eq!(
+            with_url_prefix(prefix, "//cdn.example.com/a.png"),
+            "//cdn.example.com/a.png"
+        );
+    }
+
+    #[test]
+    fn query_shorter_than_minimum_returns_no_results() {
+        let file = fs::File::open("../test-assets/federalist-min-0.7.0.st").unwrap();
+        let mut buf_reader = BufReader::new(file);
+        let mut index_bytes: Vec<u8> = Vec::new();
+        let _bytes_read = buf_reader.read_to_end(&mut index_bytes);
+
+        let mut index = Index::try_from(index_bytes.as_slice()).unwrap();
+        assert!(!search(&index, "lib").unwrap().results.is_empty());
+
+        index.config.min_query_length = 4;
+        let generated = search(&index, "lib").unwrap();
+        assert!(generated.results.is_empty());
+        assert_eq!(generated.total_hit_count, 0);
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn and_operator_only_returns_entries_matching_every_word() {
+        let file = |title: &str, contents: &str| File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            ..Default::default()
+        };
+        let index = |query_operator| {
+            build(&Config {
+                input: InputConfig {
+                    files: vec![
+                        file("Orchard fruit", "Apples and pears grow in the orchard"),
+                        file("Apples", "Apples grow in the orchard"),
+                        file("Pears", "Pears grow in the orchard"),
+                    ],
+                    ..Default::default()
+                },
+                output: OutputConfig {
+                    query_operator,
+                    ..Default::default()
+                },
+            })
+            .unwrap()
+            .index
+        };
+        let titles = |index: &Index, query: &str| -> Vec<String> {
+            let mut titles: Vec<String> = crate::index_v3::search(index, query)
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| result.entry.title)
+                .collect();
+            titles.sort();
+            titles
+        };
+
+        let or_index = index(QueryOperator::Or);
+        let and_index = index(QueryOperator::And);
+
+        assert_eq!(
+            titles(&or_index, "apples pears"),
+            vec!["Apples", "Orchard fruit", "Pears"]
+        );
+        assert_eq!(titles(&and_index, "apples pears"), vec!["Orchard fruit"]);
+
+        // Stopwords don't have to match, and one word matches the same
+        // entries either way.
+        assert_eq!(
+            titles(&and_index, "apples with pears"),
+            vec!["Orchard fruit"]
+        );
+        assert_eq!(titles(&or_index, "pears"), titles(&and_index, "pears"));
+
+        let title_output = crate::index_v3::search_titles(&and_index, "apples orchard").unwrap();
+        assert_eq!(title_output.total_hit_count, 0);
+        assert_eq!(
+            crate::index_v3::search_titles(&or_index, "apples orchard")
+                .unwrap()
+                .total_hit_count,
+            2
+        );
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn negated_words_exclude_entries_containing_them() {
+        let file = |title: &str, contents: &str| File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            ..Default::default()
+        };
+        let index = build(&Config {
+            input: InputConfig {
+                files: vec![
+                    file("Windows crash", "The installer shows an error on windows"),
+                    file("Linux crash", "The installer shows an error on linux"),
+                    file("Release notes", "Version two supports windows and linux"),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap()
+        .index;
+        let titles = |query: &str| -> Vec<String> {
+            crate::index_v3::search(&index, query)
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| result.entry.title)
+                .collect()
+        };
+
+        assert_eq!(titles("error -windows"), vec!["Linux crash"]);
+        assert_eq!(titles("installer -title:linux"), vec!["Windows crash"]);
+        assert_eq!(titles("installer -body:crash").len(), 2);
+
+        // Negated words only take results away, so on their own they find
+        // nothing.
+        assert!(titles("-windows").is_empty());
+
+        let title_output = crate::index_v3::search_titles(&index, "crash -windows").unwrap();
+        assert_eq!(title_output.total_hit_count, 1);
+        assert_eq!(title_output.results[0].title, "Linux crash");
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn searches_can_be_limited_to_one_language() {
+        let file = |title: &str, url: &str, contents: &str| File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        };
+        let index = build(&Config {
+            input: InputConfig {
+                files: vec![
+                    file("Install", "/en/install", "Install the package with cargo"),
+                    file(
+                        "Installieren",
+                        "/de/install",
+                        "Das package mit cargo installieren",
+                    ),
+                    file("Changelog", "/changelog", "Every package release"),
+                ],
+                language_url_prefixes: BTreeMap::from([
+                    ("en".to_string(), "/en/".to_string()),
+                    ("de".to_string(), "/de/".to_string()),
+                ]),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap()
+        .index;
+        let titles = |language: Option<&str>| -> Vec<String> {
+            let options = crate::SearchOptions {
+                language: language.map(ToString::to_string),
+                ..Default::default()
+            };
+            let mut titles: Vec<String> =
+                crate::index_v3::search_with_options(&index, "package", &options)
+                    .unwrap()
+                    .results
+                    .into_iter()
+                    .map(|result| result.entry.title)
+                    .collect();
+            titles.sort();
+            titles
+        };
+
+        assert_eq!(titles(None), vec!["Changelog", "Install", "Installieren"]);
+        assert_eq!(titles(Some("de-AT")), vec!["Changelog", "Installieren"]);
+        assert_eq!(titles(Some("EN")), vec!["Changelog", "Install"]);
+
+        // A language the index doesn't have searches every language.
+        assert_eq!(titles(Some("fr")), titles(None));
+
+        let title_output = crate::index_v3::search_titles_with_options(
+            &index,
+            "install",
+            &crate::SearchOptions {
+                language: Some("en".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(title_output.total_hit_count, 1);
+        assert_eq!(title_output.results[0].title, "Install");
+        assert!(index.metadata().stemming_varies);
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn results_with_the_same_score_are_ordered_by_the_tie_breaker() {
+        let file = |title: &str, url: &str| File {
+            explicit_source: Some(DataSource::Contents(
+                "one two three four five six seven eight nine ten eleven twelve".to_string(),
+            )),
+            title: title.to_string(),
+            url: url.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        };
+        let config = |tie_breaker| Config {
+            input: InputConfig {
+                files: vec![
+                    file("Beta", "/c"),
+                    file("Alpha", "/b"),
+                    file("Alpha", "/a"),
+                    file("Gamma", "/0"),
+                ],
+                ..Default::default()
+            },
+            output: OutputConfig {
+                tie_breaker,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let order = |tie_breaker| -> Vec<(String, String)> {
+            let bytes = bytes::Bytes::from(&build(&config(tie_breaker)).unwrap().index);
+            let index = Index::try_from(bytes.as_ref()).unwrap();
+            let results = crate::index_v3::search(&index, "seven").unwrap().results;
+            assert!(results
+                .windows(2)
+                .all(|pair| pair[0].score == pair[1].score));
+
+            results
+                .into_iter()
+                .map(|result| (result.entry.title, result.entry.url))
+                .collect()
+        };
+        let pairs = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(title, url)| (title.to_string(), url.to_string()))
+                .collect()
+        };
+
+        assert_eq!(
+            order(TieBreaker::Title),
+            pairs(&[
+                ("Alpha", "/a"),
+                ("Alpha", "/b"),
+                ("Beta", "/c"),
+                ("Gamma", "/0")
+            ])
+        );
+        assert_eq!(
+            order(TieBreaker::Url),
+            pairs(&[
+                ("Gamma", "/0"),
+                ("Alpha", "/a"),
+                ("Alpha", "/b"),
+                ("Beta", "/c")
+            ])
+        );
+        assert_eq!(
+            order(TieBreaker::Index),
+            pairs(&[
+                ("Beta", "/c"),
+                ("Alpha", "/b"),
+                ("Alpha", "/a"),
+                ("Gamma", "/0")
+            ])
+        );
+    }
 }
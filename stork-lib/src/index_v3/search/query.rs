@@ -0,0 +1,485 @@
+use crate::{
+    config::UnknownQueryFields,
+    index_v3::{emoji::split_emoji, WordListSource},
+    Date, SearchError,
+};
+
+/**
+ * A single word of a search query, along with the part of each document it
+ * has to match, if the query scoped it with `field:word` syntax. A negated
+ * word, written `-word`, excludes the documents it matches instead.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct QueryWord {
+    pub(super) word: String,
+    pub(super) source: Option<WordListSource>,
+    pub(super) negated: bool,
+}
+
+fn source_for_field(field: &str) -> Option<WordListSource> {
+    match field {
+        "title" => Some(WordListSource::Title),
+        "body" | "contents" => Some(WordListSource::Contents),
+        "url" => Some(WordListSource::Url),
+        "keywords" => Some(WordListSource::Keywords),
+        "summary" => Some(WordListSource::Summary),
+        "code" => Some(WordListSource::Code),
+        _ => None,
+    }
+}
+
+/**
+ * The name a query uses to scope a word to `source`.
+ */
+pub(super) fn field_name(source: WordListSource) -> &'static str {
+    match source {
+        WordListSource::Title => "title",
+        WordListSource::Contents => "body",
+        WordListSource::Url => "url",
+        WordListSource::Keywords => "keywords",
+        WordListSource::Summary => "summary",
+        WordListSource::Code => "code",
+    }
+}
+
+fn split_words(
+    text: &str,
+    source: Option<WordListSource>,
+    negated: bool,
+) -> impl Iterator<Item = QueryWord> + '_ {
+    text.split('-').map(move |word| QueryWord {
+        word: word.to_string(),
+        source,
+        negated,
+    })
+}
+
+/**
+ * Removes any `section:name` tokens from a lowercased query, returning the
+ * rest of the query and the last section named. A search with a section only
+ * returns documents whose facet, assigned from `input.facet_url_prefixes`
+ * when the index was built, is that section.
+ */
+pub(super) fn take_section(query: &str) -> (String, Option<String>) {
+    let mut section = None;
+    let rest: Vec<&str> = query
+        .split(' ')
+        .filter(|token| match token.strip_prefix("section:") {
+            Some(name) if !name.is_empty() => {
+                section = Some(name.to_string());
+                false
+            }
+            _ => true,
+        })
+        .collect();
+
+    (rest.join(" "), section)
+}
+
+/**
+ * The dates a search is limited to, from `after:` and `before:` tokens in the
+ * query. A search is limited to documents dated on or after `after`, and
+ * before `before`.
+ */
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct QueryDates {
+    pub(super) after: Option<Date>,
+    pub(super) before: Option<Date>,
+}
+
+/**
+ * Removes any `after:date` and `before:date` tokens from a lowercased query,
+ * returning the rest of the query and the dates. If a query names several
+ * of either, the latest `after` and the earliest `before` are kept.
+ */
+pub(super) fn take_dates(query: &str) -> Result<(String, QueryDates), SearchError> {
+    let mut dates = QueryDates::default();
+    let mut rest = vec![];
+
+    for token in query.split(' ') {
+        match token.split_once(':') {
+            Some(("after", date)) if !date.is_empty() => {
+                let date: Date = date.parse()?;
+                dates.after = dates.after.max(Some(date));
+            }
+            Some(("before", date)) if !date.is_empty() => {
+                let date: Date = date.parse()?;
+                dates.before = Some(dates.before.map_or(date, |before| before.min(date)));
+            }
+            _ => rest.push(token),
+        }
+    }
+
+    Ok((rest.join(" "), dates))
+}
+
+/**
+ * Splits a lowercased query into words. A `field:` prefix, like `title:` or
+ * `body:`, scopes the rest of its word to that part of each document; a
+ * prefix that isn't a field name is either kept as part of the word or
+ * rejected, depending on `unknown_fields`. A leading `-`, before any field,
+ * negates the word, and every part of it if it's hyphenated.
+ */
+pub(super) fn parse_query(
+    query: &str,
+    unknown_fields: UnknownQueryFields,
+) -> Result<Vec<QueryWord>, SearchError> {
+    let mut words = vec![];
+
+    for token in query.split(' ') {
+        let (token, negated) = match token.strip_prefix('-') {
+            Some(rest) if !rest.is_empty() => (rest, true),
+            _ => (token, false),
+        };
+
+        match token.split_once(':') {
+            Some((field, text)) if !field.is_empty() && !text.is_empty() => {
+                match (source_for_field(field), unknown_fields) {
+                    (Some(source), _) => words.extend(split_words(text, Some(source), negated)),
+                    (None, UnknownQueryFields::Literal) => {
+                        words.extend(split_words(token, None, negated));
+                    }
+                    (None, UnknownQueryFields::Error) => {
+                        return Err(SearchError::UnknownQueryField(field.to_string()));
+                    }
+                }
+            }
+            _ => words.extend(split_words(token, None, negated)),
+        }
+    }
+
+    Ok(words)
+}
+
+/**
+ * Splits each emoji out of the query's words, the way the words of an index
+ * built with `input.index_emoji` were split, so `launch🚀` looks for `launch`
+ * and `🚀`. Each piece keeps its word's field and negation.
+ */
+pub(super) fn split_emoji_query_words(words: Vec<QueryWord>) -> Vec<QueryWord> {
+    words
+        .into_iter()
+        .flat_map(|query_word| {
+            split_emoji(&query_word.word)
+                .into_iter()
+                .map(|word| QueryWord {
+                    word: word.to_string(),
+                    ..query_word.clone()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "build-v3")]
+    use crate::{
+        config::*,
+        index_v3::build::{build, errors::DocumentWarningKind},
+    };
+    use pretty_assertions::assert_eq;
+
+    fn word(word: &str, source: Option<WordListSource>) -> QueryWord {
+        QueryWord {
+            word: word.to_string(),
+            source,
+            negated: false,
+        }
+    }
+
+    fn negated(word: &str, source: Option<WordListSource>) -> QueryWord {
+        QueryWord {
+            negated: true,
+            ..self::word(word, source)
+        }
+    }
+
+    #[test]
+    fn fields_scope_their_words() {
+        assert_eq!(
+            parse_query(
+                "title:install body:error-code world",
+                UnknownQueryFields::Literal
+            )
+            .unwrap(),
+            vec![
+                word("install", Some(WordListSource::Title)),
+                word("error", Some(WordListSource::Contents)),
+                word("code", Some(WordListSource::Contents)),
+                word("world", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_literal_or_rejected() {
+        assert_eq!(
+            parse_query("author:ada", UnknownQueryFields::Literal).unwrap(),
+            vec![word("author:ada", None)]
+        );
+
+        assert!(matches!(
+            parse_query("author:ada", UnknownQueryFields::Error),
+            Err(SearchError::UnknownQueryField(field)) if field == "author"
+        ));
+    }
+
+    #[test]
+    fn sections_are_taken_out_of_the_query() {
+        assert_eq!(
+            take_section("install section:docs title:cli"),
+            ("install title:cli".to_string(), Some("docs".to_string()))
+        );
+        assert_eq!(
+            take_section("section: install"),
+            ("section: install".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn dates_are_taken_out_of_the_query() {
+        let date = |value: &str| value.parse::<Date>().unwrap();
+
+        assert_eq!(
+            take_dates("release after:2023-01-01 notes before:2024-01-01").unwrap(),
+            (
+                "release notes".to_string(),
+                QueryDates {
+                    after: Some(date("2023-01-01")),
+                    before: Some(date("2024-01-01")),
+                }
+            )
+        );
+        assert_eq!(
+            take_dates("after:2023-01-01 after:2022-06-01 before:2024-01-01 before:2025-01-01")
+                .unwrap()
+                .1,
+            QueryDates {
+                after: Some(date("2023-01-01")),
+                before: Some(date("2024-01-01")),
+            }
+        );
+        assert_eq!(
+            take_dates("after: notes").unwrap(),
+            ("after: notes".to_string(), QueryDates::default())
+        );
+        assert!(matches!(
+            take_dates("notes after:last-year"),
+            Err(SearchError::InvalidDate(error)) if error.0 == "last-year"
+        ));
+    }
+
+    #[test]
+    fn leading_hyphens_negate_words() {
+        assert_eq!(
+            parse_query(
+                "error -windows -title:blue-screen a-b",
+                UnknownQueryFields::Error
+            )
+            .unwrap(),
+            vec![
+                word("error", None),
+                negated("windows", None),
+                negated("blue", Some(WordListSource::Title)),
+                negated("screen", Some(WordListSource::Title)),
+                word("a", None),
+                word("b", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn colons_without_a_field_or_word_are_literal() {
+        assert_eq!(
+            parse_query(":title title:", UnknownQueryFields::Error).unwrap(),
+            vec![word(":title", None), word("title:", None)]
+        );
+    }
+
+    #[cfg(feature = "build-v3")]
+    fn plaintext_file(title: &str, contents: &str) -> File {
+        File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn field_scoped_query_only_matches_that_field() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    File {
+                        url: "/install".to_string(),
+                        ..plaintext_file(
+                            "Install guide",
+                            "one two three four five six seven eight nine ten eleven twelve",
+                        )
+                    },
+                    File {
+                        url: "/errors".to_string(),
+                        ..plaintext_file("Errors", "What to do when the install step fails")
+                    },
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let index = build(&config).unwrap().index;
+        let urls = |query| {
+            crate::index_v3::search(&index, query)
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| result.entry.url)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(urls("install").len(), 2);
+        assert_eq!(urls("title:install"), vec!["/install"]);
+        assert_eq!(urls("body:install"), vec!["/errors"]);
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn searches_can_be_limited_to_a_range_of_dates() {
+        let contents = "Release notes for the lighthouse keeper's logbook";
+        let dated = |title: &str, date: &str| File {
+            date: Some(date.to_string()),
+            ..plaintext_file(title, contents)
+        };
+
+        let build_result = build(&Config {
+            input: InputConfig {
+                extract_dates: true,
+                files: vec![
+                    dated("Winter", "2022-12-31"),
+                    dated("New Year", "2023-01-01T09:30:00Z"),
+                    File {
+                        explicit_source: Some(DataSource::Contents(format!(
+                            "---\ndate: 2023-06-15\n---\n\n{}",
+                            contents
+                        ))),
+                        title: "Summer".to_string(),
+                        filetype: Some(Filetype::Markdown),
+                        ..Default::default()
+                    },
+                    dated("Next Year", "2024-01-01"),
+                    plaintext_file("Undated", contents),
+                    dated("Invalid", "sometime in 2023"),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(build_result.warnings.iter().any(|warning| warning.kind
+            == DocumentWarningKind::InvalidDate("sometime in 2023".to_string())));
+
+        let index = build_result.index;
+        assert_eq!(index.entries[1].fields["date"], "2023-01-01");
+        assert_eq!(index.entries[2].fields["date"], "2023-06-15");
+
+        let date = |value: &str| Some(value.parse::<crate::Date>().unwrap());
+        let titles = |query: &str, options: crate::SearchOptions| -> Vec<String> {
+            let mut titles: Vec<String> =
+                crate::index_v3::search_with_options(&index, query, &options)
+                    .unwrap()
+                    .results
+                    .into_iter()
+                    .map(|result| result.entry.title)
+                    .collect();
+            titles.sort();
+            titles
+        };
+
+        // `after` includes its own date, and `before` doesn't.
+        let year_2023 = crate::SearchOptions {
+            after: date("2023-01-01"),
+            before: date("2024-01-01"),
+            ..Default::default()
+        };
+        assert_eq!(
+            titles("lighthouse", year_2023.clone()),
+            vec!["Invalid", "New Year", "Summer", "Undated"]
+        );
+        assert_eq!(
+            titles(
+                "lighthouse",
+                crate::SearchOptions {
+                    require_dates: true,
+                    ..year_2023
+                }
+            ),
+            vec!["New Year", "Summer"]
+        );
+
+        assert_eq!(
+            titles(
+                "lighthouse after:2023-06-15 before:2024-01-02",
+                crate::SearchOptions {
+                    require_dates: true,
+                    ..Default::default()
+                }
+            ),
+            vec!["Next Year", "Summer"]
+        );
+
+        // The query and the options narrow each other.
+        assert_eq!(
+            titles(
+                "lighthouse before:2023-06-15",
+                crate::SearchOptions {
+                    after: date("2023-01-01"),
+                    before: date("2024-06-01"),
+                    require_dates: true,
+                    ..Default::default()
+                }
+            ),
+            vec!["New Year"]
+        );
+
+        // Without a range, `require_dates` changes nothing.
+        assert_eq!(
+            titles(
+                "lighthouse",
+                crate::SearchOptions {
+                    require_dates: true,
+                    ..Default::default()
+                }
+            )
+            .len(),
+            6
+        );
+
+        assert!(matches!(
+            crate::index_v3::search(&index, "lighthouse after:2023-02-30"),
+            Err(crate::SearchError::InvalidDate(_))
+        ));
+
+        let title_output = crate::index_v3::search_titles_with_options(
+            &index,
+            "year",
+            &crate::SearchOptions {
+                before: date("2024-01-01"),
+                require_dates: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            title_output
+                .results
+                .into_iter()
+                .map(|result| result.title)
+                .collect::<Vec<_>>(),
+            vec!["New Year"]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+/// Words ending in a period that don't end a sentence.
+const ABBREVIATIONS: &[&str] = &[
+    "approx.", "cf.", "dr.", "etc.", "fig.", "jr.", "mr.", "mrs.", "ms.", "no.", "prof.", "sr.",
+    "st.", "vs.",
+];
+
+/// Characters that can close a sentence after its terminal punctuation, like
+/// the quote in `He said "stop."`
+const CLOSING_CHARACTERS: &[char] = &['"', '\'', ')', ']', '”', '’', '»'];
+
+/// Characters that can open a sentence before its first letter.
+const OPENING_CHARACTERS: &[char] = &['"', '\'', '(', '[', '“', '‘', '«', '¿', '¡'];
+
+/**
+ * The words shown in an excerpt, as the index of the first and one past the
+ * index of the last, and whether each end falls on a sentence boundary.
+ */
+#[derive(Debug, PartialEq)]
+pub(super) struct SnappedWindow {
+    pub(super) start: usize,
+    pub(super) end: usize,
+    pub(super) sentence_start: bool,
+    pub(super) sentence_end: bool,
+}
+
+/**
+ * Moves each end of the excerpt window `start..end` by up to `tolerance`
+ * words, to the nearest sentence boundary, preferring to widen the window
+ * when two boundaries are equally near. The window never shrinks past the
+ * matched words, `first_match..=last_match`. An end with no boundary near
+ * enough stays where it was.
+ */
+pub(super) fn snap_to_sentences<S: AsRef<str>>(
+    words: &[S],
+    start: usize,
+    end: usize,
+    (first_match, last_match): (usize, usize),
+    tolerance: usize,
+) -> SnappedWindow {
+    let start_candidates = start.saturating_sub(tolerance)..=(start + tolerance).min(first_match);
+    let snapped_start = start_candidates
+        .filter(|&candidate| is_sentence_boundary(words, candidate))
+        .min_by_key(|&candidate| (distance(candidate, start), candidate));
+
+    let end_candidates =
+        end.saturating_sub(tolerance).max(last_match + 1)..=(end + tolerance).min(words.len());
+    let snapped_end = end_candidates
+        .filter(|&candidate| is_sentence_boundary(words, candidate))
+        .min_by_key(|&candidate| (distance(candidate, end), usize::MAX - candidate));
+
+    SnappedWindow {
+        start: snapped_start.unwrap_or(start),
+        end: snapped_end.unwrap_or(end),
+        sentence_start: snapped_start.is_some(),
+        sentence_end: snapped_end.is_some(),
+    }
+}
+
+fn distance(a: usize, b: usize) -> usize {
+    a.max(b) - a.min(b)
+}
+
+/**
+ * Whether a sentence starts at `words[index]`: the previous word ends with
+ * terminal punctuation and isn't an abbreviation, and this word starts with
+ * an uppercase letter or an opening quote or bracket. The start and end of
+ * the text are boundaries too.
+ */
+fn is_sentence_boundary<S: AsRef<str>>(words: &[S], index: usize) -> bool {
+    if index == 0 || index >= words.len() {
+        return true;
+    }
+
+    ends_sentence(words[index - 1].as_ref()) && opens_sentence(words[index].as_ref())
+}
+
+fn ends_sentence(word: &str) -> bool {
+    let word = word.trim_end_matches(CLOSING_CHARACTERS);
+
+    if word.ends_with(['!', '?', '…']) {
+        return true;
+    }
+
+    let stem = match word.strip_suffix('.') {
+        Some(stem) => stem.trim_start_matches(OPENING_CHARACTERS),
+        None => return false,
+    };
+
+    // Initials, like the `J.` in `J. Smith`, and dotted abbreviations, like
+    // `e.g.` and `U.S.`, don't end sentences.
+    let is_initial = stem.chars().count() == 1;
+    let is_dotted = stem.contains('.');
+    let is_abbreviation = ABBREVIATIONS.contains(&format!("{}.", stem.to_lowercase()).as_str());
+
+    !stem.is_empty() && !is_initial && !is_dotted && !is_abbreviation
+}
+
+fn opens_sentence(word: &str) -> bool {
+    match word.chars().next() {
+        Some(c) => c.is_uppercase() || OPENING_CHARACTERS.contains(&c),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn words(text: &str) -> Vec<&str> {
+        text.split_whitespace().collect()
+    }
+
+    #[test]
+    fn sentences_end_at_terminal_punctuation_before_a_capital() {
+        let text = words("It works. Does it? \"Yes!\" (Really.) it does.");
+        let boundaries: Vec<usize> = (0..=text.len())
+            .filter(|&index| is_sentence_boundary(&text, index))
+            .collect();
+
+        assert_eq!(boundaries, vec![0, 2, 4, 5, text.len()]);
+    }
+
+    #[test]
+    fn abbreviations_and_initials_dont_end_sentences() {
+        let text = words(
+            "Ask Dr. Smith, e.g. About the U.S. Census, or J. Doe. Prof. Lee agrees, etc. Done.",
+        );
+        let boundaries: Vec<usize> = (0..=text.len())
+            .filter(|&index| is_sentence_boundary(&text, index))
+            .collect();
+
+        // Only the end of `Doe.` starts a new sentence, at `Prof.`.
+        assert_eq!(boundaries, vec![0, 11, text.len()]);
+    }
+
+    #[test]
+    fn window_snaps_to_nearest_boundaries() {
+        let text = words("One two three. Four five matched six seven. Eight nine ten.");
+
+        assert_eq!(
+            snap_to_sentences(&text, 4, 9, (5, 5), 2),
+            SnappedWindow {
+                start: 3,
+                end: 8,
+                sentence_start: true,
+                sentence_end: true,
+            }
+        );
+    }
+
+    #[test]
+    fn window_is_kept_without_a_boundary_nearby() {
+        let text = words("a b c d e f g h i j k l m n o p");
+
+        assert_eq!(
+            snap_to_sentences(&text, 4, 12, (8, 8), 2),
+            SnappedWindow {
+                start: 4,
+                end: 12,
+                sentence_start: false,
+                sentence_end: false,
+            }
+        );
+    }
+
+    #[test]
+    fn window_never_shrinks_past_matches() {
+        let text = words("one two three matched. Five six");
+
+        // The window can't start at `Five`, past the matched word. Its end is
+        // as near to `Five` as to the end of the text, so it widens.
+        let window = snap_to_sentences(&text, 3, 5, (3, 3), 1);
+        assert_eq!(window.start, 3);
+        assert!(!window.sentence_start);
+        assert_eq!(window.end, 6);
+        assert!(window.sentence_end);
+    }
+}
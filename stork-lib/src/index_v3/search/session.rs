@@ -0,0 +1,164 @@
+use super::{search_reusing, Index, SearchCache};
+use crate::{Output, SearchError, SearchOptions};
+
+/**
+ * Searches one index as a query is typed. Each search remembers what every
+ * word in the query matched, so when the next query only changes its last
+ * word, as typing another character does, the other words aren't looked up
+ * again, and the results of entries that the changed word doesn't match
+ * aren't built again. When characters are only added to the last word, its
+ * matches are narrowed from what it matched before, rather than looked up
+ * again. Anything else is searched from scratch.
+ *
+ * A session's results are always the same as `search_with_options` would
+ * return for the same query. A session must only be used with the index it
+ * first searched.
+ */
+#[derive(Debug, Default)]
+pub struct QuerySession {
+    cache: SearchCache,
+}
+
+impl QuerySession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Searches the index for `query`, reusing what the session's previous
+    /// search found.
+    pub fn search(
+        &mut self,
+        index: &Index,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Output, SearchError> {
+        search_reusing(index, query, options, Some(&mut self.cache))
+    }
+}
+
+#[cfg(all(test, feature = "build-v3"))]
+mod tests {
+    use super::super::{narrowed_word_excerpts, word_excerpts, IntermediateExcerpt, QueryWord};
+    use crate::config::*;
+    use crate::index_v3::build::build;
+    use crate::index_v3::{EntryIndex, Score, WordListSource};
+
+    fn plaintext_file(title: &str, contents: &str) -> File {
+        File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn query_sessions_return_the_same_results_as_full_searches() {
+        let index = build(&Config {
+            input: InputConfig {
+                files: vec![
+                    plaintext_file("Mounts", "Every telescope mount is different"),
+                    plaintext_file("Setup", "Mount the telescope, then level the tripod"),
+                    plaintext_file("Tripods", "A level tripod keeps the telescope steady"),
+                    plaintext_file("Lenses", "Clean each lens before observing"),
+                    plaintext_file("Mounting", "Mounting telescopes on mounts"),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap()
+        .index;
+
+        let mut session = crate::index_v3::QuerySession::new();
+        let options = crate::SearchOptions::default();
+        for query in [
+            "tel",
+            "telescope",
+            "telescope m",
+            "telescope mount",
+            "telescope mount lev",
+            "telescope mount level",
+            "telescope tripod level",
+            "telescope -mount",
+            "mou",
+            "moun",
+            "mount",
+            "mounti",
+            "mounting",
+            "title:tri",
+            "title:tripods",
+            "lens",
+            "lensx",
+            "",
+            "telescope mount level",
+        ] {
+            assert_eq!(
+                session.search(&index, query, &options).unwrap(),
+                crate::index_v3::search_with_options(&index, query, &options).unwrap(),
+                "{}",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn extended_words_are_narrowed_to_the_same_excerpts_as_a_lookup() {
+        let index = build(&Config {
+            input: InputConfig {
+                files: vec![
+                    plaintext_file("Telescopes", "A telescope, two telescopes"),
+                    plaintext_file("Telephones", "Tell them to use the telephone"),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap()
+        .index;
+
+        let query_word = |word: &str| QueryWord {
+            word: word.to_string(),
+            source: None,
+            negated: false,
+        };
+        let summary = |excerpts: Vec<IntermediateExcerpt>| -> Vec<(String, EntryIndex, Score, usize, WordListSource)> {
+            excerpts
+                .into_iter()
+                .map(|ie| (ie.query, ie.entry_index, ie.score, ie.word_index, ie.source))
+                .collect()
+        };
+
+        for (previous, word) in [
+            ("tel", "tele"),
+            ("tele", "telesc"),
+            ("telesc", "telescope"),
+            ("telescope", "telescopes"),
+            ("tel", "telephone"),
+        ] {
+            let previous = query_word(previous);
+            let narrowed = narrowed_word_excerpts(
+                &index,
+                &query_word(word),
+                &previous,
+                &word_excerpts(&index, &previous),
+            )
+            .unwrap();
+            assert_eq!(
+                summary(narrowed),
+                summary(word_excerpts(&index, &query_word(word))),
+                "{}",
+                word
+            );
+        }
+
+        let tele = query_word("tele");
+        let tele_excerpts = word_excerpts(&index, &tele);
+        assert!(
+            narrowed_word_excerpts(&index, &query_word("tel"), &tele, &tele_excerpts).is_none()
+        );
+        assert!(
+            narrowed_word_excerpts(&index, &query_word("telexyz"), &tele, &tele_excerpts).is_none()
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use crate::index_v3::{
+    ngrams::candidate_entries, scores::weighted_score, Entry, EntryIndex, WordListSource,
+};
+use crate::V3Index as Index;
+
+use super::intermediate_excerpt::IntermediateExcerpt;
+
+/**
+ * Finds the words that contain `query` anywhere within them, for a query
+ * word that no container matched. The entries that have every one of the
+ * query's n-grams are candidates; each candidate's title and contents are
+ * then checked word by word, so only words that really contain the query
+ * are returned.
+ */
+pub(super) fn substring_excerpts(index: &Index, query: &str) -> Vec<IntermediateExcerpt> {
+    let ngram_config = match &index.config.index_ngrams {
+        Some(ngram_config) => ngram_config,
+        None => return vec![],
+    };
+
+    candidate_entries(&index.ngrams, ngram_config, query)
+        .into_iter()
+        .flat_map(|entry_index| entry_excerpts(index, entry_index, query))
+        .collect()
+}
+
+fn entry_excerpts(index: &Index, entry_index: EntryIndex, query: &str) -> Vec<IntermediateExcerpt> {
+    let entry: &Entry = match index.entries.get(entry_index) {
+        Some(entry) => entry,
+        None => return vec![],
+    };
+
+    // Words are split the same way they were when the index was built, so
+    // that word indexes line up with the stored excerpts.
+    let title_words = entry
+        .title
+        .split(|c: char| c.is_ascii_whitespace() || c == '-')
+        .filter(|word| !word.is_empty());
+    let contents_words = entry.contents.split_whitespace();
+
+    let title_matches = title_words
+        .enumerate()
+        .map(|(word_index, word)| (WordListSource::Title, word_index, word));
+    let contents_matches = contents_words
+        .enumerate()
+        .map(|(word_index, word)| (WordListSource::Contents, word_index, word));
+
+    title_matches
+        .chain(contents_matches)
+        .filter_map(|(source, word_index, word)| {
+            let (normalized_word, match_offset) = find_in_word(word, query)?;
+
+            // The word's own excerpt carries its annotations and fields.
+            let stored_excerpt = index
+                .containers
+                .get(&normalized_word)
+                .and_then(|container| container.results.get(&entry_index))
+                .and_then(|result| {
+                    result.excerpts.iter().find(|excerpt| {
+                        excerpt.source == source && excerpt.word_index == word_index
+                    })
+                });
+
+            Some(IntermediateExcerpt {
+                query: query.to_string(),
+                match_offset,
+                entry_index,
+                score: weighted_score(index.config.match_weights.substring),
+                source,
+                word_index,
+                internal_annotations: stored_excerpt
+                    .map(|excerpt| excerpt.internal_annotations.clone())
+                    .unwrap_or_default(),
+                fields: stored_excerpt
+                    .map(|excerpt| excerpt.fields.clone())
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/**
+ * If the word, normalized the way it was indexed, contains the query,
+ * returns the normalized word and how many characters into the word as
+ * written the query starts.
+ */
+fn find_in_word(word: &str, query: &str) -> Option<(String, usize)> {
+    let trimmed_start = word.trim_start_matches(|c: char| c.is_ascii_punctuation());
+    let leading_punctuation = word.len() - trimmed_start.len();
+    let normalized_word = trimmed_start
+        .trim_end_matches(|c: char| c.is_ascii_punctuation())
+        .to_lowercase();
+
+    let byte_offset = normalized_word.find(query)?;
+    let match_offset = leading_punctuation + normalized_word[..byte_offset].chars().count();
+    Some((normalized_word, match_offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn query_is_found_within_words_as_written() {
+        assert_eq!(
+            find_in_word("(ABX2004),", "x200"),
+            Some(("abx2004".to_string(), 3))
+        );
+        assert_eq!(
+            find_in_word("Ünïcödé", "ïcö"),
+            Some(("ünïcödé".to_string(), 2))
+        );
+        assert_eq!(find_in_word("ABX2104", "x200"), None);
+    }
+}
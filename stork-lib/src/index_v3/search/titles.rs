@@ -0,0 +1,255 @@
+use std::borrow::Cow;
+
+use super::{break_tie, parse_query, resolve_language, take_dates, take_section, DateRange, Scope};
+use crate::config::{MatchWeights, QueryOperator};
+use crate::index_v3::scores::{scale_by_multiplier, weighted_score, STOPWORD_SCORE};
+use crate::index_v3::{width::normalize_width, WordListSource};
+use crate::stopwords;
+use crate::{SearchError, SearchOptions, TitleOutput, TitleResult, V3Index as Index};
+
+#[cfg(test)]
+pub fn search_titles(index: &Index, query: &str) -> Result<TitleOutput, SearchError> {
+    search_titles_with_options(index, query, &SearchOptions::default())
+}
+
+/**
+ * Searches the titles of the index's documents and nothing else, limited to
+ * the documents `options` allow. No containers are read and no excerpts are
+ * built, so this is far cheaper than `search_with_options` when only
+ * matching pages need to be listed.
+ *
+ * Each query word adds the score of an exact match to a document whose title
+ * has that word, or of a prefix match if a word of the title only starts with
+ * it, as weighted by `output.match_weights`. The total is scaled by the
+ * document's `input.path_score_multipliers` rule, if it has one. With the
+ * `and` query operator, every query word but stopwords has to match.
+ * Documents whose titles have a negated `-word` are left out.
+ * Words scoped to any part of the document other than `title:` are ignored,
+ * and a `section:` word limits the results to that section.
+ */
+pub fn search_titles_with_options(
+    index: &Index,
+    query: &str,
+    options: &SearchOptions,
+) -> Result<TitleOutput, SearchError> {
+    let _search_span = tracing::debug_span!("search_titles", query).entered();
+
+    if query.trim().chars().count() < index.config.min_query_length as usize {
+        return Ok(TitleOutput {
+            results: vec![],
+            total_hit_count: 0,
+            url_prefix: index.config.url_prefix.clone(),
+        });
+    }
+
+    let query = if index.config.normalize_width {
+        normalize_width(query)
+    } else {
+        Cow::Borrowed(query)
+    };
+    let (normalized_query, section) = take_section(&query.to_lowercase());
+    let (normalized_query, dates) = take_dates(&normalized_query)?;
+    let scope = Scope {
+        section: section.as_deref(),
+        language: resolve_language(index, options.language.as_deref()),
+        dates: DateRange::new(dates, options),
+    };
+    let parsed_query = parse_query(&normalized_query, index.config.unknown_query_fields)?;
+    let title_words_in_query = |negated: bool| -> Vec<&str> {
+        parsed_query
+            .iter()
+            .filter(|query_word| {
+                query_word.negated == negated
+                    && query_word
+                        .source
+                        .map_or(true, |source| source == WordListSource::Title)
+            })
+            .map(|query_word| query_word.word.trim_matches(|c: char| !c.is_alphanumeric()))
+            .filter(|word| !word.is_empty())
+            .collect()
+    };
+    let words_in_query = title_words_in_query(false);
+    let negated_words = title_words_in_query(true);
+
+    let mut results: Vec<TitleResult> = index
+        .entries
+        .iter()
+        .filter(|entry| {
+            scope.includes_section(entry)
+                && scope.includes_language(entry)
+                && scope.includes_date(entry)
+        })
+        .filter_map(|entry| {
+            let title = entry.title.to_lowercase();
+            let title_words: Vec<&str> = title
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .collect();
+
+            if negated_words
+                .iter()
+                .any(|negated_word| title_words.contains(negated_word))
+            {
+                return None;
+            }
+
+            let word_scores: Vec<usize> = words_in_query
+                .iter()
+                .map(|query_word| {
+                    score_query_word(query_word, &title_words, &index.config.match_weights)
+                })
+                .collect();
+            let score: usize = word_scores.iter().sum();
+
+            let matches_every_word = words_in_query
+                .iter()
+                .zip(&word_scores)
+                .all(|(query_word, &word_score)| word_score > 0 || stopwords.contains(query_word));
+            let matches_query = match index.config.query_operator {
+                QueryOperator::Or => score > 0,
+                QueryOperator::And => score > 0 && matches_every_word,
+            };
+
+            matches_query.then(|| TitleResult {
+                title: entry.title.clone(),
+                url: entry.url.clone(),
+                score: scale_by_multiplier(score, entry.score_multiplier),
+            })
+        })
+        .collect();
+
+    let total_hit_count = results.len();
+
+    results.sort_by(|a, b| {
+        b.score.cmp(&a.score).then_with(|| {
+            break_tie(
+                index.config.tie_breaker,
+                (&a.title, &a.url),
+                (&b.title, &b.url),
+            )
+        })
+    });
+    results.truncate(index.config.displayed_results_count as usize);
+
+    Ok(TitleOutput {
+        results,
+        total_hit_count,
+        url_prefix: index.config.url_prefix.clone(),
+    })
+}
+
+fn score_query_word(query_word: &str, title_words: &[&str], weights: &MatchWeights) -> usize {
+    let score = if title_words.contains(&query_word) {
+        weighted_score(weights.exact)
+    } else if title_words.iter().any(|word| word.starts_with(query_word)) {
+        weighted_score(weights.prefix)
+    } else {
+        return 0;
+    };
+
+    if stopwords.contains(&query_word) {
+        STOPWORD_SCORE as usize
+    } else {
+        score as usize
+    }
+}
+
+#[cfg(all(test, feature = "build-v3"))]
+mod tests {
+    use crate::config::*;
+    use crate::index_v3::build::build;
+    use crate::V3Index as Index;
+
+    fn plaintext_file(title: &str, contents: &str) -> File {
+        File {
+            explicit_source: Some(DataSource::Contents(contents.to_string())),
+            title: title.to_string(),
+            filetype: Some(Filetype::PlainText),
+            ..Default::default()
+        }
+    }
+
+    const TWELVE_WORDS: &str = "one two three four five six seven eight nine ten eleven twelve";
+
+    #[test]
+    fn title_search_only_matches_titles() {
+        let config = Config {
+            input: InputConfig {
+                files: vec![
+                    plaintext_file("Installation", TWELVE_WORDS),
+                    plaintext_file("Install guide", TWELVE_WORDS),
+                    plaintext_file("Errors", "What to do when the install step fails"),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let index = build(&config).unwrap().index;
+        let titles = |query| {
+            crate::index_v3::search_titles(&index, query)
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| (result.title, result.score))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            titles("install"),
+            vec![
+                ("Install guide".to_string(), 128),
+                ("Installation".to_string(), 127)
+            ]
+        );
+        assert_eq!(
+            titles("Guide, errors"),
+            vec![
+                ("Errors".to_string(), 128),
+                ("Install guide".to_string(), 128)
+            ]
+        );
+        assert!(titles("body:install").is_empty());
+    }
+
+    #[test]
+    fn title_matches_near_the_start_of_the_title_rank_higher_with_position_decay() {
+        let config = |title_position_decay, title_position_decay_curve| Config {
+            input: InputConfig {
+                files: vec![
+                    plaintext_file("Installation fails on Windows", TWELVE_WORDS),
+                    plaintext_file("Windows installation fails", TWELVE_WORDS),
+                ],
+                title_position_decay,
+                title_position_decay_curve,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let scores = |config: &Config| -> Vec<(String, usize)> {
+            let bytes = bytes::Bytes::from(&build(config).unwrap().index);
+            let index = Index::try_from(bytes.as_ref()).unwrap();
+            crate::index_v3::search(&index, "windows")
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| (result.entry.title, result.score))
+                .collect()
+        };
+
+        let undecayed = scores(&config(1.0, TitlePositionDecayCurve::Exponential));
+        assert_eq!(undecayed.len(), 2);
+        assert_eq!(undecayed[0].1, undecayed[1].1);
+
+        for curve in [
+            TitlePositionDecayCurve::Exponential,
+            TitlePositionDecayCurve::Linear,
+        ] {
+            let decayed = scores(&config(0.5, curve));
+            assert_eq!(decayed[0].0, "Windows installation fails");
+            assert_eq!(decayed[1].0, "Installation fails on Windows");
+            assert!(decayed[0].1 > decayed[1].1);
+            assert_eq!(decayed[0].1, undecayed[0].1);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    read::split_version_header, write::with_version_header, Container, Entry, EntryIndex, Index,
+    PassthroughConfig, TermSet,
+};
+use crate::StoredDocumentError;
+
+/**
+ * An index split into a manifest and a number of shard files.
+ *
+ * The manifest holds everything needed to display results, along with the
+ * shard number of every search term. Each shard holds the containers for a
+ * contiguous range of the sorted search terms, so a frontend only has to
+ * fetch the shards that the terms of a query live in.
+ */
+#[derive(Debug, Clone)]
+pub struct ShardedIndex {
+    pub manifest: Bytes,
+    pub shards: Vec<Bytes>,
+}
+
+/**
+ * An error that may occur when reassembling an index from its manifest and
+ * shards.
+ */
+#[derive(Debug, Error)]
+pub enum ShardedIndexReadError {
+    #[error("{0}")]
+    DecodeError(#[from] rmp_serde::decode::Error),
+
+    #[error("The manifest lists {expected} shards, but {found} were given.")]
+    WrongShardCount { expected: usize, found: usize },
+
+    #[error("The search term `{0}` is missing from shard {1}.")]
+    MissingTerm(String, usize),
+
+    #[error("The file is too short to be a Stork index manifest or shard.")]
+    FileTooShort,
+
+    #[error("Expected a file with version `{expected}`, but found version `{found}`.")]
+    WrongVersion {
+        expected: &'static str,
+        found: String,
+    },
+}
+
+const MANIFEST_VERSION: &str = "stork-3-manifest";
+const SHARD_VERSION: &str = "stork-3-shard";
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    config: PassthroughConfig,
+    entries: Vec<Entry>,
+    shard_count: usize,
+    shards: BTreeMap<String, usize>,
+
+    #[serde(default)]
+    document_error_count: usize,
+    #[serde(default)]
+    document_errors: Vec<StoredDocumentError>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    ngrams: BTreeMap<String, Vec<EntryIndex>>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Shard {
+    containers: BTreeMap<String, Container>,
+}
+
+impl Index {
+    /**
+     * Splits the index into a manifest and `shard_count` shards. Terms are
+     * spread evenly across the shards in sorted order; if there are fewer
+     * terms than shards, some shards are empty.
+     */
+    pub fn to_shards(&self, shard_count: usize) -> ShardedIndex {
+        let shard_count = shard_count.max(1);
+        let term_count = self.containers.len();
+
+        let mut shards: Vec<Shard> = (0..shard_count).map(|_| Shard::default()).collect();
+        let mut shard_numbers = BTreeMap::new();
+
+        for (term_index, (term, container)) in self.containers.iter().enumerate() {
+            let shard_number = term_index * shard_count / term_count;
+            shard_numbers.insert(term.clone(), shard_number);
+            shards[shard_number]
+                .containers
+                .insert(term.clone(), container.clone());
+        }
+
+        let manifest = Manifest {
+            config: self.config.clone(),
+            entries: self.entries.clone(),
+            shard_count,
+            shards: shard_numbers,
+            document_error_count: self.document_error_count,
+            document_errors: self.document_errors.clone(),
+            ngrams: self.ngrams.clone(),
+        };
+
+        ShardedIndex {
+            manifest: with_version_header(MANIFEST_VERSION, rmp_serde::to_vec(&manifest).unwrap()),
+            shards: shards
+                .iter()
+                .map(|shard| with_version_header(SHARD_VERSION, rmp_serde::to_vec(shard).unwrap()))
+                .collect(),
+        }
+    }
+
+    /**
+     * Reassembles a full index from the manifest and shards written by
+     * [`Index::to_shards`]. The shards must be given in order.
+     */
+    pub fn from_shards<S: AsRef<[u8]>>(
+        manifest: &[u8],
+        shards: &[S],
+    ) -> Result<Index, ShardedIndexReadError> {
+        let manifest: Manifest =
+            rmp_serde::from_read_ref(body_with_version(manifest, MANIFEST_VERSION)?)?;
+
+        if manifest.shard_count != shards.len() {
+            return Err(ShardedIndexReadError::WrongShardCount {
+                expected: manifest.shard_count,
+                found: shards.len(),
+            });
+        }
+
+        let mut shards = shards
+            .iter()
+            .map(|shard| {
+                let body = body_with_version(shard.as_ref(), SHARD_VERSION)?;
+                Ok(rmp_serde::from_read_ref(body)?)
+            })
+            .collect::<Result<Vec<Shard>, ShardedIndexReadError>>()?;
+
+        let mut containers = BTreeMap::new();
+        for (term, shard_number) in manifest.shards {
+            let container = shards
+                .get_mut(shard_number)
+                .and_then(|shard| shard.containers.remove(&term))
+                .ok_or_else(|| ShardedIndexReadError::MissingTerm(term.clone(), shard_number))?;
+            containers.insert(term, container);
+        }
+
+        Ok(Index {
+            config: manifest.config,
+            entries: manifest.entries,
+            terms: TermSet::from_sorted(containers.keys()),
+            containers,
+            document_error_count: manifest.document_error_count,
+            document_errors: manifest.document_errors,
+            ngrams: manifest.ngrams,
+        })
+    }
+}
+
+/// The body of a manifest or shard file, if it has the version `expected`.
+fn body_with_version<'a>(
+    file: &'a [u8],
+    expected: &'static str,
+) -> Result<&'a [u8], ShardedIndexReadError> {
+    match split_version_header(file) {
+        Some((version, body)) if version == expected => Ok(body),
+        Some((version, _)) => Err(ShardedIndexReadError::WrongVersion {
+            expected,
+            found: version.to_string(),
+        }),
+        None => Err(ShardedIndexReadError::FileTooShort),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    fn federalist() -> Index {
+        let bytes = std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap();
+        Index::try_from(bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn sharded_index_round_trips() {
+        let index = federalist();
+        let sharded = index.to_shards(4);
+        assert_eq!(sharded.shards.len(), 4);
+
+        let reassembled = Index::from_shards(&sharded.manifest, &sharded.shards).unwrap();
+        assert_eq!(Bytes::from(&reassembled), Bytes::from(&index));
+    }
+
+    #[test]
+    fn more_shards_than_terms_round_trips() {
+        let mut index = federalist();
+        index.containers = index.containers.into_iter().take(2).collect();
+        index.index_terms();
+
+        let sharded = index.to_shards(5);
+        assert_eq!(sharded.shards.len(), 5);
+
+        let reassembled = Index::from_shards(&sharded.manifest, &sharded.shards).unwrap();
+        assert_eq!(Bytes::from(&reassembled), Bytes::from(&index));
+    }
+
+    #[test]
+    fn missing_shards_are_an_error() {
+        let sharded = federalist().to_shards(3);
+
+        assert!(matches!(
+            Index::from_shards(&sharded.manifest, &sharded.shards[..2]),
+            Err(ShardedIndexReadError::WrongShardCount {
+                expected: 3,
+                found: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn truncated_or_swapped_files_are_an_error() {
+        let sharded = federalist().to_shards(2);
+
+        assert!(matches!(
+            Index::from_shards(&sharded.manifest[..10], &sharded.shards),
+            Err(ShardedIndexReadError::FileTooShort)
+        ));
+
+        let truncated_shard = sharded.shards[1].slice(..sharded.shards[1].len() - 1);
+        assert!(matches!(
+            Index::from_shards(
+                &sharded.manifest,
+                &[sharded.shards[0].clone(), truncated_shard]
+            ),
+            Err(ShardedIndexReadError::FileTooShort)
+        ));
+
+        assert!(matches!(
+            Index::from_shards(&sharded.shards[0], &sharded.shards),
+            Err(ShardedIndexReadError::WrongVersion {
+                expected: "stork-3-manifest",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,97 @@
+use std::fmt;
+
+use fst::{
+    automaton::{StartsWith, Str},
+    set::Stream,
+    Automaton, IntoStreamer, Set,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/**
+ * The search terms of an index, stored as a finite state transducer so the
+ * terms starting with a prefix can be streamed in sorted order without
+ * walking the term map. The same terms always build the same bytes.
+ */
+#[derive(Clone, Debug, Default)]
+pub(super) struct TermSet(Set<Vec<u8>>);
+
+impl TermSet {
+    /// Builds the set from terms given in lexicographic order without
+    /// duplicates, like the keys of a `BTreeMap`.
+    pub(super) fn from_sorted<'a>(terms: impl Iterator<Item = &'a String>) -> Self {
+        TermSet(Set::from_iter(terms).expect("Terms should be sorted and unique"))
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The terms that start with `prefix`, in lexicographic order.
+    pub(super) fn with_prefix<'s, 'p>(
+        &'s self,
+        prefix: &'p str,
+    ) -> Stream<'s, StartsWith<Str<'p>>> {
+        self.0.search(Str::new(prefix).starts_with()).into_stream()
+    }
+}
+
+impl Serialize for TermSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0.as_fst().as_bytes())
+    }
+}
+
+impl<'de> Deserialize<'de> for TermSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_byte_buf(TermSetVisitor)
+    }
+}
+
+struct TermSetVisitor;
+
+impl<'de> de::Visitor<'de> for TermSetVisitor {
+    type Value = TermSet;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("the bytes of a finite state transducer")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<TermSet, E> {
+        self.visit_byte_buf(bytes.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<TermSet, E> {
+        Set::new(bytes).map(TermSet).map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fst::Streamer;
+
+    fn terms(words: &[&str]) -> TermSet {
+        let words: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+        TermSet::from_sorted(words.iter())
+    }
+
+    #[test]
+    fn terms_with_a_prefix_are_streamed_in_order() {
+        let set = terms(&["cat", "cats", "dog", "doge", "dogs", "dot"]);
+        let mut stream = set.with_prefix("dog");
+        let mut found = vec![];
+        while let Some(term) = stream.next() {
+            found.push(String::from_utf8(term.to_vec()).unwrap());
+        }
+        assert_eq!(found, vec!["dog", "doge", "dogs"]);
+    }
+
+    #[test]
+    fn term_set_round_trips_through_messagepack() {
+        let set = terms(&["cat", "dog"]);
+        let bytes = rmp_serde::to_vec(&set).unwrap();
+        let read: TermSet = rmp_serde::from_read_ref(&bytes).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(rmp_serde::to_vec(&read).unwrap(), bytes);
+    }
+}
@@ -0,0 +1,294 @@
+use thiserror::Error;
+
+use super::{Index, WordListSource};
+
+/**
+ * Something about an index that's never true of one Stork built, found by
+ * `verify_index`. These usually mean the index was corrupted after it was
+ * built, or was written by an incompatible version of Stork.
+ */
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum IndexInconsistency {
+    #[error("The container for `{container}` has a result for entry {entry_index}, but the index only has {entry_count} entries.")]
+    ResultEntryOutOfRange {
+        container: String,
+        entry_index: usize,
+        entry_count: usize,
+    },
+
+    #[error("The container for `{container}` has an excerpt at word {word_index} of entry {entry_index}'s {word_list}, which only has {word_count} words.")]
+    ExcerptOutOfRange {
+        container: String,
+        entry_index: usize,
+
+        /// `title` or `contents`.
+        word_list: &'static str,
+        word_index: usize,
+        word_count: usize,
+    },
+
+    #[error("The container for `{container}` is an alias of `{target}`, which has no container.")]
+    MissingAliasTarget { container: String, target: String },
+
+    #[error("The index records {count} document errors, but stores {stored} of them.")]
+    DocumentErrorCountTooLow { count: usize, stored: usize },
+
+    #[error("The index's `{option}` is {value}, so {consequence}.")]
+    InvalidConfig {
+        option: &'static str,
+        value: String,
+        consequence: &'static str,
+    },
+}
+
+/**
+ * Checks that an index is internally consistent: every container result
+ * refers to an entry that exists, every excerpt to a word within its entry,
+ * and every alias to a container that exists, and the index's search options
+ * are usable. Returns every inconsistency found, in container order.
+ *
+ * Excerpts of URL and keyword words aren't checked, since the index doesn't
+ * keep those word lists. Scores are unsigned integers, so they can't be
+ * negative or non-finite.
+ */
+pub fn verify_index(index: &Index) -> Result<(), Vec<IndexInconsistency>> {
+    let mut inconsistencies = vec![];
+    let entry_count = index.entries.len();
+
+    for (word, container) in &index.containers {
+        for (&entry_index, result) in &container.results {
+            let entry = match index.entries.get(entry_index) {
+                Some(entry) => entry,
+                None => {
+                    inconsistencies.push(IndexInconsistency::ResultEntryOutOfRange {
+                        container: word.clone(),
+                        entry_index,
+                        entry_count,
+                    });
+                    continue;
+                }
+            };
+
+            for excerpt in &result.excerpts {
+                let (word_list, word_count) = match excerpt.source {
+                    WordListSource::Contents | WordListSource::Code => {
+                        ("contents", entry.contents.split_whitespace().count())
+                    }
+                    WordListSource::Title => ("title", title_word_count(&entry.title)),
+                    WordListSource::Url | WordListSource::Keywords | WordListSource::Summary => {
+                        continue
+                    }
+                };
+
+                if excerpt.word_index >= word_count {
+                    inconsistencies.push(IndexInconsistency::ExcerptOutOfRange {
+                        container: word.clone(),
+                        entry_index,
+                        word_list,
+                        word_index: excerpt.word_index,
+                        word_count,
+                    });
+                }
+            }
+        }
+
+        for target in container.aliases.keys() {
+            if !index.containers.contains_key(target) {
+                inconsistencies.push(IndexInconsistency::MissingAliasTarget {
+                    container: word.clone(),
+                    target: target.clone(),
+                });
+            }
+        }
+    }
+
+    if index.document_error_count < index.document_errors.len() {
+        inconsistencies.push(IndexInconsistency::DocumentErrorCountTooLow {
+            count: index.document_error_count,
+            stored: index.document_errors.len(),
+        });
+    }
+
+    if index.config.displayed_results_count == 0 {
+        inconsistencies.push(IndexInconsistency::InvalidConfig {
+            option: "displayed_results_count",
+            value: "0".to_string(),
+            consequence: "searches never return any results",
+        });
+    }
+
+    if index.config.excerpt_buffer == 0 && index.config.excerpts_per_result > 0 {
+        inconsistencies.push(IndexInconsistency::InvalidConfig {
+            option: "excerpt_buffer",
+            value: "0".to_string(),
+            consequence: "every excerpt is empty",
+        });
+    }
+
+    if inconsistencies.is_empty() {
+        Ok(())
+    } else {
+        Err(inconsistencies)
+    }
+}
+
+/// Titles are split into words the same way when they're indexed.
+fn title_word_count(title: &str) -> usize {
+    title
+        .split(|c: char| c.is_ascii_whitespace() || c == '-')
+        .filter(|word| !word.trim().is_empty())
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use super::*;
+    use crate::index_v3::{Container, Entry, Excerpt, PassthroughConfig, SearchResult, TermSet};
+    use crate::StoredDocumentError;
+
+    fn result(excerpts: Vec<(usize, WordListSource)>) -> SearchResult {
+        SearchResult {
+            excerpts: excerpts
+                .into_iter()
+                .map(|(word_index, source)| Excerpt {
+                    word_index,
+                    source,
+                    ..Excerpt::default()
+                })
+                .collect(),
+            score: 128,
+        }
+    }
+
+    fn consistent_index() -> Index {
+        let mut containers = BTreeMap::new();
+        containers.insert(
+            "stork".to_string(),
+            Container {
+                results: BTreeMap::from([(
+                    0,
+                    result(vec![
+                        (0, WordListSource::Title),
+                        (2, WordListSource::Contents),
+                    ]),
+                )]),
+                ..Container::default()
+            },
+        );
+        containers.insert(
+            "sto".to_string(),
+            Container {
+                aliases: BTreeMap::from([("stork".to_string(), 126)]),
+                ..Container::default()
+            },
+        );
+
+        Index {
+            config: PassthroughConfig::default(),
+            entries: vec![Entry {
+                contents: "Searching with Stork is fast".to_string(),
+                title: "Stork search".to_string(),
+                url: "/stork".to_string(),
+                fields: HashMap::new(),
+                score_multiplier: None,
+                word_count: None,
+                text: None,
+                item_starts: vec![],
+            }],
+            terms: TermSet::from_sorted(containers.keys()),
+            containers,
+            document_error_count: 0,
+            document_errors: vec![],
+            ngrams: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn consistent_index_verifies() {
+        assert_eq!(verify_index(&consistent_index()), Ok(()));
+    }
+
+    #[test]
+    #[cfg(feature = "build-v3")]
+    fn built_index_verifies() {
+        use std::convert::TryFrom;
+
+        let config = crate::Config::try_from(
+            r#"
+            [input]
+            files = [
+                { title = "Getting-started guide", url = "/start", contents = "Install Stork, then build an index." },
+                { title = "Configuration", url = "/config", contents = "Every option has a sensible default." },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        let index = crate::index_v3::build(&config).unwrap().index;
+        assert_eq!(verify_index(&index), Ok(()));
+    }
+
+    #[test]
+    fn inconsistencies_are_all_reported() {
+        let mut index = consistent_index();
+        index.config.displayed_results_count = 0;
+        index.document_error_count = 0;
+        index.document_errors = vec![StoredDocumentError {
+            file: "missing.md".to_string(),
+            code: "E002_FILE_NOT_FOUND".to_string(),
+            message: "The file `missing.md` could not be found.".to_string(),
+        }];
+
+        let stork = index.containers.get_mut("stork").unwrap();
+        stork.results.insert(3, result(vec![]));
+        stork.results.insert(
+            0,
+            result(vec![
+                (2, WordListSource::Title),
+                (5, WordListSource::Contents),
+                (40, WordListSource::Url),
+            ]),
+        );
+        stork.aliases.insert("storks".to_string(), 64);
+
+        assert_eq!(
+            verify_index(&index),
+            Err(vec![
+                IndexInconsistency::ExcerptOutOfRange {
+                    container: "stork".to_string(),
+                    entry_index: 0,
+                    word_list: "title",
+                    word_index: 2,
+                    word_count: 2,
+                },
+                IndexInconsistency::ExcerptOutOfRange {
+                    container: "stork".to_string(),
+                    entry_index: 0,
+                    word_list: "contents",
+                    word_index: 5,
+                    word_count: 5,
+                },
+                IndexInconsistency::ResultEntryOutOfRange {
+                    container: "stork".to_string(),
+                    entry_index: 3,
+                    entry_count: 1,
+                },
+                IndexInconsistency::MissingAliasTarget {
+                    container: "stork".to_string(),
+                    target: "storks".to_string(),
+                },
+                IndexInconsistency::DocumentErrorCountTooLow {
+                    count: 0,
+                    stored: 1,
+                },
+                IndexInconsistency::InvalidConfig {
+                    option: "displayed_results_count",
+                    value: "0".to_string(),
+                    consequence: "searches never return any results",
+                },
+            ])
+        );
+    }
+}
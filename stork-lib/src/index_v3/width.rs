@@ -0,0 +1,172 @@
+use std::borrow::Cow;
+
+/// The full-width forms of the half-width katakana and punctuation from
+/// `U+FF61` to `U+FF9F`, in order.
+const FULL_WIDTH_KATAKANA: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン゛゜";
+
+const HALF_WIDTH_VOICED_MARK: char = '\u{FF9E}';
+const HALF_WIDTH_SEMI_VOICED_MARK: char = '\u{FF9F}';
+
+/**
+ * Replaces the full-width forms of ASCII characters, like `ＡＢＣ１２３`,
+ * with the ASCII characters themselves, the ideographic space with a space,
+ * and half-width katakana, like `ｶﾞｲﾄﾞ`, with full-width katakana, joining
+ * voiced sound marks to the kana before them. This is the part of NFKC
+ * normalization that's about width. Other text is left alone, and is
+ * borrowed when nothing changes.
+ */
+pub(super) fn normalize_width(text: &str) -> Cow<'_, str> {
+    if text.chars().all(|c| normal_width(c) == c) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut normalized = String::with_capacity(text.len());
+    for c in text.chars() {
+        let mark = match c {
+            HALF_WIDTH_VOICED_MARK => normalized.pop().map(|kana| (kana, voiced(kana))),
+            HALF_WIDTH_SEMI_VOICED_MARK => normalized.pop().map(|kana| (kana, semi_voiced(kana))),
+            _ => None,
+        };
+
+        match mark {
+            Some((_, Some(combined))) => normalized.push(combined),
+            Some((kana, None)) => {
+                normalized.push(kana);
+                normalized.push(normal_width(c));
+            }
+            None => normalized.push(normal_width(c)),
+        }
+    }
+
+    Cow::Owned(normalized)
+}
+
+fn normal_width(c: char) -> char {
+    match c {
+        '\u{3000}' => ' ',
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        '\u{FF61}'..='\u{FF9F}' => FULL_WIDTH_KATAKANA
+            .chars()
+            .nth((c as u32 - 0xFF61) as usize)
+            .unwrap_or(c),
+        '\u{FFE0}' => '¢',
+        '\u{FFE1}' => '£',
+        '\u{FFE2}' => '¬',
+        '\u{FFE3}' => '¯',
+        '\u{FFE4}' => '¦',
+        '\u{FFE5}' => '¥',
+        '\u{FFE6}' => '₩',
+        _ => c,
+    }
+}
+
+/// The kana with a voiced sound mark, like `ガ` for `カ`.
+fn voiced(kana: char) -> Option<char> {
+    match kana {
+        'カ' | 'キ' | 'ク' | 'ケ' | 'コ' | 'サ' | 'シ' | 'ス' | 'セ' | 'ソ' | 'タ' | 'チ'
+        | 'ツ' | 'テ' | 'ト' | 'ハ' | 'ヒ' | 'フ' | 'ヘ' | 'ホ' => {
+            char::from_u32(kana as u32 + 1)
+        }
+        'ウ' => Some('ヴ'),
+        'ワ' => Some('ヷ'),
+        'ヲ' => Some('ヺ'),
+        _ => None,
+    }
+}
+
+/// The kana with a semi-voiced sound mark, like `パ` for `ハ`.
+fn semi_voiced(kana: char) -> Option<char> {
+    match kana {
+        'ハ' | 'ヒ' | 'フ' | 'ヘ' | 'ホ' => char::from_u32(kana as u32 + 2),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "build-v3")]
+    use crate::{config::*, index_v3::build::build, V3Index as Index};
+
+    #[test]
+    fn full_width_ascii_becomes_ascii() {
+        assert_eq!(normalize_width("ＡＢＣ１２３"), "ABC123");
+        assert_eq!(normalize_width("（ｓｔｏｒｋ）！"), "(stork)!");
+        assert_eq!(
+            normalize_width("Ｓｔｏｒｋ\u{3000}ｓｅａｒｃｈ"),
+            "Stork search"
+        );
+        assert_eq!(normalize_width("￥１００"), "¥100");
+    }
+
+    #[test]
+    fn half_width_katakana_becomes_full_width() {
+        assert_eq!(normalize_width("ｶﾀｶﾅ"), "カタカナ");
+        assert_eq!(normalize_width("ｶﾞｲﾄﾞ"), "ガイド");
+        assert_eq!(normalize_width("ﾊﾟﾝ"), "パン");
+        assert_eq!(normalize_width("ｳﾞｧｲｵﾘﾝ"), "ヴァイオリン");
+        assert_eq!(normalize_width("｢ｺﾝﾋﾟｭｰﾀｰ｣｡"), "「コンピューター」。");
+    }
+
+    #[test]
+    fn sound_marks_that_cannot_be_joined_are_kept() {
+        assert_eq!(normalize_width("ﾞｱﾞ"), "゛ア゛");
+        assert_eq!(normalize_width("ｶﾟ"), "カ゜");
+    }
+
+    #[test]
+    fn other_text_is_borrowed() {
+        assert!(matches!(
+            normalize_width("ABC カタカナ 漢字"),
+            Cow::Borrowed("ABC カタカナ 漢字")
+        ));
+    }
+
+    #[cfg(feature = "build-v3")]
+    #[test]
+    fn full_width_text_can_be_normalized() {
+        let config = |normalize_width| Config {
+            input: InputConfig {
+                files: vec![File {
+                    explicit_source: Some(DataSource::Contents(
+                        "Ｓｔｏｒｋ indexes ＡＢＣ１２３ and ｶﾞｲﾄﾞ".to_string(),
+                    )),
+                    title: "ＡＢＣ ｶﾞｲﾄﾞ".to_string(),
+                    filetype: Some(Filetype::PlainText),
+                    ..Default::default()
+                }],
+                normalize_width,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let unnormalized = build(&config(false)).unwrap().index;
+        assert!(crate::index_v3::search(&unnormalized, "abc123")
+            .unwrap()
+            .results
+            .is_empty());
+
+        let bytes = bytes::Bytes::from(&build(&config(true)).unwrap().index);
+        let index = Index::try_from(bytes.as_ref()).unwrap();
+        assert_eq!(index.entries[0].contents, "Stork indexes ABC123 and ガイド");
+        assert_eq!(index.entries[0].title, "ABC ガイド");
+
+        for query in ["abc", "abc123", "ガイド", "ＡＢＣ", "ｶﾞｲﾄﾞ"] {
+            let output = crate::index_v3::search(&index, query).unwrap();
+            assert_eq!(output.results.len(), 1, "{}", query);
+        }
+
+        // `abc123` is only in the contents, so only the other queries
+        // highlight the title.
+        for query in ["abc", "ガイド", "ＡＢＣ", "ｶﾞｲﾄﾞ"] {
+            let output = crate::index_v3::search(&index, query).unwrap();
+            assert_eq!(
+                output.results[0].title_highlight_ranges.len(),
+                1,
+                "{}",
+                query
+            );
+        }
+    }
+}
@@ -4,21 +4,29 @@ use bytes::{BufMut, Bytes, BytesMut};
 
 use super::Index;
 
+/// The index holds no timestamps, and MessagePack stores floats as
+/// big-endian IEEE 754, so the same config and documents always serialize to
+/// the same bytes.
 impl From<&Index> for Bytes {
     fn from(value: &Index) -> Self {
         let index_bytes = rmp_serde::to_vec(&value).unwrap();
-        let index_bytes = Bytes::from(index_bytes);
-        let version_bytes = Bytes::from("stork-3");
+        with_version_header("stork-3", index_bytes)
+    }
+}
 
-        let mut buf = BytesMut::with_capacity(
-            index_bytes.len() + version_bytes.len() + 2 * mem::size_of::<u64>(),
-        );
-        buf.put_u64(version_bytes.len() as u64);
+/// Prefixes a serialized body with its length and a version string, in the
+/// layout every Stork index file uses.
+pub(super) fn with_version_header(version: &str, body: Vec<u8>) -> Bytes {
+    let body = Bytes::from(body);
+    let version_bytes = Bytes::from(version.to_string());
 
-        buf.put(version_bytes);
-        buf.put_u64(index_bytes.len() as u64);
-        buf.put(index_bytes);
+    let mut buf =
+        BytesMut::with_capacity(body.len() + version_bytes.len() + 2 * mem::size_of::<u64>());
+    buf.put_u64(version_bytes.len() as u64);
 
-        buf.freeze()
-    }
+    buf.put(version_bytes);
+    buf.put_u64(body.len() as u64);
+    buf.put(body);
+
+    buf.freeze()
 }
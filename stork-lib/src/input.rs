@@ -45,6 +45,10 @@ impl TryFrom<Bytes> for VersionedIndex {
         match version_string.as_str() {
             "stork-2" => Ok(VersionedIndex::V2(buffer)),
             "stork-3" => {
+                if buffer.len() < u64_size {
+                    return Err(IndexVersioningError::FileTooShort);
+                }
+
                 let index_size = {
                     let index_size = buffer.get_u64();
                     let index_size: usize = index_size
@@ -53,6 +57,10 @@ impl TryFrom<Bytes> for VersionedIndex {
                     Ok::<usize, IndexVersioningError>(index_size)
                 }?;
 
+                if buffer.len() < index_size {
+                    return Err(IndexVersioningError::FileTooShort);
+                }
+
                 let index_bytes = buffer.split_to(index_size);
 
                 Ok(VersionedIndex::V3(index_bytes))
@@ -164,6 +172,23 @@ mod tests {
         )
     }
 
+    #[test]
+    fn truncated_v3_index_does_not_parse() {
+        let bytes =
+            Bytes::try_from(hex!("0000000000000007 73746F726B2D33 0000000000000002 00").as_ref())
+                .unwrap();
+        assert_eq!(
+            VersionedIndex::try_from(bytes).unwrap_err(),
+            IndexVersioningError::FileTooShort
+        );
+
+        let bytes = Bytes::try_from(hex!("0000000000000007 73746F726B2D33 0000").as_ref()).unwrap();
+        assert_eq!(
+            VersionedIndex::try_from(bytes).unwrap_err(),
+            IndexVersioningError::FileTooShort
+        )
+    }
+
     #[test]
     fn invalid_utf8_version_does_not_parse() {
         let bytes = Bytes::try_from(hex!("0000000000000004 F0288CBC").as_ref()).unwrap();
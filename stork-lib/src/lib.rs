@@ -9,6 +9,8 @@ use thiserror::Error;
 #[cfg(feature = "build-v3")]
 use {
     num_format::{Locale, ToFormattedString},
+    serde::Serialize,
+    std::collections::BTreeMap,
     std::fmt::Display,
 };
 
@@ -16,9 +18,13 @@ pub type Fields = HashMap<String, String>;
 
 mod output;
 pub use output::{
-    Entry, Excerpt, HighlightRange, IndexMetadata, InternalWordAnnotation, Output, Result,
+    Entry, Excerpt, ExcerptField, Fallback, FallbackStage, HighlightRange, IndexMetadata,
+    InternalWordAnnotation, Output, Result, StoredDocumentError, TitleOutput, TitleResult,
 };
 
+mod render;
+pub use render::{render_excerpt_plain, HighlightStyle};
+
 mod input;
 use input::{IndexVersioningError, VersionedIndex};
 
@@ -28,6 +34,20 @@ use stopwords::STOPWORDS as stopwords;
 mod config;
 pub use config::{Config, ConfigReadError};
 
+mod date;
+pub use date::{Date, DateParseError};
+
+mod tokenizer;
+pub use tokenizer::{AnnotatedWord, CustomTokenizer, Tokenizer};
+
+mod query_listener;
+pub use query_listener::{clear_query_listener, set_query_listener, QueryEvent};
+
+#[cfg(feature = "build-v3")]
+mod self_test;
+#[cfg(feature = "build-v3")]
+pub use self_test::{self_test_index, SelfTestError};
+
 #[cfg(feature = "search-v2")]
 mod index_v2;
 #[cfg(feature = "search-v3")]
@@ -37,20 +57,74 @@ mod index_v3;
 use {index_v2::search as V2Search, index_v2::Index as V2Index};
 
 #[cfg(feature = "search-v3")]
-use {index_v3::search as V3Search, index_v3::Index as V3Index};
+use {
+    index_v3::search_titles_with_options as V3SearchTitles,
+    index_v3::search_with_options as V3Search, index_v3::Index as V3Index,
+};
+
+#[cfg(feature = "search-v3")]
+pub use index_v3::{
+    index_checksum, verify_index, write_delta as index_delta, EntryRef, Index, IndexDeltaError,
+    IndexInconsistency, QuerySession, ShardedIndex, ShardedIndexReadError, TermStats,
+};
 
 #[cfg(feature = "build-v3")]
-pub use index_v3::DocumentError;
+pub use index_v3::{
+    group_document_errors, migrate_config, update_entry_text, BrokenUrl, BuildProgress,
+    ConfigMigration, ConfigMigrationError, DefaultTokenizer, DocumentError, DocumentErrorGroup,
+    DocumentWarning, DocumentWarningKind, EntryUpdateError, ExcludedDocument, ExclusionRule, Nudge,
+    PruningAction, SizeContributor, SizeEstimate, SourceLocation, UrlCheckReport,
+};
 
 #[cfg(feature = "build-v3")]
 use {
-    index_v3::build as V3Build, index_v3::BuildResult as V3BuildResult,
+    config::{IndexFormat, OutputConfig},
+    index_v3::build as V3Build,
+    index_v3::build_with_partial_results as V3BuildWithPartialResults,
+    index_v3::build_with_progress as V3BuildWithProgress,
+    index_v3::estimate_index_size as V3EstimateIndexSize,
+    index_v3::BuildResult as V3BuildResult,
     index_v3::IndexGenerationError,
+    index_v3::PartialBuildError as V3PartialBuildError,
 };
 
+#[cfg(feature = "async")]
+use index_v3::build_async as V3BuildAsync;
+
 // We can't pass a parsed index over the WASM boundary so we store the parsed indices here
 lazy_static! {
-    static ref INDEX_CACHE: Mutex<HashMap<String, ParsedIndex>> = Mutex::new(HashMap::new());
+    static ref INDEX_CACHE: Mutex<HashMap<String, CachedIndex>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug)]
+struct CachedIndex {
+    parsed: ParsedIndex,
+
+    /// The `index_checksum` of the file the index was read from, which a
+    /// delta has to have been made against to be applied to it.
+    #[cfg(feature = "search-v3")]
+    checksum: u64,
+}
+
+// Likewise, the query sessions begun with `begin_query_session`, by handle.
+// Whenever both are locked, `INDEX_CACHE` is locked first.
+lazy_static! {
+    static ref QUERY_SESSIONS: Mutex<QuerySessions> = Mutex::new(QuerySessions::default());
+}
+
+#[derive(Debug, Default)]
+struct QuerySessions {
+    next_handle: u32,
+    open: HashMap<u32, OpenQuerySession>,
+}
+
+#[derive(Debug)]
+struct OpenQuerySession {
+    /// The name the session's index was registered with.
+    key: String,
+
+    #[cfg(feature = "search-v3")]
+    session: index_v3::QuerySession,
 }
 
 /**
@@ -89,19 +163,34 @@ impl ParsedIndex {
             #[cfg(feature = "search-v2")]
             ParsedIndex::V2(_) => IndexMetadata {
                 index_version: "stork-2".to_string(),
+                document_error_count: 0,
+                stemming: None,
+                stemming_varies: false,
             },
 
             #[cfg(feature = "search-v3")]
-            ParsedIndex::V3(_) => IndexMetadata {
-                index_version: "stork-3".to_string(),
-            },
+            ParsedIndex::V3(index) => index.metadata(),
 
             #[cfg(not(any(feature = "search-v2", feature = "search-v3")))]
             ParsedIndex::Unknown => IndexMetadata {
                 index_version: "unknown".to_string(),
+                document_error_count: 0,
+                stemming: None,
+                stemming_varies: false,
             },
         }
     }
+
+    /// The documents that couldn't be indexed, if the index recorded them.
+    pub fn document_errors(&self) -> Vec<StoredDocumentError> {
+        match self {
+            #[cfg(feature = "search-v3")]
+            ParsedIndex::V3(index) => index.document_errors().to_vec(),
+
+            #[allow(unreachable_patterns)]
+            _ => vec![],
+        }
+    }
 }
 
 #[allow(unreachable_patterns)]
@@ -122,6 +211,20 @@ pub fn index_from_bytes(bytes: Bytes) -> core::result::Result<ParsedIndex, Index
         _ => Err(IndexParseError::ParseError()),
     }
 }
+
+/**
+ * Parses an index that was written with `output.format = "sharded"`, given
+ * its manifest and every one of its shards in order.
+ */
+#[cfg(feature = "search-v3")]
+pub fn index_from_shards(
+    manifest: Bytes,
+    shards: &[Bytes],
+) -> core::result::Result<ParsedIndex, IndexParseError> {
+    V3Index::from_shards(manifest.as_ref(), shards)
+        .map_err(|e| IndexParseError::V3Error(e.to_string()))
+        .map(ParsedIndex::V3)
+}
 #[derive(Debug, Error)]
 pub enum BuildError {
     #[error("{0}")]
@@ -130,18 +233,57 @@ pub enum BuildError {
     #[error("The Stork binary was not compiled with the ability to build indexes. Please recompile with the `build_v3` feature enabled.")]
     BinaryNotBuiltWithFeature,
 
-    #[error("{0}")]
+    #[error(transparent)]
     #[cfg(feature = "build-v3")]
     IndexGenerationError(#[from] IndexGenerationError),
+
+    #[error("The index was built, but failed its self-test. {0}")]
+    #[cfg(feature = "build-v3")]
+    SelfTestError(#[from] SelfTestError),
 }
 
 #[cfg(feature = "build-v3")]
-#[derive(Debug)]
+impl BuildError {
+    /// The per-document errors that caused the build to fail, if any.
+    pub fn document_errors(&self) -> &[DocumentError] {
+        match self {
+            BuildError::IndexGenerationError(error) => error.document_errors(),
+            _ => &[],
+        }
+    }
+}
+
+#[cfg(feature = "build-v3")]
+#[derive(Debug, Serialize)]
 pub struct IndexDescription {
     pub entries_count: usize,
     pub tokens_count: usize,
     pub index_size_bytes: usize,
     pub warnings: Vec<DocumentError>,
+    pub document_warnings: Vec<DocumentWarning>,
+
+    /// Suggestions for improving the config. These aren't part of the
+    /// `Display` output, so callers can show them however they like.
+    pub nudges: Vec<Nudge>,
+
+    /// What was removed from the index because of
+    /// `input.min_document_frequency` or to fit `output.max_index_bytes`.
+    pub pruning: Vec<PruningAction>,
+
+    /// How many documents were detected to be in each language, keyed by
+    /// ISO 639-3 code.
+    pub detected_languages: BTreeMap<String, usize>,
+
+    /// The URLs requested because of `input.verify_urls`, and which of them
+    /// are broken.
+    pub url_checks: UrlCheckReport,
+
+    /// The documents that `input.exclude` left out of the index, with the
+    /// rule that excluded each one.
+    pub excluded: Vec<ExcludedDocument>,
+
+    /// How many of the index's bytes are n-grams for substring search.
+    pub ngram_bytes: usize,
 }
 
 #[cfg(feature = "build-v3")]
@@ -149,38 +291,110 @@ impl From<&V3BuildResult> for IndexDescription {
     fn from(build_result: &V3BuildResult) -> Self {
         Self {
             entries_count: build_result.index.entries_len(),
-            tokens_count: build_result.index.search_term_count(),
+            tokens_count: build_result.index.term_count(),
             index_size_bytes: Bytes::from(&build_result.index).len(),
             warnings: build_result.errors.clone(),
+            document_warnings: build_result.warnings.clone(),
+            nudges: build_result.nudges.clone(),
+            pruning: build_result.pruning.clone(),
+            detected_languages: build_result.detected_languages.clone(),
+            url_checks: build_result.url_checks.clone(),
+            excluded: build_result.excluded.clone(),
+            ngram_bytes: build_result.index.ngram_size_bytes(),
         }
     }
 }
 
+/// Like `IndexGenerationError`, the alternate form (`{:#}`) names every
+/// document that failed to index.
 #[cfg(feature = "build-v3")]
 impl Display for IndexDescription {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
-            r#"{}Index stats:
+            r#"{}{}{}{}{}{}Index stats:
   - {} entries
   - {} search terms
   - {} bytes per entry
-  - {} bytes per search term"#,
+  - {} bytes per search term{}"#,
             if self.warnings.is_empty() {
                 "".to_string()
+            } else if f.alternate() {
+                DocumentError::display_full_list(&self.warnings) + "\n"
             } else {
                 DocumentError::display_list(&self.warnings) + "\n"
             },
+            if self.document_warnings.is_empty() {
+                "".to_string()
+            } else {
+                DocumentWarning::display_list(&self.document_warnings) + "\n"
+            },
+            if self.pruning.is_empty() {
+                "".to_string()
+            } else {
+                format!(
+                    "The index was pruned:\n{}\n",
+                    self.pruning
+                        .iter()
+                        .map(|action| format!("  - {}", action))
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                )
+            },
+            if self.detected_languages.is_empty() {
+                "".to_string()
+            } else {
+                format!(
+                    "Detected languages: {}\n",
+                    self.detected_languages
+                        .iter()
+                        .map(|(language, count)| format!("{} ({})", language, count))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            },
+            if self.url_checks.checked_count == 0 && self.url_checks.skipped_relative_count == 0 {
+                "".to_string()
+            } else {
+                format!("{}\n", self.url_checks)
+            },
+            if self.excluded.is_empty() {
+                "".to_string()
+            } else {
+                format!(
+                    "Documents excluded by `input.exclude`:\n{}\n",
+                    self.excluded
+                        .iter()
+                        .map(|excluded| format!("  - {}", excluded))
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                )
+            },
             self.entries_count.to_formatted_string(&Locale::en),
             self.tokens_count.to_formatted_string(&Locale::en),
             (self.index_size_bytes / self.entries_count).to_formatted_string(&Locale::en),
             (self.index_size_bytes / self.tokens_count).to_formatted_string(&Locale::en),
+            if self.ngram_bytes == 0 {
+                "".to_string()
+            } else {
+                format!(
+                    "\n  - {} bytes of n-grams for substring search ({}% of the index)",
+                    self.ngram_bytes.to_formatted_string(&Locale::en),
+                    self.ngram_bytes * 100 / self.index_size_bytes.max(1)
+                )
+            },
         ))
     }
 }
 
 #[cfg(feature = "build-v3")]
+#[derive(Debug)]
 pub struct BuildOutput {
+    /// The index file or, when `output.format` is `sharded`, its manifest.
     pub bytes: Bytes,
+
+    /// The shard files, in order. Empty unless `output.format` is `sharded`.
+    pub shards: Vec<Bytes>,
+
     pub description: IndexDescription,
 }
 
@@ -189,25 +403,188 @@ pub fn build_index(_config: &Config) -> core::result::Result<(), BuildError> {
     Err(BuildError::BinaryNotBuiltWithFeature)
 }
 
+#[cfg(feature = "build-v3")]
+impl BuildOutput {
+    /// Runs the self-test on the finished index when `output.self_test` is set.
+    fn checked(
+        result: V3BuildResult,
+        output_config: &OutputConfig,
+    ) -> core::result::Result<Self, BuildError> {
+        let output = BuildOutput::new(result, output_config);
+        if output_config.self_test {
+            self_test_index(&output)?;
+        }
+        Ok(output)
+    }
+
+    fn new(result: V3BuildResult, output_config: &OutputConfig) -> Self {
+        let description = IndexDescription::from(&result);
+        match output_config.format {
+            IndexFormat::Single => BuildOutput {
+                bytes: Bytes::from(&result.index),
+                shards: vec![],
+                description,
+            },
+            IndexFormat::Sharded => {
+                let ShardedIndex { manifest, shards } =
+                    result.index.to_shards(output_config.shard_count);
+                BuildOutput {
+                    bytes: manifest,
+                    shards,
+                    description,
+                }
+            }
+        }
+    }
+}
+
 #[cfg(feature = "build-v3")]
 pub fn build_index(config: &Config) -> core::result::Result<BuildOutput, BuildError> {
     let result = V3Build(config)?;
-    let description = IndexDescription::from(&result);
-    let bytes = Bytes::from(&result.index);
-    Ok(BuildOutput { bytes, description })
+    BuildOutput::checked(result, &config.output)
+}
+
+/**
+ * A build that failed, along with the output built from the documents read
+ * before it did, if there were any.
+ */
+#[cfg(feature = "build-v3")]
+#[derive(Debug, Error)]
+#[error("{error}")]
+pub struct PartialBuildError {
+    pub error: BuildError,
+    pub partial: Option<Box<BuildOutput>>,
+}
+
+/**
+ * Builds an index like `build_index` does, but when a document error stops
+ * the build, as it does with `input.break_on_file_error`, the documents read
+ * until then are still built into an index and returned with the error, so
+ * a tool can show what it has. The partial index isn't self-tested.
+ */
+#[cfg(feature = "build-v3")]
+pub fn build_index_with_partial_results(
+    config: &Config,
+) -> core::result::Result<BuildOutput, PartialBuildError> {
+    match V3BuildWithPartialResults(config) {
+        Ok(result) => {
+            BuildOutput::checked(result, &config.output).map_err(|error| PartialBuildError {
+                error,
+                partial: None,
+            })
+        }
+        Err(V3PartialBuildError { error, partial }) => Err(PartialBuildError {
+            error: BuildError::from(error),
+            partial: partial.map(|result| Box::new(BuildOutput::new(*result, &config.output))),
+        }),
+    }
+}
+
+/**
+ * Estimates how large the index built from `config` would be, without
+ * building all of it, by building an index from up to
+ * `max_sample_documents` of its documents and extrapolating.
+ */
+#[cfg(feature = "build-v3")]
+pub fn estimate_index_size(
+    config: &Config,
+    max_sample_documents: usize,
+) -> core::result::Result<SizeEstimate, BuildError> {
+    Ok(V3EstimateIndexSize(config, max_sample_documents)?)
+}
+
+/**
+ * Builds an index, calling `on_progress` as each document is read.
+ */
+#[cfg(feature = "build-v3")]
+pub fn build_index_with_progress<F: FnMut(BuildProgress)>(
+    config: &Config,
+    on_progress: F,
+) -> core::result::Result<BuildOutput, BuildError> {
+    let result = V3BuildWithProgress(config, on_progress)?;
+    BuildOutput::checked(result, &config.output)
+}
+
+/**
+ * Builds an index from within an async runtime. Web pages are fetched
+ * asynchronously and CPU-bound work is moved to the runtime's blocking
+ * thread pool. Dropping the future cancels the build once the documents
+ * being processed have finished.
+ */
+#[cfg(feature = "async")]
+pub async fn build_index_async(
+    config: &Config,
+    progress: Option<tokio::sync::mpsc::Sender<BuildProgress>>,
+) -> core::result::Result<BuildOutput, BuildError> {
+    let result = V3BuildAsync(config, progress).await?;
+    BuildOutput::checked(result, &config.output)
 }
 
 pub fn register_index(
     name: &str,
     bytes: Bytes,
 ) -> core::result::Result<IndexMetadata, IndexParseError> {
+    #[cfg(feature = "search-v3")]
+    let checksum = index_checksum(&bytes);
     let parsed = index_from_bytes(bytes)?;
     // todo: save deserialized index to cache
     let metadata = parsed.get_metadata();
-    INDEX_CACHE.lock().unwrap().insert(name.to_string(), parsed);
+    let mut cache = INDEX_CACHE.lock().unwrap();
+    cache.insert(
+        name.to_string(),
+        CachedIndex {
+            parsed,
+            #[cfg(feature = "search-v3")]
+            checksum,
+        },
+    );
+    reset_query_sessions(name);
+
     Ok(metadata)
 }
 
+/**
+ * Patches the index registered as `name` with a delta written by
+ * `index_delta`, in place of registering the whole new index. The delta has
+ * to have been made against the exact file the index was registered from,
+ * or from an earlier delta's result; otherwise it's refused, and the
+ * registered index is left as it was.
+ */
+#[cfg(feature = "search-v3")]
+pub fn register_index_delta(
+    name: &str,
+    bytes: Bytes,
+) -> core::result::Result<IndexMetadata, IndexDeltaError> {
+    let mut cache = INDEX_CACHE.lock().unwrap();
+    let cached = cache
+        .get_mut(name)
+        .ok_or_else(|| IndexDeltaError::IndexNotRegistered(name.to_string()))?;
+
+    #[allow(unreachable_patterns)]
+    match &mut cached.parsed {
+        ParsedIndex::V3(index) => {
+            cached.checksum = index.apply_delta(cached.checksum, &bytes)?;
+        }
+        _ => return Err(IndexDeltaError::IndexVersionNotSupported),
+    }
+
+    let metadata = cached.parsed.get_metadata();
+    reset_query_sessions(name);
+    Ok(metadata)
+}
+
+/// What the sessions on a replaced or patched index found can't be reused.
+/// Called with `INDEX_CACHE` locked.
+#[cfg_attr(not(feature = "search-v3"), allow(unused_variables))]
+fn reset_query_sessions(name: &str) {
+    #[cfg(feature = "search-v3")]
+    for open in QUERY_SESSIONS.lock().unwrap().open.values_mut() {
+        if open.key == name {
+            open.session = index_v3::QuerySession::new();
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum SearchError {
     #[error("{0}")]
@@ -220,39 +597,298 @@ pub enum SearchError {
         "Index `{0}` has not been registered. You need to register the index before performing searches with it."
     )]
     IndexNotInCache(String),
+
+    #[error(
+        "The query scopes a word to `{0}`, which isn't a part of a document that can be searched. Use `title`, `body`, `url`, `keywords`, or `summary`."
+    )]
+    UnknownQueryField(String),
+
+    /// An `after:` or `before:` in the query isn't a valid date.
+    #[error("{0}")]
+    InvalidDate(#[from] DateParseError),
+
+    #[error(
+        "There is no query session with handle {0}. Begin one with `begin_query_session` before updating its query."
+    )]
+    UnknownQuerySession(u32),
+}
+
+/**
+ * Options that change which of an index's documents a search returns.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Only returns documents in this language, as tagged by the index's
+    /// `input.language_url_prefixes`, and documents with no language. A
+    /// region tag like `de-AT` falls back to `de`. `None`, or a language the
+    /// index doesn't have, searches every language. Ignored by v2 indexes.
+    pub language: Option<String>,
+
+    /// Returns at most this many results from each directory of URLs, like
+    /// `/blog/page/`, so that near-duplicates can't crowd out everything
+    /// else. Ignored by title searches and v2 indexes.
+    pub max_results_per_url_prefix: Option<usize>,
+
+    /// Only matches the query's words against documents' summaries, as if
+    /// each were written `summary:word`, for quick results that a full-text
+    /// search can follow. Ignored by title searches and v2 indexes.
+    pub summaries_only: bool,
+
+    /// Only returns documents dated on or after this date. A query can also
+    /// set it with `after:2023-01-01`. Ignored by v2 indexes.
+    pub after: Option<Date>,
+
+    /// Only returns documents dated before this date, not on it, so
+    /// `after` and `before` a year apart cover exactly a year. A query can
+    /// also set it with `before:2024-01-01`. Ignored by v2 indexes.
+    pub before: Option<Date>,
+
+    /// When `after` or `before` is set, also leaves out documents without a
+    /// date, which are otherwise always returned.
+    pub require_dates: bool,
+}
+
+/**
+ * The documents that couldn't be indexed when a registered index was built.
+ * Indexes built with `output.strip_errors` only record how many there were.
+ */
+pub fn get_index_errors(key: &str) -> core::result::Result<Vec<StoredDocumentError>, SearchError> {
+    let cache = INDEX_CACHE.lock().unwrap();
+    match cache.get(key) {
+        Some(cached) => Ok(cached.parsed.document_errors()),
+        None => Err(SearchError::IndexNotInCache(key.to_string())),
+    }
 }
 
 pub fn search_from_cache(key: &str, query: &str) -> core::result::Result<Output, SearchError> {
+    search_from_cache_with_options(key, query, &SearchOptions::default())
+}
+
+pub fn search_from_cache_with_options(
+    key: &str,
+    query: &str,
+    options: &SearchOptions,
+) -> core::result::Result<Output, SearchError> {
+    let started = query_listener::start_query();
+    let output = {
+        let cache = INDEX_CACHE.lock().unwrap();
+        let parsed = match cache.get(key) {
+            Some(cached) => &cached.parsed,
+            None => return Err(SearchError::IndexNotInCache(key.to_string())),
+        };
+
+        search_parsed_index_unobserved(parsed, query, options)?
+    };
+
+    query_listener::finish_search(started, Some(key), query, &output);
+    Ok(output)
+}
+
+/**
+ * Begins a query session on a registered index, for searching it as a query
+ * is typed, and returns the session's handle for `update_query`.
+ */
+pub fn begin_query_session(key: &str) -> core::result::Result<u32, SearchError> {
     let cache = INDEX_CACHE.lock().unwrap();
-    let parsed = match cache.get(key) {
-        Some(parsed) => parsed,
-        None => return Err(SearchError::IndexNotInCache(key.to_string())),
+    if !cache.contains_key(key) {
+        return Err(SearchError::IndexNotInCache(key.to_string()));
+    }
+
+    let mut sessions = QUERY_SESSIONS.lock().unwrap();
+    let handle = sessions.next_handle;
+    sessions.next_handle = handle.wrapping_add(1);
+    sessions.open.insert(
+        handle,
+        OpenQuerySession {
+            key: key.to_string(),
+            #[cfg(feature = "search-v3")]
+            session: index_v3::QuerySession::new(),
+        },
+    );
+    Ok(handle)
+}
+
+pub fn update_query(handle: u32, query: &str) -> core::result::Result<Output, SearchError> {
+    update_query_with_options(handle, query, &SearchOptions::default())
+}
+
+/**
+ * Searches a query session's index for the session's new query. The results
+ * are the same as `search_from_cache_with_options` would return, but words
+ * that are unchanged since the session's previous query, like every word but
+ * the last as a query is typed, aren't looked up again. Sessions on v2
+ * indexes search from scratch every time.
+ */
+pub fn update_query_with_options(
+    handle: u32,
+    query: &str,
+    options: &SearchOptions,
+) -> core::result::Result<Output, SearchError> {
+    let started = query_listener::start_query();
+    let (key, output) = {
+        let cache = INDEX_CACHE.lock().unwrap();
+        let mut sessions = QUERY_SESSIONS.lock().unwrap();
+        let open = match sessions.open.get_mut(&handle) {
+            Some(open) => open,
+            None => return Err(SearchError::UnknownQuerySession(handle)),
+        };
+        let parsed = match cache.get(&open.key) {
+            Some(cached) => &cached.parsed,
+            None => return Err(SearchError::IndexNotInCache(open.key.clone())),
+        };
+
+        #[allow(unreachable_patterns)]
+        let output = match parsed {
+            #[cfg(feature = "search-v3")]
+            ParsedIndex::V3(index) => open.session.search(index, query, options)?,
+
+            _ => search_parsed_index_unobserved(parsed, query, options)?,
+        };
+        (open.key.clone(), output)
     };
 
-    match parsed {
-        #[cfg(feature = "search-v2")]
-        ParsedIndex::V2(index) => Ok(V2Search(index, query)),
+    query_listener::finish_search(started, Some(&key), query, &output);
+    Ok(output)
+}
+
+/// The name of the index a query session searches, if the session is open.
+pub fn query_session_index(handle: u32) -> Option<String> {
+    let sessions = QUERY_SESSIONS.lock().unwrap();
+    sessions.open.get(&handle).map(|open| open.key.clone())
+}
 
+/// Ends a query session, freeing what it remembered. Ending a session that
+/// isn't open does nothing.
+pub fn end_query_session(handle: u32) {
+    QUERY_SESSIONS.lock().unwrap().open.remove(&handle);
+}
+
+/**
+ * Searches an index that has already been parsed with `index_from_bytes`.
+ *
+ * Searching only reads from the index, so a single `ParsedIndex` can be
+ * shared between threads and searched concurrently.
+ */
+pub fn search_parsed_index(
+    index: &ParsedIndex,
+    query: &str,
+) -> core::result::Result<Output, SearchError> {
+    search_parsed_index_with_options(index, query, &SearchOptions::default())
+}
+
+pub fn search_parsed_index_with_options(
+    index: &ParsedIndex,
+    query: &str,
+    options: &SearchOptions,
+) -> core::result::Result<Output, SearchError> {
+    let started = query_listener::start_query();
+    let output = search_parsed_index_unobserved(index, query, options)?;
+    query_listener::finish_search(started, None, query, &output);
+    Ok(output)
+}
+
+/// Searches `index` without telling the query listener, for the functions
+/// that report the search themselves.
+#[allow(unused_variables)]
+pub(crate) fn search_parsed_index_unobserved(
+    index: &ParsedIndex,
+    query: &str,
+    options: &SearchOptions,
+) -> core::result::Result<Output, SearchError> {
+    #[allow(unreachable_patterns)]
+    match index {
         #[cfg(feature = "search-v3")]
-        ParsedIndex::V3(index) => Ok(V3Search(index, query)),
+        ParsedIndex::V3(index) => V3Search(index, query, options),
 
-        #[cfg(not(any(feature = "search-v2", feature = "search-v3")))]
-        ParsedIndex::Unknown => Err(SearchError::IndexVersionNotSupported),
+        #[cfg(feature = "search-v2")]
+        ParsedIndex::V2(index) => Ok(V2Search(index, query)),
+
+        _ => Err(SearchError::IndexVersionNotSupported),
     }
 }
 
-#[allow(unused_variables)]
 pub fn search(index: Bytes, query: &str) -> core::result::Result<Output, SearchError> {
+    let started = query_listener::start_query();
     let index = index_from_bytes(index)?;
+    let output = search_parsed_index_unobserved(&index, query, &SearchOptions::default())?;
+    query_listener::finish_search(started, None, query, &output);
+    Ok(output)
+}
+
+pub fn search_titles_from_cache(
+    key: &str,
+    query: &str,
+) -> core::result::Result<TitleOutput, SearchError> {
+    search_titles_from_cache_with_options(key, query, &SearchOptions::default())
+}
+
+pub fn search_titles_from_cache_with_options(
+    key: &str,
+    query: &str,
+    options: &SearchOptions,
+) -> core::result::Result<TitleOutput, SearchError> {
+    let started = query_listener::start_query();
+    let output = {
+        let cache = INDEX_CACHE.lock().unwrap();
+        let parsed = match cache.get(key) {
+            Some(cached) => &cached.parsed,
+            None => return Err(SearchError::IndexNotInCache(key.to_string())),
+        };
+
+        search_titles_in_parsed_index_unobserved(parsed, query, options)?
+    };
+
+    query_listener::finish_title_search(started, Some(key), query, &output);
+    Ok(output)
+}
+
+/**
+ * Matches the query against the titles of an index's documents only,
+ * without building any excerpts. Much cheaper than `search_parsed_index`, for
+ * interfaces like navigation bars that only list matching pages.
+ *
+ * Only v3 indexes can be searched this way.
+ */
+pub fn search_titles_in_parsed_index(
+    index: &ParsedIndex,
+    query: &str,
+) -> core::result::Result<TitleOutput, SearchError> {
+    search_titles_in_parsed_index_with_options(index, query, &SearchOptions::default())
+}
+
+pub fn search_titles_in_parsed_index_with_options(
+    index: &ParsedIndex,
+    query: &str,
+    options: &SearchOptions,
+) -> core::result::Result<TitleOutput, SearchError> {
+    let started = query_listener::start_query();
+    let output = search_titles_in_parsed_index_unobserved(index, query, options)?;
+    query_listener::finish_title_search(started, None, query, &output);
+    Ok(output)
+}
 
+/// Searches `index`'s titles without telling the query listener, for the
+/// functions that report the search themselves.
+#[allow(unused_variables)]
+fn search_titles_in_parsed_index_unobserved(
+    index: &ParsedIndex,
+    query: &str,
+    options: &SearchOptions,
+) -> core::result::Result<TitleOutput, SearchError> {
     #[allow(unreachable_patterns)]
     match index {
         #[cfg(feature = "search-v3")]
-        ParsedIndex::V3(index) => Ok(V3Search(&index, query)),
-
-        #[cfg(feature = "search-v2")]
-        ParsedIndex::V2(index) => Ok(V2Search(&index, query)),
+        ParsedIndex::V3(index) => V3SearchTitles(index, query, options),
 
         _ => Err(SearchError::IndexVersionNotSupported),
     }
 }
+
+pub fn search_titles(index: Bytes, query: &str) -> core::result::Result<TitleOutput, SearchError> {
+    let started = query_listener::start_query();
+    let index = index_from_bytes(index)?;
+    let output =
+        search_titles_in_parsed_index_unobserved(&index, query, &SearchOptions::default())?;
+    query_listener::finish_title_search(started, None, query, &output);
+    Ok(output)
+}
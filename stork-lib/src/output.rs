@@ -1,14 +1,57 @@
 use crate::Fields;
 use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+use std::collections::BTreeMap;
 
 /**
  * The set of data needed to display search results to a user.
+ *
+ * This is serialized as-is into the JSON that the WASM module hands to the
+ * Javascript library, so its field names are part of Stork's public schema.
  */
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct Output {
     pub results: Vec<Result>,
     pub total_hit_count: usize,
     pub url_prefix: String,
+
+    /// How many matching documents are in each section, when the index was
+    /// built with `input.facet_url_prefixes`. Counted before a `section:`
+    /// filter or `displayed_results_count` is applied.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub facet_counts: BTreeMap<String, usize>,
+
+    /// How the query was changed to find these results, when it found
+    /// nothing as it was typed and the index was built with
+    /// `output.zero_result_fallback`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<Fallback>,
+
+    /// The words in the corpus that share a stem with each word of the
+    /// query, when the index was built with `output.save_stem_surface_forms`,
+    /// so an interface can highlight every form of a word that results
+    /// matched through its stem. Only serialized when there are any.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub surface_forms: BTreeMap<String, Vec<String>>,
+}
+
+/**
+ * The stage of the zero-result fallback chain that found results, and the
+ * query it searched for instead, so an interface can show "showing results
+ * for ...".
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Fallback {
+    pub stage: FallbackStage,
+    pub query: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FallbackStage {
+    /// Words that aren't in the index were shortened to the longest start of
+    /// them that is.
+    PrefixRelaxation,
 }
 
 /**
@@ -21,6 +64,19 @@ pub struct Result {
     pub excerpts: Vec<Excerpt>,
     pub title_highlight_ranges: Vec<HighlightRange>,
     pub score: usize,
+
+    /// How many documents were merged into this result because their URLs
+    /// only differ by fragment or query string, when the index was built
+    /// with `output.group_by_url_without_fragment`. Only serialized when
+    /// more than one was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merged_entry_count: Option<usize>,
+
+    /// The entry's URL with the fragment of the section its best excerpt
+    /// came from, like `page#section`, when the index was built with
+    /// `output.save_nearest_html_id`. Only serialized when there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
 }
 
 /**
@@ -31,6 +87,28 @@ pub struct Entry {
     pub url: String,
     pub title: String,
     pub fields: Fields,
+
+    /// An image to show alongside the document, with the index's
+    /// `url_prefix` already joined to relative paths. Only serialized when
+    /// the document has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+
+    /// How many words the document had when it was indexed. Absent for
+    /// indexes built before it was stored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub word_count: Option<usize>,
+
+    /// About how many minutes the document takes to read, at the index's
+    /// `output.reading_time_wpm`. Absent when that's 0, or the word count
+    /// isn't known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reading_time_minutes: Option<usize>,
+
+    /// The document's text, for cutting excerpts of a different length or
+    /// style, when the index was built with `output.store_document_text`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
 }
 
 /**
@@ -44,6 +122,63 @@ pub struct Excerpt {
     pub score: usize,
     pub internal_annotations: Vec<InternalWordAnnotation>,
     pub fields: Fields,
+
+    /// Whether words were cut from the start of `text` to fit
+    /// `excerpt_max_chars`. Only serialized when true.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub trimmed_start: bool,
+
+    /// Whether words were cut from the end of `text` to fit
+    /// `excerpt_max_chars`. Only serialized when true.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub trimmed_end: bool,
+
+    /// Whether `text` starts at the beginning of a sentence, or of the
+    /// document, so no ellipsis is needed before it. Only set when
+    /// `excerpt_sentence_tolerance` is, and only serialized when true.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sentence_start: bool,
+
+    /// Whether `text` ends at the end of a sentence, or of the document, so
+    /// no ellipsis is needed after it. Only set when
+    /// `excerpt_sentence_tolerance` is, and only serialized when true.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sentence_end: bool,
+
+    /// The entry's URL with the fragment of the section this excerpt came
+    /// from, like `page#section`, taken from its `UrlSuffix` annotation.
+    /// Only serialized when there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    /// The part of the document the excerpt's matches are in. Only
+    /// serialized when it isn't the body.
+    #[serde(default, skip_serializing_if = "ExcerptField::is_body")]
+    pub field: ExcerptField,
+}
+
+/**
+ * The part of a document an excerpt comes from. Excerpts come from the body
+ * whenever it matches; otherwise, each other part that matched gets one,
+ * like `Matched keyword: kubernetes`, so an interface can style them
+ * differently.
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, SmartDefault)]
+#[serde(rename_all = "lowercase")]
+pub enum ExcerptField {
+    #[default]
+    Body,
+    Title,
+    Url,
+    Keywords,
+    Summary,
+}
+
+impl ExcerptField {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn is_body(&self) -> bool {
+        *self == ExcerptField::Body
+    }
 }
 
 /**
@@ -56,6 +191,19 @@ pub struct Excerpt {
 pub enum InternalWordAnnotation {
     #[serde(rename = "a")]
     UrlSuffix(String),
+
+    /// The word starts an item of a list, a term or description of a
+    /// definition list, or a table row, and isn't part of a phrase with the
+    /// word before it. Only used while indexing, and never stored in
+    /// excerpts.
+    #[serde(rename = "b")]
+    ItemStart,
+
+    /// The word is from an HTML document's `<code>` or `<pre>` element,
+    /// indexed with `input.index_code_identifiers`. Only used while indexing,
+    /// and never stored in excerpts.
+    #[serde(rename = "c")]
+    Code,
 }
 
 /**
@@ -68,11 +216,164 @@ pub struct HighlightRange {
     pub end: usize,
 }
 
+/**
+ * The documents whose titles match a query, from a titles-only search. Like
+ * `Output`, this is serialized as-is into the JSON handed to the Javascript
+ * library.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct TitleOutput {
+    pub results: Vec<TitleResult>,
+    pub total_hit_count: usize,
+    pub url_prefix: String,
+}
+
+/**
+ * A document whose title matched a titles-only search.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TitleResult {
+    pub title: String,
+    pub url: String,
+    pub score: usize,
+}
+
 /**
  * Contains metadata about an index, to be displayed to the user, often for debugging.
  */
-#[derive(Serialize, Clone, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct IndexMetadata {
     #[serde(rename = "indexVersion")]
     pub index_version: String,
+
+    /// How many documents couldn't be indexed when the index was built.
+    #[serde(rename = "documentErrorCount", default)]
+    pub document_error_count: usize,
+
+    /// The stemming the index was built with, like `English` or `none`, so
+    /// queries can be stemmed to match. Absent if the index didn't record it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stemming: Option<String>,
+
+    /// Whether some documents were stemmed differently from `stemming`.
+    /// Only serialized when true.
+    #[serde(
+        rename = "stemmingVaries",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub stemming_varies: bool,
+}
+
+/**
+ * A document that couldn't be indexed, as recorded in the index itself so
+ * that it can be seen after the index is deployed.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StoredDocumentError {
+    /// The document's path, URL, or title.
+    pub file: String,
+
+    /// The error's stable code, like `E002_FILE_NOT_FOUND`.
+    pub code: String,
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
+
+    fn sample_output() -> Output {
+        Output {
+            results: vec![Result {
+                entry: Entry {
+                    url: "https://example.com".to_string(),
+                    title: "Example".to_string(),
+                    fields: HashMap::from([("k".to_string(), "v".to_string())]),
+                    thumbnail_url: None,
+                    word_count: None,
+                    reading_time_minutes: None,
+                    text: None,
+                },
+                excerpts: vec![Excerpt {
+                    text: "an example excerpt".to_string(),
+                    highlight_ranges: vec![HighlightRange {
+                        beginning: 3,
+                        end: 10,
+                    }],
+                    score: 128,
+                    internal_annotations: vec![InternalWordAnnotation::UrlSuffix(
+                        "#id".to_string(),
+                    )],
+                    fields: HashMap::new(),
+                    trimmed_start: false,
+                    trimmed_end: false,
+                    sentence_start: false,
+                    sentence_end: false,
+                    url: None,
+                    field: ExcerptField::Body,
+                }],
+                title_highlight_ranges: vec![HighlightRange {
+                    beginning: 0,
+                    end: 7,
+                }],
+                score: 203,
+                merged_entry_count: None,
+                url: None,
+            }],
+            total_hit_count: 1,
+            url_prefix: "/prefix".to_string(),
+            facet_counts: BTreeMap::new(),
+            fallback: None,
+            surface_forms: BTreeMap::new(),
+        }
+    }
+
+    // If this test fails, the JSON handed to the Javascript library (and to
+    // anyone else consuming search results) has changed shape.
+    #[test]
+    fn output_json_schema_is_stable() {
+        let expected = r##"{"results":[{"entry":{"url":"https://example.com","title":"Example","fields":{"k":"v"}},"excerpts":[{"text":"an example excerpt","highlight_ranges":[{"beginning":3,"end":10}],"score":128,"internal_annotations":[{"a":"#id"}],"fields":{}}],"title_highlight_ranges":[{"beginning":0,"end":7}],"score":203}],"total_hit_count":1,"url_prefix":"/prefix"}"##;
+        let computed = serde_json::to_string(&sample_output()).unwrap();
+        assert_eq!(expected, computed);
+    }
+
+    #[test]
+    fn output_json_round_trips() {
+        let json = serde_json::to_string(&sample_output()).unwrap();
+        let computed: Output = serde_json::from_str(&json).unwrap();
+        assert_eq!(sample_output(), computed);
+    }
+
+    #[test]
+    fn trimmed_excerpts_are_flagged_in_json() {
+        let mut output = sample_output();
+        output.results[0].excerpts[0].trimmed_end = true;
+
+        let json = serde_json::to_string(&output).unwrap();
+        assert!(json.contains(r#""fields":{},"trimmed_end":true}"#));
+        assert!(!json.contains("trimmed_start"));
+        assert_eq!(serde_json::from_str::<Output>(&json).unwrap(), output);
+    }
+
+    #[test]
+    fn index_metadata_json_schema_is_stable() {
+        let metadata = IndexMetadata {
+            index_version: "stork-3".to_string(),
+            document_error_count: 12,
+            stemming: None,
+            stemming_varies: false,
+        };
+        let json = serde_json::to_string(&metadata).unwrap();
+        assert_eq!(
+            json,
+            r#"{"indexVersion":"stork-3","documentErrorCount":12}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<IndexMetadata>(&json).unwrap(),
+            metadata
+        );
+    }
 }
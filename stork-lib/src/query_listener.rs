@@ -0,0 +1,242 @@
+use lazy_static::lazy_static;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{Output, TitleOutput};
+
+type QueryListener = Arc<dyn Fn(&QueryEvent) + Send + Sync>;
+
+lazy_static! {
+    static ref QUERY_LISTENER: Mutex<Option<QueryListener>> = Mutex::new(None);
+}
+
+/**
+ * A search, as seen by the listener passed to `set_query_listener`.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryEvent {
+    /// The name the index was registered under, or `None` when the index
+    /// was passed to the search function directly.
+    pub index_name: Option<String>,
+    pub query: String,
+    /// The number of documents that matched, before `displayed_results_count`
+    /// is applied. Zero for queries that found nothing.
+    pub result_count: usize,
+
+    /// The URL of the first result, with the index's URL prefix, if the
+    /// search found anything.
+    pub top_result_url: Option<String>,
+    pub elapsed: Duration,
+}
+
+/**
+ * Registers a function that's called after every successful search, whether
+ * of a registered index, a parsed index, or index bytes, and of titles as
+ * well as documents. It replaces any listener registered before it.
+ *
+ * The listener runs after the search has finished and can't change its
+ * result: a listener that panics is ignored. It's called on the searching
+ * thread, so it should hand off anything slow.
+ *
+ * Searches made through the WASM module should use its
+ * `register_query_listener` instead, since `std::time::Instant` isn't
+ * available there.
+ */
+pub fn set_query_listener<F>(listener: F)
+where
+    F: Fn(&QueryEvent) + Send + Sync + 'static,
+{
+    *QUERY_LISTENER.lock().unwrap() = Some(Arc::new(listener));
+}
+
+/**
+ * Removes the listener registered with `set_query_listener`, if any.
+ */
+pub fn clear_query_listener() {
+    *QUERY_LISTENER.lock().unwrap() = None;
+}
+
+/**
+ * Starts timing a search, but only if there's a listener to report it to, so
+ * searches don't read the clock when nobody is listening.
+ */
+pub(crate) fn start_query() -> Option<Instant> {
+    QUERY_LISTENER
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|_| Instant::now())
+}
+
+/// Reports a finished search of `index_name`, if `start_query` started timing it.
+pub(crate) fn finish_search(
+    started: Option<Instant>,
+    index_name: Option<&str>,
+    query: &str,
+    output: &Output,
+) {
+    if let Some(started) = started {
+        let top_result_url = output
+            .results
+            .first()
+            .map(|result| format!("{}{}", output.url_prefix, result.entry.url));
+        finish_query(
+            started,
+            index_name,
+            query,
+            output.total_hit_count,
+            top_result_url,
+        );
+    }
+}
+
+/// Reports a finished title search of `index_name`, if `start_query` started
+/// timing it.
+pub(crate) fn finish_title_search(
+    started: Option<Instant>,
+    index_name: Option<&str>,
+    query: &str,
+    output: &TitleOutput,
+) {
+    if let Some(started) = started {
+        let top_result_url = output
+            .results
+            .first()
+            .map(|result| format!("{}{}", output.url_prefix, result.url));
+        finish_query(
+            started,
+            index_name,
+            query,
+            output.total_hit_count,
+            top_result_url,
+        );
+    }
+}
+
+fn finish_query(
+    started: Instant,
+    index_name: Option<&str>,
+    query: &str,
+    result_count: usize,
+    top_result_url: Option<String>,
+) {
+    // Clone the listener out so it isn't called with the lock held, in case it
+    // registers a new listener itself.
+    let listener = match QUERY_LISTENER.lock().unwrap().as_ref() {
+        Some(listener) => Arc::clone(listener),
+        None => return,
+    };
+
+    let event = QueryEvent {
+        index_name: index_name.map(str::to_string),
+        query: query.to_string(),
+        result_count,
+        top_result_url,
+        elapsed: started.elapsed(),
+    };
+
+    let _ = catch_unwind(AssertUnwindSafe(|| listener(&event)));
+}
+
+#[cfg(all(test, feature = "search-v3"))]
+mod tests {
+    use super::*;
+    use crate::{
+        index_from_bytes, register_index, search, search_from_cache, search_parsed_index,
+        search_titles, search_titles_from_cache, search_titles_in_parsed_index,
+    };
+    use bytes::Bytes;
+
+    /// Removes the listener when dropped, so a failing assertion doesn't
+    /// leave it registered for other tests.
+    struct ListenerGuard;
+
+    impl Drop for ListenerGuard {
+        fn drop(&mut self) {
+            clear_query_listener();
+        }
+    }
+
+    fn listen<F>(listener: F) -> ListenerGuard
+    where
+        F: Fn(&QueryEvent) + Send + Sync + 'static,
+    {
+        set_query_listener(listener);
+        ListenerGuard
+    }
+
+    #[test]
+    fn listener_sees_one_event_per_search() {
+        let bytes = Bytes::from(std::fs::read("../test-assets/federalist-min-0.7.0.st").unwrap());
+        register_index("query-listener", bytes.clone()).unwrap();
+        let parsed = index_from_bytes(bytes.clone()).unwrap();
+
+        // Other tests may be searching at the same time, and the listener is
+        // called on the searching thread, so only keep this thread's events.
+        let events: Arc<Mutex<Vec<QueryEvent>>> = Arc::default();
+        let recorded = Arc::clone(&events);
+        let test_thread = std::thread::current().id();
+        let _listener = listen(move |event| {
+            if std::thread::current().id() == test_thread {
+                recorded.lock().unwrap().push(event.clone());
+            }
+        });
+
+        let output = search_from_cache("query-listener", "liberty").unwrap();
+        search_from_cache("query-listener", "zzyzx").unwrap();
+        let titles = search_titles_from_cache("query-listener", "introduction").unwrap();
+        search_parsed_index(&parsed, "liberty").unwrap();
+        search(bytes.clone(), "liberty").unwrap();
+        search_titles_in_parsed_index(&parsed, "introduction").unwrap();
+        search_titles(bytes, "introduction").unwrap();
+
+        let events = events.lock().unwrap().clone();
+        let summaries: Vec<(Option<&str>, &str, usize)> = events
+            .iter()
+            .map(|event| {
+                (
+                    event.index_name.as_deref(),
+                    event.query.as_str(),
+                    event.result_count,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summaries,
+            vec![
+                (Some("query-listener"), "liberty", output.total_hit_count),
+                (Some("query-listener"), "zzyzx", 0),
+                (
+                    Some("query-listener"),
+                    "introduction",
+                    titles.total_hit_count
+                ),
+                (None, "liberty", output.total_hit_count),
+                (None, "liberty", output.total_hit_count),
+                (None, "introduction", titles.total_hit_count),
+                (None, "introduction", titles.total_hit_count),
+            ]
+        );
+        assert_eq!(
+            events[0].top_result_url,
+            Some(format!(
+                "{}{}",
+                output.url_prefix, output.results[0].entry.url
+            ))
+        );
+        assert_eq!(events[1].top_result_url, None);
+        assert_eq!(
+            events[2].top_result_url,
+            Some(format!("{}{}", titles.url_prefix, titles.results[0].url))
+        );
+
+        let _listener = listen(move |_| {
+            if std::thread::current().id() == test_thread {
+                panic!("listener failed");
+            }
+        });
+        let with_panicking_listener = search_from_cache("query-listener", "liberty").unwrap();
+        assert_eq!(with_panicking_listener, output);
+    }
+}
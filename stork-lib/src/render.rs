@@ -0,0 +1,204 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{Excerpt, HighlightRange};
+
+/**
+ * How `render_excerpt_plain` marks the highlighted parts of an excerpt.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HighlightStyle {
+    /// Bold yellow text in a terminal, with ANSI escape codes.
+    Ansi,
+
+    /// `**bold**` text in Markdown.
+    Markdown,
+
+    /// `before` and `after` around each highlighted part, as they are.
+    Custom { before: String, after: String },
+}
+
+impl HighlightStyle {
+    fn markers(&self) -> (&str, &str) {
+        match self {
+            HighlightStyle::Ansi => ("\u{1b}[1;33m", "\u{1b}[0m"),
+            HighlightStyle::Markdown => ("**", "**"),
+            HighlightStyle::Custom { before, after } => (before, after),
+        }
+    }
+}
+
+/**
+ * The excerpt's text, with its highlight ranges marked in `style`, for
+ * showing results where HTML can't be, like a terminal or a chat message.
+ *
+ * Highlight ranges count characters, not bytes, so text in any script is
+ * marked where the ranges say. Ranges that overlap or touch are marked as
+ * one, ranges past the end of the text are cut short, and each marked part
+ * is widened to whole grapheme clusters, so a marker never separates a
+ * letter from its accents.
+ */
+pub fn render_excerpt_plain(excerpt: &Excerpt, style: &HighlightStyle) -> String {
+    let text = excerpt.text.as_str();
+    let (before, after) = style.markers();
+
+    // The byte offset where each character starts, and the end of the text.
+    let char_offsets: Vec<usize> = text
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(text.len()))
+        .collect();
+    let byte_offset = |char_offset: usize| char_offsets[char_offset.min(char_offsets.len() - 1)];
+
+    let grapheme_starts: Vec<usize> = text
+        .grapheme_indices(true)
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(text.len()))
+        .collect();
+    let grapheme_start_at_or_before = |offset: usize| {
+        let index = grapheme_starts.partition_point(|&start| start <= offset);
+        grapheme_starts[index.saturating_sub(1)]
+    };
+    let grapheme_start_at_or_after = |offset: usize| {
+        let index = grapheme_starts.partition_point(|&start| start < offset);
+        grapheme_starts[index.min(grapheme_starts.len() - 1)]
+    };
+
+    let mut ranges: Vec<&HighlightRange> = excerpt.highlight_ranges.iter().collect();
+    ranges.sort();
+
+    let mut marked: Vec<(usize, usize)> = vec![];
+    for range in ranges {
+        let start = grapheme_start_at_or_before(byte_offset(range.beginning));
+        let end = grapheme_start_at_or_after(byte_offset(range.end));
+        if start >= end {
+            continue;
+        }
+
+        match marked.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+            _ => marked.push((start, end)),
+        }
+    }
+
+    let mut rendered =
+        String::with_capacity(text.len() + marked.len() * (before.len() + after.len()));
+    let mut position = 0;
+    for (start, end) in marked {
+        rendered.push_str(&text[position..start]);
+        rendered.push_str(before);
+        rendered.push_str(&text[start..end]);
+        rendered.push_str(after);
+        position = end;
+    }
+    rendered.push_str(&text[position..]);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ExcerptField;
+    use std::collections::HashMap;
+
+    fn excerpt(text: &str, ranges: &[(usize, usize)]) -> Excerpt {
+        Excerpt {
+            text: text.to_string(),
+            highlight_ranges: ranges
+                .iter()
+                .map(|&(beginning, end)| HighlightRange { beginning, end })
+                .collect(),
+            score: 0,
+            internal_annotations: vec![],
+            fields: HashMap::new(),
+            trimmed_start: false,
+            trimmed_end: false,
+            sentence_start: false,
+            sentence_end: false,
+            url: None,
+            field: ExcerptField::Body,
+        }
+    }
+
+    fn markdown(text: &str, ranges: &[(usize, usize)]) -> String {
+        render_excerpt_plain(&excerpt(text, ranges), &HighlightStyle::Markdown)
+    }
+
+    #[test]
+    fn each_style_marks_the_highlights() {
+        let excerpt = excerpt("the lighthouse keeper", &[(4, 14)]);
+        assert_eq!(
+            render_excerpt_plain(&excerpt, &HighlightStyle::Markdown),
+            "the **lighthouse** keeper"
+        );
+        assert_eq!(
+            render_excerpt_plain(&excerpt, &HighlightStyle::Ansi),
+            "the \u{1b}[1;33mlighthouse\u{1b}[0m keeper"
+        );
+        assert_eq!(
+            render_excerpt_plain(
+                &excerpt,
+                &HighlightStyle::Custom {
+                    before: "<".to_string(),
+                    after: ">".to_string()
+                }
+            ),
+            "the <lighthouse> keeper"
+        );
+    }
+
+    #[test]
+    fn ranges_count_characters_in_multi_byte_text() {
+        assert_eq!(
+            markdown("café crème brûlée", &[(5, 10)]),
+            "café **crème** brûlée"
+        );
+        assert_eq!(markdown("東京の灯台守", &[(3, 5)]), "東京の**灯台**守");
+        assert_eq!(
+            markdown("🏠 lighthouse 🌊", &[(2, 12)]),
+            "🏠 **lighthouse** 🌊"
+        );
+        assert_eq!(markdown("מגדלור ישן", &[(0, 6)]), "**מגדלור** ישן");
+    }
+
+    #[test]
+    fn overlapping_and_adjacent_ranges_are_marked_once() {
+        assert_eq!(
+            markdown("one two three", &[(4, 13), (0, 7)]),
+            "**one two three**"
+        );
+        assert_eq!(
+            markdown("one two three", &[(0, 3), (3, 7)]),
+            "**one two** three"
+        );
+        assert_eq!(
+            markdown("one two three", &[(0, 3), (4, 7)]),
+            "**one** **two** three"
+        );
+        assert_eq!(
+            markdown("one two three", &[(4, 7), (4, 7)]),
+            "one **two** three"
+        );
+    }
+
+    #[test]
+    fn out_of_range_and_empty_ranges_are_handled() {
+        assert_eq!(markdown("one two", &[(4, 40)]), "one **two**");
+        assert_eq!(markdown("one two", &[(40, 50)]), "one two");
+        assert_eq!(markdown("one two", &[(2, 2)]), "one two");
+        assert_eq!(markdown("", &[(0, 3)]), "");
+    }
+
+    #[test]
+    fn markers_never_split_grapheme_clusters() {
+        // `e` followed by a combining acute accent, and a flag made of two
+        // regional indicators
+        assert_eq!(
+            markdown("cafe\u{301} noir", &[(0, 4)]),
+            "**cafe\u{301}** noir"
+        );
+        assert_eq!(
+            markdown("\u{1f1eb}\u{1f1f7} phare", &[(1, 2)]),
+            "**\u{1f1eb}\u{1f1f7}** phare"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{
+    index_from_bytes, index_from_shards, search_parsed_index_unobserved, BuildOutput,
+    IndexParseError, ParsedIndex, SearchError, SearchOptions,
+};
+
+/// The most frequent terms are searched for, since they have the largest
+/// containers to read excerpts from.
+const TERM_QUERY_COUNT: usize = 10;
+
+/// Titles are searched for in at most this many documents, spread evenly
+/// across the index, so that the self-test stays fast for large indexes.
+const TITLE_QUERY_COUNT: usize = 100;
+
+/**
+ * A reason a built index failed its self-test.
+ */
+#[derive(Debug, Error)]
+pub enum SelfTestError {
+    #[error("The index couldn't be read back after it was built: {0}")]
+    IndexUnreadable(IndexParseError),
+
+    #[error("Searching the index for `{query}` failed: {error}")]
+    QueryFailed { query: String, error: SearchError },
+
+    #[error("Searching the index for the title `{title}` didn't return its document, `{url}`.")]
+    TitleNotFound { title: String, url: String },
+}
+
+/**
+ * Reads a built index back with the same parser the web library uses, then
+ * searches it: once for each of its most frequent terms, and once for the
+ * title of each document (up to `TITLE_QUERY_COUNT` of them). The self-test
+ * fails if the index can't be read, if any search fails, or if searching for
+ * a document's title doesn't return that document.
+ *
+ * Documents whose title is empty or shared with another document are
+ * skipped, since their title can't be expected to find them.
+ */
+pub fn self_test_index(output: &BuildOutput) -> Result<(), SelfTestError> {
+    let _self_test_span = tracing::info_span!("self_test_index").entered();
+
+    let parsed = if output.shards.is_empty() {
+        index_from_bytes(output.bytes.clone())
+    } else {
+        index_from_shards(output.bytes.clone(), &output.shards)
+    }
+    .map_err(SelfTestError::IndexUnreadable)?;
+
+    #[allow(unreachable_patterns)]
+    let index = match &parsed {
+        ParsedIndex::V3(index) => index,
+        _ => {
+            return Err(SelfTestError::IndexUnreadable(IndexParseError::ParseError()));
+        }
+    };
+
+    let mut terms: Vec<(&str, usize)> = index
+        .terms()
+        .filter(|(_, stats)| stats.entry_count > 0)
+        .map(|(term, stats)| (term, stats.occurrence_count))
+        .collect();
+    terms.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    for (term, _) in terms.into_iter().take(TERM_QUERY_COUNT) {
+        search(&parsed, term)?;
+    }
+
+    let mut title_counts: HashMap<&str, usize> = HashMap::new();
+    for entry in index.entries() {
+        *title_counts.entry(entry.title()).or_default() += 1;
+    }
+
+    let testable_entries: Vec<(&str, &str)> = index
+        .entries()
+        .filter(|entry| !entry.title().trim().is_empty() && title_counts[entry.title()] == 1)
+        .map(|entry| (entry.title(), entry.url()))
+        .collect();
+    let step = (testable_entries.len() / TITLE_QUERY_COUNT).max(1);
+
+    for &(title, url) in testable_entries
+        .iter()
+        .step_by(step)
+        .take(TITLE_QUERY_COUNT)
+    {
+        let found = search(&parsed, title)?
+            .results
+            .iter()
+            .any(|result| result.entry.title == title && result.entry.url == url);
+
+        if !found {
+            return Err(SelfTestError::TitleNotFound {
+                title: title.to_string(),
+                url: url.to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// Searches made to check the index aren't anyone's queries, so they aren't
+// reported to the query listener.
+fn search(parsed: &ParsedIndex, query: &str) -> Result<crate::Output, SelfTestError> {
+    search_parsed_index_unobserved(parsed, query, &SearchOptions::default()).map_err(|error| {
+        SelfTestError::QueryFailed {
+            query: query.to_string(),
+            error,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::*;
+    use crate::{build_index, Config};
+
+    fn config(output: &str) -> Config {
+        Config::try_from(
+            format!(
+                r#"
+                [input]
+                files = [
+                    {{ title = "Getting-started guide", url = "/start", contents = "Install Stork, then build an index of your documents." }},
+                    {{ title = "Configuration", url = "/config", contents = "Every option has a sensible default, so an index can be built without one." }},
+                    {{ title = "Searching", url = "/search", contents = "Search the index from the browser as the reader types." }},
+                ]
+
+                [output]
+                {}
+                "#,
+                output
+            )
+            .as_str(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn built_index_passes_self_test() {
+        let output = build_index(&config("")).unwrap();
+        assert!(self_test_index(&output).is_ok());
+    }
+
+    #[test]
+    fn sharded_index_passes_self_test() {
+        let output = build_index(&config("format = \"sharded\"\nshard_count = 2")).unwrap();
+        assert!(self_test_index(&output).is_ok());
+    }
+
+    #[test]
+    fn index_without_results_fails_self_test() {
+        let output = build_index(&config("displayed_results_count = 0")).unwrap();
+        assert!(matches!(
+            self_test_index(&output),
+            Err(SelfTestError::TitleNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn corrupted_index_fails_self_test() {
+        let mut output = build_index(&config("")).unwrap();
+        output.bytes = output.bytes.slice(0..output.bytes.len() / 2);
+        assert!(matches!(
+            self_test_index(&output),
+            Err(SelfTestError::IndexUnreadable(_))
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+use std::{fmt, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Fields, InternalWordAnnotation};
+
+/**
+ * A word of a document, as it's written, along with what the index should
+ * record about where it appears.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct AnnotatedWord {
+    /// The word before it's normalized. Surrounding punctuation is trimmed
+    /// and the word is lowercased when it's indexed. Words are shared, so a
+    /// tokenizer can hand out the same allocation for each time a word
+    /// repeats.
+    pub word: Arc<str>,
+    pub internal_annotations: Vec<InternalWordAnnotation>,
+    pub fields: Fields,
+}
+
+impl AnnotatedWord {
+    pub fn new(word: impl Into<Arc<str>>) -> Self {
+        AnnotatedWord {
+            word: word.into(),
+            ..AnnotatedWord::default()
+        }
+    }
+}
+
+/**
+ * Splits a document's text into the words that are indexed.
+ *
+ * Stork's own tokenizer, `DefaultTokenizer`, splits text at whitespace and
+ * hyphens, and handles links according to `input.email_handling` and
+ * `input.url_handling`. Set `input.tokenizer` to use a different one for
+ * every document's text, title, and keywords.
+ *
+ * Words shouldn't contain whitespace, since a document's words are joined
+ * with spaces to make the text its excerpts are drawn from. Large plain-text
+ * files are read in chunks, so `tokenize` might be given a document's text
+ * in several pieces, each ending at whitespace. Queries are still split into
+ * words by Stork's own rules.
+ */
+pub trait Tokenizer: Send + Sync {
+    fn tokenize(&self, text: &str) -> Vec<AnnotatedWord>;
+}
+
+/**
+ * A tokenizer to use in place of Stork's own, set as `input.tokenizer`. It
+ * can't be written in a config file, so it's only available to programs
+ * that build indexes with Stork as a library.
+ */
+#[derive(Clone)]
+pub struct CustomTokenizer(Arc<dyn Tokenizer>);
+
+impl CustomTokenizer {
+    pub fn new(tokenizer: impl Tokenizer + 'static) -> Self {
+        CustomTokenizer(Arc::new(tokenizer))
+    }
+}
+
+impl Tokenizer for CustomTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<AnnotatedWord> {
+        self.0.tokenize(text)
+    }
+}
+
+impl fmt::Debug for CustomTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CustomTokenizer")
+    }
+}
+
+/// Two configs are only equal if they share the same tokenizer instance.
+impl PartialEq for CustomTokenizer {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
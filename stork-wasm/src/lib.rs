@@ -1,8 +1,19 @@
 use bytes::Bytes;
+use js_sys::{Date, Function, Object, Reflect};
 use serde::Serialize;
-use std::{convert::From, fmt::Display};
+use std::{cell::RefCell, convert::From, fmt::Display};
 use wasm_bindgen::prelude::*;
 
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn warn(message: &str);
+}
+
+thread_local! {
+    static QUERY_LISTENER: RefCell<Option<Function>> = RefCell::new(None);
+}
+
 struct JsonSerializationError {}
 
 impl Display for JsonSerializationError {
@@ -31,6 +42,36 @@ impl<T: Sized + Serialize, E: Display> From<Result<T, E>> for WasmOutput {
     }
 }
 
+/**
+ * The error returned when a date given to a search, or written in its query,
+ * can't be parsed. It has an `invalidDate` property besides `error`, so
+ * JavaScript can tell it apart from other errors.
+ */
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InvalidDateOutput {
+    error: String,
+    invalid_date: String,
+}
+
+fn search_output<T: Serialize>(result: Result<T, stork_lib::SearchError>) -> String {
+    match result {
+        Err(stork_lib::SearchError::InvalidDate(error)) => {
+            let output = InvalidDateOutput {
+                error: error.to_string(),
+                invalid_date: error.0,
+            };
+            WasmOutput::from(Ok::<_, JsonSerializationError>(output)).0
+        }
+        result => WasmOutput::from(result).0,
+    }
+}
+
+fn parse_date(date: Option<String>) -> Result<Option<stork_lib::Date>, stork_lib::SearchError> {
+    date.map(|date| date.parse().map_err(stork_lib::SearchError::from))
+        .transpose()
+}
+
 #[wasm_bindgen]
 pub fn wasm_register_index(name: &str, data: &[u8]) -> String {
     console_error_panic_hook::set_once();
@@ -39,10 +80,185 @@ pub fn wasm_register_index(name: &str, data: &[u8]) -> String {
     WasmOutput::from(result).0
 }
 
+/**
+ * Patches an index registered with `wasm_register_index` with a delta file
+ * written by `stork build --delta-against`, so a small change to a large
+ * index doesn't have to be downloaded whole. The delta is refused unless it
+ * was made against the index that's registered.
+ */
+#[cfg(feature = "v3")]
+#[wasm_bindgen]
+pub fn wasm_register_index_delta(name: &str, data: &[u8]) -> String {
+    console_error_panic_hook::set_once();
+    let data = Bytes::from(Vec::from(data));
+    let result = stork_lib::register_index_delta(name, data);
+    WasmOutput::from(result).0
+}
+
+/**
+ * Registers a JavaScript function that's called after every successful
+ * search with `{ indexName, query, resultCount, elapsedMs }`. Passing
+ * `undefined` removes it. Anything the listener throws is logged with
+ * `console.warn` and otherwise ignored.
+ */
+#[wasm_bindgen]
+pub fn register_query_listener(listener: Option<Function>) {
+    console_error_panic_hook::set_once();
+    QUERY_LISTENER.with(|cell| *cell.borrow_mut() = listener);
+}
+
+// Only reads the clock when there's a listener, so native tests of the search
+// functions never call into JavaScript
+fn start_query() -> Option<f64> {
+    let listening = QUERY_LISTENER.with(|cell| cell.borrow().is_some());
+    if listening {
+        Some(Date::now())
+    } else {
+        None
+    }
+}
+
+fn finish_query(name: &str, query: &str, result_count: usize, started: f64) {
+    // Clone the listener out so it can register a different one while it runs
+    let listener = match QUERY_LISTENER.with(|cell| cell.borrow().clone()) {
+        Some(listener) => listener,
+        None => return,
+    };
+
+    let event = Object::new();
+    let properties: [(&str, JsValue); 4] = [
+        ("indexName", name.into()),
+        ("query", query.into()),
+        ("resultCount", (result_count as f64).into()),
+        ("elapsedMs", (Date::now() - started).into()),
+    ];
+    for (key, value) in properties {
+        let _ = Reflect::set(&event, &key.into(), &value);
+    }
+
+    if let Err(e) = listener.call1(&JsValue::NULL, &event) {
+        warn(&format!("Stork's query listener threw an error: {:?}", e));
+    }
+}
+
+/**
+ * Searches a registered index. `language` is a hint, like the page's
+ * `document.documentElement.lang`: when the index has documents tagged with
+ * that language, only they and untagged documents are returned. Leaving it
+ * out searches every language.
+ */
+#[wasm_bindgen]
+pub fn wasm_search(name: &str, query: &str, language: Option<String>) -> String {
+    console_error_panic_hook::set_once();
+    let started = start_query();
+    let options = stork_lib::SearchOptions {
+        language,
+        ..Default::default()
+    };
+    let result = stork_lib::search_from_cache_with_options(name, query, &options);
+    if let (Some(started), Ok(output)) = (started, &result) {
+        finish_query(name, query, output.total_hit_count, started);
+    }
+    search_output(result)
+}
+
+/**
+ * Searches a registered index like `wasm_search`, limited to documents dated
+ * on or after `after` and before `before`, which are ISO 8601 dates like
+ * `2023-01-01`. Documents without a date are only left out when
+ * `require_dates` is true.
+ */
+#[wasm_bindgen]
+pub fn wasm_search_with_dates(
+    name: &str,
+    query: &str,
+    language: Option<String>,
+    after: Option<String>,
+    before: Option<String>,
+    require_dates: bool,
+) -> String {
+    console_error_panic_hook::set_once();
+    let started = start_query();
+    let result = parse_date(after)
+        .and_then(|after| Ok((after, parse_date(before)?)))
+        .and_then(|(after, before)| {
+            let options = stork_lib::SearchOptions {
+                language,
+                after,
+                before,
+                require_dates,
+                ..Default::default()
+            };
+            stork_lib::search_from_cache_with_options(name, query, &options)
+        });
+    if let (Some(started), Ok(output)) = (started, &result) {
+        finish_query(name, query, output.total_hit_count, started);
+    }
+    search_output(result)
+}
+
+/**
+ * Searches a registered index's titles, limited to `language` the same way
+ * as `wasm_search`.
+ */
+#[wasm_bindgen]
+pub fn wasm_search_titles(name: &str, query: &str, language: Option<String>) -> String {
+    console_error_panic_hook::set_once();
+    let started = start_query();
+    let options = stork_lib::SearchOptions {
+        language,
+        ..Default::default()
+    };
+    let result = stork_lib::search_titles_from_cache_with_options(name, query, &options);
+    if let (Some(started), Ok(output)) = (started, &result) {
+        finish_query(name, query, output.total_hit_count, started);
+    }
+    search_output(result)
+}
+
+/**
+ * Begins a query session on a registered index, for searching it as the
+ * user types, and returns its handle as a number, or an error.
+ */
 #[wasm_bindgen]
-pub fn wasm_search(name: &str, query: &str) -> String {
+pub fn wasm_begin_query_session(name: &str) -> String {
     console_error_panic_hook::set_once();
-    let result = stork_lib::search_from_cache(name, query);
+    let result = stork_lib::begin_query_session(name);
+    WasmOutput::from(result).0
+}
+
+/**
+ * Searches a query session's index for its new query, with the same results
+ * as `wasm_search`. Call it with each version of the query as it's typed:
+ * the words that haven't changed since the previous call aren't looked up
+ * again.
+ */
+#[wasm_bindgen]
+pub fn wasm_update_query(handle: u32, query: &str, language: Option<String>) -> String {
+    console_error_panic_hook::set_once();
+    let started = start_query();
+    let options = stork_lib::SearchOptions {
+        language,
+        ..Default::default()
+    };
+    let result = stork_lib::update_query_with_options(handle, query, &options);
+    if let (Some(started), Ok(output)) = (started, &result) {
+        if let Some(name) = stork_lib::query_session_index(handle) {
+            finish_query(&name, query, output.total_hit_count, started);
+        }
+    }
+    search_output(result)
+}
+
+#[wasm_bindgen]
+pub fn wasm_end_query_session(handle: u32) {
+    stork_lib::end_query_session(handle);
+}
+
+#[wasm_bindgen]
+pub fn wasm_get_index_errors(name: &str) -> String {
+    console_error_panic_hook::set_once();
+    let result = stork_lib::get_index_errors(name);
     WasmOutput::from(result).0
 }
 
@@ -101,11 +317,13 @@ mod tests {
 
         let _str = wasm_register_index("zero-seven-zero", index_bytes.as_slice());
         let str = wasm_register_index("zero-zeven-zero-again", index_bytes.as_slice());
-        assert_eq!(str, r#"{"indexVersion":"stork-3"}"#);
+        assert_eq!(str, r#"{"indexVersion":"stork-3","documentErrorCount":0}"#);
 
-        let results = wasm_search("zero-seven-zero", "liberty");
+        let results = wasm_search("zero-seven-zero", "liberty", None);
         assert!(results.contains("despotic power and hostile to the principles of liberty. An over-scrupulous jealousy of danger to the"));
         assert_eq!(results.len(), 1254);
+
+        assert_eq!(wasm_get_index_errors("zero-seven-zero"), "[]");
     }
 
     #[test]
@@ -116,15 +334,112 @@ mod tests {
         let _bytes_read = buf_reader.read_to_end(&mut index_bytes).unwrap();
 
         let str = wasm_register_index("cache-name-one", index_bytes.as_slice());
-        assert_eq!(str, r#"{"indexVersion":"stork-3"}"#);
+        assert_eq!(str, r#"{"indexVersion":"stork-3","documentErrorCount":0}"#);
 
-        let results = wasm_search("cache-name-two", "liberty");
+        let results = wasm_search("cache-name-two", "liberty", None);
         assert_eq!(
             results,
             r#"{"error": "Index `cache-name-two` has not been registered. You need to register the index before performing searches with it."}"#
         );
     }
 
+    #[test]
+    fn unparseable_dates_give_a_typed_error() {
+        let index_bytes = include_bytes!("../../test-assets/federalist-min-0.7.0.st");
+        wasm_register_index("dates", index_bytes);
+
+        let results = wasm_search_with_dates(
+            "dates",
+            "liberty",
+            None,
+            Some("2023-01-01".to_string()),
+            Some("next week".to_string()),
+            false,
+        );
+        assert_eq!(
+            results,
+            r#"{"error":"`next week` isn't a date of the form YYYY-MM-DD, like `2023-01-31`.","invalidDate":"next week"}"#
+        );
+
+        let results = wasm_search("dates", "liberty before:2023-02-30", None);
+        assert!(results.contains(r#""invalidDate":"2023-02-30""#));
+
+        let undated = wasm_search("dates", "liberty", None);
+        let in_range = wasm_search_with_dates(
+            "dates",
+            "liberty",
+            None,
+            Some("2023-01-01".to_string()),
+            None,
+            false,
+        );
+        assert_eq!(in_range, undated);
+    }
+
+    #[test]
+    fn query_sessions_match_full_searches() {
+        let index_bytes = include_bytes!("../../test-assets/federalist-min-0.7.0.st");
+        wasm_register_index("session", index_bytes);
+
+        let handle: u32 = wasm_begin_query_session("session").parse().unwrap();
+        for query in ["lib", "liberty", "liberty pow", "liberty power", "liberty"] {
+            assert_eq!(
+                wasm_update_query(handle, query, None),
+                wasm_search("session", query, None),
+                "{}",
+                query
+            );
+        }
+
+        wasm_end_query_session(handle);
+        assert_eq!(
+            wasm_update_query(handle, "liberty", None),
+            format!(
+                r#"{{"error": "There is no query session with handle {}. Begin one with `begin_query_session` before updating its query."}}"#,
+                handle
+            )
+        );
+        assert!(wasm_begin_query_session("not-registered").contains("error"));
+    }
+
+    #[cfg(feature = "v3")]
+    #[test]
+    fn index_deltas_patch_registered_indexes() {
+        let index_bytes = include_bytes!("../../test-assets/federalist-min-0.7.0.st");
+        wasm_register_index("delta", index_bytes);
+        let expected = wasm_search("delta", "liberty", None);
+
+        let delta = stork_lib::index_delta(index_bytes, index_bytes).unwrap();
+        assert_eq!(
+            wasm_register_index_delta("delta", &delta),
+            r#"{"indexVersion":"stork-3","documentErrorCount":0}"#
+        );
+        assert_eq!(wasm_search("delta", "liberty", None), expected);
+
+        assert_eq!(
+            wasm_register_index_delta("delta", index_bytes),
+            r#"{"error": "The file isn't a Stork index delta."}"#
+        );
+        assert!(wasm_register_index_delta("not-registered", &delta).contains("error"));
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn throwing_query_listener_does_not_affect_results() {
+        let index_bytes = include_bytes!("../../test-assets/federalist-min-0.7.0.st");
+        wasm_register_index("throwing-listener", index_bytes);
+        let expected = wasm_search("throwing-listener", "liberty", None);
+
+        register_query_listener(Some(Function::new_with_args(
+            "event",
+            "throw new Error('listener failed on ' + event.query)",
+        )));
+        let results = wasm_search("throwing-listener", "liberty", None);
+        register_query_listener(None);
+
+        assert_eq!(results, expected);
+    }
+
     #[cfg(feature = "v2")]
     #[test]
     fn retrieve_v2_from_cache() {
@@ -134,9 +449,9 @@ mod tests {
         let _bytes_read = buf_reader.read_to_end(&mut index_bytes).unwrap();
 
         let str = wasm_register_index("zero-five-three", index_bytes.as_slice());
-        assert_eq!(str, r#"{"indexVersion":"stork-2"}"#);
+        assert_eq!(str, r#"{"indexVersion":"stork-2","documentErrorCount":0}"#);
 
-        let results = wasm_search("zero-five-three", "liberty");
+        let results = wasm_search("zero-five-three", "liberty", None);
         assert!(results.contains("despotic power and hostile to the principles of liberty. An over-scrupulous jealousy of danger to the"));
         assert_eq!(results.len(), 1254);
     }